@@ -0,0 +1,31 @@
+use std::{env, fs};
+
+fn main() {
+    // generate the gRPC server from the protobuf definitions
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/statusbot.proto")
+        .expect("failed to compile protobuf definitions");
+
+    // the schema version this build expects is that of its newest migration
+    let dir = match env::var("CARGO_FEATURE_SQLITE") {
+        Ok(_) => "sqlite/migrations",
+        Err(_) => "postgres/migrations",
+    };
+
+    let version = fs::read_dir(dir)
+        .expect("failed to read migrations")
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            name.split('_').next()?.parse::<i64>().ok()
+        })
+        .max()
+        .unwrap_or_default();
+
+    println!("cargo:rustc-env=STATUSBOT_SCHEMA_VERSION={}", version);
+    println!("cargo:rerun-if-changed={}", dir);
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "grpc")]
+    println!("cargo:rerun-if-changed=proto/statusbot.proto");
+}
@@ -0,0 +1,8 @@
+//! Fuzzes the parsing of event envelopes (`POST /`)
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    statusbot::fuzzing::event(data);
+});
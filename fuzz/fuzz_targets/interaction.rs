@@ -0,0 +1,8 @@
+//! Fuzzes the parsing of interactivity payloads (`POST /interactive`)
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    statusbot::fuzzing::interaction(data);
+});
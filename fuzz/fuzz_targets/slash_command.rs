@@ -0,0 +1,8 @@
+//! Fuzzes the parsing of slash command forms (`POST /location`), including the command text
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    statusbot::fuzzing::slash_command(data);
+});
@@ -0,0 +1,13 @@
+-- Record every status a user sets
+CREATE TABLE IF NOT EXISTS status_history (
+    id          BIGSERIAL PRIMARY KEY,
+    user_id     TEXT NOT NULL,
+    status      TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
+
+CREATE INDEX IF NOT EXISTS
+        idx_status_history_user
+    ON
+        status_history(user_id, created_at);
@@ -0,0 +1,10 @@
+-- Slack profiles of known users, refreshed periodically from users.list
+CREATE TABLE IF NOT EXISTS profiles (
+    user_id         TEXT NOT NULL PRIMARY KEY,
+    display_name    TEXT,
+    email           TEXT,
+    tz              TEXT,
+    deleted         BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
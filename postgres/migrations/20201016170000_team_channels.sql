@@ -0,0 +1,3 @@
+-- Channels bound to teams, optionally notified when a member's status changes outside of it
+ALTER TABLE teams ADD COLUMN channel TEXT;
+ALTER TABLE teams ADD COLUMN notify_changes BOOLEAN NOT NULL DEFAULT FALSE;
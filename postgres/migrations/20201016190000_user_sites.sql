@@ -0,0 +1,7 @@
+-- Which site users are working at, and an index for finding who is at a location (and site)
+ALTER TABLE users ADD COLUMN site TEXT;
+
+CREATE INDEX IF NOT EXISTS
+        idx_users_location_site
+    ON
+        users(location, site);
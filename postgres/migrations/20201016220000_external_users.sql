@@ -0,0 +1,3 @@
+-- People who aren't on Slack (contractors, candidates), added to teams as guests
+ALTER TABLE users ADD COLUMN name TEXT;
+ALTER TABLE users ADD COLUMN external BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- Minimum number of members each team needs on site, and the weekdays it applies on (a bitmask, Monday first)
+ALTER TABLE teams ADD COLUMN min_coverage BIGINT;
+ALTER TABLE teams ADD COLUMN coverage_days BIGINT NOT NULL DEFAULT 31;
@@ -0,0 +1,14 @@
+-- Anonymized usage of slash commands: which action ran, in which workspace, how long it took, and if it succeeded
+CREATE TABLE IF NOT EXISTS command_stats (
+    id          BIGSERIAL PRIMARY KEY,
+    action      TEXT NOT NULL,
+    workspace   TEXT NOT NULL,
+    latency_ms  BIGINT NOT NULL,
+    success     BOOLEAN NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS
+        idx_command_stats_created_at
+    ON
+        command_stats(created_at);
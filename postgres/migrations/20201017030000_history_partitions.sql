@@ -0,0 +1,34 @@
+-- Partition status history by month, so old months can be detached instead of deleted.
+-- Existing history lands in the default partition; monthly partitions are created (and
+-- filled from the default partition) at startup by `partitions::spawn`.
+ALTER SEQUENCE status_history_id_seq OWNED BY NONE;
+ALTER TABLE status_history RENAME TO status_history_unpartitioned;
+DROP INDEX IF EXISTS idx_status_history_user;
+
+CREATE TABLE status_history (
+    id           BIGINT NOT NULL DEFAULT nextval('status_history_id_seq'),
+    user_id      TEXT NOT NULL,
+    status       TEXT,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    location     TEXT,
+    availability TEXT,
+    PRIMARY KEY(id, created_at),
+    FOREIGN KEY(user_id) REFERENCES users(id)
+) PARTITION BY RANGE (created_at);
+
+CREATE TABLE IF NOT EXISTS status_history_default PARTITION OF status_history DEFAULT;
+
+CREATE INDEX IF NOT EXISTS
+        idx_status_history_user
+    ON
+        status_history(user_id, created_at);
+
+INSERT INTO
+    status_history (id, user_id, status, created_at, location, availability)
+SELECT
+    id, user_id, status, created_at, location, availability
+FROM
+    status_history_unpartitioned;
+
+DROP TABLE status_history_unpartitioned;
+ALTER SEQUENCE status_history_id_seq OWNED BY status_history.id;
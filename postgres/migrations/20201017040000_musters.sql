@@ -0,0 +1,20 @@
+-- Safety check-ins of a team, and each member's response
+CREATE TABLE IF NOT EXISTS musters (
+    id          BIGSERIAL PRIMARY KEY,
+    team_id     BIGINT NOT NULL,
+    channel     TEXT NOT NULL,
+    message_ts  TEXT,
+    started_by  TEXT NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(team_id) REFERENCES teams(id)
+);
+
+CREATE TABLE IF NOT EXISTS muster_responses (
+    muster_id     BIGINT NOT NULL,
+    user_id       TEXT NOT NULL,
+    safe          BOOLEAN,
+    responded_at  TIMESTAMPTZ,
+    PRIMARY KEY(muster_id, user_id),
+    FOREIGN KEY(muster_id) REFERENCES musters(id),
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
@@ -0,0 +1,21 @@
+-- Announcements sent to a team, and whether each reached its recipient
+CREATE TABLE IF NOT EXISTS announcements (
+    id          BIGSERIAL PRIMARY KEY,
+    team_id     BIGINT NOT NULL,
+    message     TEXT NOT NULL,
+    to_channel  BOOLEAN NOT NULL,
+    recipients  BIGINT NOT NULL,
+    sent_by     TEXT NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(team_id) REFERENCES teams(id)
+);
+
+CREATE TABLE IF NOT EXISTS announcement_deliveries (
+    announcement_id  BIGINT NOT NULL,
+    recipient        TEXT NOT NULL,
+    delivered        BOOLEAN NOT NULL,
+    error            TEXT,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(announcement_id, recipient),
+    FOREIGN KEY(announcement_id) REFERENCES announcements(id)
+);
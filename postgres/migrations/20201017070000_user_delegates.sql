@@ -0,0 +1,2 @@
+-- Who covers for each user while they're away
+ALTER TABLE users ADD COLUMN delegate_id TEXT;
@@ -0,0 +1,21 @@
+-- Statuses set for every member of a team at once (e.g., for an offsite), and each member's
+-- status before, so they can be undone
+CREATE TABLE IF NOT EXISTS bulk_statuses (
+    id          BIGSERIAL PRIMARY KEY,
+    team_id     BIGINT NOT NULL,
+    status      TEXT NOT NULL,
+    day         DATE NOT NULL,
+    set_by      TEXT NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    applied_at  TIMESTAMPTZ,
+    undone_at   TIMESTAMPTZ,
+    FOREIGN KEY(team_id) REFERENCES teams(id)
+);
+
+CREATE TABLE IF NOT EXISTS bulk_status_members (
+    bulk_id     BIGINT NOT NULL,
+    user_id     TEXT NOT NULL,
+    previous    TEXT,
+    PRIMARY KEY(bulk_id, user_id),
+    FOREIGN KEY(bulk_id) REFERENCES bulk_statuses(id)
+);
@@ -0,0 +1,12 @@
+-- Handoff checklists sent to users going on multi-day leave
+CREATE TABLE IF NOT EXISTS handoffs (
+    id            BIGSERIAL PRIMARY KEY,
+    user_id       TEXT NOT NULL,
+    starts_on     DATE NOT NULL,
+    ends_on       DATE NOT NULL,
+    items         TEXT NOT NULL,
+    checked       TEXT NOT NULL DEFAULT '',
+    completed_at  TIMESTAMPTZ,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
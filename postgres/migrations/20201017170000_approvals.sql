@@ -0,0 +1,28 @@
+-- Ordered approvers of a team's leave, each escalated to the next after a timeout
+CREATE TABLE IF NOT EXISTS approval_chains (
+    team_id        BIGINT NOT NULL PRIMARY KEY,
+    approvers      TEXT NOT NULL,
+    timeout_hours  BIGINT NOT NULL,
+    FOREIGN KEY(team_id) REFERENCES teams(id)
+);
+
+-- Leave waiting for, or decided by, an approver of a chain
+CREATE TABLE IF NOT EXISTS approvals (
+    id            BIGSERIAL PRIMARY KEY,
+    team_id       BIGINT NOT NULL,
+    user_id       TEXT NOT NULL,
+    starts_on     DATE NOT NULL,
+    ends_on       DATE NOT NULL,
+    approvers     TEXT NOT NULL,
+    timeout_hours BIGINT NOT NULL,
+    step          BIGINT NOT NULL DEFAULT 0,
+    approver_id   TEXT NOT NULL,
+    escalates_at  TIMESTAMPTZ,
+    approved      BOOLEAN,
+    decided_at    TIMESTAMPTZ,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(team_id) REFERENCES teams(id),
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
+
+CREATE INDEX IF NOT EXISTS approvals_escalates_at ON approvals(escalates_at);
@@ -0,0 +1,12 @@
+-- Admin-level actions (team deletions, role grants, retention changes), for audit reports
+CREATE TABLE IF NOT EXISTS audit_log (
+    id          BIGSERIAL PRIMARY KEY,
+    actor       TEXT NOT NULL,
+    action      TEXT NOT NULL,
+    team        TEXT NOT NULL,
+    subject     TEXT,
+    details     TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_created_at ON audit_log(created_at);
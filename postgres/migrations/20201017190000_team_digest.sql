@@ -0,0 +1,2 @@
+-- How often each team is sent a digest (daily or weekly), or NULL for the `DIGEST` default
+ALTER TABLE teams ADD COLUMN digest TEXT;
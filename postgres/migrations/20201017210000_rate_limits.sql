@@ -0,0 +1,6 @@
+-- Rate limit buckets that weren't full when they were last saved, loaded again on startup
+CREATE TABLE IF NOT EXISTS rate_limits (
+    key         TEXT NOT NULL PRIMARY KEY,
+    tokens      DOUBLE PRECISION NOT NULL,
+    updated_at  TIMESTAMPTZ NOT NULL
+);
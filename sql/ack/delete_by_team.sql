@@ -0,0 +1,4 @@
+DELETE FROM
+    status_acks
+WHERE
+    team_id = $1
@@ -0,0 +1,9 @@
+SELECT
+    user_id,
+    status,
+    acked_by,
+    acked_at
+FROM
+    status_acks
+WHERE
+    team_id = $1
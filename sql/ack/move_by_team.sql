@@ -0,0 +1,8 @@
+UPDATE
+    status_acks
+SET
+    team_id = $1
+WHERE
+    team_id = $2
+        AND
+    user_id NOT IN (SELECT user_id FROM status_acks WHERE team_id = $1)
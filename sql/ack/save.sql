@@ -0,0 +1,9 @@
+INSERT INTO
+    status_acks (team_id, user_id, status, acked_by, acked_at)
+VALUES
+    ($1, $2, $3, $4, $5)
+ON CONFLICT(team_id, user_id)
+    DO UPDATE SET
+        status = excluded.status,
+        acked_by = excluded.acked_by,
+        acked_at = excluded.acked_at
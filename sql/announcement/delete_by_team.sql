@@ -0,0 +1,4 @@
+DELETE FROM
+    announcements
+WHERE
+    team_id = $1
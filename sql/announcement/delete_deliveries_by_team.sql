@@ -0,0 +1,4 @@
+DELETE FROM
+    announcement_deliveries
+WHERE
+    announcement_id IN (SELECT id FROM announcements WHERE team_id = $1)
@@ -0,0 +1,10 @@
+SELECT
+    recipient,
+    delivered,
+    error
+FROM
+    announcement_deliveries
+WHERE
+    announcement_id = $1
+ORDER BY
+    recipient
@@ -0,0 +1,14 @@
+SELECT
+    id,
+    message,
+    to_channel,
+    recipients,
+    sent_by,
+    created_at
+FROM
+    announcements
+WHERE
+    team_id = $1
+ORDER BY
+    id DESC
+LIMIT 1
@@ -0,0 +1,4 @@
+INSERT INTO
+    announcements (team_id, message, to_channel, recipients, sent_by)
+VALUES
+    ($1, $2, $3, $4, $5)
@@ -0,0 +1,6 @@
+INSERT INTO
+    announcement_deliveries (announcement_id, recipient, delivered, error)
+VALUES
+    ($1, $2, $3, $4)
+ON CONFLICT(announcement_id, recipient)
+    DO UPDATE SET delivered = excluded.delivered, error = excluded.error
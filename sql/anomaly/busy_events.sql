@@ -0,0 +1,15 @@
+SELECT
+    user_id,
+    COUNT(*) AS changes,
+    MIN(created_at) AS first_changed_at,
+    MAX(created_at) AS last_changed_at
+FROM
+    status_events
+WHERE
+    created_at >= $1
+GROUP BY
+    user_id
+HAVING
+    COUNT(*) >= $2
+ORDER BY
+    changes DESC
@@ -0,0 +1,20 @@
+SELECT
+    status_history.user_id,
+    MAX(status_history.created_at) AS last_changed_at
+FROM
+    status_history
+INNER JOIN
+    profiles
+    ON profiles.user_id = status_history.user_id
+WHERE
+    NOT profiles.deleted
+        AND
+    status_history.user_id IN (SELECT user_id FROM members)
+        AND
+    status_history.user_id NOT IN (SELECT user_id FROM leave WHERE starts_on <= $2 AND ends_on >= $2)
+GROUP BY
+    status_history.user_id
+HAVING
+    MAX(status_history.created_at) < $1
+ORDER BY
+    last_changed_at
@@ -0,0 +1,12 @@
+UPDATE
+    approvals
+SET
+    approved = $2,
+    escalates_at = NULL,
+    decided_at = CURRENT_TIMESTAMP
+WHERE
+    id = $1
+        AND
+    approver_id = $3
+        AND
+    approved IS NULL
@@ -0,0 +1,4 @@
+DELETE FROM
+    approvals
+WHERE
+    team_id = $1
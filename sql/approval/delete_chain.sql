@@ -0,0 +1,4 @@
+DELETE FROM
+    approval_chains
+WHERE
+    team_id = $1
@@ -0,0 +1,12 @@
+UPDATE
+    approvals
+SET
+    step = $3,
+    approver_id = $4,
+    escalates_at = $5
+WHERE
+    id = $1
+        AND
+    step = $2
+        AND
+    approved IS NULL
@@ -0,0 +1,8 @@
+SELECT
+    team_id,
+    approvers,
+    timeout_hours
+FROM
+    approval_chains
+WHERE
+    team_id = $1
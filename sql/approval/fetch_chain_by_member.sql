@@ -0,0 +1,17 @@
+SELECT
+    approval_chains.team_id,
+    approval_chains.approvers,
+    approval_chains.timeout_hours
+FROM
+    approval_chains
+INNER JOIN
+    teams
+    ON teams.id = approval_chains.team_id
+INNER JOIN
+    members
+    ON members.team_id = teams.id
+WHERE
+    members.user_id = $1
+ORDER BY
+    teams.name
+LIMIT 1
@@ -0,0 +1,22 @@
+SELECT
+    approvals.id,
+    teams.name AS team,
+    approvals.user_id,
+    approvals.starts_on,
+    approvals.ends_on,
+    approvals.approvers,
+    approvals.timeout_hours,
+    approvals.step,
+    approvals.approver_id,
+    approvals.escalates_at,
+    approvals.approved
+FROM
+    approvals
+INNER JOIN
+    teams
+    ON teams.id = approvals.team_id
+WHERE
+    approvals.user_id = $1
+ORDER BY
+    approvals.id DESC
+LIMIT 1
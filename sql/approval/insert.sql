@@ -0,0 +1,4 @@
+INSERT INTO
+    approvals (team_id, user_id, starts_on, ends_on, approvers, timeout_hours, approver_id, escalates_at)
+VALUES
+    ($1, $2, $3, $4, $5, $6, $7, $8)
@@ -0,0 +1,6 @@
+UPDATE
+    approvals
+SET
+    team_id = $1
+WHERE
+    team_id = $2
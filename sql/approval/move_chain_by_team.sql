@@ -0,0 +1,8 @@
+UPDATE
+    approval_chains
+SET
+    team_id = $1
+WHERE
+    team_id = $2
+        AND
+    NOT EXISTS (SELECT 1 FROM approval_chains WHERE team_id = $1)
@@ -0,0 +1,8 @@
+INSERT INTO
+    approval_chains (team_id, approvers, timeout_hours)
+VALUES
+    ($1, $2, $3)
+ON CONFLICT(team_id)
+    DO UPDATE SET
+        approvers = $2,
+        timeout_hours = $3
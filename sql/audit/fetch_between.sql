@@ -0,0 +1,17 @@
+SELECT
+    id,
+    actor,
+    action,
+    team,
+    subject,
+    details,
+    created_at
+FROM
+    audit_log
+WHERE
+    created_at >= $1
+        AND
+    created_at < $2
+ORDER BY
+    created_at,
+    id
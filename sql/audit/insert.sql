@@ -0,0 +1,4 @@
+INSERT INTO
+    audit_log (actor, action, team, subject, details)
+VALUES
+    ($1, $2, $3, $4, $5)
@@ -0,0 +1,8 @@
+SELECT
+    user_id,
+    enabled,
+    contact
+FROM
+    auto_replies
+WHERE
+    user_id = $1
@@ -0,0 +1,6 @@
+INSERT INTO
+    auto_reply_threads (user_id, channel, thread_ts)
+VALUES
+    ($1, $2, $3)
+ON CONFLICT(user_id, channel, thread_ts)
+    DO NOTHING
@@ -0,0 +1,8 @@
+INSERT INTO
+    auto_replies (user_id, enabled, contact)
+VALUES
+    ($1, $2, $3)
+ON CONFLICT(user_id)
+    DO UPDATE SET
+        enabled = excluded.enabled,
+        contact = excluded.contact
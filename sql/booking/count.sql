@@ -0,0 +1,8 @@
+SELECT
+    COUNT(*) AS count
+FROM
+    bookings
+WHERE
+    site_id = $1
+        AND
+    day = $2
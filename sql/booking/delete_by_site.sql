@@ -0,0 +1,4 @@
+DELETE FROM
+    bookings
+WHERE
+    site_id = $1
@@ -0,0 +1,10 @@
+SELECT
+    user_id
+FROM
+    bookings
+WHERE
+    site_id = $1
+        AND
+    user_id = $2
+        AND
+    day = $3
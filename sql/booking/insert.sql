@@ -0,0 +1,6 @@
+INSERT INTO
+    bookings (site_id, user_id, day)
+VALUES
+    ($1, $2, $3)
+ON CONFLICT(site_id, user_id, day)
+    DO NOTHING
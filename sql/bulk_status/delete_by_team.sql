@@ -0,0 +1,4 @@
+DELETE FROM
+    bulk_statuses
+WHERE
+    team_id = $1
@@ -0,0 +1,4 @@
+DELETE FROM
+    bulk_status_members
+WHERE
+    bulk_id IN (SELECT id FROM bulk_statuses WHERE team_id = $1)
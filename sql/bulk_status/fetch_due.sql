@@ -0,0 +1,15 @@
+SELECT
+    id,
+    team_id,
+    status,
+    day,
+    set_by,
+    applied_at
+FROM
+    bulk_statuses
+WHERE
+    day <= $1
+    AND applied_at IS NULL
+    AND undone_at IS NULL
+ORDER BY
+    id
@@ -0,0 +1,15 @@
+SELECT
+    id,
+    team_id,
+    status,
+    day,
+    set_by,
+    applied_at
+FROM
+    bulk_statuses
+WHERE
+    team_id = $1
+    AND undone_at IS NULL
+ORDER BY
+    id DESC
+LIMIT 1
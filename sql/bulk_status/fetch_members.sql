@@ -0,0 +1,7 @@
+SELECT
+    user_id,
+    previous
+FROM
+    bulk_status_members
+WHERE
+    bulk_id = $1
@@ -0,0 +1,15 @@
+SELECT
+    users.id,
+    users.status,
+    users.location,
+    users.site,
+    users.availability
+FROM
+    members
+INNER JOIN
+    users
+    ON users.id = members.user_id
+WHERE
+    members.team_id = $1
+ORDER BY
+    users.id
@@ -0,0 +1,4 @@
+INSERT INTO
+    bulk_statuses (team_id, status, day, set_by)
+VALUES
+    ($1, $2, $3, $4)
@@ -0,0 +1,4 @@
+INSERT INTO
+    bulk_status_members (bulk_id, user_id, previous)
+VALUES
+    ($1, $2, $3)
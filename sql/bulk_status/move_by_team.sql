@@ -0,0 +1,6 @@
+UPDATE
+    bulk_statuses
+SET
+    team_id = $1
+WHERE
+    team_id = $2
@@ -0,0 +1,7 @@
+UPDATE
+    users
+SET
+    status = $3
+WHERE
+    id = $1
+    AND status = $2
@@ -0,0 +1,6 @@
+UPDATE
+    bulk_statuses
+SET
+    undone_at = $2
+WHERE
+    id = $1
@@ -0,0 +1,6 @@
+DELETE FROM
+    calendars
+WHERE
+    user_id = $1
+        AND
+    url = $2
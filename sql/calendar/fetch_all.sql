@@ -0,0 +1,9 @@
+SELECT
+    id,
+    user_id,
+    url,
+    synced_at
+FROM
+    calendars
+ORDER BY
+    id
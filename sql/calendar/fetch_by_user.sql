@@ -0,0 +1,11 @@
+SELECT
+    id,
+    user_id,
+    url,
+    synced_at
+FROM
+    calendars
+WHERE
+    user_id = $1
+ORDER BY
+    id
@@ -0,0 +1,6 @@
+INSERT INTO
+    calendars (user_id, url)
+VALUES
+    ($1, $2)
+ON CONFLICT(user_id, url)
+    DO NOTHING
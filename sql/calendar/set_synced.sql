@@ -0,0 +1,6 @@
+UPDATE
+    calendars
+SET
+    synced_at = $1
+WHERE
+    id = $2
@@ -0,0 +1,4 @@
+INSERT INTO
+    command_stats (action, workspace, latency_ms, success)
+VALUES
+    ($1, $2, $3, $4)
@@ -0,0 +1,15 @@
+SELECT
+    action,
+    COUNT(*) AS uses,
+    SUM(CASE WHEN success THEN 0 ELSE 1 END) AS failures,
+    CAST(AVG(latency_ms) AS BIGINT) AS avg_latency_ms,
+    COUNT(DISTINCT workspace) AS workspaces
+FROM
+    command_stats
+WHERE
+    created_at >= $1
+GROUP BY
+    action
+ORDER BY
+    uses DESC,
+    action
@@ -0,0 +1,8 @@
+SELECT
+    user_id,
+    channel,
+    phone
+FROM
+    contact_preferences
+WHERE
+    user_id = $1
@@ -0,0 +1,8 @@
+INSERT INTO
+    contact_preferences (user_id, channel, phone)
+VALUES
+    ($1, $2, $3)
+ON CONFLICT(user_id)
+    DO UPDATE SET
+        channel = excluded.channel,
+        phone = excluded.phone
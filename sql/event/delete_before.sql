@@ -0,0 +1,4 @@
+DELETE FROM
+    processed_events
+WHERE
+    processed_at <= $1
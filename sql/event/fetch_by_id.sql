@@ -0,0 +1,9 @@
+SELECT
+    event_id,
+    processed_at
+FROM
+    processed_events
+WHERE
+    event_id = $1
+        AND
+    processed_at > $2
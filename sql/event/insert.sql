@@ -0,0 +1,6 @@
+INSERT INTO
+    processed_events (event_id)
+VALUES
+    ($1)
+ON CONFLICT(event_id)
+    DO NOTHING
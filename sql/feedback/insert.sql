@@ -0,0 +1,4 @@
+INSERT INTO
+    feedback (workspace, user_id, channel_id, message)
+VALUES
+    ($1, $2, $3, $4)
@@ -0,0 +1,6 @@
+SELECT
+    COUNT(*) AS count
+FROM
+    team_fields
+WHERE
+    team_id = $1
@@ -0,0 +1,6 @@
+DELETE FROM
+    team_fields
+WHERE
+    team_id = $1
+        AND
+    name = $2
@@ -0,0 +1,4 @@
+DELETE FROM
+    team_fields
+WHERE
+    team_id = $1
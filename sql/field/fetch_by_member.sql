@@ -0,0 +1,13 @@
+SELECT
+    team_fields.id,
+    team_fields.name,
+    team_fields.label
+FROM
+    team_fields
+INNER JOIN
+    members
+    ON members.team_id = team_fields.team_id
+WHERE
+    members.user_id = $1
+ORDER BY
+    team_fields.id
@@ -0,0 +1,10 @@
+SELECT
+    id,
+    name,
+    label
+FROM
+    team_fields
+WHERE
+    team_id = $1
+ORDER BY
+    id
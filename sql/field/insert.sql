@@ -0,0 +1,4 @@
+INSERT INTO
+    team_fields (team_id, name, label)
+VALUES
+    ($1, $2, $3)
@@ -0,0 +1,8 @@
+UPDATE
+    team_fields
+SET
+    team_id = $1
+WHERE
+    team_id = $2
+        AND
+    name NOT IN (SELECT name FROM team_fields WHERE team_id = $1)
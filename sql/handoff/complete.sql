@@ -0,0 +1,8 @@
+UPDATE
+    handoffs
+SET
+    completed_at = CURRENT_TIMESTAMP
+WHERE
+    id = $1
+        AND
+    completed_at IS NULL
@@ -0,0 +1,12 @@
+SELECT
+    id,
+    user_id,
+    starts_on,
+    ends_on,
+    items,
+    checked,
+    completed_at
+FROM
+    handoffs
+WHERE
+    id = $1
@@ -0,0 +1,15 @@
+SELECT
+    id,
+    user_id,
+    starts_on,
+    ends_on,
+    items,
+    checked,
+    completed_at
+FROM
+    handoffs
+WHERE
+    user_id = $1
+ORDER BY
+    id DESC
+LIMIT 1
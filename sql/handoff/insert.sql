@@ -0,0 +1,4 @@
+INSERT INTO
+    handoffs (user_id, starts_on, ends_on, items)
+VALUES
+    ($1, $2, $3, $4)
@@ -0,0 +1,8 @@
+UPDATE
+    handoffs
+SET
+    checked = $2
+WHERE
+    id = $1
+        AND
+    completed_at IS NULL
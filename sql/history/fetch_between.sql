@@ -0,0 +1,15 @@
+SELECT
+    id,
+    user_id,
+    status,
+    location,
+    availability,
+    created_at
+FROM
+    status_history
+WHERE
+    created_at >= $1
+    AND created_at < $2
+ORDER BY
+    created_at,
+    id
@@ -0,0 +1,21 @@
+SELECT
+    status_history.id,
+    status_history.user_id,
+    status_history.status,
+    status_history.location,
+    status_history.availability,
+    status_history.created_at
+FROM
+    teams
+INNER JOIN
+    members
+    ON members.team_id = teams.id
+INNER JOIN
+    status_history
+    ON status_history.user_id = members.user_id
+WHERE
+    teams.normalized_name = $1
+ORDER BY
+    status_history.created_at DESC
+LIMIT
+    $2
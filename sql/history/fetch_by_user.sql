@@ -0,0 +1,13 @@
+SELECT
+    id,
+    user_id,
+    status,
+    location,
+    availability,
+    created_at
+FROM
+    status_history
+WHERE
+    user_id = $1
+ORDER BY
+    created_at DESC
@@ -0,0 +1,21 @@
+SELECT
+    status_events.id,
+    status_events.user_id,
+    status_events.status,
+    status_events.location,
+    status_events.availability,
+    status_events.created_at
+FROM
+    teams
+INNER JOIN
+    members
+    ON members.team_id = teams.id
+INNER JOIN
+    status_events
+    ON status_events.user_id = members.user_id
+WHERE
+    teams.normalized_name = $1
+ORDER BY
+    status_events.created_at DESC
+LIMIT
+    $2
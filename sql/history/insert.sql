@@ -0,0 +1,4 @@
+INSERT INTO
+    status_history (user_id, status, location, availability)
+VALUES
+    ($1, $2, $3, $4)
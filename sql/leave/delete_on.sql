@@ -0,0 +1,8 @@
+DELETE FROM
+    leave
+WHERE
+    user_id = $1
+        AND
+    starts_on <= $2
+        AND
+    ends_on >= $2
@@ -0,0 +1,12 @@
+SELECT
+    user_id,
+    starts_on,
+    ends_on,
+    source,
+    uid
+FROM
+    leave
+WHERE
+    user_id = $1
+        AND
+    uid = $2
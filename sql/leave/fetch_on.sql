@@ -0,0 +1,12 @@
+SELECT
+    user_id,
+    starts_on,
+    ends_on,
+    source,
+    uid
+FROM
+    leave
+WHERE
+    starts_on <= $1
+        AND
+    ends_on >= $1
@@ -0,0 +1,14 @@
+SELECT
+    user_id,
+    starts_on,
+    ends_on,
+    source,
+    uid
+FROM
+    leave
+WHERE
+    user_id = $1
+        AND
+    starts_on <= $3
+        AND
+    ends_on >= $2
@@ -0,0 +1,14 @@
+SELECT
+    user_id,
+    starts_on,
+    ends_on,
+    source,
+    uid
+FROM
+    leave
+WHERE
+    user_id = $1
+        AND
+    ends_on >= $2
+ORDER BY
+    starts_on
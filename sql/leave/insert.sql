@@ -0,0 +1,4 @@
+INSERT INTO
+    leave (user_id, starts_on, ends_on, source, uid)
+VALUES
+    ($1, $2, $3, $4, $5)
@@ -0,0 +1,6 @@
+SELECT
+    MAX(version) AS version
+FROM
+    _sqlx_migrations
+WHERE
+    success
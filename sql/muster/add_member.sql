@@ -0,0 +1,6 @@
+INSERT INTO
+    muster_responses (muster_id, user_id)
+VALUES
+    ($1, $2)
+ON CONFLICT(muster_id, user_id)
+    DO NOTHING
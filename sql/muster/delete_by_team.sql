@@ -0,0 +1,4 @@
+DELETE FROM
+    musters
+WHERE
+    team_id = $1
@@ -0,0 +1,4 @@
+DELETE FROM
+    muster_responses
+WHERE
+    muster_id IN (SELECT id FROM musters WHERE team_id = $1)
@@ -0,0 +1,12 @@
+SELECT
+    musters.id,
+    teams.name AS team,
+    musters.channel,
+    musters.message_ts
+FROM
+    musters
+INNER JOIN
+    teams
+    ON teams.id = musters.team_id
+WHERE
+    musters.id = $1
@@ -0,0 +1,17 @@
+SELECT
+    musters.id,
+    teams.name AS team,
+    musters.channel,
+    musters.message_ts
+FROM
+    musters
+INNER JOIN
+    teams
+    ON teams.id = musters.team_id
+WHERE
+    musters.team_id = $1
+        AND
+    musters.started_by = $2
+ORDER BY
+    musters.id DESC
+LIMIT 1
@@ -0,0 +1,9 @@
+SELECT
+    user_id,
+    safe
+FROM
+    muster_responses
+WHERE
+    muster_id = $1
+ORDER BY
+    user_id
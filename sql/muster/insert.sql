@@ -0,0 +1,4 @@
+INSERT INTO
+    musters (team_id, channel, started_by)
+VALUES
+    ($1, $2, $3)
@@ -0,0 +1,6 @@
+UPDATE
+    musters
+SET
+    team_id = $1
+WHERE
+    team_id = $2
@@ -0,0 +1,9 @@
+UPDATE
+    muster_responses
+SET
+    safe = $3,
+    responded_at = CURRENT_TIMESTAMP
+WHERE
+    muster_id = $1
+        AND
+    user_id = $2
@@ -0,0 +1,6 @@
+UPDATE
+    musters
+SET
+    message_ts = $2
+WHERE
+    id = $1
@@ -0,0 +1,8 @@
+UPDATE
+    outbox
+SET
+    deliver_at = $2
+WHERE
+    id = $1
+        AND
+    deliver_at <= $3
@@ -0,0 +1,4 @@
+DELETE FROM
+    outbox
+WHERE
+    id = $1
@@ -0,0 +1,12 @@
+SELECT
+    id,
+    effect,
+    attempts
+FROM
+    outbox
+WHERE
+    deliver_at <= $1
+ORDER BY
+    id
+LIMIT
+    $2
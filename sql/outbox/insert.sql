@@ -0,0 +1,4 @@
+INSERT INTO
+    outbox (effect)
+VALUES
+    ($1)
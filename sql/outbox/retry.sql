@@ -0,0 +1,7 @@
+UPDATE
+    outbox
+SET
+    attempts = attempts + 1,
+    deliver_at = $2
+WHERE
+    id = $1
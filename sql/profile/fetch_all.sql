@@ -0,0 +1,9 @@
+SELECT
+    user_id,
+    display_name,
+    email,
+    tz,
+    deleted,
+    updated_at
+FROM
+    profiles
@@ -0,0 +1,11 @@
+SELECT
+    user_id,
+    display_name,
+    email,
+    tz,
+    deleted,
+    updated_at
+FROM
+    profiles
+WHERE
+    user_id = $1
@@ -0,0 +1,11 @@
+INSERT INTO
+    profiles (user_id, display_name, email, tz, deleted, updated_at)
+VALUES
+    ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP)
+ON CONFLICT(user_id)
+    DO UPDATE SET
+        display_name = excluded.display_name,
+        email = excluded.email,
+        tz = excluded.tz,
+        deleted = excluded.deleted,
+        updated_at = excluded.updated_at
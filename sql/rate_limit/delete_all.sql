@@ -0,0 +1,2 @@
+DELETE FROM
+    rate_limits
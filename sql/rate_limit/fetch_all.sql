@@ -0,0 +1,6 @@
+SELECT
+    key,
+    tokens,
+    updated_at
+FROM
+    rate_limits
@@ -0,0 +1,4 @@
+INSERT INTO
+    rate_limits (key, tokens, updated_at)
+VALUES
+    ($1, $2, $3)
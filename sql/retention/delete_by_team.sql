@@ -0,0 +1,4 @@
+DELETE FROM
+    retention_overrides
+WHERE
+    team_id = $1
@@ -0,0 +1,9 @@
+SELECT
+    team_id,
+    months,
+    reason,
+    updated_at
+FROM
+    retention_overrides
+WHERE
+    team_id = $1
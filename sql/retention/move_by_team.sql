@@ -0,0 +1,8 @@
+UPDATE
+    retention_overrides
+SET
+    team_id = $1
+WHERE
+    team_id = $2
+        AND
+    NOT EXISTS (SELECT 1 FROM retention_overrides WHERE team_id = $1)
@@ -0,0 +1,9 @@
+INSERT INTO
+    retention_overrides (team_id, months, reason, updated_at)
+VALUES
+    ($1, $2, $3, $4)
+ON CONFLICT(team_id)
+    DO UPDATE SET
+        months = excluded.months,
+        reason = excluded.reason,
+        updated_at = excluded.updated_at
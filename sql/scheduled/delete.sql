@@ -0,0 +1,4 @@
+DELETE FROM
+    scheduled_messages
+WHERE
+    key = $1
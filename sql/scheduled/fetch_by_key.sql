@@ -0,0 +1,9 @@
+SELECT
+    key,
+    channel,
+    message_id,
+    post_at
+FROM
+    scheduled_messages
+WHERE
+    key = $1
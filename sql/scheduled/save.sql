@@ -0,0 +1,9 @@
+INSERT INTO
+    scheduled_messages (key, channel, message_id, post_at)
+VALUES
+    ($1, $2, $3, $4)
+ON CONFLICT(key)
+    DO UPDATE SET
+        channel = excluded.channel,
+        message_id = excluded.message_id,
+        post_at = excluded.post_at
@@ -0,0 +1,6 @@
+INSERT INTO
+    shift_members (shift_id, user_id)
+VALUES
+    ($1, $2)
+ON CONFLICT(shift_id, user_id)
+    DO NOTHING
@@ -0,0 +1,4 @@
+DELETE FROM
+    shifts
+WHERE
+    id = $1
@@ -0,0 +1,4 @@
+DELETE FROM
+    shifts
+WHERE
+    team_id = $1
@@ -0,0 +1,4 @@
+DELETE FROM
+    shift_members
+WHERE
+    shift_id = $1
@@ -0,0 +1,4 @@
+DELETE FROM
+    shift_members
+WHERE
+    shift_id IN (SELECT id FROM shifts WHERE team_id = $1)
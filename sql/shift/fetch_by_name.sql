@@ -0,0 +1,11 @@
+SELECT
+    id,
+    name,
+    days,
+    hours
+FROM
+    shifts
+WHERE
+    team_id = $1
+        AND
+    name = $2
@@ -0,0 +1,15 @@
+SELECT
+    users.id,
+    users.status,
+    users.location,
+    users.site,
+    users.availability
+FROM
+    shift_members
+INNER JOIN
+    users
+    ON users.id = shift_members.user_id
+WHERE
+    shift_members.shift_id = $1
+ORDER BY
+    users.id
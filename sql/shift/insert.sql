@@ -0,0 +1,4 @@
+INSERT INTO
+    shifts (team_id, name, days, hours)
+VALUES
+    ($1, $2, $3, $4)
@@ -0,0 +1,13 @@
+INSERT INTO
+    shift_members (shift_id, user_id)
+SELECT
+    kept.id,
+    shift_members.user_id
+FROM
+    shift_members
+        INNER JOIN shifts AS merged ON merged.id = shift_members.shift_id
+        INNER JOIN shifts AS kept ON kept.team_id = $1 AND kept.name = merged.name
+WHERE
+    merged.team_id = $2
+ON CONFLICT(shift_id, user_id)
+    DO NOTHING
@@ -0,0 +1,8 @@
+UPDATE
+    shifts
+SET
+    team_id = $1
+WHERE
+    team_id = $2
+        AND
+    name NOT IN (SELECT name FROM shifts WHERE team_id = $1)
@@ -0,0 +1,6 @@
+DELETE FROM
+    shift_members
+WHERE
+    shift_id = $1
+        AND
+    user_id = $2
@@ -0,0 +1,6 @@
+DELETE FROM
+    shift_members
+WHERE
+    user_id = $1
+        AND
+    shift_id IN (SELECT id FROM shifts WHERE team_id = $2)
@@ -0,0 +1,4 @@
+DELETE FROM
+    sites
+WHERE
+    id = $1
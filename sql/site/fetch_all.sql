@@ -0,0 +1,10 @@
+SELECT
+    id,
+    name,
+    tz,
+    address,
+    capacity
+FROM
+    sites
+ORDER BY
+    name
@@ -0,0 +1,4 @@
+INSERT INTO
+    sites (name)
+VALUES
+    ($1)
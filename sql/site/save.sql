@@ -0,0 +1,8 @@
+UPDATE
+    sites
+SET
+    tz = $1,
+    address = $2,
+    capacity = $3
+WHERE
+    id = $4
@@ -0,0 +1,17 @@
+SELECT
+    id,
+    user_id,
+    status,
+    location,
+    site,
+    availability,
+    created_at
+FROM
+    status_events
+WHERE
+    user_id = $1
+        AND
+    created_at <= $2
+ORDER BY
+    created_at,
+    id
@@ -0,0 +1,4 @@
+SELECT DISTINCT
+    user_id
+FROM
+    status_events
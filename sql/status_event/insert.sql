@@ -0,0 +1,4 @@
+INSERT INTO
+    status_events (user_id, status, location, site, availability)
+VALUES
+    ($1, $2, $3, $4, $5)
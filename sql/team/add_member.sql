@@ -1,6 +1,9 @@
 INSERT INTO
     members (user_id, team_id)
-VALUES
-    ($1, $2)
+SELECT
+    $1,
+    $2
+WHERE
+    (SELECT COUNT(*) FROM members WHERE team_id = $2) < $3
 ON CONFLICT(user_id, team_id)
     DO NOTHING
@@ -0,0 +1,4 @@
+DELETE FROM
+    members
+WHERE
+    team_id = $1
@@ -1,5 +1,14 @@
 SELECT
     id,
-    name
+    name,
+    description,
+    icon,
+    created_at,
+    created_by,
+    channel,
+    notify_changes,
+    min_coverage,
+    coverage_days,
+    digest
 FROM
     teams
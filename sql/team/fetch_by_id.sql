@@ -0,0 +1,16 @@
+SELECT
+    id,
+    name,
+    description,
+    icon,
+    created_at,
+    created_by,
+    channel,
+    notify_changes,
+    min_coverage,
+    coverage_days,
+    digest
+FROM
+    teams
+WHERE
+    id = $1
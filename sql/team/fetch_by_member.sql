@@ -0,0 +1,21 @@
+SELECT
+    teams.id,
+    teams.name,
+    teams.description,
+    teams.icon,
+    teams.created_at,
+    teams.created_by,
+    teams.channel,
+    teams.notify_changes,
+    teams.min_coverage,
+    teams.coverage_days,
+    teams.digest
+FROM
+    teams
+INNER JOIN
+    members
+    ON members.team_id = teams.id
+WHERE
+    members.user_id = $1
+ORDER BY
+    teams.name
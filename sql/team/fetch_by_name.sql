@@ -1,7 +1,16 @@
 SELECT
     id,
-    name
+    name,
+    description,
+    icon,
+    created_at,
+    created_by,
+    channel,
+    notify_changes,
+    min_coverage,
+    coverage_days,
+    digest
 FROM
     teams
 WHERE
-    name = $1
+    normalized_name = $1
@@ -0,0 +1,17 @@
+SELECT
+    users.id,
+    users.status,
+    users.location,
+    users.site,
+    users.availability
+FROM
+    members
+INNER JOIN
+    users
+    ON users.id = members.user_id
+WHERE
+    members.team_id = $1
+        AND
+    users.external = TRUE
+        AND
+    LOWER(users.name) = LOWER($2)
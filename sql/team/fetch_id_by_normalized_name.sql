@@ -0,0 +1,6 @@
+SELECT
+    id
+FROM
+    teams
+WHERE
+    normalized_name = $1
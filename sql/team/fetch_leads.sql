@@ -0,0 +1,15 @@
+SELECT
+    members.user_id AS id,
+    users.status,
+    users.location,
+    users.site,
+    users.availability
+FROM
+    members
+INNER JOIN
+    users
+    ON users.id = members.user_id
+WHERE
+    members.team_id = $1
+        AND
+    members.role = 'lead'
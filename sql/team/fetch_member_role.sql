@@ -0,0 +1,8 @@
+SELECT
+    role
+FROM
+    members
+WHERE
+    user_id = $1
+        AND
+    team_id = $2
@@ -1,6 +1,9 @@
 SELECT
     members.user_id AS id,
-    users.status
+    users.status,
+    users.location,
+    users.site,
+    users.availability
 FROM
     teams
 INNER JOIN
@@ -10,4 +13,4 @@ INNER JOIN
     users
     ON users.id = members.user_id
 WHERE
-    teams.name = $1
+    teams.normalized_name = $1
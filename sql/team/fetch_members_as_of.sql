@@ -0,0 +1,70 @@
+SELECT
+    members.user_id AS id,
+    (
+        SELECT
+            status_history.status
+        FROM
+            status_history
+        WHERE
+            status_history.user_id = members.user_id
+                AND
+            status_history.created_at <= $2
+                AND
+            status_history.status IS NOT NULL
+        ORDER BY
+            status_history.created_at DESC
+        LIMIT 1
+    ) AS status,
+    (
+        SELECT
+            status_history.location
+        FROM
+            status_history
+        WHERE
+            status_history.user_id = members.user_id
+                AND
+            status_history.created_at <= $2
+                AND
+            status_history.location IS NOT NULL
+        ORDER BY
+            status_history.created_at DESC
+        LIMIT 1
+    ) AS location,
+    CAST(NULL AS TEXT) AS site,
+    (
+        SELECT
+            status_history.availability
+        FROM
+            status_history
+        WHERE
+            status_history.user_id = members.user_id
+                AND
+            status_history.created_at <= $2
+                AND
+            status_history.availability IS NOT NULL
+        ORDER BY
+            status_history.created_at DESC
+        LIMIT 1
+    ) AS availability,
+    users.name,
+    users.external,
+    users.delegate_id,
+    users.fields,
+    members.role
+FROM
+    teams
+INNER JOIN
+    members
+    ON members.team_id = teams.id
+INNER JOIN
+    users
+    ON users.id = members.user_id
+WHERE
+    teams.normalized_name = $1
+ORDER BY
+    CASE WHEN members.role = 'lead' THEN 0 ELSE 1 END,
+    members.user_id
+LIMIT
+    $3
+OFFSET
+    $4
@@ -0,0 +1,84 @@
+SELECT
+    members.user_id AS id,
+    (
+        SELECT
+            status_events.status
+        FROM
+            status_events
+        WHERE
+            status_events.user_id = members.user_id
+                AND
+            status_events.created_at <= $2
+                AND
+            status_events.status IS NOT NULL
+        ORDER BY
+            status_events.created_at DESC
+        LIMIT 1
+    ) AS status,
+    (
+        SELECT
+            status_events.location
+        FROM
+            status_events
+        WHERE
+            status_events.user_id = members.user_id
+                AND
+            status_events.created_at <= $2
+                AND
+            status_events.location IS NOT NULL
+        ORDER BY
+            status_events.created_at DESC
+        LIMIT 1
+    ) AS location,
+    (
+        SELECT
+            status_events.site
+        FROM
+            status_events
+        WHERE
+            status_events.user_id = members.user_id
+                AND
+            status_events.created_at <= $2
+                AND
+            status_events.location IS NOT NULL
+        ORDER BY
+            status_events.created_at DESC
+        LIMIT 1
+    ) AS site,
+    (
+        SELECT
+            status_events.availability
+        FROM
+            status_events
+        WHERE
+            status_events.user_id = members.user_id
+                AND
+            status_events.created_at <= $2
+                AND
+            status_events.availability IS NOT NULL
+        ORDER BY
+            status_events.created_at DESC
+        LIMIT 1
+    ) AS availability,
+    users.name,
+    users.external,
+    users.delegate_id,
+    users.fields,
+    members.role
+FROM
+    teams
+INNER JOIN
+    members
+    ON members.team_id = teams.id
+INNER JOIN
+    users
+    ON users.id = members.user_id
+WHERE
+    teams.normalized_name = $1
+ORDER BY
+    CASE WHEN members.role = 'lead' THEN 0 ELSE 1 END,
+    members.user_id
+LIMIT
+    $3
+OFFSET
+    $4
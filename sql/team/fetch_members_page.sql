@@ -0,0 +1,28 @@
+SELECT
+    members.user_id AS id,
+    users.status,
+    users.location,
+    users.site,
+    users.availability,
+    users.name,
+    users.external,
+    users.delegate_id,
+    users.fields,
+    members.role
+FROM
+    teams
+INNER JOIN
+    members
+    ON members.team_id = teams.id
+INNER JOIN
+    users
+    ON users.id = members.user_id
+WHERE
+    teams.normalized_name = $1
+ORDER BY
+    CASE WHEN members.role = 'lead' THEN 0 ELSE 1 END,
+    members.user_id
+LIMIT
+    $2
+OFFSET
+    $3
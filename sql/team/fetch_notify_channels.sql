@@ -0,0 +1,13 @@
+SELECT DISTINCT
+    teams.channel
+FROM
+    teams
+INNER JOIN
+    members
+    ON members.team_id = teams.id
+WHERE
+    members.user_id = $1
+        AND
+    teams.notify_changes = TRUE
+        AND
+    teams.channel IS NOT NULL
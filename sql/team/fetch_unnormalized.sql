@@ -0,0 +1,9 @@
+SELECT
+    id,
+    name
+FROM
+    teams
+WHERE
+    normalized_name IS NULL
+ORDER BY
+    id
@@ -1,4 +1,9 @@
 INSERT INTO
-    teams (name)
-VALUES
-    ($1)
+    teams (name, normalized_name, created_at, created_by)
+SELECT
+    $1,
+    $2,
+    CURRENT_TIMESTAMP,
+    $3
+WHERE
+    (SELECT COUNT(*) FROM teams) < $4
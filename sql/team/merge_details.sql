@@ -0,0 +1,14 @@
+UPDATE
+    teams
+SET
+    description = COALESCE(description, (SELECT description FROM teams WHERE id = $2)),
+    icon = COALESCE(icon, (SELECT icon FROM teams WHERE id = $2)),
+    channel = COALESCE(channel, (SELECT channel FROM teams WHERE id = $2)),
+    notify_changes = CASE
+        WHEN channel IS NULL THEN (SELECT notify_changes FROM teams WHERE id = $2)
+        ELSE notify_changes
+    END,
+    min_coverage = COALESCE(min_coverage, (SELECT min_coverage FROM teams WHERE id = $2)),
+    digest = COALESCE(digest, (SELECT digest FROM teams WHERE id = $2))
+WHERE
+    id = $1
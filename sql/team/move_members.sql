@@ -0,0 +1,17 @@
+INSERT INTO
+    members (user_id, team_id, role)
+SELECT
+    user_id,
+    $1,
+    role
+FROM
+    members
+WHERE
+    team_id = $2
+ON CONFLICT(user_id, team_id)
+    DO UPDATE SET
+        role = CASE
+            WHEN members.role = 'lead' OR excluded.role = 'lead' THEN 'lead'
+            WHEN members.role = 'member' OR excluded.role = 'member' THEN 'member'
+            ELSE members.role
+        END
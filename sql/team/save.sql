@@ -1,6 +1,14 @@
 UPDATE
     teams
 SET
-    name = $1
+    name = $1,
+    normalized_name = $2,
+    description = $3,
+    icon = $4,
+    channel = $5,
+    notify_changes = $6,
+    min_coverage = $7,
+    coverage_days = $8,
+    digest = $9
 WHERE
-    id = $2
+    id = $10
@@ -0,0 +1,20 @@
+SELECT
+    id,
+    name,
+    description,
+    icon,
+    created_at,
+    created_by,
+    channel,
+    notify_changes,
+    min_coverage,
+    coverage_days,
+    digest
+FROM
+    teams
+WHERE
+    normalized_name LIKE $1 ESCAPE '\'
+ORDER BY
+    name
+LIMIT
+    $2
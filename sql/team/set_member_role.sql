@@ -0,0 +1,8 @@
+UPDATE
+    members
+SET
+    role = $1
+WHERE
+    user_id = $2
+        AND
+    team_id = $3
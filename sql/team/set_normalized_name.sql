@@ -0,0 +1,6 @@
+UPDATE
+    teams
+SET
+    normalized_name = $1
+WHERE
+    id = $2
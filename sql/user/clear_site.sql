@@ -0,0 +1,6 @@
+UPDATE
+    users
+SET
+    site = NULL
+WHERE
+    site = $1
@@ -0,0 +1,4 @@
+SELECT
+    id, status, location, site, availability
+FROM
+    users
@@ -1,5 +1,5 @@
 SELECT
-    id, status
+    id, status, location, site, availability
 FROM
     users
 WHERE
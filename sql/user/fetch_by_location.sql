@@ -0,0 +1,12 @@
+SELECT
+    id, status, location, site, availability
+FROM
+    users
+WHERE
+    location = $1
+ORDER BY
+    site, id
+LIMIT
+    $2
+OFFSET
+    $3
@@ -0,0 +1,14 @@
+SELECT
+    id, status, location, site, availability
+FROM
+    users
+WHERE
+    location = $1
+        AND
+    site = $2
+ORDER BY
+    id
+LIMIT
+    $3
+OFFSET
+    $4
@@ -0,0 +1,6 @@
+SELECT
+    delegate_id
+FROM
+    users
+WHERE
+    id = $1
@@ -0,0 +1,6 @@
+SELECT
+    fields
+FROM
+    users
+WHERE
+    id = $1
@@ -0,0 +1,4 @@
+INSERT INTO
+    users (id, name, external)
+VALUES
+    ($1, $2, TRUE)
@@ -1,7 +1,10 @@
 INSERT INTO
-    users (id, status)
+    users (id, status, location, site, availability)
 VALUES
-    ($1, $2)
+    ($1, $2, $3, $4, $5)
 ON CONFLICT(id)
     DO UPDATE SET
-        status = excluded.status
+        status = COALESCE(excluded.status, users.status),
+        location = COALESCE(excluded.location, users.location),
+        site = CASE WHEN excluded.location IS NULL THEN users.site ELSE excluded.site END,
+        availability = COALESCE(excluded.availability, users.availability)
@@ -0,0 +1,6 @@
+UPDATE
+    users
+SET
+    delegate_id = $2
+WHERE
+    id = $1
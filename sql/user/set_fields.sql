@@ -0,0 +1,6 @@
+UPDATE
+    users
+SET
+    fields = $2
+WHERE
+    id = $1
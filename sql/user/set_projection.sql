@@ -0,0 +1,9 @@
+UPDATE
+    users
+SET
+    status = $2,
+    location = $3,
+    site = $4,
+    availability = $5
+WHERE
+    id = $1
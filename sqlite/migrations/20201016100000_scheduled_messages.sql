@@ -0,0 +1,7 @@
+-- Messages scheduled with chat.scheduleMessage
+CREATE TABLE IF NOT EXISTS scheduled_messages (
+    key         TEXT NOT NULL PRIMARY KEY,
+    channel     TEXT NOT NULL,
+    message_id  TEXT NOT NULL,
+    post_at     BIGINT NOT NULL
+);
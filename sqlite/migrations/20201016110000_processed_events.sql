@@ -0,0 +1,5 @@
+-- Slack events that have already been processed, used to ignore retries
+CREATE TABLE IF NOT EXISTS processed_events (
+    event_id        TEXT NOT NULL PRIMARY KEY,
+    processed_at    DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,5 @@
+-- Describe teams and record who created them
+ALTER TABLE teams ADD COLUMN description TEXT;
+ALTER TABLE teams ADD COLUMN icon TEXT;
+ALTER TABLE teams ADD COLUMN created_at DATETIME;
+ALTER TABLE teams ADD COLUMN created_by TEXT;
@@ -0,0 +1,8 @@
+-- Teams are looked up by their normalized (NFC, case folded) name.  Existing teams are
+-- normalized at startup, merging teams whose names only differ by case.
+ALTER TABLE teams ADD COLUMN normalized_name TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS
+        idx_teams_normalized_name
+    ON
+        teams(normalized_name);
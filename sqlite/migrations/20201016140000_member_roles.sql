@@ -0,0 +1,2 @@
+-- Each member of a team has a role: lead, member, or viewer
+ALTER TABLE members ADD COLUMN role TEXT NOT NULL DEFAULT 'member';
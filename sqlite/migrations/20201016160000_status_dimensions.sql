@@ -0,0 +1,3 @@
+-- Where a user is working and whether they're available, set independently of their status note
+ALTER TABLE users ADD COLUMN location TEXT;
+ALTER TABLE users ADD COLUMN availability TEXT;
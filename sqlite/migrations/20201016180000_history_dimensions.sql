@@ -0,0 +1,3 @@
+-- Record where users were and whether they were available alongside their status notes
+ALTER TABLE status_history ADD COLUMN location TEXT;
+ALTER TABLE status_history ADD COLUMN availability TEXT;
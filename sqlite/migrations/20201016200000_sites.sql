@@ -0,0 +1,12 @@
+-- Offices and other sites users can work at
+CREATE TABLE IF NOT EXISTS sites (
+    id          INTEGER NOT NULL PRIMARY KEY,
+    name        TEXT NOT NULL,
+    tz          TEXT,
+    address     TEXT
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS
+        idx_sites_name
+    ON
+        sites(name);
@@ -0,0 +1,13 @@
+-- Desks booked at sites, limited by each site's capacity (unlimited if NULL)
+ALTER TABLE sites ADD COLUMN capacity BIGINT;
+
+CREATE TABLE IF NOT EXISTS bookings (
+    id          INTEGER NOT NULL PRIMARY KEY,
+    site_id     BIGINT NOT NULL,
+    user_id     TEXT NOT NULL,
+    day         DATE NOT NULL,
+    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(site_id) REFERENCES sites(id),
+    FOREIGN KEY(user_id) REFERENCES users(id),
+    UNIQUE(site_id, user_id, day)
+);
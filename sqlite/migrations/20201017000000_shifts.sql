@@ -0,0 +1,18 @@
+-- Recurring shifts of each team (on a bitmask of weekdays, Monday first), and who is assigned to them
+CREATE TABLE IF NOT EXISTS shifts (
+    id          INTEGER NOT NULL PRIMARY KEY,
+    team_id     BIGINT NOT NULL,
+    name        TEXT NOT NULL,
+    days        BIGINT NOT NULL,
+    hours       TEXT,
+    FOREIGN KEY(team_id) REFERENCES teams(id),
+    UNIQUE(team_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS shift_members (
+    shift_id    BIGINT NOT NULL,
+    user_id     TEXT NOT NULL,
+    FOREIGN KEY(shift_id) REFERENCES shifts(id),
+    FOREIGN KEY(user_id) REFERENCES users(id),
+    UNIQUE(shift_id, user_id)
+);
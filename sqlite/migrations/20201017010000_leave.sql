@@ -0,0 +1,26 @@
+-- Personal calendars synced for leave, and days users are on leave (entered manually or imported from a calendar)
+CREATE TABLE IF NOT EXISTS calendars (
+    id          INTEGER NOT NULL PRIMARY KEY,
+    user_id     TEXT NOT NULL,
+    url         TEXT NOT NULL,
+    synced_at   DATETIME,
+    FOREIGN KEY(user_id) REFERENCES users(id),
+    UNIQUE(user_id, url)
+);
+
+CREATE TABLE IF NOT EXISTS leave (
+    id          INTEGER NOT NULL PRIMARY KEY,
+    user_id     TEXT NOT NULL,
+    starts_on   DATE NOT NULL,
+    ends_on     DATE NOT NULL,
+    source      TEXT NOT NULL,
+    uid         TEXT,
+    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(user_id) REFERENCES users(id),
+    UNIQUE(user_id, uid)
+);
+
+CREATE INDEX IF NOT EXISTS
+        idx_leave_dates
+    ON
+        leave(starts_on, ends_on);
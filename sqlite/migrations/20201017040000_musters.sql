@@ -0,0 +1,20 @@
+-- Safety check-ins of a team, and each member's response
+CREATE TABLE IF NOT EXISTS musters (
+    id          INTEGER NOT NULL PRIMARY KEY,
+    team_id     INTEGER NOT NULL,
+    channel     TEXT NOT NULL,
+    message_ts  TEXT,
+    started_by  TEXT NOT NULL,
+    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(team_id) REFERENCES teams(id)
+);
+
+CREATE TABLE IF NOT EXISTS muster_responses (
+    muster_id     INTEGER NOT NULL,
+    user_id       TEXT NOT NULL,
+    safe          BOOLEAN,
+    responded_at  DATETIME,
+    PRIMARY KEY(muster_id, user_id),
+    FOREIGN KEY(muster_id) REFERENCES musters(id),
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
@@ -0,0 +1,15 @@
+-- Per-user settings for replying to mentions of users on leave, and the threads replied in
+CREATE TABLE IF NOT EXISTS auto_replies (
+    user_id     TEXT NOT NULL PRIMARY KEY,
+    enabled     BOOLEAN NOT NULL,
+    contact     TEXT,
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
+
+CREATE TABLE IF NOT EXISTS auto_reply_threads (
+    user_id     TEXT NOT NULL,
+    channel     TEXT NOT NULL,
+    thread_ts   TEXT NOT NULL,
+    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(user_id, channel, thread_ts)
+);
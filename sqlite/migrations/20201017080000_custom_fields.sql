@@ -0,0 +1,12 @@
+-- Extra fields teams collect from their members (e.g., badge number), and each user's values
+-- (a JSON object keyed by field name)
+CREATE TABLE IF NOT EXISTS team_fields (
+    id          INTEGER NOT NULL PRIMARY KEY,
+    team_id     INTEGER NOT NULL,
+    name        TEXT NOT NULL,
+    label       TEXT NOT NULL,
+    FOREIGN KEY(team_id) REFERENCES teams(id),
+    UNIQUE(team_id, name)
+);
+
+ALTER TABLE users ADD COLUMN fields TEXT;
@@ -0,0 +1,11 @@
+-- Acknowledgements of members' statuses by their team's leads, one per member per team
+CREATE TABLE IF NOT EXISTS status_acks (
+    team_id     INTEGER NOT NULL,
+    user_id     TEXT NOT NULL,
+    status      TEXT NOT NULL,
+    acked_by    TEXT NOT NULL,
+    acked_at    DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(team_id, user_id),
+    FOREIGN KEY(team_id) REFERENCES teams(id),
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
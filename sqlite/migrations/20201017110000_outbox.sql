@@ -0,0 +1,14 @@
+-- Side effects of status changes (Slack messages and reactions), written in the same
+-- transaction as the change and delivered afterwards by the outbox worker
+CREATE TABLE IF NOT EXISTS outbox (
+    id          INTEGER NOT NULL PRIMARY KEY,
+    effect      TEXT NOT NULL,
+    attempts    INTEGER NOT NULL DEFAULT 0,
+    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    deliver_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS
+        idx_outbox_deliver_at
+    ON
+        outbox(deliver_at);
@@ -0,0 +1,17 @@
+-- Append-only log of status changes, which users' statuses are a projection of when
+-- `STATUS_STORE=events`
+CREATE TABLE IF NOT EXISTS status_events (
+    id           INTEGER NOT NULL PRIMARY KEY,
+    user_id      TEXT NOT NULL,
+    status       TEXT,
+    location     TEXT,
+    site         TEXT,
+    availability TEXT,
+    created_at   DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
+
+CREATE INDEX IF NOT EXISTS
+        idx_status_events_user
+    ON
+        status_events(user_id, created_at);
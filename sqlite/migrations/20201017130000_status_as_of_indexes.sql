@@ -0,0 +1,44 @@
+-- Indexes for finding the latest status, location, and availability each user set before a
+-- point in time, which only look at the entries that set them
+
+CREATE INDEX IF NOT EXISTS
+        idx_status_history_user_status
+    ON
+        status_history(user_id, created_at)
+    WHERE
+        status IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS
+        idx_status_history_user_location
+    ON
+        status_history(user_id, created_at)
+    WHERE
+        location IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS
+        idx_status_history_user_availability
+    ON
+        status_history(user_id, created_at)
+    WHERE
+        availability IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS
+        idx_status_events_user_status
+    ON
+        status_events(user_id, created_at)
+    WHERE
+        status IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS
+        idx_status_events_user_location
+    ON
+        status_events(user_id, created_at)
+    WHERE
+        location IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS
+        idx_status_events_user_availability
+    ON
+        status_events(user_id, created_at)
+    WHERE
+        availability IS NOT NULL;
@@ -0,0 +1,8 @@
+-- Teams whose members' status history is kept longer than HISTORY_RETENTION_MONTHS
+CREATE TABLE IF NOT EXISTS retention_overrides (
+    team_id     INTEGER NOT NULL PRIMARY KEY,
+    months      INTEGER NOT NULL,
+    reason      TEXT,
+    updated_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(team_id) REFERENCES teams(id)
+);
@@ -0,0 +1,7 @@
+-- How each user prefers to be notified when they're away from Slack
+CREATE TABLE IF NOT EXISTS contact_preferences (
+    user_id     TEXT NOT NULL PRIMARY KEY,
+    channel     TEXT NOT NULL,
+    phone       TEXT,
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
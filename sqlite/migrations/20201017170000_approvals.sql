@@ -0,0 +1,28 @@
+-- Ordered approvers of a team's leave, each escalated to the next after a timeout
+CREATE TABLE IF NOT EXISTS approval_chains (
+    team_id        INTEGER NOT NULL PRIMARY KEY,
+    approvers      TEXT NOT NULL,
+    timeout_hours  INTEGER NOT NULL,
+    FOREIGN KEY(team_id) REFERENCES teams(id)
+);
+
+-- Leave waiting for, or decided by, an approver of a chain
+CREATE TABLE IF NOT EXISTS approvals (
+    id            INTEGER NOT NULL PRIMARY KEY,
+    team_id       INTEGER NOT NULL,
+    user_id       TEXT NOT NULL,
+    starts_on     DATE NOT NULL,
+    ends_on       DATE NOT NULL,
+    approvers     TEXT NOT NULL,
+    timeout_hours INTEGER NOT NULL,
+    step          INTEGER NOT NULL DEFAULT 0,
+    approver_id   TEXT NOT NULL,
+    escalates_at  DATETIME,
+    approved      BOOLEAN,
+    decided_at    DATETIME,
+    created_at    DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(team_id) REFERENCES teams(id),
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
+
+CREATE INDEX IF NOT EXISTS approvals_escalates_at ON approvals(escalates_at);
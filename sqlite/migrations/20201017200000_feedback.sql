@@ -0,0 +1,11 @@
+-- Problems users reported with `/location feedback`, with where they reported them from
+CREATE TABLE IF NOT EXISTS feedback (
+    id          INTEGER NOT NULL PRIMARY KEY,
+    workspace   TEXT NOT NULL,
+    user_id     TEXT NOT NULL,
+    channel_id  TEXT NOT NULL,
+    message     TEXT NOT NULL,
+    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS feedback_created_at ON feedback(created_at);
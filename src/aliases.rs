@@ -0,0 +1,101 @@
+//! Aliases for command keywords, so workspaces can use the bot in their own language
+//!
+//! Aliases are configured as a comma-separated list of `alias=keyword` pairs (e.g.,
+//! `equipo=team,crear=create,créer=create`).  Before a command is parsed, its keywords are
+//! replaced by the keyword they're an alias of: the first two words, and the word after a
+//! team, site, shift, or announcement's name.  Other words (e.g., names and notes) are left
+//! as typed.
+
+use crate::error::Error;
+use std::collections::HashMap;
+
+/// Commands whose second word is a name, followed by another keyword
+const NAMED_COMMANDS: &[&str] = &["announce", "shift", "site", "team"];
+
+/// Maps aliases onto the keywords of the command grammar
+#[derive(Clone, Debug, Default)]
+pub struct Aliases {
+    /// Keyword each alias stands for, keyed by lowercased alias
+    keywords: HashMap<String, String>,
+}
+
+impl Aliases {
+    /// Parses configured aliases
+    ///
+    /// # Arguments
+    /// * `spec` - Comma-separated `alias=keyword` pairs
+    pub fn parse(spec: &str) -> Result<Self, Error> {
+        let mut keywords = HashMap::new();
+        for pair in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let mut parts = pair.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(alias), Some(keyword))
+                    if !alias.trim().is_empty()
+                        && !keyword.trim().is_empty()
+                        && !alias.trim().contains(char::is_whitespace)
+                        && !keyword.trim().contains(char::is_whitespace) =>
+                {
+                    keywords.insert(alias.trim().to_lowercase(), keyword.trim().to_owned());
+                }
+                _ => {
+                    return Err(Error::Parse(format!(
+                        "invalid command alias `{}`, expected `alias=keyword`",
+                        pair
+                    )))
+                }
+            }
+        }
+
+        Ok(Aliases { keywords })
+    }
+
+    /// Returns the keyword a word stands for, or the word itself if it isn't an alias
+    ///
+    /// # Arguments
+    /// * `word` - A word of the command
+    fn keyword<'a>(&'a self, word: &'a str) -> &'a str {
+        self.keywords
+            .get(&word.to_lowercase())
+            .map(String::as_str)
+            .unwrap_or(word)
+    }
+
+    /// Replaces the aliased keywords of a command with the keywords they stand for
+    ///
+    /// # Arguments
+    /// * `text` - Text of the command, as typed
+    pub fn expand(&self, text: &str) -> String {
+        if self.keywords.is_empty() {
+            return text.to_owned();
+        }
+
+        let mut words = vec![];
+        let mut rest = text.trim_start();
+        while !rest.is_empty() && words.len() < 3 {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let (word, tail) = rest.split_at(end);
+            words.push(word);
+            rest = tail.trim_start();
+        }
+
+        let mut expanded: Vec<&str> = vec![];
+        for (i, word) in words.iter().enumerate() {
+            let aliased = match i {
+                0 | 1 => true,
+                _ => NAMED_COMMANDS.contains(&expanded[0]),
+            };
+
+            expanded.push(if aliased { self.keyword(word) } else { word });
+        }
+
+        if !rest.is_empty() {
+            expanded.push(rest);
+        }
+
+        expanded.join(" ")
+    }
+}
@@ -0,0 +1,414 @@
+//! Allowlists of the addresses inbound requests may come from
+//!
+//! Routes are split into three groups, each with its own optional allowlist of CIDR ranges:
+//! requests from Slack (`/`, `/location`, and `/interactive`, e.g. Slack's egress ranges),
+//! the admin UI and API (`/admin`, `/api`, `/auth`, and the `/desired-state` webhook, e.g. an
+//! internal gateway), and everything else (feeds, badges, and the live feed).  Requests to a
+//! group with an allowlist from anywhere else are rejected with `403 Forbidden` before their
+//! body is read.
+//!
+//! The client's address is the address of the peer, unless the peer is a trusted proxy
+//! (`TRUSTED_PROXIES`), in which case `X-Forwarded-For` is followed from the right until an
+//! address that isn't a trusted proxy is found.  `X-Forwarded-For` is never trusted from
+//! anyone else, as clients can set it to anything.  The address is recorded on every request
+//! as a `ClientAddr` extension, so later middleware (e.g., rate limits) see the same client.
+
+use crate::error::Error;
+use async_trait::async_trait;
+use std::net::{IpAddr, SocketAddr};
+use tide::{Middleware, Next, StatusCode};
+
+/// Path prefixes of routes that receive requests from Slack (`/` is matched exactly)
+const SLACK_ROUTES: &[&str] = &["/location", "/interactive"];
+
+/// Path prefixes of routes of the admin UI and API
+const ADMIN_ROUTES: &[&str] = &["/admin", "/api", "/auth", "/desired-state"];
+
+/// Address of the client that made a request, as determined by `IpAllowlist`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClientAddr(pub IpAddr);
+
+/// A range of IP addresses, e.g. `10.0.0.0/8` or `2001:db8::/32`
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cidr {
+    /// First address of the range
+    network: IpAddr,
+
+    /// Number of leading bits addresses in the range share with `network`
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Parses a range, or a single address
+    ///
+    /// # Arguments
+    /// * `s` - Range in CIDR notation, or an address
+    fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(2, '/');
+        let network = normalize(parts.next()?.trim().parse().ok()?);
+        let max = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        let prefix = match parts.next() {
+            Some(prefix) => prefix.trim().parse().ok().filter(|prefix| *prefix <= max)?,
+            None => max,
+        };
+
+        Some(Cidr { network, prefix })
+    }
+
+    /// Returns true if an address is in this range
+    ///
+    /// # Arguments
+    /// * `addr` - The address
+    fn contains(&self, addr: IpAddr) -> bool {
+        match (self.network, normalize(addr)) {
+            (IpAddr::V4(network), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix)).unwrap_or(0);
+                u32::from(network) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(self.prefix)).unwrap_or(0);
+                u128::from(network) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Converts IPv4 addresses mapped into IPv6 (`::ffff:a.b.c.d`) back to IPv4, so they match
+/// IPv4 ranges
+///
+/// # Arguments
+/// * `addr` - The address
+fn normalize(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => {
+            let octets = v6.octets();
+            if octets[..10].iter().all(|b| *b == 0) && octets[10] == 0xff && octets[11] == 0xff {
+                IpAddr::from([octets[12], octets[13], octets[14], octets[15]])
+            } else {
+                addr
+            }
+        }
+        IpAddr::V4(_) => addr,
+    }
+}
+
+/// A list of address ranges
+#[derive(Clone, Debug, Default)]
+pub struct CidrList {
+    ranges: Vec<Cidr>,
+}
+
+impl CidrList {
+    /// Parses a configured list of ranges
+    ///
+    /// # Arguments
+    /// * `spec` - Comma-separated ranges in CIDR notation, or addresses
+    pub fn parse(spec: &str) -> Result<Self, Error> {
+        let ranges = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|range| !range.is_empty())
+            .map(|range| {
+                Cidr::parse(range).ok_or_else(|| {
+                    Error::Parse(format!(
+                        "invalid address range `{}`, expected e.g. `10.0.0.0/8`",
+                        range
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(CidrList { ranges })
+    }
+
+    /// Returns true if an address is in any of the ranges
+    ///
+    /// # Arguments
+    /// * `addr` - The address
+    fn contains(&self, addr: IpAddr) -> bool {
+        self.ranges.iter().any(|range| range.contains(addr))
+    }
+}
+
+/// Middleware that rejects requests from addresses outside their route group's allowlist
+#[derive(Clone, Debug, Default)]
+pub struct IpAllowlist {
+    /// Addresses Slack requests may come from, if restricted
+    pub slack: Option<CidrList>,
+
+    /// Addresses admin UI and API requests may come from, if restricted
+    pub admin: Option<CidrList>,
+
+    /// Addresses requests to every other route may come from, if restricted
+    pub public: Option<CidrList>,
+
+    /// Proxies whose `X-Forwarded-For` headers are trusted
+    pub trusted_proxies: CidrList,
+}
+
+impl IpAllowlist {
+    /// Returns the allowlist of the group a path belongs to, if it has one
+    ///
+    /// # Arguments
+    /// * `path` - Path of the request
+    fn for_path(&self, path: &str) -> Option<&CidrList> {
+        let matches = |prefix: &&str| path == *prefix || path.starts_with(&format!("{}/", prefix));
+
+        if path == "/" || SLACK_ROUTES.iter().any(matches) {
+            self.slack.as_ref()
+        } else if ADMIN_ROUTES.iter().any(matches) {
+            self.admin.as_ref()
+        } else {
+            self.public.as_ref()
+        }
+    }
+
+    /// Determines the address of the client that made a request
+    ///
+    /// # Arguments
+    /// * `req` - Incoming HTTP request
+    fn client_addr<S>(&self, req: &tide::Request<S>) -> Option<IpAddr> {
+        let peer = req.peer_addr().and_then(|peer| {
+            peer.parse::<SocketAddr>()
+                .map(|addr| addr.ip())
+                .or_else(|_| peer.parse::<IpAddr>())
+                .ok()
+        })?;
+
+        let forwarded: Vec<IpAddr> = req
+            .header("X-Forwarded-For")
+            .map(|values| {
+                values
+                    .iter()
+                    .flat_map(|value| value.as_str().split(','))
+                    .filter_map(|addr| addr.trim().parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(self.resolve(peer, &forwarded))
+    }
+
+    /// Determines the address of the client from the peer and the addresses it forwarded for
+    ///
+    /// # Arguments
+    /// * `peer` - Address of the peer that sent the request
+    /// * `forwarded` - Addresses in `X-Forwarded-For`, from left to right
+    fn resolve(&self, peer: IpAddr, forwarded: &[IpAddr]) -> IpAddr {
+        if !self.trusted_proxies.contains(peer) {
+            return peer;
+        }
+
+        // the rightmost address not added by one of our own proxies is the client
+        forwarded
+            .iter()
+            .rev()
+            .find(|addr| !self.trusted_proxies.contains(**addr))
+            .or_else(|| forwarded.first())
+            .copied()
+            .unwrap_or(peer)
+    }
+}
+
+#[async_trait]
+impl<S: Clone + Send + Sync + 'static> Middleware<S> for IpAllowlist {
+    async fn handle(&self, mut req: tide::Request<S>, next: Next<'_, S>) -> tide::Result {
+        let addr = self.client_addr(&req);
+        if let Some(addr) = addr {
+            req.set_ext(ClientAddr(addr));
+        }
+
+        let allowlist = match self.for_path(req.url().path()) {
+            Some(allowlist) => allowlist,
+            None => return Ok(next.run(req).await),
+        };
+
+        match addr {
+            Some(addr) if allowlist.contains(addr) => Ok(next.run(req).await),
+            addr => {
+                tracing::warn!(
+                    "Rejected request to {} from {} outside the allowlist",
+                    req.url().path(),
+                    addr.map(|addr| addr.to_string())
+                        .unwrap_or_else(|| "unknown address".to_owned())
+                );
+                Ok(tide::Response::builder(StatusCode::Forbidden).build())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses an address
+    ///
+    /// # Arguments
+    /// * `s` - The address
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    /// An allowlist that trusts the `X-Forwarded-For` of proxies in `10.0.0.0/8`
+    fn behind_proxy() -> IpAllowlist {
+        IpAllowlist {
+            trusted_proxies: CidrList::parse("10.0.0.0/8").unwrap(),
+            ..IpAllowlist::default()
+        }
+    }
+
+    #[test]
+    fn parse_ranges() {
+        assert_eq!(
+            Cidr::parse("10.0.0.0/8"),
+            Some(Cidr {
+                network: ip("10.0.0.0"),
+                prefix: 8
+            })
+        );
+        assert_eq!(
+            Cidr::parse(" 2001:db8::/32 "),
+            Some(Cidr {
+                network: ip("2001:db8::"),
+                prefix: 32
+            })
+        );
+        assert_eq!(
+            Cidr::parse("192.0.2.1"),
+            Some(Cidr {
+                network: ip("192.0.2.1"),
+                prefix: 32
+            })
+        );
+        assert_eq!(Cidr::parse("10.0.0.0/33"), None);
+        assert_eq!(Cidr::parse("2001:db8::/129"), None);
+        assert_eq!(Cidr::parse("10.0.0/8"), None);
+        assert_eq!(Cidr::parse("10.0.0.0/"), None);
+    }
+
+    #[test]
+    fn parse_list() {
+        let list = CidrList::parse("10.0.0.0/8, ,192.0.2.1").unwrap();
+        assert_eq!(list.ranges.len(), 2);
+        assert!(CidrList::parse("").unwrap().ranges.is_empty());
+        assert!(CidrList::parse("10.0.0.0/8,nope").is_err());
+    }
+
+    #[test]
+    fn contains() {
+        let v4 = Cidr::parse("10.1.0.0/16").unwrap();
+        assert!(v4.contains(ip("10.1.0.0")));
+        assert!(v4.contains(ip("10.1.255.255")));
+        assert!(!v4.contains(ip("10.2.0.0")));
+        assert!(!v4.contains(ip("::a01:0")));
+
+        let v6 = Cidr::parse("2001:db8::/32").unwrap();
+        assert!(v6.contains(ip("2001:db8:ffff::1")));
+        assert!(!v6.contains(ip("2001:db9::1")));
+        assert!(!v6.contains(ip("10.1.0.0")));
+
+        let single = Cidr::parse("192.0.2.1").unwrap();
+        assert!(single.contains(ip("192.0.2.1")));
+        assert!(!single.contains(ip("192.0.2.2")));
+
+        let everything = Cidr::parse("0.0.0.0/0").unwrap();
+        assert!(everything.contains(ip("203.0.113.7")));
+        assert!(Cidr::parse("::/0").unwrap().contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_match_ipv4_ranges() {
+        assert_eq!(normalize(ip("::ffff:10.1.2.3")), ip("10.1.2.3"));
+        assert_eq!(normalize(ip("::10.1.2.3")), ip("::10.1.2.3"));
+        assert_eq!(normalize(ip("2001:db8::1")), ip("2001:db8::1"));
+
+        assert!(Cidr::parse("10.0.0.0/8")
+            .unwrap()
+            .contains(ip("::ffff:10.1.2.3")));
+        assert_eq!(
+            Cidr::parse("::ffff:10.0.0.0/8"),
+            Some(Cidr {
+                network: ip("10.0.0.0"),
+                prefix: 8
+            })
+        );
+    }
+
+    #[test]
+    fn route_groups() {
+        let allowlist = IpAllowlist {
+            slack: Some(CidrList::parse("192.0.2.0/24").unwrap()),
+            admin: Some(CidrList::parse("10.0.0.0/8").unwrap()),
+            ..IpAllowlist::default()
+        };
+        let group = |path| allowlist.for_path(path).map(|list| list.ranges[0].network);
+
+        assert_eq!(group("/"), Some(ip("192.0.2.0")));
+        assert_eq!(group("/location"), Some(ip("192.0.2.0")));
+        assert_eq!(group("/interactive"), Some(ip("192.0.2.0")));
+        assert_eq!(group("/admin/teams"), Some(ip("10.0.0.0")));
+        assert_eq!(group("/auth/login"), Some(ip("10.0.0.0")));
+        assert_eq!(group("/desired-state"), Some(ip("10.0.0.0")));
+        assert_eq!(group("/locations"), None);
+        assert_eq!(group("/feed/ops"), None);
+        assert_eq!(group("/ws"), None);
+    }
+
+    #[test]
+    fn untrusted_peer() {
+        let allowlist = behind_proxy();
+        assert_eq!(allowlist.resolve(ip("203.0.113.7"), &[]), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn forwarded_by_trusted_proxy() {
+        let allowlist = behind_proxy();
+        let forwarded = [ip("203.0.113.7"), ip("10.0.0.2")];
+        assert_eq!(
+            allowlist.resolve(ip("10.0.0.1"), &forwarded),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            allowlist.resolve(ip("::ffff:10.0.0.1"), &forwarded),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn forwarded_by_untrusted_peer_is_ignored() {
+        let allowlist = behind_proxy();
+        let spoofed = [ip("10.0.0.5")];
+        assert_eq!(
+            allowlist.resolve(ip("203.0.113.7"), &spoofed),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn spoofed_forwarded_for_behind_trusted_proxy() {
+        // the client sent its own `X-Forwarded-For`, which the proxy appended to
+        let allowlist = behind_proxy();
+        let forwarded = [ip("192.0.2.1"), ip("10.0.0.5"), ip("203.0.113.7")];
+        assert_eq!(
+            allowlist.resolve(ip("10.0.0.1"), &forwarded),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn only_trusted_proxies_forwarded() {
+        let allowlist = behind_proxy();
+        let forwarded = [ip("10.0.0.3"), ip("10.0.0.2")];
+        assert_eq!(
+            allowlist.resolve(ip("10.0.0.1"), &forwarded),
+            ip("10.0.0.3")
+        );
+        assert_eq!(allowlist.resolve(ip("10.0.0.1"), &[]), ip("10.0.0.1"));
+    }
+}
@@ -0,0 +1,142 @@
+//! Announcements to a team (e.g., "office closed tomorrow")
+//!
+//! An announcement is either DMed to every member of the team on Slack, or posted to the
+//! team's bound channel.  Messages are sent in the background, and whether each was
+//! delivered is recorded, so the sender can check who it didn't reach.
+
+use crate::{
+    models::{Announcement, SlackUserId, Team},
+    runtime, slack, SqlConn, SqlPool,
+};
+use anyhow::Result;
+
+/// Mentions a recipient of an announcement
+///
+/// # Arguments
+/// * `recipient` - Slack ID of a member or channel
+fn mention(recipient: &str) -> String {
+    if SlackUserId::parse(recipient).is_ok() {
+        format!("<@{}>", recipient)
+    } else {
+        format!("<#{}>", recipient)
+    }
+}
+
+/// Sends an announcement to a team, returning it once saved
+///
+/// Messages are sent in the background, as large teams would take longer than Slack waits
+/// for a command's response
+///
+/// # Arguments
+/// * `pool` - A configured sql pool, used to record deliveries
+/// * `db` - Connection to the SQL database
+/// * `team` - Team to send the announcement to
+/// * `message` - Text of the announcement
+/// * `channel` - Channel to post the announcement in, instead of DMing each member
+/// * `sent_by` - Slack ID of the user sending the announcement
+pub async fn send(
+    pool: SqlPool,
+    db: &mut SqlConn,
+    team: &Team,
+    message: &str,
+    channel: Option<&str>,
+    sent_by: &str,
+) -> Result<Announcement> {
+    let recipients: Vec<String> = match channel {
+        Some(channel) => vec![channel.to_owned()],
+        // guests who aren't on Slack can't be sent messages
+        None => Team::members(&mut *db, &team.name)
+            .await?
+            .into_iter()
+            .map(|member| member.id)
+            .filter(|id| SlackUserId::parse(id).is_ok())
+            .collect(),
+    };
+
+    let announcement = Announcement::new(
+        &mut *db,
+        team,
+        message,
+        channel.is_some(),
+        recipients.len() as i64,
+        sent_by,
+    )
+    .await?;
+
+    let text = format!(
+        ":loudspeaker: *Announcement for team {}* from <@{}>\n{}",
+        team.name, sent_by, message
+    );
+
+    let sent = announcement.clone();
+    runtime::spawn(async move {
+        let mut db = match pool.acquire().await {
+            Ok(db) => db,
+            Err(e) => {
+                tracing::error!("Failed to send announcement {}: {:?}", sent.id, e);
+                return;
+            }
+        };
+
+        for recipient in recipients {
+            let error = slack::chat_post_message(&recipient, &text)
+                .await
+                .err()
+                .map(|e| e.to_string());
+
+            if let Err(e) = sent
+                .record_delivery(&mut db, &recipient, error.as_deref())
+                .await
+            {
+                tracing::error!("Failed to record delivery to {}: {:?}", recipient, e);
+            }
+        }
+    });
+
+    Ok(announcement)
+}
+
+/// Describes the delivery of the last announcement sent to a team, or `None` if there
+/// hasn't been one
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `team` - The team
+pub async fn describe(db: &mut SqlConn, team: &Team) -> Result<Option<String>> {
+    let announcement = match Announcement::fetch_latest(&mut *db, team).await {
+        Some(announcement) => announcement,
+        None => return Ok(None),
+    };
+
+    let deliveries = announcement.deliveries(&mut *db).await?;
+    let delivered = deliveries.iter().filter(|d| d.delivered).count() as i64;
+    let pending = announcement.recipients - deliveries.len() as i64;
+
+    let mut text = format!(
+        "Last announcement, sent by <@{}> on {}:\n>{}\n*{}* of {} delivered",
+        announcement.sent_by,
+        announcement.created_at.format("%Y-%m-%d %H:%M UTC"),
+        announcement.message,
+        delivered,
+        announcement.recipients
+    );
+
+    if pending > 0 {
+        text.push_str(&format!(", {} still sending", pending));
+    }
+
+    let failed: Vec<String> = deliveries
+        .iter()
+        .filter(|d| !d.delivered)
+        .map(|d| match &d.error {
+            Some(error) => format!("{} (`{}`)", mention(&d.recipient), error),
+            None => mention(&d.recipient),
+        })
+        .collect();
+
+    if !failed.is_empty() {
+        text.push_str(&format!("\nNot delivered to: {}", failed.join(", ")));
+    }
+
+    Ok(Some(text))
+}
@@ -0,0 +1,60 @@
+//! Detection of unusual status activity
+//!
+//! Two patterns are flagged on the admin UI's anomalies page (`/admin/anomalies`):
+//!
+//! * Forgotten accounts: members of a team whose Slack account is still active but who haven't
+//!   set a status in `STALE_DAYS` days (users on leave are left out)
+//! * Automation: users who set at least `BUSY_MIN_CHANGES` statuses over the last
+//!   `BUSY_WINDOW_HOURS` hours, far more than anyone updating their status by hand would (a
+//!   script updating a status every minute sets 1,440 a day)
+
+use crate::{
+    models::{BusyUser, StaleUser},
+    SqlConn,
+};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+
+/// Days without setting a status after which an active member is flagged
+pub const STALE_DAYS: i64 = 30;
+
+/// Hours over which the statuses each user set are counted
+pub const BUSY_WINDOW_HOURS: i64 = 24;
+
+/// Fewest statuses set over `BUSY_WINDOW_HOURS` for a user to be flagged
+pub const BUSY_MIN_CHANGES: i64 = 100;
+
+/// Unusual status activity
+#[derive(Clone, Debug)]
+pub struct Anomalies {
+    /// Active members who haven't set a status in `STALE_DAYS` days, longest ago first
+    pub stale: Vec<StaleUser>,
+
+    /// Users who set at least `BUSY_MIN_CHANGES` statuses over the last `BUSY_WINDOW_HOURS`
+    /// hours, most first
+    pub busy: Vec<BusyUser>,
+}
+
+impl Anomalies {
+    /// Looks for unusual status activity
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `now` - Current time
+    pub async fn detect(db: &mut SqlConn, now: DateTime<Utc>) -> Result<Self> {
+        let stale = StaleUser::fetch(
+            &mut *db,
+            now - Duration::days(STALE_DAYS),
+            now.date().naive_utc(),
+        )
+        .await?;
+        let busy = BusyUser::fetch(
+            &mut *db,
+            now - Duration::hours(BUSY_WINDOW_HOURS),
+            BUSY_MIN_CHANGES,
+        )
+        .await?;
+
+        Ok(Anomalies { stale, busy })
+    }
+}
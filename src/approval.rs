@@ -0,0 +1,338 @@
+//! Approval of leave by a chain of approvers
+//!
+//! A team's leads can require its members' leave to be approved by an ordered chain of
+//! approvers (`/location team <team> approvers @first @second [hours]`).  Leave a member of
+//! the team records with `/location leave` is then sent to the first approver as a DM with
+//! buttons to approve or deny it, and only recorded once approved.  If an approver hasn't
+//! decided within the chain's timeout (`DEFAULT_TIMEOUT_HOURS` unless given), the request is
+//! escalated to the next approver; the last approver has no timeout.  Members of several
+//! teams with chains are approved by the chain of the first of them, by name.
+//!
+//! Requests are saved in the database along with the chain they were sent to, and a worker
+//! escalates requests that are due every `POLL_INTERVAL`, so pending approvals survive
+//! restarts.  DMs are sent through the outbox.
+
+use crate::{
+    handoff,
+    models::{Approval, ApprovalChain, Leave},
+    outbox::{self, Effect},
+    render::{Button, ButtonStyle, Markup, ResponseDoc, Text},
+    runtime, slack, SqlConn, SqlPool,
+};
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use serde_json::{json, Value};
+
+/// Action id of the button approvers press to approve leave
+pub const APPROVE_ACTION: &str = "approval_approve";
+
+/// Action id of the button approvers press to deny leave
+pub const DENY_ACTION: &str = "approval_deny";
+
+/// Hours an approver has to decide, if a chain doesn't say
+pub const DEFAULT_TIMEOUT_HOURS: i64 = 24;
+
+/// How often requests due to be escalated are checked for
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Maximum number of requests escalated per poll
+const BATCH_SIZE: i64 = 50;
+
+/// Describes the days of leave requested
+///
+/// # Arguments
+/// * `approval` - The request
+fn dates(approval: &Approval) -> String {
+    format!(
+        "{} to {}",
+        approval.starts_on.format("%a %b %-d"),
+        approval.ends_on.format("%a %b %-d")
+    )
+}
+
+/// Describes a team's chain of approvers
+///
+/// # Arguments
+/// * `team` - Name of the team
+/// * `chain` - The chain
+pub fn describe(team: &str, chain: &ApprovalChain) -> String {
+    let approvers: Vec<String> = chain
+        .approvers()
+        .iter()
+        .map(|id| format!("<@{}>", id))
+        .collect();
+
+    if approvers.len() > 1 {
+        format!(
+            "Leave of team *{}* needs approval by {}, each asked in turn after {} hours without a decision",
+            team,
+            approvers.join(", then "),
+            chain.timeout_hours
+        )
+    } else {
+        format!(
+            "Leave of team *{}* needs approval by {}",
+            team,
+            approvers.join("")
+        )
+    }
+}
+
+/// Renders the prompt sent to the current approver of a request, returning its fallback text
+/// and blocks
+///
+/// # Arguments
+/// * `approval` - The request
+fn prompt(approval: &Approval) -> (String, Value) {
+    let mut text = Text::new()
+        .mention(&approval.user_id, &approval.user_id)
+        .text(format!(
+            " requested leave from {} (team {})",
+            dates(approval),
+            approval.team
+        ));
+    if approval.step > 0 {
+        text = text.text(format!(
+            ". It was escalated to you after {} hours without a decision",
+            approval.timeout_hours
+        ));
+    }
+
+    let id = approval.id.to_string();
+    let mut doc = ResponseDoc::new();
+    doc.section(
+        Text::new()
+            .strong("Leave request")
+            .text("\n")
+            .append(text.clone()),
+    )
+    .actions(vec![
+        Button::new(APPROVE_ACTION, "Approve", id.as_str()).style(ButtonStyle::Primary),
+        Button::new(DENY_ACTION, "Deny", id).style(ButtonStyle::Danger),
+    ]);
+
+    (text.render(Markup::Mrkdwn), json!(doc.to_blocks()))
+}
+
+/// Queues the prompt of a request to its current approver
+///
+/// Call this inside the transaction that sends the request to the approver
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `approval` - The request
+async fn ask(db: &mut SqlConn, approval: &Approval) -> Result<()> {
+    let (text, blocks) = prompt(approval);
+    let effect = Effect::PostBlocks {
+        channel: approval.approver_id.clone(),
+        text,
+        blocks,
+    };
+
+    outbox::enqueue(db, &effect).await
+}
+
+/// Sends a user's leave to the first approver of a chain, returning the request
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `chain` - Chain approving the user's leave
+/// * `user_id` - Slack ID of the user
+/// * `starts_on` - First day of leave
+/// * `ends_on` - Last day of leave (inclusive)
+pub async fn request(
+    db: &mut SqlConn,
+    chain: &ApprovalChain,
+    user_id: &str,
+    starts_on: NaiveDate,
+    ends_on: NaiveDate,
+) -> Result<Approval> {
+    transaction!(db, async {
+        let approval = chain.request(&mut *db, user_id, starts_on, ends_on).await?;
+        ask(&mut *db, &approval).await?;
+
+        Ok::<_, anyhow::Error>(approval)
+    })
+}
+
+/// Records an approver's decision, recording the leave if it's approved, and tells the user
+/// who requested it
+///
+/// Only the current approver may decide: approvers pressing a button after the request was
+/// escalated past them are told who it's with now
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `approval_id` - Id of the request, as sent with the button pressed
+/// * `user_id` - Slack ID of the approver
+/// * `approved` - Whether the leave is approved
+/// * `container` - The message containing the button pressed
+pub async fn respond(
+    db: &mut SqlConn,
+    approval_id: &str,
+    user_id: &str,
+    approved: bool,
+    container: &Value,
+) -> Result<()> {
+    let approval_id: i64 = approval_id.parse()?;
+    let mut approval = match Approval::fetch(&mut *db, approval_id).await {
+        Some(approval) => approval,
+        None => return Ok(()),
+    };
+
+    let recorded = transaction!(db, async {
+        if !approval.decide(&mut *db, user_id, approved).await? {
+            return Ok::<_, anyhow::Error>(None);
+        }
+
+        let recorded = approved
+            && Leave::add(
+                &mut *db,
+                &approval.user_id,
+                approval.starts_on,
+                approval.ends_on,
+                Leave::MANUAL,
+                None,
+            )
+            .await?;
+
+        let text = match (approved, recorded) {
+            (true, true) => format!(
+                ":white_check_mark: <@{}> approved your leave from {}. You'll be marked out of office each morning",
+                user_id,
+                dates(&approval)
+            ),
+            (true, false) => format!(
+                ":white_check_mark: <@{}> approved your leave from {}, but you already have leave on some of those days. Use `/location calendar` to see it",
+                user_id,
+                dates(&approval)
+            ),
+            (false, _) => format!(
+                ":x: <@{}> denied your leave from {}",
+                user_id,
+                dates(&approval)
+            ),
+        };
+
+        let effect = Effect::PostMessage {
+            channel: approval.user_id.clone(),
+            text,
+        };
+        outbox::enqueue(&mut *db, &effect).await?;
+
+        Ok(Some(recorded))
+    })?;
+
+    let text = match recorded {
+        Some(_) if approved => format!(
+            ":white_check_mark: You approved <@{}>'s leave from {}",
+            approval.user_id,
+            dates(&approval)
+        ),
+        Some(_) => format!(
+            ":x: You denied <@{}>'s leave from {}",
+            approval.user_id,
+            dates(&approval)
+        ),
+        // the request changed since it was fetched, so show where it's at now
+        None => match Approval::fetch(&mut *db, approval_id).await {
+            Some(Approval {
+                approved: Some(true),
+                ..
+            }) => "This leave was already approved".to_owned(),
+            Some(Approval {
+                approved: Some(false),
+                ..
+            }) => "This leave was already denied".to_owned(),
+            Some(current) => format!("This request was escalated to <@{}>", current.approver_id),
+            None => return Ok(()),
+        },
+    };
+
+    if let (Some(channel), Some(ts)) = (
+        container["channel_id"].as_str(),
+        container["message_ts"].as_str(),
+    ) {
+        let blocks = json!([{
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": text }
+        }]);
+
+        slack::chat_update(channel, ts, &text, blocks).await?;
+    }
+
+    // approved leave gets the same handoff checklist as leave that needs no approval
+    if recorded == Some(true) {
+        handoff::start(
+            &mut *db,
+            &approval.user_id,
+            approval.starts_on,
+            approval.ends_on,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Escalates the requests whose approver didn't decide in time to the next approver of their
+/// chain, returning the number escalated
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+pub async fn escalate_due(pool: &SqlPool) -> Result<usize> {
+    let mut db = pool.acquire().await?;
+
+    let mut escalated = 0;
+    for mut approval in Approval::fetch_due(&mut db, Utc::now(), BATCH_SIZE).await? {
+        let next = match approval.approvers().get(approval.step as usize + 1) {
+            Some(next) => next.to_string(),
+            None => continue,
+        };
+
+        let done = transaction!(db, async {
+            // another replica may have escalated it, or the approver decided, in the meantime
+            if !approval.escalate(&mut db, &next).await? {
+                return Ok::<_, anyhow::Error>(false);
+            }
+
+            ask(&mut db, &approval).await?;
+
+            let effect = Effect::PostMessage {
+                channel: approval.user_id.clone(),
+                text: format!(
+                    "Your leave from {} was escalated to <@{}> for approval",
+                    dates(&approval),
+                    next
+                ),
+            };
+            outbox::enqueue(&mut db, &effect).await?;
+
+            Ok(true)
+        })?;
+
+        if done {
+            escalated += 1;
+        }
+    }
+
+    Ok(escalated)
+}
+
+/// Spawns the worker that escalates requests whose approver didn't decide in time
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+pub fn spawn(pool: SqlPool) {
+    runtime::spawn(async move {
+        loop {
+            match escalate_due(&pool).await {
+                Ok(0) => (),
+                Ok(count) => tracing::info!("escalated {} leave requests", count),
+                Err(e) => tracing::error!("failed to escalate leave requests: {:?}", e),
+            }
+
+            runtime::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
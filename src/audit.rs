@@ -0,0 +1,284 @@
+//! Weekly audit report of admin-level actions
+//!
+//! Team deletions, role grants, and retention changes are recorded in the audit log, whether
+//! they're made with slash commands, the admin UI, or the admin api.  Dry runs, whose changes
+//! are rolled back along with anything they recorded, are recorded afterwards as a single
+//! entry listing what would have changed.  When `SECURITY_CHANNEL` is set, the Monday morning
+//! run posts a summary of the previous week's actions (Monday to Sunday, UTC) to it, grouped
+//! by action, so a security team can review them without access to the database.
+
+use crate::{
+    models::AuditEntry,
+    render::{self, ResponseDoc, Text},
+    slack, SqlConn,
+};
+use anyhow::Result;
+use chrono::{Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+
+/// Records an admin-level action, logging (rather than failing on) errors
+///
+/// Use `AuditEntry::record` instead inside a transaction that should roll back if the action
+/// can't be recorded
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `actor` - Slack ID of the user who took the action, or `AuditEntry::API`
+/// * `action` - What was done
+/// * `team` - Name of the team it was done to
+/// * `subject` - Slack ID of the user it was done to, if any
+/// * `details` - What changed, if anything
+pub async fn record(
+    db: &mut SqlConn,
+    actor: &str,
+    action: &str,
+    team: &str,
+    subject: Option<&str>,
+    details: Option<&str>,
+) {
+    if let Err(e) = AuditEntry::record(db, actor, action, team, subject, details).await {
+        tracing::error!(
+            "failed to record {} of team {} by {} in the audit log: {:?}",
+            action,
+            team,
+            actor,
+            e
+        );
+    }
+}
+
+/// Records a dry run of changes once its transaction has been rolled back, so the attempt
+/// is kept even though the changes (and any actions they recorded) aren't
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database, outside the rolled back transaction
+/// * `actor` - Who asked for the dry run
+/// * `team` - Name of the team the changes were to, or `AuditEntry::ALL_TEAMS`
+/// * `changes` - What would have changed
+pub async fn record_simulated(db: &mut SqlConn, actor: &str, team: &str, changes: &[String]) {
+    if changes.is_empty() {
+        return;
+    }
+
+    record(
+        db,
+        actor,
+        AuditEntry::SIMULATED,
+        team,
+        None,
+        Some(&changes.join("; ")),
+    )
+    .await
+}
+
+/// Describes who took an action
+///
+/// # Arguments
+/// * `actor` - Slack ID of the user, or `AuditEntry::API`
+fn actor(actor: &str) -> Text {
+    match actor {
+        AuditEntry::API => Text::from("the admin api"),
+        AuditEntry::CONFIG => Text::from("the config tools"),
+        actor => Text::new().mention(actor, actor),
+    }
+}
+
+/// Describes an action, without who took it
+///
+/// # Arguments
+/// * `entry` - The action
+fn describe(entry: &AuditEntry) -> Text {
+    match entry.action.as_str() {
+        AuditEntry::TEAM_DELETED => Text::new()
+            .text("Team ")
+            .strong(&entry.team)
+            .text(" deleted"),
+        AuditEntry::ROLE_GRANTED => {
+            let subject = match &entry.subject {
+                Some(subject) => Text::new().mention(subject, subject),
+                None => Text::from("someone"),
+            };
+            subject
+                .text(format!(
+                    " made a {} of team ",
+                    entry.details.as_deref().unwrap_or("member")
+                ))
+                .strong(&entry.team)
+        }
+        AuditEntry::RETENTION_CHANGED => Text::new()
+            .text("History of team ")
+            .strong(&entry.team)
+            .text(" kept for ")
+            .text(entry.details.as_deref().unwrap_or("the default")),
+        AuditEntry::SIMULATED => Text::new()
+            .text("Dry run, nothing kept: ")
+            .text(entry.details.as_deref().unwrap_or("no changes")),
+        action => Text::new()
+            .text(format!("{} of team ", action))
+            .strong(&entry.team),
+    }
+}
+
+/// Lays out the report of a week's actions
+///
+/// # Arguments
+/// * `monday` - First day of the week
+/// * `entries` - Actions taken that week, oldest first
+fn render(monday: NaiveDate, entries: &[AuditEntry]) -> ResponseDoc {
+    let mut doc = ResponseDoc::new();
+    doc.section(Text::new().strong("Weekly audit report").text(format!(
+        " for {} to {}",
+        monday.format("%a %b %-d"),
+        (monday + Duration::days(6)).format("%a %b %-d")
+    )));
+
+    if entries.is_empty() {
+        doc.section("No admin-level actions were taken");
+        return doc;
+    }
+
+    let sections = [
+        (AuditEntry::TEAM_DELETED, "Teams deleted"),
+        (AuditEntry::ROLE_GRANTED, "Roles granted"),
+        (AuditEntry::RETENTION_CHANGED, "Retention changes"),
+        (AuditEntry::SIMULATED, "Dry runs"),
+    ];
+
+    for (action, title) in sections.iter() {
+        let matching: Vec<Text> = entries
+            .iter()
+            .filter(|e| e.action == *action)
+            .map(|entry| {
+                describe(entry)
+                    .text(" by ")
+                    .append(actor(&entry.actor))
+                    .text(format!(
+                        " on {}",
+                        entry.created_at.format("%a %b %-d %H:%M UTC")
+                    ))
+            })
+            .collect();
+        if matching.is_empty() {
+            continue;
+        }
+
+        doc.header(format!("{} ({})", title, matching.len()));
+        doc.list(matching);
+    }
+
+    doc
+}
+
+/// Posts the report of last week's actions to a channel, if today is Monday
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `channel` - Channel to post the report in
+/// * `today` - Today (UTC)
+pub async fn post_weekly(db: &mut SqlConn, channel: &str, today: NaiveDate) -> Result<()> {
+    if today.weekday() != Weekday::Mon {
+        return Ok(());
+    }
+
+    let monday = today - Duration::weeks(1);
+    let entries = AuditEntry::fetch_between(
+        db,
+        Utc.from_utc_date(&monday).and_hms(0, 0, 0),
+        Utc.from_utc_date(&today).and_hms(0, 0, 0),
+    )
+    .await?;
+
+    let report = render(monday, &entries).render(render::slack());
+    slack::chat_post_message(channel, &report).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Markup;
+    use chrono::DateTime;
+
+    /// An action taken at a time
+    ///
+    /// # Arguments
+    /// * `actor` - Who took the action
+    /// * `action` - What was done
+    /// * `subject` - Who it was done to
+    /// * `details` - What changed
+    /// * `created_at` - When it was done
+    fn entry(
+        actor: &str,
+        action: &str,
+        subject: Option<&str>,
+        details: Option<&str>,
+        created_at: DateTime<Utc>,
+    ) -> AuditEntry {
+        AuditEntry {
+            id: 1,
+            actor: actor.to_owned(),
+            action: action.to_owned(),
+            team: "ops".to_owned(),
+            subject: subject.map(str::to_owned),
+            details: details.map(str::to_owned),
+            created_at,
+        }
+    }
+
+    #[test]
+    fn empty_report() {
+        let monday = NaiveDate::from_ymd(2020, 10, 12);
+        insta::assert_snapshot!(render(monday, &[]).render(Markup::Mrkdwn), @r###"
+        *Weekly audit report* for Mon Oct 12 to Sun Oct 18
+        No admin-level actions were taken
+        "###);
+    }
+
+    #[test]
+    fn report() {
+        let monday = NaiveDate::from_ymd(2020, 10, 12);
+        let entries = [
+            entry(
+                "U1",
+                AuditEntry::ROLE_GRANTED,
+                Some("U2"),
+                Some("lead"),
+                Utc.ymd(2020, 10, 13).and_hms(9, 30, 0),
+            ),
+            entry(
+                AuditEntry::API,
+                AuditEntry::RETENTION_CHANGED,
+                None,
+                None,
+                Utc.ymd(2020, 10, 14).and_hms(17, 0, 0),
+            ),
+        ];
+
+        insta::assert_snapshot!(render(monday, &entries).render(Markup::Mrkdwn), @r###"
+        *Weekly audit report* for Mon Oct 12 to Sun Oct 18
+
+        *Roles granted (1)*
+        • <@U2> made a lead of team *ops* by <@U1> on Tue Oct 13 09:30 UTC
+
+        *Retention changes (1)*
+        • History of team *ops* kept for the default by the admin api on Wed Oct 14 17:00 UTC
+        "###);
+    }
+
+    #[test]
+    fn simulated_report() {
+        let monday = NaiveDate::from_ymd(2020, 10, 12);
+        let entries = [entry(
+            AuditEntry::API,
+            AuditEntry::SIMULATED,
+            None,
+            Some("deleted team ops"),
+            Utc.ymd(2020, 10, 15).and_hms(11, 0, 0),
+        )];
+
+        insta::assert_snapshot!(render(monday, &entries).render(Markup::Mrkdwn), @r###"
+        *Weekly audit report* for Mon Oct 12 to Sun Oct 18
+
+        *Dry runs (1)*
+        • Dry run, nothing kept: deleted team ops by the admin api on Thu Oct 15 11:00 UTC
+        "###);
+    }
+}
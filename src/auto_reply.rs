@@ -0,0 +1,158 @@
+//! Replies to mentions of users who are on leave
+//!
+//! Users opt in with `/location autoreply on [contact]`.  When a message in a channel the bot
+//! is a member of mentions them while they're on leave, the bot replies in the message's
+//! thread with their leave dates and who to contact instead.  In a direct message the bot
+//! can see, the user on the other side is replied for too, mentioned or not.  Each thread is
+//! only replied in once per user, so busy threads aren't flooded.
+
+use crate::{
+    models::{AutoReply, Leave, SlackUserId, User},
+    slack, SqlConn,
+};
+use anyhow::Result;
+use chrono::Utc;
+
+/// Returns the users mentioned in a message, without duplicates
+///
+/// # Arguments
+/// * `text` - Text of the message
+fn mentioned_users(text: &str) -> Vec<String> {
+    let mut users: Vec<String> = text
+        .split("<@")
+        .skip(1)
+        .filter_map(|rest| rest.split('>').next())
+        .filter_map(|mention| SlackUserId::parse(mention).ok())
+        .map(SlackUserId::into_inner)
+        .collect();
+
+    users.sort();
+    users.dedup();
+    users
+}
+
+/// Returns the users a message may be auto-replied for: those it mentions, and the other
+/// side of a direct message, without the sender or duplicates
+///
+/// # Arguments
+/// * `sender` - Slack ID of the user who sent the message
+/// * `text` - Text of the message
+/// * `dm_user` - Slack ID of the user on the other side, if the message is a direct message
+fn recipients(sender: &str, text: &str, dm_user: Option<&str>) -> Vec<String> {
+    let mut users = mentioned_users(text);
+    if let Some(dm_user) = dm_user {
+        if !users.iter().any(|user| user == dm_user) {
+            users.push(dm_user.to_owned());
+        }
+    }
+
+    users.retain(|user| user != sender);
+    users
+}
+
+/// Describes a user's leave, and who to contact instead
+///
+/// Users who haven't named a contact are covered by their delegate, if they have one
+///
+/// # Arguments
+/// * `settings` - The user's auto-reply settings
+/// * `delegate_id` - Slack ID of the user's delegate, if set
+/// * `leave` - The leave the user is on
+fn describe(settings: &AutoReply, delegate_id: Option<&str>, leave: &Leave) -> String {
+    let mut text = if leave.starts_on == leave.ends_on {
+        format!(
+            ":palm_tree: <@{}> is on leave today ({})",
+            settings.user_id,
+            leave.starts_on.format("%a %b %-d")
+        )
+    } else {
+        format!(
+            ":palm_tree: <@{}> is on leave from {} to {}",
+            settings.user_id,
+            leave.starts_on.format("%a %b %-d"),
+            leave.ends_on.format("%a %b %-d")
+        )
+    };
+
+    match (&settings.contact, delegate_id) {
+        (Some(contact), _) => {
+            text.push_str(&format!(". For anything urgent, contact <@{}>", contact))
+        }
+        (None, Some(delegate_id)) => text.push_str(&format!(" (covering: <@{}>)", delegate_id)),
+        (None, None) => (),
+    }
+
+    text
+}
+
+/// Replies to a message for each user it's for (see `recipients`) who is on leave and has
+/// auto-replies on
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `sender` - Slack ID of the user who sent the message
+/// * `text` - Text of the message
+/// * `channel` - Channel the message was posted in
+/// * `ts` - Timestamp of the message
+/// * `thread_ts` - Timestamp of the thread's first message, if the message is a reply
+/// * `dm_user` - Slack ID of the user on the other side, if the message is a direct message
+pub async fn reply(
+    db: &mut SqlConn,
+    sender: &str,
+    text: &str,
+    channel: &str,
+    ts: &str,
+    thread_ts: Option<&str>,
+    dm_user: Option<&str>,
+) -> Result<()> {
+    let today = Utc::today().naive_utc();
+    let thread_ts = thread_ts.unwrap_or(ts);
+
+    for user_id in recipients(sender, text, dm_user) {
+        let settings = match AutoReply::fetch(&mut *db, &user_id).await {
+            Some(settings) if settings.enabled => settings,
+            _ => continue,
+        };
+
+        let leave = match Leave::fetch_covering(&mut *db, &user_id, today).await? {
+            Some(leave) => leave,
+            None => continue,
+        };
+
+        if !settings.record_thread(&mut *db, channel, thread_ts).await? {
+            continue;
+        }
+
+        let delegate_id = User::fetch_delegate(&mut *db, &user_id).await?;
+        let text = describe(&settings, delegate_id.as_deref(), &leave);
+        if let Err(e) = slack::chat_post_reply(channel, thread_ts, &text).await {
+            tracing::error!("Failed to reply to mention of {}: {:?}", user_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_recipients_are_mentions() {
+        let text = "<@U2> and <@U3>, see <@U2>'s note (thanks <@U1>)";
+        assert_eq!(recipients("U1", text, None), vec!["U2", "U3"]);
+    }
+
+    #[test]
+    fn dm_recipients_include_the_other_side() {
+        assert_eq!(recipients("U1", "are you around?", Some("U2")), vec!["U2"]);
+        assert_eq!(recipients("U1", "ask <@U3>?", Some("U2")), vec!["U3", "U2"]);
+        assert_eq!(recipients("U1", "<@U2> hi", Some("U2")), vec!["U2"]);
+    }
+
+    #[test]
+    fn dm_with_the_bot_has_no_recipients() {
+        // in the bot's own DMs, the other side is the sender
+        assert!(recipients("U1", "in a meeting", Some("U1")).is_empty());
+    }
+}
@@ -0,0 +1,313 @@
+//! Load generator for a running StatusBot
+//!
+//! Fires synthetic, signed slash commands and event callbacks at an instance at fixed rates
+//! (requests are sent on schedule, whether or not earlier ones have finished), then reports
+//! latency percentiles for each.  Built with the `bench` feature:
+//!
+//! ```text
+//! cargo run --release --features bench --bin statusbot-bench -- \
+//!     --url http://localhost:8080 --commands-per-sec 20 --events-per-sec 5 --duration 30
+//! ```
+//!
+//! Commands and events are processed for real, so point it at a staging instance.
+
+use chrono::Utc;
+use futures::{channel::mpsc, StreamExt};
+use serde_json::json;
+use statusbot::{runtime, signing};
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+/// Command line options of the bench harness
+#[derive(StructOpt, Debug)]
+#[structopt(name = "statusbot-bench")]
+struct BenchOpt {
+    /// Base URL of the running instance
+    #[structopt(long, default_value = "http://localhost:8080")]
+    url: String,
+
+    /// Signing secret the instance verifies requests with (requests are unsigned if unset)
+    #[structopt(long, env = "SLACK_SIGNING_SECRET", hide_env_values = true)]
+    signing_secret: Option<String>,
+
+    /// Slash commands to send per second (0 sends none)
+    #[structopt(long, default_value = "10")]
+    commands_per_sec: f64,
+
+    /// Event callbacks to send per second (0 sends none)
+    #[structopt(long, default_value = "0")]
+    events_per_sec: f64,
+
+    /// How long to send requests for, in seconds
+    #[structopt(long, default_value = "30")]
+    duration: u64,
+
+    /// Seconds to wait for a response before counting a request as failed
+    #[structopt(long, default_value = "10")]
+    timeout: u64,
+
+    /// Text of the slash commands to send, used in turn (e.g., `--command "team list"
+    /// --command ops`)
+    #[structopt(long = "command", default_value = "team list")]
+    commands: Vec<String>,
+
+    /// Slack ID of the user sending commands and messages
+    #[structopt(long, default_value = "UBENCH0001")]
+    user: String,
+
+    /// Slack ID of the channel commands and messages are sent from
+    #[structopt(long, default_value = "CBENCH0001")]
+    channel: String,
+}
+
+/// Kinds of requests sent
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    /// `POST /location`
+    Command,
+
+    /// `POST /` with an `event_callback`
+    Event,
+}
+
+impl Kind {
+    /// Returns the name of this kind of request, as shown in the report
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Command => "slash commands",
+            Kind::Event => "event callbacks",
+        }
+    }
+}
+
+/// Outcome of a single request
+#[derive(Debug)]
+struct Sample {
+    /// Kind of request sent
+    kind: Kind,
+
+    /// Time until the response was received
+    latency: Duration,
+
+    /// If the instance responded with a success status in time
+    ok: bool,
+}
+
+/// Builds the `n`th request of a kind, returning its path, content type, and body
+///
+/// # Arguments
+/// * `opt` - Command line options
+/// * `kind` - Kind of request to build
+/// * `n` - Sequence number of the request
+fn request(opt: &BenchOpt, kind: Kind, n: usize) -> (&'static str, &'static str, String) {
+    match kind {
+        Kind::Command => {
+            let text = &opt.commands[n % opt.commands.len()];
+            let body = serde_urlencoded::to_string(&[
+                ("token", "bench"),
+                ("command", "/location"),
+                ("text", text.as_str()),
+                ("response_url", ""),
+                ("trigger_id", "bench"),
+                ("user_id", opt.user.as_str()),
+                ("user_name", "bench"),
+                ("team_id", "TBENCH0001"),
+                ("channel_id", opt.channel.as_str()),
+                ("api_app_id", "ABENCH0001"),
+            ])
+            .unwrap_or_default();
+
+            ("/location", "application/x-www-form-urlencoded", body)
+        }
+        Kind::Event => {
+            let now = Utc::now();
+            let ts = format!("{}.{:06}", now.timestamp(), n % 1_000_000);
+            let body = json!({
+                "token": "bench",
+                "team_id": "TBENCH0001",
+                "api_app_id": "ABENCH0001",
+                "type": "event_callback",
+                "event": {
+                    "type": "message",
+                    "channel": opt.channel,
+                    "user": opt.user,
+                    "text": "bench",
+                    "ts": ts,
+                    "event_ts": ts,
+                    "channel_type": "channel",
+                },
+                "authed_users": [],
+                // every event is new, else retries are skipped without being processed
+                "event_id": format!("EvBENCH{}{}", now.timestamp(), n),
+                "event_time": now.timestamp(),
+            });
+
+            ("/", "application/json", body.to_string())
+        }
+    }
+}
+
+/// Sends a request, returning whether it succeeded
+///
+/// # Arguments
+/// * `url` - URL to send the request to
+/// * `content_type` - Value of the `Content-Type` header
+/// * `body` - Body of the request
+/// * `secret` - Signing secret to sign the request with, if any
+async fn send(url: String, content_type: &str, body: String, secret: Option<String>) -> bool {
+    let timestamp = Utc::now().timestamp().to_string();
+    let mut req = surf::post(url)
+        .set_header("Content-Type", content_type)
+        .set_header("X-Slack-Request-Timestamp", &timestamp);
+
+    if let Some(secret) = secret {
+        let signature = signing::sign_slack(&secret, &timestamp, body.as_bytes());
+        req = req.set_header("X-Slack-Signature", signature);
+    }
+
+    match req.body_string(body).await {
+        Ok(resp) => resp.status().is_success(),
+        Err(_) => false,
+    }
+}
+
+/// Returns the latency at a percentile of sorted latencies
+///
+/// # Arguments
+/// * `sorted` - Latencies, sorted fastest first
+/// * `pct` - The percentile (e.g., `99.0`)
+fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::default();
+    }
+
+    let rank = (sorted.len() as f64 * pct / 100.0).ceil() as usize;
+    sorted[rank.max(1).min(sorted.len()) - 1]
+}
+
+/// Prints the latencies of one kind of request
+///
+/// # Arguments
+/// * `kind` - Kind of request to report on
+/// * `samples` - Outcomes of every request sent
+/// * `elapsed` - How long requests were sent for
+fn report(kind: Kind, samples: &[Sample], elapsed: Duration) {
+    let samples: Vec<&Sample> = samples.iter().filter(|s| s.kind == kind).collect();
+    if samples.is_empty() {
+        return;
+    }
+
+    let mut latencies: Vec<Duration> = samples.iter().filter(|s| s.ok).map(|s| s.latency).collect();
+    latencies.sort();
+
+    let ms = |latency: Duration| latency.as_secs_f64() * 1000.0;
+    println!(
+        "{}: {} sent ({:.1}/s), {} ok, {} failed",
+        kind.name(),
+        samples.len(),
+        samples.len() as f64 / elapsed.as_secs_f64(),
+        latencies.len(),
+        samples.len() - latencies.len()
+    );
+    println!(
+        "  p50 {:.1}ms  p90 {:.1}ms  p99 {:.1}ms  max {:.1}ms",
+        ms(percentile(&latencies, 50.0)),
+        ms(percentile(&latencies, 90.0)),
+        ms(percentile(&latencies, 99.0)),
+        ms(latencies.last().copied().unwrap_or_default())
+    );
+}
+
+/// Sends requests of one kind at a fixed rate until the run is over
+///
+/// # Arguments
+/// * `opt` - Command line options
+/// * `kind` - Kind of request to send
+/// * `rate` - Requests per second
+/// * `started` - When the run started
+/// * `tx` - Channel outcomes are sent to
+async fn generate(
+    opt: &BenchOpt,
+    kind: Kind,
+    rate: f64,
+    started: Instant,
+    tx: mpsc::UnboundedSender<Sample>,
+) {
+    if rate <= 0.0 {
+        return;
+    }
+
+    let interval = Duration::from_secs_f64(1.0 / rate);
+    let end = started + Duration::from_secs(opt.duration);
+
+    let mut n = 0;
+    loop {
+        // requests are scheduled from the start of the run, so slow sends don't lower the rate
+        let at = started + interval * n as u32;
+        if at >= end {
+            break;
+        }
+        runtime::sleep(at.saturating_duration_since(Instant::now())).await;
+
+        let (path, content_type, body) = request(opt, kind, n);
+        let url = format!("{}{}", opt.url.trim_end_matches('/'), path);
+        let secret = opt.signing_secret.clone();
+        let timeout = Duration::from_secs(opt.timeout);
+        let tx = tx.clone();
+
+        runtime::spawn(async move {
+            let sent = Instant::now();
+            let ok = runtime::timeout(timeout, send(url, content_type, body, secret))
+                .await
+                .unwrap_or(false);
+
+            tx.unbounded_send(Sample {
+                kind,
+                latency: sent.elapsed(),
+                ok,
+            })
+            .ok();
+        });
+
+        n += 1;
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    dotenv::dotenv().ok();
+    let opt = BenchOpt::from_args();
+
+    if opt.commands.is_empty() {
+        anyhow::bail!("at least one --command is required");
+    }
+
+    runtime::block_on(async {
+        let (tx, rx) = mpsc::unbounded();
+        let started = Instant::now();
+
+        println!(
+            "sending {}/s slash commands and {}/s event callbacks to {} for {}s",
+            opt.commands_per_sec, opt.events_per_sec, opt.url, opt.duration
+        );
+
+        futures::join!(
+            generate(
+                &opt,
+                Kind::Command,
+                opt.commands_per_sec,
+                started,
+                tx.clone()
+            ),
+            generate(&opt, Kind::Event, opt.events_per_sec, started, tx),
+        );
+        let elapsed = started.elapsed();
+
+        // wait for requests still in flight (every sender is dropped once they finish)
+        let samples: Vec<Sample> = rx.collect().await;
+
+        report(Kind::Command, &samples, elapsed);
+        report(Kind::Event, &samples, elapsed);
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! Circuit breakers around outbound providers
+//!
+//! Every outbound request goes through the breaker of the host it's sent to (e.g.
+//! `slack.com`, or a Jira server).  After `BREAKER_THRESHOLD` consecutive failures (errors,
+//! `429 Too Many Requests`, or `5xx` responses) the breaker opens, and requests to the host
+//! fail immediately instead of waiting on an outage.  After `BREAKER_COOLDOWN` seconds one
+//! request is let through: if it succeeds the breaker closes, otherwise it stays open for
+//! another cool-down.
+//!
+//! The state of every breaker is shown on `/health` and the admin UI's breakers page.
+
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Consecutive failures that open a breaker if `BREAKER_THRESHOLD` isn't set
+pub const DEFAULT_THRESHOLD: u32 = 5;
+
+/// Seconds a breaker stays open if `BREAKER_COOLDOWN` isn't set
+pub const DEFAULT_COOLDOWN: u64 = 60;
+
+/// When breakers open, and for how long
+static CONFIG: OnceCell<BreakerConfig> = OnceCell::new();
+
+/// Breakers of every host requests have been sent to, by host
+static BREAKERS: Lazy<Mutex<HashMap<String, Breaker>>> = Lazy::new(Default::default);
+
+/// When breakers open, and for how long
+#[derive(Clone, Copy, Debug)]
+pub struct BreakerConfig {
+    /// Consecutive failures that open a breaker (0 never opens one)
+    pub threshold: u32,
+
+    /// How long a breaker stays open before a request is let through
+    pub cooldown: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        BreakerConfig {
+            threshold: DEFAULT_THRESHOLD,
+            cooldown: Duration::from_secs(DEFAULT_COOLDOWN),
+        }
+    }
+}
+
+/// Whether requests to a host are being sent
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    /// Requests are sent
+    Closed,
+
+    /// Requests fail immediately until the cool-down ends
+    Open,
+
+    /// The cool-down ended, and one request has been let through to test the host
+    HalfOpen,
+}
+
+/// Breaker of one host
+#[derive(Clone, Debug)]
+pub struct Breaker {
+    /// Whether requests are being sent
+    pub state: State,
+
+    /// Failures since the last success
+    pub failures: u32,
+
+    /// When the breaker last opened, or let a test request through
+    pub opened_at: Option<Instant>,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Breaker {
+            state: State::Closed,
+            failures: 0,
+            opened_at: None,
+        }
+    }
+}
+
+/// Error returned for requests to a host whose breaker is open
+#[derive(Clone, Debug)]
+pub struct Open {
+    /// The host
+    pub host: String,
+}
+
+impl std::fmt::Display for Open {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is unavailable (circuit breaker open)", self.host)
+    }
+}
+
+impl std::error::Error for Open {}
+
+/// Configures when breakers open, and for how long
+///
+/// Only the first call has an effect
+///
+/// # Arguments
+/// * `config` - When breakers open, and for how long
+pub fn configure(config: BreakerConfig) {
+    CONFIG.set(config).ok();
+}
+
+/// Returns when breakers open, and for how long
+fn config() -> &'static BreakerConfig {
+    CONFIG.get_or_init(BreakerConfig::default)
+}
+
+/// Checks whether a request may be sent to a host
+///
+/// Fails if the host's breaker is open, or a request testing the host is already in flight
+///
+/// # Arguments
+/// * `host` - Host the request is sent to
+pub fn check(host: &str) -> Result<(), Open> {
+    let config = config();
+    if config.threshold == 0 {
+        return Ok(());
+    }
+
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = breakers.entry(host.to_owned()).or_default();
+    match breaker.state {
+        State::Closed => Ok(()),
+        // a test request that never finished doesn't hold the breaker open forever
+        State::Open | State::HalfOpen
+            if breaker
+                .opened_at
+                .map_or(true, |opened_at| opened_at.elapsed() >= config.cooldown) =>
+        {
+            tracing::info!("testing {} after its circuit breaker cooled down", host);
+            breaker.state = State::HalfOpen;
+            breaker.opened_at = Some(Instant::now());
+            Ok(())
+        }
+        State::Open | State::HalfOpen => Err(Open {
+            host: host.to_owned(),
+        }),
+    }
+}
+
+/// Records the outcome of a request to a host
+///
+/// # Arguments
+/// * `host` - Host the request was sent to
+/// * `ok` - True if the host handled the request
+pub fn record(host: &str, ok: bool) {
+    let config = config();
+    if config.threshold == 0 {
+        return;
+    }
+
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = breakers.entry(host.to_owned()).or_default();
+    if ok {
+        if breaker.state != State::Closed {
+            tracing::info!("{} recovered, closing its circuit breaker", host);
+        }
+        *breaker = Breaker::default();
+        return;
+    }
+
+    breaker.failures += 1;
+    let reopen = breaker.state == State::HalfOpen;
+    if reopen || (breaker.state == State::Closed && breaker.failures >= config.threshold) {
+        tracing::warn!(
+            "opening the circuit breaker of {} after {} consecutive failures",
+            host,
+            breaker.failures
+        );
+        breaker.state = State::Open;
+        breaker.opened_at = Some(Instant::now());
+    }
+}
+
+/// Returns the breakers of every host requests have been sent to, sorted by host
+pub fn breakers() -> Vec<(String, Breaker)> {
+    let mut breakers: Vec<_> = BREAKERS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(host, breaker)| (host.clone(), breaker.clone()))
+        .collect();
+
+    breakers.sort_by(|a, b| a.0.cmp(&b.0));
+    breakers
+}
@@ -0,0 +1,61 @@
+//! Conditional GET support (`ETag` and `Last-Modified`)
+//!
+//! Clients that poll frequently (feed readers, wall displays) can revalidate with
+//! `If-None-Match`/`If-Modified-Since` and receive an empty `304 Not Modified` if
+//! nothing has changed.
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use tide::StatusCode;
+
+/// Format of dates in HTTP headers
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Builds a response that honors conditional request headers
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+/// * `content_type` - Content type of the body
+/// * `body` - Full response body
+/// * `last_modified` - When the content last changed, if known
+pub fn conditional<S>(
+    req: &tide::Request<S>,
+    content_type: &str,
+    body: String,
+    last_modified: Option<DateTime<Utc>>,
+) -> tide::Response {
+    let etag = format!(
+        r#""{}""#,
+        &hex::encode(Sha256::digest(body.as_bytes()))[..32]
+    );
+    let last_modified = last_modified.map(|date| date.format(HTTP_DATE).to_string());
+
+    // If-None-Match takes precedence over If-Modified-Since
+    let not_modified = match req.header("If-None-Match") {
+        Some(values) => values
+            .iter()
+            .any(|value| value.as_str().split(',').any(|tag| tag.trim() == etag)),
+        None => match (req.header("If-Modified-Since"), &last_modified) {
+            (Some(values), Some(last_modified)) => values.last().as_str() == last_modified,
+            _ => false,
+        },
+    };
+
+    let mut builder = if not_modified {
+        tide::Response::builder(StatusCode::NotModified)
+    } else {
+        tide::Response::builder(StatusCode::Ok)
+            .header("Content-Type", content_type)
+            .body(body)
+    };
+
+    builder = builder
+        .header("ETag", etag.as_str())
+        .header("Cache-Control", "no-cache");
+
+    if let Some(last_modified) = last_modified {
+        builder = builder.header("Last-Modified", last_modified.as_str());
+    }
+
+    builder.build()
+}
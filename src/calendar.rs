@@ -0,0 +1,231 @@
+//! Periodic import of leave from users' personal calendars
+//!
+//! Calendars are fetched in iCalendar format, and every event marked as out of office
+//! (Outlook's `X-MICROSOFT-CDO-BUSYSTATUS:OOF`) or whose summary mentions leave (e.g.,
+//! "Vacation" or "OOO") is recorded as leave.  Events already imported, or overlapping
+//! leave the user entered themselves, are skipped.
+
+use crate::{
+    models::{Calendar, Leave},
+    outbound, runtime, SqlConn, SqlPool,
+};
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use std::time::Duration as StdDuration;
+
+/// Words that mark an event's summary as leave (compared in lowercase)
+const LEAVE_WORDS: &[&str] = &[
+    "ooo",
+    "out of office",
+    "vacation",
+    "holiday",
+    "leave",
+    "pto",
+];
+
+/// An event marked as leave
+#[derive(Clone, Debug)]
+struct LeaveEvent {
+    /// UID of the event
+    uid: String,
+
+    /// First day of the event
+    starts_on: NaiveDate,
+
+    /// Last day of the event (inclusive)
+    ends_on: NaiveDate,
+}
+
+/// An event being parsed
+#[derive(Debug, Default)]
+struct Draft {
+    /// UID of the event
+    uid: Option<String>,
+
+    /// First day of the event
+    starts_on: Option<NaiveDate>,
+
+    /// Day the event ends on (exclusively, for all-day events)
+    ends: Option<NaiveDate>,
+
+    /// If the event lasts all day (its start is a date without a time)
+    all_day: bool,
+
+    /// If the event is marked as leave
+    leave: bool,
+}
+
+impl Draft {
+    /// Finishes parsing the event, returning `None` if it isn't leave
+    fn finish(self) -> Option<LeaveEvent> {
+        if !self.leave {
+            return None;
+        }
+
+        let starts_on = self.starts_on?;
+        let ends_on = match self.ends {
+            // all-day events end on the day after their last day
+            Some(ends) if self.all_day && ends > starts_on => ends - Duration::days(1),
+            Some(ends) if !self.all_day && ends >= starts_on => ends,
+            _ => starts_on,
+        };
+
+        Some(LeaveEvent {
+            uid: self.uid?,
+            starts_on,
+            ends_on,
+        })
+    }
+}
+
+/// Parses the date of a `DTSTART` or `DTEND` value (e.g., `20201019` or `20201019T090000Z`)
+///
+/// # Arguments
+/// * `value` - The property's value
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+}
+
+/// Parses the events marked as leave out of an iCalendar document
+///
+/// # Arguments
+/// * `ics` - The calendar, in iCalendar format
+fn parse_leave(ics: &str) -> Vec<LeaveEvent> {
+    // long lines are folded onto lines starting with whitespace
+    let mut lines: Vec<String> = vec![];
+    for line in ics.lines() {
+        match line.strip_prefix(|c| c == ' ' || c == '\t') {
+            Some(rest) if !lines.is_empty() => {
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(rest);
+                }
+            }
+            _ => lines.push(line.to_owned()),
+        }
+    }
+
+    let mut events = vec![];
+    let mut event: Option<Draft> = None;
+
+    for line in lines {
+        let mut parts = line.splitn(2, ':');
+        let (name, value) = match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) => (name, value.trim()),
+            _ => continue,
+        };
+
+        // drop parameters (e.g., `DTSTART;VALUE=DATE`), remembering if the value is a date
+        let is_date = name.contains("VALUE=DATE") && !name.contains("VALUE=DATE-TIME");
+        let name = name.split(';').next().unwrap_or_default().to_uppercase();
+
+        match (name.as_str(), &mut event) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => {
+                event = Some(Draft::default());
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                events.extend(event.take().and_then(Draft::finish));
+            }
+            ("UID", Some(draft)) => draft.uid = Some(value.to_owned()),
+            ("DTSTART", Some(draft)) => {
+                draft.starts_on = parse_date(value);
+                draft.all_day = is_date || value.len() == 8;
+            }
+            ("DTEND", Some(draft)) => draft.ends = parse_date(value),
+            ("SUMMARY", Some(draft)) => {
+                let summary = value.to_lowercase();
+                draft.leave |= LEAVE_WORDS.iter().any(|word| summary.contains(word));
+            }
+            ("X-MICROSOFT-CDO-BUSYSTATUS", Some(draft)) => {
+                draft.leave |= value.eq_ignore_ascii_case("OOF");
+            }
+            _ => (),
+        }
+    }
+
+    events
+}
+
+/// Fetches a calendar and imports its leave, returning the number of entries added
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `calendar` - The calendar to sync
+pub async fn sync(db: &mut SqlConn, calendar: &mut Calendar) -> Result<usize> {
+    let mut resp = outbound::get(&calendar.url)
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to fetch calendar: {}", e))?;
+
+    let code = resp.status();
+    if code.is_client_error() || code.is_server_error() {
+        return Err(anyhow::anyhow!("failed to fetch calendar: HTTP {}", code));
+    }
+
+    let ics = resp
+        .body_string()
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to read calendar: {}", e))?;
+
+    // leave that already ended isn't worth importing
+    let today = Utc::now().date().naive_utc();
+
+    let mut added = 0;
+    for event in parse_leave(&ics) {
+        if event.ends_on < today {
+            continue;
+        }
+
+        let new = Leave::add(
+            &mut *db,
+            &calendar.user_id,
+            event.starts_on,
+            event.ends_on,
+            Leave::CALENDAR,
+            Some(&event.uid),
+        )
+        .await?;
+
+        if new {
+            added += 1;
+        }
+    }
+
+    calendar.mark_synced(&mut *db).await?;
+    Ok(added)
+}
+
+/// Syncs every registered calendar, returning the number of leave entries added
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+pub async fn sync_all(pool: &SqlPool) -> Result<usize> {
+    let mut db = pool.acquire().await?;
+
+    let mut added = 0;
+    for mut calendar in Calendar::fetch_all(&mut db).await? {
+        match sync(&mut db, &mut calendar).await {
+            Ok(count) => added += count,
+            Err(e) => tracing::warn!("failed to sync calendar of {}: {:?}", calendar.user_id, e),
+        }
+    }
+
+    Ok(added)
+}
+
+/// Spawns a task that syncs calendars periodically, starting immediately
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `interval` - Time between syncs
+pub fn spawn(pool: SqlPool, interval: StdDuration) {
+    runtime::spawn(async move {
+        loop {
+            match sync_all(&pool).await {
+                Ok(count) => tracing::info!("imported {} leave entries from calendars", count),
+                Err(e) => tracing::error!("failed to sync calendars: {:?}", e),
+            }
+
+            runtime::sleep(interval).await;
+        }
+    });
+}
@@ -0,0 +1,264 @@
+//! Capturing inbound requests, for replaying (see `replay`) and fuzzing
+//!
+//! Enabled by setting `--capture-dir`, then switched on and off from the admin UI (it starts
+//! off).  While on, every request is appended to a JSONL file in the directory, one
+//! `RecordedRequest` per line; a new file is started once the current one reaches
+//! `--capture-max-bytes`.
+//!
+//! Secrets are scrubbed before anything is written: credentials in headers (signatures,
+//! cookies, authorization), and tokens, codes, and response urls in query strings, form
+//! fields, and JSON bodies (including the JSON `payload` of interactivity forms).
+
+use crate::{replay::RecordedRequest, State};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+use tide::{http::Method, Middleware, Next};
+
+/// Replaces the value of anything scrubbed
+const SCRUBBED: &str = "[scrubbed]";
+
+/// Headers whose values are scrubbed
+const SECRET_HEADERS: &[&str] = &["authorization", "cookie", "x-slack-signature"];
+
+/// Query parameters, form fields, and JSON keys whose values are scrubbed
+const SECRET_FIELDS: &[&str] = &[
+    "_csrf",
+    "access_token",
+    "client_secret",
+    "code",
+    "response_url",
+    "token",
+];
+
+/// Writes captured requests to rotating files
+#[derive(Debug)]
+struct CaptureFiles {
+    /// Directory files are written to
+    dir: PathBuf,
+
+    /// Size (in bytes) at which a new file is started
+    max_bytes: u64,
+
+    /// File being written to, and the number of bytes written to it
+    current: Option<(File, u64)>,
+}
+
+impl CaptureFiles {
+    /// Appends a line to the current file, starting a new file if it's full
+    ///
+    /// # Arguments
+    /// * `line` - The line to write, without a trailing newline
+    fn append(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        let full = match &self.current {
+            Some((_, written)) => *written > 0 && written + len > self.max_bytes,
+            None => true,
+        };
+
+        if full {
+            fs::create_dir_all(&self.dir)?;
+            let name = Utc::now().format("requests-%Y%m%d-%H%M%S%.3f.jsonl");
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(name.to_string()))?;
+            self.current = Some((file, 0));
+        }
+
+        if let Some((file, written)) = &mut self.current {
+            writeln!(file, "{}", line)?;
+            *written += len;
+        }
+
+        Ok(())
+    }
+}
+
+/// Captures inbound requests to a directory, while switched on
+#[derive(Clone, Debug)]
+pub struct Capture {
+    /// If requests are being captured
+    enabled: Arc<AtomicBool>,
+
+    /// Files captured requests are written to
+    files: Arc<Mutex<CaptureFiles>>,
+}
+
+impl Capture {
+    /// Creates a capture, switched off
+    ///
+    /// # Arguments
+    /// * `dir` - Directory to write captured requests to
+    /// * `max_bytes` - Size (in bytes) at which a new file is started
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Capture {
+            enabled: Arc::new(AtomicBool::new(false)),
+            files: Arc::new(Mutex::new(CaptureFiles {
+                dir,
+                max_bytes,
+                current: None,
+            })),
+        }
+    }
+
+    /// Returns the directory captured requests are written to
+    pub fn dir(&self) -> PathBuf {
+        self.files
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .dir
+            .clone()
+    }
+
+    /// Returns if requests are being captured
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Switches capturing on or off
+    ///
+    /// # Arguments
+    /// * `enabled` - If requests should be captured
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Writes a (scrubbed) request to the current file
+    ///
+    /// # Arguments
+    /// * `req` - The captured request
+    fn write(&self, req: &RecordedRequest) {
+        let written = serde_json::to_string(req)
+            .map_err(std::io::Error::from)
+            .and_then(|line| {
+                let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+                files.append(&line)
+            });
+
+        if let Err(e) = written {
+            tracing::warn!("failed to write captured request: {:?}", e);
+        }
+    }
+}
+
+/// Middleware that captures requests, if a capture is configured and switched on
+#[derive(Debug)]
+pub struct CaptureMiddleware;
+
+#[async_trait]
+impl Middleware<State> for CaptureMiddleware {
+    async fn handle(&self, mut req: tide::Request<State>, next: Next<'_, State>) -> tide::Result {
+        let capture = match &req.state().capture {
+            Some(capture) if capture.enabled() => capture.clone(),
+            _ => return Ok(next.run(req).await),
+        };
+
+        // bodies have already been limited in size, so can be read whole
+        let body = match req.method() {
+            Method::Post => {
+                let body = req.body_bytes().await?;
+                req.set_body(body.clone());
+                body
+            }
+            _ => vec![],
+        };
+
+        let url = req.url();
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), scrub_form(query)),
+            None => url.path().to_owned(),
+        };
+
+        let headers = req
+            .iter()
+            .map(|(name, values)| {
+                let name = name.as_str().to_lowercase();
+                let value = if SECRET_HEADERS.contains(&name.as_str()) {
+                    SCRUBBED.to_owned()
+                } else {
+                    values.last().as_str().to_owned()
+                };
+                (name, value)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        capture.write(&RecordedRequest {
+            method: req.method().to_string(),
+            path,
+            headers,
+            body: scrub_body(&String::from_utf8_lossy(&body)),
+        });
+
+        Ok(next.run(req).await)
+    }
+}
+
+/// Scrubs secrets from a request body, which may be JSON or a form
+///
+/// # Arguments
+/// * `body` - The request body
+fn scrub_body(body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut json) => {
+            scrub_json(&mut json);
+            json.to_string()
+        }
+        Err(_) => scrub_form(body),
+    }
+}
+
+/// Scrubs secrets from a url-encoded form (or query string)
+///
+/// Forms that can't be parsed are dropped entirely, as they can't be checked.
+///
+/// # Arguments
+/// * `form` - The url-encoded form
+fn scrub_form(form: &str) -> String {
+    let fields: Vec<(String, String)> = serde_urlencoded::from_str(form).unwrap_or_default();
+    let fields: Vec<(String, String)> = fields
+        .into_iter()
+        .map(|(key, value)| {
+            let value = if SECRET_FIELDS.contains(&key.as_str()) {
+                SCRUBBED.to_owned()
+            } else if key == "payload" {
+                scrub_body(&value)
+            } else {
+                value
+            };
+            (key, value)
+        })
+        .collect();
+
+    serde_urlencoded::to_string(fields).unwrap_or_default()
+}
+
+/// Scrubs secrets from a JSON value, at any depth
+///
+/// # Arguments
+/// * `json` - The JSON value
+fn scrub_json(json: &mut Value) {
+    match json {
+        Value::Object(fields) => {
+            for (key, value) in fields.iter_mut() {
+                if SECRET_FIELDS.contains(&key.as_str()) && value.is_string() {
+                    *value = Value::from(SCRUBBED);
+                } else {
+                    scrub_json(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(scrub_json),
+        _ => (),
+    }
+}
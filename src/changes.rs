@@ -0,0 +1,58 @@
+//! Notifies the channels bound to teams when a member's status changes outside of them
+//!
+//! Statuses set in a DM, with a command, or by a workflow don't show up in any channel,
+//! so teams that opt in get a compact line in their bound channel instead, keeping the
+//! channel the source of truth.
+//!
+//! Notifications are written to the outbox in the same transaction as the status (see
+//! `outbox`), so they're sent exactly when the status is saved.
+
+use crate::{
+    models::{Team, User},
+    outbox::{self, Effect},
+    SqlConn,
+};
+
+/// Saves a user's status, and queues notifications of the change, in a single transaction
+///
+/// # Arguments
+/// * `db` - Connection to SQL database
+/// * `user` - The user whose status changed, with only the changed parts of it set
+/// * `source` - Channel the status was set in, which is not notified
+pub async fn save(db: &mut SqlConn, user: &User, source: Option<&str>) -> anyhow::Result<()> {
+    transaction!(db, async {
+        user.save(&mut *db).await?;
+        notify(&mut *db, user, source).await
+    })
+}
+
+/// Queues a "status changed" line to the bound channel of each of the user's teams that
+/// has notifications enabled
+///
+/// Call this inside the transaction that saves the status (or use `save`)
+///
+/// # Arguments
+/// * `db` - Connection to SQL database
+/// * `user` - The user whose status changed, with only the changed parts of it set
+/// * `source` - Channel the status was set in, which is not notified
+pub async fn notify(db: &mut SqlConn, user: &User, source: Option<&str>) -> anyhow::Result<()> {
+    let status = match user.compact_status() {
+        Some(status) => status,
+        None => return Ok(()),
+    };
+
+    let text = format!("<@{}> changed their status: {}", user.id, status);
+    for channel in Team::notify_channels(db, &user.id).await? {
+        if Some(channel.as_str()) == source {
+            continue;
+        }
+
+        let effect = Effect::PostMessage {
+            channel,
+            text: text.clone(),
+        };
+        outbox::enqueue(db, &effect).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,282 @@
+//! Checks that teams have enough members in the office or available
+//!
+//! Teams may declare a minimum number of members on site for some days of the week (e.g.,
+//! at least 2 on site Monday to Friday).  The morning scheduler warns the configured
+//! channel about every team that is short, and users going out of office are warned (along
+//! with their leads) when it leaves one of their teams short.
+//!
+//! Members assigned to a team's shift today are expected to be working it, so members out
+//! of office on a shift they're assigned to are flagged the same way.
+
+use crate::{
+    error::Error,
+    models::{Availability, Location, Team, User},
+    rota, slack, SqlConn,
+};
+use anyhow::Result;
+use chrono::{Datelike, Utc, Weekday};
+
+/// Abbreviated names of the days of the week, Monday first
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// Returns whether a user is covering for their team: in the office, or available, and
+/// not out of office
+///
+/// # Arguments
+/// * `user` - A member of the team
+pub fn is_covering(user: &User) -> bool {
+    user.availability() != Some(Availability::Ooo)
+        && (user.location() == Some(Location::Office)
+            || user.availability() == Some(Availability::Available))
+}
+
+/// Returns whether a user is on site (in the office, or at a site) and not out of office
+///
+/// # Arguments
+/// * `user` - A member of the team
+pub fn is_on_site(user: &User) -> bool {
+    user.availability() != Some(Availability::Ooo)
+        && matches!(
+            user.location(),
+            Some(Location::Office) | Some(Location::Site)
+        )
+}
+
+/// Parses the days a coverage requirement applies on into a bitmask (bit 0 is Monday)
+///
+/// Days may be typed as a range (`mon-fri`), a list (`mon,wed,fri`), or `daily`
+///
+/// # Arguments
+/// * `text` - Days typed by the user
+pub fn parse_days(text: &str) -> Result<i64, Error> {
+    let invalid = || {
+        Error::Parse(format!(
+            "`{}` is not a valid set of days (e.g., `mon-fri` or `mon,wed,fri`)",
+            text
+        ))
+    };
+
+    let index = |day: &str| {
+        day.parse::<Weekday>()
+            .map(|day| day.num_days_from_monday())
+            .map_err(|_| invalid())
+    };
+
+    if text.eq_ignore_ascii_case("daily") {
+        return Ok(0b111_1111);
+    }
+
+    let mut days = 0;
+    for part in text.split(',') {
+        let mut range = part.splitn(2, '-');
+        let start = index(range.next().unwrap_or_default())?;
+        let end = match range.next() {
+            Some(end) => index(end)?,
+            None => start,
+        };
+
+        if end < start {
+            return Err(invalid());
+        }
+
+        for day in start..=end {
+            days |= 1 << day;
+        }
+    }
+
+    Ok(days)
+}
+
+/// Describes the days a coverage requirement applies on (e.g., `mon, tue, wed`)
+///
+/// # Arguments
+/// * `days` - Bitmask of days (bit 0 is Monday)
+pub fn describe_days(days: i64) -> String {
+    DAYS.iter()
+        .enumerate()
+        .filter(|(i, _)| days & (1 << i) != 0)
+        .map(|(_, day)| *day)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A team that doesn't have enough members covering for it
+#[derive(Clone, Debug)]
+pub struct Gap {
+    /// Name of the team
+    pub team: String,
+
+    /// Number of members covering for the team
+    pub covering: i64,
+
+    /// Number of members needed on site, if the team has a requirement for today
+    pub required: Option<i64>,
+}
+
+impl Gap {
+    /// Describes the gap in a sentence
+    pub fn describe(&self) -> String {
+        match self.required {
+            Some(required) => format!(
+                "Team *{}* needs {} on site today, but only {} will be",
+                self.team, required, self.covering
+            ),
+            None => format!(
+                "Nobody on team *{}* is in the office or available today",
+                self.team
+            ),
+        }
+    }
+}
+
+/// Returns whether a team is short today, given its members' statuses
+///
+/// Teams with a coverage requirement for today need that many members on site; all other
+/// teams (with members) need at least one member covering for them
+///
+/// # Arguments
+/// * `team` - The team
+/// * `members` - Members of the team
+/// * `weekday` - Today
+fn gap(team: &Team, members: &[User], weekday: Weekday) -> Option<Gap> {
+    let (covering, required) = match team.required_coverage(weekday) {
+        Some(required) => (members.iter().filter(|m| is_on_site(m)).count(), required),
+        None if members.is_empty() => return None,
+        None => (members.iter().filter(|m| is_covering(m)).count(), 1),
+    };
+
+    if covering as i64 >= required {
+        return None;
+    }
+
+    Some(Gap {
+        team: team.name.clone(),
+        covering: covering as i64,
+        required: team.required_coverage(weekday),
+    })
+}
+
+/// Returns every team that is short today
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+pub async fn gaps(db: &mut SqlConn) -> Result<Vec<Gap>> {
+    let weekday = Utc::now().weekday();
+
+    let mut gaps = vec![];
+    for team in Team::fetch_all(&mut *db).await? {
+        let members = Team::members(&mut *db, &team.name).await?;
+        gaps.extend(gap(&team, &members, weekday));
+    }
+
+    Ok(gaps)
+}
+
+/// Returns a description of every shift of a team today that a member is assigned to, but
+/// is out of office for
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `team` - The team
+/// * `user_id` - Only check this member's assignments, if set
+async fn missed_shifts(
+    db: &mut SqlConn,
+    team: &Team,
+    user_id: Option<&str>,
+) -> Result<Vec<String>> {
+    let mut missed = vec![];
+    for (shift, members) in rota::shifts_on(&mut *db, team, Utc::now().weekday()).await? {
+        for member in members {
+            let checked = user_id.map_or(true, |id| id == member.id);
+            if checked && member.availability() == Some(Availability::Ooo) {
+                missed.push(format!(
+                    "<@{}> is out of office, but is on shift *{}* of team *{}* today",
+                    member.id, shift.name, team.name
+                ));
+            }
+        }
+    }
+
+    Ok(missed)
+}
+
+/// Warns a channel about every team that is short today, and every shift someone out of
+/// office is assigned to
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `channel` - Channel to post warnings in
+pub async fn check(db: &mut SqlConn, channel: &str) -> Result<()> {
+    for gap in gaps(&mut *db).await? {
+        tracing::info!("not enough coverage for team {}", gap.team);
+
+        let text = format!(":warning: {}", gap.describe());
+        slack::chat_post_message(channel, &text).await?;
+    }
+
+    for team in Team::fetch_all(&mut *db).await? {
+        for missed in missed_shifts(&mut *db, &team, None).await? {
+            slack::chat_post_message(channel, &format!(":warning: {}", missed)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether a user going out of office today leaves any of their teams short of
+/// their coverage requirement, or misses a shift they're assigned to, returning a
+/// description of each conflict
+///
+/// The leads of each affected team (or their delegates, if they're on leave) are sent a DM
+/// about it
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `user_id` - Slack ID of the user going out of office
+pub async fn validate_leave(db: &mut SqlConn, user_id: &str) -> Result<Vec<String>> {
+    let weekday = Utc::now().weekday();
+    let today = Utc::today().naive_utc();
+
+    let mut conflicts = vec![];
+    for team in Team::fetch_by_member(&mut *db, user_id).await? {
+        let mut found = missed_shifts(&mut *db, &team, Some(user_id)).await?;
+
+        if team.required_coverage(weekday).is_some() {
+            // the user's new status may already be saved, so leave them out either way
+            let members: Vec<User> = Team::members(&mut *db, &team.name)
+                .await?
+                .into_iter()
+                .filter(|member| member.id != user_id)
+                .collect();
+
+            found.extend(
+                gap(&team, &members, weekday).map(|gap| {
+                    format!("<@{}> is out of office today. {}", user_id, gap.describe())
+                }),
+            );
+        }
+
+        if found.is_empty() {
+            continue;
+        }
+
+        for lead in team.leads(&mut *db).await? {
+            if lead.id == user_id {
+                continue;
+            }
+
+            // leads on leave have their warnings sent to their delegate
+            let recipient = User::route_to(&mut *db, &lead.id, today).await?;
+            for conflict in &found {
+                let text = format!(":warning: {}", conflict);
+                if let Err(e) = slack::chat_post_message(&recipient, &text).await {
+                    tracing::error!("Failed to warn {} about coverage: {:?}", recipient, e);
+                }
+            }
+        }
+
+        conflicts.extend(found);
+    }
+
+    Ok(conflicts)
+}
@@ -0,0 +1,243 @@
+//! Declarative configuration, reconciled from YAML files kept in version control
+//!
+//! With `--desired-state <dir>`, teams are declared in `<dir>/teams/*.yaml` (in the format
+//! written by `statusbot config export-team`) and sites in `<dir>/sites/*.yaml`.  On startup,
+//! and whenever the webhook at `/desired-state` is called, the database is reconciled to
+//! match: declared teams and sites are created or updated, and members, guests, fields,
+//! shifts, and assignments of declared teams that aren't declared are removed.  Every change
+//! is drift from the declared state, and is logged as such.
+//!
+//! Teams and sites that aren't declared are left alone, and reported as unmanaged.
+//!
+//! A dry run reconciles inside a transaction that is rolled back, reporting the drift that
+//! would be corrected without correcting it.
+
+use crate::{
+    audit,
+    models::{normalize_name, AuditEntry, Site, Team},
+    team_config::{self, TeamConfig},
+    SqlConn, SqlPool,
+};
+use anyhow::{Context, Result};
+use chrono_tz::Tz;
+use futures::lock::Mutex;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Held while reconciling, so a webhook can't reconcile while startup (or another webhook) is
+static RECONCILING: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// A site, as declared in `<dir>/sites/*.yaml`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SiteConfig {
+    /// Name of the site
+    pub name: String,
+
+    /// The site's timezone (e.g., `America/New_York`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tz: Option<String>,
+
+    /// Where the site is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+
+    /// Number of desks that can be booked each day (unlimited if not set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<i64>,
+}
+
+/// Everything declared in a desired state directory
+#[derive(Debug, Default)]
+pub struct DesiredState {
+    /// Declared teams
+    pub teams: Vec<TeamConfig>,
+
+    /// Declared sites
+    pub sites: Vec<SiteConfig>,
+}
+
+/// What reconciling changed, and what isn't managed by the desired state
+#[derive(Debug, Default, Serialize)]
+pub struct Drift {
+    /// Changes made to match the desired state
+    pub changes: Vec<String>,
+
+    /// Teams and sites in the database that aren't declared
+    pub unmanaged: Vec<String>,
+}
+
+/// Reads every YAML file in a directory, in order of file name, returning nothing if the
+/// directory doesn't exist
+///
+/// # Arguments
+/// * `dir` - The directory
+fn read_dir<T: serde::de::DeserializeOwned>(dir: &Path) -> Result<Vec<T>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("yaml") | Some("yml")
+            )
+        })
+        .collect();
+    files.sort();
+
+    files
+        .iter()
+        .map(|file| {
+            let yaml = std::fs::read_to_string(file)
+                .with_context(|| format!("failed to read {}", file.display()))?;
+            serde_yaml::from_str(&yaml)
+                .with_context(|| format!("failed to parse {}", file.display()))
+        })
+        .collect()
+}
+
+/// Loads the desired state declared in a directory
+///
+/// Fails if any file can't be parsed, or a team or site is declared twice, so a broken
+/// commit never half-applies
+///
+/// # Arguments
+/// * `dir` - The desired state directory
+pub fn load(dir: &Path) -> Result<DesiredState> {
+    let state = DesiredState {
+        teams: read_dir(&dir.join("teams"))?,
+        sites: read_dir(&dir.join("sites"))?,
+    };
+
+    let mut names: Vec<String> = state
+        .teams
+        .iter()
+        .map(|team| format!("team {}", normalize_name(&team.name)))
+        .chain(
+            state
+                .sites
+                .iter()
+                .map(|site| format!("site {}", normalize_name(&site.name))),
+        )
+        .collect();
+    names.sort();
+    if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+        anyhow::bail!("{} is declared more than once", pair[0]);
+    }
+
+    for site in &state.sites {
+        if let Some(tz) = &site.tz {
+            tz.parse::<Tz>().map_err(|_| {
+                anyhow::anyhow!("site {} has an invalid timezone {}", site.name, tz)
+            })?;
+        }
+    }
+
+    Ok(state)
+}
+
+/// Reconciles the database to match a desired state
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `desired` - The desired state
+pub async fn reconcile(db: &mut SqlConn, desired: &DesiredState) -> Result<Drift> {
+    let mut drift = Drift::default();
+
+    for config in &desired.sites {
+        let mut site = match Site::fetch(&mut *db, &config.name).await {
+            Some(site) => site,
+            None => {
+                drift.changes.push(format!("created site {}", config.name));
+                Site::new(&mut *db, &config.name).await?
+            }
+        };
+
+        if site.tz != config.tz
+            || site.address != config.address
+            || site.capacity != config.capacity
+        {
+            site.tz = config.tz.clone();
+            site.address = config.address.clone();
+            site.capacity = config.capacity;
+            site.save(&mut *db).await?;
+            drift
+                .changes
+                .push(format!("updated settings of site {}", site.name));
+        }
+    }
+
+    for config in &desired.teams {
+        let changes = team_config::import(&mut *db, config, true)
+            .await
+            .with_context(|| format!("failed to reconcile team {}", config.name))?;
+        drift.changes.extend(changes);
+    }
+
+    for team in Team::fetch_all(&mut *db).await? {
+        if !desired
+            .teams
+            .iter()
+            .any(|config| normalize_name(&config.name) == team.name)
+        {
+            drift.unmanaged.push(format!("team {}", team.name));
+        }
+    }
+
+    for site in Site::fetch_all(&mut *db).await? {
+        if !desired
+            .sites
+            .iter()
+            .any(|config| normalize_name(&config.name) == site.name)
+        {
+            drift.unmanaged.push(format!("site {}", site.name));
+        }
+    }
+
+    Ok(drift)
+}
+
+/// Loads the desired state declared in a directory and reconciles the database to match,
+/// logging any drift
+///
+/// # Arguments
+/// * `pool` - Pool of connections to the SQL database
+/// * `dir` - The desired state directory
+/// * `dry_run` - Only report the drift, without correcting it
+pub async fn run(pool: &SqlPool, dir: &Path, dry_run: bool) -> Result<Drift> {
+    let _reconciling = RECONCILING.lock().await;
+
+    let desired = load(dir)?;
+    let mut db = pool.acquire().await?;
+    let drift = simulated!(db, dry_run, reconcile(&mut db, &desired))?;
+    if dry_run {
+        audit::record_simulated(
+            &mut db,
+            AuditEntry::CONFIG,
+            AuditEntry::ALL_TEAMS,
+            &drift.changes,
+        )
+        .await;
+    }
+
+    let prefix = if dry_run { "dry run: " } else { "" };
+    for change in &drift.changes {
+        tracing::warn!("{}desired state drift: {}", prefix, change);
+    }
+    for unmanaged in &drift.unmanaged {
+        tracing::info!("not in desired state: {}", unmanaged);
+    }
+    tracing::info!(
+        "{}reconciled {} teams and {} sites with {} changes",
+        prefix,
+        desired.teams.len(),
+        desired.sites.len(),
+        drift.changes.len()
+    );
+
+    Ok(drift)
+}
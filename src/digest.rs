@@ -0,0 +1,405 @@
+//! Daily and weekly team digests
+//!
+//! With `DIGEST=daily` (or `weekly`, sent on Mondays), the morning run sends every team a
+//! digest of where each member is working today, and (weekly) who is on leave each weekday
+//! of the week.  The digest is posted to the team's bound channel and, when email is
+//! configured (`SMTP_HOST`), emailed to the team's leads as HTML and plain text, so leads who
+//! live in email get the same report.  Teams set up with `/location setup` can choose their
+//! own frequency, and are sent digests even when `DIGEST` isn't set.
+
+use crate::{
+    models::{Leave, Profile, Team},
+    notify,
+    render::{self, Markup, ResponseDoc, Text},
+    slack, SqlConn,
+};
+use anyhow::Result;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::{collections::HashMap, str::FromStr};
+
+/// How often digests are sent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Frequency {
+    /// Every morning
+    Daily,
+
+    /// Monday mornings, with the week's leave
+    Weekly,
+}
+
+impl Frequency {
+    /// Returns true if a digest is sent on a day
+    ///
+    /// # Arguments
+    /// * `day` - The day
+    pub fn is_due(&self, day: NaiveDate) -> bool {
+        match self {
+            Frequency::Daily => true,
+            Frequency::Weekly => day.weekday() == Weekday::Mon,
+        }
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "daily" => Ok(Frequency::Daily),
+            "weekly" => Ok(Frequency::Weekly),
+            _ => Err(format!(
+                "unknown digest frequency `{}`, expected daily or weekly",
+                s
+            )),
+        }
+    }
+}
+
+/// A member, as listed in a digest
+struct Entry {
+    /// Slack ID of the member
+    user_id: String,
+
+    /// Name shown outside Slack (the member's display name, or a guest's name)
+    name: String,
+
+    /// If the member is a guest who isn't on Slack
+    external: bool,
+
+    /// The member's status, with emoji, as shown on Slack
+    compact: Option<String>,
+
+    /// The member's status, in words
+    plain: Option<String>,
+}
+
+impl Entry {
+    /// Returns how the member is named: mentioned on Slack, unless they're a guest
+    fn name(&self) -> Text {
+        if self.external {
+            Text::new().strong(&self.name).slack(" _guest_", "")
+        } else {
+            Text::new().mention(&self.user_id, &self.name)
+        }
+    }
+}
+
+/// The report sent to a team
+pub struct Digest {
+    /// Name of the team
+    team: String,
+
+    /// Day the digest is for
+    day: NaiveDate,
+
+    /// How often digests are sent
+    frequency: Frequency,
+
+    /// Every member of the team
+    entries: Vec<Entry>,
+
+    /// Names of members on leave each weekday of the week, for weekly digests
+    leave: Vec<(NaiveDate, Vec<String>)>,
+}
+
+impl Digest {
+    /// Gathers the digest of a team
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - The team
+    /// * `day` - Day the digest is for
+    /// * `frequency` - How often digests are sent
+    pub async fn build(
+        db: &mut SqlConn,
+        team: &Team,
+        day: NaiveDate,
+        frequency: Frequency,
+    ) -> Result<Self> {
+        let names: HashMap<String, String> = Profile::fetch_all(&mut *db)
+            .await?
+            .into_iter()
+            .filter_map(|profile| Some((profile.user_id, profile.display_name?)))
+            .collect();
+
+        let entries: Vec<Entry> = Team::members_page(&mut *db, &team.name, i64::MAX, 0)
+            .await?
+            .into_iter()
+            .map(|member| Entry {
+                name: match &member.name {
+                    Some(name) if member.external => name.clone(),
+                    _ => names
+                        .get(&member.id)
+                        .cloned()
+                        .unwrap_or_else(|| member.id.clone()),
+                },
+                external: member.external,
+                compact: member.compact_status(),
+                plain: member.plain_status(),
+                user_id: member.id,
+            })
+            .collect();
+
+        let mut leave = vec![];
+        if frequency == Frequency::Weekly {
+            let monday = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+            for offset in 0..5 {
+                let weekday = monday + Duration::days(offset);
+                let away: Vec<String> = Leave::fetch_on(&mut *db, weekday)
+                    .await?
+                    .into_iter()
+                    .filter_map(|leave| entries.iter().find(|e| e.user_id == leave.user_id))
+                    .map(|entry| entry.name.clone())
+                    .collect();
+                leave.push((weekday, away));
+            }
+        }
+
+        Ok(Digest {
+            team: team.name.clone(),
+            day,
+            frequency,
+            entries,
+            leave,
+        })
+    }
+
+    /// Returns the title of the digest, used as the subject of emails
+    pub fn title(&self) -> String {
+        match self.frequency {
+            Frequency::Daily => {
+                format!("{} digest for {}", self.team, self.day.format("%A, %B %-d"))
+            }
+            Frequency::Weekly => format!(
+                "{} weekly digest, week of {}",
+                self.team,
+                self.day.format("%B %-d")
+            ),
+        }
+    }
+
+    /// Lays out the digest, for any surface
+    pub fn document(&self) -> ResponseDoc {
+        let mut doc = ResponseDoc::new();
+        doc.header(self.title());
+
+        let members = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let name = entry.name();
+                match (&entry.compact, &entry.plain) {
+                    (Some(compact), Some(plain)) => {
+                        name.text(": ").slack(compact.as_str(), plain.as_str())
+                    }
+                    _ => name.text(" has not set a status"),
+                }
+            })
+            .collect();
+        doc.list(members);
+
+        if !self.leave.is_empty() {
+            doc.header("On leave this week");
+            let days = self
+                .leave
+                .iter()
+                .map(|(day, names)| {
+                    let text = Text::new().strong(day.format("%A").to_string()).text(": ");
+                    if names.is_empty() {
+                        text.emphasis("nobody")
+                    } else {
+                        text.text(names.join(", "))
+                    }
+                })
+                .collect();
+            doc.list(days);
+        }
+
+        doc
+    }
+
+    /// Renders the digest as an HTML document, for emails
+    pub fn render_html(&self) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html><body>\n{}\n</body></html>\n",
+            self.document().render(Markup::Html)
+        )
+    }
+}
+
+/// Sends the digest of a team to its bound channel, and emails it to its leads
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `team` - The team
+/// * `day` - Day the digest is for
+/// * `frequency` - How often digests are sent
+async fn send(db: &mut SqlConn, team: &Team, day: NaiveDate, frequency: Frequency) -> Result<()> {
+    let email = notify::email();
+    if team.channel.is_none() && email.is_none() {
+        return Ok(());
+    }
+
+    let digest = Digest::build(&mut *db, team, day, frequency).await?;
+    if digest.entries.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(channel) = &team.channel {
+        slack::chat_post_message(channel, &digest.document().render(render::slack())).await?;
+    }
+
+    if let Some(email) = email {
+        let text = format!("{}\n", digest.document().render(Markup::Plain));
+        let html = digest.render_html();
+        for lead in team.leads(&mut *db).await? {
+            let address = match Profile::fetch(&mut *db, &lead.id).await {
+                Some(Profile {
+                    email: Some(address),
+                    ..
+                }) => address,
+                _ => continue,
+            };
+
+            if let Err(e) = email
+                .send_email(&address, &digest.title(), &text, Some(&html))
+                .await
+            {
+                tracing::error!(
+                    "failed to email the digest of {} to {}: {:?}",
+                    team.name,
+                    lead.id,
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends the digest of every team that has one due today
+///
+/// Teams are sent digests as often as they've chosen (with `/location setup`), or `default`
+/// if they haven't
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `day` - Today
+/// * `default` - How often digests are sent to teams that haven't chosen, if at all
+pub async fn send_all(db: &mut SqlConn, day: NaiveDate, default: Option<Frequency>) -> Result<()> {
+    for team in Team::fetch_all(&mut *db).await? {
+        let chosen = team.digest.as_deref().and_then(|f| f.parse().ok());
+        let frequency = match chosen.or(default) {
+            Some(frequency) => frequency,
+            None => continue,
+        };
+
+        if !frequency.is_due(day) {
+            continue;
+        }
+
+        if let Err(e) = send(&mut *db, &team, day, frequency).await {
+            tracing::error!("failed to send the digest of team {}: {:?}", team.name, e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A digest of a team with a member and a guest, sent on a Monday
+    fn digest(frequency: Frequency) -> Digest {
+        let monday = NaiveDate::from_ymd(2020, 10, 19);
+        let leave = match frequency {
+            Frequency::Daily => vec![],
+            Frequency::Weekly => vec![
+                (monday, vec![]),
+                (monday + Duration::days(1), vec!["Alice".to_owned()]),
+            ],
+        };
+
+        Digest {
+            team: "ops".to_owned(),
+            day: monday,
+            frequency,
+            entries: vec![
+                Entry {
+                    user_id: "U1".to_owned(),
+                    name: "Alice".to_owned(),
+                    external: false,
+                    compact: Some(":house: In meetings".to_owned()),
+                    plain: Some("home: In meetings".to_owned()),
+                },
+                Entry {
+                    user_id: "G1".to_owned(),
+                    name: "Bob".to_owned(),
+                    external: true,
+                    compact: None,
+                    plain: None,
+                },
+            ],
+            leave,
+        }
+    }
+
+    #[test]
+    fn daily_mrkdwn() {
+        let digest = digest(Frequency::Daily);
+        insta::assert_snapshot!(digest.document().render(Markup::Mrkdwn), @r###"
+        *ops digest for Monday, October 19*
+        • <@U1>: :house: In meetings
+        • *Bob* _guest_ has not set a status
+        "###);
+    }
+
+    #[test]
+    fn weekly_mrkdwn() {
+        let digest = digest(Frequency::Weekly);
+        insta::assert_snapshot!(digest.document().render(Markup::Mrkdwn), @r###"
+        *ops weekly digest, week of October 19*
+        • <@U1>: :house: In meetings
+        • *Bob* _guest_ has not set a status
+
+        *On leave this week*
+        • *Monday*: _nobody_
+        • *Tuesday*: Alice
+        "###);
+    }
+
+    #[test]
+    fn weekly_plain_text() {
+        let digest = digest(Frequency::Weekly);
+        insta::assert_snapshot!(digest.document().render(Markup::Plain), @r###"
+        ops weekly digest, week of October 19
+
+        - Alice: home: In meetings
+        - Bob has not set a status
+
+        On leave this week
+
+        - Monday: nobody
+        - Tuesday: Alice
+        "###);
+    }
+
+    #[test]
+    fn weekly_html() {
+        let digest = digest(Frequency::Weekly);
+        insta::assert_snapshot!(digest.document().render(Markup::Html), @r###"
+        <h2>ops weekly digest, week of October 19</h2>
+        <ul>
+        <li>Alice: home: In meetings</li>
+        <li><strong>Bob</strong> has not set a status</li>
+        </ul>
+        <h3>On leave this week</h3>
+        <ul>
+        <li><strong>Monday</strong>: <em>nobody</em></li>
+        <li><strong>Tuesday</strong>: Alice</li>
+        </ul>
+        "###);
+    }
+}
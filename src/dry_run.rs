@@ -0,0 +1,44 @@
+//! Dry runs of operations that change or remove data
+//!
+//! Imports, reconciliation, and the admin API can be asked to only simulate their changes.
+//! The operation runs as usual inside a transaction that is then rolled back, so what it
+//! reports having changed is exactly what a real run would change, but nothing is kept.
+//! Simulated changes are logged with a `dry run` prefix, so they can be told apart from
+//! real ones.  Anything recorded in the audit log inside the transaction is rolled back too,
+//! so callers record the dry run itself afterwards (see `audit::record_simulated`).
+//!
+//! Transactions that are never simulated use `transaction!` instead.
+
+/// Runs queries inside a transaction, committing if they succeed and rolling back if not
+///
+/// Within `body`, `db` refers to the transaction's connection, so models (which take
+/// connections) run inside the transaction.  The transaction is rolled back if the future is
+/// dropped before it finishes.
+macro_rules! transaction {
+    ($db:ident, $body:expr) => {
+        simulated!($db, false, $body)
+    };
+}
+
+/// Runs queries inside a transaction, committing if they succeed unless `dry_run` is set,
+/// and rolling back otherwise
+///
+/// Within `body`, `db` refers to the transaction's connection, so models (which take
+/// connections) run inside the transaction.  The transaction is rolled back if the future is
+/// dropped before it finishes.
+macro_rules! simulated {
+    ($db:ident, $dry_run:expr, $body:expr) => {{
+        let mut tx = sqlx::Connection::begin(&mut *$db).await?;
+        let result = {
+            #[allow(unused_mut)]
+            let mut $db = &mut *tx;
+            $body.await
+        };
+        if result.is_ok() && !$dry_run {
+            tx.commit().await?;
+        } else {
+            tx.rollback().await?;
+        }
+        result
+    }};
+}
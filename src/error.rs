@@ -0,0 +1,285 @@
+//! Crate-wide error type and its conversion into HTTP responses
+
+use crate::{
+    models::InvalidUserId,
+    render::{ResponseDoc, Text},
+    response::SlashResponse,
+};
+use std::fmt;
+use tide::StatusCode;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong while handling a request
+#[derive(Debug)]
+pub enum Error {
+    /// The database returned an error
+    Db(sqlx::Error),
+
+    /// A Slack Web API call failed
+    SlackApi(String),
+
+    /// A request or command could not be understood
+    Parse(String),
+
+    /// The caller is not allowed to perform the request
+    Auth(String),
+
+    /// The requested item does not exist
+    NotFound(String),
+
+    /// A configured limit would be exceeded
+    Limit(String),
+}
+
+impl Error {
+    /// Returns the HTTP status code that best describes this error
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::Db(_) => StatusCode::InternalServerError,
+            Error::SlackApi(_) => StatusCode::BadGateway,
+            Error::Parse(_) => StatusCode::BadRequest,
+            Error::Auth(_) => StatusCode::Forbidden,
+            Error::NotFound(_) => StatusCode::NotFound,
+            Error::Limit(_) => StatusCode::UnprocessableEntity,
+        }
+    }
+
+    /// Returns a message that is safe to show to the user
+    pub fn user_message(&self) -> String {
+        match self {
+            Error::Db(_) => "Something went wrong, please try again later".to_owned(),
+            Error::SlackApi(_) => "Slack is having trouble, please try again later".to_owned(),
+            Error::Parse(reason) => reason.clone(),
+            Error::Auth(_) => "You are not allowed to do that".to_owned(),
+            Error::NotFound(what) => format!("{} not found", what),
+            Error::Limit(reason) => reason.clone(),
+        }
+    }
+
+    /// Logs this error, at a level matching whose fault it is
+    pub fn log(&self) {
+        match self {
+            Error::Db(_) | Error::SlackApi(_) => tracing::error!("{}", self),
+            Error::Parse(_) | Error::Auth(_) | Error::Limit(_) => tracing::warn!("{}", self),
+            Error::NotFound(_) => tracing::debug!("{}", self),
+        }
+    }
+
+    /// Logs this error and converts it into a plain text response
+    pub fn into_response(self) -> tide::Response {
+        self.log();
+        tide::Response::builder(self.status())
+            .body(self.user_message())
+            .build()
+    }
+
+    /// Logs this error and converts it into a slash command response
+    ///
+    /// Slack only shows responses to slash commands that return `200 OK`, so the error
+    /// is described in the message instead of the status code.
+    pub fn into_slash_response(self) -> tide::Response {
+        self.into_slash_message().into()
+    }
+
+    /// Logs this error and describes it in a slash command response, for responses posted
+    /// to a `response_url` instead of returned
+    pub fn into_slash_message(self) -> SlashResponse {
+        self.log();
+
+        let summary = match self {
+            Error::Parse(_) => "Invalid command or arguments",
+            Error::Limit(_) => "Limit reached",
+            _ => "Something went wrong",
+        };
+
+        // user messages are written in mrkdwn (e.g., "Team *ops* not found")
+        let message = self.user_message();
+        let mut doc = ResponseDoc::new();
+        doc.section(Text::new().strong("Oh-no!").text(" ").text(summary))
+            .divider()
+            .section(Text::new().slack(message.as_str(), message.as_str()));
+
+        SlashResponse::from(doc).text(message)
+    }
+
+    /// Logs this error and acknowledges the Slack event that caused it
+    ///
+    /// Events must always be acknowledged with `200 OK`, else Slack retries them and
+    /// eventually disables the app's event subscriptions.
+    pub fn into_event_ack(self) -> tide::Response {
+        self.log();
+        tide::Response::builder(StatusCode::Ok).build()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Db(e) => write!(f, "database error: {}", e),
+            Error::SlackApi(e) => write!(f, "slack api error: {}", e),
+            Error::Parse(e) => write!(f, "parse error: {}", e),
+            Error::Auth(e) => write!(f, "unauthorized: {}", e),
+            Error::NotFound(e) => write!(f, "{} not found", e),
+            Error::Limit(e) => write!(f, "limit exceeded: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Db(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(e: sqlx::Error) -> Self {
+        Error::Db(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<InvalidUserId> for Error {
+    fn from(e: InvalidUserId) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<Error> for tide::Response {
+    fn from(e: Error) -> Self {
+        e.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error_response() {
+        let resp = Error::Parse("Unknown command `foo`".to_owned()).into_slash_message();
+        insta::assert_json_snapshot!(resp.to_json(), @r###"
+        {
+          "blocks": [
+            {
+              "text": {
+                "text": "*Oh-no!* Invalid command or arguments",
+                "type": "mrkdwn"
+              },
+              "type": "section"
+            },
+            {
+              "type": "divider"
+            },
+            {
+              "text": {
+                "text": "Unknown command `foo`",
+                "type": "mrkdwn"
+              },
+              "type": "section"
+            }
+          ],
+          "response_type": "ephemeral",
+          "text": "Unknown command `foo`"
+        }
+        "###);
+    }
+
+    #[test]
+    fn limit_error_response() {
+        let resp = Error::Limit("Teams can have at most 10 fields".to_owned()).into_slash_message();
+        insta::assert_json_snapshot!(resp.to_json(), @r###"
+        {
+          "blocks": [
+            {
+              "text": {
+                "text": "*Oh-no!* Limit reached",
+                "type": "mrkdwn"
+              },
+              "type": "section"
+            },
+            {
+              "type": "divider"
+            },
+            {
+              "text": {
+                "text": "Teams can have at most 10 fields",
+                "type": "mrkdwn"
+              },
+              "type": "section"
+            }
+          ],
+          "response_type": "ephemeral",
+          "text": "Teams can have at most 10 fields"
+        }
+        "###);
+    }
+
+    #[test]
+    fn not_found_error_response() {
+        let resp = Error::NotFound("Team *ops*".to_owned()).into_slash_message();
+        insta::assert_json_snapshot!(resp.to_json(), @r###"
+        {
+          "blocks": [
+            {
+              "text": {
+                "text": "*Oh-no!* Something went wrong",
+                "type": "mrkdwn"
+              },
+              "type": "section"
+            },
+            {
+              "type": "divider"
+            },
+            {
+              "text": {
+                "text": "Team *ops* not found",
+                "type": "mrkdwn"
+              },
+              "type": "section"
+            }
+          ],
+          "response_type": "ephemeral",
+          "text": "Team *ops* not found"
+        }
+        "###);
+    }
+
+    #[test]
+    fn db_error_response_hides_details() {
+        let resp = Error::Db(sqlx::Error::RowNotFound).into_slash_message();
+        insta::assert_json_snapshot!(resp.to_json(), @r###"
+        {
+          "blocks": [
+            {
+              "text": {
+                "text": "*Oh-no!* Something went wrong",
+                "type": "mrkdwn"
+              },
+              "type": "section"
+            },
+            {
+              "type": "divider"
+            },
+            {
+              "text": {
+                "text": "Something went wrong, please try again later",
+                "type": "mrkdwn"
+              },
+              "type": "section"
+            }
+          ],
+          "response_type": "ephemeral",
+          "text": "Something went wrong, please try again later"
+        }
+        "###);
+    }
+}
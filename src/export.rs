@@ -0,0 +1,342 @@
+//! Export of status history and usage statistics for analysis
+//!
+//! `statusbot export history` writes every status set over a period, and `statusbot export
+//! stats` the usage of each slash command, as CSV or (with the `parquet` feature) as a
+//! Parquet file that can be loaded straight into a data lake.  Timestamps are UTC; in
+//! Parquet they're microsecond timestamps, and in CSV RFC 3339 strings.
+
+use crate::{
+    connect,
+    models::{CommandStat, CommandUsage, HistoryEntry},
+    Opt,
+};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Formats data can be exported as
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// Comma-separated values, with a header row
+    Csv,
+
+    /// Apache Parquet (feature `parquet`)
+    Parquet,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "parquet" => Ok(Format::Parquet),
+            _ => Err(format!("unknown export format `{}`, expected csv or parquet", s)),
+        }
+    }
+}
+
+/// Exports of bot data
+#[derive(structopt::StructOpt, Debug)]
+pub enum ExportCommand {
+    /// Exports every status set over a period
+    History {
+        /// First day to export (YYYY-MM-DD, UTC)
+        #[structopt(long)]
+        since: NaiveDate,
+
+        /// Last day to export (YYYY-MM-DD, UTC), today if not set
+        #[structopt(long)]
+        until: Option<NaiveDate>,
+
+        /// Format to write (csv or parquet)
+        #[structopt(long, default_value = "csv")]
+        format: Format,
+
+        /// File to write to, stdout if not set (only for csv)
+        #[structopt(long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Exports the usage of each slash command since a day
+    Stats {
+        /// First day to summarize (YYYY-MM-DD, UTC)
+        #[structopt(long)]
+        since: NaiveDate,
+
+        /// Format to write (csv or parquet)
+        #[structopt(long, default_value = "csv")]
+        format: Format,
+
+        /// File to write to, stdout if not set (only for csv)
+        #[structopt(long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+}
+
+/// A column of exported data
+enum Column {
+    /// Text, which may be missing
+    Text(&'static str, Vec<Option<String>>),
+
+    /// Integers
+    Int(&'static str, Vec<i64>),
+
+    /// Points in time
+    Timestamp(&'static str, Vec<DateTime<Utc>>),
+}
+
+impl Column {
+    /// Returns the name of this column
+    fn name(&self) -> &'static str {
+        match self {
+            Column::Text(name, _) | Column::Int(name, _) | Column::Timestamp(name, _) => name,
+        }
+    }
+
+    /// Returns the value of a row in this column, rendered for CSV
+    ///
+    /// # Arguments
+    /// * `row` - Index of the row
+    fn render(&self, row: usize) -> String {
+        match self {
+            Column::Text(_, values) => values[row].as_deref().map(quote).unwrap_or_default(),
+            Column::Int(_, values) => values[row].to_string(),
+            Column::Timestamp(_, values) => values[row].to_rfc3339(),
+        }
+    }
+}
+
+/// Data to export, column by column
+struct Table {
+    /// Number of rows
+    rows: usize,
+
+    /// The columns, all `rows` long
+    columns: Vec<Column>,
+}
+
+impl Table {
+    /// Builds a table of status history
+    ///
+    /// # Arguments
+    /// * `entries` - Statuses to export
+    fn history(entries: Vec<HistoryEntry>) -> Self {
+        Table {
+            rows: entries.len(),
+            columns: vec![
+                Column::Int("id", entries.iter().map(|e| e.id).collect()),
+                Column::Text(
+                    "user_id",
+                    entries.iter().map(|e| Some(e.user_id.clone())).collect(),
+                ),
+                Column::Text("status", entries.iter().map(|e| e.status.clone()).collect()),
+                Column::Text(
+                    "location",
+                    entries
+                        .iter()
+                        .map(|e| e.location().map(|l| l.as_str().to_owned()))
+                        .collect(),
+                ),
+                Column::Text(
+                    "availability",
+                    entries
+                        .iter()
+                        .map(|e| e.availability().map(|a| a.as_str().to_owned()))
+                        .collect(),
+                ),
+                Column::Timestamp(
+                    "created_at",
+                    entries.iter().map(|e| e.created_at).collect(),
+                ),
+            ],
+        }
+    }
+
+    /// Builds a table of slash command usage
+    ///
+    /// # Arguments
+    /// * `usage` - Usage of each action
+    fn stats(usage: Vec<CommandUsage>) -> Self {
+        Table {
+            rows: usage.len(),
+            columns: vec![
+                Column::Text(
+                    "action",
+                    usage.iter().map(|u| Some(u.action.clone())).collect(),
+                ),
+                Column::Int("uses", usage.iter().map(|u| u.uses).collect()),
+                Column::Int("failures", usage.iter().map(|u| u.failures).collect()),
+                Column::Int(
+                    "avg_latency_ms",
+                    usage.iter().map(|u| u.avg_latency_ms).collect(),
+                ),
+                Column::Int("workspaces", usage.iter().map(|u| u.workspaces).collect()),
+            ],
+        }
+    }
+
+    /// Writes this table as CSV
+    ///
+    /// # Arguments
+    /// * `out` - Where to write it
+    fn write_csv(&self, out: &mut dyn Write) -> Result<()> {
+        let header: Vec<_> = self.columns.iter().map(Column::name).collect();
+        writeln!(out, "{}", header.join(","))?;
+
+        for row in 0..self.rows {
+            let values: Vec<_> = self.columns.iter().map(|c| c.render(row)).collect();
+            writeln!(out, "{}", values.join(","))?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes this table as a Parquet file
+    ///
+    /// # Arguments
+    /// * `out` - File to write it to
+    #[cfg(feature = "parquet")]
+    fn write_parquet(&self, out: File) -> Result<()> {
+        use arrow2::{
+            array::{Array, Int64Array, Utf8Array},
+            chunk::Chunk,
+            datatypes::{DataType, Field, Schema, TimeUnit},
+            io::parquet::write::{
+                CompressionOptions, Encoding, FileWriter, RowGroupIterator, Version, WriteOptions,
+            },
+        };
+        use std::sync::Arc;
+
+        let timestamp = DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".to_owned()));
+
+        let mut fields = vec![];
+        let mut arrays: Vec<Arc<dyn Array>> = vec![];
+        for column in &self.columns {
+            match column {
+                Column::Text(name, values) => {
+                    fields.push(Field::new(*name, DataType::Utf8, true));
+                    let values: Utf8Array<i32> = values.iter().map(|v| v.as_deref()).collect();
+                    arrays.push(Arc::new(values));
+                }
+                Column::Int(name, values) => {
+                    fields.push(Field::new(*name, DataType::Int64, false));
+                    arrays.push(Arc::new(Int64Array::from_slice(values)));
+                }
+                Column::Timestamp(name, values) => {
+                    fields.push(Field::new(*name, timestamp.clone(), false));
+                    let micros: Vec<_> = values.iter().map(|t| t.timestamp_micros()).collect();
+                    arrays.push(Arc::new(Int64Array::from_vec(micros).to(timestamp.clone())));
+                }
+            }
+        }
+
+        let schema = Schema::from(fields);
+        let options = WriteOptions {
+            write_statistics: true,
+            compression: CompressionOptions::Snappy,
+            version: Version::V2,
+        };
+        let encodings = schema.fields.iter().map(|_| Encoding::Plain).collect();
+        let chunk = Chunk::try_new(arrays)?;
+        let row_groups =
+            RowGroupIterator::try_new(vec![Ok(chunk)].into_iter(), &schema, options, encodings)?;
+
+        let mut writer = FileWriter::try_new(out, schema, options)?;
+        writer.start()?;
+        for group in row_groups {
+            writer.write(group?)?;
+        }
+        writer.end(None)?;
+
+        Ok(())
+    }
+
+    /// Writes this table in a format, to a file or stdout
+    ///
+    /// # Arguments
+    /// * `format` - Format to write
+    /// * `output` - File to write to, or `None` for stdout
+    fn write(&self, format: Format, output: Option<&Path>) -> Result<()> {
+        match (format, output) {
+            (Format::Csv, Some(path)) => {
+                let mut out = io::BufWriter::new(File::create(path)?);
+                self.write_csv(&mut out)
+            }
+            (Format::Csv, None) => self.write_csv(&mut io::stdout().lock()),
+            #[cfg(feature = "parquet")]
+            (Format::Parquet, Some(path)) => self.write_parquet(File::create(path)?),
+            #[cfg(feature = "parquet")]
+            (Format::Parquet, None) => Err(anyhow!("parquet exports need --output")),
+            #[cfg(not(feature = "parquet"))]
+            (Format::Parquet, _) => Err(anyhow!(
+                "statusbot was built without the `parquet` feature"
+            )),
+        }
+    }
+}
+
+/// Quotes a CSV value if it holds a separator, quote, or line break
+///
+/// # Arguments
+/// * `value` - The value
+fn quote(value: &str) -> String {
+    if value.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Returns the start of a day (UTC)
+///
+/// # Arguments
+/// * `day` - The day
+fn start_of(day: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_date(&day).and_hms(0, 0, 0)
+}
+
+/// Runs an export against `--database`
+///
+/// # Arguments
+/// * `opt` - Command line options and arguments
+/// * `command` - The export to run
+pub async fn run(opt: &Opt, command: &ExportCommand) -> Result<()> {
+    let pool = connect(opt).await?;
+    let mut db = pool.acquire().await?;
+
+    match command {
+        ExportCommand::History {
+            since,
+            until,
+            format,
+            output,
+        } => {
+            let until = until.unwrap_or_else(|| Utc::today().naive_utc());
+            let entries = HistoryEntry::fetch_between(
+                &mut db,
+                start_of(*since),
+                start_of(until + Duration::days(1)),
+            )
+            .await?;
+
+            tracing::info!("exporting {} statuses", entries.len());
+            Table::history(entries).write(*format, output.as_deref())?;
+        }
+        ExportCommand::Stats {
+            since,
+            format,
+            output,
+        } => {
+            let usage = CommandStat::summarize(&mut db, start_of(*since)).await?;
+            Table::stats(usage).write(*format, output.as_deref())?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,202 @@
+//! Typed extractors for request handlers
+//!
+//! Handlers declare their inputs as types implementing `FromRequest` (or tuples of
+//! them) and are registered with `handler`.  Extractors that read a body from Slack
+//! always verify the request signature, so it can't be forgotten on new routes.
+
+use crate::{limits, signing, PooledConn, State};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use tide::StatusCode;
+
+/// A value that can be extracted from an incoming request
+///
+/// If extraction fails, the returned response is sent to the client instead of calling
+/// the handler
+#[async_trait]
+pub trait FromRequest: Sized {
+    async fn from_request(req: &mut tide::Request<State>) -> Result<Self, tide::Response>;
+}
+
+/// Builds an empty response with the given status, used to reject requests
+fn reject(status: StatusCode) -> tide::Response {
+    tide::Response::builder(status).build()
+}
+
+/// Returns the last value of a header, if present
+fn header<'a>(req: &'a tide::Request<State>, name: &str) -> Option<&'a str> {
+    req.header(name).map(|values| values.last().as_str())
+}
+
+/// Wraps a handler that takes extractors into a tide endpoint
+///
+/// # Arguments
+/// * `f` - The handler
+pub fn handler<T, F, Fut>(f: F) -> impl tide::Endpoint<State>
+where
+    T: FromRequest + Send + 'static,
+    F: Fn(T) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = tide::Result<tide::Response>> + Send + 'static,
+{
+    move |mut req: tide::Request<State>| {
+        let f = f.clone();
+        async move {
+            match T::from_request(&mut req).await {
+                Ok(input) => f(input).await,
+                Err(resp) => Ok(resp),
+            }
+        }
+    }
+}
+
+/// A connection to the SQL database
+pub struct Db(pub PooledConn);
+
+#[async_trait]
+impl FromRequest for Db {
+    async fn from_request(req: &mut tide::Request<State>) -> Result<Self, tide::Response> {
+        match req.state().pool.acquire().await {
+            Ok(conn) => Ok(Db(conn)),
+            Err(e) => {
+                tracing::error!("Failed to acquire database connection: {:?}", e);
+                Err(reject(StatusCode::ServiceUnavailable))
+            }
+        }
+    }
+}
+
+/// Shared application state
+pub struct AppState(pub State);
+
+#[async_trait]
+impl FromRequest for AppState {
+    async fn from_request(req: &mut tide::Request<State>) -> Result<Self, tide::Response> {
+        Ok(AppState(req.state().clone()))
+    }
+}
+
+/// Retry information Slack attaches to events it is delivering again
+pub struct SlackRetry {
+    /// Value of `X-Slack-Retry-Num`, if this is a retry
+    pub num: Option<String>,
+
+    /// Value of `X-Slack-Retry-Reason`, if this is a retry
+    pub reason: Option<String>,
+}
+
+#[async_trait]
+impl FromRequest for SlackRetry {
+    async fn from_request(req: &mut tide::Request<State>) -> Result<Self, tide::Response> {
+        Ok(SlackRetry {
+            num: header(req, "X-Slack-Retry-Num").map(str::to_owned),
+            reason: header(req, "X-Slack-Retry-Reason").map(str::to_owned),
+        })
+    }
+}
+
+/// The raw body of a request whose Slack signature has been verified
+///
+/// Signatures must be no older than the configured tolerance (`SIGNATURE_TOLERANCE`), and
+/// are only skipped if the server was started with `--insecure-skip-signature`
+pub struct SignedBody(pub Vec<u8>);
+
+#[async_trait]
+impl FromRequest for SignedBody {
+    async fn from_request(req: &mut tide::Request<State>) -> Result<Self, tide::Response> {
+        let body = req
+            .body_bytes()
+            .await
+            .map_err(|_| reject(StatusCode::BadRequest))?;
+
+        if let Some(secret) = req.state().signing_secret.as_deref() {
+            let verified = match (
+                header(req, "X-Slack-Request-Timestamp"),
+                header(req, "X-Slack-Signature"),
+            ) {
+                (Some(timestamp), Some(signature)) => signing::verify_slack(
+                    secret,
+                    timestamp,
+                    &body,
+                    signature,
+                    req.state().signature_tolerance,
+                ),
+                _ => false,
+            };
+
+            if !verified {
+                tracing::warn!("Rejected request with invalid slack signature");
+                return Err(reject(StatusCode::Unauthorized));
+            }
+        }
+
+        Ok(SignedBody(body))
+    }
+}
+
+/// A JSON body, sent and signed by Slack
+pub struct SignedJson<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned + Send> FromRequest for SignedJson<T> {
+    async fn from_request(req: &mut tide::Request<State>) -> Result<Self, tide::Response> {
+        let SignedBody(body) = SignedBody::from_request(req).await?;
+
+        if !limits::json_depth_ok(&body, limits::MAX_JSON_DEPTH) {
+            return Err(reject(StatusCode::BadRequest));
+        }
+
+        match serde_json::from_slice(&body) {
+            Ok(value) => Ok(SignedJson(value)),
+            Err(e) => {
+                tracing::error!("Failed to parse JSON body: {:?}", e);
+                Err(reject(StatusCode::BadRequest))
+            }
+        }
+    }
+}
+
+/// A form encoded body, sent and signed by Slack
+pub struct Form<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned + Send> FromRequest for Form<T> {
+    async fn from_request(req: &mut tide::Request<State>) -> Result<Self, tide::Response> {
+        let SignedBody(body) = SignedBody::from_request(req).await?;
+
+        match serde_urlencoded::from_bytes(&body) {
+            Ok(value) => Ok(Form(value)),
+            Err(e) => {
+                tracing::error!("Failed to parse form body: {:?}", e);
+                Err(reject(StatusCode::BadRequest))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<A, B> FromRequest for (A, B)
+where
+    A: FromRequest + Send,
+    B: FromRequest + Send,
+{
+    async fn from_request(req: &mut tide::Request<State>) -> Result<Self, tide::Response> {
+        Ok((A::from_request(req).await?, B::from_request(req).await?))
+    }
+}
+
+#[async_trait]
+impl<A, B, C> FromRequest for (A, B, C)
+where
+    A: FromRequest + Send,
+    B: FromRequest + Send,
+    C: FromRequest + Send,
+{
+    async fn from_request(req: &mut tide::Request<State>) -> Result<Self, tide::Response> {
+        Ok((
+            A::from_request(req).await?,
+            B::from_request(req).await?,
+            C::from_request(req).await?,
+        ))
+    }
+}
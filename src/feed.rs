@@ -0,0 +1,93 @@
+//! Live feed of status changes
+//!
+//! Every time a user's status is saved, a `StatusChange` is published to all
+//! current subscribers (e.g. gRPC streams).  Subscribers that have gone away are
+//! dropped the next time a change is published.
+//!
+//! Saves may only set part of a status (e.g., just the availability), so changes are
+//! published with `publish_saved`, which reads the whole status back.
+
+use crate::{models::User, SqlConn};
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+/// A single status update for a user
+#[derive(Clone, Debug, Serialize)]
+pub struct StatusChange {
+    /// The unique identifier provided by Slack
+    pub user_id: String,
+
+    /// The user's new note
+    pub status: Option<String>,
+
+    /// Where the user is working (e.g., `office`)
+    pub location: Option<String>,
+
+    /// Which site the user is working at, if their location has one
+    pub site: Option<String>,
+
+    /// Whether the user can be reached (e.g., `busy`)
+    pub availability: Option<String>,
+}
+
+impl From<&User> for StatusChange {
+    fn from(user: &User) -> Self {
+        StatusChange {
+            user_id: user.id.clone(),
+            status: user.status.clone(),
+            location: user.location().map(|location| location.as_str().to_owned()),
+            site: user.site().map(str::to_owned),
+            availability: user
+                .availability()
+                .map(|availability| availability.as_str().to_owned()),
+        }
+    }
+}
+
+/// Fan-out broadcaster for status changes
+#[derive(Clone, Debug, Default)]
+pub struct StatusFeed {
+    /// Senders for every active subscriber
+    subscribers: Arc<Mutex<Vec<UnboundedSender<StatusChange>>>>,
+}
+
+impl StatusFeed {
+    /// Creates a new feed with no subscribers
+    pub fn new() -> Self {
+        StatusFeed::default()
+    }
+
+    /// Subscribes to all future status changes
+    pub fn subscribe(&self) -> UnboundedReceiver<StatusChange> {
+        let (tx, rx) = mpsc::unbounded();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(tx);
+        }
+        rx
+    }
+
+    /// Publishes a status change to all subscribers, removing any that have disconnected
+    ///
+    /// # Arguments
+    /// * `change` - The status change to publish
+    pub fn publish(&self, change: StatusChange) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|tx| tx.unbounded_send(change.clone()).is_ok());
+        }
+    }
+
+    /// Publishes the whole status of a user whose status was just saved, logging (rather
+    /// than failing on) errors reading it back
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    pub async fn publish_saved(&self, db: &mut SqlConn, user_id: &str) {
+        match User::fetch(db, user_id).await {
+            Ok(Some(user)) => self.publish(StatusChange::from(&user)),
+            Ok(None) => tracing::warn!("saved status of {} not found to publish", user_id),
+            Err(e) => tracing::error!("failed to publish the status of {}: {:?}", user_id, e),
+        }
+    }
+}
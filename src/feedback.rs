@@ -0,0 +1,78 @@
+//! Problems reported by users
+//!
+//! `/location feedback <text>` records what a user reported in the `feedback` table, along
+//! with the workspace and channel they reported it from, so operators get reports they can
+//! query instead of hearing about problems second hand.  When `FEEDBACK_CHANNEL` is set, each
+//! report is also forwarded to it through the outbox.
+
+use crate::{
+    error::Error,
+    models::Feedback,
+    outbox::{self, Effect},
+    render::{self, Text},
+    SqlConn,
+};
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+
+/// Channel reports are forwarded to
+static CHANNEL: OnceCell<Option<String>> = OnceCell::new();
+
+/// Configures the channel reports are forwarded to, if any
+///
+/// Only the first call has an effect
+///
+/// # Arguments
+/// * `channel` - Slack ID of the channel
+pub fn configure(channel: Option<String>) {
+    CHANNEL.set(channel).ok();
+}
+
+/// Returns the channel reports are forwarded to, if any
+fn channel() -> Option<&'static str> {
+    CHANNEL.get().and_then(|channel| channel.as_deref())
+}
+
+/// Records a report, forwarding it to `FEEDBACK_CHANNEL` if set
+///
+/// Fails with `Error::Parse` if the report is longer than `Feedback::MAX_LENGTH`
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `workspace` - Slack ID of the workspace it was sent from
+/// * `user_id` - Slack ID of the user who sent it
+/// * `channel_id` - Slack ID of the channel it was sent from
+/// * `message` - What the user reported
+pub async fn submit(
+    db: &mut SqlConn,
+    workspace: &str,
+    user_id: &str,
+    channel_id: &str,
+    message: &str,
+) -> Result<()> {
+    if message.chars().count() > Feedback::MAX_LENGTH {
+        return Err(Error::Parse(format!(
+            "Feedback may be up to {} characters",
+            Feedback::MAX_LENGTH
+        ))
+        .into());
+    }
+
+    transaction!(db, async {
+        Feedback::record(&mut *db, workspace, user_id, channel_id, message).await?;
+
+        if let Some(channel) = channel() {
+            let text = Text::new()
+                .text(":speech_balloon: Feedback from ")
+                .mention(user_id, user_id)
+                .text(format!(" (workspace {}): {}", workspace, message));
+            let effect = Effect::PostMessage {
+                channel: channel.to_owned(),
+                text: text.render(render::slack()),
+            };
+            outbox::enqueue(&mut *db, &effect).await?;
+        }
+
+        Ok::<_, anyhow::Error>(())
+    })
+}
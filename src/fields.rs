@@ -0,0 +1,95 @@
+//! The modal users enter custom field values in
+//!
+//! `/location set fields` opens a modal with an input for every field of every team the user
+//! belongs to.  Submitting it replaces the user's values, which are stored as JSON alongside
+//! their status.
+
+use crate::{
+    models::{TeamField, User, MAX_VALUE_LENGTH},
+    slack, SqlConn,
+};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Callback id of the modal
+pub const CALLBACK_ID: &str = "status_fields";
+
+/// Opens the modal for a user, returning `false` if none of their teams have fields
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `user_id` - Slack ID of the user
+/// * `trigger_id` - Trigger received with the command
+pub async fn open(db: &mut SqlConn, user_id: &str, trigger_id: &str) -> Result<bool> {
+    let fields = TeamField::fetch_by_member(&mut *db, user_id).await?;
+    if fields.is_empty() {
+        return Ok(false);
+    }
+
+    let values = User::fetch_fields(&mut *db, user_id).await?;
+    let blocks: Vec<Value> = fields
+        .iter()
+        .map(|field| {
+            let mut element = json!({
+                "type": "plain_text_input",
+                "action_id": "value",
+                "max_length": MAX_VALUE_LENGTH,
+            });
+
+            if let Some(value) = values.get(&field.name) {
+                element["initial_value"] = json!(value);
+            }
+
+            json!({
+                "type": "input",
+                "block_id": field.name,
+                "optional": true,
+                "label": { "type": "plain_text", "text": field.label },
+                "element": element,
+            })
+        })
+        .collect();
+
+    slack::views_open(
+        trigger_id,
+        json!({
+            "type": "modal",
+            "callback_id": CALLBACK_ID,
+            "title": { "type": "plain_text", "text": "Status fields" },
+            "submit": { "type": "plain_text", "text": "Save" },
+            "blocks": blocks,
+        }),
+    )
+    .await?;
+
+    Ok(true)
+}
+
+/// Saves the values submitted in the modal
+///
+/// Only fields of the user's teams are kept, and empty values are removed
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `user_id` - Slack ID of the user who submitted the modal
+/// * `state` - Values of the modal's inputs, keyed by block id
+pub async fn save(db: &mut SqlConn, user_id: &str, state: &Value) -> Result<()> {
+    let mut values = BTreeMap::new();
+    for field in TeamField::fetch_by_member(&mut *db, user_id).await? {
+        let value = state[&field.name]["value"]["value"]
+            .as_str()
+            .unwrap_or_default()
+            .trim();
+
+        if !value.is_empty() {
+            values.insert(
+                field.name,
+                value.chars().take(MAX_VALUE_LENGTH).collect::<String>(),
+            );
+        }
+    }
+
+    User::fetch_or_create(&mut *db, user_id).await?;
+    User::set_fields(&mut *db, user_id, &values).await
+}
@@ -0,0 +1,53 @@
+//! Entry points for fuzzing the parsers of inbound payloads (see `fuzz/`)
+//!
+//! Enabled with the `fuzz` feature.  Each function runs the same parsing steps as the handler
+//! for its endpoint and discards the result, so fuzz targets only find inputs that panic,
+//! overflow the stack, or allocate without bound.
+
+use crate::{
+    handlers::{
+        command::{SlashAction, SlashCommand},
+        event::Event,
+        interactive::{Interaction, InteractiveForm},
+    },
+    limits,
+};
+use serde_json::Value;
+
+/// Parses the body of a `POST /` (event callbacks, URL verification)
+///
+/// # Arguments
+/// * `body` - Raw request body
+pub fn event(body: &[u8]) {
+    if !limits::json_depth_ok(body, limits::MAX_JSON_DEPTH) {
+        return;
+    }
+
+    if let Ok(json) = serde_json::from_slice::<Value>(body) {
+        if json["type"] == "event_callback" {
+            serde_json::from_slice::<Event>(body).ok();
+        }
+    }
+}
+
+/// Parses the body of a `POST /location` (slash commands), including the command's text
+///
+/// # Arguments
+/// * `body` - Raw request body
+pub fn slash_command(body: &[u8]) {
+    if let Ok(form) = serde_urlencoded::from_bytes::<SlashCommand>(body) {
+        SlashAction::parse(&form.text).ok();
+    }
+}
+
+/// Parses the body of a `POST /interactive` (interactivity payloads)
+///
+/// # Arguments
+/// * `body` - Raw request body
+pub fn interaction(body: &[u8]) {
+    if let Ok(form) = serde_urlencoded::from_bytes::<InteractiveForm>(body) {
+        if limits::json_depth_ok(form.payload.as_bytes(), limits::MAX_JSON_DEPTH) {
+            serde_json::from_str::<Interaction>(&form.payload).ok();
+        }
+    }
+}
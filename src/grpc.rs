@@ -0,0 +1,202 @@
+//! gRPC service for status data
+//!
+//! Enabled with the `grpc` feature.  The gRPC server runs on its own port and its own
+//! (tokio) runtime so it doesn't interfere with the Slack-facing web server.
+//!
+//! Clients must send `authorization: Bearer <ADMIN_API_TOKEN>` metadata, the same token the
+//! admin API requires; if `ADMIN_API_TOKEN` is not set, every call is rejected.
+
+use crate::{
+    error::Error,
+    feed::{StatusChange, StatusFeed},
+    handlers::api::{api_token, token_matches},
+    models::{Team, User},
+    SqlPool,
+};
+use anyhow::Context;
+use futures::{future, Stream, StreamExt};
+use std::{net::SocketAddr, pin::Pin, thread};
+use tonic::{
+    transport::{Identity, Server, ServerTlsConfig},
+    Request, Response, Status,
+};
+
+pub mod proto {
+    tonic::include_proto!("statusbot");
+}
+
+use proto::{
+    status_service_server::{StatusService, StatusServiceServer},
+    GetStatusRequest, ListTeamStatusesRequest, StreamStatusChangesRequest, TeamStatuses,
+    UserStatus,
+};
+
+impl From<User> for UserStatus {
+    fn from(user: User) -> Self {
+        StatusChange::from(&user).into()
+    }
+}
+
+impl From<StatusChange> for UserStatus {
+    fn from(change: StatusChange) -> Self {
+        UserStatus {
+            user_id: change.user_id,
+            status: change.status.unwrap_or_default(),
+            location: change.location.unwrap_or_default(),
+            site: change.site.unwrap_or_default(),
+            availability: change.availability.unwrap_or_default(),
+        }
+    }
+}
+
+/// Implementation of the `StatusService` gRPC service
+struct StatusServer {
+    /// A configured sql pool
+    pool: SqlPool,
+
+    /// Feed of status changes, used for streaming
+    feed: StatusFeed,
+}
+
+#[tonic::async_trait]
+impl StatusService for StatusServer {
+    async fn get_status(
+        &self,
+        req: Request<GetStatusRequest>,
+    ) -> Result<Response<UserStatus>, Status> {
+        let mut db = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+
+        match User::fetch(&mut db, &req.get_ref().user_id).await {
+            Ok(Some(user)) => Ok(Response::new(user.into())),
+            Ok(None) => Err(Status::not_found("user not found")),
+            Err(Error::Parse(e)) => Err(Status::invalid_argument(e)),
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+
+    async fn list_team_statuses(
+        &self,
+        req: Request<ListTeamStatusesRequest>,
+    ) -> Result<Response<TeamStatuses>, Status> {
+        let team = req.into_inner().team;
+
+        let mut db = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+
+        if Team::fetch(&mut db, &team).await.is_none() {
+            return Err(Status::not_found(format!("team {} not found", team)));
+        }
+
+        let members = Team::members(&mut db, &team)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(TeamStatuses {
+            team,
+            members: members.into_iter().map(UserStatus::from).collect(),
+        }))
+    }
+
+    type StreamStatusChangesStream =
+        Pin<Box<dyn Stream<Item = Result<UserStatus, Status>> + Send + Sync + 'static>>;
+
+    async fn stream_status_changes(
+        &self,
+        req: Request<StreamStatusChangesRequest>,
+    ) -> Result<Response<Self::StreamStatusChangesStream>, Status> {
+        let user_ids = req.into_inner().user_ids;
+
+        let changes = self
+            .feed
+            .subscribe()
+            .filter(move |change| {
+                future::ready(user_ids.is_empty() || user_ids.contains(&change.user_id))
+            })
+            .map(|change| Ok(UserStatus::from(change)));
+
+        Ok(Response::new(Box::pin(changes)))
+    }
+}
+
+/// Rejects calls that don't carry the admin API token
+fn check_token(req: Request<()>) -> Result<Request<()>, Status> {
+    let expected = match api_token() {
+        Some(token) => token,
+        None => return Err(Status::unavailable("gRPC API is disabled")),
+    };
+
+    let token = req
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    if !token_matches(token, &expected) {
+        tracing::warn!("Rejected gRPC request with invalid token");
+        return Err(Status::unauthenticated("Invalid API token"));
+    }
+
+    Ok(req)
+}
+
+/// Reads the TLS certificate (chain) and private key the server presents, both PEM encoded
+///
+/// # Arguments
+/// * `cert` - Path to the certificate file
+/// * `key` - Path to the private key file
+pub fn identity(cert: &str, key: &str) -> anyhow::Result<Identity> {
+    let cert = std::fs::read(cert).with_context(|| format!("reading {}", cert))?;
+    let key = std::fs::read(key).with_context(|| format!("reading {}", key))?;
+    Ok(Identity::from_pem(cert, key))
+}
+
+/// Spawns the gRPC server on a dedicated thread
+///
+/// # Arguments
+/// * `addr` - Address to listen on/bind
+/// * `tls` - Certificate and key to serve TLS with, or `None` to serve plaintext
+/// * `pool` - A configured sql pool
+/// * `feed` - Feed of status changes to stream to clients
+pub fn spawn(
+    addr: SocketAddr,
+    tls: Option<Identity>,
+    pool: SqlPool,
+    feed: StatusFeed,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut rt = match tokio::runtime::Runtime::new() {
+            Ok(rt) => rt,
+            Err(e) => {
+                tracing::error!("failed to start gRPC runtime: {:?}", e);
+                return;
+            }
+        };
+
+        let service =
+            StatusServiceServer::with_interceptor(StatusServer { pool, feed }, check_token);
+
+        let mut builder = Server::builder();
+        if let Some(identity) = tls {
+            builder = match builder.tls_config(ServerTlsConfig::new().identity(identity)) {
+                Ok(builder) => builder,
+                Err(e) => {
+                    tracing::error!("failed to configure gRPC TLS: {:?}", e);
+                    return;
+                }
+            };
+        }
+
+        tracing::info!("Starting gRPC server [{}]", addr);
+        if let Err(e) = rt.block_on(builder.add_service(service).serve(addr)) {
+            tracing::error!("gRPC server failed: {:?}", e);
+        }
+    })
+}
@@ -0,0 +1,662 @@
+//! Admin web UI for managing teams and their members
+//!
+//! All pages require a user signed in with Slack that has the admin role (see
+//! `handlers::auth`).
+
+use crate::{
+    error::Error,
+    handlers::{
+        auth::{csrf_input, session_user},
+        live::live_token,
+    },
+    anomaly::{self, Anomalies},
+    audit, breaker, issues,
+    markup::escape,
+    models::{AuditEntry, CommandStat, Leave, Profile, Team, User},
+    render::{Markup, ResponseDoc, Text},
+    timing, HasDb, State,
+};
+use chrono::{Duration, NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tide::{Redirect, StatusCode};
+
+/// Form submitted to create a team
+#[derive(Debug, Deserialize)]
+struct TeamForm {
+    name: String,
+}
+
+/// Form submitted to add a member to a team
+#[derive(Debug, Deserialize)]
+struct MemberForm {
+    user: String,
+}
+
+/// Form submitted to switch capturing of requests on or off
+#[derive(Debug, Deserialize)]
+struct CaptureForm {
+    enabled: bool,
+}
+
+/// Query string parameters accepted by the sign-in sheet
+#[derive(Debug, Deserialize)]
+struct SheetQuery {
+    /// Day the sheet is for, defaulting to today (UTC)
+    date: Option<NaiveDate>,
+}
+
+/// Query string parameters accepted by the usage page
+#[derive(Debug, Deserialize)]
+struct UsageQuery {
+    /// Number of days of usage to summarize
+    days: Option<i64>,
+}
+
+/// Wraps page content in a complete HTML document
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+/// * `title` - Title of the page
+/// * `content` - HTML content of the page
+fn page(req: &tide::Request<State>, title: &str, content: &str) -> tide::Response {
+    let user = session_user(req)
+        .map(|user| escape(&user.name))
+        .unwrap_or_default();
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>{title} - StatusBot</title></head>
+<body>
+<form method="post" action="/auth/logout">{user} {csrf}<button type="submit">Sign out</button></form>
+<h1>{title}</h1>
+{content}
+</body>
+</html>"#,
+        title = escape(title),
+        user = user,
+        csrf = csrf_input(req),
+        content = content
+    );
+
+    tide::Response::builder(StatusCode::Ok)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(html)
+        .build()
+}
+
+/// Handle a `GET` request to `/admin`, listing all teams
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn index(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let mut db = req.read_db().await?;
+    let teams = Team::fetch_all(&mut db).await?;
+
+    let csrf = csrf_input(&req);
+
+    let mut content = String::from("<h2>Teams</h2><ul>");
+    for team in teams {
+        let name = escape(&team.name);
+        content.push_str(&format!(
+            r#"<li><a href="/admin/teams/{name}">{name}</a> {description}
+<form method="post" action="/admin/teams/{name}/delete" style="display:inline">
+{csrf}<button type="submit">Delete</button></form></li>"#,
+            name = name,
+            description = escape(team.description.as_deref().unwrap_or("")),
+            csrf = csrf
+        ));
+    }
+    content.push_str("</ul>");
+    content.push_str(
+        r#"<p><a href="/admin/usage">Command usage</a> |
+<a href="/admin/queries">Query latency</a> |
+<a href="/admin/breakers">Circuit breakers</a> |
+<a href="/admin/anomalies">Anomalies</a></p>"#,
+    );
+
+    if let Some(token) = live_token() {
+        content.push_str(&format!(
+            "<h2>Live Statuses</h2><p>Connect to <code>/ws?token={}</code></p>",
+            token
+        ));
+    }
+
+    if let Some(capture) = &req.state().capture {
+        let enabled = capture.enabled();
+        content.push_str(&format!(
+            r#"<h2>Request Capture</h2>
+<form method="post" action="/admin/capture">
+{csrf}<input type="hidden" name="enabled" value="{enabled}">
+Capturing to <code>{dir}</code> is {state}. <button type="submit">{action}</button>
+</form>"#,
+            csrf = csrf,
+            enabled = !enabled,
+            dir = escape(&capture.dir().display().to_string()),
+            state = if enabled { "on" } else { "off" },
+            action = if enabled { "Stop" } else { "Start" }
+        ));
+    }
+
+    content.push_str(&format!(
+        r#"<h2>Create Team</h2>
+<form method="post" action="/admin/teams">
+{csrf}<input name="name" placeholder="Team name" required>
+<button type="submit">Create</button>
+</form>"#,
+        csrf = csrf
+    ));
+
+    Ok(page(&req, "Admin", &content))
+}
+
+/// Handle a `POST` request to `/admin/teams`, creating a team
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn create_team(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let form: TeamForm = req.body_form().await?;
+    let created_by = session_user(&req).map(|user| user.id).unwrap_or_default();
+    let mut db = req.db().await?;
+
+    if let Err(e) = Team::new(&mut db, form.name.trim(), &created_by).await {
+        tracing::error!("Failed to create team {}: {:?}", form.name, e);
+    }
+
+    Ok(Redirect::see_other("/admin").into())
+}
+
+/// Handle a `POST` request to `/admin/capture`, switching capturing of requests on or off
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn capture(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let form: CaptureForm = req.body_form().await?;
+
+    let capture = match &req.state().capture {
+        Some(capture) => capture,
+        None => return Ok(Error::NotFound("Request capture".to_owned()).into_response()),
+    };
+
+    let admin = session_user(&req).map(|user| user.id).unwrap_or_default();
+    tracing::info!(
+        "request capture switched {} by {}",
+        if form.enabled { "on" } else { "off" },
+        admin
+    );
+    capture.set_enabled(form.enabled);
+
+    Ok(Redirect::see_other("/admin").into())
+}
+
+/// Handle a `GET` request to `/admin/teams/:team`, listing a team's members
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn team(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let name: String = req.param("team").unwrap_or_default();
+    let mut db = req.read_db().await?;
+
+    let team = match Team::fetch(&mut db, &name).await {
+        Some(team) => team,
+        None => return Ok(Error::NotFound(format!("Team {}", name)).into_response()),
+    };
+
+    let members = Team::members(&mut db, &team.name).await?;
+    let name = escape(&team.name);
+
+    let summaries = match &req.state().issues {
+        Some(linker) => {
+            let keys: Vec<String> = members
+                .iter()
+                .filter_map(|member| member.status.as_deref())
+                .flat_map(issues::keys)
+                .collect();
+            linker.lookup(&keys).await
+        }
+        None => HashMap::new(),
+    };
+    let csrf = csrf_input(&req);
+
+    let mut content = String::from(r#"<p><a href="/admin">&larr; All teams</a></p>"#);
+    if let Some(description) = &team.description {
+        content.push_str(&format!("<p>{}</p>", escape(description)));
+    }
+    if let Some(icon) = &team.icon {
+        content.push_str(&format!("<p>Icon: <code>{}</code></p>", escape(icon)));
+    }
+    if let Some(created_by) = &team.created_by {
+        content.push_str(&format!(
+            "<p>Created by {}{}</p>",
+            escape(created_by),
+            team.created_at
+                .map(|at| format!(" on {}", at.format("%Y-%m-%d")))
+                .unwrap_or_default()
+        ));
+    }
+    content.push_str(&format!(
+        r#"<p><a href="/admin/teams/{name}/sign-in">Printable sign-in sheet</a></p>"#,
+        name = name
+    ));
+    content.push_str("<h2>Members</h2><ul>");
+    for member in members {
+        let display_name = match Profile::fetch(&mut db, &member.id).await {
+            Some(profile) if profile.deleted => {
+                format!("{} (deactivated)", profile.display_name.unwrap_or_default())
+            }
+            Some(profile) => profile.display_name.unwrap_or_default(),
+            None => String::new(),
+        };
+
+        content.push_str(&format!(
+            r#"<li>{display_name} ({user}): {status}
+<form method="post" action="/admin/teams/{name}/members/{user}/delete" style="display:inline">
+{csrf}<button type="submit">Remove</button></form></li>"#,
+            display_name = escape(&display_name),
+            user = escape(&member.id),
+            status = match (&req.state().issues, &member.status) {
+                (Some(linker), Some(status)) => linker.link_html(status, &summaries),
+                (_, status) => escape(status.as_deref().unwrap_or("(no status)")),
+            },
+            name = name,
+            csrf = csrf
+        ));
+    }
+    content.push_str("</ul>");
+
+    content.push_str(&format!(
+        r#"<h2>Add Member</h2>
+<form method="post" action="/admin/teams/{name}/members">
+{csrf}<input name="user" placeholder="Slack user id" required>
+<button type="submit">Add</button>
+</form>"#,
+        name = name,
+        csrf = csrf
+    ));
+
+    Ok(page(&req, &format!("{} Team", team.name), &content))
+}
+
+/// Handle a `GET` request to `/admin/teams/:team/sign-in`, rendering a printable sign-in
+/// sheet of a team's members and their current statuses
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn sign_in_sheet(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let name: String = req.param("team").unwrap_or_default();
+    let date = req
+        .query::<SheetQuery>()
+        .ok()
+        .and_then(|query| query.date)
+        .unwrap_or_else(|| Utc::today().naive_utc());
+    let mut db = req.read_db().await?;
+
+    let team = match Team::fetch(&mut db, &name).await {
+        Some(team) => team,
+        None => return Ok(Error::NotFound(format!("Team {}", name)).into_response()),
+    };
+
+    let members = Team::members(&mut db, &team.name).await?;
+    let leave = Leave::fetch_on(&mut db, date).await.unwrap_or_default();
+
+    let mut rows = vec![];
+    for member in members {
+        let display_name = match &member.name {
+            Some(name) if member.external => format!("{} (guest)", name),
+            _ => Profile::fetch(&mut db, &member.id)
+                .await
+                .and_then(|profile| profile.display_name)
+                .unwrap_or_else(|| member.id.clone()),
+        };
+
+        let on_leave = leave.iter().any(|leave| leave.user_id == member.id);
+        let location = match (member.location(), member.site()) {
+            (Some(location), Some(site)) => format!("{} ({})", location.as_str(), site),
+            (Some(location), None) => location.as_str().to_owned(),
+            (None, _) => String::new(),
+        };
+        let availability = if on_leave {
+            "on leave".to_owned()
+        } else {
+            member
+                .availability()
+                .map(|availability| availability.as_str().to_owned())
+                .unwrap_or_default()
+        };
+
+        rows.push((
+            display_name,
+            location,
+            availability,
+            member.status,
+            on_leave,
+        ));
+    }
+    rows.sort_by(|a, b| a.0.to_lowercase().cmp(&b.0.to_lowercase()));
+
+    let mut html = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>{team} Sign-In Sheet - {date}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ width: 100%; border-collapse: collapse; }}
+th, td {{ border: 1px solid #000; padding: 0.4em; text-align: left; }}
+td.blank {{ width: 12%; }}
+tr.leave td {{ color: #666; }}
+@media print {{ body {{ margin: 0; }} tr {{ page-break-inside: avoid; }} }}
+</style></head>
+<body>
+<h1>{team} Sign-In Sheet</h1>
+<p>{date} &middot; statuses as of {now} UTC</p>
+<table>
+<tr><th>Name</th><th>Location</th><th>Availability</th><th>Note</th><th>Time In</th><th>Time Out</th><th>Signature</th></tr>"#,
+        team = escape(&team.display_name()),
+        date = date.format("%A, %B %-d, %Y"),
+        now = Utc::now().format("%Y-%m-%d %H:%M"),
+    );
+
+    for (display_name, location, availability, note, on_leave) in rows {
+        html.push_str(&format!(
+            r#"<tr{class}><td>{name}</td><td>{location}</td><td>{availability}</td><td>{note}</td><td class="blank"></td><td class="blank"></td><td class="blank"></td></tr>"#,
+            class = if on_leave { r#" class="leave""# } else { "" },
+            name = escape(&display_name),
+            location = escape(&location),
+            availability = escape(&availability),
+            note = escape(note.as_deref().unwrap_or("")),
+        ));
+    }
+    html.push_str("</table>\n</body>\n</html>");
+
+    Ok(tide::Response::builder(StatusCode::Ok)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(html)
+        .build())
+}
+
+/// Handle a `POST` request to `/admin/teams/:team/delete`, deleting a team
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn delete_team(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let name: String = req.param("team").unwrap_or_default();
+    let mut db = req.db().await?;
+
+    if let Some(team) = Team::fetch(&mut db, &name).await {
+        match team.delete(&mut db).await {
+            Ok(()) => {
+                let actor = session_user(&req).map(|user| user.id).unwrap_or_default();
+                audit::record(&mut db, &actor, AuditEntry::TEAM_DELETED, &name, None, None).await;
+            }
+            Err(e) => tracing::error!("Failed to delete team {}: {:?}", name, e),
+        }
+    }
+
+    Ok(Redirect::see_other("/admin").into())
+}
+
+/// Handle a `POST` request to `/admin/teams/:team/members`, adding a member to a team
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn add_member(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let form: MemberForm = req.body_form().await?;
+    let name: String = req.param("team").unwrap_or_default();
+    let mut db = req.db().await?;
+
+    if let Some(team) = Team::fetch(&mut db, &name).await {
+        match User::fetch_or_create(&mut db, form.user.trim()).await {
+            Ok(user) => {
+                if let Err(e) = team.add_member(&mut db, &user).await {
+                    tracing::error!("Failed to add {} to team {}: {:?}", user.id, name, e);
+                }
+            }
+            Err(e) => tracing::error!("Failed to load user {}: {:?}", form.user, e),
+        }
+    }
+
+    Ok(Redirect::see_other(format!("/admin/teams/{}", name)).into())
+}
+
+/// Handle a `POST` request to `/admin/teams/:team/members/:user/delete`, removing a member
+/// from a team
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn delete_member(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let name: String = req.param("team").unwrap_or_default();
+    let user: String = req.param("user").unwrap_or_default();
+    let mut db = req.db().await?;
+
+    let team = Team::fetch(&mut db, &name).await;
+    match (team, User::fetch(&mut db, &user).await) {
+        (Some(team), Ok(Some(user))) => {
+            if let Err(e) = team.delete_member(&mut db, &user).await {
+                tracing::error!("Failed to remove {} from team {}: {:?}", user.id, name, e);
+            }
+        }
+        (_, Err(e)) => tracing::error!("Failed to load user {}: {:?}", user, e),
+        _ => (),
+    }
+
+    Ok(Redirect::see_other(format!("/admin/teams/{}", name)).into())
+}
+
+/// Handle a `GET` request to `/admin/usage`, summarizing how often each command was used
+/// over the last `days` days (default 30, at most 365)
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn usage(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let days = req
+        .query::<UsageQuery>()
+        .ok()
+        .and_then(|query| query.days)
+        .filter(|days| (1..=365).contains(days))
+        .unwrap_or(30);
+
+    let mut db = req.read_db().await?;
+    let usage = CommandStat::summarize(&mut db, Utc::now() - Duration::days(days)).await?;
+
+    let mut content = format!(
+        r#"<p><a href="/admin">&larr; All teams</a></p>
+<p>Commands run over the last {} days. Only the command, the workspace, how long it took, and
+whether it succeeded are recorded.</p>"#,
+        days
+    );
+
+    if usage.is_empty() {
+        content.push_str("<p>No commands have been run.</p>");
+    } else {
+        content.push_str(
+            "<table><tr><th>Command</th><th>Uses</th><th>Failures</th>\
+<th>Average latency (ms)</th><th>Workspaces</th></tr>",
+        );
+        for command in usage {
+            content.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&command.action),
+                command.uses,
+                command.failures,
+                command.avg_latency_ms,
+                command.workspaces
+            ));
+        }
+        content.push_str("</table>");
+    }
+
+    Ok(page(&req, "Command Usage", &content))
+}
+
+/// Handle a `GET` request to `/admin/queries`, showing how long each database query has taken
+/// since the bot started
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn queries(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let bucket = |bound: Option<u64>| match bound {
+        Some(ms) => format!("&le; {}", ms),
+        None => format!(
+            "&gt; {}",
+            timing::BUCKETS_MS.last().copied().unwrap_or_default()
+        ),
+    };
+
+    let mut content = String::from(
+        r#"<p><a href="/admin">&larr; All teams</a></p>
+<p>Latency of each query since the bot started, slowest (by total time) first. Queries slower
+than <code>SLOW_QUERY_MS</code> are logged.</p>"#,
+    );
+
+    let histograms = timing::histograms();
+    if histograms.is_empty() {
+        content.push_str("<p>No queries have been run.</p>");
+    } else {
+        content.push_str(
+            "<table><tr><th>Query</th><th>Count</th><th>Mean (ms)</th><th>p50 (ms)</th>\
+<th>p95 (ms)</th><th>p99 (ms)</th><th>Max (ms)</th></tr>",
+        );
+        for (path, histogram) in histograms {
+            content.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+<td>{}</td><td>{}</td></tr>",
+                escape(path),
+                histogram.count,
+                histogram.mean().as_millis(),
+                bucket(histogram.quantile(0.5)),
+                bucket(histogram.quantile(0.95)),
+                bucket(histogram.quantile(0.99)),
+                histogram.max.as_millis()
+            ));
+        }
+        content.push_str("</table>");
+    }
+
+    Ok(page(&req, "Query Latency", &content))
+}
+
+/// Handle a `GET` request to `/admin/breakers`, showing the circuit breaker of each provider
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn breakers(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let mut content = String::from(
+        r#"<p><a href="/admin">&larr; All teams</a></p>
+<p>Circuit breakers of every host the bot has sent requests to.  Requests to a host whose
+breaker is open fail immediately until <code>BREAKER_COOLDOWN</code> seconds have passed.</p>"#,
+    );
+
+    let breakers = breaker::breakers();
+    if breakers.is_empty() {
+        content.push_str("<p>No requests have been sent.</p>");
+    } else {
+        content.push_str(
+            "<table><tr><th>Host</th><th>State</th><th>Consecutive failures</th>\
+<th>Opened (seconds ago)</th></tr>",
+        );
+        for (host, breaker) in breakers {
+            let state = match breaker.state {
+                breaker::State::Closed => "closed",
+                breaker::State::Open => "open",
+                breaker::State::HalfOpen => "half open",
+            };
+            content.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&host),
+                state,
+                breaker.failures,
+                breaker
+                    .opened_at
+                    .map(|opened_at| opened_at.elapsed().as_secs().to_string())
+                    .unwrap_or_default()
+            ));
+        }
+        content.push_str("</table>");
+    }
+
+    Ok(page(&req, "Circuit Breakers", &content))
+}
+
+/// Handle a `GET` request to `/admin/anomalies`, listing active members who haven't set a
+/// status in a while and users setting statuses unusually often
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn anomalies(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let mut db = req.read_db().await?;
+    let anomalies = Anomalies::detect(&mut db, Utc::now()).await?;
+
+    let mut doc = ResponseDoc::new();
+    doc.header("Forgotten accounts").section(format!(
+        "Members of a team whose Slack account is active, who aren't on leave, and who haven't \
+set a status in {} days.",
+        anomaly::STALE_DAYS
+    ));
+
+    if anomalies.stale.is_empty() {
+        doc.section("No forgotten accounts found.");
+    } else {
+        let mut rows = vec![];
+        for user in anomalies.stale {
+            let display_name = Profile::fetch(&mut db, &user.user_id)
+                .await
+                .and_then(|profile| profile.display_name)
+                .unwrap_or_default();
+
+            rows.push(vec![
+                Text::new().text(user.user_id.as_str()),
+                Text::new().text(display_name),
+                Text::new().text(user.last_changed_at.format("%Y-%m-%d").to_string()),
+            ]);
+        }
+        doc.table(&["User", "Name", "Last status set"], rows);
+    }
+
+    doc.header("Possible automation").section(format!(
+        "Users who set at least {} statuses over the last {} hours.",
+        anomaly::BUSY_MIN_CHANGES,
+        anomaly::BUSY_WINDOW_HOURS
+    ));
+
+    if anomalies.busy.is_empty() {
+        doc.section("No unusual activity found.");
+    } else {
+        let mut rows = vec![];
+        for user in anomalies.busy {
+            let display_name = Profile::fetch(&mut db, &user.user_id)
+                .await
+                .and_then(|profile| profile.display_name)
+                .unwrap_or_default();
+
+            rows.push(vec![
+                Text::new().text(user.user_id.as_str()),
+                Text::new().text(display_name),
+                Text::new().text(user.changes.to_string()),
+                Text::new().text(user.average_interval_secs().to_string()),
+                Text::new().text(
+                    user.last_changed_at
+                        .format("%Y-%m-%d %H:%M UTC")
+                        .to_string(),
+                ),
+            ]);
+        }
+        doc.table(
+            &[
+                "User",
+                "Name",
+                "Statuses set",
+                "Average interval (s)",
+                "Last status set",
+            ],
+            rows,
+        );
+    }
+
+    let content = format!(
+        "<p><a href=\"/admin\">&larr; All teams</a></p>\n{}",
+        doc.render(Markup::Html)
+    );
+
+    Ok(page(&req, "Anomalies", &content))
+}
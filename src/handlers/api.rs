@@ -0,0 +1,1101 @@
+//! JSON admin API for managing teams and their memberships, e.g. from a Terraform provider
+//!
+//! Teams are addressed by their numeric id, which never changes (even when a team is
+//! renamed), and memberships by the team's id and the member's Slack id.  Every resource is
+//! returned with an `ETag`; `PUT` and `DELETE` requests may send it back in `If-Match` to
+//! fail with `412 Precondition Failed` instead of overwriting a concurrent change.  `PUT`s
+//! replace the whole resource and `DELETE`s of resources that don't exist succeed, so
+//! repeating a request has no further effect.
+//!
+//! Requests that change something accept `?dry_run=true`, to run inside a transaction that is
+//! rolled back: the response is the one a real request would get (deletes respond with what
+//! they would delete), but nothing is changed.
+//!
+//! Requests must carry `Authorization: Bearer <ADMIN_API_TOKEN>`.  If `ADMIN_API_TOKEN` is
+//! not set, the API is disabled.
+
+use crate::{
+    audit,
+    error::Error,
+    models::{
+        normalize_name, validate_name, AuditEntry, MemberRole, RetentionOverride, SlackUserId,
+        Team, User,
+    },
+    HasDb, SqlConn, State,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tide::{Middleware, Next, StatusCode};
+
+/// Weekdays a team's coverage requirement applies on when none are given (Monday to Friday)
+const DEFAULT_COVERAGE_DAYS: i64 = 31;
+
+/// A team, as returned by the API
+#[derive(Debug, Serialize)]
+struct TeamResource {
+    id: i64,
+    name: String,
+    description: Option<String>,
+    icon: Option<String>,
+    channel: Option<String>,
+    notify_changes: bool,
+    min_coverage: Option<i64>,
+    coverage_days: i64,
+    created_at: Option<DateTime<Utc>>,
+    created_by: Option<String>,
+}
+
+impl From<&Team> for TeamResource {
+    fn from(team: &Team) -> Self {
+        TeamResource {
+            id: team.id(),
+            name: team.name.clone(),
+            description: team.description.clone(),
+            icon: team.icon.clone(),
+            channel: team.channel.clone(),
+            notify_changes: team.notify_changes,
+            min_coverage: team.min_coverage,
+            coverage_days: team.coverage_days,
+            created_at: team.created_at,
+            created_by: team.created_by.clone(),
+        }
+    }
+}
+
+/// Body of a request creating or replacing a team
+///
+/// Fields that are left out are cleared (or reset to their defaults), as a `PUT` replaces
+/// the whole team
+#[derive(Debug, Deserialize)]
+struct TeamSpec {
+    name: String,
+    description: Option<String>,
+    icon: Option<String>,
+    channel: Option<String>,
+    #[serde(default)]
+    notify_changes: bool,
+    min_coverage: Option<i64>,
+    coverage_days: Option<i64>,
+}
+
+impl TeamSpec {
+    /// Copies the settings in this spec onto a team
+    ///
+    /// # Arguments
+    /// * `team` - Team to update
+    fn apply(self, team: &mut Team) {
+        team.name = self.name.trim().to_owned();
+        team.description = self.description;
+        team.icon = self.icon;
+        team.channel = self.channel;
+        team.notify_changes = self.notify_changes;
+        team.min_coverage = self.min_coverage;
+        team.coverage_days = self.coverage_days.unwrap_or(DEFAULT_COVERAGE_DAYS);
+    }
+}
+
+/// A user's membership of a team, as returned by the API
+#[derive(Debug, Serialize)]
+struct MembershipResource {
+    team_id: i64,
+    user_id: String,
+    role: MemberRole,
+}
+
+/// A member's status at a point in time, as returned by the API
+#[derive(Debug, Serialize)]
+struct StatusResource {
+    user_id: String,
+    status: Option<String>,
+    location: Option<&'static str>,
+    site: Option<String>,
+    availability: Option<&'static str>,
+}
+
+/// Query string parameters of a request for statuses at a point in time
+#[derive(Debug, Deserialize)]
+struct AsOfQuery {
+    /// The point in time (RFC 3339), or a day (`YYYY-MM-DD`) to return statuses at the end of,
+    /// in UTC
+    at: String,
+}
+
+impl AsOfQuery {
+    /// Returns the point in time asked for, or `None` if it can't be parsed
+    fn at(&self) -> Option<DateTime<Utc>> {
+        if let Ok(at) = DateTime::parse_from_rfc3339(&self.at) {
+            return Some(at.with_timezone(&Utc));
+        }
+
+        NaiveDate::parse_from_str(&self.at, "%Y-%m-%d")
+            .ok()
+            .and_then(|day| day.succ_opt())
+            .map(|day| DateTime::from_utc(day.and_hms(0, 0, 0), Utc))
+    }
+}
+
+/// Body of a request adding a member to a team, or changing their role
+#[derive(Debug, Deserialize)]
+struct MembershipSpec {
+    #[serde(default = "default_role")]
+    role: MemberRole,
+}
+
+/// Role given to members when a request doesn't name one
+fn default_role() -> MemberRole {
+    MemberRole::Member
+}
+
+/// How long a team's members' status history is kept, as returned by the API
+#[derive(Debug, Serialize)]
+struct RetentionResource {
+    team_id: i64,
+    months: i64,
+    reason: Option<String>,
+}
+
+impl From<&RetentionOverride> for RetentionResource {
+    fn from(retention: &RetentionOverride) -> Self {
+        RetentionResource {
+            team_id: retention.team_id,
+            months: retention.months,
+            reason: retention.reason.clone(),
+        }
+    }
+}
+
+/// Body of a request setting how long a team's members' status history is kept
+#[derive(Debug, Deserialize)]
+struct RetentionSpec {
+    /// Number of months of history to keep before the current month (0 keeps everything)
+    months: i64,
+
+    /// Why the history is kept (e.g., a legal hold)
+    reason: Option<String>,
+}
+
+/// Query string parameters accepted by requests that change something
+#[derive(Debug, Default, Deserialize)]
+struct WriteQuery {
+    /// Simulate the request, without keeping its changes
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Returns true if a request only simulates its changes
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+fn dry_run(req: &tide::Request<State>) -> bool {
+    req.query::<WriteQuery>()
+        .map(|query| query.dry_run)
+        .unwrap_or_default()
+}
+
+/// Returns the prefix of log messages about a request's changes, marking simulated ones
+///
+/// # Arguments
+/// * `dry_run` - If the changes are only simulated
+fn log_prefix(dry_run: bool) -> &'static str {
+    if dry_run {
+        "dry run: "
+    } else {
+        ""
+    }
+}
+
+/// Computes the entity tag of a resource, from its JSON representation
+///
+/// # Arguments
+/// * `resource` - Resource to tag
+fn etag<T: Serialize>(resource: &T) -> String {
+    let json = serde_json::to_vec(resource).unwrap_or_default();
+    format!("\"{}\"", hex::encode(Sha256::digest(&json)))
+}
+
+/// Returns true if the request's `If-Match` header doesn't match a resource's current entity
+/// tag (a missing resource never matches)
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+/// * `current` - Entity tag of the resource, or `None` if it doesn't exist
+fn precondition_failed(req: &tide::Request<State>, current: Option<&str>) -> bool {
+    let expected = match req.header("If-Match") {
+        Some(values) => values.last().as_str().to_owned(),
+        None => return false,
+    };
+
+    match current {
+        Some(current) => {
+            expected.trim() != "*" && !expected.split(',').any(|tag| tag.trim() == current)
+        }
+        None => true,
+    }
+}
+
+/// Builds a JSON response for a resource, tagged with its entity tag
+///
+/// # Arguments
+/// * `status` - Status code of the response
+/// * `resource` - Resource to return
+fn resource_response<T: Serialize>(status: StatusCode, resource: &T) -> tide::Response {
+    tide::Response::builder(status)
+        .header("ETag", etag(resource))
+        .body(json!(resource))
+        .build()
+}
+
+/// Builds a JSON response describing an error
+///
+/// # Arguments
+/// * `status` - Status code of the response
+/// * `message` - Description of the error
+fn error_response(status: StatusCode, message: &str) -> tide::Response {
+    tide::Response::builder(status)
+        .body(json!({ "error": message }))
+        .build()
+}
+
+/// Logs an error and converts it into a JSON response
+///
+/// # Arguments
+/// * `e` - Error to convert
+fn from_error(e: anyhow::Error) -> tide::Response {
+    match e.downcast::<Error>() {
+        Ok(e) => {
+            e.log();
+            error_response(e.status(), &e.user_message())
+        }
+        Err(e) => {
+            tracing::error!("admin api request failed: {:?}", e);
+            error_response(StatusCode::InternalServerError, "Internal server error")
+        }
+    }
+}
+
+/// Returns the id of the team a request is for, from its path
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+fn team_id(req: &tide::Request<State>) -> Option<i64> {
+    req.param("team").ok()
+}
+
+/// Fetches the role of a user in a team, returning `None` if the user doesn't exist or isn't
+/// a member
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `team` - Team to look in
+/// * `user_id` - Slack ID of the user
+async fn membership(
+    db: &mut SqlConn,
+    team: &Team,
+    user_id: &str,
+) -> anyhow::Result<Option<MembershipResource>> {
+    let user = match User::fetch(&mut *db, user_id).await? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+
+    let role = team.member_role(&mut *db, &user).await?;
+    Ok(role.map(|role| MembershipResource {
+        team_id: team.id(),
+        user_id: user.id,
+        role,
+    }))
+}
+
+/// Checks that a team can be given a name, returning a response describing why not if it
+/// can't
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `name` - Proposed name of the team
+/// * `id` - Id of the team being renamed, or `None` if it's being created
+async fn check_name(
+    db: &mut SqlConn,
+    name: &str,
+    id: Option<i64>,
+) -> Option<tide::Response> {
+    if let Err(e) = validate_name(name) {
+        return Some(error_response(e.status(), &e.user_message()));
+    }
+
+    match Team::fetch(&mut *db, name).await {
+        Some(existing) if Some(existing.id()) != id => Some(error_response(
+            StatusCode::Conflict,
+            &format!("Team {} already exists (id {})", existing.name, existing.id()),
+        )),
+        _ => None,
+    }
+}
+
+/// Handle a `GET` request to `/api/teams`, listing all teams
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn list_teams(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let mut db = req.read_db().await?;
+
+    let teams = match Team::fetch_all(&mut db).await {
+        Ok(teams) => teams,
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let teams: Vec<TeamResource> = teams.iter().map(TeamResource::from).collect();
+    Ok(tide::Response::builder(StatusCode::Ok)
+        .body(json!(teams))
+        .build())
+}
+
+/// Handle a `POST` request to `/api/teams`, creating a team
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn create_team(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let spec: TeamSpec = match req.body_json().await {
+        Ok(spec) => spec,
+        Err(e) => return Ok(error_response(StatusCode::BadRequest, &e.to_string())),
+    };
+
+    let dry_run = dry_run(&req);
+    let mut db = req.db().await?;
+    if let Some(resp) = check_name(&mut db, spec.name.trim(), None).await {
+        return Ok(resp);
+    }
+
+    let created = simulated!(db, dry_run, async {
+        let mut team = Team::new(&mut db, spec.name.trim(), "").await?;
+        spec.apply(&mut team);
+        team.save(&mut db).await?;
+        Ok::<_, anyhow::Error>(team)
+    });
+
+    let team = match created {
+        Ok(team) => team,
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    if dry_run {
+        let changes = [format!("created team {}", team.name)];
+        audit::record_simulated(&mut db, AuditEntry::API, &team.name, &changes).await;
+    }
+
+    tracing::info!(
+        "{}team {} (id {}) created through the admin api",
+        log_prefix(dry_run),
+        team.name,
+        team.id()
+    );
+
+    let mut resp = resource_response(StatusCode::Created, &TeamResource::from(&team));
+    resp.insert_header("Location", format!("/api/teams/{}", team.id()));
+    Ok(resp)
+}
+
+/// Handle a `GET` request to `/api/teams/:team`, returning a team
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn get_team(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let mut db = req.read_db().await?;
+
+    let team = match team_id(&req) {
+        Some(id) => Team::fetch_by_id(&mut db, id).await,
+        None => Ok(None),
+    };
+
+    Ok(match team {
+        Ok(Some(team)) => resource_response(StatusCode::Ok, &TeamResource::from(&team)),
+        Ok(None) => error_response(StatusCode::NotFound, "Team not found"),
+        Err(e) => from_error(e),
+    })
+}
+
+/// Handle a `PUT` request to `/api/teams/:team`, replacing a team's settings
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn put_team(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let spec: TeamSpec = match req.body_json().await {
+        Ok(spec) => spec,
+        Err(e) => return Ok(error_response(StatusCode::BadRequest, &e.to_string())),
+    };
+
+    let id = match team_id(&req) {
+        Some(id) => id,
+        None => return Ok(error_response(StatusCode::NotFound, "Team not found")),
+    };
+
+    let dry_run = dry_run(&req);
+    let mut db = req.db().await?;
+    let mut team = match Team::fetch_by_id(&mut db, id).await {
+        Ok(Some(team)) => team,
+        Ok(None) => return Ok(error_response(StatusCode::NotFound, "Team not found")),
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let current = etag(&TeamResource::from(&team));
+    if precondition_failed(&req, Some(&current)) {
+        return Ok(error_response(
+            StatusCode::PreconditionFailed,
+            "Team has changed since it was read",
+        ));
+    }
+
+    if normalize_name(&spec.name) != normalize_name(&team.name) {
+        if let Some(resp) = check_name(&mut db, spec.name.trim(), Some(id)).await {
+            return Ok(resp);
+        }
+    }
+
+    spec.apply(&mut team);
+    if let Err(e) = simulated!(db, dry_run, team.save(&mut db)) {
+        return Ok(from_error(e));
+    }
+
+    if dry_run {
+        let changes = [format!("updated team {}", team.name)];
+        audit::record_simulated(&mut db, AuditEntry::API, &team.name, &changes).await;
+    }
+
+    tracing::info!(
+        "{}team {} (id {}) updated through the admin api",
+        log_prefix(dry_run),
+        team.name,
+        id
+    );
+
+    Ok(resource_response(StatusCode::Ok, &TeamResource::from(&team)))
+}
+
+/// Handle a `DELETE` request to `/api/teams/:team`, deleting a team
+///
+/// *THIS ACTION CANNOT BE UNDONE*
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn delete_team(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let dry_run = dry_run(&req);
+    let mut db = req.db().await?;
+
+    let team = match team_id(&req) {
+        Some(id) => Team::fetch_by_id(&mut db, id).await,
+        None => Ok(None),
+    };
+
+    let team = match team {
+        Ok(team) => team,
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let current = team.as_ref().map(|team| etag(&TeamResource::from(team)));
+    if precondition_failed(&req, current.as_deref()) {
+        return Ok(error_response(
+            StatusCode::PreconditionFailed,
+            "Team has changed since it was read",
+        ));
+    }
+
+    let team = match team {
+        Some(team) => team,
+        None => return Ok(tide::Response::builder(StatusCode::NoContent).build()),
+    };
+
+    let resource = TeamResource::from(&team);
+    let deleted = simulated!(db, dry_run, async {
+        team.delete(&mut db).await?;
+        AuditEntry::record(
+            &mut db,
+            AuditEntry::API,
+            AuditEntry::TEAM_DELETED,
+            &resource.name,
+            None,
+            None,
+        )
+        .await
+    });
+
+    if let Err(e) = deleted {
+        return Ok(from_error(e));
+    }
+
+    if dry_run {
+        let changes = [format!("deleted team {}", resource.name)];
+        audit::record_simulated(&mut db, AuditEntry::API, &resource.name, &changes).await;
+    }
+
+    tracing::info!(
+        "{}team {} (id {}) deleted through the admin api",
+        log_prefix(dry_run),
+        resource.name,
+        resource.id
+    );
+
+    if dry_run {
+        return Ok(resource_response(StatusCode::Ok, &resource));
+    }
+
+    Ok(tide::Response::builder(StatusCode::NoContent).build())
+}
+
+/// Handle a `GET` request to `/api/teams/:team/members`, listing a team's memberships
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn list_members(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let mut db = req.read_db().await?;
+
+    let team = match team_id(&req) {
+        Some(id) => Team::fetch_by_id(&mut db, id).await,
+        None => Ok(None),
+    };
+
+    let team = match team {
+        Ok(Some(team)) => team,
+        Ok(None) => return Ok(error_response(StatusCode::NotFound, "Team not found")),
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let members = match Team::members_page(&mut db, &team.name, i64::MAX, 0).await {
+        Ok(members) => members,
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    // guests aren't on slack, so they can't be managed as memberships
+    let memberships: Vec<MembershipResource> = members
+        .iter()
+        .filter(|member| !member.external)
+        .map(|member| MembershipResource {
+            team_id: team.id(),
+            user_id: member.id.clone(),
+            role: member.role(),
+        })
+        .collect();
+
+    Ok(tide::Response::builder(StatusCode::Ok)
+        .body(json!(memberships))
+        .build())
+}
+
+/// Handle a `GET` request to `/api/teams/:team/statuses?at=...`, returning the status each
+/// member of a team had at a point in time
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn list_statuses_as_of(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let at = match req.query::<AsOfQuery>().ok().and_then(|query| query.at()) {
+        Some(at) => at,
+        None => {
+            return Ok(error_response(
+                StatusCode::BadRequest,
+                "`at` must be a date (YYYY-MM-DD) or an RFC 3339 timestamp",
+            ))
+        }
+    };
+
+    let mut db = req.read_db().await?;
+
+    let team = match team_id(&req) {
+        Some(id) => Team::fetch_by_id(&mut db, id).await,
+        None => Ok(None),
+    };
+
+    let team = match team {
+        Ok(Some(team)) => team,
+        Ok(None) => return Ok(error_response(StatusCode::NotFound, "Team not found")),
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let members = match Team::members_as_of(&mut db, &team.name, at, i64::MAX, 0).await {
+        Ok(members) => members,
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let statuses: Vec<StatusResource> = members
+        .iter()
+        .map(|member| StatusResource {
+            user_id: member.id.clone(),
+            status: member.status.clone(),
+            location: member.location().map(|l| l.as_str()),
+            site: member.site().map(str::to_owned),
+            availability: member.availability().map(|a| a.as_str()),
+        })
+        .collect();
+
+    Ok(tide::Response::builder(StatusCode::Ok)
+        .body(json!({ "at": at, "statuses": statuses }))
+        .build())
+}
+
+/// Handle a `GET` request to `/api/teams/:team/members/:user`, returning a membership
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn get_member(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let user_id: String = req.param("user").unwrap_or_default();
+    let mut db = req.read_db().await?;
+
+    let team = match team_id(&req) {
+        Some(id) => Team::fetch_by_id(&mut db, id).await,
+        None => Ok(None),
+    };
+
+    let team = match team {
+        Ok(Some(team)) => team,
+        Ok(None) => return Ok(error_response(StatusCode::NotFound, "Team not found")),
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    Ok(match membership(&mut db, &team, &user_id).await {
+        Ok(Some(membership)) => resource_response(StatusCode::Ok, &membership),
+        Ok(None) => error_response(StatusCode::NotFound, "Membership not found"),
+        Err(e) => from_error(e),
+    })
+}
+
+/// Returns whether putting a membership grants a role: changing an existing member's role,
+/// or adding a member with a role other than the default (`member`), which new members get
+/// without one being granted
+///
+/// # Arguments
+/// * `existing` - Role of the existing member, or `None` if the user is being added
+/// * `role` - Role the membership is put with
+fn role_granted(existing: Option<MemberRole>, role: MemberRole) -> bool {
+    match existing {
+        Some(existing) => existing != role,
+        None => role != MemberRole::Member,
+    }
+}
+
+/// Handle a `PUT` request to `/api/teams/:team/members/:user`, adding a user to a team or
+/// changing their role
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn put_member(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let spec: MembershipSpec = match req.body_json().await {
+        Ok(spec) => spec,
+        Err(e) => return Ok(error_response(StatusCode::BadRequest, &e.to_string())),
+    };
+
+    let user_id: String = req.param("user").unwrap_or_default();
+    if let Err(e) = SlackUserId::parse(&user_id) {
+        return Ok(error_response(StatusCode::BadRequest, &e.to_string()));
+    }
+
+    let dry_run = dry_run(&req);
+    let mut db = req.db().await?;
+
+    let team = match team_id(&req) {
+        Some(id) => Team::fetch_by_id(&mut db, id).await,
+        None => Ok(None),
+    };
+
+    let team = match team {
+        Ok(Some(team)) => team,
+        Ok(None) => return Ok(error_response(StatusCode::NotFound, "Team not found")),
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let existing = match membership(&mut db, &team, &user_id).await {
+        Ok(existing) => existing,
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let current = existing.as_ref().map(etag);
+    if precondition_failed(&req, current.as_deref()) {
+        return Ok(error_response(
+            StatusCode::PreconditionFailed,
+            "Membership has changed since it was read",
+        ));
+    }
+
+    let added = existing.is_none();
+    let granted = role_granted(existing.map(|existing| existing.role), spec.role);
+
+    let updated = simulated!(db, dry_run, async {
+        let user = User::fetch_or_create(&mut db, &user_id).await?;
+
+        if added {
+            team.add_member(&mut db, &user).await?;
+        }
+
+        if granted {
+            team.set_role(&mut db, &user, spec.role).await?;
+            AuditEntry::record(
+                &mut db,
+                AuditEntry::API,
+                AuditEntry::ROLE_GRANTED,
+                &team.name,
+                Some(&user.id),
+                Some(spec.role.as_str()),
+            )
+            .await?;
+        }
+
+        Ok::<_, anyhow::Error>(user)
+    });
+
+    let user = match updated {
+        Ok(user) => user,
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    if dry_run {
+        let mut changes = Vec::new();
+        if added {
+            changes.push(format!("added {} to team {}", user.id, team.name));
+        }
+        if granted {
+            changes.push(format!(
+                "made {} a {} of team {}",
+                user.id,
+                spec.role.as_str(),
+                team.name
+            ));
+        }
+        audit::record_simulated(&mut db, AuditEntry::API, &team.name, &changes).await;
+    }
+
+    tracing::info!(
+        "{}{} made a {} of team {} through the admin api",
+        log_prefix(dry_run),
+        user.id,
+        spec.role.as_str(),
+        team.name
+    );
+
+    let membership = MembershipResource {
+        team_id: team.id(),
+        user_id: user.id,
+        role: spec.role,
+    };
+
+    let status = match current {
+        Some(_) => StatusCode::Ok,
+        None => StatusCode::Created,
+    };
+
+    Ok(resource_response(status, &membership))
+}
+
+/// Handle a `DELETE` request to `/api/teams/:team/members/:user`, removing a user from a team
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn delete_member(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let user_id: String = req.param("user").unwrap_or_default();
+    let dry_run = dry_run(&req);
+    let mut db = req.db().await?;
+
+    let team = match team_id(&req) {
+        Some(id) => Team::fetch_by_id(&mut db, id).await,
+        None => Ok(None),
+    };
+
+    let team = match team {
+        Ok(Some(team)) => team,
+        Ok(None) => return Ok(tide::Response::builder(StatusCode::NoContent).build()),
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let existing = match membership(&mut db, &team, &user_id).await {
+        Ok(existing) => existing,
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let current = existing.as_ref().map(etag);
+    if precondition_failed(&req, current.as_deref()) {
+        return Ok(error_response(
+            StatusCode::PreconditionFailed,
+            "Membership has changed since it was read",
+        ));
+    }
+
+    let user = match User::fetch(&mut db, &user_id).await {
+        Ok(user) => user,
+        Err(e) => return Ok(from_error(e.into())),
+    };
+
+    let (existing, user) = match (existing, user) {
+        (Some(existing), Some(user)) => (existing, user),
+        _ => return Ok(tide::Response::builder(StatusCode::NoContent).build()),
+    };
+
+    if let Err(e) = simulated!(db, dry_run, team.delete_member(&mut db, &user)) {
+        return Ok(from_error(e));
+    }
+
+    if dry_run {
+        let changes = [format!("removed {} from team {}", user.id, team.name)];
+        audit::record_simulated(&mut db, AuditEntry::API, &team.name, &changes).await;
+    }
+
+    tracing::info!(
+        "{}{} removed from team {} through the admin api",
+        log_prefix(dry_run),
+        user.id,
+        team.name
+    );
+
+    if dry_run {
+        return Ok(resource_response(StatusCode::Ok, &existing));
+    }
+
+    Ok(tide::Response::builder(StatusCode::NoContent).build())
+}
+
+/// Handle a `GET` request to `/api/teams/:team/retention`, returning a team's retention
+/// override
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn get_retention(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let mut db = req.read_db().await?;
+
+    let retention = match team_id(&req) {
+        Some(id) => RetentionOverride::fetch_by_team(&mut db, id).await,
+        None => Ok(None),
+    };
+
+    Ok(match retention {
+        Ok(Some(retention)) => {
+            resource_response(StatusCode::Ok, &RetentionResource::from(&retention))
+        }
+        Ok(None) => error_response(StatusCode::NotFound, "Retention override not found"),
+        Err(e) => from_error(e),
+    })
+}
+
+/// Handle a `PUT` request to `/api/teams/:team/retention`, setting how long a team's members'
+/// status history is kept
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn put_retention(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let spec: RetentionSpec = match req.body_json().await {
+        Ok(spec) => spec,
+        Err(e) => return Ok(error_response(StatusCode::BadRequest, &e.to_string())),
+    };
+
+    if spec.months < 0 {
+        return Ok(error_response(
+            StatusCode::BadRequest,
+            "`months` must be 0 (keep everything) or more",
+        ));
+    }
+
+    let dry_run = dry_run(&req);
+    let mut db = req.db().await?;
+
+    let team = match team_id(&req) {
+        Some(id) => Team::fetch_by_id(&mut db, id).await,
+        None => Ok(None),
+    };
+
+    let team = match team {
+        Ok(Some(team)) => team,
+        Ok(None) => return Ok(error_response(StatusCode::NotFound, "Team not found")),
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let existing = match RetentionOverride::fetch_by_team(&mut db, team.id()).await {
+        Ok(existing) => existing,
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let current = existing
+        .as_ref()
+        .map(|existing| etag(&RetentionResource::from(existing)));
+    if precondition_failed(&req, current.as_deref()) {
+        return Ok(error_response(
+            StatusCode::PreconditionFailed,
+            "Retention override has changed since it was read",
+        ));
+    }
+
+    let saved = simulated!(db, dry_run, async {
+        let retention =
+            RetentionOverride::save(&mut db, team.id(), spec.months, spec.reason).await?;
+        AuditEntry::record(
+            &mut db,
+            AuditEntry::API,
+            AuditEntry::RETENTION_CHANGED,
+            &team.name,
+            None,
+            Some(&format!("{} months", retention.months)),
+        )
+        .await?;
+
+        Ok::<_, anyhow::Error>(retention)
+    });
+
+    let retention = match saved {
+        Ok(retention) => retention,
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    if dry_run {
+        let changes = [format!(
+            "kept history of team {} for {} months",
+            team.name, retention.months
+        )];
+        audit::record_simulated(&mut db, AuditEntry::API, &team.name, &changes).await;
+    }
+
+    tracing::info!(
+        "{}history of team {} kept for {} months ({}) through the admin api",
+        log_prefix(dry_run),
+        team.name,
+        retention.months,
+        retention.reason.as_deref().unwrap_or("no reason given")
+    );
+
+    let status = match current {
+        Some(_) => StatusCode::Ok,
+        None => StatusCode::Created,
+    };
+
+    Ok(resource_response(status, &RetentionResource::from(&retention)))
+}
+
+/// Handle a `DELETE` request to `/api/teams/:team/retention`, removing a team's retention
+/// override
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn delete_retention(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let dry_run = dry_run(&req);
+    let mut db = req.db().await?;
+
+    let team = match team_id(&req) {
+        Some(id) => Team::fetch_by_id(&mut db, id).await,
+        None => Ok(None),
+    };
+
+    let team = match team {
+        Ok(Some(team)) => team,
+        Ok(None) => return Ok(tide::Response::builder(StatusCode::NoContent).build()),
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let existing = match RetentionOverride::fetch_by_team(&mut db, team.id()).await {
+        Ok(existing) => existing.map(|existing| RetentionResource::from(&existing)),
+        Err(e) => return Ok(from_error(e)),
+    };
+
+    let current = existing.as_ref().map(etag);
+    if precondition_failed(&req, current.as_deref()) {
+        return Ok(error_response(
+            StatusCode::PreconditionFailed,
+            "Retention override has changed since it was read",
+        ));
+    }
+
+    let existing = match existing {
+        Some(existing) => existing,
+        None => return Ok(tide::Response::builder(StatusCode::NoContent).build()),
+    };
+
+    let deleted = simulated!(db, dry_run, async {
+        RetentionOverride::delete_by_team(&mut db, team.id()).await?;
+        AuditEntry::record(
+            &mut db,
+            AuditEntry::API,
+            AuditEntry::RETENTION_CHANGED,
+            &team.name,
+            None,
+            None,
+        )
+        .await
+    });
+
+    if let Err(e) = deleted {
+        return Ok(from_error(e));
+    }
+
+    if dry_run {
+        let changes = [format!("removed retention override of team {}", team.name)];
+        audit::record_simulated(&mut db, AuditEntry::API, &team.name, &changes).await;
+    }
+
+    tracing::info!(
+        "{}retention override of team {} removed through the admin api",
+        log_prefix(dry_run),
+        team.name
+    );
+
+    if dry_run {
+        return Ok(resource_response(StatusCode::Ok, &existing));
+    }
+
+    Ok(tide::Response::builder(StatusCode::NoContent).build())
+}
+
+/// Returns the admin API token, or `None` if it isn't configured
+pub(crate) fn api_token() -> Option<String> {
+    dotenv::var("ADMIN_API_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+/// Checks a token a client presented against the admin API token
+pub(crate) fn token_matches(token: &str, expected: &str) -> bool {
+    // compare digests, so the time taken doesn't reveal how much of the token matched
+    Sha256::digest(token.as_bytes()) == Sha256::digest(expected.as_bytes())
+}
+
+/// Middleware that requires the admin API token, disabling the API if none is configured
+#[derive(Debug, Default)]
+pub struct RequireApiToken;
+
+#[async_trait]
+impl<S: Clone + Send + Sync + 'static> Middleware<S> for RequireApiToken {
+    async fn handle(&self, req: tide::Request<S>, next: Next<'_, S>) -> tide::Result {
+        let expected = match api_token() {
+            Some(token) => token,
+            None => return Ok(tide::Response::builder(StatusCode::NotFound).build()),
+        };
+
+        let token = req
+            .header("Authorization")
+            .and_then(|values| {
+                values
+                    .last()
+                    .as_str()
+                    .strip_prefix("Bearer ")
+                    .map(str::to_owned)
+            })
+            .unwrap_or_default();
+
+        if !token_matches(&token, &expected) {
+            tracing::warn!("Rejected admin api request with invalid token");
+            return Ok(error_response(StatusCode::Unauthorized, "Invalid API token"));
+        }
+
+        Ok(next.run(req).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_new_member_is_not_a_role_grant() {
+        assert!(!role_granted(None, MemberRole::Member));
+        assert!(role_granted(None, MemberRole::Lead));
+        assert!(role_granted(None, MemberRole::Viewer));
+    }
+
+    #[test]
+    fn changed_role_is_a_role_grant() {
+        assert!(role_granted(Some(MemberRole::Member), MemberRole::Lead));
+        assert!(!role_granted(Some(MemberRole::Lead), MemberRole::Lead));
+    }
+}
@@ -0,0 +1,161 @@
+//! Atom feeds of team status changes
+//!
+//! Each feed is protected by a token, the hex encoded HMAC-SHA256 of the team name keyed
+//! with `FEED_SECRET`.  If `FEED_SECRET` is not set, feeds are disabled.
+
+use crate::{caching, error::Error, markup::escape, models::HistoryEntry, HasDb, State};
+use hmac::{Hmac, Mac, NewMac};
+use serde::Deserialize;
+use sha2::Sha256;
+use tide::StatusCode;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Maximum number of entries included in a feed
+const FEED_LENGTH: i64 = 50;
+
+/// Query string parameters accepted by the feed endpoint
+#[derive(Debug, Deserialize)]
+struct FeedQuery {
+    /// Access token for this feed
+    token: String,
+}
+
+/// Computes the access token for a team's feed, returning `None` if feeds are disabled
+///
+/// # Arguments
+/// * `team` - Name of the team
+pub fn feed_token(team: &str) -> Option<String> {
+    let secret = dotenv::var("FEED_SECRET").ok()?;
+    let mut mac = HmacSha256::new_varkey(secret.as_bytes()).ok()?;
+    mac.update(team.as_bytes());
+
+    Some(hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Builds the URL of a team's feed, returning `None` if feeds are disabled
+///
+/// The URL is relative unless `PUBLIC_URL` is set
+///
+/// # Arguments
+/// * `team` - Name of the team
+pub fn feed_url(team: &str) -> Option<String> {
+    let token = feed_token(team)?;
+    let base = dotenv::var("PUBLIC_URL").unwrap_or_default();
+
+    Some(format!(
+        "{}/feed/{}.atom?token={}",
+        base.trim_end_matches('/'),
+        team,
+        token
+    ))
+}
+
+/// Checks a token supplied with a feed request
+///
+/// # Arguments
+/// * `team` - Name of the team
+/// * `token` - Token supplied by the client
+fn verify_token(team: &str, token: &str) -> bool {
+    let secret = match dotenv::var("FEED_SECRET") {
+        Ok(secret) => secret,
+        Err(_) => return false,
+    };
+
+    let token = match hex::decode(token) {
+        Ok(token) => token,
+        Err(_) => return false,
+    };
+
+    match HmacSha256::new_varkey(secret.as_bytes()) {
+        Ok(mut mac) => {
+            mac.update(team.as_bytes());
+            mac.verify(&token).is_ok()
+        }
+        Err(_) => false,
+    }
+}
+
+/// Checks a token supplied for a feed, named as in its path (e.g., `<team>.atom`), for
+/// rate limits (see `ratelimit`)
+///
+/// # Arguments
+/// * `feed` - Last segment of the request's path
+/// * `token` - Token supplied by the client
+pub(crate) fn check_token(feed: &str, token: &str) -> bool {
+    feed.strip_suffix(".atom")
+        .map_or(false, |team| verify_token(team, token))
+}
+
+/// Renders history entries as an Atom feed
+///
+/// # Arguments
+/// * `team` - Name of the team
+/// * `entries` - Status changes, newest first
+fn render(team: &str, entries: &[HistoryEntry]) -> String {
+    let team = escape(team);
+    let updated = entries
+        .first()
+        .map(|entry| entry.created_at)
+        .unwrap_or_else(chrono::Utc::now);
+
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    xml.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+    xml.push_str(&format!("<title>{} Status</title>", team));
+    xml.push_str(&format!("<id>urn:statusbot:team:{}</id>", team));
+    xml.push_str(&format!("<updated>{}</updated>", updated.to_rfc3339()));
+
+    for entry in entries {
+        xml.push_str("<entry>");
+        xml.push_str(&format!(
+            "<title>{}</title>",
+            escape(entry.status.as_deref().unwrap_or(""))
+        ));
+        xml.push_str(&format!(
+            "<author><name>{}</name></author>",
+            escape(&entry.user_id)
+        ));
+        xml.push_str(&format!("<id>urn:statusbot:status:{}</id>", entry.id));
+        xml.push_str(&format!(
+            "<updated>{}</updated>",
+            entry.created_at.to_rfc3339()
+        ));
+        xml.push_str("</entry>");
+    }
+
+    xml.push_str("</feed>");
+    xml
+}
+
+/// Handle a `GET` request to the `/feed/<team>.atom` endpoint
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn feed(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let feed: String = req.param("feed").unwrap_or_default();
+    let team = match feed.strip_suffix(".atom") {
+        Some(team) => team,
+        None => return Ok(tide::Response::builder(StatusCode::NotFound).build()),
+    };
+
+    let authorized = match req.query::<FeedQuery>() {
+        Ok(query) => verify_token(team, &query.token),
+        Err(_) => false,
+    };
+
+    if !authorized {
+        return Ok(Error::Auth(format!("invalid token for feed {}", team)).into_response());
+    }
+
+    let mut db = req.read_db().await?;
+    let entries = HistoryEntry::fetch_by_team(&mut db, team, FEED_LENGTH).await?;
+
+    let last_modified = entries.first().map(|entry| entry.created_at);
+
+    Ok(caching::conditional(
+        &req,
+        "application/atom+xml",
+        render(team, &entries),
+        last_modified,
+    ))
+}
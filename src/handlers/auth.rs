@@ -0,0 +1,270 @@
+//! "Sign in with Slack" (OpenID Connect) for the web UI
+//!
+//! Signed-in users are stored in the session along with their role.  Users listed in
+//! `ADMIN_USERS` (comma separated Slack ids) are admins, everyone else is a viewer.
+//! Every session also carries a CSRF token that must be submitted (as `_csrf`) with
+//! all form posts.
+
+use crate::{outbound, State};
+use async_trait::async_trait;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tide::{
+    http::{
+        url::{form_urlencoded, Url},
+        Method,
+    },
+    Middleware, Next, Redirect, StatusCode,
+};
+
+/// Session key of the signed-in user
+const USER_KEY: &str = "user";
+
+/// Session key of the OpenID Connect `state` parameter
+const STATE_KEY: &str = "oidc_state";
+
+/// Session key of the CSRF token
+const CSRF_KEY: &str = "csrf";
+
+/// What a signed-in user is allowed to do
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Can view and manage everything
+    Admin,
+
+    /// Can only view statuses
+    Viewer,
+}
+
+/// A user that has signed in with Slack
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SessionUser {
+    /// The unique identifier provided by Slack
+    pub id: String,
+
+    /// The user's display name
+    pub name: String,
+
+    /// The user's role
+    pub role: Role,
+}
+
+/// Query string parameters Slack redirects back with
+#[derive(Debug, Deserialize)]
+struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+}
+
+/// Generates a random hex encoded token
+fn random_token() -> String {
+    hex::encode(rand::thread_rng().gen::<[u8; 32]>())
+}
+
+/// The URL Slack redirects to after a user signs in
+fn redirect_uri() -> String {
+    format!(
+        "{}/auth/callback",
+        dotenv::var("PUBLIC_URL")
+            .unwrap_or_default()
+            .trim_end_matches('/')
+    )
+}
+
+/// Determines the role of a Slack user
+///
+/// # Arguments
+/// * `user_id` - Slack ID of the user
+pub fn role_for(user_id: &str) -> Role {
+    match dotenv::var("ADMIN_USERS") {
+        Ok(admins) if admins.split(',').any(|admin| admin.trim() == user_id) => Role::Admin,
+        _ => Role::Viewer,
+    }
+}
+
+/// Returns the user signed in to this request's session, if any
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub fn session_user<S>(req: &tide::Request<S>) -> Option<SessionUser> {
+    req.session().get(USER_KEY)
+}
+
+/// Returns a hidden form input containing this session's CSRF token
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub fn csrf_input<S>(req: &tide::Request<S>) -> String {
+    let token: String = req.session().get(CSRF_KEY).unwrap_or_default();
+    format!(r#"<input type="hidden" name="_csrf" value="{}">"#, token)
+}
+
+/// Checks the CSRF token submitted with a form post
+///
+/// The body is restored after it is read so handlers can still parse the form
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+async fn verify_csrf<S>(req: &mut tide::Request<S>) -> tide::Result<bool> {
+    let body = req.body_string().await?;
+    let expected: String = req.session().get(CSRF_KEY).unwrap_or_default();
+
+    let valid = !expected.is_empty()
+        && form_urlencoded::parse(body.as_bytes())
+            .any(|(key, value)| key == "_csrf" && value == expected);
+
+    req.set_body(body);
+
+    Ok(valid)
+}
+
+/// Exchanges an authorization code for the signed-in user's id token claims
+///
+/// # Arguments
+/// * `code` - Authorization code Slack redirected back with
+async fn exchange_code(code: &str) -> anyhow::Result<Value> {
+    let form = form_urlencoded::Serializer::new(String::new())
+        .append_pair("client_id", &dotenv::var("SLACK_CLIENT_ID")?)
+        .append_pair("client_secret", &dotenv::var("SLACK_CLIENT_SECRET")?)
+        .append_pair("code", code)
+        .append_pair("redirect_uri", &redirect_uri())
+        .finish();
+
+    let mut resp = outbound::post("https://slack.com/api/openid.connect.token")
+        .set_header("Content-Type", "application/x-www-form-urlencoded")
+        .body_string(form)
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("openid.connect.token: {}", e))?;
+
+    let body: Value = resp
+        .body_json()
+        .await
+        .map_err(|e| anyhow::anyhow!("openid.connect.token: {}", e))?;
+
+    // the id token was received directly from slack over TLS, so its claims can be
+    // trusted without verifying the signature
+    let claims = body["id_token"]
+        .as_str()
+        .and_then(|token| token.split('.').nth(1))
+        .and_then(|claims| base64::decode_config(claims, base64::URL_SAFE_NO_PAD).ok())
+        .and_then(|claims| serde_json::from_slice(&claims).ok());
+
+    match claims {
+        Some(claims) => Ok(claims),
+        None => Err(anyhow::anyhow!(
+            "openid.connect.token: {}",
+            body["error"].as_str().unwrap_or("missing id token")
+        )),
+    }
+}
+
+/// Handle a `GET` request to `/auth/login`, redirecting to Slack to sign in
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn login(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let client_id = match dotenv::var("SLACK_CLIENT_ID") {
+        Ok(client_id) => client_id,
+        Err(_) => return Ok(tide::Response::builder(StatusCode::NotFound).build()),
+    };
+
+    let state = random_token();
+    req.session_mut().insert(STATE_KEY, &state)?;
+
+    let mut url = Url::parse("https://slack.com/openid/connect/authorize")?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("scope", "openid profile")
+        .append_pair("client_id", &client_id)
+        .append_pair("state", &state)
+        .append_pair("redirect_uri", &redirect_uri());
+
+    Ok(Redirect::new(url.as_str()).into())
+}
+
+/// Handle a `GET` request to `/auth/callback`, completing sign in
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn callback(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let query: CallbackQuery = req.query()?;
+    let expected: Option<String> = req.session().get(STATE_KEY);
+    req.session_mut().remove(STATE_KEY);
+
+    let code = match (query.code, query.state, expected) {
+        (Some(code), Some(state), Some(expected)) if state == expected => code,
+        _ => return Ok(tide::Response::builder(StatusCode::BadRequest).build()),
+    };
+
+    let claims = match exchange_code(&code).await {
+        Ok(claims) => claims,
+        Err(e) => {
+            tracing::error!("Sign in with Slack failed: {:?}", e);
+            return Ok(tide::Response::builder(StatusCode::Forbidden).build());
+        }
+    };
+
+    // only allow users from our workspace, if configured
+    if let Ok(team_id) = dotenv::var("SLACK_TEAM_ID") {
+        if claims["https://slack.com/team_id"] != team_id.as_str() {
+            return Ok(tide::Response::builder(StatusCode::Forbidden).build());
+        }
+    }
+
+    let id = match claims["https://slack.com/user_id"].as_str() {
+        Some(id) => id.to_owned(),
+        None => return Ok(tide::Response::builder(StatusCode::Forbidden).build()),
+    };
+
+    let user = SessionUser {
+        name: claims["name"].as_str().unwrap_or(&id).to_owned(),
+        role: role_for(&id),
+        id,
+    };
+
+    // start a fresh session to prevent session fixation
+    let session = req.session_mut();
+    session.regenerate();
+    session.insert(USER_KEY, &user)?;
+    session.insert(CSRF_KEY, random_token())?;
+
+    Ok(Redirect::see_other("/admin").into())
+}
+
+/// Handle a `POST` request to `/auth/logout`, ending the session
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn logout(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
+    if !verify_csrf(&mut req).await? {
+        return Ok(tide::Response::builder(StatusCode::Forbidden).build());
+    }
+
+    req.session_mut().destroy();
+
+    Ok(Redirect::see_other("/").into())
+}
+
+/// Middleware that requires a signed-in admin, and a valid CSRF token on form posts
+#[derive(Debug, Default)]
+pub struct RequireAdmin;
+
+#[async_trait]
+impl<S: Clone + Send + Sync + 'static> Middleware<S> for RequireAdmin {
+    async fn handle(&self, mut req: tide::Request<S>, next: Next<'_, S>) -> tide::Result {
+        match session_user(&req) {
+            Some(user) if user.role == Role::Admin => (),
+            Some(_) => return Ok(tide::Response::builder(StatusCode::Forbidden).build()),
+            None => return Ok(Redirect::new("/auth/login").into()),
+        }
+
+        if req.method() == Method::Post && !verify_csrf(&mut req).await? {
+            return Ok(tide::Response::builder(StatusCode::Forbidden).build());
+        }
+
+        Ok(next.run(req).await)
+    }
+}
@@ -0,0 +1,191 @@
+//! Status badges, shields.io-style SVGs of a user's current status for embedding in wikis
+//! and email signatures
+//!
+//! Each badge is protected by a token, the hex encoded HMAC-SHA256 of the user's Slack ID
+//! keyed with `BADGE_SECRET`.  If `BADGE_SECRET` is not set, badges are disabled.
+
+use crate::{
+    caching,
+    error::Error,
+    markup::escape,
+    models::{Availability, User},
+    HasDb, State,
+};
+use hmac::{Hmac, Mac, NewMac};
+use serde::Deserialize;
+use sha2::Sha256;
+use tide::StatusCode;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Text on the left of every badge
+const LABEL: &str = "status";
+
+/// Maximum number of characters of a status shown on a badge
+const MAX_MESSAGE_LEN: usize = 60;
+
+/// Approximate width of a character on a badge, in pixels (11px Verdana)
+const CHAR_WIDTH: usize = 7;
+
+/// Query string parameters accepted by the badge endpoint
+#[derive(Debug, Deserialize)]
+struct BadgeQuery {
+    /// Access token for this badge
+    token: String,
+}
+
+/// Computes a user's mac, returning `None` if badges are disabled
+///
+/// # Arguments
+/// * `user_id` - Slack ID of the user
+fn mac(user_id: &str) -> Option<HmacSha256> {
+    let secret = dotenv::var("BADGE_SECRET").ok()?;
+    let mut mac = HmacSha256::new_varkey(secret.as_bytes()).ok()?;
+    mac.update(user_id.as_bytes());
+
+    Some(mac)
+}
+
+/// Builds the URL of a user's badge, returning `None` if badges are disabled
+///
+/// The URL is relative unless `PUBLIC_URL` is set
+///
+/// # Arguments
+/// * `user_id` - Slack ID of the user
+pub fn badge_url(user_id: &str) -> Option<String> {
+    let token = hex::encode(mac(user_id)?.finalize().into_bytes());
+    let base = dotenv::var("PUBLIC_URL").unwrap_or_default();
+
+    Some(format!(
+        "{}/badge/{}.svg?token={}",
+        base.trim_end_matches('/'),
+        user_id,
+        token
+    ))
+}
+
+/// Checks a token supplied with a badge request
+///
+/// # Arguments
+/// * `user_id` - Slack ID of the user
+/// * `token` - Token supplied by the client
+fn verify_token(user_id: &str, token: &str) -> bool {
+    match (mac(user_id), hex::decode(token)) {
+        (Some(mac), Ok(token)) => mac.verify(&token).is_ok(),
+        _ => false,
+    }
+}
+
+/// Checks a token supplied for a badge, named as in its path (e.g., `<user_id>.svg`), for
+/// rate limits (see `ratelimit`)
+///
+/// # Arguments
+/// * `badge` - Last segment of the request's path
+/// * `token` - Token supplied by the client
+pub(crate) fn check_token(badge: &str, token: &str) -> bool {
+    badge
+        .strip_suffix(".svg")
+        .map_or(false, |user_id| verify_token(user_id, token))
+}
+
+/// Renders a user's status as a badge
+///
+/// Emoji shortcodes can't be rendered, so the location and availability are spelled out
+/// (e.g., `office · busy · In meetings`).
+///
+/// # Arguments
+/// * `user` - The user whose status to render
+fn render(user: &User) -> String {
+    let parts: Vec<String> = user
+        .location()
+        .map(|location| match user.site() {
+            Some(site) => format!("{} ({})", location.as_str(), site),
+            None => location.as_str().to_owned(),
+        })
+        .into_iter()
+        .chain(user.availability().map(|a| a.as_str().to_owned()))
+        .chain(user.status.clone())
+        .collect();
+
+    let mut message = if parts.is_empty() {
+        "not set".to_owned()
+    } else {
+        parts.join(" · ")
+    };
+    if message.chars().count() > MAX_MESSAGE_LEN {
+        message = message
+            .chars()
+            .take(MAX_MESSAGE_LEN - 1)
+            .collect::<String>()
+            + "…";
+    }
+
+    let color = match user.availability() {
+        Some(Availability::Available) => "#4c1",
+        Some(Availability::Busy) => "#fe7d37",
+        Some(Availability::Ooo) => "#9f9f9f",
+        None => "#007ec6",
+    };
+
+    let label_width = LABEL.len() * CHAR_WIDTH + 10;
+    let message_width = message.chars().count() * CHAR_WIDTH + 10;
+    let width = label_width + message_width;
+    let message = escape(&message);
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+<title>{label}: {message}</title>
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+<text x="{label_x}" y="14">{label}</text><text x="{message_x}" y="14">{message}</text>
+</g>
+</svg>"##,
+        width = width,
+        label = LABEL,
+        message = message,
+        label_width = label_width,
+        message_width = message_width,
+        color = color,
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2,
+    )
+}
+
+/// Handle a `GET` request to the `/badge/<user>.svg` endpoint
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn badge(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let badge: String = req.param("badge").unwrap_or_default();
+    let user_id = match badge.strip_suffix(".svg") {
+        Some(user_id) => user_id,
+        None => return Ok(tide::Response::builder(StatusCode::NotFound).build()),
+    };
+
+    let authorized = match req.query::<BadgeQuery>() {
+        Ok(query) => verify_token(user_id, &query.token),
+        Err(_) => false,
+    };
+
+    if !authorized {
+        return Ok(Error::Auth(format!("invalid token for badge {}", user_id)).into_response());
+    }
+
+    let mut db = req.read_db().await?;
+    let user = match User::fetch(&mut db, user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) | Err(Error::Parse(_)) => {
+            return Ok(Error::NotFound(format!("User {}", user_id)).into_response())
+        }
+        Err(e) => return Ok(e.into_response()),
+    };
+
+    Ok(caching::conditional(
+        &req,
+        "image/svg+xml",
+        render(&user),
+        None,
+    ))
+}
@@ -1,44 +1,43 @@
 use crate::{
-    models::{Team, User},
-    HasDb, State,
+    announce, approval, audit, changes, coverage,
+    error::Error,
+    extract::{AppState, Db, Form},
+    feed::StatusChange,
+    feedback, fields,
+    handlers::{
+        atom,
+        auth::{self, Role},
+        badge,
+    },
+    handoff, issues,
+    models::{
+        compact_status, plain_status, validate_phone, ApprovalChain, AuditEntry, AutoReply,
+        Availability, BulkStatus, Calendar, CommandStat, ContactChannel, ContactPreference,
+        HistoryEntry, Leave, Location, Member, MemberRole, Profile, Shift, Site, SlackUserId,
+        StatusAck, Team, TeamField, User,
+    },
+    muster, notify, profiles,
+    render::{ResponseDoc, Text},
+    response::SlashResponse,
+    rota, runtime, setup, suggest, wizard, SqlConn, State,
 };
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
 use serde::Deserialize;
-use serde_json::{json, Value};
-use std::borrow::Cow;
-use tide::StatusCode;
-
-macro_rules! header {
-    ($container:expr, $text:expr) => {
-        $container.push(serde_json::json!({
-            "type": "header",
-            "text": {
-                "type": "plain_text",
-                "text": $text,
-            }
-        }))
-    }
-}
+use std::{collections::HashMap, time::Instant};
 
-macro_rules! mrkdwn {
-    ($container:expr, $text:expr) => {
-        $container.push(serde_json::json!({
-            "type": "section",
-            "text": {
-                "type": "mrkdwn",
-                "text": $text,
-            }
-        }))
-    }
-}
+/// Most site headings shown when grouping a page of users by site
+///
+/// Each heading is a block, and Slack allows at most 50 blocks per message
+const MAX_SITE_GROUPS: usize = 5;
 
-macro_rules! divider {
-    ($container:expr) => {
-        $container.push(serde_json::json!({ "type": "divider" }))
-    }
-}
+/// Number of teams or members shown per page
+///
+/// Slack allows at most 50 blocks per message, leaving room for the header and footer
+const PAGE_SIZE: i64 = 40;
 
 #[derive(Debug, Deserialize)]
-struct SlashCommand {
+pub struct SlashCommand {
     // Deprecated verification token (use signed secrets instead)
     pub token: String,
 
@@ -77,11 +76,18 @@ pub enum SlashAction<'a> {
     /// Shows a user's last set status
     ShowUser { user: &'a str },
 
-    /// Shows all members on a team statuses
-    ShowTeam { team: &'a str },
+    /// Shows all members on a team statuses, a page at a time
+    ShowTeam { team: &'a str, page: i64 },
 
-    /// List all teams (no members)
-    ListTeams,
+    /// Shows the statuses all members of a team had at the end of a day, a page at a time
+    ShowTeamOn {
+        team: &'a str,
+        day: NaiveDate,
+        page: i64,
+    },
+
+    /// List all teams (no members), a page at a time
+    ListTeams { page: i64 },
 
     /// Creates a new team
     CreateTeam { name: &'a str },
@@ -95,8 +101,802 @@ pub enum SlashAction<'a> {
     /// Removes a member from an existing team
     RemoveMember { team: &'a str, user: &'a str },
 
-    /// A specific error message is parsing failed
-    ParsingFailed(Cow<'a, str>),
+    /// Shows the URL of a team's Atom feed
+    TeamFeed { team: &'a str },
+
+    /// Changes the role of a member of a team
+    SetRole {
+        team: &'a str,
+        user: &'a str,
+        role: MemberRole,
+    },
+
+    /// Adds a guest who isn't on Slack to a team
+    AddGuest { team: &'a str, name: String },
+
+    /// Removes a guest from a team
+    RemoveGuest { team: &'a str, name: String },
+
+    /// Sets the status of a guest
+    SetGuestStatus {
+        team: &'a str,
+        name: String,
+        status: String,
+    },
+
+    /// Sets a team's description
+    Describe { team: &'a str, description: String },
+
+    /// Sets a team's emoji icon
+    SetIcon { team: &'a str, icon: &'a str },
+
+    /// Binds a channel to a team
+    BindChannel { team: &'a str, channel: &'a str },
+
+    /// Turns status change notifications in a team's bound channel on or off
+    SetNotify { team: &'a str, notify: bool },
+
+    /// Sets (or clears) the minimum number of members a team needs on site, and the days
+    /// it applies on
+    SetCoverage {
+        team: &'a str,
+        min: Option<i64>,
+        days: i64,
+    },
+
+    /// Shows the chain of approvers of a team's leave
+    ShowApprovers { team: &'a str },
+
+    /// Sets (or, if no approvers are given, clears) the chain of approvers of a team's leave,
+    /// and the hours each has to decide before the next is asked
+    SetApprovers {
+        team: &'a str,
+        approvers: Vec<&'a str>,
+        timeout_hours: i64,
+    },
+
+    /// Shows where a user was on each day of a week
+    Timeline {
+        user: &'a str,
+        week: Option<&'a str>,
+    },
+
+    /// Sets the free-text note of the user running the command
+    SetNote { note: String },
+
+    /// Sets where (and at which site) the user running the command is working
+    SetLocation {
+        location: Location,
+        site: Option<&'a str>,
+    },
+
+    /// Lists all sites
+    ListSites,
+
+    /// Creates a new site
+    CreateSite { name: &'a str },
+
+    /// Deletes an existing site
+    DeleteSite { name: &'a str },
+
+    /// Sets a site's timezone
+    SetSiteTz { site: &'a str, tz: Tz },
+
+    /// Sets a site's address
+    SetSiteAddress { site: &'a str, address: String },
+
+    /// Sets how many desks can be booked at a site each day (`None` for unlimited)
+    SetSiteCapacity {
+        site: &'a str,
+        capacity: Option<i64>,
+    },
+
+    /// Books (or cancels the booking of) a desk at a site for the user running the command
+    Book {
+        site: &'a str,
+        day: Option<&'a str>,
+        cancel: bool,
+    },
+
+    /// Lists everyone working from the office (optionally at one site), a page at a time
+    ListOffice { site: Option<&'a str>, page: i64 },
+
+    /// Sets whether the user running the command can be reached
+    SetAvailability { availability: Availability },
+
+    /// Shows who is on each of a team's shifts today
+    ShowShifts { team: &'a str },
+
+    /// Shows the calendars the user running the command imports leave from, and their
+    /// upcoming leave
+    ShowCalendars,
+
+    /// Registers (or unregisters) a calendar to import the user's leave from
+    SetCalendar { url: &'a str, remove: bool },
+
+    /// Records leave for the user running the command
+    AddLeave {
+        from: Option<&'a str>,
+        to: Option<&'a str>,
+    },
+
+    /// Cancels the leave of the user running the command covering a day
+    CancelLeave { day: Option<&'a str> },
+
+    /// Creates a recurring shift for a team
+    CreateShift {
+        team: &'a str,
+        name: &'a str,
+        days: i64,
+        hours: Option<&'a str>,
+    },
+
+    /// Deletes one of a team's shifts
+    DeleteShift { team: &'a str, name: &'a str },
+
+    /// Assigns a member of a team to (or removes them from) one of its shifts
+    AssignShift {
+        team: &'a str,
+        name: &'a str,
+        user: &'a str,
+        remove: bool,
+    },
+
+    /// Shows the URL of the status badge of the user running the command
+    ShowBadge,
+
+    /// Asks every member of a team whether they're safe, summarizing their responses in the
+    /// channel the command was run in
+    Muster { team: &'a str },
+
+    /// Sends an announcement to a team, DMing each member or posting to its bound channel
+    Announce {
+        team: &'a str,
+        message: String,
+        channel: bool,
+    },
+
+    /// Shows whether the last announcement sent to a team was delivered
+    ShowAnnouncement { team: &'a str },
+
+    /// Shows whether mentions of the user running the command are replied to while they're
+    /// on leave
+    ShowAutoReply,
+
+    /// Turns replies to mentions of the user running the command while they're on leave on
+    /// (with who to contact instead) or off
+    SetAutoReply {
+        enabled: bool,
+        contact: Option<&'a str>,
+    },
+
+    /// Shows who covers for the user running the command while they're away
+    ShowDelegate,
+
+    /// Sets (or clears, if `None`) who covers for the user running the command while they're
+    /// away
+    SetDelegate { user: Option<&'a str> },
+
+    /// Shows how the user running the command is notified while they're away from Slack
+    ShowContact,
+
+    /// Sets how the user running the command is notified while they're away from Slack
+    SetContact {
+        channel: ContactChannel,
+        phone: Option<&'a str>,
+    },
+
+    /// Sets a status for every member of a team, today or on a later day
+    SetAll {
+        team: &'a str,
+        status: String,
+        day: Option<String>,
+    },
+
+    /// Undoes the last status set for every member of a team
+    UndoSetAll { team: &'a str },
+
+    /// Acknowledges a member's current status
+    AckStatus { team: &'a str, user: &'a str },
+
+    /// Shows the custom fields a team collects from its members
+    ShowFields { team: &'a str },
+
+    /// Adds a custom field to a team
+    AddField { team: &'a str, label: String },
+
+    /// Removes a custom field from a team
+    RemoveField { team: &'a str, label: String },
+
+    /// Opens a modal for the user running the command to enter custom field values in
+    EditFields,
+
+    /// Opens a modal that builds a command from menus
+    Wizard,
+
+    /// Opens a modal that walks a new workspace through its initial configuration
+    Setup,
+
+    /// Reports a problem to the bot's operators
+    Feedback { message: String },
+}
+
+/// Extracts a channel id from a channel typed in a command
+///
+/// Slack sends channels typed in commands as `<#C012AB3CD|general>`
+///
+/// # Arguments
+/// * `text` - A channel typed in a command
+fn parse_channel(text: &str) -> Option<&str> {
+    let id = text
+        .strip_prefix("<#")?
+        .trim_end_matches('>')
+        .split('|')
+        .next()?;
+
+    if id.is_empty() {
+        None
+    } else {
+        Some(id)
+    }
+}
+
+/// Parses the week to show in a timeline, returning the Monday it starts on
+///
+/// Weeks may be typed as `this` (the default), `last`, or any date in the week
+/// (`YYYY-MM-DD`)
+///
+/// # Arguments
+/// * `arg` - Week typed by the user, if any
+/// * `today` - Today's date, in the user's timezone
+fn parse_week(arg: Option<&str>, today: NaiveDate) -> Result<NaiveDate, Error> {
+    let day = match arg {
+        None | Some("this") => today,
+        Some("last") => today - Duration::weeks(1),
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+            Error::Parse(format!(
+                "`{}` is not a valid week. Please specify `this`, `last`, or a date (`YYYY-MM-DD`)",
+                date
+            ))
+        })?,
+    };
+
+    Ok(day - Duration::days(day.weekday().num_days_from_monday() as i64))
+}
+
+/// Parses a date typed in a command (`YYYY-MM-DD`)
+///
+/// # Arguments
+/// * `arg` - Date typed by the user, if any
+fn parse_date(arg: Option<&str>) -> Result<NaiveDate, Error> {
+    match arg {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+            Error::Parse(format!(
+                "`{}` is not a valid date. Please specify a date (`YYYY-MM-DD`)",
+                date
+            ))
+        }),
+        None => Err(Error::Parse("Please specify a date (`YYYY-MM-DD`)".into())),
+    }
+}
+
+/// Parses a day typed in a command
+///
+/// Days may be typed as `today` (the default), `tomorrow`, the name of a weekday (the
+/// next one, including today), or a date (`YYYY-MM-DD`)
+///
+/// # Arguments
+/// * `arg` - Day typed by the user, if any
+/// * `today` - Today's date
+fn parse_day(arg: Option<&str>, today: NaiveDate) -> Result<NaiveDate, Error> {
+    let arg = match arg {
+        None => return Ok(today),
+        Some(arg) => arg,
+    };
+
+    match arg.to_lowercase().as_str() {
+        "today" => Ok(today),
+        "tomorrow" => Ok(today + Duration::days(1)),
+        day => match day.parse::<chrono::Weekday>() {
+            Ok(weekday) => {
+                let ahead = weekday.num_days_from_monday() as i64 + 7
+                    - today.weekday().num_days_from_monday() as i64;
+                Ok(today + Duration::days(ahead % 7))
+            }
+            Err(_) => NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| {
+                Error::Parse(format!(
+                    "`{}` is not a valid day. Please specify `today`, `tomorrow`, a weekday, or a date (`YYYY-MM-DD`)",
+                    arg
+                ))
+            }),
+        },
+    }
+}
+
+/// Returns today's date at a site, in the site's timezone (or UTC if it doesn't have one)
+///
+/// # Arguments
+/// * `site` - The site
+fn today_at(site: &Site) -> NaiveDate {
+    Utc::now()
+        .with_timezone(&site.timezone().unwrap_or(Tz::UTC))
+        .date()
+        .naive_local()
+}
+
+/// Returns the instant a day starts at in a timezone
+///
+/// # Arguments
+/// * `tz` - The timezone
+/// * `day` - The day
+fn start_of_day(tz: Tz, day: NaiveDate) -> DateTime<Utc> {
+    match tz.from_local_datetime(&day.and_hms(0, 0, 0)).earliest() {
+        Some(start) => start.with_timezone(&Utc),
+        None => Utc.from_utc_datetime(&day.and_hms(0, 0, 0)),
+    }
+}
+
+/// Builds one field per day of a week, showing the status the user had at the end of each
+/// day
+///
+/// Days without a status update show the last status set before them
+///
+/// # Arguments
+/// * `entries` - Statuses the user set, oldest first
+/// * `tz` - The user's timezone, which days are in
+/// * `monday` - First day of the week
+/// * `today` - Today's date, in the user's timezone
+fn timeline_fields(
+    entries: &[HistoryEntry],
+    tz: Tz,
+    monday: NaiveDate,
+    today: NaiveDate,
+) -> Vec<(Text, Text)> {
+    let mut entries = entries.iter().peekable();
+    let (mut location, mut availability, mut note) = (None, None, None);
+
+    (0..7)
+        .map(|offset| monday + Duration::days(offset))
+        .map(|day| {
+            let end = start_of_day(tz, day + Duration::days(1));
+            while let Some(entry) = entries.peek() {
+                if entry.created_at >= end {
+                    break;
+                }
+
+                location = entry.location().or(location);
+                availability = entry.availability().or(availability);
+                note = entry.status.as_deref().or(note);
+                entries.next();
+            }
+
+            let status = match compact_status(location, None, availability, note) {
+                _ if day > today => Text::from("—"),
+                Some(compact) => {
+                    let plain = plain_status(location, None, availability, note);
+                    Text::new().slack(compact, plain.unwrap_or_default())
+                }
+                None => Text::new().emphasis("no status"),
+            };
+
+            (Text::from(day.format("%a %b %-d").to_string()), status)
+        })
+        .collect()
+}
+
+/// Adds a heading for the site users are at whenever it changes, if the users (already
+/// ordered by site) are at only a few sites
+///
+/// # Arguments
+/// * `doc` - Response to add the users to
+/// * `sites` - Registered sites
+/// * `lines` - Site of each user, and the line shown for them, in the order they're shown
+fn group_by_site(doc: &mut ResponseDoc, sites: &[Site], lines: Vec<(Option<String>, Text)>) {
+    let mut groups: Vec<&Option<String>> = lines.iter().map(|(site, _)| site).collect();
+    groups.dedup();
+    let grouped = groups.iter().any(|site| site.is_some()) && groups.len() <= MAX_SITE_GROUPS;
+
+    let mut current = None;
+    for (site, line) in lines {
+        if grouped && current.as_ref() != Some(&site) {
+            let office = Text::new().slack(":office: ", "");
+            match sites.iter().find(|s| Some(&s.name) == site.as_ref()) {
+                Some(s) => doc.context(office.append(mrkdwn(&s.describe()))),
+                None => match &site {
+                    Some(name) => doc.context(office.strong(name)),
+                    None => doc.context("No site"),
+                },
+            };
+            current = Some(site.clone());
+        }
+
+        doc.section(line);
+    }
+}
+
+/// Lays out text that's already in mrkdwn (e.g., described by a model), shown as is
+/// everywhere
+///
+/// # Arguments
+/// * `text` - The text
+fn mrkdwn(text: &str) -> Text {
+    Text::new().slack(text, text)
+}
+
+/// Lays out that something wasn't found (e.g., `Team *ops* not found`)
+///
+/// # Arguments
+/// * `kind` - What wasn't found (e.g., `Team`)
+/// * `name` - Name of what wasn't found
+fn not_found(kind: &str, name: &str) -> Text {
+    Text::from(format!("{} ", kind))
+        .strong(name)
+        .text(" not found")
+}
+
+/// Lays out that something couldn't be saved
+///
+/// # Arguments
+/// * `kind` - What couldn't be saved (e.g., `Team`)
+/// * `name` - Name of what couldn't be saved
+fn update_failed(kind: &str, name: &str) -> Text {
+    Text::from(format!("Failed to update {} ", kind))
+        .strong(name)
+        .text(". Please try again later")
+}
+
+/// Lays out that a user typed in a command isn't a valid user
+///
+/// # Arguments
+/// * `user` - The user typed in the command
+fn invalid_user(user: &str) -> Text {
+    Text::new().strong(user).text(" is not a valid user")
+}
+
+/// Lays out that a user isn't a member of a team
+///
+/// # Arguments
+/// * `user_id` - Slack ID of the user
+/// * `team` - The team
+fn not_a_member(user_id: &str, team: &Team) -> Text {
+    Text::new()
+        .mention(user_id, user_id)
+        .text(format!(" is not a member of team {}", team.name))
+}
+
+/// Lays out the heading of a team's statuses: its name, what it does, and who created it
+///
+/// # Arguments
+/// * `doc` - Response to add the heading to
+/// * `team` - The team
+fn team_heading(doc: &mut ResponseDoc, team: &Team) {
+    doc.header(format!("{} Status", team.display_name()));
+    if let Some(description) = &team.description {
+        doc.context(description.as_str());
+    }
+    if let Some(created_by) = &team.created_by {
+        let created_at = team
+            .created_at
+            .map(|at| format!(" on {}", at.format("%Y-%m-%d")))
+            .unwrap_or_default();
+        doc.context(
+            Text::new()
+                .text("Created by ")
+                .mention(created_by, created_by)
+                .text(created_at),
+        );
+    }
+    doc.divider();
+}
+
+/// A member of a team, as shown in the team's statuses
+struct MemberLine {
+    /// Slack ID of the member
+    id: String,
+
+    /// Name of the member, if they're a guest who isn't on Slack
+    guest: Option<String>,
+
+    /// The member's role within the team
+    role: MemberRole,
+
+    /// Emoji showing whether the member is active on Slack, if known
+    presence: Option<&'static str>,
+
+    /// The member's status as shown on Slack (with emoji and linked issues), and in words,
+    /// if they've set one
+    status: Option<(String, String)>,
+
+    /// If the member's current status has been acknowledged
+    acked: bool,
+
+    /// The meeting the member is in, described in words (e.g., `in a meeting until 14:30`)
+    meeting: Option<String>,
+
+    /// Slack ID of who covers for the member while they're out of office
+    covering: Option<String>,
+
+    /// Values the member entered for the team's custom fields (e.g., `Badge number: 1234`)
+    details: Option<String>,
+}
+
+impl MemberLine {
+    /// Creates the line of a member showing only their role and status
+    ///
+    /// # Arguments
+    /// * `member` - The member
+    fn new(member: &Member) -> Self {
+        let status = member
+            .compact_status()
+            .map(|compact| (compact, member.plain_status().unwrap_or_default()));
+
+        MemberLine {
+            id: member.id.clone(),
+            guest: member.name.clone().filter(|_| member.external),
+            role: member.role(),
+            presence: None,
+            status,
+            acked: false,
+            meeting: None,
+            covering: None,
+            details: None,
+        }
+    }
+
+    /// Lays out the line
+    ///
+    /// # Arguments
+    /// * `past` - If the status is the one the member had at the end of an earlier day
+    fn to_text(&self, past: bool) -> Text {
+        // guests aren't on Slack, so can't be mentioned
+        let mut text = match &self.guest {
+            Some(name) => Text::new().strong(name).text(" ").emphasis("guest"),
+            None => Text::new().mention(&self.id, &self.id),
+        };
+
+        text = match self.role {
+            MemberRole::Lead => text.slack(" :star:", "").text(" ").emphasis("lead"),
+            MemberRole::Viewer => text.text(" ").emphasis("viewer"),
+            MemberRole::Member => text,
+        };
+        if let Some(presence) = self.presence {
+            text = text.slack(format!(" {}", presence), "");
+        }
+
+        text = match &self.status {
+            Some((compact, plain)) => text.text(": ").slack(compact.as_str(), plain.as_str()),
+            None if past => text.text(" had not set a status"),
+            None => text.text(" has not set a status"),
+        };
+        if self.acked {
+            text = text.slack(" :heavy_check_mark:", " (acknowledged)");
+        }
+        if let Some(meeting) = &self.meeting {
+            text = text.text(" ").emphasis(format!("({})", meeting));
+        }
+        if let Some(covering) = &self.covering {
+            text = text
+                .text(" (covering: ")
+                .mention(covering, covering)
+                .text(")");
+        }
+        if let Some(details) = &self.details {
+            text = text.text(format!(" ({})", details));
+        }
+
+        text
+    }
+}
+
+/// Splits a name, which may be quoted to include spaces, from the start of some text,
+/// returning the name and the rest of the text
+///
+/// # Arguments
+/// * `text` - Text starting with the name
+fn split_name(text: &str) -> Option<(String, String)> {
+    let is_quote = |c| c == '"' || c == '\u{201c}' || c == '\u{201d}';
+    let text = text.trim();
+
+    let (name, rest) = match text.chars().next() {
+        Some(quote) if is_quote(quote) => {
+            let inner = &text[quote.len_utf8()..];
+            let end = inner.find(is_quote)?;
+            let close = inner[end..].chars().next()?.len_utf8();
+            (&inner[..end], &inner[end + close..])
+        }
+        _ => {
+            let mut parts = text.splitn(2, char::is_whitespace);
+            (parts.next()?, parts.next().unwrap_or_default())
+        }
+    };
+
+    match name.trim() {
+        "" => None,
+        name => Some((name.to_owned(), rest.trim().to_owned())),
+    }
+}
+
+/// Fetches a team the user running a command may manage (its guests, for now)
+///
+/// Admins and leads of the team may manage it.  Fails with `Error::NotFound` if the team
+/// doesn't exist, or `Error::Auth` if the user may not manage it
+///
+/// # Arguments
+/// * `db` - Connection to the database
+/// * `team` - Name of the team
+/// * `user_id` - Slack ID of the user
+async fn managed_team(db: &mut SqlConn, team: &str, user_id: &str) -> Result<Team, Error> {
+    let team = match Team::fetch(db, team).await {
+        Some(team) => team,
+        None => return Err(Error::NotFound(format!("Team *{}*", team))),
+    };
+
+    if auth::role_for(user_id) == Role::Admin {
+        return Ok(team);
+    }
+
+    let role = match User::new(user_id) {
+        Ok(user) => team.member_role(db, &user).await?,
+        Err(_) => None,
+    };
+
+    match role {
+        Some(MemberRole::Lead) => Ok(team),
+        _ => Err(Error::Auth(format!(
+            "only leads may manage team {}",
+            team.name
+        ))),
+    }
+}
+
+/// Fetches a team the user running a command owns
+///
+/// Admins and the user who created the team own it.  Fails with `Error::NotFound` if the
+/// team doesn't exist, or `Error::Auth` if the user doesn't own it
+///
+/// # Arguments
+/// * `db` - Connection to the database
+/// * `team` - Name of the team
+/// * `user_id` - Slack ID of the user
+async fn owned_team(db: &mut SqlConn, team: &str, user_id: &str) -> Result<Team, Error> {
+    let team = match Team::fetch(db, team).await {
+        Some(team) => team,
+        None => return Err(Error::NotFound(format!("Team *{}*", team))),
+    };
+
+    if auth::role_for(user_id) == Role::Admin || team.created_by.as_deref() == Some(user_id) {
+        Ok(team)
+    } else {
+        Err(Error::Auth(format!(
+            "only the owner of team {} may do that",
+            team.name
+        )))
+    }
+}
+
+/// Parses a URL typed in a command, which Slack wraps in angle brackets (e.g.,
+/// `<https://example.com/cal.ics>` or `<https://example.com|label>`)
+///
+/// # Arguments
+/// * `arg` - The URL typed by the user
+fn parse_url(arg: &str) -> &str {
+    arg.trim_start_matches('<')
+        .trim_end_matches('>')
+        .split('|')
+        .next()
+        .unwrap_or_default()
+}
+
+/// Returns a user's timezone (or UTC if their timezone isn't known)
+///
+/// # Arguments
+/// * `db` - Connection to the database
+/// * `user_id` - Slack ID of the user
+async fn user_tz(db: &mut SqlConn, user_id: &str) -> Tz {
+    Profile::fetch(db, user_id)
+        .await
+        .and_then(|profile| profile.tz)
+        .and_then(|tz| tz.parse::<Tz>().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// Returns today's date in a user's timezone (or UTC if their timezone isn't known)
+///
+/// # Arguments
+/// * `db` - Connection to the database
+/// * `user_id` - Slack ID of the user
+async fn user_today(db: &mut SqlConn, user_id: &str) -> NaiveDate {
+    let tz = user_tz(db, user_id).await;
+    Utc::now().with_timezone(&tz).date().naive_local()
+}
+
+/// Joins the remaining words of a command, stripping surrounding (straight or curly) quotes
+///
+/// # Arguments
+/// * `words` - The remaining words of the command
+fn quoted_text<'a>(words: impl Iterator<Item = &'a str>) -> String {
+    let text = words.collect::<Vec<_>>().join(" ");
+    text.trim_matches(|c| c == '"' || c == '\u{201c}' || c == '\u{201d}')
+        .trim()
+        .to_owned()
+}
+
+/// Parses an optional, 1-based page number
+///
+/// # Arguments
+/// * `arg` - Page number typed by the user, if any
+fn parse_page(arg: Option<&str>) -> Result<i64, Error> {
+    match arg {
+        None => Ok(1),
+        Some(page) => match page.parse::<i64>() {
+            Ok(page) if page >= 1 => Ok(page),
+            _ => Err(Error::Parse(format!(
+                "`{}` is not a valid page number",
+                page
+            ))),
+        },
+    }
+}
+
+/// Adds a footer pointing to the next page, if there is one
+///
+/// # Arguments
+/// * `doc` - Response to add the footer to
+/// * `page` - Page being shown
+/// * `has_more` - If there are more items after this page
+/// * `command` - Arguments to `/location` that show the next page, without the page number
+fn page_footer(doc: &mut ResponseDoc, page: i64, has_more: bool, command: &str) {
+    if has_more {
+        doc.context(
+            Text::new()
+                .text(format!("Page {}. Use ", page))
+                .code(format!("/location {} {}", command, page + 1))
+                .text(" to see more"),
+        );
+    } else if page > 1 {
+        doc.context(format!("Page {}", page));
+    }
+}
+
+/// Extracts an email address from command text, if the text is one
+///
+/// Slack sends emails typed in commands as `<mailto:alice@example.com|alice@example.com>`
+///
+/// # Arguments
+/// * `text` - A user typed in a command
+fn parse_email(text: &str) -> Option<&str> {
+    let email = match text.strip_prefix("<mailto:") {
+        Some(email) => email.trim_end_matches('>').split('|').next()?,
+        None => text,
+    };
+
+    match email.find('@') {
+        Some(at) if at > 0 && !email.starts_with('<') => Some(email),
+        _ => None,
+    }
+}
+
+/// Resolves a user typed in a command to something `User::fetch` accepts
+///
+/// Users may be typed as mentions, Slack IDs, or email addresses
+///
+/// # Arguments
+/// * `db` - Connection to the database
+/// * `user` - The user typed in the command
+async fn resolve_user(db: &mut SqlConn, user: &str) -> Result<String, Error> {
+    match parse_email(user) {
+        Some(email) => match profiles::lookup_by_email(db, email).await {
+            Ok(id) => Ok(id),
+            Err(e) => {
+                tracing::warn!("Failed to look up user by email {}: {:?}", email, e);
+                Err(Error::NotFound(format!(
+                    "Slack user with email *{}*",
+                    email
+                )))
+            }
+        },
+        None => Ok(user.to_owned()),
+    }
 }
 
 impl<'a> SlashAction<'a> {
@@ -106,29 +906,29 @@ impl<'a> SlashAction<'a> {
     /// * `text` - Text received from `SlashCommand`
     ///
     /// # Examples
-    /// ```rust
-    /// let action = SlashAction::parse("team create Senate");
-    /// assert_eq!(action, SlashAction::CreateTeam { team: "Senate" });
+    /// ```ignore
+    /// let action = SlashAction::parse("team create Senate")?;
+    /// assert_eq!(action, SlashAction::CreateTeam { name: "Senate" });
     /// ```
-    pub fn parse(text: &'a str) -> anyhow::Result<Self> {
+    pub fn parse(text: &'a str) -> Result<Self, Error> {
         // first split text by whitespace, then iterate over it
         let mut iter = text.split_whitespace();
         match iter.next() {
             Some("team") => match iter.next() {
                 Some("create") => match iter.next() {
                     Some(team_name) => Ok(SlashAction::CreateTeam { name: team_name }),
-                    None => Ok(SlashAction::ParsingFailed(
+                    None => Err(Error::Parse(
                         "Please specify a team name when creating a team".into(),
                     )),
                 },
                 Some("delete") => match iter.next() {
                     Some(team_name) => Ok(SlashAction::DeleteTeam { name: team_name }),
-                    None => Ok(SlashAction::ParsingFailed(
-                        "Please specify a team name to delete".into(),
-                    )),
+                    None => Err(Error::Parse("Please specify a team name to delete".into())),
                 },
 
-                Some("list") => Ok(SlashAction::ListTeams),
+                Some("list") => Ok(SlashAction::ListTeams {
+                    page: parse_page(iter.next())?,
+                }),
 
                 Some(team_name) => match iter.next() {
                     Some("add") => match iter.next() {
@@ -136,162 +936,2718 @@ impl<'a> SlashAction<'a> {
                             team: team_name,
                             user,
                         }),
-                        None => Ok(SlashAction::ParsingFailed(
-                            format!("Please specify a user to add to team {}", team_name).into(),
-                        )),
+                        None => Err(Error::Parse(format!(
+                            "Please specify a user to add to team {}",
+                            team_name
+                        ))),
                     },
                     Some("del") => match iter.next() {
                         Some(user) => Ok(SlashAction::RemoveMember {
                             team: team_name,
                             user,
                         }),
-                        None => Ok(SlashAction::ParsingFailed(
-                            format!("Please specify a user to delete from team {}", team_name)
-                                .into(),
-                        )),
+                        None => Err(Error::Parse(format!(
+                            "Please specify a user to delete from team {}",
+                            team_name
+                        ))),
                     },
-                    _ => Ok(SlashAction::ParsingFailed(
-                        "Please specify either the `add` or `del` command".into(),
-                    )),
+                    Some("feed") => Ok(SlashAction::TeamFeed { team: team_name }),
+                    Some(role @ "lead") | Some(role @ "member") | Some(role @ "viewer") => {
+                        match iter.next() {
+                            Some(user) => Ok(SlashAction::SetRole {
+                                team: team_name,
+                                user,
+                                role: role.parse()?,
+                            }),
+                            None => Err(Error::Parse(format!(
+                                "Please specify a member of team {} to make a {}",
+                                team_name, role
+                            ))),
+                        }
+                    }
+                    Some("guest") => {
+                        let text = iter.collect::<Vec<_>>().join(" ");
+                        let (command, rest) = split_name(&text).unwrap_or_default();
+                        match (command.as_str(), split_name(&rest)) {
+                            ("add", Some((name, _))) => Ok(SlashAction::AddGuest {
+                                team: team_name,
+                                name,
+                            }),
+                            ("del", Some((name, _))) => Ok(SlashAction::RemoveGuest {
+                                team: team_name,
+                                name,
+                            }),
+                            ("add", None) | ("del", None) | ("", _) => Err(Error::Parse(
+                                "Please specify the guest's name (e.g., `\"Jane Doe\"`)".into(),
+                            )),
+                            _ => {
+                                let status = quoted_text(rest.split_whitespace());
+                                if status.is_empty() {
+                                    Err(Error::Parse(format!(
+                                        "Please specify a status for {}",
+                                        command
+                                    )))
+                                } else {
+                                    Ok(SlashAction::SetGuestStatus {
+                                        team: team_name,
+                                        name: command,
+                                        status,
+                                    })
+                                }
+                            }
+                        }
+                    }
+                    Some("describe") => {
+                        let description = quoted_text(iter);
+                        if description.is_empty() {
+                            Err(Error::Parse(format!(
+                                "Please specify a description for team {}",
+                                team_name
+                            )))
+                        } else {
+                            Ok(SlashAction::Describe {
+                                team: team_name,
+                                description,
+                            })
+                        }
+                    }
+                    Some("icon") => match iter.next() {
+                        Some(icon)
+                            if icon.len() > 2 && icon.starts_with(':') && icon.ends_with(':') =>
+                        {
+                            Ok(SlashAction::SetIcon {
+                                team: team_name,
+                                icon,
+                            })
+                        }
+                        _ => Err(Error::Parse(
+                            "Please specify an emoji for the icon (e.g., `:rocket:`)".into(),
+                        )),
+                    },
+                    Some("channel") => match iter.next().and_then(parse_channel) {
+                        Some(channel) => Ok(SlashAction::BindChannel {
+                            team: team_name,
+                            channel,
+                        }),
+                        None => Err(Error::Parse(
+                            "Please specify a channel to bind (e.g., `#daily_status`)".into(),
+                        )),
+                    },
+                    Some("notify") => match iter.next() {
+                        Some("on") => Ok(SlashAction::SetNotify {
+                            team: team_name,
+                            notify: true,
+                        }),
+                        Some("off") => Ok(SlashAction::SetNotify {
+                            team: team_name,
+                            notify: false,
+                        }),
+                        _ => Err(Error::Parse("Please specify `on` or `off`".into())),
+                    },
+                    Some("coverage") => match iter.next() {
+                        Some("off") => Ok(SlashAction::SetCoverage {
+                            team: team_name,
+                            min: None,
+                            days: 0,
+                        }),
+                        Some(min) => match min.parse::<i64>() {
+                            Ok(min) if min > 0 => {
+                                match coverage::parse_days(iter.next().unwrap_or("mon-fri")) {
+                                    Ok(days) => Ok(SlashAction::SetCoverage {
+                                        team: team_name,
+                                        min: Some(min),
+                                        days,
+                                    }),
+                                    Err(e) => Err(e),
+                                }
+                            }
+                            _ => Err(Error::Parse(format!(
+                                "*{}* is not a valid number of members",
+                                min
+                            ))),
+                        },
+                        None => Err(Error::Parse(
+                            "Please specify a number of members (e.g., `2 mon-fri`) or `off`"
+                                .into(),
+                        )),
+                    },
+                    Some("approvers") => {
+                        let mut approvers: Vec<&str> = iter.collect();
+                        let timeout_hours = match approvers.last().map(|last| last.parse::<i64>()) {
+                            Some(Ok(hours)) if hours > 0 => {
+                                approvers.pop();
+                                hours
+                            }
+                            Some(Ok(hours)) => {
+                                return Err(Error::Parse(format!(
+                                    "*{}* is not a valid number of hours",
+                                    hours
+                                )))
+                            }
+                            _ => approval::DEFAULT_TIMEOUT_HOURS,
+                        };
+
+                        match approvers.as_slice() {
+                            [] => Ok(SlashAction::ShowApprovers { team: team_name }),
+                            ["off"] => Ok(SlashAction::SetApprovers {
+                                team: team_name,
+                                approvers: vec![],
+                                timeout_hours,
+                            }),
+                            _ => Ok(SlashAction::SetApprovers {
+                                team: team_name,
+                                approvers,
+                                timeout_hours,
+                            }),
+                        }
+                    }
+                    Some("setall") => {
+                        let text = iter.collect::<Vec<_>>().join(" ");
+                        match (text.as_str(), split_name(&text)) {
+                            ("undo", _) => Ok(SlashAction::UndoSetAll { team: team_name }),
+                            (_, Some((status, day))) => Ok(SlashAction::SetAll {
+                                team: team_name,
+                                status,
+                                day: Some(day).filter(|day| !day.is_empty()),
+                            }),
+                            (_, None) => Err(Error::Parse(
+                                "Please specify the status to set (e.g., `\"Offsite in Denver\" fri`)"
+                                    .into(),
+                            )),
+                        }
+                    }
+                    Some("ack") => match iter.next() {
+                        Some(user) => Ok(SlashAction::AckStatus {
+                            team: team_name,
+                            user,
+                        }),
+                        None => Err(Error::Parse(format!(
+                            "Please specify a member of team {} whose status to acknowledge",
+                            team_name
+                        ))),
+                    },
+                    Some("field") => match iter.next() {
+                        None => Ok(SlashAction::ShowFields { team: team_name }),
+                        Some(command @ "add") | Some(command @ "del") => {
+                            let label = quoted_text(iter);
+                            if label.is_empty() {
+                                Err(Error::Parse(format!(
+                                    "Please specify the label of the field to {}",
+                                    command
+                                )))
+                            } else if command == "add" {
+                                Ok(SlashAction::AddField {
+                                    team: team_name,
+                                    label,
+                                })
+                            } else {
+                                Ok(SlashAction::RemoveField {
+                                    team: team_name,
+                                    label,
+                                })
+                            }
+                        }
+                        _ => Err(Error::Parse(
+                            "Please specify either the `add` or `del` command".into(),
+                        )),
+                    },
+                    _ => Err(Error::Parse(
+                        "Please specify either the `add`, `del`, `lead`, `member`, `viewer`, `guest`, `feed`, `describe`, `icon`, `channel`, `notify`, `coverage`, `approvers`, `field`, `ack`, or `setall` command"
+                            .into(),
+                    )),
                 },
-                _ => Ok(SlashAction::ParsingFailed(
+                _ => Err(Error::Parse(
                     "Please specify `create`, `delete`, or a team name".into(),
                 )),
             },
-            Some(user) if user.starts_with(|c| c == '<' || c == '@') => {
+            Some("site") => match iter.next() {
+                Some("list") | None => Ok(SlashAction::ListSites),
+                Some("create") => match iter.next() {
+                    Some(name) => Ok(SlashAction::CreateSite { name }),
+                    None => Err(Error::Parse(
+                        "Please specify a site name when creating a site".into(),
+                    )),
+                },
+                Some("delete") => match iter.next() {
+                    Some(name) => Ok(SlashAction::DeleteSite { name }),
+                    None => Err(Error::Parse("Please specify a site name to delete".into())),
+                },
+                Some(site) => match iter.next() {
+                    Some("tz") => match iter.next().map(|tz| tz.parse::<Tz>()) {
+                        Some(Ok(tz)) => Ok(SlashAction::SetSiteTz { site, tz }),
+                        _ => Err(Error::Parse(
+                            "Please specify a timezone (e.g., `America/New_York`)".into(),
+                        )),
+                    },
+                    Some("address") => {
+                        let address = quoted_text(iter);
+                        if address.is_empty() {
+                            Err(Error::Parse(format!(
+                                "Please specify an address for site {}",
+                                site
+                            )))
+                        } else {
+                            Ok(SlashAction::SetSiteAddress { site, address })
+                        }
+                    }
+                    Some("capacity") => match iter.next() {
+                        Some("none") => Ok(SlashAction::SetSiteCapacity {
+                            site,
+                            capacity: None,
+                        }),
+                        Some(capacity) => match capacity.parse::<i64>() {
+                            Ok(capacity) if capacity >= 0 => Ok(SlashAction::SetSiteCapacity {
+                                site,
+                                capacity: Some(capacity),
+                            }),
+                            _ => Err(Error::Parse(format!(
+                                "`{}` is not a valid capacity",
+                                capacity
+                            ))),
+                        },
+                        None => Err(Error::Parse(
+                            "Please specify the number of desks, or `none` for unlimited".into(),
+                        )),
+                    },
+                    _ => Err(Error::Parse(
+                        "Please specify either the `tz`, `address`, or `capacity` command".into(),
+                    )),
+                },
+            },
+            Some(command @ "book") | Some(command @ "unbook") => match iter.next() {
+                Some(site) => Ok(SlashAction::Book {
+                    site,
+                    day: iter.next(),
+                    cancel: command == "unbook",
+                }),
+                None => Err(Error::Parse(format!(
+                    "Please specify a site to {} a desk at",
+                    command
+                ))),
+            },
+            Some("office") => {
+                // the site is optional, so a lone number is a page
+                let (site, page) = match (iter.next(), iter.next()) {
+                    (Some(arg), None) if arg.parse::<i64>().is_ok() => (None, Some(arg)),
+                    (site, page) => (site, page),
+                };
+
+                Ok(SlashAction::ListOffice {
+                    site,
+                    page: parse_page(page)?,
+                })
+            }
+            Some("shift") => match iter.next() {
+                Some(team) => match iter.next() {
+                    None => Ok(SlashAction::ShowShifts { team }),
+                    Some("create") => match (iter.next(), iter.next()) {
+                        (Some(name), Some(days)) => Ok(SlashAction::CreateShift {
+                            team,
+                            name,
+                            days: coverage::parse_days(days)?,
+                            hours: iter.next(),
+                        }),
+                        _ => Err(Error::Parse(
+                            "Please specify a shift name and the days it runs on (e.g., `early mon-fri 06:00-14:00`)"
+                                .into(),
+                        )),
+                    },
+                    Some("delete") => match iter.next() {
+                        Some(name) => Ok(SlashAction::DeleteShift { team, name }),
+                        None => Err(Error::Parse("Please specify a shift to delete".into())),
+                    },
+                    Some(command @ "assign") | Some(command @ "unassign") => {
+                        match (iter.next(), iter.next()) {
+                            (Some(name), Some(user)) => Ok(SlashAction::AssignShift {
+                                team,
+                                name,
+                                user,
+                                remove: command == "unassign",
+                            }),
+                            _ => Err(Error::Parse(format!(
+                                "Please specify a shift and a user to {}",
+                                command
+                            ))),
+                        }
+                    }
+                    _ => Err(Error::Parse(
+                        "Please specify either the `create`, `delete`, `assign`, or `unassign` command"
+                            .into(),
+                    )),
+                },
+                None => Err(Error::Parse(
+                    "Please specify a team to show the shifts of".into(),
+                )),
+            },
+            Some("calendar") => match iter.next() {
+                None | Some("list") => Ok(SlashAction::ShowCalendars),
+                Some(command @ "add") | Some(command @ "remove") => match iter.next() {
+                    Some(url) => Ok(SlashAction::SetCalendar {
+                        url: parse_url(url),
+                        remove: command == "remove",
+                    }),
+                    None => Err(Error::Parse(format!(
+                        "Please specify the URL of the calendar to {}",
+                        command
+                    ))),
+                },
+                _ => Err(Error::Parse(
+                    "Please specify either the `add`, `remove`, or `list` command".into(),
+                )),
+            },
+            Some("leave") => match iter.next() {
+                Some("cancel") => Ok(SlashAction::CancelLeave { day: iter.next() }),
+                from => Ok(SlashAction::AddLeave {
+                    from,
+                    to: iter.next(),
+                }),
+            },
+            Some("badge") => Ok(SlashAction::ShowBadge),
+            Some("wizard") => Ok(SlashAction::Wizard),
+            Some("setup") => Ok(SlashAction::Setup),
+            Some("feedback") => {
+                let message = quoted_text(iter);
+                if message.is_empty() {
+                    Err(Error::Parse(
+                        "Please describe the problem, e.g. `/location feedback my status didn't update`"
+                            .into(),
+                    ))
+                } else {
+                    Ok(SlashAction::Feedback { message })
+                }
+            }
+            Some("muster") => match iter.next() {
+                Some(team) => Ok(SlashAction::Muster { team }),
+                None => Err(Error::Parse("Please specify a team to muster".into())),
+            },
+            Some("announce") => match iter.next() {
+                Some(team) => {
+                    let words: Vec<&str> = iter.collect();
+                    let (channel, words) = match words.as_slice() {
+                        ["status"] => return Ok(SlashAction::ShowAnnouncement { team }),
+                        ["channel", words @ ..] => (true, words),
+                        words => (false, words),
+                    };
+
+                    let message = quoted_text(words.iter().copied());
+                    if message.is_empty() {
+                        Err(Error::Parse("Please specify a message to announce".into()))
+                    } else {
+                        Ok(SlashAction::Announce {
+                            team,
+                            message,
+                            channel,
+                        })
+                    }
+                }
+                None => Err(Error::Parse(
+                    "Please specify a team to send an announcement to".into(),
+                )),
+            },
+            Some("delegate") => match iter.next() {
+                None => Ok(SlashAction::ShowDelegate),
+                Some("none") => Ok(SlashAction::SetDelegate { user: None }),
+                Some(user) => Ok(SlashAction::SetDelegate { user: Some(user) }),
+            },
+            Some("contact") => match iter.next() {
+                None => Ok(SlashAction::ShowContact),
+                Some(channel) => Ok(SlashAction::SetContact {
+                    channel: channel.parse()?,
+                    phone: iter.next(),
+                }),
+            },
+            Some("autoreply") => match iter.next() {
+                None => Ok(SlashAction::ShowAutoReply),
+                Some("on") => Ok(SlashAction::SetAutoReply {
+                    enabled: true,
+                    contact: iter.next(),
+                }),
+                Some("off") => Ok(SlashAction::SetAutoReply {
+                    enabled: false,
+                    contact: None,
+                }),
+                _ => Err(Error::Parse(
+                    "Please specify either the `on` or `off` command".into(),
+                )),
+            },
+            Some("timeline") => match iter.next() {
+                Some(user) => Ok(SlashAction::Timeline {
+                    user,
+                    week: iter.next(),
+                }),
+                None => Err(Error::Parse(
+                    "Please specify a user to show the timeline of".into(),
+                )),
+            },
+            Some("set") => match iter.next() {
+                Some("fields") => Ok(SlashAction::EditFields),
+                Some("note") => {
+                    let note = quoted_text(iter);
+                    if note.is_empty() {
+                        Err(Error::Parse("Please specify a note".into()))
+                    } else {
+                        Ok(SlashAction::SetNote { note })
+                    }
+                }
+                Some("where") => match iter.next() {
+                    Some(location) => {
+                        let mut parts = location.splitn(2, ':');
+                        Ok(SlashAction::SetLocation {
+                            location: parts.next().unwrap_or_default().parse()?,
+                            site: parts.next().filter(|site| !site.is_empty()),
+                        })
+                    }
+                    None => Err(Error::Parse(
+                        "Please specify `office`, `remote`, or `site`".into(),
+                    )),
+                },
+                Some("availability") => match iter.next() {
+                    Some(availability) => Ok(SlashAction::SetAvailability {
+                        availability: availability.parse()?,
+                    }),
+                    None => Err(Error::Parse(
+                        "Please specify `available`, `busy`, or `ooo`".into(),
+                    )),
+                },
+                _ => Err(Error::Parse(
+                    "Please specify either the `note`, `where`, `availability`, or `fields` command"
+                        .into(),
+                )),
+            },
+            Some(user) if user.starts_with(|c| c == '<' || c == '@') || parse_email(user).is_some() => {
                 Ok(SlashAction::ShowUser { user })
             }
-            Some(team) => Ok(SlashAction::ShowTeam { team }),
-            None => Ok(SlashAction::ParsingFailed(
+            Some(team) => match iter.next() {
+                Some("on") => Ok(SlashAction::ShowTeamOn {
+                    team,
+                    day: parse_date(iter.next())?,
+                    page: parse_page(iter.next())?,
+                }),
+                page => Ok(SlashAction::ShowTeam {
+                    team,
+                    page: parse_page(page)?,
+                }),
+            },
+            None => Err(Error::Parse(
                 "Please specify a username, team name, or `team`".into(),
             )),
         }
     }
+
+    /// Returns if the action only reads from the database, so may run against a replica
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            SlashAction::ShowTeam { .. }
+                | SlashAction::ShowTeamOn { .. }
+                | SlashAction::ListTeams { .. }
+                | SlashAction::ListSites
+                | SlashAction::ListOffice { .. }
+                | SlashAction::ShowShifts { .. }
+        )
+    }
+
+    /// Returns the name of the action (e.g., `show_team`), as recorded in usage statistics
+    pub fn name(&self) -> &'static str {
+        match self {
+            SlashAction::ShowUser { .. } => "show_user",
+            SlashAction::ShowTeam { .. } => "show_team",
+            SlashAction::ShowTeamOn { .. } => "show_team_on",
+            SlashAction::ListTeams { .. } => "list_teams",
+            SlashAction::CreateTeam { .. } => "create_team",
+            SlashAction::DeleteTeam { .. } => "delete_team",
+            SlashAction::AddMember { .. } => "add_member",
+            SlashAction::RemoveMember { .. } => "remove_member",
+            SlashAction::TeamFeed { .. } => "team_feed",
+            SlashAction::SetRole { .. } => "set_role",
+            SlashAction::AddGuest { .. } => "add_guest",
+            SlashAction::RemoveGuest { .. } => "remove_guest",
+            SlashAction::SetGuestStatus { .. } => "set_guest_status",
+            SlashAction::Describe { .. } => "describe",
+            SlashAction::SetIcon { .. } => "set_icon",
+            SlashAction::BindChannel { .. } => "bind_channel",
+            SlashAction::SetNotify { .. } => "set_notify",
+            SlashAction::SetCoverage { .. } => "set_coverage",
+            SlashAction::ShowApprovers { .. } => "show_approvers",
+            SlashAction::SetApprovers { .. } => "set_approvers",
+            SlashAction::Timeline { .. } => "timeline",
+            SlashAction::SetNote { .. } => "set_note",
+            SlashAction::SetLocation { .. } => "set_location",
+            SlashAction::ListSites => "list_sites",
+            SlashAction::CreateSite { .. } => "create_site",
+            SlashAction::DeleteSite { .. } => "delete_site",
+            SlashAction::SetSiteTz { .. } => "set_site_tz",
+            SlashAction::SetSiteAddress { .. } => "set_site_address",
+            SlashAction::SetSiteCapacity { .. } => "set_site_capacity",
+            SlashAction::Book { .. } => "book",
+            SlashAction::ListOffice { .. } => "list_office",
+            SlashAction::SetAvailability { .. } => "set_availability",
+            SlashAction::ShowShifts { .. } => "show_shifts",
+            SlashAction::ShowCalendars => "show_calendars",
+            SlashAction::SetCalendar { .. } => "set_calendar",
+            SlashAction::AddLeave { .. } => "add_leave",
+            SlashAction::CancelLeave { .. } => "cancel_leave",
+            SlashAction::CreateShift { .. } => "create_shift",
+            SlashAction::DeleteShift { .. } => "delete_shift",
+            SlashAction::AssignShift { .. } => "assign_shift",
+            SlashAction::ShowBadge => "show_badge",
+            SlashAction::Muster { .. } => "muster",
+            SlashAction::Announce { .. } => "announce",
+            SlashAction::ShowAnnouncement { .. } => "show_announcement",
+            SlashAction::ShowAutoReply => "show_auto_reply",
+            SlashAction::SetAutoReply { .. } => "set_auto_reply",
+            SlashAction::ShowDelegate => "show_delegate",
+            SlashAction::SetDelegate { .. } => "set_delegate",
+            SlashAction::ShowContact => "show_contact",
+            SlashAction::SetContact { .. } => "set_contact",
+            SlashAction::SetAll { .. } => "set_all",
+            SlashAction::UndoSetAll { .. } => "undo_set_all",
+            SlashAction::AckStatus { .. } => "ack_status",
+            SlashAction::ShowFields { .. } => "show_fields",
+            SlashAction::AddField { .. } => "add_field",
+            SlashAction::RemoveField { .. } => "remove_field",
+            SlashAction::EditFields => "edit_fields",
+            SlashAction::Wizard => "wizard",
+            SlashAction::Setup => "setup",
+            SlashAction::Feedback { .. } => "feedback",
+        }
+    }
 }
 
 /// Handle a `POST` request to the `/location` endpoint
 ///
 /// # Arguments
-/// * `req` - Incoming HTTP request
-pub async fn location(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
-    // parse the encoded form into a slash command, extracting the relevant details
-    let form: SlashCommand = match req.body_form().await {
-        Ok(form) => form,
-        Err(e) => {
-            tracing::error!("Failed to parse location request: {:?}", e);
-            return Ok(tide::Response::builder(StatusCode::Ok).build());
+/// * `form` - The signed slash command
+/// * `db` - Connection to the database
+/// * `state` - Shared application state
+pub async fn location(
+    (Form(form), Db(mut db), AppState(state)): (Form<SlashCommand>, Db, AppState),
+) -> tide::Result<tide::Response> {
+    let started = Instant::now();
+
+    // parse and execute the text received as commands, after replacing any aliases
+    let text = state.aliases.expand(&form.text);
+    let (name, result) = match SlashAction::parse(&text) {
+        Ok(action) => {
+            // actions that only read run against the replica, if there is one
+            let mut replica = if action.is_read_only() {
+                state.replica_conn().await
+            } else {
+                None
+            };
+            let conn = replica.as_mut().unwrap_or(&mut db);
+
+            (action.name(), execute(action, &form, conn, &state).await)
         }
+        Err(e) => ("invalid", Err(e)),
     };
 
-    // grab a connection to the database
-    let mut db = req.db().await?;
+    // only the action is recorded, never who ran it or what they typed
+    let latency_ms = started.elapsed().as_millis() as i64;
+    if let Err(e) =
+        CommandStat::record(&mut db, name, &form.team_id, latency_ms, result.is_ok()).await
+    {
+        tracing::warn!("failed to record command usage: {:?}", e);
+    }
+
+    match result {
+        Ok(resp) => Ok(resp.into()),
+        Err(e @ Error::Parse(_)) | Err(e @ Error::NotFound(_)) => {
+            // typos are the most common reason for both, so offer to fix them
+            let corrected = suggest::correct(&mut db, &text).await;
+            let mut resp = e.into_slash_message();
+            if let Some(corrected) = corrected {
+                suggest::offer(&mut resp, &corrected);
+            }
+            Ok(resp.into())
+        }
+        Err(e) => Ok(e.into_slash_response()),
+    }
+}
+
+/// Parses and executes a command that wasn't typed (e.g., one built by the wizard),
+/// returning the response to send
+///
+/// # Arguments
+/// * `form` - The command, as if it had been typed
+/// * `db` - Connection to the database
+/// * `state` - Shared application state
+pub(crate) async fn run(
+    form: &SlashCommand,
+    db: &mut SqlConn,
+    state: &State,
+) -> Result<SlashResponse, Error> {
+    let action = SlashAction::parse(&form.text)?;
+    execute(action, form, db, state).await
+}
+
+/// Runs a command that wasn't typed in the background, posting its response to the
+/// command's `response_url`
+///
+/// # Arguments
+/// * `state` - Shared application state
+/// * `form` - The command, as if it had been typed
+/// * `replace_original` - If the response replaces the message the command came from
+pub(crate) fn spawn_run(state: &State, form: SlashCommand, replace_original: bool) {
+    let state = state.clone();
+    runtime::spawn(async move {
+        let mut db = match state.pool().acquire().await {
+            Ok(db) => db,
+            Err(e) => {
+                tracing::error!("Failed to run command: {:?}", e);
+                return;
+            }
+        };
+
+        let resp = match run(&form, &mut db, &state).await {
+            Ok(resp) => resp,
+            Err(e) => e.into_slash_message(),
+        };
 
-    // create our response structure of blocks
-    let mut blocks: Vec<Value> = vec![];
+        let resp = if replace_original {
+            resp.replace_original()
+        } else {
+            resp
+        };
 
-    // parse and execute the text received as commands
-    match SlashAction::parse(&form.text)? {
-        SlashAction::ShowUser { user } => match User::fetch(&mut db, user).await {
-            Some(user) => match user.status {
-                Some(status) => mrkdwn!(blocks, format!("*<@{}>*: {}", user.id, status)),
-                None => mrkdwn!(blocks, format!("*<@{}>* has not set a status", user.id)),
-            },
-            None => mrkdwn!(blocks, "User not found"),
-        },
+        if let Err(e) = resp.post(&form.response_url).await {
+            tracing::error!("Failed to respond to command: {:?}", e);
+        }
+    });
+}
+
+/// Executes a parsed slash command, returning the response to send
+///
+/// # Arguments
+/// * `action` - The parsed command
+/// * `form` - The signed slash command
+/// * `db` - Connection to the database
+/// * `state` - Shared application state
+async fn execute(
+    action: SlashAction<'_>,
+    form: &SlashCommand,
+    db: &mut SqlConn,
+    state: &State,
+) -> Result<SlashResponse, Error> {
+    // create our response, laid out independently of the surface it's shown on
+    let mut doc = ResponseDoc::new();
+
+    match action {
+        SlashAction::ShowUser { user } => {
+            let user = resolve_user(db, user).await?;
 
-        SlashAction::ShowTeam { team } => match Team::members(&mut db, team).await {
-            Ok(members) => {
-                header!(blocks, format!("{} Status", team));
-                divider!(blocks);
-                for member in members {
-                    match member.status {
-                        Some(status) => mrkdwn!(blocks, format!("*<@{}>*: {}", member.id, status)),
-                        None => mrkdwn!(blocks, format!("*<@{}>* has not set a status", member.id)),
+            doc.section(match User::fetch(db, &user).await {
+                Ok(Some(user)) => {
+                    let name = Text::new().mention(&user.id, &user.id);
+                    match (user.compact_status(), user.plain_status()) {
+                        (Some(compact), plain) => {
+                            name.text(": ").slack(compact, plain.unwrap_or_default())
+                        }
+                        (None, _) => name.text(" has not set a status"),
                     }
                 }
+                Ok(None) => Text::from("User not found"),
+                Err(Error::Parse(_)) => invalid_user(&user),
+                Err(e) => return Err(e),
+            });
+        }
+
+        SlashAction::ShowTeam { team, page } => {
+            let offset = (page - 1) * PAGE_SIZE;
+            match Team::members_page(db, team, PAGE_SIZE + 1, offset).await {
+                Ok(mut members) => {
+                    let has_more = members.len() as i64 > PAGE_SIZE;
+                    members.truncate(PAGE_SIZE as usize);
+
+                    let presence = match &state.presence {
+                        Some(cache) => {
+                            let ids: Vec<String> = members
+                                .iter()
+                                .filter(|member| !member.external)
+                                .map(|member| member.id.clone())
+                                .collect();
+                            cache.lookup(&ids).await
+                        }
+                        None => HashMap::new(),
+                    };
+
+                    let meetings = match &state.meetings {
+                        Some(cache) => {
+                            let ids: Vec<String> = members
+                                .iter()
+                                .filter(|member| !member.external)
+                                .map(|member| member.id.clone())
+                                .collect();
+                            cache.lookup(&ids).await
+                        }
+                        None => HashMap::new(),
+                    };
+
+                    // meetings end at times shown in the viewer's timezone
+                    let tz = user_tz(db, &form.user_id).await;
+
+                    let summaries = match &state.issues {
+                        Some(linker) => {
+                            let keys: Vec<String> = members
+                                .iter()
+                                .filter_map(|member| member.status.as_deref())
+                                .flat_map(issues::keys)
+                                .collect();
+                            linker.lookup(&keys).await
+                        }
+                        None => HashMap::new(),
+                    };
+
+                    let (fields, acks) = match Team::fetch(db, team).await {
+                        Some(team) => {
+                            team_heading(&mut doc, &team);
+
+                            let fields = TeamField::fetch_by_team(db, &team)
+                                .await
+                                .unwrap_or_default();
+                            let acks = StatusAck::fetch_by_team(db, &team)
+                                .await
+                                .unwrap_or_default();
+                            (fields, acks)
+                        }
+                        None => return Err(Error::NotFound(format!("Team *{}*", team))),
+                    };
+
+                    // members at the same site are shown together, keeping leads first
+                    members.sort_by(|a, b| {
+                        (a.site().is_none(), a.site()).cmp(&(b.site().is_none(), b.site()))
+                    });
+                    let sites = Site::fetch_all(db).await.unwrap_or_default();
+                    let lines = members
+                        .iter()
+                        .map(|member| {
+                            let mut line = MemberLine::new(member);
+                            line.presence = presence.get(&member.id).map(|p| p.badge());
+                            line.meeting = meetings.get(&member.id).map(|m| m.describe(tz));
+                            line.details = TeamField::describe(&fields, &member.field_values());
+
+                            if let (Some(linker), Some((compact, _))) =
+                                (&state.issues, &mut line.status)
+                            {
+                                *compact = linker.link_mrkdwn(compact, &summaries);
+                            }
+                            line.acked = match acks.get(&member.id) {
+                                Some(ack) => ack.covers(member.compact_status().as_deref()),
+                                None => false,
+                            };
+                            line.covering = match member.availability() {
+                                Some(Availability::Ooo) => member.delegate_id.clone(),
+                                _ => None,
+                            };
+
+                            (
+                                member.site().map(|site| site.to_owned()),
+                                line.to_text(false),
+                            )
+                        })
+                        .collect();
+
+                    group_by_site(&mut doc, &sites, lines);
+                    page_footer(&mut doc, page, has_more, team);
+                }
+                Err(_) => {
+                    doc.section(not_found("Team", team));
+                }
             }
-            Err(_) => mrkdwn!(blocks, format!("Team *{}* not found", team)),
-        },
+        }
+
+        SlashAction::ShowTeamOn { team, day, page } => {
+            // days end in the viewer's timezone
+            let tz = user_tz(db, &form.user_id).await;
+            let today = Utc::now().with_timezone(&tz).date().naive_local();
+            if day > today {
+                return Err(Error::Parse(
+                    "Please specify a day that isn't in the future".into(),
+                ));
+            }
+
+            let team = match Team::fetch(db, team).await {
+                Some(team) => team,
+                None => return Err(Error::NotFound(format!("Team *{}*", team))),
+            };
+
+            let offset = (page - 1) * PAGE_SIZE;
+            let end_of_day = start_of_day(tz, day + Duration::days(1));
+            match Team::members_as_of(db, &team.name, end_of_day, PAGE_SIZE + 1, offset).await {
+                Ok(mut members) => {
+                    let has_more = members.len() as i64 > PAGE_SIZE;
+                    members.truncate(PAGE_SIZE as usize);
+
+                    doc.header(format!(
+                        "{} Status on {}",
+                        team.display_name(),
+                        day.format("%B %-d, %Y")
+                    ))
+                    .context(format!("Statuses at the end of the day, in {}", tz.name()))
+                    .divider();
+
+                    // members at the same site are shown together, keeping leads first
+                    members.sort_by(|a, b| {
+                        (a.site().is_none(), a.site()).cmp(&(b.site().is_none(), b.site()))
+                    });
+                    let sites = Site::fetch_all(db).await.unwrap_or_default();
+                    let lines = members
+                        .iter()
+                        .map(|member| {
+                            let line = MemberLine::new(member).to_text(true);
+                            (member.site().map(|site| site.to_owned()), line)
+                        })
+                        .collect();
 
-        SlashAction::ListTeams => match Team::fetch_all(&mut db).await {
-            Ok(teams) => {
-                header!(blocks, "Available Teams:");
-                divider!(blocks);
-                for team in teams {
-                    mrkdwn!(blocks, format!("• {}", team.name));
+                    group_by_site(&mut doc, &sites, lines);
+                    page_footer(
+                        &mut doc,
+                        page,
+                        has_more,
+                        &format!("{} on {}", team.name, day),
+                    );
+                }
+                Err(_) => {
+                    doc.section(
+                        Text::from("Failed to fetch the statuses of team ").strong(&team.name),
+                    );
                 }
             }
-            Err(_) => mrkdwn!(blocks, "Failed to fetch teams"),
-        },
+        }
 
-        SlashAction::CreateTeam { name } => match Team::new(&mut db, name).await {
-            Ok(team) => mrkdwn!(
-                blocks,
-                format!("Team *{}* successfully created!", team.name)
-            ),
-            Err(_) => mrkdwn!(
-                blocks,
-                format!("Failed to create Team {}, perhaps it already exists?", name)
-            ),
-        },
+        SlashAction::ListTeams { page } => {
+            let offset = (page - 1) * PAGE_SIZE;
+            match Team::fetch_page(db, PAGE_SIZE + 1, offset).await {
+                Ok(mut teams) => {
+                    let has_more = teams.len() as i64 > PAGE_SIZE;
+                    teams.truncate(PAGE_SIZE as usize);
 
-        SlashAction::DeleteTeam { name } => match Team::fetch(&mut db, name).await {
-            Some(team) => match team.delete(&mut db).await {
-                Ok(_) => mrkdwn!(blocks, format!("Team *{}* deleted", name)),
-                Err(_) => mrkdwn!(
-                    blocks,
-                    format!("Failed to delete Team *{}*. Please try again later", name)
-                ),
-            },
-            None => mrkdwn!(blocks, format!("Team *{}* not found", name)),
-        },
+                    let teams = teams
+                        .iter()
+                        .map(|team| {
+                            let name = Text::new().strong(team.display_name());
+                            match &team.description {
+                                Some(description) => name.text(" — ").text(description),
+                                None => name,
+                            }
+                        })
+                        .collect();
 
-        SlashAction::AddMember { team, user } => match Team::fetch(&mut db, team).await {
-            Some(team) => match User::fetch_or_create(&mut db, user).await {
-                Ok(user) => match team.add_member(&mut db, &user).await {
-                    Ok(_) => mrkdwn!(
-                        blocks,
-                        format!("<@{}> added to team {}", user.id, team.name)
-                    ),
-                    Err(_) => mrkdwn!(
-                        blocks,
-                        format!("Failed to add user <@{}> to Team {}", user.id, team.name)
-                    ),
+                    doc.header("Available Teams:").divider().list(teams);
+                    page_footer(&mut doc, page, has_more, "team list");
+                }
+                Err(_) => {
+                    doc.section("Failed to fetch teams");
+                }
+            }
+        }
+
+        SlashAction::CreateTeam { name } => {
+            doc.section(match Team::new(db, name, &form.user_id).await {
+                Ok(team) => Text::from("Team ")
+                    .strong(&team.name)
+                    .text(" successfully created!"),
+                Err(e) => match e.downcast::<Error>() {
+                    Ok(e) => return Err(e),
+                    Err(_) => Text::from(format!(
+                        "Failed to create Team {}, perhaps it already exists?",
+                        name
+                    )),
                 },
-                Err(_) => mrkdwn!(blocks, format!("Failed to load user with id <@{}>", user)),
-            },
-            None => mrkdwn!(blocks, format!("Team *{}* not found", team)),
-        },
+            });
+        }
 
-        SlashAction::RemoveMember { team, user } => match Team::fetch(&mut db, team).await {
-            Some(team) => match User::fetch(&mut db, user).await {
-                Some(user) => match team.delete_member(&mut db, &user).await {
-                    Ok(_) => mrkdwn!(
-                        blocks,
-                        format!("<@{}> deleted from team {}", user.id, team.name)
-                    ),
-                    Err(_) => mrkdwn!(
-                        blocks,
-                        format!(
-                            "Failed to delete user <@{}> from Team {}",
-                            user.id, team.name
+        SlashAction::DeleteTeam { name } => {
+            doc.section(match Team::fetch(db, name).await {
+                Some(team) => match team.delete(db).await {
+                    Ok(_) => {
+                        audit::record(
+                            db,
+                            &form.user_id,
+                            AuditEntry::TEAM_DELETED,
+                            name,
+                            None,
+                            None,
                         )
-                    ),
+                        .await;
+                        Text::from("Team ").strong(name).text(" deleted")
+                    }
+                    Err(_) => Text::from("Failed to delete Team ")
+                        .strong(name)
+                        .text(". Please try again later"),
                 },
-                None => mrkdwn!(blocks, format!("User with id *{}* not found", user)),
-            },
-            None => mrkdwn!(blocks, format!("Team *{}* not found", team)),
+                None => not_found("Team", name),
+            });
+        }
+
+        SlashAction::AddMember { team, user } => {
+            let user = resolve_user(db, user).await?;
+
+            doc.section(match Team::fetch(db, team).await {
+                Some(team) => match User::fetch_or_create(db, &user).await {
+                    Ok(user) => match team.add_member(db, &user).await {
+                        Ok(_) => Text::new()
+                            .mention(&user.id, &user.id)
+                            .text(format!(" added to team {}", team.name)),
+                        Err(e) => match e.downcast::<Error>() {
+                            Ok(e) => return Err(e),
+                            Err(_) => Text::from("Failed to add user ")
+                                .mention(&user.id, &user.id)
+                                .text(format!(" to Team {}", team.name)),
+                        },
+                    },
+                    Err(_) => Text::from("Failed to load user with id ").mention(&user, &user),
+                },
+                None => not_found("Team", team),
+            });
+        }
+
+        SlashAction::RemoveMember { team, user } => {
+            let user = resolve_user(db, user).await?;
+
+            doc.section(match Team::fetch(db, team).await {
+                Some(team) => match User::fetch(db, &user).await {
+                    Ok(Some(user)) => match team.delete_member(db, &user).await {
+                        Ok(_) => Text::new()
+                            .mention(&user.id, &user.id)
+                            .text(format!(" deleted from team {}", team.name)),
+                        Err(_) => Text::from("Failed to delete user ")
+                            .mention(&user.id, &user.id)
+                            .text(format!(" from Team {}", team.name)),
+                    },
+                    Ok(None) => not_found("User with id", &user),
+                    Err(Error::Parse(_)) => invalid_user(&user),
+                    Err(e) => return Err(e),
+                },
+                None => not_found("Team", team),
+            });
+        }
+
+        SlashAction::AddGuest { team, name } => {
+            let team = managed_team(db, team, &form.user_id).await?;
+
+            let failed = Text::from("Failed to add ")
+                .strong(&name)
+                .text(format!(" to Team {}", team.name));
+            doc.section(match team.guest(db, &name).await {
+                Ok(Some(_)) => Text::new()
+                    .strong(&name)
+                    .text(format!(" is already a guest of team {}", team.name)),
+                Ok(None) => match User::new_external(db, &name).await {
+                    Ok(guest) => match team.add_member(db, &guest).await {
+                        Ok(_) => Text::new()
+                            .strong(&name)
+                            .text(format!(" added to team {} as a guest", team.name)),
+                        Err(e) => match e.downcast::<Error>() {
+                            Ok(e) => return Err(e),
+                            Err(_) => failed,
+                        },
+                    },
+                    Err(_) => failed,
+                },
+                Err(_) => failed,
+            });
+        }
+
+        SlashAction::RemoveGuest { team, name } => {
+            let team = managed_team(db, team, &form.user_id).await?;
+
+            let failed = Text::from("Failed to delete ")
+                .strong(&name)
+                .text(format!(" from Team {}", team.name));
+            doc.section(match team.guest(db, &name).await {
+                Ok(Some(guest)) => match team.delete_member(db, &guest).await {
+                    Ok(_) => Text::new()
+                        .strong(&name)
+                        .text(format!(" deleted from team {}", team.name)),
+                    Err(_) => failed,
+                },
+                Ok(None) => Text::new()
+                    .strong(&name)
+                    .text(format!(" is not a guest of team {}", team.name)),
+                Err(_) => failed,
+            });
+        }
+
+        SlashAction::SetGuestStatus { team, name, status } => {
+            let team = managed_team(db, team, &form.user_id).await?;
+
+            let failed = Text::from("Failed to update ")
+                .strong(&name)
+                .text(". Please try again later");
+            doc.section(match team.guest(db, &name).await {
+                Ok(Some(mut guest)) => {
+                    guest.set_status(status);
+                    match guest.save(db).await {
+                        Ok(_) => {
+                            // guests were fetched whole, and aren't on slack to read back
+                            state.feed.publish(StatusChange::from(&guest));
+                            Text::from("Status of ").strong(&name).text(" updated")
+                        }
+                        Err(_) => failed,
+                    }
+                }
+                Ok(None) => Text::new()
+                    .strong(&name)
+                    .text(format!(" is not a guest of team {}", team.name)),
+                Err(_) => failed,
+            });
+        }
+
+        SlashAction::SetRole { team, user, role } => {
+            // the team's owner, or one of its leads, may change roles
+            let team = match owned_team(db, team, &form.user_id).await {
+                Ok(team) => team,
+                Err(Error::Auth(_)) => managed_team(db, team, &form.user_id).await?,
+                Err(e) => return Err(e),
+            };
+            let user = resolve_user(db, user).await?;
+
+            doc.section(match User::fetch(db, &user).await {
+                Ok(Some(user)) if user.id == form.user_id => {
+                    return Err(Error::Auth(format!(
+                        "{} may not change their own role in team {}",
+                        user.id, team.name
+                    )))
+                }
+                Ok(Some(user)) => {
+                    let failed = Text::from("Failed to update ")
+                        .mention(&user.id, &user.id)
+                        .text(format!(" in Team {}", team.name));
+                    match team.member_role(db, &user).await {
+                        Ok(Some(_)) => match team.set_role(db, &user, role).await {
+                            Ok(_) => {
+                                audit::record(
+                                    db,
+                                    &form.user_id,
+                                    AuditEntry::ROLE_GRANTED,
+                                    &team.name,
+                                    Some(&user.id),
+                                    Some(role.as_str()),
+                                )
+                                .await;
+                                Text::new().mention(&user.id, &user.id).text(format!(
+                                    " is now a {} of team {}",
+                                    role.as_str(),
+                                    team.name
+                                ))
+                            }
+                            Err(_) => failed,
+                        },
+                        Ok(None) => not_a_member(&user.id, &team),
+                        Err(_) => failed,
+                    }
+                }
+                Ok(None) => not_found("User with id", &user),
+                Err(Error::Parse(_)) => invalid_user(&user),
+                Err(e) => return Err(e),
+            });
+        }
+
+        SlashAction::TeamFeed { team } => {
+            doc.section(match Team::fetch(db, team).await {
+                Some(team) => match atom::feed_url(&team.name) {
+                    Some(url) => Text::from("Atom feed for team ")
+                        .strong(&team.name)
+                        .text(format!(": {}", url)),
+                    None => Text::from("Feeds are not enabled"),
+                },
+                None => not_found("Team", team),
+            });
+        }
+
+        SlashAction::Describe { team, description } => {
+            doc.section(match Team::fetch(db, team).await {
+                Some(mut team) => {
+                    team.description = Some(description);
+                    match team.save(db).await {
+                        Ok(_) => Text::from("Description of team ")
+                            .strong(&team.name)
+                            .text(" updated"),
+                        Err(_) => update_failed("Team", &team.name),
+                    }
+                }
+                None => not_found("Team", team),
+            });
+        }
+
+        SlashAction::SetIcon { team, icon } => {
+            doc.section(match Team::fetch(db, team).await {
+                Some(mut team) => {
+                    team.icon = Some(icon.to_owned());
+                    match team.save(db).await {
+                        Ok(_) => Text::from("Icon of team ")
+                            .strong(&team.name)
+                            .text(" set to ")
+                            .slack(icon, icon),
+                        Err(_) => update_failed("Team", &team.name),
+                    }
+                }
+                None => not_found("Team", team),
+            });
+        }
+
+        SlashAction::BindChannel { team, channel } => {
+            doc.section(match Team::fetch(db, team).await {
+                Some(mut team) => {
+                    team.channel = Some(channel.to_owned());
+                    match team.save(db).await {
+                        Ok(_) => Text::from("Team ")
+                            .strong(&team.name)
+                            .text(" bound to ")
+                            .slack(format!("<#{}>", channel), channel),
+                        Err(_) => update_failed("Team", &team.name),
+                    }
+                }
+                None => not_found("Team", team),
+            });
+        }
+
+        SlashAction::SetNotify { team, notify } => {
+            doc.section(match Team::fetch(db, team).await {
+                Some(team) if team.channel.is_none() => Text::from("Team ")
+                    .strong(&team.name)
+                    .text(" has no bound channel. Use ")
+                    .code(format!("/location team {} channel <#channel>", team.name))
+                    .text(" first"),
+                Some(mut team) => {
+                    team.notify_changes = notify;
+                    match team.save(db).await {
+                        Ok(_) if notify => Text::from("Status changes of team ")
+                            .strong(&team.name)
+                            .text(" will be posted in its channel"),
+                        Ok(_) => Text::from("Status changes of team ")
+                            .strong(&team.name)
+                            .text(" will no longer be posted"),
+                        Err(_) => update_failed("Team", &team.name),
+                    }
+                }
+                None => not_found("Team", team),
+            });
+        }
+
+        SlashAction::SetCoverage { team, min, days } => {
+            let mut team = managed_team(db, team, &form.user_id).await?;
+
+            team.min_coverage = min;
+            if min.is_some() {
+                team.coverage_days = days;
+            }
+
+            doc.section(match team.save(db).await {
+                Ok(_) => match min {
+                    Some(min) => Text::from("Team ").strong(&team.name).text(format!(
+                        " needs at least {} on site on {}",
+                        min,
+                        coverage::describe_days(days)
+                    )),
+                    None => Text::from("Team ")
+                        .strong(&team.name)
+                        .text(" no longer has a coverage requirement"),
+                },
+                Err(_) => update_failed("Team", &team.name),
+            });
+        }
+
+        SlashAction::ShowApprovers { team } => {
+            let team = managed_team(db, team, &form.user_id).await?;
+
+            doc.section(match ApprovalChain::fetch_by_team(db, team.id()).await {
+                Ok(Some(chain)) => mrkdwn(&approval::describe(&team.name, &chain)),
+                Ok(None) => Text::from("Leave of team ")
+                    .strong(&team.name)
+                    .text(" doesn't need approval. Use ")
+                    .code(format!(
+                        "/location team {} approvers @user [@user...] [hours]",
+                        team.name
+                    ))
+                    .text(" to require it"),
+                Err(_) => Text::from("Failed to fetch the approvers of Team ")
+                    .strong(&team.name)
+                    .text(". Please try again later"),
+            });
+        }
+
+        SlashAction::SetApprovers {
+            team,
+            approvers,
+            timeout_hours,
+        } => {
+            let team = managed_team(db, team, &form.user_id).await?;
+
+            let mut ids = vec![];
+            for approver in approvers {
+                let id = SlackUserId::parse(&resolve_user(db, approver).await?)?;
+                if !ids.iter().any(|existing| existing == id.as_str()) {
+                    ids.push(id.into_inner());
+                }
+            }
+
+            let chain = ApprovalChain::new(team.id(), &ids, timeout_hours);
+            let saved = if ids.is_empty() {
+                ApprovalChain::delete(db, team.id()).await
+            } else {
+                chain.save(db).await
+            };
+
+            doc.section(match saved {
+                Ok(_) if ids.is_empty() => Text::from("Leave of team ")
+                    .strong(&team.name)
+                    .text(" no longer needs approval. Requests already sent are still pending"),
+                Ok(_) => mrkdwn(&approval::describe(&team.name, &chain)),
+                Err(_) => update_failed("Team", &team.name),
+            });
+        }
+
+        SlashAction::Timeline { user, week } => {
+            let user = resolve_user(db, user).await?;
+
+            let user = match User::fetch(db, &user).await? {
+                Some(user) => user,
+                None => return Err(Error::NotFound(format!("User *{}*", user))),
+            };
+
+            // days are shown in the user's own timezone
+            let tz = Profile::fetch(db, &user.id)
+                .await
+                .and_then(|profile| profile.tz)
+                .and_then(|tz| tz.parse::<Tz>().ok())
+                .unwrap_or(Tz::UTC);
+
+            let today = Utc::now().with_timezone(&tz).date().naive_local();
+            let monday = parse_week(week, today)?;
+
+            match HistoryEntry::fetch_by_user_since(db, &user.id, start_of_day(tz, monday)).await {
+                Ok(entries) => {
+                    doc.header(format!("Week of {}", monday.format("%B %-d, %Y")))
+                        .section(Text::new().mention(&user.id, &user.id))
+                        .fields(timeline_fields(&entries, tz, monday, today))
+                        .context(format!(
+                            "Days are in {}. Days without an update show the last status set before them",
+                            tz.name()
+                        ));
+                }
+                Err(_) => {
+                    doc.section(
+                        Text::from("Failed to fetch the history of ").mention(&user.id, &user.id),
+                    );
+                }
+            }
+        }
+
+        SlashAction::SetNote { note } => {
+            let mut user = User::new(&form.user_id)?;
+
+            user.set_status(note);
+            match changes::save(db, &user, Some(&form.channel_id)).await {
+                Ok(_) => {
+                    state.feed.publish_saved(db, &user.id).await;
+                    doc.section("Note updated");
+                }
+                Err(_) => {
+                    doc.section("Failed to update your note. Please try again later");
+                }
+            }
+        }
+
+        SlashAction::ListSites => match Site::fetch_all(db).await {
+            Ok(sites) => {
+                doc.header("Sites:").divider();
+                if sites.is_empty() {
+                    doc.section("No sites have been created");
+                } else {
+                    doc.list(sites.iter().map(|site| mrkdwn(&site.describe())).collect());
+                }
+            }
+            Err(_) => {
+                doc.section("Failed to fetch sites");
+            }
         },
 
-        SlashAction::ParsingFailed(reason) => {
-            mrkdwn!(blocks, "*Oh-no!* Invalid command or arguments");
-            divider!(blocks);
-            mrkdwn!(blocks, reason);
+        SlashAction::CreateSite { .. }
+        | SlashAction::DeleteSite { .. }
+        | SlashAction::SetSiteTz { .. }
+        | SlashAction::SetSiteAddress { .. }
+        | SlashAction::SetSiteCapacity { .. }
+            if auth::role_for(&form.user_id) != Role::Admin =>
+        {
+            return Err(Error::Auth("only admins may manage sites".into()))
         }
-    }
 
-    Ok(tide::Response::builder(StatusCode::Ok)
-        .header("Content-Type", "application/json")
-        .body(json!({ "blocks": blocks }))
-        .build())
+        SlashAction::CreateSite { name } => {
+            doc.section(match Site::new(db, name).await {
+                Ok(site) => Text::from("Site ")
+                    .strong(&site.name)
+                    .text(" successfully created!"),
+                Err(e) => match e.downcast::<Error>() {
+                    Ok(e) => return Err(e),
+                    Err(_) => Text::from(format!(
+                        "Failed to create Site {}, perhaps it already exists?",
+                        name
+                    )),
+                },
+            });
+        }
+
+        SlashAction::DeleteSite { name } => {
+            doc.section(match Site::fetch(db, name).await {
+                Some(site) => match site.delete(db).await {
+                    Ok(_) => Text::from("Site ").strong(name).text(" deleted"),
+                    Err(_) => Text::from("Failed to delete Site ")
+                        .strong(name)
+                        .text(". Please try again later"),
+                },
+                None => not_found("Site", name),
+            });
+        }
+
+        SlashAction::SetSiteTz { site, tz } => {
+            doc.section(match Site::fetch(db, site).await {
+                Some(mut site) => {
+                    site.tz = Some(tz.name().to_owned());
+                    match site.save(db).await {
+                        Ok(_) => Text::from("Timezone of site ")
+                            .strong(&site.name)
+                            .text(format!(" set to {}", tz.name())),
+                        Err(_) => update_failed("Site", &site.name),
+                    }
+                }
+                None => not_found("Site", site),
+            });
+        }
+
+        SlashAction::SetSiteAddress { site, address } => {
+            doc.section(match Site::fetch(db, site).await {
+                Some(mut site) => {
+                    site.address = Some(address);
+                    match site.save(db).await {
+                        Ok(_) => Text::from("Address of site ")
+                            .strong(&site.name)
+                            .text(" updated"),
+                        Err(_) => update_failed("Site", &site.name),
+                    }
+                }
+                None => not_found("Site", site),
+            });
+        }
+
+        SlashAction::SetSiteCapacity { site, capacity } => {
+            doc.section(match Site::fetch(db, site).await {
+                Some(mut site) => {
+                    site.capacity = capacity;
+                    match site.save(db).await {
+                        Ok(_) => match capacity {
+                            Some(capacity) => Text::from("Site ")
+                                .strong(&site.name)
+                                .text(format!(" now has {} desks", capacity)),
+                            None => Text::from("Site ")
+                                .strong(&site.name)
+                                .text(" now has unlimited desks"),
+                        },
+                        Err(_) => update_failed("Site", &site.name),
+                    }
+                }
+                None => not_found("Site", site),
+            });
+        }
+
+        SlashAction::Book { site, day, cancel } => {
+            let site = match Site::fetch(db, site).await {
+                Some(site) => site,
+                None => return Err(Error::NotFound(format!("Site *{}*", site))),
+            };
+
+            let day = match parse_day(day, today_at(&site)) {
+                Ok(day) if day < today_at(&site) => {
+                    return Err(Error::Parse("Desks can't be booked in the past".into()))
+                }
+                Ok(day) => day,
+                Err(e) => return Err(e),
+            };
+
+            let user = match User::fetch_or_create(db, &form.user_id).await {
+                Ok(user) => user,
+                Err(_) => return Err(Error::NotFound("Your user".into())),
+            };
+
+            let result = if cancel {
+                site.cancel_booking(db, &user, day).await
+            } else {
+                site.book(db, &user, day).await
+            };
+
+            match result {
+                Ok(_) => {
+                    let verb = if cancel {
+                        "Cancelled your booking of"
+                    } else {
+                        "Booked"
+                    };
+                    doc.section(
+                        Text::from(format!("{} a desk at ", verb))
+                            .strong(&site.name)
+                            .text(format!(" on {}", day.format("%a %b %-d"))),
+                    );
+
+                    if let Ok(count) = site.headcount(db, day).await {
+                        let capacity = site
+                            .capacity
+                            .map(|capacity| format!(" of {}", capacity))
+                            .unwrap_or_default();
+                        doc.context(format!("{}{} desks booked", count, capacity));
+                    }
+                }
+                Err(e) => match e.downcast::<Error>() {
+                    Ok(e) => return Err(e),
+                    Err(_) => {
+                        doc.section("Failed to update your booking. Please try again later");
+                    }
+                },
+            }
+        }
+
+        SlashAction::ListOffice { site, page } => {
+            let site = match site {
+                Some(name) => match Site::fetch(db, name).await {
+                    Some(site) => Some(site),
+                    None => return Err(Error::NotFound(format!("Site *{}*", name))),
+                },
+                None => None,
+            };
+
+            let offset = (page - 1) * PAGE_SIZE;
+            let name = site.as_ref().map(|site| site.name.as_str());
+            match User::fetch_by_location(db, Location::Office, name, PAGE_SIZE + 1, offset).await {
+                Ok(mut users) => {
+                    let has_more = users.len() as i64 > PAGE_SIZE;
+                    users.truncate(PAGE_SIZE as usize);
+
+                    match &site {
+                        Some(site) => {
+                            doc.header(format!("In the office at {}", site.name))
+                                .context(mrkdwn(&site.describe()));
+                            if let Ok(count) = site.headcount(db, today_at(site)).await {
+                                doc.context(format!("{} desks booked today", count));
+                            }
+                        }
+                        None => {
+                            doc.header("In the office");
+                        }
+                    }
+                    doc.divider();
+                    if users.is_empty() {
+                        doc.section("Nobody has said they're in the office");
+                    }
+
+                    // when not filtered by site, users are already ordered by site
+                    let sites = match &site {
+                        Some(_) => vec![],
+                        None => Site::fetch_all(db).await.unwrap_or_default(),
+                    };
+                    let lines = users
+                        .iter()
+                        .map(|user| {
+                            let line = Text::new().mention(&user.id, &user.id);
+                            let line = match (user.compact_status(), user.plain_status()) {
+                                (Some(compact), plain) => {
+                                    line.text(": ").slack(compact, plain.unwrap_or_default())
+                                }
+                                (None, _) => line,
+                            };
+                            (user.site().map(|site| site.to_owned()), line)
+                        })
+                        .collect();
+
+                    group_by_site(&mut doc, &sites, lines);
+
+                    let command = match site {
+                        Some(site) => format!("office {}", site.name),
+                        None => String::from("office"),
+                    };
+                    page_footer(&mut doc, page, has_more, &command);
+                }
+                Err(_) => {
+                    doc.section("Failed to fetch who is in the office");
+                }
+            }
+        }
+
+        SlashAction::SetLocation { location, site } => {
+            let mut user = User::new(&form.user_id)?;
+
+            // sites must be registered
+            let site = match site {
+                Some(name) => match Site::fetch(db, name).await {
+                    Some(site) => Some(site.name),
+                    None => {
+                        return Err(Error::NotFound(format!(
+                            "Site *{}*. Use `/location site list` to see the available sites",
+                            name
+                        )))
+                    }
+                },
+                None => None,
+            };
+            let site = site.as_deref();
+
+            user.set_location(location, site);
+            match changes::save(db, &user, Some(&form.channel_id)).await {
+                Ok(_) => {
+                    state.feed.publish_saved(db, &user.id).await;
+                    let site = site.map(|site| format!(" ({})", site)).unwrap_or_default();
+                    doc.section(
+                        Text::from("Location set to ")
+                            .slack(format!("{} ", location.emoji()), "")
+                            .text(format!("{}{}", location.as_str(), site)),
+                    );
+                }
+                Err(_) => {
+                    doc.section("Failed to update your location. Please try again later");
+                }
+            }
+        }
+
+        SlashAction::SetAvailability { availability } => {
+            let mut user = User::new(&form.user_id)?;
+
+            user.set_availability(availability);
+            match changes::save(db, &user, Some(&form.channel_id)).await {
+                Ok(_) => {
+                    state.feed.publish_saved(db, &user.id).await;
+                    doc.section(
+                        Text::from("Availability set to ")
+                            .slack(format!("{} ", availability.emoji()), "")
+                            .text(availability.as_str()),
+                    );
+
+                    if availability == Availability::Ooo {
+                        match coverage::validate_leave(db, &user.id).await {
+                            Ok(conflicts) => {
+                                for conflict in conflicts {
+                                    doc.context(
+                                        Text::new()
+                                            .slack(":warning: ", "")
+                                            .append(mrkdwn(&conflict)),
+                                    );
+                                }
+                            }
+                            Err(e) => tracing::error!("Failed to check coverage: {:?}", e),
+                        }
+                    }
+                }
+                Err(_) => {
+                    doc.section("Failed to update your availability. Please try again later");
+                }
+            }
+        }
+
+        SlashAction::ShowCalendars => {
+            let today = user_today(db, &form.user_id).await;
+            let (calendars, leave) = match (
+                Calendar::fetch_by_user(db, &form.user_id).await,
+                Leave::fetch_upcoming(db, &form.user_id, today).await,
+            ) {
+                (Ok(calendars), Ok(leave)) => (calendars, leave),
+                _ => {
+                    doc.section("Failed to fetch your calendars");
+                    return Ok(doc.into());
+                }
+            };
+
+            doc.header("Your Leave").divider();
+            if leave.is_empty() {
+                doc.section("No upcoming leave");
+            } else {
+                doc.list(
+                    leave
+                        .iter()
+                        .map(|leave| Text::from(leave.describe()))
+                        .collect(),
+                );
+            }
+
+            if calendars.is_empty() {
+                doc.context(
+                    Text::from("No calendars. Use ")
+                        .code("/location calendar add <url>")
+                        .text(" to import leave"),
+                );
+            }
+            for calendar in calendars {
+                let synced = match calendar.synced_at {
+                    Some(synced_at) => format!("synced {}", synced_at.format("%b %-d %H:%M UTC")),
+                    None => "not synced yet".to_owned(),
+                };
+                doc.context(
+                    Text::new()
+                        .slack(":calendar: ", "")
+                        .text(format!("{} ({})", calendar.url, synced)),
+                );
+            }
+        }
+
+        SlashAction::SetCalendar { url, remove } => {
+            if remove {
+                doc.section(match Calendar::remove(db, &form.user_id, url).await {
+                    Ok(_) => "Calendar removed. Leave already imported from it is kept",
+                    Err(_) => "Failed to remove your calendar. Please try again later",
+                });
+            } else {
+                let added = match User::fetch_or_create(db, &form.user_id).await {
+                    Ok(_) => Calendar::add(db, &form.user_id, url).await,
+                    Err(e) => Err(e),
+                };
+
+                doc.section(match added {
+                    Ok(_) => "Calendar added. Events marked as out of office or vacation will be imported as leave at the next sync",
+                    Err(e) => match e.downcast::<Error>() {
+                        Ok(e) => return Err(e),
+                        Err(_) => "Failed to add your calendar. Please try again later",
+                    },
+                });
+            }
+        }
+
+        SlashAction::AddLeave { from, to } => {
+            let today = user_today(db, &form.user_id).await;
+            let (starts_on, ends_on) = match (parse_day(from, today), to) {
+                (Ok(starts_on), None) => (starts_on, starts_on),
+                (Ok(starts_on), to) => (starts_on, parse_day(to, starts_on)?),
+                (Err(e), _) => return Err(e),
+            };
+
+            if starts_on < today || ends_on < starts_on {
+                return Err(Error::Parse(
+                    "Leave must start today or later, and end on or after the day it starts".into(),
+                ));
+            }
+
+            let chain = match User::fetch_or_create(db, &form.user_id).await {
+                Ok(_) => ApprovalChain::fetch_by_member(db, &form.user_id).await,
+                Err(e) => Err(e),
+            };
+
+            // leave of members of a team with an approval chain is recorded once approved
+            match chain {
+                Ok(None) => (),
+                Ok(Some(chain)) => {
+                    doc.section(
+                        match approval::request(db, &chain, &form.user_id, starts_on, ends_on).await
+                        {
+                            Ok(approval) => Text::from(format!(
+                                "Leave from {} to {} sent to ",
+                                starts_on.format("%a %b %-d"),
+                                ends_on.format("%a %b %-d")
+                            ))
+                            .mention(&approval.approver_id, &approval.approver_id)
+                            .text(" for approval. You'll get a DM once it's decided"),
+                            Err(_) => Text::from(
+                                "Failed to request approval of your leave. Please try again later",
+                            ),
+                        },
+                    );
+                    return Ok(doc.into());
+                }
+                Err(_) => {
+                    doc.section("Failed to record your leave. Please try again later");
+                    return Ok(doc.into());
+                }
+            }
+
+            let added =
+                Leave::add(db, &form.user_id, starts_on, ends_on, Leave::MANUAL, None).await;
+
+            doc.section(match added {
+                Ok(true) => {
+                    // longer leave comes with a checklist to hand off work before it starts
+                    let handoff = handoff::start(db, &form.user_id, starts_on, ends_on)
+                        .await
+                        .unwrap_or_else(|e| {
+                            tracing::error!(
+                                "failed to send {} a handoff checklist: {:?}",
+                                form.user_id,
+                                e
+                            );
+                            false
+                        });
+
+                    Text::from(format!(
+                        "Leave recorded from {} to {}. You'll be marked out of office each morning{}",
+                        starts_on.format("%a %b %-d"),
+                        ends_on.format("%a %b %-d"),
+                        if handoff {
+                            ". I've sent you a checklist to hand off your work before you go"
+                        } else {
+                            ""
+                        }
+                    ))
+                }
+                Ok(false) => Text::from("You already have leave on some of those days. Use ")
+                    .code("/location calendar")
+                    .text(" to see it"),
+                Err(_) => Text::from("Failed to record your leave. Please try again later"),
+            });
+        }
+
+        SlashAction::CancelLeave { day } => {
+            let today = user_today(db, &form.user_id).await;
+            let day = parse_day(day, today)?;
+
+            doc.section(match Leave::cancel(db, &form.user_id, day).await {
+                Ok(_) => format!("Leave on {} cancelled", day.format("%a %b %-d")),
+                Err(_) => "Failed to cancel your leave. Please try again later".to_owned(),
+            });
+        }
+
+        SlashAction::ShowShifts { team } => {
+            let team = match Team::fetch(db, team).await {
+                Some(team) => team,
+                None => return Err(Error::NotFound(format!("Team *{}*", team))),
+            };
+
+            let (today, all) = match (
+                rota::shifts_on(db, &team, Utc::now().weekday()).await,
+                Shift::fetch_by_team(db, &team).await,
+            ) {
+                (Ok(today), Ok(all)) => (today, all),
+                _ => {
+                    doc.section(Text::from("Failed to fetch shifts of team ").strong(&team.name));
+                    return Ok(doc.into());
+                }
+            };
+
+            doc.header(format!("Today's shifts for {}", team.display_name()))
+                .divider();
+            if today.is_empty() {
+                doc.section("No shifts today");
+            } else {
+                doc.list(
+                    today
+                        .iter()
+                        .map(|(shift, members)| mrkdwn(&rota::describe(shift, members)))
+                        .collect(),
+                );
+            }
+
+            if !all.is_empty() {
+                let mut shifts = Text::from("All shifts: ");
+                for (i, shift) in all.iter().enumerate() {
+                    if i > 0 {
+                        shifts = shifts.text(", ");
+                    }
+                    shifts = shifts
+                        .strong(&shift.name)
+                        .text(format!(" ({})", coverage::describe_days(shift.days)));
+                }
+                doc.context(shifts);
+            }
+        }
+
+        SlashAction::CreateShift {
+            team,
+            name,
+            days,
+            hours,
+        } => {
+            let team = managed_team(db, team, &form.user_id).await?;
+
+            doc.section(match Shift::new(db, &team, name, days, hours).await {
+                Ok(shift) => Text::from("Shift ").strong(&shift.name).text(format!(
+                    " of team {} runs on {}",
+                    team.name,
+                    coverage::describe_days(shift.days)
+                )),
+                Err(e) => match e.downcast::<Error>() {
+                    Ok(e) => return Err(e),
+                    Err(_) => Text::from(format!(
+                        "Failed to create Shift {}, perhaps it already exists?",
+                        name
+                    )),
+                },
+            });
+        }
+
+        SlashAction::DeleteShift { team, name } => {
+            let team = managed_team(db, team, &form.user_id).await?;
+
+            doc.section(match Shift::fetch(db, &team, name).await {
+                Some(shift) => match shift.delete(db).await {
+                    Ok(_) => Text::from("Shift ")
+                        .strong(name)
+                        .text(format!(" of team {} deleted", team.name)),
+                    Err(_) => Text::from("Failed to delete Shift ").strong(name),
+                },
+                None => not_found("Shift", name),
+            });
+        }
+
+        SlashAction::AssignShift {
+            team,
+            name,
+            user,
+            remove,
+        } => {
+            let team = managed_team(db, team, &form.user_id).await?;
+
+            let shift = match Shift::fetch(db, &team, name).await {
+                Some(shift) => shift,
+                None => return Err(Error::NotFound(format!("Shift *{}*", name))),
+            };
+
+            let user = resolve_user(db, user).await?;
+
+            let user = match User::fetch(db, &user).await {
+                Ok(Some(user)) => user,
+                Ok(None) => return Err(Error::NotFound(format!("User *{}*", user))),
+                Err(Error::Parse(_)) => {
+                    return Err(Error::Parse(format!("*{}* is not a valid user", user)))
+                }
+                Err(e) => return Err(e),
+            };
+
+            let mention = Text::new().mention(&user.id, &user.id);
+            doc.section(if remove {
+                match shift.unassign(db, &user).await {
+                    Ok(_) => mention.text(" removed from shift ").strong(&shift.name),
+                    Err(_) => Text::from("Failed to remove ")
+                        .append(mention)
+                        .text(" from shift ")
+                        .strong(&shift.name),
+                }
+            } else {
+                let failed = Text::from("Failed to assign ")
+                    .append(mention.clone())
+                    .text(" to shift ")
+                    .strong(&shift.name);
+                match team.member_role(db, &user).await {
+                    Ok(Some(_)) => match shift.assign(db, &user).await {
+                        Ok(_) => mention.text(" assigned to shift ").strong(&shift.name),
+                        Err(_) => failed,
+                    },
+                    Ok(None) => not_a_member(&user.id, &team),
+                    Err(_) => failed,
+                }
+            });
+        }
+
+        SlashAction::ShowBadge => {
+            doc.section(match badge::badge_url(&form.user_id) {
+                Some(url) => Text::from(format!("Your status badge: {}\nEmbed it with ", url))
+                    .code(format!("![status]({})", url)),
+                None => Text::from("Badges are not enabled"),
+            });
+        }
+
+        SlashAction::Muster { team } => {
+            let team = managed_team(db, team, &form.user_id).await?;
+
+            doc.section(
+                match muster::start(db, &team, &form.channel_id, &form.user_id).await {
+                    Ok(0) => format!("Team {} has no members on Slack to muster", team.name),
+                    Ok(count) => format!(
+                        "Asking {} members of team {} if they're safe. Responses will be summarized in this channel",
+                        count, team.name
+                    ),
+                    Err(e) => {
+                        tracing::error!("Failed to start muster of team {}: {:?}", team.name, e);
+                        format!(
+                            "Failed to start a muster of team {}. Is the bot in this channel?",
+                            team.name
+                        )
+                    }
+                },
+            );
+        }
+
+        SlashAction::Announce {
+            team,
+            message,
+            channel,
+        } => {
+            let team = owned_team(db, team, &form.user_id).await?;
+
+            let channel = if channel {
+                match team.channel.clone() {
+                    Some(channel) => Some(channel),
+                    None => {
+                        return Err(Error::NotFound(format!(
+                            "A channel bound to team *{}*",
+                            team.name
+                        )))
+                    }
+                }
+            } else {
+                None
+            };
+
+            doc.section(
+                match announce::send(
+                    state.pool(),
+                    db,
+                    &team,
+                    &message,
+                    channel.as_deref(),
+                    &form.user_id,
+                )
+                .await
+                {
+                    Ok(announcement) => match channel {
+                        Some(channel) => Text::from("Posting announcement in ")
+                            .slack(format!("<#{}>", channel), channel),
+                        None => Text::from(format!(
+                            "Sending announcement to {} members of team {}. Use ",
+                            announcement.recipients, team.name
+                        ))
+                        .code(format!("/location announce {} status", team.name))
+                        .text(" to check delivery"),
+                    },
+                    Err(e) => {
+                        tracing::error!("Failed to send announcement to {}: {:?}", team.name, e);
+                        Text::from(format!("Failed to send announcement to team {}", team.name))
+                    }
+                },
+            );
+        }
+
+        SlashAction::ShowAutoReply => {
+            doc.section(match AutoReply::fetch(db, &form.user_id).await {
+                Some(settings) if settings.enabled => {
+                    let text = Text::from("Auto-replies are ")
+                        .strong("on")
+                        .text(": mentions of you while you're on leave are answered with your leave dates");
+                    match settings.contact {
+                        Some(contact) => text
+                            .text(", and ")
+                            .mention(&contact, &contact)
+                            .text(" as your contact"),
+                        None => text,
+                    }
+                }
+                _ => Text::from("Auto-replies are ")
+                    .strong("off")
+                    .text(". Use ")
+                    .code("/location autoreply on [contact]")
+                    .text(" to turn them on"),
+            });
+        }
+
+        SlashAction::SetAutoReply { enabled, contact } => {
+            let contact = match contact {
+                Some(contact) => Some(resolve_user(db, contact).await?),
+                None => None,
+            };
+
+            let saved = match User::fetch_or_create(db, &form.user_id).await {
+                Ok(_) => {
+                    AutoReply::new(&form.user_id, enabled, contact)
+                        .save(db)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+
+            doc.section(match saved {
+                Ok(_) if enabled => Text::from("Auto-replies turned ").strong("on"),
+                Ok(_) => Text::from("Auto-replies turned ").strong("off"),
+                Err(_) => Text::from("Failed to save your auto-reply settings"),
+            });
+        }
+
+        SlashAction::ShowDelegate => {
+            doc.section(match User::fetch_delegate(db, &form.user_id).await {
+                Ok(Some(delegate_id)) => Text::new()
+                    .mention(&delegate_id, &delegate_id)
+                    .text(" covers for you while you're away"),
+                Ok(None) => Text::from("You haven't set a delegate. Use ")
+                    .code("/location delegate @user")
+                    .text(" to set one"),
+                Err(_) => Text::from("Failed to fetch your delegate"),
+            });
+        }
+
+        SlashAction::SetDelegate { user } => {
+            let delegate_id = match user {
+                Some(user) => {
+                    let delegate_id = resolve_user(db, user).await?;
+                    if delegate_id == form.user_id {
+                        return Err(Error::Parse("You can't be your own delegate".into()));
+                    }
+                    Some(delegate_id)
+                }
+                None => None,
+            };
+
+            let saved = match User::fetch_or_create(db, &form.user_id).await {
+                Ok(_) => User::set_delegate(db, &form.user_id, delegate_id.as_deref()).await,
+                Err(e) => Err(e),
+            };
+
+            doc.section(match (saved, delegate_id) {
+                (Ok(_), Some(delegate_id)) => Text::new()
+                    .mention(&delegate_id, &delegate_id)
+                    .text(" now covers for you while you're away"),
+                (Ok(_), None) => Text::from("Delegate cleared"),
+                (Err(_), _) => Text::from("Failed to save your delegate"),
+            });
+        }
+
+        SlashAction::ShowContact => {
+            doc.section(match ContactPreference::fetch(db, &form.user_id).await {
+                Ok(Some(preference)) if preference.channel() != ContactChannel::Slack => {
+                    let via = match (preference.channel(), &preference.phone) {
+                        (ContactChannel::Sms, Some(phone)) => format!("text message to {}", phone),
+                        _ => "email to the address on your Slack profile".to_owned(),
+                    };
+                    Text::from(format!(
+                        "You're notified by {} while you're away from Slack. Use ",
+                        via
+                    ))
+                    .code("/location contact slack")
+                    .text(" to be sent DMs instead")
+                }
+                Ok(_) => Text::from("You're notified by Slack DM. Use ")
+                    .code("/location contact email")
+                    .text(" or ")
+                    .code("/location contact sms +15551234567")
+                    .text(" to be notified elsewhere while you're away from Slack"),
+                Err(_) => Text::from("Failed to fetch how you're notified"),
+            });
+        }
+
+        SlashAction::SetContact { channel, phone } => {
+            if !notify::is_configured(channel) {
+                return Err(Error::Parse(format!(
+                    "Notifications by {} aren't set up in this workspace",
+                    channel.as_str()
+                )));
+            }
+
+            // a phone number set earlier is kept when switching channels
+            let existing = ContactPreference::fetch(db, &form.user_id)
+                .await
+                .ok()
+                .flatten()
+                .and_then(|preference| preference.phone);
+            let phone = match phone {
+                Some(phone) => {
+                    validate_phone(phone)?;
+                    Some(phone.to_owned())
+                }
+                None => existing,
+            };
+            if channel == ContactChannel::Sms && phone.is_none() {
+                return Err(Error::Parse(
+                    "Please specify a phone number, e.g. `/location contact sms +15551234567`"
+                        .into(),
+                ));
+            }
+
+            let preference = ContactPreference::new(&form.user_id, channel, phone);
+            let saved = match User::fetch_or_create(db, &form.user_id).await {
+                Ok(_) => preference.save(db).await,
+                Err(e) => Err(e),
+            };
+
+            let has_email = Profile::fetch(db, &form.user_id)
+                .await
+                .and_then(|profile| profile.email)
+                .is_some();
+
+            doc.section(match (saved, channel) {
+                (Ok(_), ContactChannel::Email) if !has_email => {
+                    "You'll be notified by email, but your Slack profile has no email address the bot can read, so you'll be sent DMs until it does".to_owned()
+                }
+                (Ok(_), ContactChannel::Slack) => "You'll be notified by Slack DM".to_owned(),
+                (Ok(_), channel) => format!(
+                    "You'll be notified by {} while you're away from Slack",
+                    channel.as_str()
+                ),
+                (Err(_), _) => "Failed to save how you're notified".to_owned(),
+            });
+        }
+
+        SlashAction::SetAll { team, status, day } => {
+            let team = owned_team(db, team, &form.user_id).await?;
+            let today = Utc::now().date().naive_utc();
+            let day = parse_day(day.as_deref(), today)?;
+            if day < today {
+                return Err(Error::Parse("Statuses can't be set in the past".into()));
+            }
+
+            let mut bulk = match BulkStatus::new(db, &team, &status, day, &form.user_id).await {
+                Ok(bulk) => bulk,
+                Err(_) => {
+                    doc.section(format!("Failed to set the status of team {}", team.name));
+                    return Ok(doc.into());
+                }
+            };
+
+            let undo = format!("/location team {} setall undo", team.name);
+            if day > today {
+                doc.section(
+                    Text::from(format!(
+                        "Every member of team {} will be set to ",
+                        team.name
+                    ))
+                    .strong(&status)
+                    .text(format!(" on {}. Use ", day.format("%a %b %-d")))
+                    .code(undo)
+                    .text(" to cancel"),
+                );
+                return Ok(doc.into());
+            }
+
+            doc.section(match bulk.apply(db).await {
+                Ok(members) => {
+                    for member in &members {
+                        state.feed.publish_saved(db, &member.id).await;
+                    }
+                    Text::from(format!(
+                        "Set {} members of team {} to ",
+                        members.len(),
+                        team.name
+                    ))
+                    .strong(&status)
+                    .text(". Use ")
+                    .code(undo)
+                    .text(" to undo")
+                }
+                Err(_) => Text::from(format!(
+                    "Failed to set the status of team {}, so no members were changed",
+                    team.name
+                )),
+            });
+        }
+
+        SlashAction::UndoSetAll { team } => {
+            let team = owned_team(db, team, &form.user_id).await?;
+            let bulk = match BulkStatus::fetch_latest(db, &team).await {
+                Some(bulk) => bulk,
+                None => {
+                    return Err(Error::NotFound(format!(
+                        "Team-wide status for {}",
+                        team.name
+                    )))
+                }
+            };
+
+            doc.section(match bulk.undo(db).await {
+                Ok(_) if bulk.applied_at.is_none() => {
+                    Text::from(format!("Cancelled setting team {} to ", team.name))
+                        .strong(&bulk.status)
+                }
+                Ok(members) => {
+                    for member in &members {
+                        state.feed.publish_saved(db, &member.id).await;
+                    }
+                    Text::from(format!(
+                        "Restored the previous status of {} members of team {}",
+                        members.len(),
+                        team.name
+                    ))
+                }
+                Err(_) => Text::from(format!("Failed to undo the status of team {}", team.name)),
+            });
+        }
+
+        SlashAction::AckStatus { team, user } => {
+            let team = managed_team(db, team, &form.user_id).await?;
+            let user = resolve_user(db, user).await?;
+
+            let member = match User::fetch(db, &user).await {
+                Ok(Some(member)) => member,
+                Ok(None) => return Err(Error::NotFound(format!("User with id *{}*", user))),
+                Err(Error::Parse(_)) => {
+                    return Err(Error::Parse(format!("*{}* is not a valid user", user)))
+                }
+                Err(e) => return Err(e),
+            };
+
+            let is_member = matches!(team.member_role(db, &member).await, Ok(Some(_)));
+            let mention = Text::new().mention(&member.id, &member.id);
+            doc.section(match member.compact_status() {
+                _ if !is_member => not_a_member(&member.id, &team),
+                Some(status) => {
+                    match StatusAck::save(db, &team, &member.id, &status, &form.user_id).await {
+                        Ok(_) => Text::new()
+                            .slack(":heavy_check_mark: ", "")
+                            .text("Acknowledged ")
+                            .append(mention)
+                            .text("'s status: ")
+                            .slack(status.as_str(), member.plain_status().unwrap_or_default()),
+                        Err(_) => Text::from("Failed to acknowledge ")
+                            .append(mention)
+                            .text("'s status"),
+                    }
+                }
+                None => mention.text(" has not set a status"),
+            });
+        }
+
+        SlashAction::ShowFields { team } => {
+            let team = match Team::fetch(db, team).await {
+                Some(team) => team,
+                None => return Err(Error::NotFound(format!("Team *{}*", team))),
+            };
+
+            doc.section(match TeamField::fetch_by_team(db, &team).await {
+                Ok(fields) if fields.is_empty() => Text::from(format!(
+                    "Team {} doesn't collect any custom fields",
+                    team.name
+                )),
+                Ok(fields) => {
+                    let mut text = Text::from(format!("Team {} collects: ", team.name));
+                    for (i, field) in fields.iter().enumerate() {
+                        if i > 0 {
+                            text = text.text(", ");
+                        }
+                        text = text.strong(&field.label);
+                    }
+                    text.text("\nUse ")
+                        .code("/location set fields")
+                        .text(" to enter yours")
+                }
+                Err(_) => Text::from(format!("Failed to fetch the fields of team {}", team.name)),
+            });
+        }
+
+        SlashAction::AddField { team, label } => {
+            let team = owned_team(db, team, &form.user_id).await?;
+
+            doc.section(match TeamField::add(db, &team, &label).await {
+                Ok(field) => {
+                    Text::from(format!("Team {} now collects ", team.name)).strong(&field.label)
+                }
+                Err(e) => match e.downcast::<Error>() {
+                    Ok(e) => return Err(e),
+                    Err(_) => Text::from("Failed to add field ")
+                        .strong(&label)
+                        .text(", perhaps it already exists?"),
+                },
+            });
+        }
+
+        SlashAction::RemoveField { team, label } => {
+            let team = owned_team(db, team, &form.user_id).await?;
+
+            doc.section(match TeamField::delete(db, &team, &label).await {
+                Ok(true) => {
+                    Text::from(format!("Team {} no longer collects ", team.name)).strong(&label)
+                }
+                Ok(false) => not_found("Field", &label),
+                Err(_) => Text::from("Failed to remove field ").strong(&label),
+            });
+        }
+
+        SlashAction::Wizard => {
+            if let Err(e) = wizard::open(form).await {
+                tracing::error!("Failed to open wizard: {:?}", e);
+                doc.section("Failed to open the wizard. Please try again later");
+            }
+        }
+
+        SlashAction::Setup if auth::role_for(&form.user_id) != Role::Admin => {
+            return Err(Error::Auth("only admins may set up the workspace".into()))
+        }
+
+        SlashAction::Setup => {
+            if let Err(e) = setup::open(&form.user_id, &form.trigger_id).await {
+                tracing::error!("Failed to open setup: {:?}", e);
+                doc.section("Failed to open the setup wizard. Please try again later");
+            }
+        }
+
+        SlashAction::Feedback { message } => {
+            doc.section(
+                match feedback::submit(db, &form.team_id, &form.user_id, &form.channel_id, &message)
+                    .await
+                {
+                    Ok(()) => "Thanks! Your feedback has been sent to the maintainers",
+                    Err(e) => match e.downcast::<Error>() {
+                        Ok(e) => return Err(e),
+                        Err(_) => "Failed to send your feedback. Please try again later",
+                    },
+                },
+            );
+        }
+
+        SlashAction::EditFields => match fields::open(db, &form.user_id, &form.trigger_id).await {
+            Ok(true) => (),
+            Ok(false) => {
+                doc.section("None of your teams collect custom fields");
+            }
+            Err(e) => {
+                tracing::error!("Failed to open fields modal: {:?}", e);
+                doc.section("Failed to open the fields form. Please try again later");
+            }
+        },
+
+        SlashAction::ShowAnnouncement { team } => {
+            let team = owned_team(db, team, &form.user_id).await?;
+
+            doc.section(match announce::describe(db, &team).await {
+                Ok(Some(text)) => mrkdwn(&text),
+                Ok(None) => Text::from(format!(
+                    "No announcements have been sent to team {}",
+                    team.name
+                )),
+                Err(_) => Text::from(format!(
+                    "Failed to fetch the last announcement to team {}",
+                    team.name
+                )),
+            });
+        }
+    }
+
+    Ok(doc.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Markup;
+
+    /// A team with an icon and a description, created by a user
+    fn team() -> Team {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "ops",
+            "description": "Keeps the lights on",
+            "icon": ":rocket:",
+            "created_at": "2020-10-01T12:00:00Z",
+            "created_by": "U9",
+            "notify_changes": false,
+            "coverage_days": 31,
+        }))
+        .unwrap()
+    }
+
+    /// Lays out a status as shown on Slack and in words
+    ///
+    /// # Arguments
+    /// * `location` - Where the member is working
+    /// * `site` - Which site the member is working at, if any
+    /// * `availability` - Whether the member can be reached
+    /// * `note` - The member's free-text note
+    fn status(
+        location: Option<Location>,
+        site: Option<&str>,
+        availability: Option<Availability>,
+        note: Option<&str>,
+    ) -> Option<(String, String)> {
+        compact_status(location, site, availability, note).zip(plain_status(
+            location,
+            site,
+            availability,
+            note,
+        ))
+    }
+
+    /// A lead in the office, a member out of office, a viewer without a status, and a guest,
+    /// ordered by site
+    fn members() -> Vec<(Option<String>, MemberLine)> {
+        let line = |id: &str, role| MemberLine {
+            id: id.to_owned(),
+            guest: None,
+            role,
+            presence: None,
+            status: None,
+            acked: false,
+            meeting: None,
+            covering: None,
+            details: None,
+        };
+
+        vec![
+            (
+                Some("nyc".to_owned()),
+                MemberLine {
+                    presence: Some(":large_green_circle:"),
+                    status: status(
+                        Some(Location::Office),
+                        Some("nyc"),
+                        None,
+                        Some("Standup at 10"),
+                    ),
+                    acked: true,
+                    meeting: Some("in a meeting until 14:30".to_owned()),
+                    details: Some("Badge number: 1234".to_owned()),
+                    ..line("U1", MemberRole::Lead)
+                },
+            ),
+            (
+                None,
+                MemberLine {
+                    status: status(None, None, Some(Availability::Ooo), None),
+                    covering: Some("U1".to_owned()),
+                    ..line("U2", MemberRole::Member)
+                },
+            ),
+            (None, line("U3", MemberRole::Viewer)),
+            (
+                None,
+                MemberLine {
+                    guest: Some("Jane Doe".to_owned()),
+                    status: status(Some(Location::Remote), None, None, None),
+                    ..line("G1", MemberRole::Member)
+                },
+            ),
+        ]
+    }
+
+    /// The first page of a team's statuses, with more to come
+    fn team_view() -> ResponseDoc {
+        let mut doc = ResponseDoc::new();
+        team_heading(&mut doc, &team());
+
+        let lines = members()
+            .into_iter()
+            .map(|(site, line)| (site, line.to_text(false)))
+            .collect();
+        group_by_site(&mut doc, &[], lines);
+        page_footer(&mut doc, 1, true, "ops");
+        doc
+    }
+
+    #[test]
+    fn team_view_blocks() {
+        insta::assert_json_snapshot!(SlashResponse::from(team_view()).to_json(), @r###"
+            {
+              "blocks": [
+                {
+                  "text": {
+                    "text": ":rocket: ops Status",
+                    "type": "plain_text"
+                  },
+                  "type": "header"
+                },
+                {
+                  "elements": [
+                    {
+                      "text": "Keeps the lights on",
+                      "type": "mrkdwn"
+                    }
+                  ],
+                  "type": "context"
+                },
+                {
+                  "elements": [
+                    {
+                      "text": "Created by <@U9> on 2020-10-01",
+                      "type": "mrkdwn"
+                    }
+                  ],
+                  "type": "context"
+                },
+                {
+                  "type": "divider"
+                },
+                {
+                  "elements": [
+                    {
+                      "text": ":office: *nyc*",
+                      "type": "mrkdwn"
+                    }
+                  ],
+                  "type": "context"
+                },
+                {
+                  "text": {
+                    "text": "<@U1> :star: _lead_ :large_green_circle:: :office: nyc Standup at 10 :heavy_check_mark: _(in a meeting until 14:30)_ (Badge number: 1234)",
+                    "type": "mrkdwn"
+                  },
+                  "type": "section"
+                },
+                {
+                  "elements": [
+                    {
+                      "text": "No site",
+                      "type": "mrkdwn"
+                    }
+                  ],
+                  "type": "context"
+                },
+                {
+                  "text": {
+                    "text": "<@U2>: :palm_tree: (covering: <@U1>)",
+                    "type": "mrkdwn"
+                  },
+                  "type": "section"
+                },
+                {
+                  "text": {
+                    "text": "<@U3> _viewer_ has not set a status",
+                    "type": "mrkdwn"
+                  },
+                  "type": "section"
+                },
+                {
+                  "text": {
+                    "text": "*Jane Doe* _guest_: :house_with_garden:",
+                    "type": "mrkdwn"
+                  },
+                  "type": "section"
+                },
+                {
+                  "elements": [
+                    {
+                      "text": "Page 1. Use `/location ops 2` to see more",
+                      "type": "mrkdwn"
+                    }
+                  ],
+                  "type": "context"
+                }
+              ],
+              "response_type": "ephemeral",
+              "text": ":rocket: ops Status\n<@U1> :star: _lead_ :large_green_circle:: :office: nyc Standup at 10 :heavy_check_mark: _(in a meeting until 14:30)_ (Badge number: 1234)\n<@U2>: :palm_tree: (covering: <@U1>)\n<@U3> _viewer_ has not set a status\n*Jane Doe* _guest_: :house_with_garden:"
+            }
+        "###);
+    }
+
+    #[test]
+    fn team_view_plain_text() {
+        insta::assert_snapshot!(team_view().render(Markup::Plain), @r###"
+            :rocket: ops Status
+
+            Keeps the lights on
+            Created by U9 on 2020-10-01
+            nyc
+            U1 lead: office (nyc): Standup at 10 (acknowledged) (in a meeting until 14:30) (Badge number: 1234)
+            No site
+            U2: ooo (covering: U1)
+            U3 viewer has not set a status
+            Jane Doe guest: remote
+            Page 1. Use /location ops 2 to see more
+        "###);
+    }
+
+    #[test]
+    fn team_view_on_a_past_day() {
+        let lines = members()
+            .into_iter()
+            .map(|(site, line)| (site, line.to_text(true)))
+            .collect();
+
+        let mut doc = ResponseDoc::new();
+        group_by_site(&mut doc, &[], lines);
+        page_footer(&mut doc, 2, false, "ops on 2020-10-19");
+        insta::assert_snapshot!(doc.render(Markup::Mrkdwn), @r###"
+            :office: *nyc*
+            <@U1> :star: _lead_ :large_green_circle:: :office: nyc Standup at 10 :heavy_check_mark: _(in a meeting until 14:30)_ (Badge number: 1234)
+            No site
+            <@U2>: :palm_tree: (covering: <@U1>)
+            <@U3> _viewer_ had not set a status
+            *Jane Doe* _guest_: :house_with_garden:
+            Page 2
+        "###);
+    }
+
+    #[test]
+    fn many_sites_are_not_grouped() {
+        let lines = (0..=MAX_SITE_GROUPS)
+            .map(|i| (Some(format!("site{}", i)), Text::new().mention("U1", "U1")))
+            .collect();
+
+        let mut doc = ResponseDoc::new();
+        group_by_site(&mut doc, &[], lines);
+        insta::assert_snapshot!(doc.render(Markup::Mrkdwn), @r###"
+            <@U1>
+            <@U1>
+            <@U1>
+            <@U1>
+            <@U1>
+            <@U1>
+        "###);
+    }
+
+    #[test]
+    fn timeline() {
+        let monday = NaiveDate::from_ymd(2020, 10, 19);
+        let fields = timeline_fields(&[], Tz::UTC, monday, monday + Duration::days(2));
+
+        let mut doc = ResponseDoc::new();
+        doc.fields(fields);
+        insta::assert_json_snapshot!(doc.to_blocks(), @r###"
+            [
+              {
+                "fields": [
+                  {
+                    "text": "*Mon Oct 19*\n_no status_",
+                    "type": "mrkdwn"
+                  },
+                  {
+                    "text": "*Tue Oct 20*\n_no status_",
+                    "type": "mrkdwn"
+                  },
+                  {
+                    "text": "*Wed Oct 21*\n_no status_",
+                    "type": "mrkdwn"
+                  },
+                  {
+                    "text": "*Thu Oct 22*\n—",
+                    "type": "mrkdwn"
+                  },
+                  {
+                    "text": "*Fri Oct 23*\n—",
+                    "type": "mrkdwn"
+                  },
+                  {
+                    "text": "*Sat Oct 24*\n—",
+                    "type": "mrkdwn"
+                  },
+                  {
+                    "text": "*Sun Oct 25*\n—",
+                    "type": "mrkdwn"
+                  }
+                ],
+                "type": "section"
+              }
+            ]
+        "###);
+    }
+
+    #[test]
+    fn confirmations() {
+        let mut doc = ResponseDoc::new();
+        doc.section(not_found("Team", "ops"))
+            .section(update_failed("Site", "nyc"))
+            .section(invalid_user("<bob>"))
+            .section(not_a_member("U2", &team()));
+        insta::assert_json_snapshot!(SlashResponse::from(doc).to_json(), @r###"
+            {
+              "blocks": [
+                {
+                  "text": {
+                    "text": "Team *ops* not found",
+                    "type": "mrkdwn"
+                  },
+                  "type": "section"
+                },
+                {
+                  "text": {
+                    "text": "Failed to update Site *nyc*. Please try again later",
+                    "type": "mrkdwn"
+                  },
+                  "type": "section"
+                },
+                {
+                  "text": {
+                    "text": "*&lt;bob&gt;* is not a valid user",
+                    "type": "mrkdwn"
+                  },
+                  "type": "section"
+                },
+                {
+                  "text": {
+                    "text": "<@U2> is not a member of team ops",
+                    "type": "mrkdwn"
+                  },
+                  "type": "section"
+                }
+              ],
+              "response_type": "ephemeral",
+              "text": "Team *ops* not found\nFailed to update Site *nyc*. Please try again later\n*&lt;bob&gt;* is not a valid user\n<@U2> is not a member of team ops"
+            }
+        "###);
+    }
 }
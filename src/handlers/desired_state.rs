@@ -0,0 +1,68 @@
+//! Webhook that reconciles the database with the desired state, for use after a push
+//!
+//! Requests must be signed with `DESIRED_STATE_SECRET` the way GitHub signs webhooks.  If
+//! `DESIRED_STATE_SECRET` is not set, or the bot wasn't started with `--desired-state`, the
+//! webhook is disabled.
+//!
+//! With `?dry_run=true`, the drift is reported without being corrected.
+
+use crate::{desired_state, signing, State};
+use serde::Deserialize;
+use serde_json::json;
+use tide::StatusCode;
+
+/// Query string parameters accepted by the webhook
+#[derive(Debug, Default, Deserialize)]
+struct ReconcileQuery {
+    /// Only report the drift, without correcting it
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Handle a `POST` request to `/desired-state`, responding with the drift that was corrected
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn reconcile(mut req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let (dir, secret) = match (
+        req.state().desired_state.clone(),
+        dotenv::var("DESIRED_STATE_SECRET"),
+    ) {
+        (Some(dir), Ok(secret)) => (dir, secret),
+        _ => return Ok(tide::Response::builder(StatusCode::NotFound).build()),
+    };
+
+    let body = req.body_bytes().await?;
+    let verified = req
+        .header("X-Hub-Signature-256")
+        .map(|values| signing::verify_github(&secret, &body, values.last().as_str()))
+        .unwrap_or(false);
+
+    if !verified {
+        tracing::warn!("Rejected desired state webhook with invalid signature");
+        return Ok(tide::Response::builder(StatusCode::Unauthorized).build());
+    }
+
+    let dry_run = req
+        .query::<ReconcileQuery>()
+        .map(|query| query.dry_run)
+        .unwrap_or_default();
+
+    let resp = match desired_state::run(&req.state().pool(), &dir, dry_run).await {
+        Ok(drift) => tide::Response::builder(StatusCode::Ok)
+            .body(json!({
+                "dry_run": dry_run,
+                "changes": drift.changes,
+                "unmanaged": drift.unmanaged,
+            }))
+            .build(),
+        Err(e) => {
+            tracing::error!("Failed to reconcile desired state: {:?}", e);
+            tide::Response::builder(StatusCode::UnprocessableEntity)
+                .body(json!({ "error": format!("{:#}", e) }))
+                .build()
+        }
+    };
+
+    Ok(resp)
+}
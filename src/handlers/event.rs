@@ -1,9 +1,18 @@
 //! Handle callback events
 
-use crate::{models::User, SqlConn};
+use crate::{
+    auto_reply, changes,
+    error::Error,
+    feed::StatusFeed,
+    handlers::workflow::{self, WorkflowStep},
+    models::{ProcessedEvent, User},
+    outbox::{self, Effect},
+    runtime, slack, SqlConn, SqlPool, State,
+};
 use anyhow::Result;
+use chrono::{Duration, Utc};
+use futures::{channel::mpsc, StreamExt};
 use serde::Deserialize;
-use serde_json::json;
 use tide::StatusCode;
 
 /// Specific types of events that our bot is registered to receive
@@ -28,14 +37,22 @@ pub enum AppEvent {
         user: String,
         text: String,
         ts: String,
+        thread_ts: Option<String>,
         event_ts: String,
         channel_type: String,
     },
+
+    /// This event occurs when a workflow containing one of our steps reaches that step
+    #[serde(alias = "workflow_step_execute")]
+    WorkflowStepExecute {
+        callback_id: String,
+        workflow_step: WorkflowStep,
+    },
 }
 
 /// Structure received via `POST` request for registering a form
 #[derive(Debug, Deserialize)]
-struct Event {
+pub struct Event {
     /// This depcrecated verification token is proof the request is coming from Slack
     pub token: String,
 
@@ -62,36 +79,125 @@ struct Event {
     pub event_time: u64,
 }
 
+/// Sends parsed events to the event worker
+pub type EventSender = mpsc::Sender<Event>;
+
+/// How often processed events older than the deduplication window are forgotten
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
 /// Handle the event callback from a `POST` request
 ///
+/// The event is only parsed here; processing happens on the event worker so Slack
+/// receives a response well within its 3 second deadline.
+///
 /// # Arguments
 /// * `body` - The body of the POST request
-/// * `db` - Conenction to the sql database
-pub async fn callback(body: &[u8], db: &mut SqlConn) -> tide::Result<tide::Response> {
+/// * `state` - Shared application state
+pub async fn callback(body: &[u8], state: &State) -> tide::Result<tide::Response> {
     // deserialize into the actual event type
     let event: Event = match serde_json::from_slice(body) {
         Ok(e) => e,
-        Err(e) => {
-            tracing::error!("Callback parse error: {:?}", e);
-
-            // if parsing fails, just respond with `200 OK` else slack will ban our bot eventually
-            return Ok(tide::Response::builder(StatusCode::Ok).build());
-        }
+        // if parsing fails, just respond with `200 OK` else slack will ban our bot eventually
+        Err(e) => return Ok(Error::from(e).into_event_ack()),
     };
 
-    handle_app_event(event.event, db).await?;
+    // if the queue is full, let slack retry the event later
+    if let Err(e) = state.events.clone().try_send(event) {
+        tracing::error!("Failed to queue event: {:?}", e);
+        return Ok(tide::Response::builder(StatusCode::ServiceUnavailable).build());
+    }
 
     let resp = tide::Response::builder(StatusCode::Ok).build();
 
     Ok(resp)
 }
 
+/// Spawns the worker that processes events received from Slack, one at a time
+///
+/// Events are remembered for `dedup_ttl`, so redeliveries within it are ignored, and then
+/// forgotten
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `feed` - Feed to publish status changes to
+/// * `capacity` - Number of events that can be queued before new events are rejected
+/// * `dedup_ttl` - How long processed events are remembered
+pub fn spawn_worker(
+    pool: SqlPool,
+    feed: StatusFeed,
+    capacity: usize,
+    dedup_ttl: Duration,
+) -> EventSender {
+    let (tx, mut rx) = mpsc::channel::<Event>(capacity);
+
+    let purge_pool = pool.clone();
+    runtime::spawn(async move {
+        loop {
+            let purged = match purge_pool.acquire().await {
+                Ok(mut db) => ProcessedEvent::purge(&mut db, Utc::now() - dedup_ttl).await,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = purged {
+                tracing::error!("Failed to forget processed events: {:?}", e);
+            }
+
+            runtime::sleep(PURGE_INTERVAL).await;
+        }
+    });
+
+    runtime::spawn(async move {
+        while let Some(event) = rx.next().await {
+            let event_id = event.event_id.clone();
+            if let Err(e) = process(&pool, &feed, event, dedup_ttl).await {
+                tracing::error!("Failed to handle event {}: {:?}", event_id, e);
+            }
+        }
+    });
+
+    tx
+}
+
+/// Processes a single queued event
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `feed` - Feed to publish status changes to
+/// * `event` - The event to process
+/// * `dedup_ttl` - How long processed events are remembered
+pub(crate) async fn process(
+    pool: &SqlPool,
+    feed: &StatusFeed,
+    event: Event,
+    dedup_ttl: Duration,
+) -> Result<()> {
+    let mut db = pool.acquire().await?;
+
+    // retries of events we've already processed are ignored
+    if ProcessedEvent::fetch(&mut db, &event.event_id, Utc::now() - dedup_ttl)
+        .await
+        .is_some()
+    {
+        tracing::debug!("ignoring already processed event {}", event.event_id);
+        return Ok(());
+    }
+
+    handle_app_event(event.event, feed, &mut db).await?;
+    ProcessedEvent::record(&mut db, &event.event_id).await?;
+
+    Ok(())
+}
+
 /// Handle the actual event received after it has been unpacked
 ///
 /// # Arguments
 /// * `app_event` - Specific event received
+/// * `feed` - Feed to publish status changes to
 /// * `db` - Connection to the SQL database
-pub async fn handle_app_event(app_event: AppEvent, db: &mut SqlConn) -> Result<()> {
+pub async fn handle_app_event(
+    app_event: AppEvent,
+    feed: &StatusFeed,
+    db: &mut SqlConn,
+) -> Result<()> {
     match app_event {
         AppEvent::AppMention {
             user,
@@ -99,26 +205,36 @@ pub async fn handle_app_event(app_event: AppEvent, db: &mut SqlConn) -> Result<(
             channel,
             event_ts,
             ..
-        } => handle_mention(db, user, text, channel, event_ts).await,
+        } => handle_mention(db, feed, user, text, channel, event_ts).await,
 
         AppEvent::Message {
             user,
             text,
             channel,
+            ts,
+            thread_ts,
+            channel_type,
             ..
-        } => handle_message(db, user, text, channel).await,
+        } => handle_message(db, feed, user, text, channel, ts, thread_ts, channel_type).await,
+
+        AppEvent::WorkflowStepExecute {
+            callback_id,
+            workflow_step,
+        } => workflow::execute(db, feed, &callback_id, workflow_step).await,
     }
 }
 
 /// Handles an `app_mention` event
 ///
 /// # Arguments
+/// * `feed` - Feed to publish status changes to
 /// * `user` - User who mentioned the bot
 /// * `text` - Text the user entered
 /// * `channel` - What channel this occured in
 /// * `event_ts` - The timestamp the event occured (used in response to add emoji)
 pub async fn handle_mention(
     db: &mut SqlConn,
+    feed: &StatusFeed,
     user: String,
     text: String,
     channel: String,
@@ -130,31 +246,22 @@ pub async fn handle_mention(
         .map(|s| s.to_owned())
         .unwrap_or_else(|| text);
 
-    let mut user = User::new(user);
+    let mut user = User::new(&user)?;
     user.set_status(status);
-    user.save(&mut *db).await?;
 
     // Respond with a thumbs up to let the user know the message has been received
-    let resp = surf::post("https://slack.com/api/reactions.add")
-        .set_header(
-            "Authorization",
-            format!(
-                "Bearer {}",
-                dotenv::var("SLACK_BOT_TOKEN").unwrap_or_else(|_| "".to_owned())
-            ),
-        )
-        .body_json(&json!({
-            "channel": channel,
-            "name": "thumbsup",
-            "timestamp": event_ts
-        }))?
-        .await
-        .unwrap();
+    let reaction = Effect::AddReaction {
+        channel: channel.clone(),
+        name: "thumbsup".to_owned(),
+        timestamp: event_ts,
+    };
 
-    let code = resp.status();
-    if code.is_client_error() || code.is_server_error() {
-        tracing::error!("Failed to post ephemeral message: {}", resp.status());
-    }
+    transaction!(db, async {
+        user.save(&mut *db).await?;
+        changes::notify(&mut *db, &user, Some(&channel)).await?;
+        outbox::enqueue(&mut *db, &reaction).await
+    })?;
+    feed.publish_saved(&mut *db, &user.id).await;
 
     Ok(())
 }
@@ -162,20 +269,64 @@ pub async fn handle_mention(
 /// Handles an `app_mention` event
 ///
 /// # Arguments
+/// * `feed` - Feed to publish status changes to
 /// * `user` - User who mentioned the bot
 /// * `text` - Text the user entered
 /// * `channel` - What channel this occured in
+/// * `ts` - Timestamp of the message
+/// * `thread_ts` - Timestamp of the thread's first message, if the message is a reply
+/// * `channel_type` - Type of the channel (e.g., `channel` or `im`)
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_message(
     db: &mut SqlConn,
+    feed: &StatusFeed,
     user: String,
     text: String,
-    _channel: String,
+    channel: String,
+    ts: String,
+    thread_ts: Option<String>,
+    channel_type: String,
 ) -> Result<()> {
     // TODO verify the channel is daily_status
 
-    let mut user = User::new(user);
+    // a DM is for the user on the other side, whether or not it mentions them
+    let dm_user = if channel_type == "im" {
+        match slack::conversations_im_user(&channel).await {
+            Ok(dm_user) => dm_user,
+            Err(e) => {
+                tracing::error!("Failed to look up the user in DM {}: {:?}", channel, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Err(e) = auto_reply::reply(
+        &mut *db,
+        &user,
+        &text,
+        &channel,
+        &ts,
+        thread_ts.as_deref(),
+        dm_user.as_deref(),
+    )
+    .await
+    {
+        tracing::error!("Failed to send auto-replies: {:?}", e);
+    }
+
+    let mut user = User::new(&user)?;
     user.set_status(text);
-    user.save(&mut *db).await?;
+
+    // statuses sent by DM don't show up in any channel, so every bound channel is notified
+    let source = if channel_type == "im" {
+        None
+    } else {
+        Some(channel.as_str())
+    };
+    changes::save(&mut *db, &user, source).await?;
+    feed.publish_saved(&mut *db, &user.id).await;
 
     // Note: since this is a passive monitor, we don't acknowledge receiving the messages
 
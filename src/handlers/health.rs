@@ -0,0 +1,51 @@
+//! Health of the bot and the providers it depends on
+//!
+//! `/health` responds with `503 Service Unavailable` if the database can't be reached, since
+//! the bot can't do anything without it.  Outages of Slack or integrations only mark the bot
+//! `degraded`, with the state of each provider's circuit breaker, as restarting the bot
+//! wouldn't fix them.
+
+use crate::{breaker, HasDb, State};
+use serde_json::{json, Map, Value};
+use tide::StatusCode;
+
+/// Handle a `GET` request to `/health`
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request
+pub async fn health(req: tide::Request<State>) -> tide::Result<tide::Response> {
+    let database = req.db().await.is_ok();
+
+    let breakers = breaker::breakers();
+    let degraded = breakers
+        .iter()
+        .any(|(_, breaker)| breaker.state != breaker::State::Closed);
+
+    let providers: Map<String, Value> = breakers
+        .into_iter()
+        .map(|(host, breaker)| {
+            let provider = json!({
+                "state": breaker.state,
+                "failures": breaker.failures,
+                "opened_secs_ago": breaker
+                    .opened_at
+                    .map(|opened_at| opened_at.elapsed().as_secs()),
+            });
+            (host, provider)
+        })
+        .collect();
+
+    let (code, status) = match (database, degraded) {
+        (false, _) => (StatusCode::ServiceUnavailable, "unavailable"),
+        (true, true) => (StatusCode::Ok, "degraded"),
+        (true, false) => (StatusCode::Ok, "ok"),
+    };
+
+    Ok(tide::Response::builder(code)
+        .body(json!({
+            "status": status,
+            "database": if database { "ok" } else { "unavailable" },
+            "providers": providers,
+        }))
+        .build())
+}
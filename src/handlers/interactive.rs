@@ -0,0 +1,231 @@
+//! Handle interactivity payloads (button clicks, modal submissions, workflow steps, etc.)
+
+use crate::{
+    approval,
+    extract::{AppState, Db, Form},
+    fields,
+    handlers::{
+        command::{self, SlashCommand},
+        workflow,
+    },
+    handoff, limits, muster, setup, suggest, wizard,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use tide::StatusCode;
+
+/// Interactivity requests are form encoded, with a single JSON encoded `payload` field
+#[derive(Debug, Deserialize)]
+pub struct InteractiveForm {
+    pub payload: String,
+}
+
+/// An interactive component that was used
+#[derive(Debug, Deserialize)]
+pub(crate) struct BlockAction {
+    /// Identifies the component (e.g., `muster_safe`)
+    action_id: String,
+
+    /// Value attached to the component, if any
+    value: Option<String>,
+
+    /// Identifies the block containing the component
+    block_id: Option<String>,
+
+    /// Options selected in the component, for checkboxes and multi-selects
+    #[serde(default)]
+    selected_options: Vec<Value>,
+}
+
+/// The interactions our bot handles
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub(crate) enum Interaction {
+    /// A workflow step was added to, or edited in, a workflow
+    #[serde(alias = "workflow_step_edit")]
+    WorkflowStepEdit {
+        callback_id: String,
+        trigger_id: String,
+        workflow_step: workflow::WorkflowStep,
+    },
+
+    /// A modal was submitted
+    #[serde(alias = "view_submission")]
+    ViewSubmission {
+        view: Value,
+        workflow_step: Option<workflow::WorkflowStep>,
+        #[serde(default)]
+        user: Value,
+    },
+
+    /// A button (or other interactive component) in a message was pressed
+    #[serde(alias = "block_actions")]
+    BlockActions {
+        user: Value,
+        actions: Vec<BlockAction>,
+        container: Value,
+    },
+
+    /// A select menu with external options needs the options matching what's been typed
+    #[serde(alias = "block_suggestion")]
+    BlockSuggestion { block_id: String, value: String },
+
+    /// All other interactions are ignored
+    #[serde(other)]
+    Unknown,
+}
+
+/// Handle a `POST` request to the `/interactive` endpoint
+///
+/// # Arguments
+/// * `form` - The signed interactivity payload
+/// * `db` - Connection to the database
+/// * `state` - Shared application state
+pub async fn interactive(
+    (Form(form), Db(mut db), AppState(state)): (Form<InteractiveForm>, Db, AppState),
+) -> tide::Result<tide::Response> {
+    if !limits::json_depth_ok(form.payload.as_bytes(), limits::MAX_JSON_DEPTH) {
+        return Ok(tide::Response::builder(StatusCode::BadRequest).build());
+    }
+
+    let interaction: Interaction = match serde_json::from_str(&form.payload) {
+        Ok(interaction) => interaction,
+        Err(e) => {
+            tracing::error!("Interactive payload parse error: {:?}", e);
+            return Ok(tide::Response::builder(StatusCode::Ok).build());
+        }
+    };
+
+    let result = match interaction {
+        Interaction::WorkflowStepEdit {
+            callback_id,
+            trigger_id,
+            workflow_step,
+        } => workflow::edit(&callback_id, &trigger_id, &workflow_step).await,
+
+        Interaction::ViewSubmission {
+            view,
+            workflow_step: Some(workflow_step),
+            ..
+        } if view["type"] == "workflow_step" => {
+            let callback_id = view["callback_id"].as_str().unwrap_or("");
+            workflow::save(callback_id, &workflow_step, &view["state"]["values"]).await
+        }
+
+        Interaction::ViewSubmission { view, user, .. }
+            if view["callback_id"] == fields::CALLBACK_ID =>
+        {
+            let user_id = user["id"].as_str().unwrap_or("");
+            fields::save(&mut db, user_id, &view["state"]["values"]).await
+        }
+
+        Interaction::ViewSubmission { view, .. } if view["callback_id"] == wizard::CALLBACK_ID => {
+            let payload: Value = serde_json::from_str(&form.payload)?;
+            if let Some(errors) = wizard::submit(&state, &payload) {
+                // errors are shown next to the inputs, keeping the modal open
+                return Ok(tide::Response::builder(StatusCode::Ok).body(errors).build());
+            }
+            Ok(())
+        }
+
+        Interaction::ViewSubmission { view, user, .. }
+            if setup::is_step(view["callback_id"].as_str().unwrap_or("")) =>
+        {
+            let user_id = user["id"].as_str().unwrap_or("");
+            match setup::submit(&mut db, user_id, &view).await {
+                // the next step, or errors shown next to the inputs
+                Ok(Some(response)) => {
+                    return Ok(tide::Response::builder(StatusCode::Ok)
+                        .body(response)
+                        .build())
+                }
+                Ok(None) => Ok(()),
+                Err(e) => Err(e),
+            }
+        }
+
+        Interaction::BlockSuggestion { block_id, value } if block_id == wizard::TEAM_BLOCK => {
+            match wizard::team_options(&mut db, &value).await {
+                Ok(options) => {
+                    return Ok(tide::Response::builder(StatusCode::Ok)
+                        .body(options)
+                        .build())
+                }
+                Err(e) => Err(e),
+            }
+        }
+
+        Interaction::BlockActions {
+            user,
+            actions,
+            container,
+        } => match actions.first() {
+            Some(BlockAction {
+                action_id,
+                value: Some(value),
+                ..
+            }) if action_id == muster::SAFE_ACTION || action_id == muster::UNSAFE_ACTION => {
+                let user_id = user["id"].as_str().unwrap_or("");
+                let safe = action_id == muster::SAFE_ACTION;
+                muster::respond(&mut db, value, user_id, safe, &container).await
+            }
+            Some(BlockAction {
+                action_id,
+                value: Some(value),
+                ..
+            }) if action_id == approval::APPROVE_ACTION || action_id == approval::DENY_ACTION => {
+                let user_id = user["id"].as_str().unwrap_or("");
+                let approved = action_id == approval::APPROVE_ACTION;
+                approval::respond(&mut db, value, user_id, approved, &container).await
+            }
+            Some(BlockAction {
+                action_id,
+                value: Some(value),
+                ..
+            }) if action_id == suggest::RERUN_ACTION => {
+                let payload: Value = serde_json::from_str(&form.payload)?;
+                let str_of = |value: &Value| value.as_str().unwrap_or_default().to_owned();
+                let command = SlashCommand {
+                    token: String::new(),
+                    command: "/location".to_owned(),
+                    text: value.to_owned(),
+                    response_url: str_of(&payload["response_url"]),
+                    trigger_id: str_of(&payload["trigger_id"]),
+                    user_id: str_of(&user["id"]),
+                    user_name: String::new(),
+                    team_id: str_of(&user["team_id"]),
+                    channel_id: str_of(&payload["channel"]["id"]),
+                    api_app_id: str_of(&payload["api_app_id"]),
+                };
+
+                // the corrected command replaces the error it was suggested in
+                command::spawn_run(&state, command, true);
+                Ok(())
+            }
+            Some(BlockAction {
+                action_id,
+                block_id: Some(block_id),
+                selected_options,
+                ..
+            }) if action_id == handoff::CHECK_ACTION => {
+                let user_id = user["id"].as_str().unwrap_or("");
+                let selected: Vec<String> = selected_options
+                    .iter()
+                    .filter_map(|option| option["value"].as_str())
+                    .map(str::to_owned)
+                    .collect();
+                handoff::respond(&mut db, block_id, user_id, &selected, &container).await
+            }
+            _ => Ok(()),
+        },
+
+        _ => Ok(()),
+    };
+
+    if let Err(e) = result {
+        tracing::error!("Failed to handle interaction: {:?}", e);
+    }
+
+    // interactions must always be acknowledged, else slack shows the user an error
+    Ok(tide::Response::builder(StatusCode::Ok).build())
+}
@@ -0,0 +1,131 @@
+//! Live status updates over WebSockets
+//!
+//! Users signed in to the web UI (see `handlers::auth`) may connect with their session.  Other
+//! clients need a token, the hex encoded HMAC-SHA256 of `live` keyed with `LIVE_SECRET`,
+//! passed as `?token=`.  If `LIVE_SECRET` is not set, only signed-in users may connect.
+
+use crate::{
+    feed::StatusChange, handlers::auth::session_user, models::User, runtime, HasDb, State,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use hmac::{Hmac, Mac, NewMac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::Duration;
+use tide::{Middleware, Next, StatusCode};
+use tide_websockets::WebSocketConnection;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How long to wait without a status change before sending a heartbeat
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Message signed to build the access token
+const TOKEN_MESSAGE: &[u8] = b"live";
+
+/// Query string parameters accepted by the live endpoint
+#[derive(Debug, Deserialize)]
+struct LiveQuery {
+    /// Access token for the endpoint
+    token: Option<String>,
+}
+
+/// Computes the mac of the access token, returning `None` if tokens are disabled
+fn mac() -> Option<HmacSha256> {
+    let secret = dotenv::var("LIVE_SECRET").ok()?;
+    let mut mac = HmacSha256::new_varkey(secret.as_bytes()).ok()?;
+    mac.update(TOKEN_MESSAGE);
+
+    Some(mac)
+}
+
+/// Returns the access token of the endpoint, returning `None` if tokens are disabled
+pub fn live_token() -> Option<String> {
+    Some(hex::encode(mac()?.finalize().into_bytes()))
+}
+
+/// Checks a token supplied with a request to the endpoint
+///
+/// # Arguments
+/// * `token` - Token supplied by the client
+fn verify_token(token: &str) -> bool {
+    match (mac(), hex::decode(token)) {
+        (Some(mac), Ok(token)) => mac.verify(&token).is_ok(),
+        _ => false,
+    }
+}
+
+/// Middleware rejecting requests to the endpoint without a signed-in session or a valid token,
+/// before the connection is upgraded
+pub struct RequireLiveAccess;
+
+#[async_trait]
+impl<S: Clone + Send + Sync + 'static> Middleware<S> for RequireLiveAccess {
+    async fn handle(&self, req: tide::Request<S>, next: Next<'_, S>) -> tide::Result {
+        let authorized = session_user(&req).is_some()
+            || match req.query::<LiveQuery>() {
+                Ok(LiveQuery { token: Some(token) }) => verify_token(&token),
+                _ => false,
+            };
+
+        if !authorized {
+            return Ok(tide::Response::builder(StatusCode::Unauthorized).build());
+        }
+
+        Ok(next.run(req).await)
+    }
+}
+
+/// A message sent to a connected client
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LiveMessage {
+    /// Current status of every known user, sent when a client first connects
+    Snapshot { statuses: Vec<StatusChange> },
+
+    /// A single user's status has changed
+    Status(StatusChange),
+
+    /// Sent periodically to keep idle connections open
+    Heartbeat,
+}
+
+/// Handles a WebSocket connection to the `/ws` endpoint
+///
+/// Sends a snapshot of all statuses when the client connects, then pushes every status
+/// change as it occurs.  If no changes occur, a heartbeat is sent every 30 seconds.
+///
+/// # Arguments
+/// * `req` - Incoming HTTP request that was upgraded
+/// * `stream` - The WebSocket connection
+pub async fn websocket(req: tide::Request<State>, stream: WebSocketConnection) -> tide::Result<()> {
+    // subscribe first so no changes are missed while the snapshot is built
+    let mut changes = req.state().feed.subscribe();
+
+    let statuses = {
+        let mut db = req.db().await?;
+        User::fetch_all(&mut db).await?
+    };
+
+    stream
+        .send_json(&LiveMessage::Snapshot {
+            statuses: statuses.iter().map(StatusChange::from).collect(),
+        })
+        .await?;
+
+    loop {
+        let msg = match runtime::timeout(HEARTBEAT_INTERVAL, changes.next()).await {
+            Ok(Some(change)) => LiveMessage::Status(change),
+            Ok(None) => break,
+            Err(_) => LiveMessage::Heartbeat,
+        };
+
+        // an error here means the client has disconnected
+        if stream.send_json(&msg).await.is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
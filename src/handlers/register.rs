@@ -1,5 +1,6 @@
 //! Register this slack app
 
+use crate::error::Error;
 use serde::Deserialize;
 use serde_json::json;
 use tide::StatusCode;
@@ -23,7 +24,10 @@ struct FormRegister {
 /// # Arguments
 /// * `body` - Request body to parse as JSON
 pub fn url_verification(body: &[u8]) -> tide::Result<tide::Response> {
-    let form: FormRegister = serde_json::from_slice(body)?;
+    let form: FormRegister = match serde_json::from_slice(body) {
+        Ok(form) => form,
+        Err(e) => return Ok(Error::from(e).into_response()),
+    };
 
     match dotenv::var("SLACK_APP_TOKEN") {
         Ok(token) if token == form.token => {
@@ -33,6 +37,6 @@ pub fn url_verification(body: &[u8]) -> tide::Result<tide::Response> {
 
             Ok(resp)
         }
-        _ => Ok(tide::Response::builder(StatusCode::BadRequest).build()),
+        _ => Ok(Error::Auth("url verification token mismatch".to_owned()).into_response()),
     }
 }
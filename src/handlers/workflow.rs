@@ -0,0 +1,191 @@
+//! Slack Workflow Builder steps
+//!
+//! Two steps are provided, identified by their callback ids:
+//! * `set_status` - Sets a user's status
+//! * `get_team_status` - Outputs the status of every member of a team
+
+use crate::{
+    changes,
+    feed::StatusFeed,
+    models::{Team, User},
+    slack, SqlConn,
+};
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+/// Callback id of the "Set status" step
+const SET_STATUS: &str = "set_status";
+
+/// Callback id of the "Get team status" step
+const GET_TEAM_STATUS: &str = "get_team_status";
+
+/// Details of a workflow step, received when the step is edited or executed
+#[derive(Debug, Deserialize)]
+pub struct WorkflowStep {
+    /// Present when the step is being edited
+    pub workflow_step_edit_id: Option<String>,
+
+    /// Present when the step is being executed
+    pub workflow_step_execute_id: Option<String>,
+
+    /// Values configured for the step's inputs
+    #[serde(default)]
+    pub inputs: Map<String, Value>,
+}
+
+impl WorkflowStep {
+    /// Returns the value configured for an input, if present
+    ///
+    /// # Arguments
+    /// * `name` - Name of the input
+    fn input(&self, name: &str) -> Option<&str> {
+        self.inputs
+            .get(name)
+            .and_then(|input| input["value"].as_str())
+    }
+}
+
+/// Builds an input block for a text value
+///
+/// # Arguments
+/// * `name` - Name of the input, used as the block id
+/// * `label` - Label shown to the user
+/// * `value` - Current value of the input
+fn text_input(name: &str, label: &str, value: Option<&str>) -> Value {
+    let mut element = json!({
+        "type": "plain_text_input",
+        "action_id": "value",
+    });
+
+    if let Some(value) = value {
+        element["initial_value"] = json!(value);
+    }
+
+    json!({
+        "type": "input",
+        "block_id": name,
+        "label": { "type": "plain_text", "text": label },
+        "element": element,
+    })
+}
+
+/// Opens the configuration modal when a step is added to, or edited in, a workflow
+///
+/// # Arguments
+/// * `callback_id` - Which step is being edited
+/// * `trigger_id` - Trigger used to open the modal
+/// * `step` - The step's current configuration
+pub async fn edit(callback_id: &str, trigger_id: &str, step: &WorkflowStep) -> Result<()> {
+    let blocks = match callback_id {
+        SET_STATUS => vec![
+            text_input("user", "User", step.input("user")),
+            text_input("status", "Status", step.input("status")),
+        ],
+        GET_TEAM_STATUS => vec![text_input("team", "Team", step.input("team"))],
+        _ => {
+            tracing::warn!("unknown workflow step: {}", callback_id);
+            return Ok(());
+        }
+    };
+
+    slack::views_open(
+        trigger_id,
+        json!({
+            "type": "workflow_step",
+            "callback_id": callback_id,
+            "blocks": blocks,
+        }),
+    )
+    .await
+}
+
+/// Saves a step's configuration after the configuration modal is submitted
+///
+/// # Arguments
+/// * `callback_id` - Which step is being edited
+/// * `step` - The step being edited
+/// * `values` - State of the submitted view (`view.state.values`)
+pub async fn save(callback_id: &str, step: &WorkflowStep, values: &Value) -> Result<()> {
+    let edit_id = match &step.workflow_step_edit_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let value = |name: &str| json!({ "value": values[name]["value"]["value"] });
+
+    let (inputs, outputs) = match callback_id {
+        SET_STATUS => (
+            json!({ "user": value("user"), "status": value("status") }),
+            json!([{ "name": "status", "type": "text", "label": "Status" }]),
+        ),
+        GET_TEAM_STATUS => (
+            json!({ "team": value("team") }),
+            json!([{ "name": "statuses", "type": "text", "label": "Team statuses" }]),
+        ),
+        _ => return Ok(()),
+    };
+
+    slack::workflows_update_step(edit_id, inputs, outputs).await
+}
+
+/// Executes a step when a workflow containing it runs
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `feed` - Feed to publish status changes to
+/// * `callback_id` - Which step is being executed
+/// * `step` - The step being executed
+pub async fn execute(
+    db: &mut SqlConn,
+    feed: &StatusFeed,
+    callback_id: &str,
+    step: WorkflowStep,
+) -> Result<()> {
+    let execute_id = match &step.workflow_step_execute_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let outputs = match callback_id {
+        SET_STATUS => match (step.input("user"), step.input("status")) {
+            (Some(user), Some(status)) => match User::new(user) {
+                Ok(mut user) => {
+                    user.set_status(status.to_owned());
+                    changes::save(&mut *db, &user, None).await?;
+                    feed.publish_saved(&mut *db, &user.id).await;
+
+                    Ok(json!({ "status": status }))
+                }
+                Err(_) => Err("The user is not a valid Slack user"),
+            },
+            _ => Err("A user and status are required"),
+        },
+
+        GET_TEAM_STATUS => match step.input("team") {
+            Some(team) => match Team::members(&mut *db, team).await {
+                Ok(members) => {
+                    let statuses = members
+                        .into_iter()
+                        .map(|member| match member.status {
+                            Some(status) => format!("<@{}>: {}", member.id, status),
+                            None => format!("<@{}> has not set a status", member.id),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+
+                    Ok(json!({ "statuses": statuses }))
+                }
+                Err(_) => Err("Failed to fetch team members"),
+            },
+            None => Err("A team is required"),
+        },
+
+        _ => Err("Unknown workflow step"),
+    };
+
+    match outputs {
+        Ok(outputs) => slack::workflows_step_completed(execute_id, outputs).await,
+        Err(reason) => slack::workflows_step_failed(execute_id, reason).await,
+    }
+}
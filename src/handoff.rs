@@ -0,0 +1,250 @@
+//! Handoff checklists for multi-day leave
+//!
+//! When `HANDOFF_CHECKLIST` is set, users recording leave of at least `HANDOFF_MIN_DAYS` days
+//! with `/location leave` are sent a DM listing its items (e.g., naming who covers for them,
+//! setting an autoresponder), each with a checkbox.  Once every item is checked off, the leads
+//! of the user's teams are told the handoff is done.
+//!
+//! The checklist is saved with the leave, and both it and the leads' notifications are sent
+//! through the outbox, so neither is lost if Slack is unavailable or the bot restarts.
+
+use crate::{
+    models::{Handoff, Team},
+    outbox::{self, Effect},
+    slack, SqlConn,
+};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use once_cell::sync::OnceCell;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+/// Action id of the checkboxes of a checklist
+pub const CHECK_ACTION: &str = "handoff_check";
+
+/// Prefix of the block id of a checklist, followed by the id of its handoff
+const BLOCK_PREFIX: &str = "handoff_";
+
+/// Most items a checklist can have (Slack's limit on checkbox options)
+pub const MAX_ITEMS: usize = 10;
+
+/// Configured checklist
+static CONFIG: OnceCell<HandoffConfig> = OnceCell::new();
+
+/// Checklist sent to users going on multi-day leave
+#[derive(Clone, Debug)]
+pub struct HandoffConfig {
+    /// Items of the checklist, none if checklists aren't sent
+    pub items: Vec<String>,
+
+    /// Fewest days of leave a checklist is sent for
+    pub min_days: i64,
+}
+
+impl Default for HandoffConfig {
+    fn default() -> Self {
+        HandoffConfig {
+            items: vec![],
+            min_days: 2,
+        }
+    }
+}
+
+/// Configures the checklist sent to users going on multi-day leave
+///
+/// Only the first call has an effect
+///
+/// # Arguments
+/// * `config` - The checklist
+pub fn configure(mut config: HandoffConfig) {
+    if config.items.len() > MAX_ITEMS {
+        tracing::warn!(
+            "handoff checklists can have at most {} items, ignoring the rest",
+            MAX_ITEMS
+        );
+        config.items.truncate(MAX_ITEMS);
+    }
+
+    CONFIG.set(config).ok();
+}
+
+/// Returns the configured checklist
+fn config() -> &'static HandoffConfig {
+    CONFIG.get_or_init(HandoffConfig::default)
+}
+
+/// Renders a checklist, returning its fallback text and blocks
+///
+/// # Arguments
+/// * `handoff` - The handoff
+fn checklist(handoff: &Handoff) -> (String, Value) {
+    let text = format!(
+        "Before your leave from {} to {}, check these off to hand off your work. Your lead will be told once you're done",
+        handoff.starts_on.format("%a %b %-d"),
+        handoff.ends_on.format("%a %b %-d")
+    );
+
+    let options: Vec<Value> = handoff
+        .items()
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            json!({
+                "text": { "type": "mrkdwn", "text": item },
+                "value": index.to_string(),
+            })
+        })
+        .collect();
+
+    let initial: Vec<Value> = options
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| handoff.is_checked(*index))
+        .map(|(_, option)| option.clone())
+        .collect();
+
+    let mut checkboxes = json!({
+        "type": "checkboxes",
+        "action_id": CHECK_ACTION,
+        "options": options,
+    });
+
+    // slack rejects an empty list of initial options
+    if !initial.is_empty() {
+        checkboxes["initial_options"] = json!(initial);
+    }
+
+    let blocks = json!([
+        {
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("*Handoff checklist*\n{}", text) }
+        },
+        {
+            "type": "actions",
+            "block_id": format!("{}{}", BLOCK_PREFIX, handoff.id),
+            "elements": [checkboxes]
+        }
+    ]);
+
+    (text, blocks)
+}
+
+/// Sends a user a handoff checklist for leave they recorded, if checklists are configured
+/// and the leave is long enough, returning whether one was sent
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `user_id` - Slack ID of the user
+/// * `starts_on` - First day of the leave
+/// * `ends_on` - Last day of the leave (inclusive)
+pub async fn start(
+    db: &mut SqlConn,
+    user_id: &str,
+    starts_on: NaiveDate,
+    ends_on: NaiveDate,
+) -> Result<bool> {
+    let config = config();
+    let days = (ends_on - starts_on).num_days() + 1;
+    if config.items.is_empty() || days < config.min_days {
+        return Ok(false);
+    }
+
+    transaction!(db, async {
+        let handoff = Handoff::start(&mut *db, user_id, starts_on, ends_on, &config.items).await?;
+        let (text, blocks) = checklist(&handoff);
+        let effect = Effect::PostBlocks {
+            channel: user_id.to_owned(),
+            text,
+            blocks,
+        };
+        outbox::enqueue(&mut *db, &effect).await?;
+
+        Ok::<_, anyhow::Error>(true)
+    })
+}
+
+/// Queues a message to the leads of each of a user's teams that their handoff is done
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `handoff` - The completed handoff
+async fn notify_leads(db: &mut SqlConn, handoff: &Handoff) -> Result<()> {
+    let mut leads = BTreeSet::new();
+    for team in Team::fetch_by_member(&mut *db, &handoff.user_id).await? {
+        for lead in team.leads(&mut *db).await? {
+            if lead.id != handoff.user_id {
+                leads.insert(lead.id);
+            }
+        }
+    }
+
+    let text = format!(
+        ":white_check_mark: <@{}> finished their handoff checklist for their leave from {} to {}",
+        handoff.user_id,
+        handoff.starts_on.format("%a %b %-d"),
+        handoff.ends_on.format("%a %b %-d")
+    );
+
+    for lead in leads {
+        let effect = Effect::PostMessage {
+            channel: lead,
+            text: text.clone(),
+        };
+        outbox::enqueue(&mut *db, &effect).await?;
+    }
+
+    Ok(())
+}
+
+/// Records the items a user checked off, telling their leads once every item is
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `block_id` - Block id of the checklist, identifying the handoff
+/// * `user_id` - Slack ID of the user who checked the items
+/// * `selected` - Values of the items checked off
+/// * `container` - The message containing the checklist
+pub async fn respond(
+    db: &mut SqlConn,
+    block_id: &str,
+    user_id: &str,
+    selected: &[String],
+    container: &Value,
+) -> Result<()> {
+    let handoff_id: i64 = block_id
+        .strip_prefix(BLOCK_PREFIX)
+        .ok_or_else(|| anyhow!("invalid handoff block id `{}`", block_id))?
+        .parse()?;
+
+    let mut handoff = match Handoff::fetch(&mut *db, handoff_id).await {
+        Some(handoff) if handoff.user_id == user_id && handoff.completed_at.is_none() => handoff,
+        _ => return Ok(()),
+    };
+
+    let checked: Vec<usize> = selected.iter().filter_map(|v| v.parse().ok()).collect();
+    let completed = transaction!(db, async {
+        handoff.set_checked(&mut *db, &checked).await?;
+        if !handoff.is_done() || !handoff.complete(&mut *db).await? {
+            return Ok::<_, anyhow::Error>(false);
+        }
+
+        notify_leads(&mut *db, &handoff).await?;
+        Ok(true)
+    })?;
+
+    if let (true, Some(channel), Some(ts)) = (
+        completed,
+        container["channel_id"].as_str(),
+        container["message_ts"].as_str(),
+    ) {
+        let text = ":white_check_mark: Handoff done, your lead has been told. Enjoy your time off!";
+        let blocks = json!([{
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": text }
+        }]);
+
+        slack::chat_update(channel, ts, text, blocks).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,221 @@
+//! Nightly import of approved time off from an HR system
+//!
+//! HR systems are connected through the `LeaveProvider` trait; BambooHR is the only
+//! provider so far.  Once a day, at the configured hour, approved time off over the next
+//! `LOOKAHEAD_DAYS` days is recorded as leave for every employee whose work email matches a
+//! cached Slack profile, so people on PTO are marked out of office instead of showing no
+//! status.
+
+use crate::{
+    models::{Leave, Profile},
+    outbound, runtime, SqlPool,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDate, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Number of days ahead to import time off for
+const LOOKAHEAD_DAYS: i64 = 90;
+
+/// Approved time off of an employee
+#[derive(Clone, Debug)]
+pub struct TimeOff {
+    /// Id of the request in the HR system
+    pub id: String,
+
+    /// Work email of the employee
+    pub email: String,
+
+    /// First day off
+    pub starts_on: NaiveDate,
+
+    /// Last day off (inclusive)
+    pub ends_on: NaiveDate,
+}
+
+/// An HR system time off can be imported from
+#[async_trait]
+pub trait LeaveProvider: Send + Sync {
+    /// Name of the provider, recorded as the source of imported leave (e.g., `bamboohr`)
+    fn name(&self) -> &'static str;
+
+    /// Fetches approved time off overlapping a range of days
+    ///
+    /// # Arguments
+    /// * `start` - First day of the range
+    /// * `end` - Last day of the range (inclusive)
+    async fn time_off(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<TimeOff>>;
+}
+
+/// Imports time off from BambooHR
+#[derive(Clone, Debug)]
+pub struct BambooHr {
+    /// Company subdomain (e.g., `acme` for `acme.bamboohr.com`)
+    pub company: String,
+
+    /// API key of a user allowed to see time off and the employee directory
+    pub api_key: String,
+}
+
+impl BambooHr {
+    /// Sends a `GET` request to the BambooHR API, returning the JSON response
+    ///
+    /// # Arguments
+    /// * `path` - Path of the endpoint, relative to `/v1/`
+    async fn get(&self, path: &str) -> Result<Value> {
+        let mut resp = outbound::get(format!(
+            "https://api.bamboohr.com/api/gateway.php/{}/v1/{}",
+            self.company, path
+        ))
+        .set_header(
+            "Authorization",
+            format!("Basic {}", base64::encode(format!("{}:x", self.api_key))),
+        )
+        .set_header("Accept", "application/json")
+        .send()
+        .await
+        .map_err(|e| anyhow!("bamboohr request failed: {}", e))?;
+
+        let code = resp.status();
+        if code.is_client_error() || code.is_server_error() {
+            return Err(anyhow!("bamboohr request failed: HTTP {}", code));
+        }
+
+        resp.body_json()
+            .await
+            .map_err(|e| anyhow!("bamboohr response invalid: {}", e))
+    }
+}
+
+#[async_trait]
+impl LeaveProvider for BambooHr {
+    fn name(&self) -> &'static str {
+        "bamboohr"
+    }
+
+    async fn time_off(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<TimeOff>> {
+        // requests only name the employee by id, so emails come from the directory
+        let directory = self.get("employees/directory").await?;
+        let emails: HashMap<String, String> = directory["employees"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|employee| {
+                Some((
+                    employee["id"].as_str()?.to_owned(),
+                    employee["workEmail"].as_str()?.to_owned(),
+                ))
+            })
+            .collect();
+
+        let requests = self
+            .get(&format!(
+                "time_off/requests/?start={}&end={}&status=approved",
+                start, end
+            ))
+            .await?;
+
+        let parse = |value: &Value| {
+            NaiveDate::parse_from_str(value.as_str().unwrap_or_default(), "%Y-%m-%d").ok()
+        };
+
+        let time_off = requests
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|request| {
+                Some(TimeOff {
+                    id: request["id"].as_str()?.to_owned(),
+                    email: emails.get(request["employeeId"].as_str()?)?.clone(),
+                    starts_on: parse(&request["start"])?,
+                    ends_on: parse(&request["end"])?,
+                })
+            })
+            .collect();
+
+        Ok(time_off)
+    }
+}
+
+/// Imports approved time off from a provider as leave, returning the number of leave
+/// entries added
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `provider` - The HR system to import from
+pub async fn import(pool: &SqlPool, provider: &dyn LeaveProvider) -> Result<usize> {
+    let mut db = pool.acquire().await?;
+    let today = Utc::now().date().naive_utc();
+
+    let mut added = 0;
+    for time_off in provider
+        .time_off(today, today + Duration::days(LOOKAHEAD_DAYS))
+        .await?
+    {
+        // only users the bot already knows are imported
+        let profile = match Profile::fetch_by_email(&mut db, &time_off.email).await {
+            Some(profile) => profile,
+            None => continue,
+        };
+
+        let uid = format!("{}:{}", provider.name(), time_off.id);
+        let new = Leave::add(
+            &mut db,
+            &profile.user_id,
+            time_off.starts_on,
+            time_off.ends_on,
+            provider.name(),
+            Some(&uid),
+        )
+        .await?;
+
+        if new {
+            added += 1;
+        }
+    }
+
+    Ok(added)
+}
+
+/// Spawns a task that imports time off from a provider once a day
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `provider` - The HR system to import from
+/// * `hour` - Hour of the day (UTC) to import at
+pub fn spawn(pool: SqlPool, provider: Box<dyn LeaveProvider>, hour: u32) {
+    runtime::spawn(async move {
+        loop {
+            // sleep until the next time the configured hour comes around
+            let now = Utc::now();
+            let today = match now.date().and_hms_opt(hour, 0, 0) {
+                Some(today) => today,
+                None => {
+                    tracing::error!("invalid hr sync hour: {}", hour);
+                    return;
+                }
+            };
+
+            let next = if today <= now {
+                today + Duration::days(1)
+            } else {
+                today
+            };
+
+            let wait = (next - now).to_std().unwrap_or_default();
+            tracing::debug!("next hr import at {} ({}s)", next, wait.as_secs());
+            runtime::sleep(wait).await;
+
+            match import(&pool, provider.as_ref()).await {
+                Ok(count) => {
+                    tracing::info!("imported {} leave entries from {}", count, provider.name())
+                }
+                Err(e) => tracing::error!("failed to import from {}: {:?}", provider.name(), e),
+            }
+        }
+    });
+}
@@ -0,0 +1,254 @@
+//! Links issue keys in statuses (e.g., `INC-1234`) to Jira, with the issue's summary
+//!
+//! Summaries are cached, as statuses are rendered far more often than issues change.
+
+use crate::{markup::escape, outbound};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Finds the issue keys (a project key, a dash, and a number, e.g. `INC-1234`) in text,
+/// returning the byte range of each
+///
+/// # Arguments
+/// * `text` - Text to search
+fn find_keys(text: &str) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let word = |i: usize| bytes.get(i).map_or(false, |b| b.is_ascii_alphanumeric());
+
+    let mut keys = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_uppercase() || (i > 0 && word(i - 1)) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut end = i + 1;
+        while end < bytes.len() && (bytes[end].is_ascii_uppercase() || bytes[end].is_ascii_digit())
+        {
+            end += 1;
+        }
+
+        if end - start < 2 || bytes.get(end) != Some(&b'-') {
+            i = end;
+            continue;
+        }
+
+        let digits = end + 1;
+        end = digits;
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+
+        if end > digits && !word(end) {
+            keys.push((start, end));
+        }
+        i = end;
+    }
+
+    keys
+}
+
+/// Returns the issue keys mentioned in text, without duplicates
+///
+/// # Arguments
+/// * `text` - Text to search
+pub fn keys(text: &str) -> Vec<String> {
+    let mut keys: Vec<String> = find_keys(text)
+        .into_iter()
+        .map(|(start, end)| text[start..end].to_owned())
+        .collect();
+
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+/// Looks up (and briefly caches) the summaries of Jira issues
+#[derive(Clone, Debug)]
+pub struct IssueLinker {
+    /// Base URL of the Jira site (e.g., `https://acme.atlassian.net`)
+    base_url: String,
+
+    /// Value of the `Authorization` header sent to Jira
+    authorization: String,
+
+    /// How long a summary is reused for
+    ttl: Duration,
+
+    /// Cached summaries (`None` for issues that couldn't be found), and when they were
+    /// looked up
+    entries: Arc<Mutex<HashMap<String, (Instant, Option<String>)>>>,
+}
+
+impl IssueLinker {
+    /// Creates a linker with an empty cache
+    ///
+    /// With an email, the token is sent as an Atlassian Cloud API token (basic auth);
+    /// without one, it's sent as a personal access token (bearer auth)
+    ///
+    /// # Arguments
+    /// * `base_url` - Base URL of the Jira site
+    /// * `email` - Email of the account the API token belongs to, if any
+    /// * `token` - API token
+    /// * `ttl` - How long a summary is reused for
+    pub fn new(base_url: &str, email: Option<&str>, token: &str, ttl: Duration) -> Self {
+        let authorization = match email {
+            Some(email) => format!("Basic {}", base64::encode(format!("{}:{}", email, token))),
+            None => format!("Bearer {}", token),
+        };
+
+        IssueLinker {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            authorization,
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Fetches the summary of an issue
+    ///
+    /// # Arguments
+    /// * `key` - Key of the issue
+    async fn fetch(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let mut resp = outbound::get(format!(
+            "{}/rest/api/2/issue/{}?fields=summary",
+            self.base_url, key
+        ))
+        .set_header("Authorization", &self.authorization)
+        .set_header("Accept", "application/json")
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("issue request failed: {}", e))?;
+
+        // unknown issues (or typos that look like keys) are remembered, not retried
+        let code = resp.status();
+        if code == tide::StatusCode::NotFound {
+            return Ok(None);
+        }
+        if code.is_client_error() || code.is_server_error() {
+            return Err(anyhow::anyhow!("issue request failed: HTTP {}", code));
+        }
+
+        let body: serde_json::Value = resp
+            .body_json()
+            .await
+            .map_err(|e| anyhow::anyhow!("issue response invalid: {}", e))?;
+
+        Ok(body["fields"]["summary"].as_str().map(str::to_owned))
+    }
+
+    /// Looks up the summaries of issues, returning the issues that were found
+    ///
+    /// # Arguments
+    /// * `keys` - Keys of the issues
+    pub async fn lookup(&self, keys: &[String]) -> HashMap<String, String> {
+        let mut found = HashMap::new();
+        let mut missing = vec![];
+
+        {
+            let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            for key in keys {
+                match entries.get(key) {
+                    Some((at, summary)) if at.elapsed() < self.ttl => {
+                        if let Some(summary) = summary {
+                            found.insert(key.clone(), summary.clone());
+                        }
+                    }
+                    _ => missing.push(key.as_str()),
+                }
+            }
+        }
+
+        let mut entries = vec![];
+        for key in missing {
+            match self.fetch(key).await {
+                Ok(summary) => {
+                    if let Some(summary) = &summary {
+                        found.insert(key.to_owned(), summary.clone());
+                    }
+                    entries.push((key.to_owned(), summary));
+                }
+                Err(e) => tracing::warn!("failed to look up issue {}: {:?}", key, e),
+            }
+        }
+
+        let mut cache = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        cache.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        for (key, summary) in entries {
+            cache.insert(key, (Instant::now(), summary));
+        }
+
+        found
+    }
+
+    /// Rewrites the issue keys in text, leaving keys without a summary unchanged
+    ///
+    /// # Arguments
+    /// * `text` - Text to rewrite
+    /// * `summaries` - Summaries of the issues mentioned in the text
+    /// * `plain` - Renders text between issue keys
+    /// * `link` - Renders an issue key, given its URL and summary
+    fn rewrite(
+        &self,
+        text: &str,
+        summaries: &HashMap<String, String>,
+        plain: impl Fn(&str) -> String,
+        link: impl Fn(&str, &str, &str) -> String,
+    ) -> String {
+        let mut out = String::new();
+        let mut last = 0;
+        for (start, end) in find_keys(text) {
+            let key = &text[start..end];
+            if let Some(summary) = summaries.get(key) {
+                out.push_str(&plain(&text[last..start]));
+                let url = format!("{}/browse/{}", self.base_url, key);
+                out.push_str(&link(key, &url, summary));
+                last = end;
+            }
+        }
+
+        out.push_str(&plain(&text[last..]));
+        out
+    }
+
+    /// Links the issue keys in Slack `mrkdwn` text, appending each issue's summary
+    /// (e.g., `<https://acme.atlassian.net/browse/INC-1234|INC-1234> (Server down)`)
+    ///
+    /// # Arguments
+    /// * `text` - Text to rewrite
+    /// * `summaries` - Summaries of the issues mentioned in the text
+    pub fn link_mrkdwn(&self, text: &str, summaries: &HashMap<String, String>) -> String {
+        // slack only requires these three to be escaped
+        let escape = |text: &str| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+        };
+
+        self.rewrite(text, summaries, str::to_owned, |key, url, summary| {
+            format!("<{}|{}> ({})", url, key, escape(summary))
+        })
+    }
+
+    /// Links the issue keys in text rendered as HTML, appending each issue's summary, and
+    /// escaping everything else
+    ///
+    /// # Arguments
+    /// * `text` - Text to rewrite
+    /// * `summaries` - Summaries of the issues mentioned in the text
+    pub fn link_html(&self, text: &str, summaries: &HashMap<String, String>) -> String {
+        self.rewrite(text, summaries, escape, |key, url, summary| {
+            format!(
+                r#"<a href="{}">{}</a> ({})"#,
+                escape(url),
+                escape(key),
+                escape(summary)
+            )
+        })
+    }
+}
@@ -0,0 +1,1427 @@
+//! StatusBot: a Slack bot to track user and team location
+//!
+//! The bot can be run standalone (see `main.rs`) or embedded in another application
+//! by calling `run_server`.
+
+mod aliases;
+mod allowlist;
+mod announce;
+mod anomaly;
+mod approval;
+mod audit;
+mod auto_reply;
+mod breaker;
+mod caching;
+mod calendar;
+mod capture;
+#[macro_use]
+mod dry_run;
+mod changes;
+mod coverage;
+mod desired_state;
+mod digest;
+pub mod error;
+pub mod export;
+pub mod extract;
+mod feed;
+mod feedback;
+mod fields;
+
+#[cfg(feature = "fuzz")]
+pub mod fuzzing;
+
+mod handoff;
+mod hr;
+mod issues;
+
+#[cfg(feature = "grpc")]
+mod grpc;
+
+#[cfg(feature = "sheets")]
+mod sheets;
+
+mod limits;
+mod markup;
+mod meetings;
+mod muster;
+mod notify;
+pub mod outbound;
+mod outbox;
+mod outlook;
+mod partitions;
+mod presence;
+mod profiles;
+mod ratelimit;
+mod render;
+pub mod replay;
+mod response;
+mod rota;
+pub mod runtime;
+mod scheduler;
+mod setup;
+pub mod signing;
+mod slack;
+mod suggest;
+pub mod team_config;
+#[macro_use]
+mod timing;
+mod wizard;
+
+mod handlers {
+    pub(crate) mod admin;
+    pub(crate) mod api;
+    pub(crate) mod atom;
+    pub(crate) mod auth;
+    pub(crate) mod badge;
+    pub(crate) mod command;
+    pub(crate) mod desired_state;
+    pub(crate) mod event;
+    pub(crate) mod health;
+    pub(crate) mod interactive;
+    pub(crate) mod live;
+    pub(crate) mod register;
+    pub(crate) mod workflow;
+}
+
+mod models {
+    mod ack;
+    mod announcement;
+    mod anomaly;
+    mod approval;
+    mod audit;
+    mod auto_reply;
+    mod bulk_status;
+    mod calendar;
+    mod command_stat;
+    mod contact;
+    mod event;
+    mod feedback;
+    mod field;
+    mod handoff;
+    mod history;
+    mod leave;
+    mod muster;
+    mod outbox;
+    mod profile;
+    mod rate_limit;
+    mod retention;
+    mod scheduled;
+    mod shift;
+    mod site;
+    mod status;
+    mod status_event;
+    mod team;
+    mod user;
+
+    pub use self::ack::StatusAck;
+    pub use self::announcement::{Announcement, Delivery};
+    pub use self::anomaly::{BusyUser, StaleUser};
+    pub use self::approval::{Approval, ApprovalChain};
+    pub use self::audit::AuditEntry;
+    pub use self::auto_reply::AutoReply;
+    pub use self::bulk_status::BulkStatus;
+    pub use self::calendar::Calendar;
+    pub use self::command_stat::{CommandStat, CommandUsage};
+    pub use self::contact::{validate_phone, ContactChannel, ContactPreference};
+    pub use self::event::ProcessedEvent;
+    pub use self::feedback::Feedback;
+    pub use self::field::{parse_values, TeamField, MAX_VALUE_LENGTH};
+    pub use self::handoff::Handoff;
+    pub use self::history::HistoryEntry;
+    pub use self::leave::Leave;
+    pub use self::muster::{Muster, MusterResponse};
+    pub use self::outbox::OutboxEntry;
+    pub use self::profile::Profile;
+    pub use self::rate_limit::RateBucket;
+    pub use self::retention::RetentionOverride;
+    pub use self::scheduled::ScheduledMessage;
+    pub use self::shift::Shift;
+    pub use self::site::Site;
+    pub use self::status::{compact_status, plain_status, Availability, Location};
+    pub use self::status_event::{StatusEvent, StatusStore};
+    pub use self::team::{normalize_name, validate_name, Member, MemberRole, Team};
+    pub use self::user::{InvalidUserId, SlackUserId, User};
+}
+
+use aliases::Aliases;
+use allowlist::{CidrList, IpAllowlist};
+use anyhow::Result;
+use async_trait::async_trait;
+use capture::Capture;
+use extract::{AppState, SignedBody, SlackRetry};
+use feed::StatusFeed;
+use handlers::event::EventSender;
+use issues::IssueLinker;
+use meetings::MeetingCache;
+use presence::PresenceCache;
+use rand::Rng;
+use serde_json::Value;
+use sqlx::pool::PoolConnection;
+use std::fmt;
+use structopt::StructOpt;
+use tide::{
+    http::{cookies::SameSite, headers::HeaderValue},
+    security::{CorsMiddleware, Origin},
+    sessions::{MemoryStore, SessionMiddleware},
+    StatusCode,
+};
+use tide_compress::CompressMiddleware;
+use tide_tracing::TraceMiddleware;
+use tide_websockets::WebSocket;
+
+#[cfg(all(feature = "sqlite", feature = "postgres"))]
+compile_error!("Must enable only feature `sqlite` or `postgres`. Bot cannot be enabled");
+
+#[cfg(not(any(feature = "sqlite", feature = "postgres")))]
+compile_error!("Must enable either feature `sqlite` or `postgres`. Bot cannot be enabled");
+
+#[cfg(all(feature = "rt-async-std", feature = "rt-tokio"))]
+compile_error!("Must enable only feature `rt-async-std` or `rt-tokio`. Bot cannot be enabled");
+
+#[cfg(not(any(feature = "rt-async-std", feature = "rt-tokio")))]
+compile_error!("Must enable either feature `rt-async-std` or `rt-tokio`. Bot cannot be enabled");
+
+#[cfg(feature = "sqlite")]
+pub type SqlPool = sqlx::sqlite::SqlitePool;
+#[cfg(feature = "sqlite")]
+pub type PooledConn = PoolConnection<sqlx::Sqlite>;
+#[cfg(feature = "sqlite")]
+pub type SqlConn = sqlx::sqlite::SqliteConnection;
+
+#[cfg(feature = "postgres")]
+pub type SqlPool = sqlx::postgres::PgPool;
+#[cfg(feature = "postgres")]
+pub type PooledConn = PoolConnection<sqlx::Postgres>;
+#[cfg(feature = "postgres")]
+pub type SqlConn = sqlx::postgres::PgConnection;
+
+/// Command line options and arguments
+#[derive(StructOpt, Debug)]
+#[structopt(name = "statusbot")]
+pub struct Opt {
+    /// Database connection string
+    // SQLite: `sqlite://statusbot.sqlite3`
+    // Postgres: `postgres://<username>:<password>@<host>:<port>/<database>`
+    #[structopt(
+        short,
+        long,
+        env = "DATABASE_URL",
+        default_value = "sqlite://statusbot.sqlite3"
+    )]
+    database: String,
+
+    /// Connection string of a read-only replica, used for read-heavy paths (team views,
+    /// listings, and admin pages) while it's reachable
+    #[structopt(long, env = "REPLICA_DATABASE_URL")]
+    replica_database: Option<String>,
+
+    /// IP address to listen on/bind
+    #[structopt(short, long, env = "HOST", default_value = "0.0.0.0")]
+    host: String,
+
+    /// Port to listen on/bind
+    #[structopt(short, long, env = "PORT", default_value = "5010")]
+    port: u16,
+
+    /// Skip running migrations when app starts
+    #[structopt(long)]
+    skip_migrations: bool,
+
+    /// Serve even if the database schema doesn't match the one this build expects (e.g.,
+    /// while another replica is migrating during a rolling deploy)
+    #[structopt(long, env = "ALLOW_PENDING_MIGRATIONS")]
+    allow_pending_migrations: bool,
+
+    /// Ask Slack not to retry requests that can never succeed
+    #[structopt(long, env = "SLACK_NO_RETRY")]
+    slack_no_retry: bool,
+
+    /// Acknowledge, without processing, deliveries Slack has retried more than this many
+    /// times
+    #[structopt(long, env = "MAX_SLACK_RETRIES")]
+    max_slack_retries: Option<u32>,
+
+    /// Signing secret of the Slack app, used to verify requests come from Slack
+    #[structopt(long, env = "SLACK_SIGNING_SECRET", hide_env_values = true)]
+    slack_signing_secret: Option<String>,
+
+    /// Accept requests without verifying their Slack signature, if no signing secret is set
+    /// (only for local development, as anyone can then send commands and events)
+    #[structopt(long, env = "INSECURE_SKIP_SIGNATURE")]
+    insecure_skip_signature: bool,
+
+    /// Seconds a Slack request signature is valid for, either side of its timestamp
+    #[structopt(long, env = "SIGNATURE_TOLERANCE", default_value = "300")]
+    signature_tolerance: u64,
+
+    /// Accept Slack request signatures of any age (only for testing with hand-signed
+    /// requests, as it allows captured requests to be replayed)
+    #[structopt(long, env = "SKIP_SIGNATURE_AGE_CHECK")]
+    skip_signature_age_check: bool,
+
+    /// Seconds processed events are remembered, so redeliveries of them are ignored
+    #[structopt(long, env = "EVENT_DEDUP_TTL", default_value = "86400")]
+    event_dedup_ttl: u64,
+
+    /// Consecutive failures after which requests to a provider are stopped (0 never stops them)
+    #[structopt(long, env = "BREAKER_THRESHOLD", default_value = "5")]
+    breaker_threshold: u32,
+
+    /// Seconds requests to a failing provider are stopped for before it is tried again
+    #[structopt(long, env = "BREAKER_COOLDOWN", default_value = "60")]
+    breaker_cooldown: u64,
+
+    /// Number of received events that can wait to be processed
+    #[structopt(long, env = "EVENT_QUEUE_SIZE", default_value = "1024")]
+    event_queue_size: usize,
+
+    /// Maximum size (in bytes) of request bodies
+    #[structopt(long, env = "MAX_BODY_SIZE", default_value = "1048576")]
+    max_body_size: usize,
+
+    /// Seconds between refreshes of cached Slack profiles (0 disables refreshing)
+    #[structopt(long, env = "PROFILE_REFRESH_INTERVAL", default_value = "21600")]
+    profile_refresh_interval: u64,
+
+    /// Seconds between imports of leave from users' calendars (0 disables importing)
+    #[structopt(long, env = "CALENDAR_SYNC_INTERVAL", default_value = "3600")]
+    calendar_sync_interval: u64,
+
+    /// Azure AD tenant id used to read Outlook automatic replies
+    #[structopt(long, env = "GRAPH_TENANT_ID")]
+    graph_tenant_id: Option<String>,
+
+    /// Client id of the Azure AD app used to read Outlook automatic replies
+    #[structopt(long, env = "GRAPH_CLIENT_ID")]
+    graph_client_id: Option<String>,
+
+    /// Client secret of the Azure AD app used to read Outlook automatic replies
+    #[structopt(long, env = "GRAPH_CLIENT_SECRET")]
+    graph_client_secret: Option<String>,
+
+    /// Seconds between imports of leave from Outlook automatic replies (0 disables importing)
+    #[structopt(long, env = "OUTLOOK_SYNC_INTERVAL", default_value = "3600")]
+    outlook_sync_interval: u64,
+
+    /// Company subdomain of BambooHR, to import approved time off from
+    #[structopt(long, env = "BAMBOOHR_COMPANY")]
+    bamboohr_company: Option<String>,
+
+    /// BambooHR API key, used to import approved time off
+    #[structopt(long, env = "BAMBOOHR_API_KEY")]
+    bamboohr_api_key: Option<String>,
+
+    /// Hour of the day (UTC) to import approved time off from the HR system
+    #[structopt(long, env = "HR_SYNC_HOUR", default_value = "2")]
+    hr_sync_hour: u32,
+
+    /// Base URL of the Jira site issue keys in statuses are linked to (e.g.,
+    /// `https://acme.atlassian.net`)
+    #[structopt(long, env = "JIRA_BASE_URL")]
+    jira_base_url: Option<String>,
+
+    /// Email of the Jira account the API token belongs to (omit for a personal access token)
+    #[structopt(long, env = "JIRA_EMAIL")]
+    jira_email: Option<String>,
+
+    /// Jira API token, used to look up the summaries of issues mentioned in statuses
+    #[structopt(long, env = "JIRA_API_TOKEN")]
+    jira_api_token: Option<String>,
+
+    /// Seconds to cache the summaries of Jira issues
+    #[structopt(long, env = "JIRA_CACHE_TTL", default_value = "600")]
+    jira_cache_ttl: u64,
+
+    /// Seconds to cache the presence and do-not-disturb status of users shown in team views
+    /// (0 disables showing presence)
+    #[structopt(long, env = "PRESENCE_TTL", default_value = "0")]
+    presence_ttl: u64,
+
+    /// Seconds to cache whether users shown in team views are in a huddle or meeting
+    /// (0 disables showing meetings)
+    #[structopt(long, env = "MEETING_TTL", default_value = "0")]
+    meeting_ttl: u64,
+
+    /// Hour of the day (UTC) of the morning run, which marks users on leave out of office,
+    /// checks coverage, and posts today's shifts
+    #[structopt(long, env = "MORNING_HOUR", default_value = "8")]
+    morning_hour: u32,
+
+    /// Channel to warn about teams nobody is in the office or available for, checked during
+    /// the morning run
+    #[structopt(long, env = "COVERAGE_CHANNEL")]
+    coverage_channel: Option<String>,
+
+    /// How often to send each team a digest of where its members are working (daily, or
+    /// weekly on Mondays), posted to the team's channel and emailed to its leads
+    #[structopt(long, env = "DIGEST")]
+    digest: Option<digest::Frequency>,
+
+    /// Markup of messages the bot posts on its own, like digests (mrkdwn, or plain for
+    /// clients and screen readers that shouldn't see markup)
+    #[structopt(long, env = "SLACK_MARKUP", default_value = "mrkdwn")]
+    slack_markup: render::Markup,
+
+    /// Channel to post a weekly report of admin-level actions (team deletions, role grants,
+    /// and retention changes) in, on Monday mornings
+    #[structopt(long, env = "SECURITY_CHANNEL")]
+    security_channel: Option<String>,
+
+    /// Channel to forward problems users report with `/location feedback` to
+    #[structopt(long, env = "FEEDBACK_CHANNEL")]
+    feedback_channel: Option<String>,
+
+    /// IP address for the gRPC server to listen on/bind (local only unless set, as it
+    /// isn't behind the web server's allowlist)
+    #[cfg(feature = "grpc")]
+    #[structopt(long, env = "GRPC_HOST", default_value = "127.0.0.1")]
+    grpc_host: String,
+
+    /// Port for the gRPC server to listen on/bind
+    #[cfg(feature = "grpc")]
+    #[structopt(long, env = "GRPC_PORT", default_value = "5011")]
+    grpc_port: u16,
+
+    /// PEM encoded certificate (chain) for the gRPC server to serve TLS with
+    #[cfg(feature = "grpc")]
+    #[structopt(long, env = "GRPC_TLS_CERT", requires = "grpc-tls-key")]
+    grpc_tls_cert: Option<String>,
+
+    /// PEM encoded private key of `--grpc-tls-cert`
+    #[cfg(feature = "grpc")]
+    #[structopt(long, env = "GRPC_TLS_KEY", requires = "grpc-tls-cert")]
+    grpc_tls_key: Option<String>,
+
+    /// Google service account key file used to export statuses to Google Sheets
+    #[cfg(feature = "sheets")]
+    #[structopt(long, env = "GOOGLE_APPLICATION_CREDENTIALS")]
+    sheets_credentials: Option<String>,
+
+    /// Id of the Google Sheet to export daily statuses to
+    #[cfg(feature = "sheets")]
+    #[structopt(long, env = "SHEETS_SPREADSHEET_ID")]
+    sheets_spreadsheet_id: Option<String>,
+
+    /// Sheet (or A1 range) to append exported statuses to
+    #[cfg(feature = "sheets")]
+    #[structopt(long, env = "SHEETS_RANGE", default_value = "Sheet1")]
+    sheets_range: String,
+
+    /// Hour of the day (UTC) to export statuses to Google Sheets
+    #[cfg(feature = "sheets")]
+    #[structopt(long, env = "SHEETS_EXPORT_HOUR", default_value = "17")]
+    sheets_export_hour: u32,
+
+    /// Average requests per minute allowed to each key (valid token, or client address) of a
+    /// public endpoint (0 disables limits)
+    #[structopt(long, env = "RATE_LIMIT_PER_MINUTE", default_value = "60")]
+    rate_limit_per_minute: u32,
+
+    /// Requests each key of a public endpoint can make at once
+    #[structopt(long, env = "RATE_LIMIT_BURST", default_value = "10")]
+    rate_limit_burst: u32,
+
+    /// Seconds between saving rate limit buckets to the database, so they survive restarts
+    /// (0 keeps them in memory only)
+    #[structopt(long, env = "RATE_LIMIT_PERSIST_INTERVAL", default_value = "60")]
+    rate_limit_persist_interval: u64,
+
+    /// Months of status history to keep before the current month (0 keeps everything)
+    #[structopt(long, env = "HISTORY_RETENTION_MONTHS", default_value = "0")]
+    history_retention_months: u32,
+
+    /// Directory to move status history older than the retention period to
+    #[cfg(feature = "sqlite")]
+    #[structopt(
+        long,
+        env = "HISTORY_ARCHIVE_DIR",
+        default_value = "archive",
+        parse(from_os_str)
+    )]
+    history_archive_dir: std::path::PathBuf,
+
+    /// Directory to capture inbound requests to, while capturing is switched on in the
+    /// admin UI
+    #[structopt(long, env = "CAPTURE_DIR", parse(from_os_str))]
+    capture_dir: Option<std::path::PathBuf>,
+
+    /// Size (in bytes) at which a new capture file is started
+    #[structopt(long, env = "CAPTURE_MAX_BYTES", default_value = "67108864")]
+    capture_max_bytes: u64,
+
+    /// Comma-separated aliases for command keywords (e.g., `equipo=team,crear=create`)
+    #[structopt(long, env = "COMMAND_ALIASES", parse(try_from_str = Aliases::parse))]
+    command_aliases: Option<Aliases>,
+
+    /// Comma-separated address ranges (e.g., `10.0.0.0/8`) requests from Slack may come from
+    #[structopt(long, env = "SLACK_ALLOWLIST", parse(try_from_str = CidrList::parse))]
+    slack_allowlist: Option<CidrList>,
+
+    /// Comma-separated address ranges requests to the admin UI and API may come from
+    #[structopt(long, env = "ADMIN_ALLOWLIST", parse(try_from_str = CidrList::parse))]
+    admin_allowlist: Option<CidrList>,
+
+    /// Comma-separated address ranges requests to every other route may come from
+    #[structopt(long, env = "PUBLIC_ALLOWLIST", parse(try_from_str = CidrList::parse))]
+    public_allowlist: Option<CidrList>,
+
+    /// Comma-separated address ranges of proxies whose `X-Forwarded-For` headers are trusted
+    #[structopt(long, env = "TRUSTED_PROXIES", parse(try_from_str = CidrList::parse))]
+    trusted_proxies: Option<CidrList>,
+
+    /// Directory of YAML files declaring teams and sites, which the database is reconciled
+    /// to match on startup and when `/desired-state` is called
+    #[structopt(long, env = "DESIRED_STATE", parse(from_os_str))]
+    desired_state: Option<std::path::PathBuf>,
+
+    /// Client outbound requests are sent with: `surf`, or `reqwest` (feature `reqwest-client`)
+    #[structopt(long, env = "OUTBOUND_BACKEND", default_value = "surf")]
+    outbound_backend: outbound::Backend,
+
+    /// Proxy outbound requests are sent through (`http://`, `https://`, or `socks5://`), with
+    /// credentials in the URL if it requires authentication
+    #[structopt(long, env = "OUTBOUND_PROXY")]
+    outbound_proxy: Option<String>,
+
+    /// PEM certificate presented to servers that require mutual TLS
+    #[structopt(long, env = "OUTBOUND_CLIENT_CERT", parse(from_os_str))]
+    outbound_client_cert: Option<std::path::PathBuf>,
+
+    /// PEM private key of `--outbound-client-cert`
+    #[structopt(long, env = "OUTBOUND_CLIENT_KEY", parse(from_os_str))]
+    outbound_client_key: Option<std::path::PathBuf>,
+
+    /// PEM bundle of certificate authorities outbound requests trust instead of the system's
+    #[structopt(long, env = "OUTBOUND_CA_CERT", parse(from_os_str))]
+    outbound_ca_cert: Option<std::path::PathBuf>,
+
+    /// Mail server notifications are emailed through (STARTTLS is required)
+    #[structopt(long, env = "SMTP_HOST")]
+    smtp_host: Option<String>,
+
+    /// Port of `--smtp-host`
+    #[structopt(long, env = "SMTP_PORT", default_value = "587")]
+    smtp_port: u16,
+
+    /// User name used to sign in to `--smtp-host`, if it requires one
+    #[structopt(long, env = "SMTP_USERNAME")]
+    smtp_username: Option<String>,
+
+    /// Password used to sign in to `--smtp-host`
+    #[structopt(long, env = "SMTP_PASSWORD")]
+    smtp_password: Option<String>,
+
+    /// Address notifications are emailed from (e.g., `StatusBot <statusbot@example.com>`)
+    #[structopt(long, env = "SMTP_FROM", default_value = "statusbot@localhost")]
+    smtp_from: String,
+
+    /// Twilio account SID, used to send notifications as text messages
+    #[structopt(long, env = "TWILIO_ACCOUNT_SID")]
+    twilio_account_sid: Option<String>,
+
+    /// Auth token of the Twilio account
+    #[structopt(long, env = "TWILIO_AUTH_TOKEN")]
+    twilio_auth_token: Option<String>,
+
+    /// Number text messages are sent from, in E.164 format (e.g., `+15551234567`)
+    #[structopt(long, env = "TWILIO_FROM")]
+    twilio_from: Option<String>,
+
+    /// Items of the checklist sent to users recording multi-day leave, separated by `;` (e.g.,
+    /// `Name who covers for you;Set an autoresponder`), no checklist if not set
+    #[structopt(long, env = "HANDOFF_CHECKLIST")]
+    handoff_checklist: Option<String>,
+
+    /// Fewest days of leave a handoff checklist is sent for
+    #[structopt(long, env = "HANDOFF_MIN_DAYS", default_value = "2")]
+    handoff_min_days: i64,
+
+    /// Runs a tool instead of the bot
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Tools run in place of the bot
+#[derive(StructOpt, Debug)]
+pub enum Command {
+    /// Re-dispatches recorded requests through the handlers, against `--database`
+    Replay {
+        /// File of recorded requests, one JSON object per line
+        #[structopt(parse(from_os_str))]
+        file: std::path::PathBuf,
+    },
+
+    /// Exports and imports team configuration as YAML, against `--database`
+    Config(team_config::ConfigCommand),
+
+    /// Exports status history or usage statistics as CSV or Parquet, against `--database`
+    Export(export::ExportCommand),
+
+    /// Rebuilds users' statuses from their status events (`STATUS_STORE=events`), against
+    /// `--database`
+    RebuildStatuses,
+
+    /// Normalizes the names of teams created by older versions, merging teams whose names
+    /// only differ by case, against `--database`
+    MigrateTeamNames {
+        /// Print the teams that would be merged, without changing anything
+        #[structopt(long)]
+        dry_run: bool,
+    },
+}
+
+impl Opt {
+    /// Returns how long processed events are remembered
+    pub(crate) fn event_dedup_ttl(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.event_dedup_ttl as i64)
+    }
+
+    /// Returns when circuit breakers open, and for how long
+    pub(crate) fn breakers(&self) -> breaker::BreakerConfig {
+        breaker::BreakerConfig {
+            threshold: self.breaker_threshold,
+            cooldown: std::time::Duration::from_secs(self.breaker_cooldown),
+        }
+    }
+
+    /// Returns the channels notifications can be delivered through besides Slack
+    pub(crate) fn notify(&self) -> notify::NotifyConfig {
+        let smtp = self.smtp_host.clone().map(|host| notify::SmtpConfig {
+            host,
+            port: self.smtp_port,
+            credentials: self.smtp_username.clone().zip(self.smtp_password.clone()),
+            from: self.smtp_from.clone(),
+        });
+
+        let twilio = match (
+            &self.twilio_account_sid,
+            &self.twilio_auth_token,
+            &self.twilio_from,
+        ) {
+            (Some(account_sid), Some(auth_token), Some(from)) => Some(notify::TwilioConfig {
+                account_sid: account_sid.clone(),
+                auth_token: auth_token.clone(),
+                from: from.clone(),
+            }),
+            _ => None,
+        };
+
+        notify::NotifyConfig { smtp, twilio }
+    }
+
+    /// Returns the checklist sent to users going on multi-day leave
+    pub(crate) fn handoff(&self) -> handoff::HandoffConfig {
+        let items = self
+            .handoff_checklist
+            .as_deref()
+            .unwrap_or_default()
+            .split(';')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        handoff::HandoffConfig {
+            items,
+            min_days: self.handoff_min_days,
+        }
+    }
+
+    /// Returns the secret Slack request signatures are verified with, or `None` if they aren't
+    /// verified, failing if no secret is set unless `--insecure-skip-signature` is given
+    pub(crate) fn signing_secret(&self) -> Result<Option<String>> {
+        match self.slack_signing_secret.as_deref() {
+            Some(secret) if !secret.is_empty() => Ok(Some(secret.to_owned())),
+            _ if self.insecure_skip_signature => {
+                tracing::warn!(
+                    "SLACK_SIGNING_SECRET is not set, accepting requests without verifying \
+                     their signature (--insecure-skip-signature)"
+                );
+                Ok(None)
+            }
+            _ => anyhow::bail!(
+                "SLACK_SIGNING_SECRET is not set (pass --insecure-skip-signature to accept \
+                 unsigned requests)"
+            ),
+        }
+    }
+
+    /// Returns how many seconds Slack request signatures are valid for, or `None` if their
+    /// age isn't checked
+    pub(crate) fn signature_tolerance(&self) -> Option<u64> {
+        if self.skip_signature_age_check {
+            None
+        } else {
+            Some(self.signature_tolerance)
+        }
+    }
+
+    /// Returns the rate limit applied to public endpoints
+    pub(crate) fn rate_limit(&self) -> ratelimit::RateLimit {
+        ratelimit::RateLimit::new(self.rate_limit_per_minute, self.rate_limit_burst)
+    }
+
+    /// Returns how outbound requests leave the deployment
+    pub(crate) fn outbound(&self) -> outbound::OutboundConfig {
+        outbound::OutboundConfig {
+            backend: self.outbound_backend,
+            proxy: self.outbound_proxy.clone(),
+            client_cert: self.outbound_client_cert.clone(),
+            client_key: self.outbound_client_key.clone(),
+            ca_cert: self.outbound_ca_cert.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Opt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "host={}, port={}", self.host, self.port)
+    }
+}
+
+#[async_trait]
+pub trait HasDb {
+    //type Target;
+    type Error;
+
+    async fn db(&self) -> std::result::Result<PooledConn, Self::Error>;
+
+    /// Acquires a connection for reads only, from the replica if one is reachable
+    async fn read_db(&self) -> std::result::Result<PooledConn, Self::Error>;
+}
+
+#[async_trait]
+impl HasDb for tide::Request<State> {
+    //type Target = PooledConn;
+    type Error = sqlx::Error;
+
+    async fn db(&self) -> std::result::Result<PooledConn, Self::Error> {
+        self.state().pool.acquire().await
+    }
+
+    async fn read_db(&self) -> std::result::Result<PooledConn, Self::Error> {
+        match self.state().replica_conn().await {
+            Some(conn) => Ok(conn),
+            None => self.state().pool.acquire().await,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct State {
+    /// A configured sql pool
+    pool: SqlPool,
+
+    /// Pool of connections to a read-only replica, if configured
+    replica: Option<SqlPool>,
+
+    /// Feed of status changes
+    feed: StatusFeed,
+
+    /// Queue of events waiting to be processed
+    events: EventSender,
+
+    /// Send `X-Slack-No-Retry` when a request can never succeed
+    slack_no_retry: bool,
+
+    /// Retried deliveries beyond this many retries are acknowledged without processing
+    max_slack_retries: Option<u32>,
+
+    /// Secret Slack request signatures are verified with, or `None` to skip verification
+    signing_secret: Option<String>,
+
+    /// Seconds a Slack request signature is valid for, or `None` to accept any age
+    signature_tolerance: Option<u64>,
+
+    /// Presence of users, if team views are annotated with it
+    presence: Option<PresenceCache>,
+
+    /// Meetings users are in, if team views are annotated with them
+    meetings: Option<MeetingCache>,
+
+    /// Links issue keys in statuses to Jira, if configured
+    issues: Option<IssueLinker>,
+
+    /// Captures inbound requests, if configured
+    capture: Option<Capture>,
+
+    /// Aliases for command keywords
+    aliases: Aliases,
+
+    /// Directory declaring the desired state of teams and sites, if configured
+    desired_state: Option<std::path::PathBuf>,
+}
+
+/// Configuration of the state shared by every request, besides its pool, feed, and queue
+pub struct StateConfig {
+    /// Pool of connections to a read-only replica, if configured
+    pub replica: Option<SqlPool>,
+
+    /// Send `X-Slack-No-Retry` when a request can never succeed
+    pub slack_no_retry: bool,
+
+    /// Retried deliveries beyond this many retries are acknowledged without processing
+    pub max_slack_retries: Option<u32>,
+
+    /// Secret Slack request signatures are verified with, or `None` to skip verification
+    pub signing_secret: Option<String>,
+
+    /// Seconds a Slack request signature is valid for, or `None` to accept any age
+    pub signature_tolerance: Option<u64>,
+
+    /// Presence of users, if team views are annotated with it
+    pub presence: Option<PresenceCache>,
+
+    /// Meetings users are in, if team views are annotated with them
+    pub meetings: Option<MeetingCache>,
+
+    /// Links issue keys in statuses to Jira, if configured
+    pub issues: Option<IssueLinker>,
+
+    /// Captures inbound requests, if configured
+    pub capture: Option<Capture>,
+
+    /// Aliases for command keywords
+    pub aliases: Aliases,
+
+    /// Directory declaring the desired state of teams and sites, if configured
+    pub desired_state: Option<std::path::PathBuf>,
+}
+
+impl State {
+    /// Creates the state shared by every request
+    ///
+    /// # Arguments
+    /// * `pool` - A configured sql pool
+    /// * `feed` - Feed of status changes
+    /// * `events` - Queue of events waiting to be processed
+    /// * `config` - Everything else requests need, as configured
+    pub fn new(pool: SqlPool, feed: StatusFeed, events: EventSender, config: StateConfig) -> Self {
+        State {
+            pool,
+            replica: config.replica,
+            feed,
+            events,
+            slack_no_retry: config.slack_no_retry,
+            max_slack_retries: config.max_slack_retries,
+            signing_secret: config.signing_secret,
+            signature_tolerance: config.signature_tolerance,
+            presence: config.presence,
+            meetings: config.meetings,
+            issues: config.issues,
+            capture: config.capture,
+            aliases: config.aliases,
+            desired_state: config.desired_state,
+        }
+    }
+
+    /// Returns the primary sql pool, for tasks that outlive a request
+    pub(crate) fn pool(&self) -> SqlPool {
+        self.pool.clone()
+    }
+
+    /// Acquires a connection to the read-only replica, returning `None` if there isn't one
+    /// or it can't be reached (so reads fall back to the primary)
+    pub(crate) async fn replica_conn(&self) -> Option<PooledConn> {
+        match self.replica.as_ref()?.acquire().await {
+            Ok(conn) => Some(conn),
+            Err(e) => {
+                tracing::warn!("replica unavailable, reading from primary: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Handles all `POST`s received to the root (`/`) uri.
+///
+/// Depending on the `type` JSON field, dispatches messages to the appropriate handler
+///
+/// # Arguments
+/// * `retry` - Slack retry headers, if this is a redelivery
+/// * `body` - The signed request body
+/// * `state` - Shared application state
+pub async fn handle_post(
+    (retry, SignedBody(body), AppState(state)): (SlackRetry, SignedBody, AppState),
+) -> tide::Result<tide::Response> {
+    // first decode the body as an unknown JSON request to extract the type
+    if !limits::json_depth_ok(&body, limits::MAX_JSON_DEPTH) {
+        return Ok(tide::Response::builder(StatusCode::BadRequest).build());
+    }
+
+    let json: Value = match serde_json::from_slice(&body) {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::error!("Invalid JSON received: {:?}", e);
+
+            // a malformed request will never succeed, no sense in slack retrying it
+            let mut resp = tide::Response::builder(StatusCode::BadRequest);
+            if state.slack_no_retry {
+                resp = resp.header("X-Slack-No-Retry", "1");
+            }
+
+            return Ok(resp.build());
+        }
+    };
+
+    // slack retries events it doesn't receive a timely response for
+    if let Some(num) = retry.num {
+        tracing::info!(
+            "slack retry #{} ({})",
+            num,
+            retry.reason.as_deref().unwrap_or("unknown")
+        );
+
+        if let (Ok(num), Some(max)) = (num.parse::<u32>(), state.max_slack_retries) {
+            if num > max {
+                tracing::warn!("ignoring slack retry #{} (more than {})", num, max);
+                return Ok(tide::Response::builder(StatusCode::Ok).build());
+            }
+        }
+    }
+
+    match json["type"].as_str() {
+        Some("url_verification") => handlers::register::url_verification(&body),
+        Some("event_callback") => handlers::event::callback(&body, &state).await,
+
+        // ignore all other events, but respond with 200 OK so we don't get blocked by Slack
+        _ => Ok(tide::Response::builder(StatusCode::Ok).build()),
+    }
+}
+
+/// Seconds to wait for a connection to the read-only replica before reading from the primary
+const REPLICA_CONNECT_TIMEOUT: u64 = 2;
+
+/// Version of the newest migration this build knows about (see `build.rs`)
+const SCHEMA_VERSION: &str = env!("STATUSBOT_SCHEMA_VERSION");
+
+/// Returns the version of the newest migration applied to the database, or 0 if none have
+/// been applied
+///
+/// # Arguments
+/// * `db` - A configured sql pool
+async fn schema_version(db: &SqlPool) -> i64 {
+    use sqlx::Row;
+
+    // sqlx creates its migrations table itself, so the query can't be checked at compile time
+    sqlx::query(include_str!("../sql/migration/fetch_version.sql"))
+        .fetch_one(db)
+        .await
+        .and_then(|row| row.try_get::<Option<i64>, _>("version"))
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Checks the database schema matches the one this build expects, so a build never writes
+/// to a schema it doesn't know (e.g., when migrations were skipped or failed, or a newer
+/// replica has already migrated the database)
+///
+/// # Arguments
+/// * `db` - A configured sql pool
+/// * `allow_mismatch` - Only warn, instead of failing, if the schemas don't match
+async fn check_schema(db: &SqlPool, allow_mismatch: bool) -> Result<()> {
+    let expected: i64 = SCHEMA_VERSION.parse()?;
+    let applied = schema_version(db).await;
+
+    let mismatch = match applied.cmp(&expected) {
+        std::cmp::Ordering::Equal => return Ok(()),
+        std::cmp::Ordering::Less => format!(
+            "database schema ({}) is older than expected ({}), migrations are pending",
+            applied, expected
+        ),
+        std::cmp::Ordering::Greater => format!(
+            "database schema ({}) is newer than expected ({}), this build is out of date",
+            applied, expected
+        ),
+    };
+
+    if !allow_mismatch {
+        anyhow::bail!(
+            "{} (pass --allow-pending-migrations to serve anyway)",
+            mismatch
+        );
+    }
+
+    tracing::warn!("{}, serving anyway", mismatch);
+    Ok(())
+}
+
+async fn run_migrations(db: &SqlPool) -> Result<()> {
+    use sqlx::migrate::Migrator;
+    use std::path::Path;
+
+    #[cfg(feature = "postgres")]
+    let path = Path::new("./postgres/migrations");
+
+    #[cfg(feature = "sqlite")]
+    let path = Path::new("./sqlite/migrations");
+
+    tracing::info!("running migrations [{}]", path.display());
+
+    let migrator = Migrator::new(path).await?;
+    match migrator.run(db).await {
+        Ok(()) => tracing::info!("migrations complete"),
+        Err(e) => {
+            tracing::error!("failed to run migrations:\n{:?}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Rebuilds every user's status from their status events, replacing what's stored
+///
+/// # Arguments
+/// * `opt` - Command line options and arguments
+pub async fn rebuild_statuses(opt: &Opt) -> Result<()> {
+    let pool = connect(opt).await?;
+    let mut db = pool.acquire().await?;
+
+    if models::StatusStore::current() != models::StatusStore::Events {
+        tracing::warn!("STATUS_STORE isn't `events`, so recent changes may be missing");
+    }
+
+    let count = transaction!(db, models::StatusEvent::rebuild(&mut db))?;
+    println!("rebuilt the statuses of {} users", count);
+
+    Ok(())
+}
+
+/// Normalizes the names of teams created by older versions, printing each merge
+///
+/// # Arguments
+/// * `opt` - Command line options and arguments
+/// * `dry_run` - Only print the merges, without making them
+pub async fn migrate_team_names(opt: &Opt, dry_run: bool) -> Result<()> {
+    let pool = connect(opt).await?;
+    let mut db = pool.acquire().await?;
+
+    let merged = simulated!(db, dry_run, models::Team::normalize_names(&mut db))?;
+    if merged.is_empty() {
+        println!("no teams needed merging");
+    } else if dry_run {
+        println!("dry run, nothing was changed:");
+    }
+    for merge in merged {
+        println!("{}", merge);
+    }
+
+    Ok(())
+}
+
+/// Runs the bot until the web server exits
+///
+/// This may be awaited from either an async-std or a tokio runtime, matching the
+/// enabled `rt-*` feature.
+///
+/// # Arguments
+/// * `opt` - Command line options and arguments
+pub async fn run_server(opt: Opt) -> Result<()> {
+    // refuse to serve unverified requests unless asked to
+    let signing_secret = opt.signing_secret()?;
+
+    // a client installed by an embedder (or a test) takes precedence
+    if !outbound::installed() {
+        outbound::init(&opt.outbound())?;
+    }
+    breaker::configure(opt.breakers());
+    notify::configure(opt.notify());
+    handoff::configure(opt.handoff());
+    render::configure(opt.slack_markup);
+    feedback::configure(opt.feedback_channel.clone());
+
+    let pool = connect(&opt).await?;
+
+    // teams created by older versions may be merged when normalized, so that's left to the
+    // `migrate-team-names` command
+    let mut db = pool.acquire().await?;
+    let unnormalized = models::Team::count_unnormalized(&mut db).await?;
+    drop(db);
+    if unnormalized > 0 {
+        tracing::warn!(
+            "{} teams can't be looked up by name until `statusbot migrate-team-names` is run",
+            unnormalized
+        );
+    }
+
+    // every status change is published to this feed
+    let feed = StatusFeed::new();
+
+    #[cfg(feature = "grpc")]
+    {
+        // run the gRPC server on its own port
+        let addr = format!("{}:{}", opt.grpc_host, opt.grpc_port).parse()?;
+        let tls = match (&opt.grpc_tls_cert, &opt.grpc_tls_key) {
+            (Some(cert), Some(key)) => Some(grpc::identity(cert, key)?),
+            _ => None,
+        };
+        grpc::spawn(addr, tls, pool.clone(), feed.clone());
+    }
+
+    #[cfg(feature = "sheets")]
+    {
+        if let (Some(credentials), Some(spreadsheet_id)) = (
+            opt.sheets_credentials.clone(),
+            opt.sheets_spreadsheet_id.clone(),
+        ) {
+            sheets::spawn(
+                pool.clone(),
+                sheets::SheetsConfig {
+                    credentials,
+                    spreadsheet_id,
+                    range: opt.sheets_range.clone(),
+                    hour: opt.sheets_export_hour,
+                },
+            );
+        }
+    }
+
+    // deliver side effects of status changes
+    outbox::spawn(pool.clone());
+
+    // escalate leave requests whose approver didn't decide in time
+    approval::spawn(pool.clone());
+
+    // run morning checks and post today's shifts
+    scheduler::spawn(
+        pool.clone(),
+        feed.clone(),
+        scheduler::MorningConfig {
+            hour: opt.morning_hour,
+            coverage_channel: opt.coverage_channel.clone(),
+            digest: opt.digest,
+            security_channel: opt.security_channel.clone(),
+        },
+    );
+
+    // keep cached slack profiles up to date
+    if opt.profile_refresh_interval > 0 {
+        profiles::spawn(
+            pool.clone(),
+            std::time::Duration::from_secs(opt.profile_refresh_interval),
+        );
+    }
+
+    // import leave from users' calendars
+    if opt.calendar_sync_interval > 0 {
+        calendar::spawn(
+            pool.clone(),
+            std::time::Duration::from_secs(opt.calendar_sync_interval),
+        );
+    }
+
+    // mirror outlook automatic replies as leave, if graph credentials are configured
+    if let (Some(tenant_id), Some(client_id), Some(client_secret)) = (
+        opt.graph_tenant_id.clone(),
+        opt.graph_client_id.clone(),
+        opt.graph_client_secret.clone(),
+    ) {
+        if opt.outlook_sync_interval > 0 {
+            outlook::spawn(
+                pool.clone(),
+                outlook::OutlookConfig {
+                    tenant_id,
+                    client_id,
+                    client_secret,
+                    interval: std::time::Duration::from_secs(opt.outlook_sync_interval),
+                },
+            );
+        }
+    }
+
+    // import approved time off from the hr system, if one is configured
+    if let (Some(company), Some(api_key)) =
+        (opt.bamboohr_company.clone(), opt.bamboohr_api_key.clone())
+    {
+        hr::spawn(
+            pool.clone(),
+            Box::new(hr::BambooHr { company, api_key }),
+            opt.hr_sync_hour,
+        );
+    }
+
+    // partition (or archive) status history by month
+    partitions::spawn(
+        pool.clone(),
+        partitions::PartitionConfig {
+            retention_months: opt.history_retention_months,
+            #[cfg(feature = "sqlite")]
+            archive_dir: opt.history_archive_dir.clone(),
+        },
+    );
+
+    // bring teams and sites in line with the desired state, if one is declared
+    if let Some(dir) = &opt.desired_state {
+        desired_state::run(&pool, dir, false).await?;
+    }
+
+    // keep rate limits of public endpoints across restarts
+    let ratelimit = opt.rate_limit();
+    if opt.rate_limit_persist_interval > 0 {
+        ratelimit::spawn(
+            pool.clone(),
+            ratelimit.clone(),
+            std::time::Duration::from_secs(opt.rate_limit_persist_interval),
+        );
+    }
+
+    // events from slack are processed in the background
+    let events = handlers::event::spawn_worker(
+        pool.clone(),
+        feed.clone(),
+        opt.event_queue_size,
+        opt.event_dedup_ttl(),
+    );
+
+    let replica = connect_replica(&opt).await;
+    let state = build_state(&opt, pool, replica, feed, events, signing_secret);
+    let app = build_app(&opt, state, ratelimit);
+
+    // run the app
+    tracing::info!("Starting web server");
+    app.listen(format!("{}:{}", opt.host, opt.port)).await?;
+
+    Ok(())
+}
+
+/// Connects to the database, running migrations unless they're skipped
+///
+/// # Arguments
+/// * `opt` - Command line options and arguments
+async fn connect(opt: &Opt) -> Result<SqlPool> {
+    // connect to sql and build connection pool
+    let pool = SqlPool::connect(&opt.database).await?;
+
+    if !opt.skip_migrations {
+        // run migrations
+        run_migrations(&pool).await?;
+    }
+
+    // refuse to serve against a schema this build doesn't match
+    check_schema(&pool, opt.allow_pending_migrations).await?;
+
+    Ok(pool)
+}
+
+/// Connects to the read-only replica, if one is configured and reachable
+///
+/// Connections to the replica time out quickly, so reads fall back to the primary without
+/// much delay while it's down.
+///
+/// # Arguments
+/// * `opt` - Command line options and arguments
+async fn connect_replica(opt: &Opt) -> Option<SqlPool> {
+    let url = opt.replica_database.as_deref()?;
+
+    let replica = sqlx::pool::PoolOptions::new()
+        .connect_timeout(std::time::Duration::from_secs(REPLICA_CONNECT_TIMEOUT))
+        .connect(url)
+        .await;
+
+    match replica {
+        Ok(replica) => Some(replica),
+        Err(e) => {
+            tracing::warn!(
+                "failed to connect to replica, reading from primary: {:?}",
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Builds the state shared by every request
+///
+/// # Arguments
+/// * `opt` - Command line options and arguments
+/// * `pool` - A configured sql pool
+/// * `replica` - Pool of connections to a read-only replica, if configured
+/// * `feed` - Feed to publish status changes to
+/// * `events` - Queue of events waiting to be processed
+/// * `signing_secret` - Secret Slack request signatures are verified with, if any
+fn build_state(
+    opt: &Opt,
+    pool: SqlPool,
+    replica: Option<SqlPool>,
+    feed: StatusFeed,
+    events: EventSender,
+    signing_secret: Option<String>,
+) -> State {
+    // annotate team views with presence, if enabled
+    let presence = match opt.presence_ttl {
+        0 => None,
+        ttl => Some(PresenceCache::new(std::time::Duration::from_secs(ttl))),
+    };
+
+    // annotate team views with meetings, if enabled
+    let meetings = match opt.meeting_ttl {
+        0 => None,
+        ttl => Some(MeetingCache::new(std::time::Duration::from_secs(ttl))),
+    };
+
+    // link issue keys in statuses to jira, if enabled
+    let issues = match (&opt.jira_base_url, &opt.jira_api_token) {
+        (Some(base_url), Some(token)) => Some(IssueLinker::new(
+            base_url,
+            opt.jira_email.as_deref(),
+            token,
+            std::time::Duration::from_secs(opt.jira_cache_ttl),
+        )),
+        _ => None,
+    };
+
+    // capture inbound requests, if a directory is given
+    let capture = opt
+        .capture_dir
+        .clone()
+        .map(|dir| Capture::new(dir, opt.capture_max_bytes));
+
+    State::new(
+        pool,
+        feed,
+        events,
+        StateConfig {
+            replica,
+            slack_no_retry: opt.slack_no_retry,
+            max_slack_retries: opt.max_slack_retries,
+            signing_secret,
+            signature_tolerance: opt.signature_tolerance(),
+            presence,
+            meetings,
+            issues,
+            capture,
+            aliases: opt.command_aliases.clone().unwrap_or_default(),
+            desired_state: opt.desired_state.clone(),
+        },
+    )
+}
+
+/// Builds the web app, with its middleware and routes
+///
+/// # Arguments
+/// * `opt` - Command line options and arguments
+/// * `state` - State shared by every request
+/// * `ratelimit` - Rate limit applied to public endpoints
+fn build_app(opt: &Opt, state: State, ratelimit: ratelimit::RateLimit) -> tide::Server<State> {
+    // configure CORS middleware
+    let cors = CorsMiddleware::new()
+        .allow_methods("GET, POST, OPTIONS".parse::<HeaderValue>().unwrap())
+        .allow_origin(Origin::from("*"))
+        .allow_credentials(false);
+
+    // configure tracing middleware
+    let trace = TraceMiddleware::new();
+
+    // configure allowlists of the addresses requests may come from
+    let allowlist = IpAllowlist {
+        slack: opt.slack_allowlist.clone(),
+        admin: opt.admin_allowlist.clone(),
+        public: opt.public_allowlist.clone(),
+        trusted_proxies: opt.trusted_proxies.clone().unwrap_or_default(),
+    };
+
+    // configure body size limits
+    let limit = limits::BodyLimit::new(opt.max_body_size);
+
+    // configure compression middleware (gzip/brotli, based on Accept-Encoding)
+    let compress = CompressMiddleware::new();
+
+    // the admin ui requires an admin signed in with slack
+    let mut admin = tide::with_state(state.clone());
+    admin.with(handlers::auth::RequireAdmin);
+    admin.at("/").get(handlers::admin::index);
+    admin.at("/teams").post(handlers::admin::create_team);
+    admin.at("/usage").get(handlers::admin::usage);
+    admin.at("/queries").get(handlers::admin::queries);
+    admin.at("/breakers").get(handlers::admin::breakers);
+    admin.at("/anomalies").get(handlers::admin::anomalies);
+    admin.at("/capture").post(handlers::admin::capture);
+    admin.at("/teams/:team").get(handlers::admin::team);
+    admin
+        .at("/teams/:team/sign-in")
+        .get(handlers::admin::sign_in_sheet);
+    admin
+        .at("/teams/:team/delete")
+        .post(handlers::admin::delete_team);
+    admin
+        .at("/teams/:team/members")
+        .post(handlers::admin::add_member);
+    admin
+        .at("/teams/:team/members/:user/delete")
+        .post(handlers::admin::delete_member);
+
+    // the admin api requires the admin api token
+    let mut api = tide::with_state(state.clone());
+    api.with(handlers::api::RequireApiToken);
+    api.at("/teams")
+        .get(handlers::api::list_teams)
+        .post(handlers::api::create_team);
+    api.at("/teams/:team")
+        .get(handlers::api::get_team)
+        .put(handlers::api::put_team)
+        .delete(handlers::api::delete_team);
+    api.at("/teams/:team/members").get(handlers::api::list_members);
+    api.at("/teams/:team/statuses").get(handlers::api::list_statuses_as_of);
+    api.at("/teams/:team/retention")
+        .get(handlers::api::get_retention)
+        .put(handlers::api::put_retention)
+        .delete(handlers::api::delete_retention);
+    api.at("/teams/:team/members/:user")
+        .get(handlers::api::get_member)
+        .put(handlers::api::put_member)
+        .delete(handlers::api::delete_member);
+
+    // configure session middleware, used by the web ui
+    let secret = match dotenv::var("SESSION_SECRET") {
+        Ok(secret) if secret.len() >= 32 => secret.into_bytes(),
+        _ => {
+            tracing::warn!(
+                "SESSION_SECRET not set (or too short), sessions will not survive restarts"
+            );
+            rand::thread_rng().gen::<[u8; 32]>().to_vec()
+        }
+    };
+    let sessions = SessionMiddleware::new(MemoryStore::new(), &secret)
+        .with_cookie_name("statusbot.sid")
+        .with_same_site_policy(SameSite::Lax);
+
+    // create the actual web app
+    let mut app = tide::with_state(state);
+
+    // enable middlewares
+    app.with(cors);
+    app.with(trace);
+    app.with(allowlist);
+    app.with(limit);
+    app.with(capture::CaptureMiddleware);
+    app.with(compress);
+    app.with(sessions);
+
+    // add routes
+    app.at("/").post(extract::handler(handle_post));
+    app.at("/location")
+        .post(extract::handler(handlers::command::location));
+    app.at("/interactive")
+        .post(extract::handler(handlers::interactive::interactive));
+    app.at("/ws")
+        .with(handlers::live::RequireLiveAccess)
+        .get(WebSocket::new(handlers::live::websocket));
+    app.at("/feed/:feed")
+        .with(ratelimit.with_tokens(handlers::atom::check_token))
+        .get(handlers::atom::feed);
+    app.at("/badge/:badge")
+        .with(ratelimit.with_tokens(handlers::badge::check_token))
+        .get(handlers::badge::badge);
+    app.at("/health").get(handlers::health::health);
+    app.at("/desired-state")
+        .with(ratelimit)
+        .post(handlers::desired_state::reconcile);
+    app.at("/auth/login").get(handlers::auth::login);
+    app.at("/auth/callback").get(handlers::auth::callback);
+    app.at("/auth/logout").post(handlers::auth::logout);
+    app.at("/admin").nest(admin);
+    app.at("/api").nest(api);
+
+    app
+}
@@ -0,0 +1,150 @@
+//! Guards against oversized or pathological request bodies
+
+use async_trait::async_trait;
+use futures::io::AsyncReadExt;
+use tide::{http::Method, Middleware, Next, StatusCode};
+
+/// Maximum nesting depth of JSON objects/arrays accepted from clients
+pub const MAX_JSON_DEPTH: usize = 32;
+
+/// Middleware that rejects `POST` bodies larger than a configured size
+///
+/// Bodies are read (up to the limit) before being passed on, so requests without a
+/// `Content-Length` (e.g. chunked) are limited as well.
+#[derive(Debug)]
+pub struct BodyLimit {
+    /// Maximum body size, in bytes
+    max_bytes: usize,
+}
+
+impl BodyLimit {
+    /// Creates a new body limit
+    ///
+    /// # Arguments
+    /// * `max_bytes` - Maximum body size, in bytes
+    pub fn new(max_bytes: usize) -> Self {
+        BodyLimit { max_bytes }
+    }
+}
+
+#[async_trait]
+impl<S: Clone + Send + Sync + 'static> Middleware<S> for BodyLimit {
+    async fn handle(&self, mut req: tide::Request<S>, next: Next<'_, S>) -> tide::Result {
+        if req.method() != Method::Post {
+            return Ok(next.run(req).await);
+        }
+
+        if req.len().map(|len| len > self.max_bytes).unwrap_or(false) {
+            return Ok(tide::Response::builder(StatusCode::PayloadTooLarge).build());
+        }
+
+        let mut body = Vec::new();
+        req.take_body()
+            .take(self.max_bytes as u64 + 1)
+            .read_to_end(&mut body)
+            .await?;
+
+        if body.len() > self.max_bytes {
+            return Ok(tide::Response::builder(StatusCode::PayloadTooLarge).build());
+        }
+
+        req.set_body(body);
+
+        Ok(next.run(req).await)
+    }
+}
+
+/// Checks that JSON objects/arrays are not nested deeper than `max_depth`
+///
+/// This only scans for brackets (ignoring those inside strings) and does not validate
+/// the JSON, so it is cheap enough to run before deserializing.
+///
+/// # Arguments
+/// * `json` - Raw JSON text
+/// * `max_depth` - Maximum allowed nesting depth
+pub fn json_depth_ok(json: &[u8], max_depth: usize) -> bool {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for &b in json {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+
+        match b {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                depth += 1;
+                if depth > max_depth {
+                    return false;
+                }
+            }
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nesting_within_limit() {
+        assert!(json_depth_ok(b"", 0));
+        assert!(json_depth_ok(b"\"text\"", 0));
+        assert!(json_depth_ok(br#"{"a":[1,{"b":[]}],"c":{}}"#, 4));
+        assert!(json_depth_ok(br#"[[],[],[]]"#, 2));
+    }
+
+    #[test]
+    fn nesting_past_limit() {
+        assert!(!json_depth_ok(b"{}", 0));
+        assert!(!json_depth_ok(br#"{"a":[1,{"b":[]}]}"#, 3));
+
+        let deep = format!(
+            "{}{}",
+            "[".repeat(MAX_JSON_DEPTH + 1),
+            "]".repeat(MAX_JSON_DEPTH + 1)
+        );
+        assert!(json_depth_ok(
+            &deep.as_bytes()[1..deep.len() - 1],
+            MAX_JSON_DEPTH
+        ));
+        assert!(!json_depth_ok(deep.as_bytes(), MAX_JSON_DEPTH));
+
+        // unbalanced bodies are still limited by how deep they get
+        assert!(!json_depth_ok(
+            "[".repeat(MAX_JSON_DEPTH + 1).as_bytes(),
+            MAX_JSON_DEPTH
+        ));
+    }
+
+    #[test]
+    fn brackets_inside_strings() {
+        assert!(json_depth_ok(br#"{"a":"[[[{{{"}"#, 1));
+        assert!(json_depth_ok(br#"{"[":"{"}"#, 1));
+
+        // closing brackets in strings don't hide nesting after them
+        assert!(!json_depth_ok(br#"{"a":"]]]}}}","b":{"c":{}}}"#, 2));
+    }
+
+    #[test]
+    fn escaped_quotes() {
+        assert!(json_depth_ok(br#"{"a":"\"[[[\""}"#, 1));
+        assert!(json_depth_ok(br#"{"a":"\\\"[[["}"#, 1));
+
+        // an escaped backslash doesn't escape the quote after it
+        assert!(json_depth_ok(br#"{"a":"\\","b":[[]]}"#, 3));
+        assert!(!json_depth_ok(br#"{"a":"\\","b":[[]]}"#, 2));
+    }
+}
@@ -0,0 +1,13 @@
+//! Helpers for generating HTML and XML
+
+/// Escapes text for inclusion in an HTML or XML document
+///
+/// # Arguments
+/// * `text` - Text to escape
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
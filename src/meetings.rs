@@ -0,0 +1,149 @@
+//! Whether users are in a meeting, read from their Slack profiles
+//!
+//! Users are in a meeting while they're in a huddle, or while a calendar integration (e.g.,
+//! Google Calendar or Outlook for Slack) has set a meeting status that hasn't expired yet.
+//! Lookups are cached briefly, as meetings change often but `users.profile.get` is
+//! called once per user.
+
+use crate::slack;
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Status emoji set by calendar integrations while a user is in a meeting
+const MEETING_EMOJI: &[&str] = &[":spiral_calendar_pad:", ":calendar:", ":date:"];
+
+/// A meeting a user is in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Meeting {
+    /// When the meeting ends, if known
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl Meeting {
+    /// Describes the meeting for appending to a status (e.g., `in a meeting until 14:30`)
+    ///
+    /// # Arguments
+    /// * `tz` - Timezone to show the end of the meeting in
+    pub fn describe(&self, tz: Tz) -> String {
+        match self.until {
+            Some(until) => format!(
+                "in a meeting until {}",
+                until.with_timezone(&tz).format("%H:%M")
+            ),
+            None => "in a meeting".to_owned(),
+        }
+    }
+}
+
+/// Parses the meeting a user is in out of their Slack profile, if any
+///
+/// # Arguments
+/// * `profile` - Profile returned by `users.profile.get`
+/// * `now` - The current time
+fn parse_meeting(profile: &serde_json::Value, now: DateTime<Utc>) -> Option<Meeting> {
+    if profile["huddle_state"] == "in_a_huddle" {
+        let until = profile["huddle_state_expiration_ts"]
+            .as_i64()
+            .filter(|ts| *ts > now.timestamp())
+            .map(|ts| Utc.timestamp(ts, 0));
+        return Some(Meeting { until });
+    }
+
+    let emoji = profile["status_emoji"].as_str().unwrap_or_default();
+    let text = profile["status_text"]
+        .as_str()
+        .unwrap_or_default()
+        .to_lowercase();
+    if !MEETING_EMOJI.contains(&emoji) && !text.contains("meeting") && !text.contains("call") {
+        return None;
+    }
+
+    // calendar integrations clear the status when the meeting ends
+    match profile["status_expiration"].as_i64() {
+        Some(ts) if ts > now.timestamp() => Some(Meeting {
+            until: Some(Utc.timestamp(ts, 0)),
+        }),
+        _ => None,
+    }
+}
+
+/// Briefly caches whether users are in a meeting
+#[derive(Clone, Debug)]
+pub struct MeetingCache {
+    /// How long a lookup is reused for
+    ttl: Duration,
+
+    /// Cached lookups, and when they were made
+    entries: Arc<Mutex<HashMap<String, (Instant, Option<Meeting>)>>>,
+}
+
+impl MeetingCache {
+    /// Creates an empty cache
+    ///
+    /// # Arguments
+    /// * `ttl` - How long a lookup is reused for
+    pub fn new(ttl: Duration) -> Self {
+        MeetingCache {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Looks up which users are in a meeting, returning the meeting each of them is in
+    ///
+    /// Users whose profile could not be looked up are left out
+    ///
+    /// # Arguments
+    /// * `user_ids` - Slack IDs of the users
+    pub async fn lookup(&self, user_ids: &[String]) -> HashMap<String, Meeting> {
+        let now = Utc::now();
+        let mut found = HashMap::new();
+        let mut missing = vec![];
+
+        {
+            let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            for id in user_ids {
+                match entries.get(id) {
+                    Some((at, meeting)) if at.elapsed() < self.ttl => {
+                        // a cached meeting may have ended since it was looked up
+                        if let Some(meeting) = meeting {
+                            if meeting.until.map_or(true, |until| until > now) {
+                                found.insert(id.clone(), *meeting);
+                            }
+                        }
+                    }
+                    _ => missing.push(id.as_str()),
+                }
+            }
+        }
+
+        let mut entries = vec![];
+        for id in missing {
+            let meeting = match slack::get("users.profile.get", &[("user", id)]).await {
+                Ok(resp) => parse_meeting(&resp["profile"], now),
+                Err(e) => {
+                    tracing::warn!("failed to look up profile of {}: {:?}", id, e);
+                    continue;
+                }
+            };
+
+            if let Some(meeting) = meeting {
+                found.insert(id.to_owned(), meeting);
+            }
+            entries.push((id.to_owned(), meeting));
+        }
+
+        let mut cache = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        cache.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        for (id, meeting) in entries {
+            cache.insert(id, (Instant::now(), meeting));
+        }
+
+        found
+    }
+}
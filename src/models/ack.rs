@@ -0,0 +1,125 @@
+//! Acknowledgements of members' statuses by their team's leads
+
+use crate::{models::Team, SqlConn};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct StatusAck {
+    /// Slack ID of the member whose status was acknowledged
+    pub user_id: String,
+
+    /// The status that was acknowledged
+    pub status: String,
+
+    /// Slack ID of the lead who acknowledged it
+    pub acked_by: String,
+
+    /// When the status was acknowledged
+    pub acked_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+impl StatusAck {
+    /// Acknowledges a member's status, replacing any earlier acknowledgement in the team
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - Team the member belongs to
+    /// * `user_id` - Slack ID of the member
+    /// * `status` - The member's current status
+    /// * `acked_by` - Slack ID of the lead acknowledging it
+    pub async fn save(
+        db: &mut SqlConn,
+        team: &Team,
+        user_id: &str,
+        status: &str,
+        acked_by: &str,
+    ) -> anyhow::Result<Self> {
+        let ack = StatusAck {
+            user_id: user_id.to_owned(),
+            status: status.to_owned(),
+            acked_by: acked_by.to_owned(),
+            acked_at: Utc::now(),
+        };
+
+        timed!(
+            "sql/ack/save.sql",
+            sqlx::query_file!(
+                "sql/ack/save.sql",
+                team.id(),
+                ack.user_id,
+                ack.status,
+                ack.acked_by,
+                ack.acked_at
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(ack)
+    }
+
+    /// Fetches the acknowledgements of a team's members, keyed by member
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - The team
+    pub async fn fetch_by_team(
+        db: &mut SqlConn,
+        team: &Team,
+    ) -> anyhow::Result<HashMap<String, Self>> {
+        let acks = timed!(
+            "sql/ack/fetch_by_team.sql",
+            sqlx::query_file_as!(StatusAck, "sql/ack/fetch_by_team.sql", team.id())
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(acks
+            .into_iter()
+            .map(|ack| (ack.user_id.clone(), ack))
+            .collect())
+    }
+
+    /// Deletes all acknowledgements in a team
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_id` - Id of the team
+    pub async fn delete_by_team(db: &mut SqlConn, team_id: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/ack/delete_by_team.sql",
+            sqlx::query_file!("sql/ack/delete_by_team.sql", team_id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Moves a team's acknowledgements to another team, except of members it has already
+    /// acknowledged the status of
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `from` - Id of the team to move them from
+    /// * `to` - Id of the team to move them to
+    pub async fn move_by_team(db: &mut SqlConn, from: i64, to: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/ack/move_by_team.sql",
+            sqlx::query_file!("sql/ack/move_by_team.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns whether this acknowledges a status, which is no longer the case once the
+    /// member changes it
+    ///
+    /// # Arguments
+    /// * `status` - The member's current status
+    pub fn covers(&self, status: Option<&str>) -> bool {
+        status == Some(self.status.as_str())
+    }
+}
@@ -0,0 +1,181 @@
+//! Announcements sent to a team, and whether each reached its recipient
+
+use crate::{models::Team, SqlConn};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+
+#[derive(Clone, Debug)]
+pub struct Announcement {
+    /// Unique announcement id
+    pub id: i64,
+
+    /// Text of the announcement
+    pub message: String,
+
+    /// If the announcement was posted to the team's bound channel, instead of DMed to
+    /// each member
+    pub to_channel: bool,
+
+    /// Number of recipients the announcement was sent to
+    pub recipients: i64,
+
+    /// Slack ID of the user who sent the announcement
+    pub sent_by: String,
+
+    /// When the announcement was sent
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Delivery {
+    /// Slack ID of the member (or channel) the announcement was sent to
+    pub recipient: String,
+
+    /// If Slack accepted the message
+    pub delivered: bool,
+
+    /// Error reported by Slack, if the message wasn't delivered
+    pub error: Option<String>,
+}
+
+#[allow(dead_code)]
+impl Announcement {
+    /// Saves a new announcement to a team in the database
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - Team the announcement is sent to
+    /// * `message` - Text of the announcement
+    /// * `to_channel` - If the announcement is posted to the team's bound channel
+    /// * `recipients` - Number of recipients the announcement will be sent to
+    /// * `sent_by` - Slack ID of the user sending the announcement
+    pub async fn new(
+        db: &mut SqlConn,
+        team: &Team,
+        message: &str,
+        to_channel: bool,
+        recipients: i64,
+        sent_by: &str,
+    ) -> anyhow::Result<Self> {
+        timed!(
+            "sql/announcement/insert.sql",
+            sqlx::query_file!(
+                "sql/announcement/insert.sql",
+                team.id(),
+                message,
+                to_channel,
+                recipients,
+                sent_by
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        let announcement = timed!(
+            "sql/announcement/fetch_latest.sql",
+            sqlx::query_file_as!(Announcement, "sql/announcement/fetch_latest.sql", team.id())
+                .fetch_one(&mut *db)
+        )
+        .await?;
+
+        Ok(announcement)
+    }
+
+    /// Attempts to fetch the last announcement sent to a team, returning `None` if there
+    /// hasn't been one
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - Team the announcement was sent to
+    pub async fn fetch_latest(db: &mut SqlConn, team: &Team) -> Option<Self> {
+        let mut rows =
+            sqlx::query_file_as!(Announcement, "sql/announcement/fetch_latest.sql", team.id())
+                .fetch(&mut *db);
+
+        timed!("sql/announcement/fetch_latest.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Records whether the announcement reached a recipient
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `recipient` - Slack ID of the member (or channel)
+    /// * `error` - Error reported by Slack, if the message wasn't delivered
+    pub async fn record_delivery(
+        &self,
+        db: &mut SqlConn,
+        recipient: &str,
+        error: Option<&str>,
+    ) -> anyhow::Result<()> {
+        timed!(
+            "sql/announcement/insert_delivery.sql",
+            sqlx::query_file!(
+                "sql/announcement/insert_delivery.sql",
+                self.id,
+                recipient,
+                error.is_none(),
+                error
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetches the deliveries recorded so far, ordered by recipient
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn deliveries(&self, db: &mut SqlConn) -> anyhow::Result<Vec<Delivery>> {
+        let deliveries = timed!(
+            "sql/announcement/fetch_deliveries.sql",
+            sqlx::query_file_as!(Delivery, "sql/announcement/fetch_deliveries.sql", self.id)
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// Deletes every announcement sent to a team, and their deliveries
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_id` - Id of the team
+    pub async fn delete_by_team(db: &mut SqlConn, team_id: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/announcement/delete_deliveries_by_team.sql",
+            sqlx::query_file!("sql/announcement/delete_deliveries_by_team.sql", team_id)
+                .execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/announcement/delete_by_team.sql",
+            sqlx::query_file!("sql/announcement/delete_by_team.sql", team_id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Moves every announcement sent to a team, and their deliveries, to another team
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `from` - Id of the team to move them from
+    /// * `to` - Id of the team to move them to
+    pub async fn move_by_team(db: &mut SqlConn, from: i64, to: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/announcement/move_by_team.sql",
+            sqlx::query_file!("sql/announcement/move_by_team.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
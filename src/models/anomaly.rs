@@ -0,0 +1,138 @@
+//! Unusual patterns in the statuses users set (see `anomaly`)
+//!
+//! Like `HistoryEntry`, these read `status_history`, or the status events when
+//! `STATUS_STORE=events`.
+
+use crate::{models::StatusStore, SqlConn};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// An active user who hasn't set a status in a while
+#[derive(Clone, Debug)]
+pub struct StaleUser {
+    /// The unique identifier provided by Slack
+    pub user_id: String,
+
+    /// When the user last set a status
+    pub last_changed_at: DateTime<Utc>,
+}
+
+/// A user who set many statuses in a short period
+#[derive(Clone, Debug)]
+pub struct BusyUser {
+    /// The unique identifier provided by Slack
+    pub user_id: String,
+
+    /// Number of statuses the user set over the period
+    pub changes: i64,
+
+    /// When the user first set a status over the period
+    pub first_changed_at: DateTime<Utc>,
+
+    /// When the user last set a status over the period
+    pub last_changed_at: DateTime<Utc>,
+}
+
+impl StaleUser {
+    /// Returns the members of a team with an active Slack account who last set a status
+    /// before a point in time, longest ago first
+    ///
+    /// Users who never set a status and users on leave are left out
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `before` - Point in time users must not have set a status since
+    /// * `today` - Today, to leave out users on leave
+    pub async fn fetch(
+        db: &mut SqlConn,
+        before: DateTime<Utc>,
+        today: NaiveDate,
+    ) -> anyhow::Result<Vec<Self>> {
+        let users = match StatusStore::current() {
+            StatusStore::History => timed!(
+                "sql/anomaly/stale.sql",
+                sqlx::query_file!("sql/anomaly/stale.sql", before, today).fetch_all(&mut *db)
+            )
+            .await?
+            .into_iter()
+            .filter_map(|row| {
+                row.last_changed_at.map(|last_changed_at| StaleUser {
+                    user_id: row.user_id,
+                    last_changed_at,
+                })
+            })
+            .collect(),
+            StatusStore::Events => timed!(
+                "sql/anomaly/stale_events.sql",
+                sqlx::query_file!("sql/anomaly/stale_events.sql", before, today)
+                    .fetch_all(&mut *db)
+            )
+            .await?
+            .into_iter()
+            .filter_map(|row| {
+                row.last_changed_at.map(|last_changed_at| StaleUser {
+                    user_id: row.user_id,
+                    last_changed_at,
+                })
+            })
+            .collect(),
+        };
+
+        Ok(users)
+    }
+}
+
+impl BusyUser {
+    /// Returns the users who set at least `min_changes` statuses since a point in time, most
+    /// first
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `since` - Start of the period
+    /// * `min_changes` - Fewest statuses a user must have set
+    pub async fn fetch(
+        db: &mut SqlConn,
+        since: DateTime<Utc>,
+        min_changes: i64,
+    ) -> anyhow::Result<Vec<Self>> {
+        let users = match StatusStore::current() {
+            StatusStore::History => timed!(
+                "sql/anomaly/busy.sql",
+                sqlx::query_file!("sql/anomaly/busy.sql", since, min_changes).fetch_all(&mut *db)
+            )
+            .await?
+            .into_iter()
+            .map(|row| BusyUser {
+                user_id: row.user_id,
+                changes: row.changes.unwrap_or_default(),
+                first_changed_at: row.first_changed_at.unwrap_or(since),
+                last_changed_at: row.last_changed_at.unwrap_or(since),
+            })
+            .collect(),
+            StatusStore::Events => timed!(
+                "sql/anomaly/busy_events.sql",
+                sqlx::query_file!("sql/anomaly/busy_events.sql", since, min_changes)
+                    .fetch_all(&mut *db)
+            )
+            .await?
+            .into_iter()
+            .map(|row| BusyUser {
+                user_id: row.user_id,
+                changes: row.changes.unwrap_or_default(),
+                first_changed_at: row.first_changed_at.unwrap_or(since),
+                last_changed_at: row.last_changed_at.unwrap_or(since),
+            })
+            .collect(),
+        };
+
+        Ok(users)
+    }
+
+    /// Returns the average number of seconds between the statuses the user set
+    pub fn average_interval_secs(&self) -> i64 {
+        if self.changes < 2 {
+            return 0;
+        }
+
+        (self.last_changed_at - self.first_changed_at).num_seconds() / (self.changes - 1)
+    }
+}
@@ -0,0 +1,350 @@
+//! Chains of approvers of a team's leave, and leave waiting for approval (see `approval`)
+
+use crate::SqlConn;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use futures::TryStreamExt;
+
+#[derive(Clone, Debug)]
+pub struct ApprovalChain {
+    /// Id of the team
+    pub team_id: i64,
+
+    /// Slack IDs of the approvers, comma-separated, in the order they're asked
+    approvers: String,
+
+    /// Hours an approver has to decide before the next one is asked
+    pub timeout_hours: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Approval {
+    /// Unique approval id
+    pub id: i64,
+
+    /// Name of the team whose chain approves the leave
+    pub team: String,
+
+    /// Slack ID of the user who requested the leave
+    pub user_id: String,
+
+    /// First day of leave
+    pub starts_on: NaiveDate,
+
+    /// Last day of leave (inclusive)
+    pub ends_on: NaiveDate,
+
+    /// Slack IDs of the approvers, comma-separated, as configured when the leave was requested
+    approvers: String,
+
+    /// Hours each approver has to decide
+    pub timeout_hours: i64,
+
+    /// Position of the current approver in the chain
+    pub step: i64,
+
+    /// Slack ID of the current approver
+    pub approver_id: String,
+
+    /// When the request is escalated to the next approver, `None` if it's decided or the
+    /// current approver is the last
+    pub escalates_at: Option<DateTime<Utc>>,
+
+    /// Whether the leave was approved, `None` while it's pending
+    pub approved: Option<bool>,
+}
+
+/// Splits a comma-separated list of approvers
+///
+/// # Arguments
+/// * `approvers` - The approvers, comma-separated
+fn split(approvers: &str) -> Vec<&str> {
+    approvers.split(',').filter(|id| !id.is_empty()).collect()
+}
+
+impl ApprovalChain {
+    /// Creates a chain but does *not* save it in the database
+    ///
+    /// # Arguments
+    /// * `team_id` - Id of the team
+    /// * `approvers` - Slack IDs of the approvers, in the order they're asked
+    /// * `timeout_hours` - Hours an approver has to decide before the next one is asked
+    pub fn new(team_id: i64, approvers: &[String], timeout_hours: i64) -> Self {
+        ApprovalChain {
+            team_id,
+            approvers: approvers.join(","),
+            timeout_hours,
+        }
+    }
+
+    /// Returns the Slack IDs of the approvers, in the order they're asked
+    pub fn approvers(&self) -> Vec<&str> {
+        split(&self.approvers)
+    }
+
+    /// Fetches the chain of a team, returning `None` if it has none
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_id` - Id of the team
+    pub async fn fetch_by_team(db: &mut SqlConn, team_id: i64) -> anyhow::Result<Option<Self>> {
+        let chain = timed!(
+            "sql/approval/fetch_chain.sql",
+            sqlx::query_file_as!(ApprovalChain, "sql/approval/fetch_chain.sql", team_id)
+                .fetch_optional(&mut *db)
+        )
+        .await?;
+
+        Ok(chain)
+    }
+
+    /// Fetches the chain approving a user's leave: that of the first of their teams (by name)
+    /// with one, or `None` if none of their teams has one
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    pub async fn fetch_by_member(db: &mut SqlConn, user_id: &str) -> anyhow::Result<Option<Self>> {
+        let chain = timed!(
+            "sql/approval/fetch_chain_by_member.sql",
+            sqlx::query_file_as!(
+                ApprovalChain,
+                "sql/approval/fetch_chain_by_member.sql",
+                user_id
+            )
+            .fetch_optional(&mut *db)
+        )
+        .await?;
+
+        Ok(chain)
+    }
+
+    /// Saves this chain, replacing the team's previous one
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn save(&self, db: &mut SqlConn) -> anyhow::Result<()> {
+        timed!(
+            "sql/approval/save_chain.sql",
+            sqlx::query_file!(
+                "sql/approval/save_chain.sql",
+                self.team_id,
+                self.approvers,
+                self.timeout_hours
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the chain of a team, leaving requests already sent to it pending
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_id` - Id of the team
+    pub async fn delete(db: &mut SqlConn, team_id: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/approval/delete_chain.sql",
+            sqlx::query_file!("sql/approval/delete_chain.sql", team_id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the chain of a team, and every request sent to it
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_id` - Id of the team
+    pub async fn delete_by_team(db: &mut SqlConn, team_id: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/approval/delete_by_team.sql",
+            sqlx::query_file!("sql/approval/delete_by_team.sql", team_id).execute(&mut *db)
+        )
+        .await?;
+
+        ApprovalChain::delete(db, team_id).await
+    }
+
+    /// Moves every request sent to a team's chain to another team, and the chain itself unless
+    /// the other team has its own
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `from` - Id of the team to move them from
+    /// * `to` - Id of the team to move them to
+    pub async fn move_by_team(db: &mut SqlConn, from: i64, to: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/approval/move_by_team.sql",
+            sqlx::query_file!("sql/approval/move_by_team.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/approval/move_chain_by_team.sql",
+            sqlx::query_file!("sql/approval/move_chain_by_team.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Requests approval of a user's leave from the first approver of this chain, returning
+    /// the request
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `starts_on` - First day of leave
+    /// * `ends_on` - Last day of leave (inclusive)
+    pub async fn request(
+        &self,
+        db: &mut SqlConn,
+        user_id: &str,
+        starts_on: NaiveDate,
+        ends_on: NaiveDate,
+    ) -> anyhow::Result<Approval> {
+        let approvers = self.approvers();
+        let approver_id = approvers.first().copied().unwrap_or_default();
+        let escalates_at = if approvers.len() > 1 {
+            Some(Utc::now() + Duration::hours(self.timeout_hours))
+        } else {
+            None
+        };
+
+        timed!(
+            "sql/approval/insert.sql",
+            sqlx::query_file!(
+                "sql/approval/insert.sql",
+                self.team_id,
+                user_id,
+                starts_on,
+                ends_on,
+                self.approvers,
+                self.timeout_hours,
+                approver_id,
+                escalates_at
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        let approval = timed!(
+            "sql/approval/fetch_latest.sql",
+            sqlx::query_file_as!(Approval, "sql/approval/fetch_latest.sql", user_id)
+                .fetch_one(&mut *db)
+        )
+        .await?;
+
+        Ok(approval)
+    }
+}
+
+impl Approval {
+    /// Attempts to fetch an approval, returning `None` if it does not exist
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `id` - Id of the approval
+    pub async fn fetch(db: &mut SqlConn, id: i64) -> Option<Self> {
+        let mut rows = sqlx::query_file_as!(Approval, "sql/approval/fetch.sql", id).fetch(&mut *db);
+
+        timed!("sql/approval/fetch.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Fetches pending approvals due to be escalated, soonest first
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `now` - Current time
+    /// * `limit` - Maximum number of approvals to fetch
+    pub async fn fetch_due(
+        db: &mut SqlConn,
+        now: DateTime<Utc>,
+        limit: i64,
+    ) -> anyhow::Result<Vec<Self>> {
+        let approvals = timed!(
+            "sql/approval/fetch_due.sql",
+            sqlx::query_file_as!(Approval, "sql/approval/fetch_due.sql", now, limit)
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(approvals)
+    }
+
+    /// Returns the Slack IDs of the approvers, in the order they're asked
+    pub fn approvers(&self) -> Vec<&str> {
+        split(&self.approvers)
+    }
+
+    /// Passes this request to the next approver of its chain, returning whether it was
+    /// escalated (`false` if it was decided, or escalated by another replica, in the meantime)
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `approver_id` - Slack ID of the next approver
+    pub async fn escalate(&mut self, db: &mut SqlConn, approver_id: &str) -> anyhow::Result<bool> {
+        let step = self.step + 1;
+        let escalates_at = if (step as usize) + 1 < self.approvers().len() {
+            Some(Utc::now() + Duration::hours(self.timeout_hours))
+        } else {
+            None
+        };
+
+        let updated = timed!(
+            "sql/approval/escalate.sql",
+            sqlx::query_file!(
+                "sql/approval/escalate.sql",
+                self.id,
+                self.step,
+                step,
+                approver_id,
+                escalates_at
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        if updated > 0 {
+            self.step = step;
+            self.approver_id = approver_id.to_owned();
+            self.escalates_at = escalates_at;
+        }
+
+        Ok(updated > 0)
+    }
+
+    /// Records the current approver's decision, returning whether it was recorded (`false` if
+    /// the request was decided or escalated in the meantime)
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `approver_id` - Slack ID of the approver deciding
+    /// * `approved` - Whether the leave is approved
+    pub async fn decide(
+        &mut self,
+        db: &mut SqlConn,
+        approver_id: &str,
+        approved: bool,
+    ) -> anyhow::Result<bool> {
+        let updated = timed!(
+            "sql/approval/decide.sql",
+            sqlx::query_file!("sql/approval/decide.sql", self.id, approved, approver_id)
+                .execute(&mut *db)
+        )
+        .await?;
+
+        if updated > 0 {
+            self.approved = Some(approved);
+            self.escalates_at = None;
+        }
+
+        Ok(updated > 0)
+    }
+}
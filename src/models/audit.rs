@@ -0,0 +1,108 @@
+//! Log of admin-level actions, reported weekly (see `audit`)
+
+use crate::SqlConn;
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug)]
+pub struct AuditEntry {
+    /// Unique entry id
+    pub id: i64,
+
+    /// Slack ID of the user who took the action, or `AuditEntry::API` for the admin api (or
+    /// `AuditEntry::CONFIG` for configuration files)
+    pub actor: String,
+
+    /// What was done (e.g., `AuditEntry::TEAM_DELETED`)
+    pub action: String,
+
+    /// Name of the team it was done to, or `AuditEntry::ALL_TEAMS`
+    pub team: String,
+
+    /// Slack ID of the user it was done to, if any
+    pub subject: Option<String>,
+
+    /// What changed (e.g., the role granted), if anything
+    pub details: Option<String>,
+
+    /// When it was done
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    /// Actor of actions taken through the admin api
+    pub const API: &'static str = "admin api";
+
+    /// Actor of actions taken from configuration files (`statusbot config` and desired state
+    /// reconciliation)
+    pub const CONFIG: &'static str = "config";
+
+    /// Team of actions taken to several teams at once
+    pub const ALL_TEAMS: &'static str = "*";
+
+    /// A team was deleted
+    pub const TEAM_DELETED: &'static str = "team_deleted";
+
+    /// A member of a team was given a role (`details`)
+    pub const ROLE_GRANTED: &'static str = "role_granted";
+
+    /// How long a team's history is kept was changed (`details`, or the default if `None`)
+    pub const RETENTION_CHANGED: &'static str = "retention_changed";
+
+    /// Changes were only simulated by a dry run, and rolled back (`details` lists them)
+    pub const SIMULATED: &'static str = "simulated";
+
+    /// Records an action
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `actor` - Slack ID of the user who took the action, or `AuditEntry::API`
+    /// * `action` - What was done
+    /// * `team` - Name of the team it was done to
+    /// * `subject` - Slack ID of the user it was done to, if any
+    /// * `details` - What changed, if anything
+    pub async fn record(
+        db: &mut SqlConn,
+        actor: &str,
+        action: &str,
+        team: &str,
+        subject: Option<&str>,
+        details: Option<&str>,
+    ) -> anyhow::Result<()> {
+        timed!(
+            "sql/audit/insert.sql",
+            sqlx::query_file!(
+                "sql/audit/insert.sql",
+                actor,
+                action,
+                team,
+                subject,
+                details
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetches the actions taken over a period, oldest first
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `since` - Start of the period
+    /// * `until` - End of the period (exclusive)
+    pub async fn fetch_between(
+        db: &mut SqlConn,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Self>> {
+        let entries = timed!(
+            "sql/audit/fetch_between.sql",
+            sqlx::query_file_as!(AuditEntry, "sql/audit/fetch_between.sql", since, until)
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(entries)
+    }
+}
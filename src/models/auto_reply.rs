@@ -0,0 +1,97 @@
+//! Per-user settings for replying to mentions of users who are on leave
+
+use crate::SqlConn;
+use futures::TryStreamExt;
+
+#[derive(Clone, Debug)]
+pub struct AutoReply {
+    /// Slack ID of the user these settings belong to
+    pub user_id: String,
+
+    /// If mentions of the user are replied to while they're on leave
+    pub enabled: bool,
+
+    /// Slack ID of who to contact instead, if set
+    pub contact: Option<String>,
+}
+
+#[allow(dead_code)]
+impl AutoReply {
+    /// Creates auto-reply settings but does *not* save them in the database
+    ///
+    /// # Arguments
+    /// * `user_id` - Slack ID of the user
+    /// * `enabled` - If mentions of the user are replied to while they're on leave
+    /// * `contact` - Slack ID of who to contact instead, if set
+    pub fn new(user_id: &str, enabled: bool, contact: Option<String>) -> Self {
+        AutoReply {
+            user_id: user_id.to_owned(),
+            enabled,
+            contact,
+        }
+    }
+
+    /// Attempts to fetch a user's auto-reply settings, returning `None` if they haven't
+    /// set any
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    pub async fn fetch(db: &mut SqlConn, user_id: &str) -> Option<Self> {
+        let mut rows = sqlx::query_file_as!(AutoReply, "sql/auto_reply/fetch_by_id.sql", user_id)
+            .fetch(&mut *db);
+
+        timed!("sql/auto_reply/fetch_by_id.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Saves these settings in the database, replacing any the user already has
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn save(&self, db: &mut SqlConn) -> anyhow::Result<()> {
+        timed!(
+            "sql/auto_reply/save.sql",
+            sqlx::query_file!(
+                "sql/auto_reply/save.sql",
+                self.user_id,
+                self.enabled,
+                self.contact
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a reply to a mention of the user in a thread, returning `false` if the
+    /// thread was already replied in
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `channel` - Channel the mention was in
+    /// * `thread_ts` - Timestamp of the thread's first message
+    pub async fn record_thread(
+        &self,
+        db: &mut SqlConn,
+        channel: &str,
+        thread_ts: &str,
+    ) -> anyhow::Result<bool> {
+        let inserted = timed!(
+            "sql/auto_reply/record_thread.sql",
+            sqlx::query_file!(
+                "sql/auto_reply/record_thread.sql",
+                self.user_id,
+                channel,
+                thread_ts
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(inserted > 0)
+    }
+}
@@ -0,0 +1,265 @@
+//! Statuses set for every member of a team at once (e.g., for an offsite)
+
+use crate::{
+    models::{HistoryEntry, Team, User},
+    SqlConn,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::TryStreamExt;
+
+#[derive(Clone, Debug)]
+pub struct BulkStatus {
+    /// Unique bulk status id
+    pub id: i64,
+
+    /// Id of the team whose members the status is set for
+    team_id: i64,
+
+    /// The status set for every member
+    pub status: String,
+
+    /// Day the status is set on
+    pub day: NaiveDate,
+
+    /// Slack ID of the user who set the status
+    pub set_by: String,
+
+    /// When the status was set for the team's members, or `None` if its day hasn't come
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+/// A member's status before a bulk status was set
+struct PreviousStatus {
+    /// Slack ID of the member
+    user_id: String,
+
+    /// The member's status before, if they had one
+    previous: Option<String>,
+}
+
+#[allow(dead_code)]
+impl BulkStatus {
+    /// Saves a status to set for every member of a team on a day, without setting it
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - The team
+    /// * `status` - The status to set
+    /// * `day` - Day to set the status on
+    /// * `set_by` - Slack ID of the user setting the status
+    pub async fn new(
+        db: &mut SqlConn,
+        team: &Team,
+        status: &str,
+        day: NaiveDate,
+        set_by: &str,
+    ) -> anyhow::Result<Self> {
+        timed!(
+            "sql/bulk_status/insert.sql",
+            sqlx::query_file!("sql/bulk_status/insert.sql", team.id(), status, day, set_by)
+                .execute(&mut *db)
+        )
+        .await?;
+
+        let bulk = BulkStatus::fetch_latest(&mut *db, team)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("bulk status for team {} not saved", team.name))?;
+
+        Ok(bulk)
+    }
+
+    /// Attempts to fetch the last bulk status set for a team that hasn't been undone,
+    /// returning `None` if there isn't one
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - The team
+    pub async fn fetch_latest(db: &mut SqlConn, team: &Team) -> Option<Self> {
+        let mut rows =
+            sqlx::query_file_as!(BulkStatus, "sql/bulk_status/fetch_latest.sql", team.id())
+                .fetch(&mut *db);
+
+        timed!("sql/bulk_status/fetch_latest.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Fetches the bulk statuses whose day has come but haven't been set yet
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `today` - Today's date
+    pub async fn fetch_due(db: &mut SqlConn, today: NaiveDate) -> anyhow::Result<Vec<Self>> {
+        let due = timed!(
+            "sql/bulk_status/fetch_due.sql",
+            sqlx::query_file_as!(BulkStatus, "sql/bulk_status/fetch_due.sql", today)
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(due)
+    }
+
+    /// Sets the status for every member of the team in a single transaction, recording each
+    /// member's status before and returning the updated members
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn apply(&mut self, db: &mut SqlConn) -> anyhow::Result<Vec<User>> {
+        let applied_at = Utc::now();
+        let members = transaction!(db, async {
+            let mut members = timed!(
+                "sql/bulk_status/fetch_team_members.sql",
+                sqlx::query_file_as!(User, "sql/bulk_status/fetch_team_members.sql", self.team_id)
+                    .fetch_all(&mut *db)
+            )
+            .await?;
+
+            for member in &mut members {
+                timed!(
+                    "sql/bulk_status/insert_member.sql",
+                    sqlx::query_file!(
+                        "sql/bulk_status/insert_member.sql",
+                        self.id,
+                        member.id,
+                        member.status
+                    )
+                    .execute(&mut *db)
+                )
+                .await?;
+
+                member.set_status(self.status.clone());
+                member.save(&mut *db).await?;
+            }
+
+            timed!(
+                "sql/bulk_status/set_applied.sql",
+                sqlx::query_file!("sql/bulk_status/set_applied.sql", self.id, applied_at)
+                    .execute(&mut *db)
+            )
+            .await?;
+
+            Ok::<_, anyhow::Error>(members)
+        })?;
+
+        self.applied_at = Some(applied_at);
+        Ok(members)
+    }
+
+    /// Undoes this bulk status, returning the members whose status was restored
+    ///
+    /// Members who have changed their status since keep it.  If the status hasn't been set
+    /// yet, it's cancelled instead
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn undo(&self, db: &mut SqlConn) -> anyhow::Result<Vec<User>> {
+        let undone_at = Utc::now();
+        transaction!(db, async {
+            let previous = timed!(
+                "sql/bulk_status/fetch_members.sql",
+                sqlx::query_file_as!(PreviousStatus, "sql/bulk_status/fetch_members.sql", self.id)
+                    .fetch_all(&mut *db)
+            )
+            .await?;
+
+            // guests can't be fetched by id, so the whole team is fetched instead
+            let mut members = timed!(
+                "sql/bulk_status/fetch_team_members.sql",
+                sqlx::query_file_as!(User, "sql/bulk_status/fetch_team_members.sql", self.team_id)
+                    .fetch_all(&mut *db)
+            )
+            .await?;
+
+            let mut restored = vec![];
+            for member in previous {
+                let updated = timed!(
+                    "sql/bulk_status/restore.sql",
+                    sqlx::query_file!(
+                        "sql/bulk_status/restore.sql",
+                        member.user_id,
+                        self.status,
+                        member.previous
+                    )
+                    .execute(&mut *db)
+                )
+                .await?;
+
+                if updated == 0 {
+                    continue;
+                }
+
+                if let Some(i) = members.iter().position(|user| user.id == member.user_id) {
+                    let mut user = members.swap_remove(i);
+                    user.status = member.previous;
+                    if user.status.is_some() {
+                        HistoryEntry::record(&mut *db, &user).await?;
+                    }
+                    restored.push(user);
+                }
+            }
+
+            timed!(
+                "sql/bulk_status/set_undone.sql",
+                sqlx::query_file!("sql/bulk_status/set_undone.sql", self.id, undone_at)
+                    .execute(&mut *db)
+            )
+            .await?;
+
+            Ok::<_, anyhow::Error>(restored)
+        })
+    }
+
+    /// Deletes all of a team's bulk statuses
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_id` - Id of the team
+    pub async fn delete_by_team(db: &mut SqlConn, team_id: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/bulk_status/delete_members_by_team.sql",
+            sqlx::query_file!("sql/bulk_status/delete_members_by_team.sql", team_id)
+                .execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/bulk_status/delete_by_team.sql",
+            sqlx::query_file!("sql/bulk_status/delete_by_team.sql", team_id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Moves all of a team's bulk statuses to another team
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `from` - Id of the team to move them from
+    /// * `to` - Id of the team to move them to
+    pub async fn move_by_team(db: &mut SqlConn, from: i64, to: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/bulk_status/move_by_team.sql",
+            sqlx::query_file!("sql/bulk_status/move_by_team.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sets every bulk status whose day has come, returning the members whose status was set
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `today` - Today's date
+    pub async fn apply_due(db: &mut SqlConn, today: NaiveDate) -> anyhow::Result<Vec<User>> {
+        let mut members = vec![];
+        for mut bulk in BulkStatus::fetch_due(&mut *db, today).await? {
+            members.extend(bulk.apply(&mut *db).await?);
+        }
+
+        Ok(members)
+    }
+}
@@ -0,0 +1,113 @@
+//! Personal calendars users have registered to import their leave from
+
+use crate::{error::Error, SqlConn};
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug)]
+pub struct Calendar {
+    /// Unique calendar id
+    id: i64,
+
+    /// Slack ID of the user this calendar belongs to
+    pub user_id: String,
+
+    /// URL of the calendar, in iCalendar format
+    pub url: String,
+
+    /// When the calendar was last synced, if it has been
+    pub synced_at: Option<DateTime<Utc>>,
+}
+
+#[allow(dead_code)]
+impl Calendar {
+    /// Registers a calendar for a user
+    ///
+    /// Fails with `Error::Parse` if the URL isn't an `http(s)` URL.  If the user has already
+    /// registered the calendar, does nothing
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `url` - URL of the calendar
+    pub async fn add(db: &mut SqlConn, user_id: &str, url: &str) -> anyhow::Result<()> {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(Error::Parse(format!(
+                "`{}` is not a valid calendar URL. Please specify an `https://` URL",
+                url
+            ))
+            .into());
+        }
+
+        timed!(
+            "sql/calendar/insert.sql",
+            sqlx::query_file!("sql/calendar/insert.sql", user_id, url).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetches the calendars a user has registered
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    pub async fn fetch_by_user(db: &mut SqlConn, user_id: &str) -> anyhow::Result<Vec<Self>> {
+        let calendars = timed!(
+            "sql/calendar/fetch_by_user.sql",
+            sqlx::query_file_as!(Calendar, "sql/calendar/fetch_by_user.sql", user_id)
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(calendars)
+    }
+
+    /// Fetches every registered calendar
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn fetch_all(db: &mut SqlConn) -> anyhow::Result<Vec<Self>> {
+        let calendars = timed!(
+            "sql/calendar/fetch_all.sql",
+            sqlx::query_file_as!(Calendar, "sql/calendar/fetch_all.sql").fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(calendars)
+    }
+
+    /// Records that the calendar was just synced
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn mark_synced(&mut self, db: &mut SqlConn) -> anyhow::Result<()> {
+        let now = Utc::now();
+        timed!(
+            "sql/calendar/set_synced.sql",
+            sqlx::query_file!("sql/calendar/set_synced.sql", now, self.id).execute(&mut *db)
+        )
+        .await?;
+
+        self.synced_at = Some(now);
+        Ok(())
+    }
+
+    /// Unregisters one of a user's calendars
+    ///
+    /// Leave already imported from the calendar is kept
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `url` - URL of the calendar
+    pub async fn remove(db: &mut SqlConn, user_id: &str, url: &str) -> anyhow::Result<()> {
+        timed!(
+            "sql/calendar/delete.sql",
+            sqlx::query_file!("sql/calendar/delete.sql", user_id, url).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+//! Anonymized usage of slash commands
+//!
+//! Only the action that ran, the workspace it ran in, how long it took, and whether it
+//! succeeded are recorded; never who ran it or what they typed.
+
+use crate::SqlConn;
+use chrono::{DateTime, Utc};
+
+/// Usage of one action over a period
+#[derive(Clone, Debug)]
+pub struct CommandUsage {
+    /// Name of the action (e.g., `show_team`)
+    pub action: String,
+
+    /// Number of times the action ran
+    pub uses: i64,
+
+    /// Number of times the action failed
+    pub failures: i64,
+
+    /// Average time taken to run the action, in milliseconds
+    pub avg_latency_ms: i64,
+
+    /// Number of workspaces the action ran in
+    pub workspaces: i64,
+}
+
+/// A slash command that ran
+pub struct CommandStat;
+
+#[allow(dead_code)]
+impl CommandStat {
+    /// Records that a slash command ran
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `action` - Name of the action that ran
+    /// * `workspace` - Slack ID of the workspace it ran in
+    /// * `latency_ms` - Time taken to run it, in milliseconds
+    /// * `success` - If it succeeded
+    pub async fn record(
+        db: &mut SqlConn,
+        action: &str,
+        workspace: &str,
+        latency_ms: i64,
+        success: bool,
+    ) -> anyhow::Result<()> {
+        timed!(
+            "sql/command_stat/insert.sql",
+            sqlx::query_file!(
+                "sql/command_stat/insert.sql",
+                action,
+                workspace,
+                latency_ms,
+                success
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Summarizes the usage of every action run since a point in time, most used first
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `since` - Start of the period to summarize
+    pub async fn summarize(
+        db: &mut SqlConn,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<CommandUsage>> {
+        let rows = timed!(
+            "sql/command_stat/summarize.sql",
+            sqlx::query_file!("sql/command_stat/summarize.sql", since).fetch_all(&mut *db)
+        )
+        .await?;
+
+        let usage = rows
+            .into_iter()
+            .map(|row| CommandUsage {
+                action: row.action,
+                uses: row.uses.unwrap_or_default(),
+                failures: row.failures.unwrap_or_default(),
+                avg_latency_ms: row.avg_latency_ms.unwrap_or_default(),
+                workspaces: row.workspaces.unwrap_or_default(),
+            })
+            .collect();
+
+        Ok(usage)
+    }
+}
@@ -0,0 +1,132 @@
+//! How users prefer to be notified when they're away from Slack
+
+use crate::{error::Error, SqlConn};
+
+/// Channels notifications can be delivered through
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContactChannel {
+    /// A direct message on Slack
+    Slack,
+
+    /// An email to the address on the user's Slack profile
+    Email,
+
+    /// A text message to the user's phone
+    Sms,
+}
+
+impl ContactChannel {
+    /// Returns the name of this channel, as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContactChannel::Slack => "slack",
+            ContactChannel::Email => "email",
+            ContactChannel::Sms => "sms",
+        }
+    }
+}
+
+impl std::str::FromStr for ContactChannel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "slack" | "dm" => Ok(ContactChannel::Slack),
+            "email" => Ok(ContactChannel::Email),
+            "sms" | "text" => Ok(ContactChannel::Sms),
+            _ => Err(Error::Parse(format!(
+                "*{}* is not a valid channel. Please specify `slack`, `email`, or `sms`",
+                s
+            ))),
+        }
+    }
+}
+
+/// Validates a phone number in E.164 format (e.g., `+15551234567`)
+///
+/// # Arguments
+/// * `phone` - The phone number
+pub fn validate_phone(phone: &str) -> Result<(), Error> {
+    let digits = phone.strip_prefix('+').unwrap_or_default();
+    if (8..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit()) {
+        Ok(())
+    } else {
+        Err(Error::Parse(format!(
+            "*{}* is not a valid phone number. Please include the country code (e.g., `+15551234567`)",
+            phone
+        )))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ContactPreference {
+    /// Slack ID of the user
+    pub user_id: String,
+
+    /// Channel the user prefers, as stored in the database
+    channel: String,
+
+    /// Phone number text messages are sent to, in E.164 format
+    pub phone: Option<String>,
+}
+
+impl ContactPreference {
+    /// Creates a preference but does *not* save it in the database
+    ///
+    /// # Arguments
+    /// * `user_id` - Slack ID of the user
+    /// * `channel` - Channel the user prefers
+    /// * `phone` - Phone number text messages are sent to
+    pub fn new(user_id: &str, channel: ContactChannel, phone: Option<String>) -> Self {
+        ContactPreference {
+            user_id: user_id.to_owned(),
+            channel: channel.as_str().to_owned(),
+            phone,
+        }
+    }
+
+    /// Returns the channel the user prefers
+    pub fn channel(&self) -> ContactChannel {
+        self.channel.parse().unwrap_or(ContactChannel::Slack)
+    }
+
+    /// Fetches the preference of a user, returning `None` if they haven't set one
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    pub async fn fetch(db: &mut SqlConn, user_id: &str) -> anyhow::Result<Option<Self>> {
+        let preference = timed!(
+            "sql/contact/fetch_by_user.sql",
+            sqlx::query_file_as!(
+                ContactPreference,
+                "sql/contact/fetch_by_user.sql",
+                user_id
+            )
+            .fetch_optional(&mut *db)
+        )
+        .await?;
+
+        Ok(preference)
+    }
+
+    /// Saves this preference, replacing the user's previous one
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn save(&self, db: &mut SqlConn) -> anyhow::Result<()> {
+        timed!(
+            "sql/contact/save.sql",
+            sqlx::query_file!(
+                "sql/contact/save.sql",
+                self.user_id,
+                self.channel,
+                self.phone
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+//! Slack events that have already been processed
+
+use crate::SqlConn;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+
+#[derive(Clone, Debug)]
+pub struct ProcessedEvent {
+    /// Unique id of the event, provided by Slack
+    pub event_id: String,
+
+    /// When the event was processed
+    pub processed_at: DateTime<Utc>,
+}
+
+impl ProcessedEvent {
+    /// Attempts to fetch a processed event, returning `None` if the event has not been processed
+    /// since `since`
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `event_id` - Unique id of the event
+    /// * `since` - Ignore events processed at or before this time
+    pub async fn fetch(db: &mut SqlConn, event_id: &str, since: DateTime<Utc>) -> Option<Self> {
+        let mut rows =
+            sqlx::query_file_as!(ProcessedEvent, "sql/event/fetch_by_id.sql", event_id, since)
+                .fetch(&mut *db);
+
+        timed!("sql/event/fetch_by_id.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Records that an event has been processed
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `event_id` - Unique id of the event
+    pub async fn record(db: &mut SqlConn, event_id: &str) -> anyhow::Result<()> {
+        timed!(
+            "sql/event/insert.sql",
+            sqlx::query_file!("sql/event/insert.sql", event_id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Forgets events processed at or before a time, so they're no longer deduplicated
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `before` - Forget events processed at or before this time
+    pub async fn purge(db: &mut SqlConn, before: DateTime<Utc>) -> anyhow::Result<()> {
+        timed!(
+            "sql/event/delete_before.sql",
+            sqlx::query_file!("sql/event/delete_before.sql", before).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
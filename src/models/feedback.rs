@@ -0,0 +1,43 @@
+//! Problems reported by users (see `feedback`)
+
+use crate::SqlConn;
+
+/// A problem a user reported
+#[derive(Clone, Debug)]
+pub struct Feedback;
+
+impl Feedback {
+    /// Most characters a report can have
+    pub const MAX_LENGTH: usize = 2000;
+
+    /// Records a report
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `workspace` - Slack ID of the workspace it was sent from
+    /// * `user_id` - Slack ID of the user who sent it
+    /// * `channel_id` - Slack ID of the channel it was sent from
+    /// * `message` - What the user reported
+    pub async fn record(
+        db: &mut SqlConn,
+        workspace: &str,
+        user_id: &str,
+        channel_id: &str,
+        message: &str,
+    ) -> anyhow::Result<()> {
+        timed!(
+            "sql/feedback/insert.sql",
+            sqlx::query_file!(
+                "sql/feedback/insert.sql",
+                workspace,
+                user_id,
+                channel_id,
+                message
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
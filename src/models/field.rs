@@ -0,0 +1,205 @@
+//! Extra fields a team collects from its members alongside their status (e.g., badge number)
+
+use crate::{
+    error::Error,
+    models::{normalize_name, Team},
+    SqlConn,
+};
+use std::collections::{BTreeMap, HashSet};
+
+/// Maximum length of a field label, in characters
+const MAX_LABEL_LENGTH: usize = 32;
+
+/// Maximum number of fields per team
+const MAX_FIELDS: i64 = 10;
+
+/// Maximum length of a field value, in characters
+pub const MAX_VALUE_LENGTH: usize = 100;
+
+/// Parses a user's field values, as stored in the database
+///
+/// Values that can't be parsed are ignored
+///
+/// # Arguments
+/// * `fields` - JSON object of values, keyed by field name
+pub fn parse_values(fields: Option<&str>) -> BTreeMap<String, String> {
+    fields
+        .and_then(|fields| serde_json::from_str(fields).ok())
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Debug)]
+pub struct TeamField {
+    /// Unique field id
+    id: i64,
+
+    /// Name of the field, always normalized
+    pub name: String,
+
+    /// Label of the field, as typed when it was added
+    pub label: String,
+}
+
+#[allow(dead_code)]
+impl TeamField {
+    /// Adds a field to a team
+    ///
+    /// Fails with `Error::Parse` if the label is invalid, or `Error::Limit` if the team
+    /// already has `MAX_FIELDS` fields
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - Team to add the field to
+    /// * `label` - Label of the field (e.g., `Badge number`)
+    pub async fn add(db: &mut SqlConn, team: &Team, label: &str) -> anyhow::Result<Self> {
+        let label = label.trim();
+        if label.is_empty() || label.chars().count() > MAX_LABEL_LENGTH {
+            return Err(Error::Parse(format!(
+                "Field labels may be up to {} characters",
+                MAX_LABEL_LENGTH
+            ))
+            .into());
+        }
+
+        let count = timed!(
+            "sql/field/count_by_team.sql",
+            sqlx::query_file!("sql/field/count_by_team.sql", team.id()).fetch_one(&mut *db)
+        )
+        .await?
+        .count
+        .unwrap_or_default();
+
+        if count >= MAX_FIELDS {
+            return Err(Error::Limit(format!(
+                "Team {} already has the maximum of {} fields",
+                team.name, MAX_FIELDS
+            ))
+            .into());
+        }
+
+        let name = normalize_name(label);
+        timed!(
+            "sql/field/insert.sql",
+            sqlx::query_file!("sql/field/insert.sql", team.id(), name, label).execute(&mut *db)
+        )
+        .await?;
+
+        let field = TeamField::fetch_by_team(&mut *db, team)
+            .await?
+            .into_iter()
+            .find(|field| field.name == name)
+            .ok_or_else(|| anyhow::anyhow!("field {} not saved", name))?;
+
+        Ok(field)
+    }
+
+    /// Fetches a team's fields, in the order they were added
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - The team
+    pub async fn fetch_by_team(db: &mut SqlConn, team: &Team) -> anyhow::Result<Vec<Self>> {
+        let fields = timed!(
+            "sql/field/fetch_by_team.sql",
+            sqlx::query_file_as!(TeamField, "sql/field/fetch_by_team.sql", team.id())
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(fields)
+    }
+
+    /// Fetches the fields of every team a user belongs to, without duplicates
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    pub async fn fetch_by_member(db: &mut SqlConn, user_id: &str) -> anyhow::Result<Vec<Self>> {
+        let mut fields = timed!(
+            "sql/field/fetch_by_member.sql",
+            sqlx::query_file_as!(TeamField, "sql/field/fetch_by_member.sql", user_id)
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        // teams may share a field (e.g., `site`), which is only collected once
+        let mut seen = HashSet::new();
+        fields.retain(|field| seen.insert(field.name.clone()));
+
+        Ok(fields)
+    }
+
+    /// Removes one of a team's fields, returning whether it existed
+    ///
+    /// Values members entered are kept, in case the field is added again
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - The team
+    /// * `label` - Label of the field, in any case
+    pub async fn delete(db: &mut SqlConn, team: &Team, label: &str) -> anyhow::Result<bool> {
+        let deleted = timed!(
+            "sql/field/delete.sql",
+            sqlx::query_file!("sql/field/delete.sql", team.id(), normalize_name(label))
+                .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Deletes all of a team's fields
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_id` - Id of the team
+    pub async fn delete_by_team(db: &mut SqlConn, team_id: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/field/delete_by_team.sql",
+            sqlx::query_file!("sql/field/delete_by_team.sql", team_id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Moves a team's fields to another team, except those it already has a field of the same
+    /// name for (members' values are kept either way, as they're stored by name)
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `from` - Id of the team to move them from
+    /// * `to` - Id of the team to move them to
+    pub async fn move_by_team(db: &mut SqlConn, from: i64, to: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/field/move_by_team.sql",
+            sqlx::query_file!("sql/field/move_by_team.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Formats the values a member entered for some fields (e.g., `Badge number: 1234`),
+    /// returning `None` if they haven't entered any
+    ///
+    /// # Arguments
+    /// * `fields` - Fields to include
+    /// * `values` - The member's values, keyed by field name
+    pub fn describe(fields: &[TeamField], values: &BTreeMap<String, String>) -> Option<String> {
+        let described: Vec<String> = fields
+            .iter()
+            .filter_map(|field| {
+                values
+                    .get(&field.name)
+                    .map(|value| format!("{}: {}", field.label, value))
+            })
+            .collect();
+
+        if described.is_empty() {
+            None
+        } else {
+            Some(described.join(", "))
+        }
+    }
+}
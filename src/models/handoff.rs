@@ -0,0 +1,138 @@
+//! Handoff checklists sent to users going on multi-day leave (see `handoff`)
+
+use crate::SqlConn;
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::TryStreamExt;
+
+#[derive(Clone, Debug)]
+pub struct Handoff {
+    /// Unique handoff id
+    pub id: i64,
+
+    /// Slack ID of the user going on leave
+    pub user_id: String,
+
+    /// First day of the leave
+    pub starts_on: NaiveDate,
+
+    /// Last day of the leave (inclusive)
+    pub ends_on: NaiveDate,
+
+    /// Items of the checklist, one per line, as configured when the leave was recorded
+    items: String,
+
+    /// Indexes of the items checked off, comma-separated
+    checked: String,
+
+    /// When every item was checked off
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl Handoff {
+    /// Saves a checklist for leave a user recorded, returning it
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `starts_on` - First day of the leave
+    /// * `ends_on` - Last day of the leave (inclusive)
+    /// * `items` - Items of the checklist
+    pub async fn start(
+        db: &mut SqlConn,
+        user_id: &str,
+        starts_on: NaiveDate,
+        ends_on: NaiveDate,
+        items: &[String],
+    ) -> anyhow::Result<Self> {
+        let items = items.join("\n");
+        timed!(
+            "sql/handoff/insert.sql",
+            sqlx::query_file!("sql/handoff/insert.sql", user_id, starts_on, ends_on, items)
+                .execute(&mut *db)
+        )
+        .await?;
+
+        let handoff = timed!(
+            "sql/handoff/fetch_latest.sql",
+            sqlx::query_file_as!(Handoff, "sql/handoff/fetch_latest.sql", user_id)
+                .fetch_one(&mut *db)
+        )
+        .await?;
+
+        Ok(handoff)
+    }
+
+    /// Attempts to fetch a handoff, returning `None` if it does not exist
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `id` - Id of the handoff
+    pub async fn fetch(db: &mut SqlConn, id: i64) -> Option<Self> {
+        let mut rows = sqlx::query_file_as!(Handoff, "sql/handoff/fetch.sql", id).fetch(&mut *db);
+
+        timed!("sql/handoff/fetch.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Returns the items of the checklist
+    pub fn items(&self) -> Vec<&str> {
+        self.items.lines().collect()
+    }
+
+    /// Returns whether an item has been checked off
+    ///
+    /// # Arguments
+    /// * `index` - Index of the item
+    pub fn is_checked(&self, index: usize) -> bool {
+        self.checked
+            .split(',')
+            .any(|checked| checked.parse() == Ok(index))
+    }
+
+    /// Returns whether every item has been checked off
+    pub fn is_done(&self) -> bool {
+        (0..self.items().len()).all(|index| self.is_checked(index))
+    }
+
+    /// Records which items are checked off, unless the checklist is already complete
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `checked` - Indexes of the items checked off
+    pub async fn set_checked(&mut self, db: &mut SqlConn, checked: &[usize]) -> anyhow::Result<()> {
+        let checked = checked
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        timed!(
+            "sql/handoff/set_checked.sql",
+            sqlx::query_file!("sql/handoff/set_checked.sql", self.id, checked).execute(&mut *db)
+        )
+        .await?;
+
+        self.checked = checked;
+        Ok(())
+    }
+
+    /// Marks the checklist complete, returning whether it wasn't already
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn complete(&mut self, db: &mut SqlConn) -> anyhow::Result<bool> {
+        let updated = timed!(
+            "sql/handoff/complete.sql",
+            sqlx::query_file!("sql/handoff/complete.sql", self.id).execute(&mut *db)
+        )
+        .await?;
+
+        if updated > 0 {
+            self.completed_at = Some(Utc::now());
+        }
+
+        Ok(updated > 0)
+    }
+}
@@ -0,0 +1,227 @@
+//! History of statuses set by users
+//!
+//! History is kept in `status_history`, or read from the status events when
+//! `STATUS_STORE=events` (see `StatusEvent`).
+
+use crate::{
+    models::{normalize_name, Availability, Location, StatusEvent, StatusStore, User},
+    SqlConn,
+};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+
+#[derive(Clone, Debug)]
+pub struct HistoryEntry {
+    /// Unique history entry id
+    pub id: i64,
+
+    /// The unique identifier provided by Slack
+    pub user_id: String,
+
+    /// The status the user set (a free-text note)
+    pub status: Option<String>,
+
+    /// Where the user said they were working, as stored in the database
+    location: Option<String>,
+
+    /// Whether the user said they could be reached, as stored in the database
+    availability: Option<String>,
+
+    /// When the status was set
+    pub created_at: DateTime<Utc>,
+}
+
+impl HistoryEntry {
+    /// Returns where the user said they were working, if this entry changed it
+    pub fn location(&self) -> Option<Location> {
+        self.location.as_deref().and_then(|l| l.parse().ok())
+    }
+
+    /// Returns whether the user said they could be reached, if this entry changed it
+    pub fn availability(&self) -> Option<Availability> {
+        self.availability.as_deref().and_then(|a| a.parse().ok())
+    }
+
+    /// Records the user's current status in the history table (or the status events)
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user` - User whose status to record
+    pub async fn record(db: &mut SqlConn, user: &User) -> anyhow::Result<()> {
+        if StatusStore::current() == StatusStore::Events {
+            return StatusEvent::append(db, user).await;
+        }
+
+        // SQLx 0.4 doesn't allow refs like 0.3.5
+        let id = user.id.clone();
+        let status = user.status.clone();
+        let location = user.location().map(|l| l.as_str());
+        let availability = user.availability().map(|a| a.as_str());
+
+        timed!(
+            "sql/history/insert.sql",
+            sqlx::query_file!("sql/history/insert.sql", id, status, location, availability)
+                .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns the most recent statuses set by members of a team, newest first
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_name` - Name of the team, in any case
+    /// * `limit` - Maximum number of entries to return
+    pub async fn fetch_by_team(
+        db: &mut SqlConn,
+        team_name: &str,
+        limit: i64,
+    ) -> anyhow::Result<Vec<Self>> {
+        let team_name = normalize_name(team_name);
+        let entries = match StatusStore::current() {
+            StatusStore::History => {
+                timed!(
+                    "sql/history/fetch_by_team.sql",
+                    sqlx::query_file_as!(
+                        HistoryEntry,
+                        "sql/history/fetch_by_team.sql",
+                        team_name,
+                        limit
+                    )
+                    .fetch_all(&mut *db)
+                )
+                .await?
+            }
+            StatusStore::Events => {
+                timed!(
+                    "sql/history/fetch_events_by_team.sql",
+                    sqlx::query_file_as!(
+                        HistoryEntry,
+                        "sql/history/fetch_events_by_team.sql",
+                        team_name,
+                        limit
+                    )
+                    .fetch_all(&mut *db)
+                )
+                .await?
+            }
+        };
+
+        Ok(entries)
+    }
+
+    /// Returns the statuses a user set since a point in time, oldest first, along with the
+    /// last status set before it (so the status at that point in time is known)
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `since` - Earliest point in time to return statuses for
+    pub async fn fetch_by_user_since(
+        db: &mut SqlConn,
+        user_id: &str,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Self>> {
+        // entries are streamed newest first, so stop after the first one before `since`
+        let mut entries = match StatusStore::current() {
+            StatusStore::History => {
+                timed!("sql/history/fetch_by_user.sql", async {
+                    let rows = sqlx::query_file_as!(
+                        HistoryEntry,
+                        "sql/history/fetch_by_user.sql",
+                        user_id
+                    )
+                    .fetch(&mut *db);
+
+                    take_until(rows, since).await
+                })
+                .await?
+            }
+            StatusStore::Events => {
+                timed!("sql/history/fetch_events_by_user.sql", async {
+                    let rows = sqlx::query_file_as!(
+                        HistoryEntry,
+                        "sql/history/fetch_events_by_user.sql",
+                        user_id
+                    )
+                    .fetch(&mut *db);
+
+                    take_until(rows, since).await
+                })
+                .await?
+            }
+        };
+
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Returns every status set over a period, oldest first
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `since` - Start of the period
+    /// * `until` - End of the period (exclusive)
+    pub async fn fetch_between(
+        db: &mut SqlConn,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Self>> {
+        let entries = match StatusStore::current() {
+            StatusStore::History => {
+                timed!(
+                    "sql/history/fetch_between.sql",
+                    sqlx::query_file_as!(
+                        HistoryEntry,
+                        "sql/history/fetch_between.sql",
+                        since,
+                        until
+                    )
+                    .fetch_all(&mut *db)
+                )
+                .await?
+            }
+            StatusStore::Events => {
+                timed!(
+                    "sql/history/fetch_events_between.sql",
+                    sqlx::query_file_as!(
+                        HistoryEntry,
+                        "sql/history/fetch_events_between.sql",
+                        since,
+                        until
+                    )
+                    .fetch_all(&mut *db)
+                )
+                .await?
+            }
+        };
+
+        Ok(entries)
+    }
+}
+
+/// Collects entries streamed newest first, up to and including the first one before `since`
+///
+/// # Arguments
+/// * `rows` - Stream of entries, newest first
+/// * `since` - Earliest point in time to collect entries for
+async fn take_until<S>(
+    mut rows: S,
+    since: DateTime<Utc>,
+) -> Result<Vec<HistoryEntry>, sqlx::Error>
+where
+    S: futures::Stream<Item = Result<HistoryEntry, sqlx::Error>> + Unpin,
+{
+    let mut entries = vec![];
+    while let Some(entry) = rows.try_next().await? {
+        let done = entry.created_at < since;
+        entries.push(entry);
+        if done {
+            break;
+        }
+    }
+
+    Ok(entries)
+}
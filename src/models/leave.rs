@@ -0,0 +1,191 @@
+//! Days users are on leave, entered manually or imported from their calendars, Outlook, or
+//! an HR system
+
+use crate::SqlConn;
+use chrono::NaiveDate;
+
+#[derive(Clone, Debug)]
+pub struct Leave {
+    /// Slack ID of the user on leave
+    pub user_id: String,
+
+    /// First day of leave
+    pub starts_on: NaiveDate,
+
+    /// Last day of leave (inclusive)
+    pub ends_on: NaiveDate,
+
+    /// Where the leave came from (`manual`, `calendar`, `outlook`, or the name of an HR
+    /// system, e.g. `bamboohr`)
+    pub source: String,
+
+    /// UID of the calendar event the leave was imported from
+    pub uid: Option<String>,
+}
+
+#[allow(dead_code)]
+impl Leave {
+    /// Source of leave entered with `/location leave`
+    pub const MANUAL: &'static str = "manual";
+
+    /// Source of leave imported from a calendar
+    pub const CALENDAR: &'static str = "calendar";
+
+    /// Source of leave mirrored from Outlook automatic replies
+    pub const OUTLOOK: &'static str = "outlook";
+
+    /// Records leave for a user, unless it overlaps leave they already have
+    ///
+    /// Returns whether the leave was recorded.  Imported leave is also skipped if the event
+    /// it came from was imported before, so syncing a calendar twice (or entering leave
+    /// manually before it's synced) doesn't create duplicates
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `starts_on` - First day of leave
+    /// * `ends_on` - Last day of leave (inclusive)
+    /// * `source` - Where the leave came from
+    /// * `uid` - UID of the calendar event the leave was imported from, if any
+    pub async fn add(
+        db: &mut SqlConn,
+        user_id: &str,
+        starts_on: NaiveDate,
+        ends_on: NaiveDate,
+        source: &str,
+        uid: Option<&str>,
+    ) -> anyhow::Result<bool> {
+        let overlapping = timed!(
+            "sql/leave/fetch_overlapping.sql",
+            sqlx::query_file_as!(
+                Leave,
+                "sql/leave/fetch_overlapping.sql",
+                user_id,
+                starts_on,
+                ends_on
+            )
+            .fetch_all(&mut *db)
+        )
+        .await?;
+
+        let imported = match uid {
+            Some(uid) => timed!(
+                "sql/leave/fetch_by_uid.sql",
+                sqlx::query_file_as!(Leave, "sql/leave/fetch_by_uid.sql", user_id, uid)
+                    .fetch_optional(&mut *db)
+            )
+            .await?
+            .is_some(),
+            None => false,
+        };
+
+        if !overlapping.is_empty() || imported {
+            return Ok(false);
+        }
+
+        timed!(
+            "sql/leave/insert.sql",
+            sqlx::query_file!(
+                "sql/leave/insert.sql",
+                user_id,
+                starts_on,
+                ends_on,
+                source,
+                uid
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Fetches a user's leave that hasn't ended yet, soonest first
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `today` - Today's date
+    pub async fn fetch_upcoming(
+        db: &mut SqlConn,
+        user_id: &str,
+        today: NaiveDate,
+    ) -> anyhow::Result<Vec<Self>> {
+        let leave = timed!(
+            "sql/leave/fetch_upcoming.sql",
+            sqlx::query_file_as!(Leave, "sql/leave/fetch_upcoming.sql", user_id, today)
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(leave)
+    }
+
+    /// Fetches everyone's leave that covers a day
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `day` - The day
+    pub async fn fetch_on(db: &mut SqlConn, day: NaiveDate) -> anyhow::Result<Vec<Self>> {
+        let leave = timed!(
+            "sql/leave/fetch_on.sql",
+            sqlx::query_file_as!(Leave, "sql/leave/fetch_on.sql", day).fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(leave)
+    }
+
+    /// Attempts to fetch a user's leave covering a day, returning `None` if they aren't on
+    /// leave
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `day` - The day
+    pub async fn fetch_covering(
+        db: &mut SqlConn,
+        user_id: &str,
+        day: NaiveDate,
+    ) -> anyhow::Result<Option<Self>> {
+        let leave = timed!(
+            "sql/leave/fetch_overlapping.sql",
+            sqlx::query_file_as!(Leave, "sql/leave/fetch_overlapping.sql", user_id, day, day)
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(leave.into_iter().next())
+    }
+
+    /// Cancels a user's leave covering a day
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `day` - A day of the leave to cancel
+    pub async fn cancel(db: &mut SqlConn, user_id: &str, day: NaiveDate) -> anyhow::Result<()> {
+        timed!(
+            "sql/leave/delete_on.sql",
+            sqlx::query_file!("sql/leave/delete_on.sql", user_id, day).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Describes the leave on a single line (e.g., `Mon Oct 19 – Fri Oct 23 (calendar)`)
+    pub fn describe(&self) -> String {
+        let starts = self.starts_on.format("%a %b %-d");
+        if self.starts_on == self.ends_on {
+            format!("{} ({})", starts, self.source)
+        } else {
+            format!(
+                "{} – {} ({})",
+                starts,
+                self.ends_on.format("%a %b %-d"),
+                self.source
+            )
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Safety check-ins ("musters") of a team, and each member's response
+
+use crate::{
+    models::{Team, User},
+    SqlConn,
+};
+use futures::TryStreamExt;
+
+#[derive(Clone, Debug)]
+pub struct Muster {
+    /// Unique muster id
+    pub id: i64,
+
+    /// Name of the team being mustered
+    pub team: String,
+
+    /// Channel the summary is posted in
+    pub channel: String,
+
+    /// Timestamp of the summary message, once posted
+    pub message_ts: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MusterResponse {
+    /// Slack ID of the member asked
+    pub user_id: String,
+
+    /// Whether the member is safe, or `None` if they haven't responded yet
+    pub safe: Option<bool>,
+}
+
+#[allow(dead_code)]
+impl Muster {
+    /// Starts a muster of a team, saving it and the members asked in the database
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - Team being mustered
+    /// * `channel` - Channel the summary will be posted in
+    /// * `started_by` - Slack ID of the user starting the muster
+    /// * `members` - Members who will be asked if they're safe
+    pub async fn start(
+        db: &mut SqlConn,
+        team: &Team,
+        channel: &str,
+        started_by: &str,
+        members: &[User],
+    ) -> anyhow::Result<Self> {
+        timed!(
+            "sql/muster/insert.sql",
+            sqlx::query_file!("sql/muster/insert.sql", team.id(), channel, started_by)
+                .execute(&mut *db)
+        )
+        .await?;
+
+        let muster = timed!(
+            "sql/muster/fetch_latest.sql",
+            sqlx::query_file_as!(Muster, "sql/muster/fetch_latest.sql", team.id(), started_by)
+                .fetch_one(&mut *db)
+        )
+        .await?;
+
+        for member in members {
+            timed!(
+                "sql/muster/add_member.sql",
+                sqlx::query_file!("sql/muster/add_member.sql", muster.id, member.id)
+                    .execute(&mut *db)
+            )
+            .await?;
+        }
+
+        Ok(muster)
+    }
+
+    /// Attempts to fetch a muster, returning `None` if it does not exist
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `id` - Id of the muster
+    pub async fn fetch(db: &mut SqlConn, id: i64) -> Option<Self> {
+        let mut rows = sqlx::query_file_as!(Muster, "sql/muster/fetch.sql", id).fetch(&mut *db);
+
+        timed!("sql/muster/fetch.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Records the timestamp of the summary message
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `ts` - Timestamp of the message
+    pub async fn set_message(&mut self, db: &mut SqlConn, ts: &str) -> anyhow::Result<()> {
+        timed!(
+            "sql/muster/set_message.sql",
+            sqlx::query_file!("sql/muster/set_message.sql", self.id, ts).execute(&mut *db)
+        )
+        .await?;
+
+        self.message_ts = Some(ts.to_owned());
+        Ok(())
+    }
+
+    /// Records a member's response, returning whether they were asked
+    ///
+    /// Members may change their response until the muster is over
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the member
+    /// * `safe` - Whether the member is safe
+    pub async fn respond(
+        &self,
+        db: &mut SqlConn,
+        user_id: &str,
+        safe: bool,
+    ) -> anyhow::Result<bool> {
+        let updated = timed!(
+            "sql/muster/respond.sql",
+            sqlx::query_file!("sql/muster/respond.sql", self.id, user_id, safe).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(updated > 0)
+    }
+
+    /// Fetches the response of every member asked, ordered by user id
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn responses(&self, db: &mut SqlConn) -> anyhow::Result<Vec<MusterResponse>> {
+        let responses = timed!(
+            "sql/muster/fetch_responses.sql",
+            sqlx::query_file_as!(MusterResponse, "sql/muster/fetch_responses.sql", self.id)
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(responses)
+    }
+
+    /// Deletes every muster of a team, and their responses
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_id` - Id of the team
+    pub async fn delete_by_team(db: &mut SqlConn, team_id: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/muster/delete_responses_by_team.sql",
+            sqlx::query_file!("sql/muster/delete_responses_by_team.sql", team_id).execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/muster/delete_by_team.sql",
+            sqlx::query_file!("sql/muster/delete_by_team.sql", team_id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Moves every muster of a team, and their responses, to another team
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `from` - Id of the team to move them from
+    /// * `to` - Id of the team to move them to
+    pub async fn move_by_team(db: &mut SqlConn, from: i64, to: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/muster/move_by_team.sql",
+            sqlx::query_file!("sql/muster/move_by_team.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+//! Side effects waiting to be delivered (see `outbox`)
+
+use crate::SqlConn;
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug)]
+pub struct OutboxEntry {
+    /// Unique entry id
+    pub id: i64,
+
+    /// The side effect, serialized as JSON
+    pub effect: String,
+
+    /// Number of failed attempts to deliver it
+    pub attempts: i64,
+}
+
+impl OutboxEntry {
+    /// Adds a side effect to the outbox, to be delivered once the current transaction commits
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `effect` - The side effect, serialized as JSON
+    pub async fn insert(db: &mut SqlConn, effect: &str) -> anyhow::Result<()> {
+        timed!(
+            "sql/outbox/insert.sql",
+            sqlx::query_file!("sql/outbox/insert.sql", effect).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetches the entries due for delivery, oldest first
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `now` - Current time
+    /// * `limit` - Maximum number of entries to fetch
+    pub async fn fetch_due(
+        db: &mut SqlConn,
+        now: DateTime<Utc>,
+        limit: i64,
+    ) -> anyhow::Result<Vec<Self>> {
+        let entries = timed!(
+            "sql/outbox/fetch_due.sql",
+            sqlx::query_file_as!(OutboxEntry, "sql/outbox/fetch_due.sql", now, limit)
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Claims this entry for delivery until `until`, returning false if another worker
+    /// claimed it first
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `now` - Current time
+    /// * `until` - When the entry is due again if it's neither delivered nor retried
+    pub async fn claim(
+        &self,
+        db: &mut SqlConn,
+        now: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
+        let claimed = timed!(
+            "sql/outbox/claim.sql",
+            sqlx::query_file!("sql/outbox/claim.sql", self.id, until, now).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(claimed == 1)
+    }
+
+    /// Records a failed attempt to deliver this entry, and when to try again
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `at` - When to try again
+    pub async fn retry(&self, db: &mut SqlConn, at: DateTime<Utc>) -> anyhow::Result<()> {
+        timed!(
+            "sql/outbox/retry.sql",
+            sqlx::query_file!("sql/outbox/retry.sql", self.id, at).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes this entry, once it's delivered (or given up on)
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn delete(self, db: &mut SqlConn) -> anyhow::Result<()> {
+        timed!(
+            "sql/outbox/delete.sql",
+            sqlx::query_file!("sql/outbox/delete.sql", self.id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
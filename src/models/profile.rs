@@ -0,0 +1,96 @@
+//! Cached Slack profiles of known users
+
+use crate::SqlConn;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+
+#[derive(Clone, Debug)]
+pub struct Profile {
+    /// Slack ID of the user this profile belongs to
+    pub user_id: String,
+
+    /// The name the user has chosen to display, falling back to their real name
+    pub display_name: Option<String>,
+
+    /// The user's email address, if the bot may read it
+    pub email: Option<String>,
+
+    /// The user's timezone (e.g. `America/New_York`)
+    pub tz: Option<String>,
+
+    /// If the user has been deactivated
+    pub deleted: bool,
+
+    /// When this profile was last refreshed from Slack
+    pub updated_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+impl Profile {
+    /// Attempts to fetch the cached profile of a user, returning `None` if there isn't one
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    pub async fn fetch(db: &mut SqlConn, user_id: &str) -> Option<Self> {
+        let mut rows =
+            sqlx::query_file_as!(Profile, "sql/profile/fetch_by_id.sql", user_id).fetch(&mut *db);
+
+        timed!("sql/profile/fetch_by_id.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Attempts to fetch the cached profile with an email address (ignoring case), returning
+    /// `None` if there isn't one
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `email` - Email address of the user
+    pub async fn fetch_by_email(db: &mut SqlConn, email: &str) -> Option<Self> {
+        let mut rows =
+            sqlx::query_file_as!(Profile, "sql/profile/fetch_by_email.sql", email).fetch(&mut *db);
+
+        timed!("sql/profile/fetch_by_email.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Fetches all cached profiles
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn fetch_all(db: &mut SqlConn) -> anyhow::Result<Vec<Self>> {
+        let profiles = timed!(
+            "sql/profile/fetch_all.sql",
+            sqlx::query_file_as!(Profile, "sql/profile/fetch_all.sql").fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(profiles)
+    }
+
+    /// Saves this profile, replacing the previously cached profile of the user
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn save(&self, db: &mut SqlConn) -> anyhow::Result<()> {
+        timed!(
+            "sql/profile/save.sql",
+            sqlx::query_file!(
+                "sql/profile/save.sql",
+                self.user_id,
+                self.display_name,
+                self.email,
+                self.tz,
+                self.deleted
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+//! Rate limit buckets saved between restarts (see `ratelimit`)
+
+use crate::SqlConn;
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug)]
+pub struct RateBucket {
+    /// Key the bucket belongs to (e.g., `addr:<ip>` or `token:<sha256>`)
+    pub key: String,
+
+    /// Requests that could be made when the bucket was last updated
+    pub tokens: f64,
+
+    /// When the bucket was last updated
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RateBucket {
+    /// Fetches every saved bucket
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn fetch_all(db: &mut SqlConn) -> anyhow::Result<Vec<Self>> {
+        let buckets = timed!(
+            "sql/rate_limit/fetch_all.sql",
+            sqlx::query_file_as!(RateBucket, "sql/rate_limit/fetch_all.sql").fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(buckets)
+    }
+
+    /// Replaces every saved bucket with `buckets`
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `buckets` - Buckets to save
+    pub async fn replace_all(db: &mut SqlConn, buckets: &[RateBucket]) -> anyhow::Result<()> {
+        transaction!(db, async {
+            timed!(
+                "sql/rate_limit/delete_all.sql",
+                sqlx::query_file!("sql/rate_limit/delete_all.sql").execute(&mut *db)
+            )
+            .await?;
+
+            for bucket in buckets {
+                timed!(
+                    "sql/rate_limit/insert.sql",
+                    sqlx::query_file!(
+                        "sql/rate_limit/insert.sql",
+                        bucket.key,
+                        bucket.tokens,
+                        bucket.updated_at
+                    )
+                    .execute(&mut *db)
+                )
+                .await?;
+            }
+
+            Ok::<_, anyhow::Error>(())
+        })
+    }
+}
@@ -0,0 +1,109 @@
+//! Teams whose members' status history is kept longer than `HISTORY_RETENTION_MONTHS`
+
+use crate::SqlConn;
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug)]
+pub struct RetentionOverride {
+    /// Id of the team whose members' history is kept longer
+    pub team_id: i64,
+
+    /// Number of months of history to keep before the current month (0 keeps everything)
+    pub months: i64,
+
+    /// Why the history is kept (e.g., a legal hold)
+    pub reason: Option<String>,
+
+    /// When the override was last set
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RetentionOverride {
+    /// Fetches the retention override of a team, if it has one
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_id` - Id of the team
+    pub async fn fetch_by_team(db: &mut SqlConn, team_id: i64) -> anyhow::Result<Option<Self>> {
+        let retention = timed!(
+            "sql/retention/fetch_by_team.sql",
+            sqlx::query_file_as!(
+                RetentionOverride,
+                "sql/retention/fetch_by_team.sql",
+                team_id
+            )
+            .fetch_optional(&mut *db)
+        )
+        .await?;
+
+        Ok(retention)
+    }
+
+    /// Sets how long a team's members' history is kept, replacing any earlier override
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_id` - Id of the team
+    /// * `months` - Number of months of history to keep (0 keeps everything)
+    /// * `reason` - Why the history is kept
+    pub async fn save(
+        db: &mut SqlConn,
+        team_id: i64,
+        months: i64,
+        reason: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let retention = RetentionOverride {
+            team_id,
+            months,
+            reason,
+            updated_at: Utc::now(),
+        };
+
+        timed!(
+            "sql/retention/save.sql",
+            sqlx::query_file!(
+                "sql/retention/save.sql",
+                retention.team_id,
+                retention.months,
+                retention.reason,
+                retention.updated_at
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(retention)
+    }
+
+    /// Removes a team's retention override, so its members' history is kept for the default
+    /// retention period
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team_id` - Id of the team
+    pub async fn delete_by_team(db: &mut SqlConn, team_id: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/retention/delete_by_team.sql",
+            sqlx::query_file!("sql/retention/delete_by_team.sql", team_id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Moves a team's retention override to another team, unless it has its own
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `from` - Id of the team to move them from
+    /// * `to` - Id of the team to move them to
+    pub async fn move_by_team(db: &mut SqlConn, from: i64, to: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/retention/move_by_team.sql",
+            sqlx::query_file!("sql/retention/move_by_team.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+//! Bookkeeping for messages scheduled with Slack
+
+use crate::SqlConn;
+use futures::TryStreamExt;
+
+#[derive(Clone, Debug)]
+pub struct ScheduledMessage {
+    /// Identifies what the message is for (e.g. `digest:<team>`).  Only one message
+    /// is scheduled per key
+    pub key: String,
+
+    /// Channel the message will be posted to
+    pub channel: String,
+
+    /// Id of the scheduled message, as returned by Slack
+    pub message_id: String,
+
+    /// Unix timestamp the message will be posted at
+    pub post_at: i64,
+}
+
+#[allow(dead_code)]
+impl ScheduledMessage {
+    /// Attempts to fetch the message scheduled for a key, returning `None` if there isn't one
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `key` - Key the message was scheduled with
+    pub async fn fetch(db: &mut SqlConn, key: &str) -> Option<Self> {
+        let mut rows =
+            sqlx::query_file_as!(ScheduledMessage, "sql/scheduled/fetch_by_key.sql", key)
+                .fetch(&mut *db);
+
+        timed!("sql/scheduled/fetch_by_key.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Saves this message, replacing any message previously scheduled with the same key
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn save(&self, db: &mut SqlConn) -> anyhow::Result<()> {
+        timed!(
+            "sql/scheduled/save.sql",
+            sqlx::query_file!(
+                "sql/scheduled/save.sql",
+                self.key,
+                self.channel,
+                self.message_id,
+                self.post_at
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes this message's bookkeeping record
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn delete(self, db: &mut SqlConn) -> anyhow::Result<()> {
+        timed!(
+            "sql/scheduled/delete.sql",
+            sqlx::query_file!("sql/scheduled/delete.sql", self.key).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
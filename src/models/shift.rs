@@ -0,0 +1,217 @@
+//! Recurring shifts of a team, and the members assigned to them
+
+use crate::{
+    error::Error,
+    models::{normalize_name, Team, User},
+    SqlConn,
+};
+use chrono::{NaiveTime, Weekday};
+use futures::TryStreamExt;
+
+/// Maximum length of a shift name, in characters
+const MAX_NAME_LENGTH: usize = 32;
+
+/// Checks that hours are typed as a range of 24-hour times (e.g., `09:00-17:00`)
+///
+/// # Arguments
+/// * `hours` - Hours typed by the user
+fn validate_hours(hours: &str) -> Result<(), Error> {
+    let mut times = hours.splitn(2, '-');
+    let valid = match (times.next(), times.next()) {
+        (Some(start), Some(end)) => {
+            NaiveTime::parse_from_str(start, "%H:%M").is_ok()
+                && NaiveTime::parse_from_str(end, "%H:%M").is_ok()
+        }
+        _ => false,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Parse(format!(
+            "`{}` are not valid hours. Please specify a range of times (e.g., `09:00-17:00`)",
+            hours
+        )))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Shift {
+    /// Unique shift id
+    id: i64,
+
+    /// Name of the shift (e.g., `early`), always normalized
+    pub name: String,
+
+    /// Weekdays the shift runs on (bit 0 is Monday)
+    pub days: i64,
+
+    /// Hours the shift covers (e.g., `09:00-17:00`), if set
+    pub hours: Option<String>,
+}
+
+#[allow(dead_code)]
+impl Shift {
+    /// Creates a new shift for a team and saves it in the database
+    ///
+    /// Fails with `Error::Parse` if the name or hours are invalid
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - Team the shift belongs to
+    /// * `name` - Name of the shift
+    /// * `days` - Weekdays the shift runs on (bit 0 is Monday)
+    /// * `hours` - Hours the shift covers (e.g., `09:00-17:00`)
+    pub async fn new(
+        db: &mut SqlConn,
+        team: &Team,
+        name: &str,
+        days: i64,
+        hours: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let name = normalize_name(name);
+
+        let valid = !name.is_empty()
+            && name.chars().count() <= MAX_NAME_LENGTH
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+
+        if !valid {
+            return Err(Error::Parse(format!(
+                "*{}* is not a valid shift name. Names may be up to {} letters, numbers, `-`, and `_`",
+                name, MAX_NAME_LENGTH
+            ))
+            .into());
+        }
+
+        if let Some(hours) = hours {
+            validate_hours(hours)?;
+        }
+
+        timed!(
+            "sql/shift/insert.sql",
+            sqlx::query_file!("sql/shift/insert.sql", team.id(), name, days, hours)
+                .execute(&mut *db)
+        )
+        .await?;
+
+        let shift = timed!(
+            "sql/shift/fetch_by_name.sql",
+            sqlx::query_file_as!(Shift, "sql/shift/fetch_by_name.sql", team.id(), name)
+                .fetch_one(&mut *db)
+        )
+        .await?;
+
+        Ok(shift)
+    }
+
+    /// Attempts to fetch one of a team's shifts, returning `None` if it does not exist
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - Team the shift belongs to
+    /// * `name` - Name of the shift, in any case
+    pub async fn fetch(db: &mut SqlConn, team: &Team, name: &str) -> Option<Self> {
+        let name = normalize_name(name);
+        let mut rows = sqlx::query_file_as!(Shift, "sql/shift/fetch_by_name.sql", team.id(), name)
+            .fetch(&mut *db);
+
+        timed!("sql/shift/fetch_by_name.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Fetches all of a team's shifts, ordered by name
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `team` - Team the shifts belong to
+    pub async fn fetch_by_team(db: &mut SqlConn, team: &Team) -> anyhow::Result<Vec<Self>> {
+        let shifts = timed!(
+            "sql/shift/fetch_by_team.sql",
+            sqlx::query_file_as!(Shift, "sql/shift/fetch_by_team.sql", team.id())
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(shifts)
+    }
+
+    /// Returns whether the shift runs on a weekday
+    ///
+    /// # Arguments
+    /// * `weekday` - Day of the week
+    pub fn runs_on(&self, weekday: Weekday) -> bool {
+        self.days & (1 << weekday.num_days_from_monday()) != 0
+    }
+
+    /// Returns the members assigned to the shift
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn members(&self, db: &mut SqlConn) -> anyhow::Result<Vec<User>> {
+        let users = timed!(
+            "sql/shift/fetch_members.sql",
+            sqlx::query_file_as!(User, "sql/shift/fetch_members.sql", self.id).fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(users)
+    }
+
+    /// Assigns a user to the shift
+    ///
+    /// If the user is already assigned, does nothing
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user` - User to assign
+    pub async fn assign(&self, db: &mut SqlConn, user: &User) -> anyhow::Result<()> {
+        timed!(
+            "sql/shift/assign.sql",
+            sqlx::query_file!("sql/shift/assign.sql", self.id, user.id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes a user from the shift
+    ///
+    /// If the user isn't assigned, does nothing
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user` - User to remove
+    pub async fn unassign(&self, db: &mut SqlConn, user: &User) -> anyhow::Result<()> {
+        timed!(
+            "sql/shift/unassign.sql",
+            sqlx::query_file!("sql/shift/unassign.sql", self.id, user.id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the shift and its assignments
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn delete(self, db: &mut SqlConn) -> anyhow::Result<()> {
+        timed!(
+            "sql/shift/delete_members.sql",
+            sqlx::query_file!("sql/shift/delete_members.sql", self.id).execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/shift/delete.sql",
+            sqlx::query_file!("sql/shift/delete.sql", self.id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
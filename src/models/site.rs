@@ -0,0 +1,252 @@
+//! Offices and other sites users can work at
+
+use crate::{
+    error::Error,
+    models::{normalize_name, User},
+    SqlConn,
+};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use futures::TryStreamExt;
+
+/// Maximum length of a site name, in characters
+const MAX_NAME_LENGTH: usize = 32;
+
+#[derive(Clone, Debug)]
+pub struct Site {
+    /// Unique site id
+    id: i64,
+
+    /// Name of the site (e.g., `nyc`), always normalized
+    pub name: String,
+
+    /// The site's timezone (e.g., `America/New_York`)
+    pub tz: Option<String>,
+
+    /// Where the site is
+    pub address: Option<String>,
+
+    /// Number of desks that can be booked each day (unlimited if `None`)
+    pub capacity: Option<i64>,
+}
+
+#[allow(dead_code)]
+impl Site {
+    /// Creates a new site and saves it in the database
+    ///
+    /// Fails with `Error::Parse` if the name is invalid.  Names are normalized like team
+    /// names, so `NYC` and `nyc` are the same site
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `name` - Name of the site
+    pub async fn new(db: &mut SqlConn, name: &str) -> anyhow::Result<Self> {
+        let name = normalize_name(name);
+
+        let valid = !name.is_empty()
+            && name.chars().count() <= MAX_NAME_LENGTH
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+
+        if !valid {
+            return Err(Error::Parse(format!(
+                "*{}* is not a valid site name. Names may be up to {} letters, numbers, `-`, and `_`",
+                name, MAX_NAME_LENGTH
+            ))
+            .into());
+        }
+
+        timed!(
+            "sql/site/insert.sql",
+            sqlx::query_file!("sql/site/insert.sql", name).execute(&mut *db)
+        )
+        .await?;
+
+        let site = timed!(
+            "sql/site/fetch_by_name.sql",
+            sqlx::query_file_as!(Site, "sql/site/fetch_by_name.sql", name).fetch_one(&mut *db)
+        )
+        .await?;
+
+        Ok(site)
+    }
+
+    /// Attempts to fetch a site, returning `None` if it does not exist
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `name` - Name of the site, in any case
+    pub async fn fetch(db: &mut SqlConn, name: &str) -> Option<Self> {
+        let name = normalize_name(name);
+        let mut rows =
+            sqlx::query_file_as!(Site, "sql/site/fetch_by_name.sql", name).fetch(&mut *db);
+
+        timed!("sql/site/fetch_by_name.sql", rows.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Fetches all sites, ordered by name
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn fetch_all(db: &mut SqlConn) -> anyhow::Result<Vec<Self>> {
+        let sites = timed!(
+            "sql/site/fetch_all.sql",
+            sqlx::query_file_as!(Site, "sql/site/fetch_all.sql").fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(sites)
+    }
+
+    /// Returns the site's timezone, if it has a valid one
+    pub fn timezone(&self) -> Option<Tz> {
+        self.tz.as_deref().and_then(|tz| tz.parse().ok())
+    }
+
+    /// Describes the site on a single line (e.g., `nyc — 1 Main St (America/New_York)`)
+    pub fn describe(&self) -> String {
+        let mut text = format!("*{}*", self.name);
+        if let Some(address) = &self.address {
+            text.push_str(&format!(" — {}", address));
+        }
+        if let Some(tz) = &self.tz {
+            text.push_str(&format!(" ({})", tz));
+        }
+        if let Some(capacity) = self.capacity {
+            text.push_str(&format!(", {} desks", capacity));
+        }
+        text
+    }
+
+    /// Returns the number of desks booked at the site on a day
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `day` - Day to count bookings on
+    pub async fn headcount(&self, db: &mut SqlConn, day: NaiveDate) -> anyhow::Result<i64> {
+        let count = timed!(
+            "sql/booking/count.sql",
+            sqlx::query_file!("sql/booking/count.sql", self.id, day).fetch_one(&mut *db)
+        )
+        .await?
+        .count
+        .unwrap_or_default();
+
+        Ok(count)
+    }
+
+    /// Books a desk at the site for a user on a day
+    ///
+    /// If the user has already booked a desk that day, does nothing.  Fails with
+    /// `Error::Limit` if every desk is already booked
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user` - User booking the desk
+    /// * `day` - Day to book the desk for
+    pub async fn book(&self, db: &mut SqlConn, user: &User, day: NaiveDate) -> anyhow::Result<()> {
+        let booked = timed!(
+            "sql/booking/fetch.sql",
+            sqlx::query_file!("sql/booking/fetch.sql", self.id, user.id, day)
+                .fetch_optional(&mut *db)
+        )
+        .await?;
+
+        if booked.is_some() {
+            return Ok(());
+        }
+
+        if let Some(capacity) = self.capacity {
+            if self.headcount(&mut *db, day).await? >= capacity {
+                return Err(Error::Limit(format!(
+                    "All {} desks at *{}* are booked on {}",
+                    capacity,
+                    self.name,
+                    day.format("%a %b %-d")
+                ))
+                .into());
+            }
+        }
+
+        timed!(
+            "sql/booking/insert.sql",
+            sqlx::query_file!("sql/booking/insert.sql", self.id, user.id, day).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Cancels a user's booking at the site on a day, if they have one
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user` - User who booked the desk
+    /// * `day` - Day the desk was booked for
+    pub async fn cancel_booking(
+        &self,
+        db: &mut SqlConn,
+        user: &User,
+        day: NaiveDate,
+    ) -> anyhow::Result<()> {
+        timed!(
+            "sql/booking/delete.sql",
+            sqlx::query_file!("sql/booking/delete.sql", self.id, user.id, day).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Saves the site's timezone, address, and capacity
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn save(&self, db: &mut SqlConn) -> anyhow::Result<()> {
+        timed!(
+            "sql/site/save.sql",
+            sqlx::query_file!(
+                "sql/site/save.sql",
+                self.tz,
+                self.address,
+                self.capacity,
+                self.id
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the site and its bookings, clearing it from the status of everyone working
+    /// there
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn delete(self, db: &mut SqlConn) -> anyhow::Result<()> {
+        timed!(
+            "sql/booking/delete_by_site.sql",
+            sqlx::query_file!("sql/booking/delete_by_site.sql", self.id).execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/user/clear_site.sql",
+            sqlx::query_file!("sql/user/clear_site.sql", self.name).execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/site/delete.sql",
+            sqlx::query_file!("sql/site/delete.sql", self.id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+}
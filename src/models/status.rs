@@ -0,0 +1,170 @@
+//! Dimensions of a user's status, which are set independently of each other
+//!
+//! Alongside the free-text note (the `status` column), users have a location and an
+//! availability.
+
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+
+/// Where a user is working
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Location {
+    /// Working from the office
+    Office,
+
+    /// Working remotely (telework)
+    Remote,
+
+    /// Working at a customer or field site
+    Site,
+}
+
+impl Location {
+    /// Returns the name of this location, as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Location::Office => "office",
+            Location::Remote => "remote",
+            Location::Site => "site",
+        }
+    }
+
+    /// Returns the emoji shown for this location in team views
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Location::Office => ":office:",
+            Location::Remote => ":house_with_garden:",
+            Location::Site => ":building_construction:",
+        }
+    }
+}
+
+impl std::str::FromStr for Location {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "office" => Ok(Location::Office),
+            "remote" | "home" | "telework" => Ok(Location::Remote),
+            "site" => Ok(Location::Site),
+            _ => Err(Error::Parse(format!(
+                "*{}* is not a valid location. Please specify `office`, `remote`, or `site`",
+                s
+            ))),
+        }
+    }
+}
+
+/// Whether a user can be reached
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Availability {
+    /// Working and available
+    Available,
+
+    /// Working, but shouldn't be interrupted
+    Busy,
+
+    /// Out of office
+    Ooo,
+}
+
+impl Availability {
+    /// Returns the name of this availability, as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Availability::Available => "available",
+            Availability::Busy => "busy",
+            Availability::Ooo => "ooo",
+        }
+    }
+
+    /// Returns the emoji shown for this availability in team views
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Availability::Available => ":white_check_mark:",
+            Availability::Busy => ":no_entry:",
+            Availability::Ooo => ":palm_tree:",
+        }
+    }
+}
+
+impl std::str::FromStr for Availability {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "available" => Ok(Availability::Available),
+            "busy" => Ok(Availability::Busy),
+            "ooo" | "out" => Ok(Availability::Ooo),
+            _ => Err(Error::Parse(format!(
+                "*{}* is not a valid availability. Please specify `available`, `busy`, or `ooo`",
+                s
+            ))),
+        }
+    }
+}
+
+/// Renders the dimensions of a status on a single line (e.g., `:office: nyc :no_entry: In meetings`),
+/// returning `None` if none of them are set
+///
+/// # Arguments
+/// * `location` - Where the user is working
+/// * `site` - Which site the user is working at, if any
+/// * `availability` - Whether the user can be reached
+/// * `note` - The user's free-text note
+pub fn compact_status(
+    location: Option<Location>,
+    site: Option<&str>,
+    availability: Option<Availability>,
+    note: Option<&str>,
+) -> Option<String> {
+    let parts: Vec<&str> = location
+        .map(|location| location.emoji())
+        .into_iter()
+        .chain(location.and(site))
+        .chain(availability.map(|availability| availability.emoji()))
+        .chain(note)
+        .collect();
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+/// Renders the dimensions of a status in words (e.g., `office (nyc), busy: In meetings`),
+/// returning `None` if none of them are set
+///
+/// # Arguments
+/// * `location` - Where the user is working
+/// * `site` - Which site the user is working at, if any
+/// * `availability` - Whether the user can be reached
+/// * `note` - The user's free-text note
+pub fn plain_status(
+    location: Option<Location>,
+    site: Option<&str>,
+    availability: Option<Availability>,
+    note: Option<&str>,
+) -> Option<String> {
+    let mut parts = vec![];
+    if let Some(location) = location {
+        match site {
+            Some(site) => parts.push(format!("{} ({})", location.as_str(), site)),
+            None => parts.push(location.as_str().to_owned()),
+        }
+    }
+    if let Some(availability) = availability {
+        parts.push(availability.as_str().to_owned());
+    }
+
+    let dimensions = parts.join(", ");
+    match (dimensions.is_empty(), note) {
+        (true, None) => None,
+        (true, Some(note)) => Some(note.to_owned()),
+        (false, None) => Some(dimensions),
+        (false, Some(note)) => Some(format!("{}: {}", dimensions, note)),
+    }
+}
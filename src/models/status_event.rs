@@ -0,0 +1,171 @@
+//! Append-only log of status changes
+//!
+//! With `STATUS_STORE=events`, every status change is appended to `status_events` (instead of
+//! `status_history`), and each user's row in `users` is a projection of their events: what
+//! folding their events in order produces.  The projection can be rebuilt from the events at
+//! any time, and folding only the events up to a point in time gives a user's status as of
+//! then.  `status_history` is used otherwise (the default, `STATUS_STORE=history`).
+
+use crate::{models::User, SqlConn};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+
+/// Where status changes are recorded, read once from `STATUS_STORE`
+static STORE: Lazy<StatusStore> = Lazy::new(|| {
+    let store = dotenv::var("STATUS_STORE").unwrap_or_default();
+    match store.as_str() {
+        "" | "history" => StatusStore::History,
+        "events" => StatusStore::Events,
+        _ => {
+            tracing::warn!("unknown STATUS_STORE `{}`, using history", store);
+            StatusStore::History
+        }
+    }
+});
+
+/// Where status changes are recorded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatusStore {
+    /// Statuses live in `users`, and changes are copied to `status_history`
+    History,
+
+    /// Changes are appended to `status_events`, and `users` is a projection of them
+    Events,
+}
+
+impl StatusStore {
+    /// Returns where status changes are recorded
+    pub fn current() -> Self {
+        *STORE
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StatusEvent {
+    /// Unique event id
+    pub id: i64,
+
+    /// The unique identifier provided by Slack
+    pub user_id: String,
+
+    /// The status the user set, if this event changed it
+    pub status: Option<String>,
+
+    /// Where the user said they were working, if this event changed it
+    pub location: Option<String>,
+
+    /// Which site the user said they were working at, along with their location
+    pub site: Option<String>,
+
+    /// Whether the user said they could be reached, if this event changed it
+    pub availability: Option<String>,
+
+    /// When the status was set
+    pub created_at: DateTime<Utc>,
+}
+
+/// A user with at least one event
+struct EventUser {
+    /// The unique identifier provided by Slack
+    user_id: String,
+}
+
+impl StatusEvent {
+    /// Appends the changed parts of a user's status to the log
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user` - User whose status changed, with only the changed parts of it set
+    pub async fn append(db: &mut SqlConn, user: &User) -> anyhow::Result<()> {
+        // SQLx 0.4 doesn't allow refs like 0.3.5
+        let id = user.id.clone();
+        let status = user.status.clone();
+        let location = user.location().map(|l| l.as_str());
+        let site = user.site().map(str::to_owned);
+        let availability = user.availability().map(|a| a.as_str());
+
+        timed!(
+            "sql/status_event/insert.sql",
+            sqlx::query_file!(
+                "sql/status_event/insert.sql",
+                id,
+                status,
+                location,
+                site,
+                availability
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns a user's events up to a point in time, oldest first
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `until` - Latest point in time to return events for
+    pub async fn fetch_by_user_until(
+        db: &mut SqlConn,
+        user_id: &str,
+        until: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Self>> {
+        let events = timed!(
+            "sql/status_event/fetch_by_user_until.sql",
+            sqlx::query_file_as!(
+                StatusEvent,
+                "sql/status_event/fetch_by_user_until.sql",
+                user_id,
+                until
+            )
+            .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(events)
+    }
+
+    /// Returns a user's status as of a point in time, or `None` if they had no events by then
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `at` - The point in time
+    pub async fn as_of(
+        db: &mut SqlConn,
+        user_id: &str,
+        at: DateTime<Utc>,
+    ) -> anyhow::Result<Option<User>> {
+        let events = Self::fetch_by_user_until(db, user_id, at).await?;
+        if events.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(User::project(user_id, &events)))
+    }
+
+    /// Rebuilds every user's status from their events, returning the number of users rebuilt
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn rebuild(db: &mut SqlConn) -> anyhow::Result<usize> {
+        let users = timed!(
+            "sql/status_event/fetch_user_ids.sql",
+            sqlx::query_file_as!(EventUser, "sql/status_event/fetch_user_ids.sql")
+                .fetch_all(&mut *db)
+        )
+        .await?;
+
+        let now = Utc::now();
+        for user in &users {
+            let events = Self::fetch_by_user_until(&mut *db, &user.user_id, now).await?;
+            User::project(&user.user_id, &events)
+                .save_projection(&mut *db)
+                .await?;
+        }
+
+        Ok(users.len())
+    }
+}
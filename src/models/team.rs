@@ -1,8 +1,222 @@
 //! Team Representation for sqlx
 
-use crate::{models::User, SqlConn};
-use futures::TryStreamExt;
+use crate::{
+    error::Error,
+    models::{
+        compact_status, parse_values, plain_status, Announcement, ApprovalChain, Availability,
+        BulkStatus, Location, Muster, RetentionOverride, StatusAck, StatusStore, TeamField, User,
+    },
+    SqlConn,
+};
+use chrono::{DateTime, Utc, Weekday};
+use futures::{Stream, TryStreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalizes a team name for lookups, so names differing only by case or unicode
+/// representation refer to the same team
+///
+/// # Arguments
+/// * `name` - Name of the team
+pub fn normalize_name(name: &str) -> String {
+    caseless::default_case_fold_str(name.trim()).nfc().collect()
+}
+
+/// Names that can't be used for teams, as they clash with commands
+const RESERVED_NAMES: &[&str] = &[
+    "all",
+    "announce",
+    "autoreply",
+    "badge",
+    "book",
+    "calendar",
+    "contact",
+    "create",
+    "delegate",
+    "delete",
+    "feedback",
+    "help",
+    "leave",
+    "list",
+    "muster",
+    "office",
+    "set",
+    "setup",
+    "shift",
+    "site",
+    "team",
+    "timeline",
+    "unbook",
+    "wizard",
+];
+
+/// Maximum length of a team name, in characters
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Reads an optional numeric limit from the environment
+///
+/// # Arguments
+/// * `var` - Name of the environment variable
+fn limit(var: &str) -> Option<i64> {
+    dotenv::var(var).ok().and_then(|limit| limit.parse().ok())
+}
+
+/// Checks that a name can be used for a team
+///
+/// Names must start with a letter or digit, may only contain letters, digits, `-`, `_`,
+/// and `.`, and must not be a reserved name
+///
+/// # Arguments
+/// * `name` - Proposed name of the team
+pub fn validate_name(name: &str) -> Result<(), Error> {
+    let length = name.chars().count();
+    if length == 0 || length > MAX_NAME_LENGTH {
+        return Err(Error::Parse(format!(
+            "Team names must be between 1 and {} characters long",
+            MAX_NAME_LENGTH
+        )));
+    }
+
+    let valid = name.starts_with(char::is_alphanumeric)
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.');
+
+    if !valid {
+        return Err(Error::Parse(format!(
+            "*{}* is not a valid team name. Names must start with a letter or number and may only contain letters, numbers, `-`, `_`, and `.`",
+            name
+        )));
+    }
+
+    if RESERVED_NAMES.contains(&normalize_name(name).as_str()) {
+        return Err(Error::Parse(format!("*{}* is a reserved name", name)));
+    }
+
+    Ok(())
+}
+
+/// What a member of a team is responsible for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemberRole {
+    /// Leads the team, and is notified about (and approves) changes to it
+    Lead,
+
+    /// A regular member of the team
+    Member,
+
+    /// Follows the team without being one of its members
+    Viewer,
+}
+
+impl MemberRole {
+    /// Returns the name of this role, as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemberRole::Lead => "lead",
+            MemberRole::Member => "member",
+            MemberRole::Viewer => "viewer",
+        }
+    }
+}
+
+impl std::str::FromStr for MemberRole {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lead" => Ok(MemberRole::Lead),
+            "member" => Ok(MemberRole::Member),
+            "viewer" => Ok(MemberRole::Viewer),
+            _ => Err(Error::Parse(format!("*{}* is not a valid role", s))),
+        }
+    }
+}
+
+/// A member of a team, along with their status and role
+#[derive(Clone, Debug)]
+pub struct Member {
+    /// The unique identifier provided by Slack
+    pub id: String,
+
+    /// The status the user sets (a free-text note)
+    pub status: Option<String>,
+
+    /// Where the member is working, as stored in the database
+    location: Option<String>,
+
+    /// Which site the member is working at, if their location has one
+    site: Option<String>,
+
+    /// Whether the member can be reached, as stored in the database
+    availability: Option<String>,
+
+    /// Name of the member, if they're a guest
+    pub name: Option<String>,
+
+    /// If the member is a guest who isn't on Slack
+    pub external: bool,
+
+    /// Slack ID of who covers for the member while they're away, if set
+    pub delegate_id: Option<String>,
+
+    /// Values the member entered for custom fields, as stored in the database
+    fields: Option<String>,
+
+    /// The member's role, as stored in the database
+    role: String,
+}
+
+impl Member {
+    /// Returns the member's role within the team
+    pub fn role(&self) -> MemberRole {
+        self.role.parse().unwrap_or(MemberRole::Member)
+    }
+
+    /// Returns where the member is working, if they've said
+    pub fn location(&self) -> Option<Location> {
+        self.location.as_deref().and_then(|l| l.parse().ok())
+    }
+
+    /// Returns which site the member is working at, if their location has one
+    pub fn site(&self) -> Option<&str> {
+        self.site.as_deref()
+    }
+
+    /// Returns whether the member can be reached, if they've said
+    pub fn availability(&self) -> Option<Availability> {
+        self.availability.as_deref().and_then(|a| a.parse().ok())
+    }
+
+    /// Renders the member's location, availability, and note on a single line, returning
+    /// `None` if the member has not set any of them
+    pub fn compact_status(&self) -> Option<String> {
+        compact_status(
+            self.location(),
+            self.site(),
+            self.availability(),
+            self.status.as_deref(),
+        )
+    }
+
+    /// Describes the member's location, availability, and note in words, returning `None` if
+    /// the member has not set any of them
+    pub fn plain_status(&self) -> Option<String> {
+        plain_status(
+            self.location(),
+            self.site(),
+            self.availability(),
+            self.status.as_deref(),
+        )
+    }
+
+    /// Returns the values the member entered for custom fields, keyed by field name
+    pub fn field_values(&self) -> BTreeMap<String, String> {
+        parse_values(self.fields.as_deref())
+    }
+}
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Team {
@@ -11,6 +225,33 @@ pub struct Team {
 
     // Name of team
     pub name: String,
+
+    // What the team does
+    pub description: Option<String>,
+
+    // Emoji shown next to the team's name (e.g., `:rocket:`)
+    pub icon: Option<String>,
+
+    // When the team was created (unknown for teams created before this was recorded)
+    pub created_at: Option<DateTime<Utc>>,
+
+    // Slack ID of the user who created the team
+    pub created_by: Option<String>,
+
+    // Slack ID of the channel bound to the team
+    pub channel: Option<String>,
+
+    // Post a line in the bound channel when a member's status changes outside of it
+    pub notify_changes: bool,
+
+    // Minimum number of members needed on site, if the team has a coverage requirement
+    pub min_coverage: Option<i64>,
+
+    // Weekdays the coverage requirement applies on (bit 0 is Monday)
+    pub coverage_days: i64,
+
+    // How often the team is sent a digest (`daily` or `weekly`), if not the `DIGEST` default
+    pub digest: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -18,16 +259,47 @@ impl Team {
     /// Creates a new team with the supplied name and save
     /// it in the database
     ///
+    /// Fails with `Error::Parse` if the name is invalid, or `Error::Limit` if the workspace
+    /// already has `MAX_TEAMS` teams
+    ///
     /// # Arguments
     /// * `name` - Name of this team
-    pub async fn new(db: &mut SqlConn, name: &str) -> anyhow::Result<Self> {
-        sqlx::query_file!("sql/team/insert.sql", name)
+    /// * `created_by` - Slack ID of the user creating the team
+    pub async fn new(db: &mut SqlConn, name: &str, created_by: &str) -> anyhow::Result<Self> {
+        validate_name(name)?;
+
+        let normalized = normalize_name(name);
+        let max = limit("MAX_TEAMS");
+
+        // the limit is checked by the insert itself, so concurrent creates can't exceed it
+        let inserted = timed!(
+            "sql/team/insert.sql",
+            sqlx::query_file!(
+                "sql/team/insert.sql",
+                name,
+                normalized,
+                created_by,
+                max.unwrap_or(i64::MAX)
+            )
             .execute(&mut *db)
-            .await?;
+        )
+        .await?
+        .rows_affected();
 
-        let team = sqlx::query_file_as!(Team, "sql/team/fetch_by_name.sql", name)
-            .fetch_one(&mut *db)
-            .await?;
+        if let (0, Some(max)) = (inserted, max) {
+            return Err(Error::Limit(format!(
+                "This workspace already has the maximum of {} teams",
+                max
+            ))
+            .into());
+        }
+
+        let team = timed!(
+            "sql/team/fetch_by_name.sql",
+            sqlx::query_file_as!(Team, "sql/team/fetch_by_name.sql", normalized)
+                .fetch_one(&mut *db)
+        )
+        .await?;
 
         Ok(team)
     }
@@ -36,12 +308,31 @@ impl Team {
     ///
     /// # Arguments
     /// * `db` - Connection to SQL database
-    /// * `name` - Name of team to fetch
+    /// * `name` - Name of team to fetch, in any case
     pub async fn fetch(db: &mut SqlConn, name: &str) -> Option<Self> {
+        let name = normalize_name(name);
         let mut row =
             sqlx::query_file_as!(Team, "sql/team/fetch_by_name.sql", name).fetch(&mut *db);
 
-        row.try_next().await.ok().flatten()
+        timed!("sql/team/fetch_by_name.sql", row.try_next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Attempts to retrieve a team by its unique id, returning None if one does not exist
+    ///
+    /// # Arguments
+    /// * `db` - Connection to SQL database
+    /// * `id` - Unique id of the team
+    pub async fn fetch_by_id(db: &mut SqlConn, id: i64) -> anyhow::Result<Option<Self>> {
+        let team = timed!(
+            "sql/team/fetch_by_id.sql",
+            sqlx::query_file_as!(Team, "sql/team/fetch_by_id.sql", id).fetch_optional(&mut *db)
+        )
+        .await?;
+
+        Ok(team)
     }
 
     /// Fetches all teams from the database
@@ -49,41 +340,330 @@ impl Team {
     /// # Arguments
     /// * `db` - Conenction to the SQL database
     pub async fn fetch_all(db: &mut SqlConn) -> anyhow::Result<Vec<Team>> {
-        let teams = sqlx::query_file_as!(Team, "sql/team/fetch_all.sql")
-            .fetch_all(&mut *db)
-            .await?;
+        let teams = timed!(
+            "sql/team/fetch_all.sql",
+            sqlx::query_file_as!(Team, "sql/team/fetch_all.sql").fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(teams)
+    }
+
+    /// Fetches a page of teams from the database, ordered by name
+    ///
+    /// # Arguments
+    /// * `db` - Conenction to the SQL database
+    /// * `limit` - Maximum number of teams to return
+    /// * `offset` - Number of teams to skip
+    pub async fn fetch_page(
+        db: &mut SqlConn,
+        limit: i64,
+        offset: i64,
+    ) -> anyhow::Result<Vec<Team>> {
+        let teams = timed!(
+            "sql/team/fetch_page.sql",
+            sqlx::query_file_as!(Team, "sql/team/fetch_page.sql", limit, offset)
+                .fetch_all(&mut *db)
+        )
+        .await?;
 
         Ok(teams)
     }
 
+    /// Fetches the teams whose names contain some text, ordered by name
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `text` - Text to search for, in any case
+    /// * `limit` - Most teams to return
+    pub async fn search(db: &mut SqlConn, text: &str, limit: i64) -> anyhow::Result<Vec<Team>> {
+        // `%` and `_` are matched literally, as `_` is allowed in team names
+        let pattern = format!(
+            "%{}%",
+            normalize_name(text)
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        let teams = timed!(
+            "sql/team/search.sql",
+            sqlx::query_file_as!(Team, "sql/team/search.sql", pattern, limit).fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(teams)
+    }
+
+    /// Streams all teams from the database, without loading them all into memory
+    ///
+    /// # Arguments
+    /// * `db` - Conenction to the SQL database
+    pub fn stream_all(
+        db: &mut SqlConn,
+    ) -> impl Stream<Item = Result<Team, sqlx::Error>> + Send + Unpin + '_ {
+        sqlx::query_file_as!(Team, "sql/team/fetch_all.sql").fetch(db)
+    }
+
     /// Returns all members belonging to a team with name `name`
     ///
     /// # Arguments
     /// * `db` - Connection to SQL database
-    /// * `team_name` - Name of this team
+    /// * `team_name` - Name of this team, in any case
     pub async fn members(db: &mut SqlConn, team_name: &str) -> anyhow::Result<Vec<User>> {
-        let users = sqlx::query_file_as!(User, "sql/team/fetch_members.sql", team_name)
-            .fetch_all(&mut *db)
-            .await?;
+        let team_name = normalize_name(team_name);
+        let users = timed!(
+            "sql/team/fetch_members.sql",
+            sqlx::query_file_as!(User, "sql/team/fetch_members.sql", team_name).fetch_all(&mut *db)
+        )
+        .await?;
 
         Ok(users)
     }
 
+    /// Returns a page of the members belonging to a team with name `name`, leads first and
+    /// then ordered by id
+    ///
+    /// # Arguments
+    /// * `db` - Connection to SQL database
+    /// * `team_name` - Name of this team, in any case
+    /// * `limit` - Maximum number of members to return
+    /// * `offset` - Number of members to skip
+    pub async fn members_page(
+        db: &mut SqlConn,
+        team_name: &str,
+        limit: i64,
+        offset: i64,
+    ) -> anyhow::Result<Vec<Member>> {
+        let team_name = normalize_name(team_name);
+        let members = timed!(
+            "sql/team/fetch_members_page.sql",
+            sqlx::query_file_as!(
+                Member,
+                "sql/team/fetch_members_page.sql",
+                team_name,
+                limit,
+                offset
+            )
+            .fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(members)
+    }
+
+    /// Returns a page of the members belonging to a team with name `name`, as `members_page`
+    /// does, with the status, location, and availability each had at a point in time
+    ///
+    /// Statuses are taken from the status history (or the status events, which also record
+    /// sites).  Everything else about members is as it is now.
+    ///
+    /// # Arguments
+    /// * `db` - Connection to SQL database
+    /// * `team_name` - Name of this team, in any case
+    /// * `at` - The point in time
+    /// * `limit` - Maximum number of members to return
+    /// * `offset` - Number of members to skip
+    pub async fn members_as_of(
+        db: &mut SqlConn,
+        team_name: &str,
+        at: DateTime<Utc>,
+        limit: i64,
+        offset: i64,
+    ) -> anyhow::Result<Vec<Member>> {
+        let team_name = normalize_name(team_name);
+        let members = match StatusStore::current() {
+            StatusStore::History => {
+                timed!(
+                    "sql/team/fetch_members_as_of.sql",
+                    sqlx::query_file_as!(
+                        Member,
+                        "sql/team/fetch_members_as_of.sql",
+                        team_name,
+                        at,
+                        limit,
+                        offset
+                    )
+                    .fetch_all(&mut *db)
+                )
+                .await?
+            }
+            StatusStore::Events => {
+                timed!(
+                    "sql/team/fetch_members_as_of_events.sql",
+                    sqlx::query_file_as!(
+                        Member,
+                        "sql/team/fetch_members_as_of_events.sql",
+                        team_name,
+                        at,
+                        limit,
+                        offset
+                    )
+                    .fetch_all(&mut *db)
+                )
+                .await?
+            }
+        };
+
+        Ok(members)
+    }
+
+    /// Streams the members belonging to a team with name `name`, without loading them all
+    /// into memory
+    ///
+    /// # Arguments
+    /// * `db` - Connection to SQL database
+    /// * `team_name` - Normalized name of this team (see `normalize_name`)
+    pub fn stream_members<'a>(
+        db: &'a mut SqlConn,
+        team_name: &'a str,
+    ) -> impl Stream<Item = Result<User, sqlx::Error>> + Send + Unpin + 'a {
+        sqlx::query_file_as!(User, "sql/team/fetch_members.sql", team_name).fetch(db)
+    }
+
     /// Adds a member to this team.
     ///
-    /// If the member is already on this team, do nothing
+    /// If the member is already on this team, do nothing.  Fails with `Error::Limit` if
+    /// the team already has `MAX_TEAM_MEMBERS` members
     ///
     /// # Arguments
     /// * `db` - Conenction to SQL database
     /// * `user` - User to add
     pub async fn add_member(&self, db: &mut SqlConn, user: &User) -> anyhow::Result<()> {
-        sqlx::query_file!("sql/team/add_member.sql", user.id, self.id)
+        let max = limit("MAX_TEAM_MEMBERS");
+
+        // the limit is checked by the insert itself, so concurrent adds can't exceed it
+        let added = timed!(
+            "sql/team/add_member.sql",
+            sqlx::query_file!(
+                "sql/team/add_member.sql",
+                user.id,
+                self.id,
+                max.unwrap_or(i64::MAX)
+            )
             .execute(&mut *db)
-            .await?;
+        )
+        .await?
+        .rows_affected();
+
+        // nothing is added for users who are already members, either
+        if let (0, Some(max)) = (added, max) {
+            if self.member_role(&mut *db, user).await?.is_none() {
+                return Err(Error::Limit(format!(
+                    "Team *{}* already has the maximum of {} members",
+                    self.name, max
+                ))
+                .into());
+            }
+        }
 
         Ok(())
     }
 
+    /// Returns the role of a user in this team, or `None` if they are not a member
+    ///
+    /// # Arguments
+    /// * `db` - Conenction to SQL database
+    /// * `user` - User to look up
+    pub async fn member_role(
+        &self,
+        db: &mut SqlConn,
+        user: &User,
+    ) -> Result<Option<MemberRole>, Error> {
+        let row = timed!(
+            "sql/team/fetch_member_role.sql",
+            sqlx::query_file!("sql/team/fetch_member_role.sql", user.id, self.id)
+                .fetch_optional(&mut *db)
+        )
+        .await?;
+
+        Ok(row.map(|row| row.role.parse().unwrap_or(MemberRole::Member)))
+    }
+
+    /// Sets the role of a member of this team
+    ///
+    /// If the user isn't a member of the team, does nothing
+    ///
+    /// # Arguments
+    /// * `db` - Conenction to SQL database
+    /// * `user` - Member to update
+    /// * `role` - The member's new role
+    pub async fn set_role(
+        &self,
+        db: &mut SqlConn,
+        user: &User,
+        role: MemberRole,
+    ) -> anyhow::Result<()> {
+        timed!(
+            "sql/team/set_member_role.sql",
+            sqlx::query_file!(
+                "sql/team/set_member_role.sql",
+                role.as_str(),
+                user.id,
+                self.id
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns the leads of this team, who notifications and approvals are sent to
+    ///
+    /// # Arguments
+    /// * `db` - Conenction to SQL database
+    pub async fn leads(&self, db: &mut SqlConn) -> anyhow::Result<Vec<User>> {
+        let users = timed!(
+            "sql/team/fetch_leads.sql",
+            sqlx::query_file_as!(User, "sql/team/fetch_leads.sql", self.id).fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(users)
+    }
+
+    /// Fetches the teams a user belongs to
+    ///
+    /// # Arguments
+    /// * `db` - Connection to SQL database
+    /// * `user_id` - Slack ID of the user
+    pub async fn fetch_by_member(db: &mut SqlConn, user_id: &str) -> anyhow::Result<Vec<Self>> {
+        let teams = timed!(
+            "sql/team/fetch_by_member.sql",
+            sqlx::query_file_as!(Team, "sql/team/fetch_by_member.sql", user_id).fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(teams)
+    }
+
+    /// Returns the minimum number of members the team needs on site on a weekday, if it
+    /// has a coverage requirement that applies then
+    ///
+    /// # Arguments
+    /// * `weekday` - Day of the week
+    pub fn required_coverage(&self, weekday: Weekday) -> Option<i64> {
+        let day = 1 << weekday.num_days_from_monday();
+        self.min_coverage.filter(|_| self.coverage_days & day != 0)
+    }
+
+    /// Returns the channels bound to the teams a user belongs to that are notified when
+    /// the user's status changes
+    ///
+    /// # Arguments
+    /// * `db` - Connection to SQL database
+    /// * `user_id` - Slack ID of the user
+    pub async fn notify_channels(db: &mut SqlConn, user_id: &str) -> anyhow::Result<Vec<String>> {
+        let rows = timed!(
+            "sql/team/fetch_notify_channels.sql",
+            sqlx::query_file!("sql/team/fetch_notify_channels.sql", user_id).fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(rows.into_iter().filter_map(|row| row.channel).collect())
+    }
+
     /// Deletes a member from the team.
     ///
     /// If the member isn't a part of the team, does nothing.
@@ -92,36 +672,253 @@ impl Team {
     /// * `db` - Conenction to SQL database
     /// * `user` - User to add
     pub async fn delete_member(&self, db: &mut SqlConn, user: &User) -> anyhow::Result<()> {
-        sqlx::query_file!("sql/team/delete_member.sql", user.id, self.id)
-            .execute(&mut *db)
-            .await?;
+        timed!(
+            "sql/shift/unassign_by_team.sql",
+            sqlx::query_file!("sql/shift/unassign_by_team.sql", user.id, self.id).execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/team/delete_member.sql",
+            sqlx::query_file!("sql/team/delete_member.sql", user.id, self.id).execute(&mut *db)
+        )
+        .await?;
 
         Ok(())
     }
 
+    /// Attempts to fetch a guest (a member who isn't on Slack) by name, returning `None` if
+    /// the team has no such guest
+    ///
+    /// # Arguments
+    /// * `db` - Connection to SQL database
+    /// * `name` - Name of the guest, in any case
+    pub async fn guest(&self, db: &mut SqlConn, name: &str) -> anyhow::Result<Option<User>> {
+        let guest = timed!(
+            "sql/team/fetch_guest.sql",
+            sqlx::query_file_as!(User, "sql/team/fetch_guest.sql", self.id, name)
+                .fetch_optional(&mut *db)
+        )
+        .await?;
+
+        Ok(guest)
+    }
+
+    /// Returns the team's unique id
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Returns the team's name, prefixed with its icon if it has one
+    pub fn display_name(&self) -> String {
+        match &self.icon {
+            Some(icon) => format!("{} {}", icon, self.name),
+            None => self.name.clone(),
+        }
+    }
+
     /// Saves this team into the database
     ///
-    /// If this team does not exist, a new record is created.  If it does,
-    /// the team name is updated
+    /// The team's name, description, icon, channel binding, coverage requirement, and digest
+    /// frequency are updated
     ///
     /// # Arguments
     /// * `db` - Connection to SQL database
     pub async fn save(&self, db: &mut SqlConn) -> anyhow::Result<()> {
-        sqlx::query_file!("sql/team/save.sql", self.name, self.id)
+        let normalized = normalize_name(&self.name);
+
+        timed!(
+            "sql/team/save.sql",
+            sqlx::query_file!(
+                "sql/team/save.sql",
+                self.name,
+                normalized,
+                self.description,
+                self.icon,
+                self.channel,
+                self.notify_changes,
+                self.min_coverage,
+                self.coverage_days,
+                self.digest,
+                self.id
+            )
             .execute(&mut *db)
-            .await?;
+        )
+        .await?;
 
         Ok(())
     }
 
+    /// Counts the teams created before names were normalized, which can't be looked up by
+    /// name until `normalize_names` is run
+    ///
+    /// # Arguments
+    /// * `db` - Connection to SQL database
+    pub async fn count_unnormalized(db: &mut SqlConn) -> anyhow::Result<usize> {
+        let teams = timed!(
+            "sql/team/fetch_unnormalized.sql",
+            sqlx::query_file!("sql/team/fetch_unnormalized.sql").fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(teams.len())
+    }
+
+    /// Normalizes the names of teams created before names were normalized
+    ///
+    /// Teams whose names only differ by case (or unicode representation) are merged into
+    /// the oldest of them, see `merge_into`.  Returns a description of each merge, so
+    /// conflicts can be reported.  Run it inside a transaction, so a failure part way through
+    /// leaves every team as it was.
+    ///
+    /// # Arguments
+    /// * `db` - Connection to SQL database
+    pub async fn normalize_names(db: &mut SqlConn) -> anyhow::Result<Vec<String>> {
+        let teams = timed!(
+            "sql/team/fetch_unnormalized.sql",
+            sqlx::query_file!("sql/team/fetch_unnormalized.sql").fetch_all(&mut *db)
+        )
+        .await?;
+
+        let mut merged = vec![];
+        for team in teams {
+            let normalized = normalize_name(&team.name);
+            let existing = timed!(
+                "sql/team/fetch_id_by_normalized_name.sql",
+                sqlx::query_file!("sql/team/fetch_id_by_normalized_name.sql", normalized)
+                    .fetch_optional(&mut *db)
+            )
+            .await?;
+
+            match existing {
+                Some(existing) => {
+                    Self::merge_into(&mut *db, team.id, existing.id).await?;
+                    merged.push(format!(
+                        "team '{}' (id {}) merged into team id {}",
+                        team.name, team.id, existing.id
+                    ));
+                }
+                None => {
+                    timed!(
+                        "sql/team/set_normalized_name.sql",
+                        sqlx::query_file!("sql/team/set_normalized_name.sql", normalized, team.id)
+                            .execute(&mut *db)
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        Ok(merged)
+    }
+
+    /// Merges a team into another, then deletes it
+    ///
+    /// Members keep the higher of their roles in the two teams, and details the other team
+    /// hasn't set (description, icon, channel, coverage, and digest) are carried over.
+    /// Shifts, fields, acknowledgements, musters, announcements, bulk statuses, approvals,
+    /// and the retention override are moved; where the other team already has a shift or
+    /// field of the same name, an acknowledgement of the same member, or its own retention
+    /// override or approval chain, the other team's is kept (and shift assignments are
+    /// combined).
+    ///
+    /// # Arguments
+    /// * `db` - Connection to SQL database
+    /// * `from` - Id of the team to merge, which is deleted
+    /// * `to` - Id of the team to merge it into
+    async fn merge_into(db: &mut SqlConn, from: i64, to: i64) -> anyhow::Result<()> {
+        timed!(
+            "sql/team/merge_details.sql",
+            sqlx::query_file!("sql/team/merge_details.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/team/move_members.sql",
+            sqlx::query_file!("sql/team/move_members.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/shift/move_by_team.sql",
+            sqlx::query_file!("sql/shift/move_by_team.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/shift/merge_members_by_team.sql",
+            sqlx::query_file!("sql/shift/merge_members_by_team.sql", to, from).execute(&mut *db)
+        )
+        .await?;
+
+        Announcement::move_by_team(&mut *db, from, to).await?;
+        ApprovalChain::move_by_team(&mut *db, from, to).await?;
+        StatusAck::move_by_team(&mut *db, from, to).await?;
+        RetentionOverride::move_by_team(&mut *db, from, to).await?;
+        BulkStatus::move_by_team(&mut *db, from, to).await?;
+        TeamField::move_by_team(&mut *db, from, to).await?;
+        Muster::move_by_team(&mut *db, from, to).await?;
+
+        // only what the other team already had is left
+        timed!(
+            "sql/team/delete_all_members.sql",
+            sqlx::query_file!("sql/team/delete_all_members.sql", from).execute(&mut *db)
+        )
+        .await?;
+
+        match Self::fetch_by_id(&mut *db, from).await? {
+            Some(team) => team.delete(&mut *db).await,
+            None => Ok(()),
+        }
+    }
+
     /// Deletes this team from the database
     ///
     /// *THIS ACTION CANNOT BE UNDONE*
     pub async fn delete(self, db: &mut SqlConn) -> anyhow::Result<()> {
-        sqlx::query_file!("sql/team/delete.sql", self.id)
-            .execute(&mut *db)
-            .await?;
+        Announcement::delete_by_team(&mut *db, self.id).await?;
+        ApprovalChain::delete_by_team(&mut *db, self.id).await?;
+        StatusAck::delete_by_team(&mut *db, self.id).await?;
+        RetentionOverride::delete_by_team(&mut *db, self.id).await?;
+        BulkStatus::delete_by_team(&mut *db, self.id).await?;
+        TeamField::delete_by_team(&mut *db, self.id).await?;
+        Muster::delete_by_team(&mut *db, self.id).await?;
+
+        timed!(
+            "sql/shift/delete_members_by_team.sql",
+            sqlx::query_file!("sql/shift/delete_members_by_team.sql", self.id).execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/shift/delete_by_team.sql",
+            sqlx::query_file!("sql/shift/delete_by_team.sql", self.id).execute(&mut *db)
+        )
+        .await?;
+
+        timed!(
+            "sql/team/delete.sql",
+            sqlx::query_file!("sql/team/delete.sql", self.id).execute(&mut *db)
+        )
+        .await?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_names_are_reserved() {
+        for name in &["delegate", "Delegate", "team", "help"] {
+            assert!(
+                matches!(validate_name(name), Err(Error::Parse(_))),
+                "{} should be reserved",
+                name
+            );
+        }
+        assert!(validate_name("delegates").is_ok());
+    }
+}
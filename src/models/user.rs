@@ -1,23 +1,104 @@
 //! A user in the system
 
-use crate::SqlConn;
-use futures::TryStreamExt;
+use crate::{
+    error::Error,
+    models::{
+        compact_status, parse_values, plain_status, Availability, HistoryEntry, Leave, Location,
+        StatusEvent,
+    },
+    SqlConn,
+};
+use chrono::NaiveDate;
+use std::{collections::BTreeMap, fmt, str::FromStr};
 
-macro_rules! extract_user_id {
-    ($user:expr) => {
-        $user
+/// Error returned when a string is not a valid Slack user id
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidUserId(pub String);
+
+impl fmt::Display for InvalidUserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid slack user id: '{}'", self.0)
+    }
+}
+
+impl std::error::Error for InvalidUserId {}
+
+/// A validated Slack user id (e.g., `U012AB3CD` or `W012AB3CD`)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SlackUserId(String);
+
+#[allow(dead_code)]
+impl SlackUserId {
+    /// Parses a Slack user id, accepting either a raw id or a mention (`<@U012AB3CD|name>`)
+    ///
+    /// # Arguments
+    /// * `s` - Text containing the user id
+    pub fn parse(s: &str) -> Result<Self, InvalidUserId> {
+        let id = s
+            .trim()
             .trim_matches(|c| c == '<' || c == '>' || c == '@')
             .split('|')
             .next()
-    };
+            .unwrap_or_default();
+
+        let mut chars = id.chars();
+        let valid = matches!(chars.next(), Some('U') | Some('W'))
+            && id.len() > 1
+            && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+
+        if valid {
+            Ok(SlackUserId(id.to_owned()))
+        } else {
+            Err(InvalidUserId(s.to_owned()))
+        }
+    }
+
+    /// Returns the id as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Consumes this id, returning the underlying string
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl FromStr for SlackUserId {
+    type Err = InvalidUserId;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SlackUserId::parse(s)
+    }
+}
+
+impl fmt::Display for SlackUserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for SlackUserId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
 }
 
 pub struct User {
     /// The unique identifier provided by Slack
     pub id: String,
 
-    /// The status the user sets
+    /// The status the user sets (a free-text note)
     pub status: Option<String>,
+
+    /// Where the user is working, as stored in the database
+    location: Option<String>,
+
+    /// Which site the user is working at, if their location has one
+    site: Option<String>,
+
+    /// Whether the user can be reached, as stored in the database
+    availability: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -25,28 +106,66 @@ impl User {
     /// Creates a new user but does *not* save in the database
     ///
     /// # Arguments
-    /// `id` - The user's Slack ID
-    pub fn new(id: String) -> Self {
-        // Parse the id, if necessary
-        let id = extract_user_id!(id).unwrap().to_string();
+    /// `id` - The user's Slack ID, or a mention of the user
+    pub fn new(id: &str) -> Result<Self, InvalidUserId> {
+        let id = SlackUserId::parse(id)?.into_inner();
 
-        User { id, status: None }
+        Ok(User {
+            id,
+            status: None,
+            location: None,
+            site: None,
+            availability: None,
+        })
+    }
+
+    /// Creates a placeholder for someone who isn't on Slack (e.g., a contractor) and saves
+    /// it in the database
+    ///
+    /// External users get a generated id (`ext-<hex>`), so they can never be mentioned
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `name` - The person's name
+    pub async fn new_external(db: &mut SqlConn, name: &str) -> anyhow::Result<Self> {
+        let id = format!("ext-{}", hex::encode(rand::random::<[u8; 8]>()));
+
+        timed!(
+            "sql/user/insert_external.sql",
+            sqlx::query_file!("sql/user/insert_external.sql", id, name).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(User {
+            id,
+            status: None,
+            location: None,
+            site: None,
+            availability: None,
+        })
     }
 
     /// Attempts to fetch a user and their status from the database, returning
     /// `None` if the user does not exist
     ///
+    /// Fails with `Error::Parse` if the id isn't a valid Slack user id, and `Error::Db` if
+    /// the user couldn't be fetched.
+    ///
     /// # Arguments
     /// * `db` - Connection to the SQL database
-    /// * `user_id` - Slack ID of user to fetch
-    pub async fn fetch(db: &mut SqlConn, user_id: &str) -> Option<Self> {
-        // Parse the user id, if necessary
-        let user_id = extract_user_id!(user_id).unwrap();
+    /// * `user_id` - Slack ID of user to fetch, or a mention of the user
+    pub async fn fetch(db: &mut SqlConn, user_id: &str) -> Result<Option<Self>, Error> {
+        let user_id = SlackUserId::parse(user_id)?;
+        let user_id = user_id.as_str();
 
-        let mut rows =
-            sqlx::query_file_as!(User, "sql/user/fetch_by_id.sql", user_id).fetch(&mut *db);
+        let user = timed!(
+            "sql/user/fetch_by_id.sql",
+            sqlx::query_file_as!(User, "sql/user/fetch_by_id.sql", user_id)
+                .fetch_optional(&mut *db)
+        )
+        .await?;
 
-        rows.try_next().await.ok().flatten()
+        Ok(user)
     }
 
     /// Attempts to fetch a user and their status from the database, creating
@@ -54,19 +173,21 @@ impl User {
     ///
     /// # Arguments
     /// * `db` - Connection to the SQL database
-    /// * `user_id` - Slack ID of user to fetch
+    /// * `user_id` - Slack ID of user to fetch, or a mention of the user
     pub async fn fetch_or_create(db: &mut SqlConn, user_id: &str) -> anyhow::Result<Self> {
-        // Parse the user id, if necessary
-        let user_id = extract_user_id!(user_id).unwrap();
+        let user_id = SlackUserId::parse(user_id)?;
+        let user_id = user_id.as_str();
 
-        let user = sqlx::query_file_as!(User, "sql/user/fetch_by_id.sql", user_id)
-            .fetch_one(&mut *db)
-            .await;
+        let user = timed!(
+            "sql/user/fetch_by_id.sql",
+            sqlx::query_file_as!(User, "sql/user/fetch_by_id.sql", user_id).fetch_one(&mut *db)
+        )
+        .await;
 
         match user {
             Ok(user) => Ok(user),
             Err(sqlx::Error::RowNotFound) => {
-                let user = User::new(user_id.to_owned());
+                let user = User::new(user_id)?;
                 user.save(&mut *db).await?;
                 Ok(user)
             }
@@ -74,6 +195,170 @@ impl User {
         }
     }
 
+    /// Fetches the Slack ID of who covers for a user while they're away, returning `None`
+    /// if they haven't set a delegate
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    pub async fn fetch_delegate(db: &mut SqlConn, user_id: &str) -> anyhow::Result<Option<String>> {
+        let row = timed!(
+            "sql/user/fetch_delegate.sql",
+            sqlx::query_file!("sql/user/fetch_delegate.sql", user_id).fetch_optional(&mut *db)
+        )
+        .await?;
+
+        Ok(row.and_then(|row| row.delegate_id))
+    }
+
+    /// Sets (or clears) who covers for a user while they're away
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `delegate_id` - Slack ID of the delegate, or `None` to clear it
+    pub async fn set_delegate(
+        db: &mut SqlConn,
+        user_id: &str,
+        delegate_id: Option<&str>,
+    ) -> anyhow::Result<()> {
+        timed!(
+            "sql/user/set_delegate.sql",
+            sqlx::query_file!("sql/user/set_delegate.sql", user_id, delegate_id).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetches the values a user entered for custom fields, keyed by field name
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    pub async fn fetch_fields(
+        db: &mut SqlConn,
+        user_id: &str,
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        let row = timed!(
+            "sql/user/fetch_fields.sql",
+            sqlx::query_file!("sql/user/fetch_fields.sql", user_id).fetch_optional(&mut *db)
+        )
+        .await?;
+
+        Ok(parse_values(row.and_then(|row| row.fields).as_deref()))
+    }
+
+    /// Replaces the values a user entered for custom fields
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `values` - Values, keyed by field name
+    pub async fn set_fields(
+        db: &mut SqlConn,
+        user_id: &str,
+        values: &BTreeMap<String, String>,
+    ) -> anyhow::Result<()> {
+        let fields = serde_json::to_string(values)?;
+        timed!(
+            "sql/user/set_fields.sql",
+            sqlx::query_file!("sql/user/set_fields.sql", user_id, fields).execute(&mut *db)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns who requests meant for a user should go to on a day: their delegate, if
+    /// they're on leave and have one, else the user themselves
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `user_id` - Slack ID of the user
+    /// * `day` - The day
+    pub async fn route_to(
+        db: &mut SqlConn,
+        user_id: &str,
+        day: NaiveDate,
+    ) -> anyhow::Result<String> {
+        if Leave::fetch_covering(&mut *db, user_id, day)
+            .await?
+            .is_none()
+        {
+            return Ok(user_id.to_owned());
+        }
+
+        Ok(User::fetch_delegate(&mut *db, user_id)
+            .await?
+            .unwrap_or_else(|| user_id.to_owned()))
+    }
+
+    /// Fetches all users and their statuses from the database
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn fetch_all(db: &mut SqlConn) -> anyhow::Result<Vec<Self>> {
+        let users = timed!(
+            "sql/user/fetch_all.sql",
+            sqlx::query_file_as!(User, "sql/user/fetch_all.sql").fetch_all(&mut *db)
+        )
+        .await?;
+
+        Ok(users)
+    }
+
+    /// Fetches a page of the users working at a location, optionally only those at a site
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    /// * `location` - Where the users are working
+    /// * `site` - Which site the users are working at, if only one
+    /// * `limit` - Maximum number of users to return
+    /// * `offset` - Number of users to skip
+    pub async fn fetch_by_location(
+        db: &mut SqlConn,
+        location: Location,
+        site: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> anyhow::Result<Vec<Self>> {
+        let location = location.as_str();
+        let users = match site {
+            Some(site) => {
+                timed!(
+                    "sql/user/fetch_by_location_and_site.sql",
+                    sqlx::query_file_as!(
+                        User,
+                        "sql/user/fetch_by_location_and_site.sql",
+                        location,
+                        site,
+                        limit,
+                        offset
+                    )
+                    .fetch_all(&mut *db)
+                )
+                .await?
+            }
+            None => {
+                timed!(
+                    "sql/user/fetch_by_location.sql",
+                    sqlx::query_file_as!(
+                        User,
+                        "sql/user/fetch_by_location.sql",
+                        location,
+                        limit,
+                        offset
+                    )
+                    .fetch_all(&mut *db)
+                )
+                .await?
+            }
+        };
+
+        Ok(users)
+    }
+
     /// Sets the user's status.
     ///
     /// This does *not* save the status in the database. To do that, you must all the `save()`
@@ -85,10 +370,72 @@ impl User {
         self.status = Some(status);
     }
 
+    /// Returns where the user is working, if they've said
+    pub fn location(&self) -> Option<Location> {
+        self.location.as_deref().and_then(|l| l.parse().ok())
+    }
+
+    /// Returns which site the user is working at, if their location has one
+    pub fn site(&self) -> Option<&str> {
+        self.site.as_deref()
+    }
+
+    /// Sets where the user is working, and at which site
+    ///
+    /// This does *not* save the location in the database.
+    ///
+    /// # Arguments
+    /// * `location` - Where the user is working
+    /// * `site` - Which site the user is working at, if any
+    pub fn set_location(&mut self, location: Location, site: Option<&str>) {
+        self.location = Some(location.as_str().to_owned());
+        self.site = site.map(|site| site.to_owned());
+    }
+
+    /// Returns whether the user can be reached, if they've said
+    pub fn availability(&self) -> Option<Availability> {
+        self.availability.as_deref().and_then(|a| a.parse().ok())
+    }
+
+    /// Sets whether the user can be reached
+    ///
+    /// This does *not* save the availability in the database.
+    ///
+    /// # Arguments
+    /// * `availability` - Whether the user can be reached
+    pub fn set_availability(&mut self, availability: Availability) {
+        self.availability = Some(availability.as_str().to_owned());
+    }
+
+    /// Renders the user's location, availability, and note on a single line, returning
+    /// `None` if the user has not set any of them
+    pub fn compact_status(&self) -> Option<String> {
+        compact_status(
+            self.location(),
+            self.site(),
+            self.availability(),
+            self.status.as_deref(),
+        )
+    }
+
+    /// Describes the user's location, availability, and note in words, returning `None` if
+    /// the user has not set any of them
+    pub fn plain_status(&self) -> Option<String> {
+        plain_status(
+            self.location(),
+            self.site(),
+            self.availability(),
+            self.status.as_deref(),
+        )
+    }
+
     /// Saves this user and their status into the database
     ///
     /// If a row for this user does not exist, then one is inserted.
-    /// If one does exist, the status is updated.
+    /// If one does exist, the status, location, and availability that are set are
+    /// updated, leaving the others unchanged.  Whatever is set is also recorded in the
+    /// status history (or appended to the status events, see `StatusEvent`), in the same
+    /// transaction.
     ///
     /// # Arguments
     /// * `db` - Connection to the SQL database
@@ -96,11 +443,92 @@ impl User {
         // SQLx 0.4 doesn't allow refs like 0.3.5
         let id = self.id.clone();
         let status = self.status.clone();
+        let location = self.location.clone();
+        let site = self.site.clone();
+        let availability = self.availability.clone();
 
-        sqlx::query_file!("sql/user/save.sql", id, status)
-            .execute(&mut *db)
+        // the status and its history entry are saved together, or not at all
+        transaction!(db, async {
+            timed!(
+                "sql/user/save.sql",
+                sqlx::query_file!(
+                    "sql/user/save.sql",
+                    id,
+                    status,
+                    location,
+                    site,
+                    availability
+                )
+                .execute(&mut *db)
+            )
             .await?;
 
+            if self.status.is_some() || self.location.is_some() || self.availability.is_some() {
+                HistoryEntry::record(&mut *db, self).await?;
+            }
+
+            Ok::<_, anyhow::Error>(())
+        })
+    }
+
+    /// Builds a user's status from their status events, oldest first, combining them the
+    /// same way `save` combines changes
+    ///
+    /// # Arguments
+    /// * `id` - Slack ID of the user
+    /// * `events` - The user's events
+    pub fn project(id: &str, events: &[StatusEvent]) -> Self {
+        let mut user = User {
+            id: id.to_owned(),
+            status: None,
+            location: None,
+            site: None,
+            availability: None,
+        };
+
+        for event in events {
+            if event.status.is_some() {
+                user.status = event.status.clone();
+            }
+            if event.location.is_some() {
+                user.location = event.location.clone();
+                user.site = event.site.clone();
+            }
+            if event.availability.is_some() {
+                user.availability = event.availability.clone();
+            }
+        }
+
+        user
+    }
+
+    /// Overwrites this user's stored status with a projection of their status events,
+    /// without recording it as a change
+    ///
+    /// # Arguments
+    /// * `db` - Connection to the SQL database
+    pub async fn save_projection(&self, db: &mut SqlConn) -> anyhow::Result<()> {
+        // SQLx 0.4 doesn't allow refs like 0.3.5
+        let id = self.id.clone();
+        let status = self.status.clone();
+        let location = self.location.clone();
+        let site = self.site.clone();
+        let availability = self.availability.clone();
+
+        timed!(
+            "sql/user/set_projection.sql",
+            sqlx::query_file!(
+                "sql/user/set_projection.sql",
+                id,
+                status,
+                location,
+                site,
+                availability
+            )
+            .execute(&mut *db)
+        )
+        .await?;
+
         Ok(())
     }
 }
@@ -0,0 +1,247 @@
+//! Safety check-ins ("musters") of a team
+//!
+//! Every member of the team on Slack is sent a DM asking whether they're safe, with a button
+//! for each answer.  A summary of the responses is posted to the channel the muster was
+//! started from, and updated as each member responds.
+
+use crate::{
+    models::{Muster, MusterResponse, SlackUserId, Team},
+    notify::{self, Notification},
+    runtime, slack, SqlConn,
+};
+use anyhow::Result;
+use serde_json::{json, Value};
+
+/// Action id of the button members press if they're safe
+pub const SAFE_ACTION: &str = "muster_safe";
+
+/// Action id of the button members press if they need help
+pub const UNSAFE_ACTION: &str = "muster_unsafe";
+
+/// Lists the members who gave a response, or `_none_`
+///
+/// # Arguments
+/// * `responses` - Responses of every member asked
+/// * `safe` - Response to list the members of
+fn mentions(responses: &[MusterResponse], safe: Option<bool>) -> String {
+    let mentions: Vec<String> = responses
+        .iter()
+        .filter(|response| response.safe == safe)
+        .map(|response| format!("<@{}>", response.user_id))
+        .collect();
+
+    if mentions.is_empty() {
+        "_none_".to_owned()
+    } else {
+        mentions.join(", ")
+    }
+}
+
+/// Renders the summary of a muster, returning its fallback text and blocks
+///
+/// # Arguments
+/// * `muster` - The muster
+/// * `responses` - Responses of every member asked
+fn summary(muster: &Muster, responses: &[MusterResponse]) -> (String, Value) {
+    let count = |safe| responses.iter().filter(|r| r.safe == safe).count();
+    let (safe, unsafe_, pending) = (count(Some(true)), count(Some(false)), count(None));
+
+    let text = format!(
+        "Muster of team {}: {} safe, {} need help, {} not responded",
+        muster.team, safe, unsafe_, pending
+    );
+
+    let blocks = json!([
+        {
+            "type": "header",
+            "text": {
+                "type": "plain_text",
+                "text": format!("Muster: {}", muster.team),
+            }
+        },
+        {
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": format!(
+                    ":x: *Need help ({})*: {}\n:white_check_mark: *Safe ({})*: {}\n:hourglass: *Not responded ({})*: {}",
+                    unsafe_,
+                    mentions(responses, Some(false)),
+                    safe,
+                    mentions(responses, Some(true)),
+                    pending,
+                    mentions(responses, None)
+                ),
+            }
+        }
+    ]);
+
+    (text, blocks)
+}
+
+/// Renders the prompt sent to each member
+///
+/// # Arguments
+/// * `muster` - The muster
+fn prompt(muster: &Muster) -> Value {
+    json!([
+        {
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": format!(
+                    "*Are you safe?* A muster of team {} has been started",
+                    muster.team
+                ),
+            }
+        },
+        {
+            "type": "actions",
+            "elements": [
+                {
+                    "type": "button",
+                    "action_id": SAFE_ACTION,
+                    "style": "primary",
+                    "text": { "type": "plain_text", "text": "✅ I'm safe" },
+                    "value": muster.id.to_string(),
+                },
+                {
+                    "type": "button",
+                    "action_id": UNSAFE_ACTION,
+                    "style": "danger",
+                    "text": { "type": "plain_text", "text": "❌ I need help" },
+                    "value": muster.id.to_string(),
+                }
+            ]
+        }
+    ])
+}
+
+/// Posts (or updates) a muster's summary
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `muster` - The muster
+async fn post_summary(db: &mut SqlConn, muster: &mut Muster) -> Result<()> {
+    let responses = muster.responses(&mut *db).await?;
+    let (text, blocks) = summary(muster, &responses);
+
+    match muster.message_ts.clone() {
+        Some(ts) => slack::chat_update(&muster.channel, &ts, &text, blocks).await?,
+        None => {
+            let ts = slack::chat_post_blocks(&muster.channel, &text, blocks).await?;
+            muster.set_message(&mut *db, &ts).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Starts a muster of a team, posting its summary to a channel and asking every member on
+/// Slack whether they're safe, returning the number of members asked
+///
+/// The DMs are sent in the background, as large teams would take longer than Slack waits
+/// for a command's response
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `team` - Team to muster
+/// * `channel` - Channel to post the summary in
+/// * `started_by` - Slack ID of the user starting the muster
+pub async fn start(
+    db: &mut SqlConn,
+    team: &Team,
+    channel: &str,
+    started_by: &str,
+) -> Result<usize> {
+    // guests who aren't on Slack can't be asked
+    let members: Vec<_> = Team::members(&mut *db, &team.name)
+        .await?
+        .into_iter()
+        .filter(|member| SlackUserId::parse(&member.id).is_ok())
+        .collect();
+
+    let mut muster = Muster::start(&mut *db, team, channel, started_by, &members).await?;
+    post_summary(&mut *db, &mut muster).await?;
+
+    // members away from slack are asked through the channel they prefer
+    let mut recipients = vec![];
+    for member in &members {
+        recipients.push(notify::recipient(&mut *db, &member.id).await?);
+    }
+
+    let count = members.len();
+    runtime::spawn(async move {
+        let notification = Notification {
+            subject: format!("Are you safe? Muster of team {}", muster.team),
+            text: format!(
+                "Are you safe? A muster of team {} has been started. Respond in Slack, or let your lead know",
+                muster.team
+            ),
+            blocks: Some(prompt(&muster)),
+        };
+
+        for recipient in recipients {
+            if let Err(e) = notify::send(&recipient, &notification).await {
+                tracing::error!("Failed to ask {} if they're safe: {:?}", recipient.user_id, e);
+            }
+        }
+    });
+
+    Ok(count)
+}
+
+/// Records a member's response to a muster, replacing the prompt they answered and updating
+/// the summary
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `muster_id` - Id of the muster, as sent with the button pressed
+/// * `user_id` - Slack ID of the member
+/// * `safe` - Whether the member is safe
+/// * `container` - The message containing the button pressed
+pub async fn respond(
+    db: &mut SqlConn,
+    muster_id: &str,
+    user_id: &str,
+    safe: bool,
+    container: &Value,
+) -> Result<()> {
+    let muster_id: i64 = muster_id.parse()?;
+    let mut muster = match Muster::fetch(&mut *db, muster_id).await {
+        Some(muster) => muster,
+        None => return Ok(()),
+    };
+
+    if !muster.respond(&mut *db, user_id, safe).await? {
+        tracing::warn!(
+            "{} responded to muster {} but wasn't asked",
+            user_id,
+            muster.id
+        );
+        return Ok(());
+    }
+
+    if let (Some(channel), Some(ts)) = (
+        container["channel_id"].as_str(),
+        container["message_ts"].as_str(),
+    ) {
+        let text = if safe {
+            format!(
+                ":white_check_mark: Thanks, team {} knows you're safe",
+                muster.team
+            )
+        } else {
+            format!(":x: Team {} has been told you need help", muster.team)
+        };
+
+        let blocks = json!([{
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": text }
+        }]);
+
+        slack::chat_update(channel, ts, &text, blocks).await?;
+    }
+
+    post_summary(&mut *db, &mut muster).await
+}
@@ -0,0 +1,356 @@
+//! Delivery of notifications through the channel each user prefers
+//!
+//! Notifications meant to reach people who may be away from Slack (e.g., muster prompts)
+//! are sent through a `Notifier`: a Slack DM, an email over SMTP, or a text message through
+//! Twilio.  Users choose theirs with `/location contact`.  Email goes to the address on the
+//! user's cached Slack profile.
+//!
+//! A notification falls back to a Slack DM if the user's channel isn't configured
+//! (`SMTP_HOST`, or `TWILIO_ACCOUNT_SID`), the user has no address on it, or delivery
+//! through it fails.
+
+use crate::{
+    models::{ContactChannel, ContactPreference, Profile},
+    outbound, slack, SqlConn,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use lettre::{
+    message::MultiPart, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message,
+};
+use once_cell::sync::OnceCell;
+use serde_json::Value;
+
+#[cfg(feature = "rt-async-std")]
+type Executor = lettre::AsyncStd1Executor;
+
+#[cfg(feature = "rt-tokio")]
+type Executor = lettre::Tokio02Executor;
+
+/// Notifiers of the configured channels
+static NOTIFIERS: OnceCell<Notifiers> = OnceCell::new();
+
+/// Mail server notifications are sent through
+#[derive(Clone, Debug)]
+pub struct SmtpConfig {
+    /// Host name of the server
+    pub host: String,
+
+    /// Port of the server (STARTTLS is required)
+    pub port: u16,
+
+    /// User name and password, if the server requires them
+    pub credentials: Option<(String, String)>,
+
+    /// Address emails are sent from (e.g., `StatusBot <statusbot@example.com>`)
+    pub from: String,
+}
+
+/// Twilio account text messages are sent through
+#[derive(Clone, Debug)]
+pub struct TwilioConfig {
+    /// Account SID
+    pub account_sid: String,
+
+    /// Auth token of the account
+    pub auth_token: String,
+
+    /// Number text messages are sent from, in E.164 format
+    pub from: String,
+}
+
+/// Channels configured besides Slack, which is always available
+#[derive(Clone, Debug, Default)]
+pub struct NotifyConfig {
+    /// Mail server, if email is configured
+    pub smtp: Option<SmtpConfig>,
+
+    /// Twilio account, if text messages are configured
+    pub twilio: Option<TwilioConfig>,
+}
+
+/// A notification to deliver
+#[derive(Clone, Debug)]
+pub struct Notification {
+    /// Subject of emails
+    pub subject: String,
+
+    /// Text of the notification, for every channel
+    pub text: String,
+
+    /// Blocks of the Slack message, if it has more than text (e.g., buttons)
+    pub blocks: Option<Value>,
+}
+
+/// Someone to notify, and how to reach them
+#[derive(Clone, Debug)]
+pub struct Recipient {
+    /// Slack ID of the user
+    pub user_id: String,
+
+    /// Channel the user prefers
+    pub channel: ContactChannel,
+
+    /// Email address on the user's Slack profile, if the bot may read it
+    pub email: Option<String>,
+
+    /// Phone number text messages are sent to
+    pub phone: Option<String>,
+}
+
+/// A way of delivering notifications
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Delivers a notification to someone
+    ///
+    /// # Arguments
+    /// * `recipient` - Who to notify
+    /// * `notification` - The notification
+    async fn send(&self, recipient: &Recipient, notification: &Notification) -> Result<()>;
+}
+
+/// Delivers notifications as Slack DMs
+pub struct SlackDm;
+
+#[async_trait]
+impl Notifier for SlackDm {
+    async fn send(&self, recipient: &Recipient, notification: &Notification) -> Result<()> {
+        match &notification.blocks {
+            Some(blocks) => {
+                slack::chat_post_blocks(&recipient.user_id, &notification.text, blocks.clone())
+                    .await?;
+            }
+            None => slack::chat_post_message(&recipient.user_id, &notification.text).await?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Delivers notifications as emails over SMTP
+pub struct SmtpEmail {
+    /// Mail server emails are sent through
+    config: SmtpConfig,
+}
+
+impl SmtpEmail {
+    /// Creates a notifier sending emails through a mail server
+    ///
+    /// # Arguments
+    /// * `config` - The mail server
+    pub fn new(config: SmtpConfig) -> Self {
+        SmtpEmail { config }
+    }
+
+    /// Sends an email, with an HTML part if one is given
+    ///
+    /// # Arguments
+    /// * `to` - Address to send it to
+    /// * `subject` - Subject of the email
+    /// * `text` - Plain text body
+    /// * `html` - HTML body, shown instead of the text by clients that can
+    pub async fn send_email(
+        &self,
+        to: &str,
+        subject: &str,
+        text: &str,
+        html: Option<&str>,
+    ) -> Result<()> {
+        let builder = Message::builder()
+            .from(self.config.from.parse()?)
+            .to(to.parse()?)
+            .subject(subject);
+
+        let email = match html {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+                text.to_owned(),
+                html.to_owned(),
+            ))?,
+            None => builder.body(text.to_owned())?,
+        };
+
+        let mut transport = AsyncSmtpTransport::<Executor>::starttls_relay(&self.config.host)?
+            .port(self.config.port);
+        if let Some((username, password)) = &self.config.credentials {
+            transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        transport.build().send(email).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for SmtpEmail {
+    async fn send(&self, recipient: &Recipient, notification: &Notification) -> Result<()> {
+        let to = recipient
+            .email
+            .as_deref()
+            .ok_or_else(|| anyhow!("{} has no email address", recipient.user_id))?;
+
+        self.send_email(to, &notification.subject, &notification.text, None)
+            .await
+    }
+}
+
+/// Delivers notifications as text messages through Twilio
+pub struct TwilioSms {
+    /// Account text messages are sent through
+    config: TwilioConfig,
+}
+
+impl TwilioSms {
+    /// Creates a notifier sending text messages through a Twilio account
+    ///
+    /// # Arguments
+    /// * `config` - The account
+    pub fn new(config: TwilioConfig) -> Self {
+        TwilioSms { config }
+    }
+}
+
+#[async_trait]
+impl Notifier for TwilioSms {
+    async fn send(&self, recipient: &Recipient, notification: &Notification) -> Result<()> {
+        let to = recipient
+            .phone
+            .as_deref()
+            .ok_or_else(|| anyhow!("{} has no phone number", recipient.user_id))?;
+
+        let body = serde_urlencoded::to_string(&[
+            ("To", to),
+            ("From", &self.config.from),
+            ("Body", &notification.text),
+        ])?;
+
+        let resp = outbound::post(format!(
+            "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+            self.config.account_sid
+        ))
+        .set_header(
+            "Authorization",
+            format!(
+                "Basic {}",
+                base64::encode(format!(
+                    "{}:{}",
+                    self.config.account_sid, self.config.auth_token
+                ))
+            ),
+        )
+        .set_header("Content-Type", "application/x-www-form-urlencoded")
+        .body_string(body)
+        .send()
+        .await?;
+
+        let code = resp.status();
+        if code.is_client_error() || code.is_server_error() {
+            return Err(anyhow!("twilio request failed: HTTP {}", code));
+        }
+
+        Ok(())
+    }
+}
+
+/// Notifiers of every channel, `None` if a channel isn't configured
+struct Notifiers {
+    /// Email notifier
+    email: Option<SmtpEmail>,
+
+    /// Text message notifier
+    sms: Option<TwilioSms>,
+}
+
+/// Configures the channels notifications can be delivered through
+///
+/// Only the first call has an effect
+///
+/// # Arguments
+/// * `config` - Channels configured besides Slack
+pub fn configure(config: NotifyConfig) {
+    NOTIFIERS
+        .set(Notifiers {
+            email: config.smtp.map(SmtpEmail::new),
+            sms: config.twilio.map(TwilioSms::new),
+        })
+        .ok();
+}
+
+/// Returns the notifiers of every channel
+fn notifiers() -> &'static Notifiers {
+    NOTIFIERS.get_or_init(|| Notifiers {
+        email: None,
+        sms: None,
+    })
+}
+
+/// Returns the email notifier, if email is configured
+pub(crate) fn email() -> Option<&'static SmtpEmail> {
+    notifiers().email.as_ref()
+}
+
+/// Returns true if notifications can be delivered through a channel
+///
+/// # Arguments
+/// * `channel` - The channel
+pub fn is_configured(channel: ContactChannel) -> bool {
+    match channel {
+        ContactChannel::Slack => true,
+        ContactChannel::Email => notifiers().email.is_some(),
+        ContactChannel::Sms => notifiers().sms.is_some(),
+    }
+}
+
+/// Looks up how to reach a user
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `user_id` - Slack ID of the user
+pub async fn recipient(db: &mut SqlConn, user_id: &str) -> Result<Recipient> {
+    let preference = ContactPreference::fetch(&mut *db, user_id).await?;
+    let email = match preference.as_ref().map(ContactPreference::channel) {
+        Some(ContactChannel::Email) => Profile::fetch(&mut *db, user_id)
+            .await
+            .and_then(|profile| profile.email),
+        _ => None,
+    };
+
+    Ok(Recipient {
+        user_id: user_id.to_owned(),
+        channel: preference
+            .as_ref()
+            .map(ContactPreference::channel)
+            .unwrap_or(ContactChannel::Slack),
+        email,
+        phone: preference.and_then(|preference| preference.phone),
+    })
+}
+
+/// Delivers a notification through the channel its recipient prefers, falling back to a
+/// Slack DM
+///
+/// # Arguments
+/// * `recipient` - Who to notify
+/// * `notification` - The notification
+pub async fn send(recipient: &Recipient, notification: &Notification) -> Result<()> {
+    let notifiers = notifiers();
+    let notifier: Option<&dyn Notifier> = match recipient.channel {
+        ContactChannel::Slack => None,
+        ContactChannel::Email => notifiers.email.as_ref().map(|n| n as &dyn Notifier),
+        ContactChannel::Sms => notifiers.sms.as_ref().map(|n| n as &dyn Notifier),
+    };
+
+    if let Some(notifier) = notifier {
+        match notifier.send(recipient, notification).await {
+            Ok(()) => return Ok(()),
+            Err(e) => tracing::warn!(
+                "failed to notify {} by {}, sending a slack dm instead: {:?}",
+                recipient.user_id,
+                recipient.channel.as_str(),
+                e
+            ),
+        }
+    }
+
+    SlackDm.send(recipient, notification).await
+}
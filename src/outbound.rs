@@ -0,0 +1,508 @@
+//! Outbound HTTP, through one client configured for the deployment's egress
+//!
+//! Every request the bot makes, to Slack and to integrations (calendars, Outlook, HR, Jira,
+//! and Google Sheets), is built with `get` or `post` and sent by the installed `HttpClient`,
+//! so the egress configuration applies to all of them:
+//!
+//! * `OUTBOUND_BACKEND` - client that sends requests: `surf` (the default), or `reqwest`
+//!   (feature `reqwest-client`, which needs the tokio runtime)
+//! * `OUTBOUND_PROXY` - proxy to send requests through (`http://`, `https://`, or
+//!   `socks5://`), with credentials in the URL if it requires authentication
+//! * `OUTBOUND_CLIENT_CERT` and `OUTBOUND_CLIENT_KEY` - PEM certificate and private key
+//!   presented to servers (or proxies) that require mutual TLS
+//! * `OUTBOUND_CA_CERT` - PEM bundle of certificate authorities to trust instead of the
+//!   system's, e.g. for a proxy that intercepts TLS
+//!
+//! The client is built by `init`, which fails if the configuration is invalid, so the bot
+//! never quietly bypasses the proxy.  Tests (and embedders) can `install` their own client
+//! instead, e.g. a `RecordingClient` to assert on the requests that would have been sent.
+//! Until either is called, requests use a surf client with default settings.
+//!
+//! Requests to a host that keeps failing are cut off by its circuit breaker (see `breaker`).
+
+use crate::{breaker, signing};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+use tide::http::{Body, Method, Request, Response, StatusCode, Url};
+
+/// Client every outbound request is sent with, once configured
+static CLIENT: once_cell::sync::OnceCell<Arc<dyn HttpClient>> = once_cell::sync::OnceCell::new();
+
+/// Sends outbound HTTP requests
+#[async_trait]
+pub trait HttpClient: fmt::Debug + Send + Sync + 'static {
+    /// Sends a request, returning the response once its headers are received
+    ///
+    /// # Arguments
+    /// * `req` - The request to send
+    async fn send(&self, req: Request) -> Result<Response>;
+}
+
+/// Clients outbound requests can be sent with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    /// surf, over libcurl
+    Surf,
+
+    /// reqwest, over hyper (feature `reqwest-client`)
+    Reqwest,
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::Surf
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "surf" => Ok(Backend::Surf),
+            "reqwest" => Ok(Backend::Reqwest),
+            _ => Err(format!("unknown outbound backend `{}`, expected surf or reqwest", s)),
+        }
+    }
+}
+
+/// How outbound requests leave the deployment
+#[derive(Clone, Debug, Default)]
+pub struct OutboundConfig {
+    /// Client requests are sent with
+    pub backend: Backend,
+
+    /// Proxy to send requests through, if any
+    pub proxy: Option<String>,
+
+    /// Certificate (PEM) to present to servers, if any
+    pub client_cert: Option<PathBuf>,
+
+    /// Private key (PEM) of the client certificate
+    pub client_key: Option<PathBuf>,
+
+    /// Certificate authorities (PEM) to trust instead of the system's, if any
+    pub ca_cert: Option<PathBuf>,
+}
+
+impl OutboundConfig {
+    /// Returns the client certificate and its private key, if configured
+    ///
+    /// Fails if only one of them is configured
+    fn identity(&self) -> Result<Option<(&PathBuf, &PathBuf)>> {
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => Ok(Some((cert, key))),
+            (None, None) => Ok(None),
+            _ => Err(anyhow!(
+                "OUTBOUND_CLIENT_CERT and OUTBOUND_CLIENT_KEY must be set together"
+            )),
+        }
+    }
+}
+
+/// Sends requests with surf
+#[derive(Debug)]
+pub struct SurfClient {
+    client: surf::Client,
+}
+
+impl SurfClient {
+    /// Builds a client matching the egress configuration
+    ///
+    /// # Arguments
+    /// * `config` - How outbound requests leave the deployment
+    pub fn new(config: &OutboundConfig) -> Result<Self> {
+        use isahc::{
+            auth::Credentials,
+            config::{CaCertificate, ClientCertificate, Configurable, PrivateKey},
+        };
+
+        let mut builder = isahc::HttpClient::builder();
+
+        if let Some(proxy) = &config.proxy {
+            let url = Url::parse(proxy).context("invalid OUTBOUND_PROXY")?;
+            if !url.username().is_empty() {
+                builder = builder.proxy_credentials(Credentials::new(
+                    url.username(),
+                    url.password().unwrap_or_default(),
+                ));
+            }
+
+            // credentials are passed separately, so they never appear in the proxy's uri
+            let mut uri = url.clone();
+            uri.set_username("").ok();
+            uri.set_password(None).ok();
+            builder = builder.proxy(Some(uri.as_str().parse().context("invalid OUTBOUND_PROXY")?));
+        }
+
+        if let Some((cert, key)) = config.identity()? {
+            builder = builder.ssl_client_certificate(ClientCertificate::pem_file(
+                cert,
+                PrivateKey::pem_file(key, None),
+            ));
+        }
+
+        if let Some(ca_cert) = &config.ca_cert {
+            builder = builder.ssl_ca_certificate(CaCertificate::file(ca_cert));
+        }
+
+        let client = builder.build().context("failed to build outbound http client")?;
+        Ok(SurfClient {
+            client: surf::Client::with_http_client(
+                http_client::isahc::IsahcClient::from_client(client),
+            ),
+        })
+    }
+}
+
+#[async_trait]
+impl HttpClient for SurfClient {
+    async fn send(&self, req: Request) -> Result<Response> {
+        let resp = self
+            .client
+            .send(surf::Request::from(req))
+            .await
+            .map_err(|e| anyhow!("{}", e))?;
+
+        Ok(resp.into())
+    }
+}
+
+/// Sends requests with reqwest
+#[cfg(feature = "reqwest-client")]
+#[derive(Debug)]
+pub struct ReqwestClient {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest-client")]
+impl ReqwestClient {
+    /// Builds a client matching the egress configuration
+    ///
+    /// # Arguments
+    /// * `config` - How outbound requests leave the deployment
+    pub fn new(config: &OutboundConfig) -> Result<Self> {
+        let mut builder = reqwest::Client::builder();
+
+        // reqwest reads proxy credentials from the url itself
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy).context("invalid OUTBOUND_PROXY")?);
+        }
+
+        if let Some((cert, key)) = config.identity()? {
+            let mut pem = std::fs::read(cert)
+                .with_context(|| format!("failed to read {}", cert.display()))?;
+            pem.extend(
+                std::fs::read(key).with_context(|| format!("failed to read {}", key.display()))?,
+            );
+            builder = builder.identity(
+                reqwest::Identity::from_pem(&pem).context("invalid OUTBOUND_CLIENT_CERT")?,
+            );
+        }
+
+        if let Some(ca_cert) = &config.ca_cert {
+            let pem = std::fs::read(ca_cert)
+                .with_context(|| format!("failed to read {}", ca_cert.display()))?;
+            builder = builder.tls_built_in_root_certs(false).add_root_certificate(
+                reqwest::Certificate::from_pem(&pem).context("invalid OUTBOUND_CA_CERT")?,
+            );
+        }
+
+        let client = builder.build().context("failed to build outbound http client")?;
+        Ok(ReqwestClient { client })
+    }
+}
+
+#[cfg(feature = "reqwest-client")]
+#[async_trait]
+impl HttpClient for ReqwestClient {
+    async fn send(&self, mut req: Request) -> Result<Response> {
+        let method = reqwest::Method::from_bytes(req.method().to_string().as_bytes())?;
+        let mut builder = self.client.request(method, req.url().as_str());
+        for (name, values) in req.iter() {
+            for value in values.iter() {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+        }
+
+        let body = req.body_bytes().await.map_err(|e| anyhow!("{}", e))?;
+        let resp = builder.body(body).send().await?;
+
+        let mut out = Response::new(resp.status().as_u16());
+        let headers = resp.headers().clone();
+        out.set_body(resp.bytes().await?.to_vec());
+
+        // headers are copied after the body, which would otherwise reset `Content-Type`
+        for name in headers.keys() {
+            let values: Vec<&str> = headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .collect();
+            out.insert_header(name.as_str(), values.join(", "));
+        }
+
+        Ok(out)
+    }
+}
+
+/// A request sent to a `RecordingClient`
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedRequest {
+    /// HTTP method
+    pub method: Method,
+
+    /// Where the request was sent
+    pub url: Url,
+
+    /// Headers of the request, in the order they were set
+    pub headers: Vec<(String, String)>,
+
+    /// Body of the request
+    pub body: String,
+}
+
+/// Client that records requests instead of sending them, for tests
+///
+/// Responds to each request with the next queued response, or `200 OK` with
+/// `{"ok": true}` (what Slack's API returns on success) once the queue is empty
+#[derive(Clone, Debug, Default)]
+pub struct RecordingClient {
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    responses: Arc<Mutex<VecDeque<(StatusCode, String)>>>,
+}
+
+impl RecordingClient {
+    /// Creates a client that hasn't recorded any requests
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a response, returned after any already queued
+    ///
+    /// # Arguments
+    /// * `status` - Status code of the response
+    /// * `body` - Body of the response
+    pub fn respond_with(&self, status: StatusCode, body: impl Into<String>) {
+        self.responses.lock().unwrap().push_back((status, body.into()));
+    }
+
+    /// Returns the requests sent so far, oldest first
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl HttpClient for RecordingClient {
+    async fn send(&self, mut req: Request) -> Result<Response> {
+        let headers = req
+            .iter()
+            .flat_map(|(name, values)| {
+                values
+                    .iter()
+                    .map(|value| (name.to_string(), value.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let body = req.body_string().await.map_err(|e| anyhow!("{}", e))?;
+        self.requests.lock().unwrap().push(RecordedRequest {
+            method: req.method(),
+            url: req.url().clone(),
+            headers,
+            body,
+        });
+
+        let (status, body) = self
+            .responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| (StatusCode::Ok, r#"{"ok":true}"#.to_owned()));
+
+        let mut resp = Response::new(status);
+        resp.set_body(body);
+        resp.insert_header("Content-Type", "application/json");
+        Ok(resp)
+    }
+}
+
+/// Configures the client every outbound request is sent with
+///
+/// Fails if the configuration is invalid, or a client was already installed
+///
+/// # Arguments
+/// * `config` - How outbound requests leave the deployment
+pub fn init(config: &OutboundConfig) -> Result<()> {
+    let client: Arc<dyn HttpClient> = match config.backend {
+        Backend::Surf => Arc::new(SurfClient::new(config)?),
+
+        #[cfg(feature = "reqwest-client")]
+        Backend::Reqwest => Arc::new(ReqwestClient::new(config)?),
+
+        #[cfg(not(feature = "reqwest-client"))]
+        Backend::Reqwest => {
+            anyhow::bail!("the reqwest backend requires the `reqwest-client` feature")
+        }
+    };
+
+    install(client)?;
+
+    if let Some(proxy) = &config.proxy {
+        let host = Url::parse(proxy)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_owned))
+            .unwrap_or_default();
+        tracing::info!("outbound requests are sent through the proxy at {}", host);
+    }
+
+    Ok(())
+}
+
+/// Installs the client every outbound request is sent with
+///
+/// Fails if a client was already installed
+///
+/// # Arguments
+/// * `client` - The client
+pub fn install(client: Arc<dyn HttpClient>) -> Result<()> {
+    CLIENT
+        .set(client)
+        .map_err(|_| anyhow!("outbound http client is already configured"))
+}
+
+/// Returns true if a client was installed, e.g. by an embedder before starting the bot
+pub(crate) fn installed() -> bool {
+    CLIENT.get().is_some()
+}
+
+/// Returns the client outbound requests are sent with
+fn client() -> &'static Arc<dyn HttpClient> {
+    CLIENT.get_or_init(|| {
+        Arc::new(SurfClient {
+            client: surf::Client::new(),
+        })
+    })
+}
+
+/// An outbound request being built
+#[derive(Debug)]
+pub struct RequestBuilder {
+    req: Result<Request>,
+}
+
+impl RequestBuilder {
+    /// Starts building a request
+    ///
+    /// # Arguments
+    /// * `method` - HTTP method
+    /// * `url` - Where to send the request
+    fn new(method: Method, url: &str) -> Self {
+        let req = Url::parse(url)
+            .map(|url| Request::new(method, url))
+            .with_context(|| format!("invalid url `{}`", url));
+
+        RequestBuilder { req }
+    }
+
+    /// Sets a header, replacing any previous value
+    ///
+    /// # Arguments
+    /// * `name` - Name of the header
+    /// * `value` - Value of the header
+    pub fn set_header(mut self, name: &'static str, value: impl AsRef<str>) -> Self {
+        if let Ok(req) = &mut self.req {
+            req.insert_header(name, value.as_ref());
+        }
+        self
+    }
+
+    /// Sets the body to a value serialized as JSON
+    ///
+    /// # Arguments
+    /// * `body` - The value
+    pub fn body_json(mut self, body: &impl Serialize) -> Result<Self> {
+        if let Ok(req) = &mut self.req {
+            req.set_body(Body::from_json(body).map_err(|e| anyhow!("{}", e))?);
+        }
+        Ok(self)
+    }
+
+    /// Sets the body to a string, keeping any `Content-Type` already set
+    ///
+    /// # Arguments
+    /// * `body` - The body
+    pub fn body_string(mut self, body: String) -> Self {
+        if let Ok(req) = &mut self.req {
+            let content_type = req
+                .header("Content-Type")
+                .map(|values| values.last().as_str().to_owned());
+            req.set_body(Body::from_string(body));
+            if let Some(content_type) = content_type {
+                req.insert_header("Content-Type", content_type);
+            }
+        }
+        self
+    }
+
+    /// Sets the body to a webhook payload serialized as JSON, signed with the webhook's
+    /// secret (see `signing::verify_webhook`)
+    ///
+    /// # Arguments
+    /// * `secret` - The webhook's secret
+    /// * `payload` - The payload
+    pub fn body_signed(self, secret: &str, payload: &impl Serialize) -> Result<Self> {
+        let body = serde_json::to_string(payload)?;
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let signature = signing::sign_webhook(secret, &timestamp, body.as_bytes());
+
+        Ok(self
+            .set_header("Content-Type", "application/json")
+            .set_header(signing::WEBHOOK_TIMESTAMP_HEADER, timestamp)
+            .set_header(signing::WEBHOOK_SIGNATURE_HEADER, signature)
+            .body_string(body))
+    }
+
+    /// Sends the request with the installed client, unless the breaker of its host is open
+    pub async fn send(self) -> Result<Response> {
+        let req = self.req?;
+        let host = req.url().host_str().unwrap_or_default().to_owned();
+        breaker::check(&host)?;
+
+        let resp = client().send(req).await;
+        let ok = match &resp {
+            Ok(resp) => {
+                !resp.status().is_server_error() && resp.status() != StatusCode::TooManyRequests
+            }
+            Err(_) => false,
+        };
+        breaker::record(&host, ok);
+
+        resp
+    }
+}
+
+/// Starts building a `GET` request
+///
+/// # Arguments
+/// * `url` - Where to send the request
+pub fn get(url: impl AsRef<str>) -> RequestBuilder {
+    RequestBuilder::new(Method::Get, url.as_ref())
+}
+
+/// Starts building a `POST` request
+///
+/// # Arguments
+/// * `url` - Where to send the request
+pub fn post(url: impl AsRef<str>) -> RequestBuilder {
+    RequestBuilder::new(Method::Post, url.as_ref())
+}
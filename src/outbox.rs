@@ -0,0 +1,164 @@
+//! Delivery of side effects written alongside status changes
+//!
+//! Slack side effects of a status change (notifications of bound channels, and the reaction
+//! acknowledging a mention) are written to the `outbox` table in the same transaction as the
+//! change, instead of being sent while the change is saved.  A change is therefore never
+//! saved without its side effects, nor its side effects sent for a change that rolled back,
+//! and side effects pending when the bot stops are delivered once it starts again.
+//!
+//! A worker delivers due entries every `POLL_INTERVAL`.  Entries are claimed before delivery,
+//! so replicas sharing a database don't deliver them twice, and failed deliveries are retried
+//! with exponential backoff, up to `MAX_ATTEMPTS` times.  Delivery is at least once: an entry
+//! delivered just before the bot stops may be delivered again.
+
+use crate::{models::OutboxEntry, runtime, slack, SqlConn, SqlPool};
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How often due entries are delivered
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Maximum number of entries delivered per poll
+const BATCH_SIZE: i64 = 50;
+
+/// How long a claimed entry is left to its worker before others may deliver it, in seconds
+const CLAIM_SECS: i64 = 60;
+
+/// Failed deliveries after which an entry is dropped
+const MAX_ATTEMPTS: i64 = 10;
+
+/// Longest wait between attempts, in seconds
+const MAX_BACKOFF_SECS: i64 = 3600;
+
+/// A side effect to deliver
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Effect {
+    /// Post a message to a channel
+    PostMessage { channel: String, text: String },
+
+    /// React to a message
+    AddReaction {
+        channel: String,
+        name: String,
+        timestamp: String,
+    },
+
+    /// Post a message with blocks (e.g., buttons) to a channel
+    PostBlocks {
+        channel: String,
+        text: String,
+        blocks: Value,
+    },
+}
+
+impl Effect {
+    /// Sends this side effect to Slack
+    async fn deliver(&self) -> Result<()> {
+        match self {
+            Effect::PostMessage { channel, text } => slack::chat_post_message(channel, text).await,
+            Effect::AddReaction {
+                channel,
+                name,
+                timestamp,
+            } => slack::reactions_add(channel, name, timestamp).await,
+            Effect::PostBlocks {
+                channel,
+                text,
+                blocks,
+            } => slack::chat_post_blocks(channel, text, blocks.clone())
+                .await
+                .map(|_| ()),
+        }
+    }
+}
+
+/// Adds a side effect to the outbox
+///
+/// Call this inside the transaction that makes the change the side effect belongs to, so it's
+/// only delivered if the change is committed
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `effect` - The side effect
+pub async fn enqueue(db: &mut SqlConn, effect: &Effect) -> Result<()> {
+    OutboxEntry::insert(db, &serde_json::to_string(effect)?).await
+}
+
+/// Delivers the entries that are due, returning the number delivered
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+pub async fn deliver_due(pool: &SqlPool) -> Result<usize> {
+    let mut db = pool.acquire().await?;
+    let now = Utc::now();
+
+    let mut delivered = 0;
+    for entry in OutboxEntry::fetch_due(&mut db, now, BATCH_SIZE).await? {
+        if !entry
+            .claim(&mut db, now, now + Duration::seconds(CLAIM_SECS))
+            .await?
+        {
+            continue;
+        }
+
+        let effect: Effect = match serde_json::from_str(&entry.effect) {
+            Ok(effect) => effect,
+            Err(e) => {
+                tracing::error!("dropping invalid outbox entry {}: {}", entry.id, e);
+                entry.delete(&mut db).await?;
+                continue;
+            }
+        };
+
+        match effect.deliver().await {
+            Ok(()) => {
+                entry.delete(&mut db).await?;
+                delivered += 1;
+            }
+            Err(e) if entry.attempts + 1 >= MAX_ATTEMPTS => {
+                tracing::error!(
+                    "giving up on {:?} after {} attempts: {:?}",
+                    effect,
+                    MAX_ATTEMPTS,
+                    e
+                );
+                entry.delete(&mut db).await?;
+            }
+            Err(e) => {
+                let backoff = 2i64.pow(entry.attempts as u32).min(MAX_BACKOFF_SECS);
+                tracing::warn!(
+                    "failed to deliver {:?}, retrying in {}s: {:?}",
+                    effect,
+                    backoff,
+                    e
+                );
+                entry
+                    .retry(&mut db, Utc::now() + Duration::seconds(backoff))
+                    .await?;
+            }
+        }
+    }
+
+    Ok(delivered)
+}
+
+/// Spawns the worker that delivers the outbox
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+pub fn spawn(pool: SqlPool) {
+    runtime::spawn(async move {
+        loop {
+            match deliver_due(&pool).await {
+                Ok(0) => (),
+                Ok(count) => tracing::debug!("delivered {} side effects", count),
+                Err(e) => tracing::error!("failed to deliver the outbox: {:?}", e),
+            }
+
+            runtime::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
@@ -0,0 +1,202 @@
+//! Mirrors Outlook automatic replies (out of office) as leave
+//!
+//! Enabled by setting the `GRAPH_*` variables to an Azure AD app registration granted the
+//! `MailboxSettings.Read` application permission.  Periodically, the automatic-reply
+//! settings of every user with a cached email address are read from Microsoft Graph, and
+//! scheduled (or always-on) automatic replies are recorded as leave, so orgs that set OOO
+//! in Outlook first don't have to set it twice.
+
+use crate::{
+    models::{Leave, Profile},
+    outbound, runtime, SqlPool,
+};
+use anyhow::{anyhow, Result};
+use chrono::{Duration, NaiveDate, Utc};
+use serde_json::Value;
+use std::time::Duration as StdDuration;
+
+/// Microsoft Graph credentials, and how often to read automatic replies
+#[derive(Clone, Debug)]
+pub struct OutlookConfig {
+    /// Azure AD tenant (directory) id
+    pub tenant_id: String,
+
+    /// Application (client) id of the app registration
+    pub client_id: String,
+
+    /// Client secret of the app registration
+    pub client_secret: String,
+
+    /// Time between syncs
+    pub interval: StdDuration,
+}
+
+/// Requests an app-only access token for Microsoft Graph
+///
+/// # Arguments
+/// * `config` - Graph credentials
+async fn access_token(config: &OutlookConfig) -> Result<String> {
+    let form = serde_urlencoded::to_string(&[
+        ("client_id", config.client_id.as_str()),
+        ("client_secret", config.client_secret.as_str()),
+        ("scope", "https://graph.microsoft.com/.default"),
+        ("grant_type", "client_credentials"),
+    ])?;
+
+    let mut resp = outbound::post(format!(
+        "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+        config.tenant_id
+    ))
+    .set_header("Content-Type", "application/x-www-form-urlencoded")
+    .body_string(form)
+    .send()
+    .await
+    .map_err(|e| anyhow!("token request failed: {}", e))?;
+
+    let body: Value = resp
+        .body_json()
+        .await
+        .map_err(|e| anyhow!("token response invalid: {}", e))?;
+
+    match body["access_token"].as_str() {
+        Some(token) => Ok(token.to_owned()),
+        None => Err(anyhow!("token request failed: {}", body)),
+    }
+}
+
+/// Parses the day of a Graph `dateTimeTimeZone` (e.g., `2020-10-19T00:00:00.0000000`),
+/// and whether it falls exactly at midnight
+///
+/// # Arguments
+/// * `value` - The `dateTimeTimeZone` object
+fn parse_day(value: &Value) -> Option<(NaiveDate, bool)> {
+    let date_time = value["dateTime"].as_str()?;
+    let day = NaiveDate::parse_from_str(date_time.get(..10)?, "%Y-%m-%d").ok()?;
+    let midnight = date_time
+        .get(11..19)
+        .map_or(true, |time| time == "00:00:00");
+
+    Some((day, midnight))
+}
+
+/// Reads a user's automatic-reply settings, returning the days (inclusive) they're on if
+/// automatic replies are on
+///
+/// Always-on automatic replies count for today only, so they're picked up again each day
+/// until they're turned off
+///
+/// # Arguments
+/// * `token` - Graph access token
+/// * `email` - Email address (user principal name) of the user
+async fn automatic_replies(token: &str, email: &str) -> Result<Option<(NaiveDate, NaiveDate)>> {
+    let mut resp = outbound::get(format!(
+        "https://graph.microsoft.com/v1.0/users/{}/mailboxSettings/automaticRepliesSetting",
+        email
+    ))
+    .set_header("Authorization", format!("Bearer {}", token))
+    .send()
+    .await
+    .map_err(|e| anyhow!("automatic replies request failed: {}", e))?;
+
+    let code = resp.status();
+    if code.is_client_error() || code.is_server_error() {
+        return Err(anyhow!("automatic replies request failed: HTTP {}", code));
+    }
+
+    let body: Value = resp
+        .body_json()
+        .await
+        .map_err(|e| anyhow!("automatic replies response invalid: {}", e))?;
+
+    let today = Utc::now().date().naive_utc();
+    let days = match body["status"].as_str() {
+        Some("alwaysEnabled") => Some((today, today)),
+        Some("scheduled") => {
+            match (
+                parse_day(&body["scheduledStartDateTime"]),
+                parse_day(&body["scheduledEndDateTime"]),
+            ) {
+                // replies ending at midnight end on the day before
+                (Some((starts_on, _)), Some((ends, true))) if ends > starts_on => {
+                    Some((starts_on, ends - Duration::days(1)))
+                }
+                (Some((starts_on, _)), Some((ends, _))) if ends >= starts_on => {
+                    Some((starts_on, ends))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    Ok(days)
+}
+
+/// Records the automatic replies of every user with a cached email address as leave,
+/// returning the number of leave entries added
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `config` - Graph credentials
+pub async fn sync(pool: &SqlPool, config: &OutlookConfig) -> Result<usize> {
+    let mut db = pool.acquire().await?;
+    let token = access_token(config).await?;
+    let today = Utc::now().date().naive_utc();
+
+    let mut added = 0;
+    for profile in Profile::fetch_all(&mut db).await? {
+        let email = match (&profile.email, profile.deleted) {
+            (Some(email), false) => email,
+            _ => continue,
+        };
+
+        let (starts_on, ends_on) = match automatic_replies(&token, email).await {
+            Ok(Some(days)) => days,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!("failed to read automatic replies of {}: {:?}", email, e);
+                continue;
+            }
+        };
+
+        if ends_on < today {
+            continue;
+        }
+
+        // the same automatic replies always map to the same uid
+        let uid = format!("outlook:{}:{}", starts_on, ends_on);
+        let new = Leave::add(
+            &mut db,
+            &profile.user_id,
+            starts_on,
+            ends_on,
+            Leave::OUTLOOK,
+            Some(&uid),
+        )
+        .await?;
+
+        if new {
+            added += 1;
+        }
+    }
+
+    Ok(added)
+}
+
+/// Spawns a task that syncs automatic replies periodically, starting immediately
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `config` - Graph credentials
+pub fn spawn(pool: SqlPool, config: OutlookConfig) {
+    runtime::spawn(async move {
+        loop {
+            match sync(&pool, &config).await {
+                Ok(count) => tracing::info!("imported {} leave entries from outlook", count),
+                Err(e) => tracing::error!("failed to sync outlook automatic replies: {:?}", e),
+            }
+
+            runtime::sleep(config.interval).await;
+        }
+    });
+}
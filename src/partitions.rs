@@ -0,0 +1,410 @@
+//! Monthly partitioning of status history
+//!
+//! On Postgres, `status_history` is partitioned by month (see the `history_partitions`
+//! migration).  Once a day, partitions are created for this month and next, moving any of
+//! their rows out of the default partition; if `HISTORY_RETENTION_MONTHS` is set, partitions
+//! of older months are detached and dropped whole, instead of deleting their rows.
+//!
+//! SQLite can't partition tables, so instead months older than the retention period are moved
+//! to an archive database per month (`status_history-YYYY-MM.sqlite3` in
+//! `HISTORY_ARCHIVE_DIR`), which can be attached to query them.
+//!
+//! Teams with a retention override (set through the admin API) keep their members' history
+//! longer: their rows are moved back into the default partition before a partition is dropped,
+//! and left out of archives, until they're older than the longest override of the member's
+//! teams.  Overrides shorter than `HISTORY_RETENTION_MONTHS` have no effect.
+//!
+//! Partition and archive names are only known at runtime, so these queries aren't checked at
+//! compile time.
+
+use crate::{runtime, SqlConn, SqlPool};
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate, Utc};
+use sqlx::{Connection, Row};
+use std::time::Duration;
+
+#[cfg(feature = "postgres")]
+use chrono::{DateTime, TimeZone};
+
+#[cfg(feature = "sqlite")]
+use std::path::PathBuf;
+
+/// Time between runs
+const INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// Members of teams whose retention override keeps the history of a month that's `$1`
+/// months before the current one
+const EXEMPT_USERS: &str = "SELECT members.user_id
+    FROM members
+    JOIN retention_overrides ON retention_overrides.team_id = members.team_id
+    WHERE retention_overrides.months = 0 OR retention_overrides.months >= $1";
+
+/// Configuration for partitioning status history
+#[derive(Clone, Debug)]
+pub struct PartitionConfig {
+    /// Number of months of history to keep before the current month (0 keeps everything)
+    pub retention_months: u32,
+
+    /// Directory monthly archives are written to
+    #[cfg(feature = "sqlite")]
+    pub archive_dir: PathBuf,
+}
+
+/// Returns the first day of the month some number of months away from another
+///
+/// # Arguments
+/// * `month` - First day of the month to start from
+/// * `offset` - Number of months to move forward (or backward, if negative)
+fn add_months(month: NaiveDate, offset: i32) -> NaiveDate {
+    let months = month.year() * 12 + month.month0() as i32 + offset;
+    NaiveDate::from_ymd(months.div_euclid(12), months.rem_euclid(12) as u32 + 1, 1)
+}
+
+/// Returns the first day of the current month (UTC)
+fn this_month() -> NaiveDate {
+    let today = Utc::today().naive_utc();
+    NaiveDate::from_ymd(today.year(), today.month(), 1)
+}
+
+/// Returns the number of months between a month and the current month
+///
+/// # Arguments
+/// * `month` - First day of the month
+fn months_ago(month: NaiveDate) -> i64 {
+    let this_month = this_month();
+    i64::from(this_month.year() * 12 + this_month.month0() as i32)
+        - i64::from(month.year() * 12 + month.month0() as i32)
+}
+
+/// Returns the first day of the oldest month kept, or `None` if everything is kept
+///
+/// # Arguments
+/// * `retention_months` - Number of months to keep before the current month
+fn oldest_kept(retention_months: u32) -> Option<NaiveDate> {
+    match retention_months {
+        0 => None,
+        months => Some(add_months(this_month(), -(months as i32))),
+    }
+}
+
+/// Returns the name of a month's partition (e.g., `status_history_2020_10`)
+///
+/// # Arguments
+/// * `month` - First day of the month
+#[cfg(feature = "postgres")]
+fn partition_name(month: NaiveDate) -> String {
+    format!("status_history_{}", month.format("%Y_%m"))
+}
+
+/// Returns the start of a month, as stored in the database
+///
+/// # Arguments
+/// * `month` - First day of the month
+#[cfg(feature = "postgres")]
+fn month_start(month: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_date(&month).and_hms(0, 0, 0)
+}
+
+/// Returns the monthly partitions of status history, with the month each holds
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+#[cfg(feature = "postgres")]
+async fn partitions(db: &mut SqlConn) -> Result<Vec<(String, NaiveDate)>> {
+    let rows = sqlx::query(
+        "SELECT child.relname AS name
+        FROM pg_inherits
+        JOIN pg_class parent ON parent.oid = pg_inherits.inhparent
+        JOIN pg_class child ON child.oid = pg_inherits.inhrelid
+        WHERE parent.relname = 'status_history'",
+    )
+    .fetch_all(&mut *db)
+    .await?;
+
+    let mut partitions = vec![];
+    for row in rows {
+        let name: String = row.try_get("name")?;
+
+        // the default partition doesn't hold a month
+        let month = name
+            .strip_prefix("status_history_")
+            .and_then(|month| NaiveDate::parse_from_str(&format!("{}_01", month), "%Y_%m_%d").ok());
+
+        if let Some(month) = month {
+            partitions.push((name, month));
+        }
+    }
+
+    Ok(partitions)
+}
+
+/// Creates a month's partition, moving its rows out of the default partition, unless it
+/// already exists
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `month` - First day of the month
+#[cfg(feature = "postgres")]
+async fn create_partition(db: &mut SqlConn, month: NaiveDate) -> Result<()> {
+    if partitions(db).await?.iter().any(|(_, m)| *m == month) {
+        return Ok(());
+    }
+
+    let name = partition_name(month);
+    let from = month_start(month);
+    let to = month_start(add_months(month, 1));
+
+    // a partition can't be attached while the default partition holds any of its rows
+    let mut tx = db.begin().await?;
+    sqlx::query(&format!(
+        "CREATE TABLE {} (LIKE status_history INCLUDING DEFAULTS)",
+        name
+    ))
+    .execute(&mut tx)
+    .await?;
+
+    let moved = sqlx::query(&format!(
+        "WITH moved AS (
+            DELETE FROM status_history_default
+            WHERE created_at >= $1 AND created_at < $2
+            RETURNING *
+        )
+        INSERT INTO {} SELECT * FROM moved",
+        name
+    ))
+    .bind(from)
+    .bind(to)
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query(&format!(
+        "ALTER TABLE status_history ATTACH PARTITION {} FOR VALUES FROM ('{}') TO ('{}')",
+        name,
+        from.to_rfc3339(),
+        to.to_rfc3339()
+    ))
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    tracing::info!("created partition {} ({} rows moved)", name, moved);
+    Ok(())
+}
+
+/// Creates partitions for this month and next, then drops those older than the retention
+/// period, keeping the history of teams with a longer retention override
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `config` - Partitioning configuration
+#[cfg(feature = "postgres")]
+async fn maintain(pool: &SqlPool, config: &PartitionConfig) -> Result<()> {
+    let mut db = pool.acquire().await?;
+
+    let month = this_month();
+    create_partition(&mut db, month).await?;
+    create_partition(&mut db, add_months(month, 1)).await?;
+
+    let oldest = match oldest_kept(config.retention_months) {
+        Some(oldest) => oldest,
+        None => return Ok(()),
+    };
+
+    for (name, month) in partitions(&mut db).await? {
+        if month < oldest {
+            let mut tx = db.begin().await?;
+            sqlx::query(&format!(
+                "ALTER TABLE status_history DETACH PARTITION {}",
+                name
+            ))
+            .execute(&mut tx)
+            .await?;
+
+            // once detached, the month's rows that are still kept land in the default partition
+            let kept = sqlx::query(&format!(
+                "INSERT INTO status_history SELECT * FROM {} WHERE user_id IN ({})",
+                name, EXEMPT_USERS
+            ))
+            .bind(months_ago(month))
+            .execute(&mut tx)
+            .await?;
+
+            sqlx::query(&format!("DROP TABLE {}", name))
+                .execute(&mut tx)
+                .await?;
+            tx.commit().await?;
+            tracing::info!("dropped partition {} ({} rows kept)", name, kept);
+        }
+    }
+
+    // history from before partitioning (or kept by an override) remains in the default
+    // partition
+    sqlx::query(
+        "DELETE FROM status_history_default
+        WHERE created_at < $1 AND user_id NOT IN (
+            SELECT members.user_id
+            FROM members
+            JOIN retention_overrides ON retention_overrides.team_id = members.team_id
+            WHERE retention_overrides.months = 0
+                OR status_history_default.created_at
+                    >= $2 - make_interval(months => retention_overrides.months::int)
+        )",
+    )
+    .bind(month_start(oldest))
+    .bind(month_start(this_month()))
+    .execute(&mut db)
+    .await?;
+
+    Ok(())
+}
+
+/// Moves a month of history to its archive database, except that of teams with a retention
+/// override keeping it
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `config` - Partitioning configuration
+/// * `month` - First day of the month
+#[cfg(feature = "sqlite")]
+async fn archive_month(
+    db: &mut SqlConn,
+    config: &PartitionConfig,
+    month: NaiveDate,
+) -> Result<u64> {
+    // matches the format of `CURRENT_TIMESTAMP`, which created_at defaults to
+    let from = format!("{} 00:00:00", month);
+    let to = format!("{} 00:00:00", add_months(month, 1));
+
+    let archived_rows = format!(
+        "user_id NOT IN ({}) AND created_at >= $2 AND created_at < $3",
+        EXEMPT_USERS
+    );
+    let age = months_ago(month);
+
+    // don't create archives of empty months
+    let count: i64 = sqlx::query(&format!(
+        "SELECT COUNT(*) AS count FROM status_history WHERE {}",
+        archived_rows
+    ))
+    .bind(age)
+    .bind(&from)
+    .bind(&to)
+    .fetch_one(&mut *db)
+    .await?
+    .try_get("count")?;
+
+    if count == 0 {
+        return Ok(0);
+    }
+
+    std::fs::create_dir_all(&config.archive_dir)?;
+    let path = config
+        .archive_dir
+        .join(format!("status_history-{}.sqlite3", month.format("%Y-%m")));
+
+    sqlx::query("ATTACH DATABASE $1 AS archive")
+        .bind(path.to_string_lossy().into_owned())
+        .execute(&mut *db)
+        .await?;
+
+    let archived = async {
+        let mut tx = db.begin().await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS archive.status_history AS
+            SELECT * FROM main.status_history WHERE 0",
+        )
+        .execute(&mut tx)
+        .await?;
+
+        let archived = sqlx::query(&format!(
+            "INSERT INTO archive.status_history
+            SELECT * FROM main.status_history WHERE {}",
+            archived_rows
+        ))
+        .bind(age)
+        .bind(&from)
+        .bind(&to)
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(&format!(
+            "DELETE FROM main.status_history WHERE {}",
+            archived_rows
+        ))
+        .bind(age)
+        .bind(&from)
+        .bind(&to)
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok::<_, sqlx::Error>(archived)
+    }
+    .await;
+
+    sqlx::query("DETACH DATABASE archive")
+        .execute(&mut *db)
+        .await?;
+
+    Ok(archived?)
+}
+
+/// Moves every month of history older than the retention period to its archive database,
+/// keeping the history of teams with a longer retention override
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `config` - Partitioning configuration
+#[cfg(feature = "sqlite")]
+async fn maintain(pool: &SqlPool, config: &PartitionConfig) -> Result<()> {
+    let oldest = match oldest_kept(config.retention_months) {
+        Some(oldest) => oldest,
+        None => return Ok(()),
+    };
+
+    let mut db = pool.acquire().await?;
+    let first: Option<String> = sqlx::query("SELECT MIN(created_at) AS first FROM status_history")
+        .fetch_one(&mut db)
+        .await?
+        .try_get("first")?;
+
+    let mut month = match first
+        .as_deref()
+        .and_then(|first| first.get(..7))
+        .and_then(|month| NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").ok())
+    {
+        Some(month) => month,
+        None => return Ok(()),
+    };
+
+    while month < oldest {
+        let archived = archive_month(&mut db, config, month).await?;
+        if archived > 0 {
+            tracing::info!(
+                "archived {} statuses from {}",
+                archived,
+                month.format("%Y-%m")
+            );
+        }
+        month = add_months(month, 1);
+    }
+
+    Ok(())
+}
+
+/// Spawns a task that maintains status history partitions daily, starting immediately
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `config` - Partitioning configuration
+pub fn spawn(pool: SqlPool, config: PartitionConfig) {
+    runtime::spawn(async move {
+        loop {
+            if let Err(e) = maintain(&pool, &config).await {
+                tracing::error!("failed to maintain status history partitions: {:?}", e);
+            }
+
+            runtime::sleep(INTERVAL).await;
+        }
+    });
+}
@@ -0,0 +1,125 @@
+//! Slack presence and do-not-disturb status of users
+//!
+//! Lookups are cached briefly, as `users.getPresence` and `dnd.teamInfo` are rate limited.
+
+use crate::slack;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Whether a user is currently active, and whether they've silenced notifications
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Presence {
+    /// The user is active in Slack
+    pub active: bool,
+
+    /// The user has do-not-disturb enabled (or notifications snoozed)
+    pub dnd: bool,
+}
+
+impl Presence {
+    /// Returns emoji describing this presence, for annotating statuses
+    pub fn badge(&self) -> &'static str {
+        match (self.active, self.dnd) {
+            (true, false) => ":large_green_circle:",
+            (true, true) => ":large_green_circle: :no_bell:",
+            (false, false) => ":white_circle:",
+            (false, true) => ":white_circle: :no_bell:",
+        }
+    }
+}
+
+/// Briefly caches the presence of users
+#[derive(Clone, Debug)]
+pub struct PresenceCache {
+    /// How long a lookup is reused for
+    ttl: Duration,
+
+    /// Cached lookups, and when they were made
+    entries: Arc<Mutex<HashMap<String, (Instant, Presence)>>>,
+}
+
+impl PresenceCache {
+    /// Creates an empty cache
+    ///
+    /// # Arguments
+    /// * `ttl` - How long a lookup is reused for
+    pub fn new(ttl: Duration) -> Self {
+        PresenceCache {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Looks up the presence of users, returning the users whose presence is known
+    ///
+    /// Users whose presence could not be looked up are left out
+    ///
+    /// # Arguments
+    /// * `user_ids` - Slack IDs of the users
+    pub async fn lookup(&self, user_ids: &[String]) -> HashMap<String, Presence> {
+        let mut found = HashMap::new();
+        let mut missing = vec![];
+
+        {
+            let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            for id in user_ids {
+                match entries.get(id) {
+                    Some((at, presence)) if at.elapsed() < self.ttl => {
+                        found.insert(id.clone(), *presence);
+                    }
+                    _ => missing.push(id.as_str()),
+                }
+            }
+        }
+
+        if missing.is_empty() {
+            return found;
+        }
+
+        // do-not-disturb can be looked up for many users at once
+        let now = chrono::Utc::now().timestamp();
+        let dnd = match slack::get("dnd.teamInfo", &[("users", &missing.join(","))]).await {
+            Ok(resp) => resp["users"].clone(),
+            Err(e) => {
+                tracing::warn!("failed to look up do-not-disturb status: {:?}", e);
+                serde_json::Value::Null
+            }
+        };
+
+        let mut entries = vec![];
+        for id in missing {
+            let active = match slack::get("users.getPresence", &[("user", id)]).await {
+                Ok(resp) => resp["presence"] == "active",
+                Err(e) => {
+                    tracing::warn!("failed to look up presence of {}: {:?}", id, e);
+                    continue;
+                }
+            };
+
+            let info = &dnd[id];
+            let scheduled = info["dnd_enabled"].as_bool().unwrap_or(false)
+                && info["next_dnd_start_ts"].as_i64().unwrap_or(i64::MAX) <= now
+                && now < info["next_dnd_end_ts"].as_i64().unwrap_or(0);
+            let snoozed = info["snooze_enabled"].as_bool().unwrap_or(false);
+
+            let presence = Presence {
+                active,
+                dnd: scheduled || snoozed,
+            };
+
+            found.insert(id.to_owned(), presence);
+            entries.push((id.to_owned(), presence));
+        }
+
+        let mut cache = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        cache.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        for (id, presence) in entries {
+            cache.insert(id, (Instant::now(), presence));
+        }
+
+        found
+    }
+}
@@ -0,0 +1,204 @@
+//! Periodic refresh of cached Slack user profiles
+//!
+//! Profiles (display names, emails, timezones, and deactivation) of every known user are
+//! refreshed from `users.list`, so nothing needs to call `users.info` per request.
+
+use crate::{
+    models::{Profile, User},
+    runtime, slack, SqlConn, SqlPool,
+};
+use anyhow::Result;
+use chrono::Utc;
+use serde_json::Value;
+use std::{collections::HashSet, time::Duration};
+
+/// Number of users requested per page of `users.list`
+const PAGE_SIZE: &str = "200";
+
+/// Delay between pages, to stay within `users.list`'s rate limit (tier 2)
+const PAGE_DELAY: Duration = Duration::from_secs(3);
+
+/// Builds a profile from a member returned by `users.list`
+///
+/// # Arguments
+/// * `member` - Member object returned by Slack
+fn parse_member(member: &Value) -> Option<Profile> {
+    let profile = &member["profile"];
+    let display_name = [
+        &profile["display_name"],
+        &profile["real_name"],
+        &member["name"],
+    ]
+    .iter()
+    .filter_map(|name| name.as_str())
+    .find(|name| !name.is_empty())
+    .map(str::to_owned);
+
+    Some(Profile {
+        user_id: member["id"].as_str()?.to_owned(),
+        display_name,
+        email: profile["email"].as_str().map(str::to_owned),
+        tz: member["tz"].as_str().map(str::to_owned),
+        deleted: member["deleted"].as_bool().unwrap_or(false),
+        updated_at: Utc::now(),
+    })
+}
+
+/// Refreshes the profiles of all known users, returning the number refreshed
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+pub async fn refresh(pool: &SqlPool) -> Result<usize> {
+    let mut db = pool.acquire().await?;
+    let known: HashSet<String> = User::fetch_all(&mut db)
+        .await?
+        .into_iter()
+        .map(|user| user.id)
+        .collect();
+
+    let mut refreshed = 0;
+    let mut cursor = String::new();
+    loop {
+        let page = slack::get(
+            "users.list",
+            &[("limit", PAGE_SIZE), ("cursor", cursor.as_str())],
+        )
+        .await?;
+
+        let members = page["members"].as_array().cloned().unwrap_or_default();
+        for profile in members.iter().filter_map(parse_member) {
+            if known.contains(&profile.user_id) {
+                profile.save(&mut db).await?;
+                refreshed += 1;
+            }
+        }
+
+        cursor = page["response_metadata"]["next_cursor"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned();
+
+        if cursor.is_empty() {
+            break;
+        }
+
+        runtime::sleep(PAGE_DELAY).await;
+    }
+
+    Ok(refreshed)
+}
+
+/// Resolves the Slack ID of the user with an email address
+///
+/// Cached profiles are checked first; otherwise the user is looked up with
+/// `users.lookupByEmail` and their profile is cached
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `email` - Email address of the user
+pub async fn lookup_by_email(db: &mut SqlConn, email: &str) -> Result<String> {
+    if let Some(profile) = Profile::fetch_by_email(&mut *db, email).await {
+        return Ok(profile.user_id);
+    }
+
+    let resp = slack::get("users.lookupByEmail", &[("email", email)]).await?;
+    let profile = match parse_member(&resp["user"]) {
+        Some(profile) => profile,
+        None => return Err(anyhow::anyhow!("users.lookupByEmail: missing user")),
+    };
+
+    // profiles are only kept for known users
+    User::fetch_or_create(&mut *db, &profile.user_id).await?;
+    profile.save(&mut *db).await?;
+
+    Ok(profile.user_id)
+}
+
+/// Spawns a task that refreshes profiles periodically, starting immediately
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `interval` - Time between refreshes
+pub fn spawn(pool: SqlPool, interval: Duration) {
+    runtime::spawn(async move {
+        loop {
+            match refresh(&pool).await {
+                Ok(count) => tracing::info!("refreshed {} user profiles", count),
+                Err(e) => tracing::error!("failed to refresh user profiles: {:?}", e),
+            }
+
+            runtime::sleep(interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    //! Contract tests against user objects recorded from Slack (`fixtures/slack`)
+
+    use super::*;
+
+    /// Parses the user in a recorded `users.info` response
+    ///
+    /// # Arguments
+    /// * `body` - Body of the response
+    fn parse_user(body: &str) -> Option<Profile> {
+        let value: Value = serde_json::from_str(body).unwrap();
+        parse_member(&value["user"])
+    }
+
+    #[test]
+    fn users_info_user() {
+        let profile = parse_user(include_str!("../fixtures/slack/users_info_user.json")).unwrap();
+        assert_eq!(profile.user_id, "U01AR0CS9PF");
+        assert_eq!(profile.display_name.as_deref(), Some("alice"));
+        assert_eq!(profile.email.as_deref(), Some("alice@example.com"));
+        assert_eq!(profile.tz.as_deref(), Some("America/New_York"));
+        assert!(!profile.deleted);
+    }
+
+    #[test]
+    fn users_info_falls_back_to_real_name() {
+        let body = include_str!("../fixtures/slack/users_info_no_display_name.json");
+        let profile = parse_user(body).unwrap();
+        assert_eq!(profile.display_name.as_deref(), Some("Bob Jones"));
+        assert_eq!(profile.tz.as_deref(), Some("Europe/London"));
+    }
+
+    #[test]
+    fn users_info_deleted() {
+        let body = include_str!("../fixtures/slack/users_info_deleted.json");
+        let profile = parse_user(body).unwrap();
+        assert!(profile.deleted);
+        assert_eq!(profile.email, None);
+        assert_eq!(profile.tz, None);
+    }
+
+    #[test]
+    fn users_info_bot() {
+        let profile = parse_user(include_str!("../fixtures/slack/users_info_bot.json")).unwrap();
+        assert_eq!(profile.display_name.as_deref(), Some("statusbot"));
+        assert_eq!(profile.email, None);
+    }
+
+    #[test]
+    fn users_list_page() {
+        let body = include_str!("../fixtures/slack/users_list_page.json");
+        let page: Value = serde_json::from_str(body).unwrap();
+
+        // members without an id are skipped
+        let ids: Vec<String> = page["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(parse_member)
+            .map(|profile| profile.user_id)
+            .collect();
+        assert_eq!(ids, vec!["USLACKBOT", "U01AR0CS9PF"]);
+
+        assert_eq!(
+            page["response_metadata"]["next_cursor"],
+            "dXNlcjpVMEc5V0ZYTlo="
+        );
+    }
+}
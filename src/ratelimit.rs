@@ -0,0 +1,275 @@
+//! Per-key rate limits for public, token-protected endpoints (e.g., Atom feeds)
+//!
+//! Each key has a bucket holding up to `RATE_LIMIT_BURST` requests, refilled at
+//! `RATE_LIMIT_PER_MINUTE`.  Requests carrying a valid token for the resource they ask for
+//! are keyed on a hash of that token; everything else (including requests with a wrong
+//! token) is keyed on the client's address, as resolved by the allowlist (see `allowlist`),
+//! so made-up tokens can't be used to get a fresh bucket.  Every response carries
+//! `X-RateLimit-Limit`, `X-RateLimit-Remaining`, and `X-RateLimit-Reset` (seconds until the
+//! bucket is full again); requests made with an empty bucket are rejected with
+//! `429 Too Many Requests` and a `Retry-After`.
+//!
+//! Buckets are kept in memory and written to the `rate_limits` table every
+//! `RATE_LIMIT_PERSIST_INTERVAL` seconds, then loaded again on startup, so restarting the bot
+//! doesn't hand every key a full bucket.  Limits still apply per instance.
+
+use crate::{allowlist::ClientAddr, models::RateBucket, runtime, SqlPool};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tide::{Middleware, Next, StatusCode};
+
+/// Number of buckets kept before full (idle) buckets are dropped
+const MAX_BUCKETS: usize = 10_000;
+
+/// Checks a token against the last segment of a request's path (e.g., `<team>.atom`)
+pub type TokenCheck = fn(&str, &str) -> bool;
+
+/// Query string parameters used to identify a client
+#[derive(Debug, Deserialize)]
+struct KeyQuery {
+    token: Option<String>,
+}
+
+/// Requests remaining for one key
+#[derive(Clone, Debug)]
+struct Bucket {
+    /// Requests that can be made right now
+    tokens: f64,
+
+    /// When `tokens` was last updated
+    updated: DateTime<Utc>,
+}
+
+/// Middleware that limits the rate of requests made with each key
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    /// Requests allowed per minute, on average
+    per_minute: u32,
+
+    /// Requests that can be made at once
+    burst: u32,
+
+    /// Checks tokens supplied to this route, or `None` if tokens aren't accepted
+    check_token: Option<TokenCheck>,
+
+    /// Buckets, by key (shared by every route the limit is applied to)
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+/// Outcome of checking a request against its key's bucket
+struct Decision {
+    /// If the request may proceed
+    allowed: bool,
+
+    /// Requests remaining in the bucket
+    remaining: u32,
+
+    /// Seconds until the bucket is full again
+    reset: u64,
+
+    /// Seconds until another request can be made
+    retry_after: u64,
+}
+
+impl RateLimit {
+    /// Creates a rate limit
+    ///
+    /// # Arguments
+    /// * `per_minute` - Requests allowed per minute, on average
+    /// * `burst` - Requests that can be made at once
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        RateLimit {
+            per_minute,
+            burst: burst.max(1),
+            check_token: None,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns a copy of this limit, sharing its buckets, that keys requests on their token
+    /// when `check` accepts it
+    ///
+    /// # Arguments
+    /// * `check` - Checks a token against the last segment of the request's path
+    pub fn with_tokens(&self, check: TokenCheck) -> Self {
+        RateLimit {
+            check_token: Some(check),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the number of requests in a bucket, after refilling it up to `now`
+    ///
+    /// # Arguments
+    /// * `bucket` - Bucket to refill
+    /// * `now` - Current time
+    fn refill(&self, bucket: &Bucket, now: DateTime<Utc>) -> f64 {
+        let per_sec = f64::from(self.per_minute) / 60.0;
+        let elapsed = (now - bucket.updated).num_milliseconds().max(0) as f64 / 1000.0;
+        (bucket.tokens + elapsed * per_sec).min(f64::from(self.burst))
+    }
+
+    /// Takes a request from a key's bucket, if it isn't empty
+    ///
+    /// # Arguments
+    /// * `key` - Identifies the client
+    fn check(&self, key: &str) -> Decision {
+        let now = Utc::now();
+        let per_sec = f64::from(self.per_minute) / 60.0;
+        let burst = f64::from(self.burst);
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_BUCKETS {
+            buckets.retain(|_, bucket| self.refill(bucket, now) < burst);
+        }
+
+        let bucket = buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        bucket.tokens = self.refill(bucket, now);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        Decision {
+            allowed,
+            remaining: bucket.tokens.floor() as u32,
+            reset: ((burst - bucket.tokens) / per_sec).ceil() as u64,
+            retry_after: ((1.0 - bucket.tokens).max(0.0) / per_sec).ceil() as u64,
+        }
+    }
+
+    /// Determines the key a request is counted against
+    ///
+    /// # Arguments
+    /// * `req` - Incoming HTTP request
+    fn key<S>(&self, req: &tide::Request<S>) -> String {
+        let resource = req
+            .url()
+            .path_segments()
+            .and_then(|segments| segments.last())
+            .unwrap_or_default();
+
+        let token = self.check_token.and_then(|check| {
+            req.query::<KeyQuery>()
+                .ok()
+                .and_then(|query| query.token)
+                .filter(|token| check(resource, token))
+        });
+
+        match token {
+            // keys are persisted, so never store the token itself
+            Some(token) => format!("token:{}", hex::encode(Sha256::digest(token.as_bytes()))),
+            None => match req.ext::<ClientAddr>() {
+                Some(ClientAddr(addr)) => format!("addr:{}", addr),
+                None => "addr:unknown".to_owned(),
+            },
+        }
+    }
+
+    /// Loads buckets saved by a previous run, keeping any already in use
+    ///
+    /// # Arguments
+    /// * `pool` - Pool of connections to the SQL database
+    async fn load(&self, pool: &SqlPool) -> anyhow::Result<usize> {
+        let mut db = pool.acquire().await?;
+        let saved = RateBucket::fetch_all(&mut db).await?;
+        let count = saved.len();
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        for saved in saved {
+            buckets.entry(saved.key).or_insert(Bucket {
+                tokens: saved.tokens,
+                updated: saved.updated_at,
+            });
+        }
+
+        Ok(count)
+    }
+
+    /// Replaces the saved buckets with the ones currently in use
+    ///
+    /// Full buckets are skipped, as a missing bucket starts out full anyway
+    ///
+    /// # Arguments
+    /// * `pool` - Pool of connections to the SQL database
+    async fn persist(&self, pool: &SqlPool) -> anyhow::Result<usize> {
+        let now = Utc::now();
+        let burst = f64::from(self.burst);
+        let snapshot: Vec<RateBucket> = {
+            let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+            buckets
+                .iter()
+                .filter(|(_, bucket)| self.refill(bucket, now) < burst)
+                .map(|(key, bucket)| RateBucket {
+                    key: key.clone(),
+                    tokens: bucket.tokens,
+                    updated_at: bucket.updated,
+                })
+                .collect()
+        };
+
+        let mut db = pool.acquire().await?;
+        RateBucket::replace_all(&mut db, &snapshot).await?;
+        Ok(snapshot.len())
+    }
+}
+
+/// Loads saved buckets, then periodically saves the buckets in use in the background
+///
+/// # Arguments
+/// * `pool` - Pool of connections to the SQL database
+/// * `limit` - Rate limit whose buckets to save (and every copy sharing them)
+/// * `interval` - How often to save them
+pub fn spawn(pool: SqlPool, limit: RateLimit, interval: Duration) {
+    runtime::spawn(async move {
+        match limit.load(&pool).await {
+            Ok(count) => tracing::debug!("loaded {} rate limit buckets", count),
+            Err(e) => tracing::error!("failed to load rate limit buckets: {:?}", e),
+        }
+
+        loop {
+            runtime::sleep(interval).await;
+
+            match limit.persist(&pool).await {
+                Ok(count) => tracing::debug!("saved {} rate limit buckets", count),
+                Err(e) => tracing::error!("failed to save rate limit buckets: {:?}", e),
+            }
+        }
+    });
+}
+
+#[async_trait]
+impl<S: Clone + Send + Sync + 'static> Middleware<S> for RateLimit {
+    async fn handle(&self, req: tide::Request<S>, next: Next<'_, S>) -> tide::Result {
+        if self.per_minute == 0 {
+            return Ok(next.run(req).await);
+        }
+
+        let decision = self.check(&self.key(&req));
+        let mut resp = if decision.allowed {
+            next.run(req).await
+        } else {
+            tide::Response::builder(StatusCode::TooManyRequests)
+                .header("Retry-After", decision.retry_after.to_string())
+                .build()
+        };
+
+        resp.insert_header("X-RateLimit-Limit", self.per_minute.to_string());
+        resp.insert_header("X-RateLimit-Remaining", decision.remaining.to_string());
+        resp.insert_header("X-RateLimit-Reset", decision.reset.to_string());
+
+        Ok(resp)
+    }
+}
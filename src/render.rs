@@ -0,0 +1,761 @@
+//! Rendering of messages for each surface they're shown on
+//!
+//! Messages are laid out as a `ResponseDoc` of headers, sections, context lines, fields,
+//! lists, tables, and buttons made of `Text`, which is rendered as Block Kit blocks for Slack
+//! responses, or with the `Markup` of the surface it's sent to: Slack mrkdwn, plain text
+//! (emails and Slack clients that shouldn't see markup), or HTML (emails and the web).  Users
+//! are mentioned on Slack and named everywhere else, and statuses keep their emoji only on
+//! Slack.
+//!
+//! Messages the bot posts to Slack on its own (digests and audit reports) use mrkdwn unless
+//! `SLACK_MARKUP=plain`.
+
+use crate::markup::escape;
+use once_cell::sync::OnceCell;
+use serde_json::{json, Value};
+use std::str::FromStr;
+
+/// Markup used for messages posted to Slack
+static SLACK: OnceCell<Markup> = OnceCell::new();
+
+/// How text is marked up on a surface
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Markup {
+    /// Slack's mrkdwn, with mentions and emoji
+    Mrkdwn,
+
+    /// Plain text, without any markup
+    Plain,
+
+    /// An HTML fragment
+    Html,
+}
+
+impl FromStr for Markup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mrkdwn" => Ok(Markup::Mrkdwn),
+            "plain" => Ok(Markup::Plain),
+            "html" => Ok(Markup::Html),
+            _ => Err(format!(
+                "unknown markup `{}`, expected mrkdwn, plain, or html",
+                s
+            )),
+        }
+    }
+}
+
+/// Configures the markup of messages posted to Slack
+///
+/// Only the first call has an effect
+///
+/// # Arguments
+/// * `markup` - Markup of messages posted to Slack (HTML is posted as plain text)
+pub fn configure(markup: Markup) {
+    let markup = match markup {
+        Markup::Html => {
+            tracing::warn!("slack can't show html, posting plain text instead");
+            Markup::Plain
+        }
+        markup => markup,
+    };
+
+    SLACK.set(markup).ok();
+}
+
+/// Returns the markup of messages posted to Slack
+pub fn slack() -> Markup {
+    SLACK.get().copied().unwrap_or(Markup::Mrkdwn)
+}
+
+/// Escapes text for Slack mrkdwn, leaving formatting characters alone
+///
+/// # Arguments
+/// * `text` - Text to escape
+fn escape_mrkdwn(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// A run of text with a single style
+#[derive(Clone, Debug, PartialEq)]
+pub enum Span {
+    /// Unstyled text
+    Text(String),
+
+    /// Bold text
+    Strong(String),
+
+    /// Italic text
+    Emphasis(String),
+
+    /// Text to be typed as is (e.g., a command)
+    Code(String),
+
+    /// A Slack user: mentioned on Slack, named elsewhere
+    Mention {
+        /// Slack ID of the user
+        user_id: String,
+
+        /// Name of the user
+        name: String,
+    },
+
+    /// Text with a richer form on Slack (e.g., a status with emoji)
+    Slack {
+        /// Text shown on Slack, already in mrkdwn
+        mrkdwn: String,
+
+        /// Text shown elsewhere
+        fallback: String,
+    },
+}
+
+impl Span {
+    /// Renders this span
+    ///
+    /// # Arguments
+    /// * `markup` - Markup of the surface the span is shown on
+    fn render(&self, markup: Markup) -> String {
+        match (self, markup) {
+            (Span::Text(text), Markup::Mrkdwn) => escape_mrkdwn(text),
+            (Span::Strong(text), Markup::Mrkdwn) => format!("*{}*", escape_mrkdwn(text)),
+            (Span::Emphasis(text), Markup::Mrkdwn) => format!("_{}_", escape_mrkdwn(text)),
+            (Span::Code(text), Markup::Mrkdwn) => format!("`{}`", escape_mrkdwn(text)),
+            (Span::Mention { user_id, .. }, Markup::Mrkdwn) => format!("<@{}>", user_id),
+            (Span::Slack { mrkdwn, .. }, Markup::Mrkdwn) => mrkdwn.clone(),
+
+            (Span::Text(text), Markup::Plain)
+            | (Span::Strong(text), Markup::Plain)
+            | (Span::Emphasis(text), Markup::Plain)
+            | (Span::Code(text), Markup::Plain)
+            | (Span::Mention { name: text, .. }, Markup::Plain)
+            | (Span::Slack { fallback: text, .. }, Markup::Plain) => text.clone(),
+
+            (Span::Text(text), Markup::Html)
+            | (Span::Mention { name: text, .. }, Markup::Html)
+            | (Span::Slack { fallback: text, .. }, Markup::Html) => escape(text),
+            (Span::Strong(text), Markup::Html) => format!("<strong>{}</strong>", escape(text)),
+            (Span::Emphasis(text), Markup::Html) => format!("<em>{}</em>", escape(text)),
+            (Span::Code(text), Markup::Html) => format!("<code>{}</code>", escape(text)),
+        }
+    }
+}
+
+/// A line of text, made of spans of different styles
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Text {
+    /// Spans of the text, in order
+    spans: Vec<Span>,
+}
+
+impl Text {
+    /// Creates an empty text
+    pub fn new() -> Self {
+        Text::default()
+    }
+
+    /// Appends unstyled text
+    ///
+    /// # Arguments
+    /// * `text` - The text
+    pub fn text<S: Into<String>>(mut self, text: S) -> Self {
+        self.spans.push(Span::Text(text.into()));
+        self
+    }
+
+    /// Appends bold text
+    ///
+    /// # Arguments
+    /// * `text` - The text
+    pub fn strong<S: Into<String>>(mut self, text: S) -> Self {
+        self.spans.push(Span::Strong(text.into()));
+        self
+    }
+
+    /// Appends italic text
+    ///
+    /// # Arguments
+    /// * `text` - The text
+    pub fn emphasis<S: Into<String>>(mut self, text: S) -> Self {
+        self.spans.push(Span::Emphasis(text.into()));
+        self
+    }
+
+    /// Appends text to be typed as is (e.g., a command)
+    ///
+    /// # Arguments
+    /// * `text` - The text
+    pub fn code<S: Into<String>>(mut self, text: S) -> Self {
+        self.spans.push(Span::Code(text.into()));
+        self
+    }
+
+    /// Appends a Slack user, mentioned on Slack and named elsewhere
+    ///
+    /// # Arguments
+    /// * `user_id` - Slack ID of the user
+    /// * `name` - Name of the user
+    pub fn mention<S: Into<String>, N: Into<String>>(mut self, user_id: S, name: N) -> Self {
+        self.spans.push(Span::Mention {
+            user_id: user_id.into(),
+            name: name.into(),
+        });
+        self
+    }
+
+    /// Appends text with a richer form on Slack
+    ///
+    /// # Arguments
+    /// * `mrkdwn` - Text shown on Slack, already in mrkdwn
+    /// * `fallback` - Text shown elsewhere
+    pub fn slack<S: Into<String>, F: Into<String>>(mut self, mrkdwn: S, fallback: F) -> Self {
+        self.spans.push(Span::Slack {
+            mrkdwn: mrkdwn.into(),
+            fallback: fallback.into(),
+        });
+        self
+    }
+
+    /// Appends another text
+    ///
+    /// # Arguments
+    /// * `other` - The text to append
+    pub fn append(mut self, other: Text) -> Self {
+        self.spans.extend(other.spans);
+        self
+    }
+
+    /// Renders this text
+    ///
+    /// # Arguments
+    /// * `markup` - Markup of the surface the text is shown on
+    pub fn render(&self, markup: Markup) -> String {
+        self.spans.iter().map(|span| span.render(markup)).collect()
+    }
+}
+
+impl From<&str> for Text {
+    fn from(text: &str) -> Self {
+        Text::new().text(text)
+    }
+}
+
+impl From<String> for Text {
+    fn from(text: String) -> Self {
+        Text::new().text(text)
+    }
+}
+
+/// Style of a button
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ButtonStyle {
+    /// Green, for the action most users take
+    Primary,
+
+    /// Red, for destructive actions
+    Danger,
+}
+
+/// A button the user can press, only shown on Slack
+#[derive(Clone, Debug, PartialEq)]
+pub struct Button {
+    /// Action id sent when the button is pressed
+    pub action_id: String,
+
+    /// Label of the button
+    pub label: String,
+
+    /// Value sent when the button is pressed
+    pub value: String,
+
+    /// Style of the button, if not the default
+    pub style: Option<ButtonStyle>,
+}
+
+impl Button {
+    /// Creates a button in the default style
+    ///
+    /// # Arguments
+    /// * `action_id` - Action id sent when the button is pressed
+    /// * `label` - Label of the button
+    /// * `value` - Value sent when the button is pressed
+    pub fn new<A: Into<String>, L: Into<String>, V: Into<String>>(
+        action_id: A,
+        label: L,
+        value: V,
+    ) -> Self {
+        Button {
+            action_id: action_id.into(),
+            label: label.into(),
+            value: value.into(),
+            style: None,
+        }
+    }
+
+    /// Sets the style of this button
+    ///
+    /// # Arguments
+    /// * `style` - Style of the button
+    pub fn style(mut self, style: ButtonStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// Returns the Block Kit element of this button
+    fn to_element(&self) -> Value {
+        let mut element = json!({
+            "type": "button",
+            "action_id": self.action_id,
+            "text": { "type": "plain_text", "text": self.label },
+            "value": self.value,
+        });
+
+        match self.style {
+            Some(ButtonStyle::Primary) => element["style"] = json!("primary"),
+            Some(ButtonStyle::Danger) => element["style"] = json!("danger"),
+            None => (),
+        }
+
+        element
+    }
+}
+
+/// A part of a response
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    /// A header, the first of a response being its title
+    Header(Text),
+
+    /// A section of text
+    Section(Text),
+
+    /// A less prominent line of text (e.g., a note or a page number)
+    Context(Text),
+
+    /// Labelled values, shown side by side on Slack
+    Fields(Vec<(Text, Text)>),
+
+    /// A bulleted list
+    List(Vec<Text>),
+
+    /// A table with a row of column names
+    Table {
+        /// Names of the columns
+        columns: Vec<String>,
+
+        /// Cells of each row
+        rows: Vec<Vec<Text>>,
+    },
+
+    /// A horizontal line
+    Divider,
+
+    /// Buttons the user can press, only shown on Slack
+    Actions(Vec<Button>),
+}
+
+/// A response, independent of the surface it's shown on
+///
+/// Handlers lay out what they respond with as a `ResponseDoc`, which is rendered as Block
+/// Kit blocks for Slack (`to_blocks`), or as mrkdwn, plain text, or HTML (`render`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResponseDoc {
+    /// Parts of the response, in order
+    blocks: Vec<Block>,
+}
+
+/// Renders the rows of a table as lines of text, column names first
+///
+/// # Arguments
+/// * `columns` - Names of the columns
+/// * `rows` - Cells of each row
+/// * `markup` - Markup of the surface the table is shown on (not HTML)
+fn table_lines(columns: &[String], rows: &[Vec<Text>], markup: Markup) -> Vec<String> {
+    let header: Vec<String> = columns
+        .iter()
+        .map(|column| Text::new().strong(column.as_str()).render(markup))
+        .collect();
+
+    std::iter::once(header.join(" | "))
+        .chain(rows.iter().map(|row| {
+            row.iter()
+                .map(|cell| cell.render(markup))
+                .collect::<Vec<_>>()
+                .join(" | ")
+        }))
+        .collect()
+}
+
+impl ResponseDoc {
+    /// Creates an empty response
+    pub fn new() -> Self {
+        ResponseDoc::default()
+    }
+
+    /// Appends a header
+    ///
+    /// # Arguments
+    /// * `text` - Text of the header
+    pub fn header<T: Into<Text>>(&mut self, text: T) -> &mut Self {
+        self.blocks.push(Block::Header(text.into()));
+        self
+    }
+
+    /// Appends a section of text
+    ///
+    /// # Arguments
+    /// * `text` - Text of the section
+    pub fn section<T: Into<Text>>(&mut self, text: T) -> &mut Self {
+        self.blocks.push(Block::Section(text.into()));
+        self
+    }
+
+    /// Appends a less prominent line of text
+    ///
+    /// # Arguments
+    /// * `text` - Text of the line
+    pub fn context<T: Into<Text>>(&mut self, text: T) -> &mut Self {
+        self.blocks.push(Block::Context(text.into()));
+        self
+    }
+
+    /// Appends labelled values
+    ///
+    /// # Arguments
+    /// * `fields` - Label and value of each field
+    pub fn fields(&mut self, fields: Vec<(Text, Text)>) -> &mut Self {
+        self.blocks.push(Block::Fields(fields));
+        self
+    }
+
+    /// Appends a bulleted list
+    ///
+    /// # Arguments
+    /// * `items` - Items of the list
+    pub fn list(&mut self, items: Vec<Text>) -> &mut Self {
+        self.blocks.push(Block::List(items));
+        self
+    }
+
+    /// Appends a table
+    ///
+    /// # Arguments
+    /// * `columns` - Names of the columns
+    /// * `rows` - Cells of each row, one per column
+    pub fn table(&mut self, columns: &[&str], rows: Vec<Vec<Text>>) -> &mut Self {
+        self.blocks.push(Block::Table {
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            rows,
+        });
+        self
+    }
+
+    /// Appends a horizontal line
+    pub fn divider(&mut self) -> &mut Self {
+        self.blocks.push(Block::Divider);
+        self
+    }
+
+    /// Appends buttons
+    ///
+    /// # Arguments
+    /// * `buttons` - The buttons
+    pub fn actions(&mut self, buttons: Vec<Button>) -> &mut Self {
+        self.blocks.push(Block::Actions(buttons));
+        self
+    }
+
+    /// Renders this response as Block Kit blocks, for Slack
+    ///
+    /// Each item of a list is a section of its own, and tables are a single section, since
+    /// Block Kit has neither
+    pub fn to_blocks(&self) -> Vec<Value> {
+        let section = |text: String| {
+            json!({
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": text }
+            })
+        };
+
+        let mut blocks = vec![];
+        for block in &self.blocks {
+            match block {
+                Block::Header(text) => blocks.push(json!({
+                    "type": "header",
+                    "text": { "type": "plain_text", "text": text.render(Markup::Plain) }
+                })),
+                Block::Section(text) => blocks.push(section(text.render(Markup::Mrkdwn))),
+                Block::Context(text) => blocks.push(json!({
+                    "type": "context",
+                    "elements": [{ "type": "mrkdwn", "text": text.render(Markup::Mrkdwn) }]
+                })),
+                Block::Fields(fields) => blocks.push(json!({
+                    "type": "section",
+                    "fields": fields
+                        .iter()
+                        .map(|(label, value)| json!({
+                            "type": "mrkdwn",
+                            "text": format!(
+                                "*{}*\n{}",
+                                label.render(Markup::Mrkdwn),
+                                value.render(Markup::Mrkdwn)
+                            )
+                        }))
+                        .collect::<Vec<_>>()
+                })),
+                Block::List(items) => {
+                    for item in items {
+                        blocks.push(section(format!("• {}", item.render(Markup::Mrkdwn))));
+                    }
+                }
+                Block::Table { columns, rows } => blocks.push(section(
+                    table_lines(columns, rows, Markup::Mrkdwn).join("\n"),
+                )),
+                Block::Divider => blocks.push(json!({ "type": "divider" })),
+                Block::Actions(buttons) => blocks.push(json!({
+                    "type": "actions",
+                    "elements": buttons.iter().map(Button::to_element).collect::<Vec<_>>()
+                })),
+            }
+        }
+
+        blocks
+    }
+
+    /// Renders this response as text or HTML
+    ///
+    /// Headers are preceded by a blank line in mrkdwn and plain text, unless they start the
+    /// response, and are an `<h2>` in HTML if they do (`<h3>` otherwise).  Buttons can only
+    /// be pressed on Slack, so they're left out
+    ///
+    /// # Arguments
+    /// * `markup` - Markup of the surface the response is shown on
+    pub fn render(&self, markup: Markup) -> String {
+        let mut lines: Vec<String> = vec![];
+        for (index, block) in self.blocks.iter().enumerate() {
+            match (block, markup) {
+                (Block::Header(text), Markup::Html) => {
+                    let tag = if index == 0 { "h2" } else { "h3" };
+                    lines.push(format!("<{}>{}</{}>", tag, text.render(markup), tag));
+                }
+                (Block::Header(text), _) => {
+                    if index > 0 {
+                        lines.push(String::new());
+                    }
+                    match markup {
+                        Markup::Mrkdwn => lines.push(format!("*{}*", text.render(markup))),
+                        _ => lines.push(format!("{}\n", text.render(markup))),
+                    }
+                }
+                (Block::Section(text), Markup::Html) => {
+                    lines.push(format!("<p>{}</p>", text.render(markup)))
+                }
+                (Block::Context(text), Markup::Html) => {
+                    lines.push(format!("<p><small>{}</small></p>", text.render(markup)))
+                }
+                (Block::Section(text), _) | (Block::Context(text), _) => {
+                    lines.push(text.render(markup))
+                }
+                (Block::Fields(fields), Markup::Html) => {
+                    lines.push("<dl>".to_owned());
+                    for (label, value) in fields {
+                        lines.push(format!(
+                            "<dt>{}</dt><dd>{}</dd>",
+                            label.render(markup),
+                            value.render(markup)
+                        ));
+                    }
+                    lines.push("</dl>".to_owned());
+                }
+                (Block::Fields(fields), _) => {
+                    for (label, value) in fields {
+                        let label = Text::new().strong(label.render(Markup::Plain));
+                        lines.push(format!(
+                            "{}: {}",
+                            label.render(markup),
+                            value.render(markup)
+                        ));
+                    }
+                }
+                (Block::List(items), Markup::Html) => {
+                    lines.push("<ul>".to_owned());
+                    for item in items {
+                        lines.push(format!("<li>{}</li>", item.render(markup)));
+                    }
+                    lines.push("</ul>".to_owned());
+                }
+                (Block::List(items), _) => {
+                    let bullet = if markup == Markup::Mrkdwn { "•" } else { "-" };
+                    for item in items {
+                        lines.push(format!("{} {}", bullet, item.render(markup)));
+                    }
+                }
+                (Block::Table { columns, rows }, Markup::Html) => {
+                    let header: String = columns
+                        .iter()
+                        .map(|column| format!("<th>{}</th>", escape(column)))
+                        .collect();
+                    lines.push(format!("<table><tr>{}</tr>", header));
+                    for row in rows {
+                        let cells: String = row
+                            .iter()
+                            .map(|cell| format!("<td>{}</td>", cell.render(markup)))
+                            .collect();
+                        lines.push(format!("<tr>{}</tr>", cells));
+                    }
+                    lines.push("</table>".to_owned());
+                }
+                (Block::Table { columns, rows }, _) => {
+                    lines.extend(table_lines(columns, rows, markup))
+                }
+                (Block::Divider, Markup::Html) => lines.push("<hr>".to_owned()),
+                (Block::Divider, _) | (Block::Actions(_), _) => (),
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A response using every kind of block
+    fn sample() -> ResponseDoc {
+        let mut doc = ResponseDoc::new();
+        doc.header("Team <ops>")
+            .section(
+                Text::new()
+                    .mention("U1", "Alice")
+                    .text(" is ")
+                    .strong("away"),
+            )
+            .divider()
+            .list(vec![Text::from("one & two"), Text::new().emphasis("three")])
+            .table(
+                &["User", "Status"],
+                vec![vec![Text::new().mention("U1", "Alice"), Text::from("home")]],
+            )
+            .actions(vec![
+                Button::new("approve", "Approve", "1").style(ButtonStyle::Primary),
+                Button::new("deny", "Deny", "1"),
+            ]);
+        doc
+    }
+
+    #[test]
+    fn block_kit() {
+        insta::assert_json_snapshot!(sample().to_blocks(), @r###"
+        [
+          {
+            "text": {
+              "text": "Team <ops>",
+              "type": "plain_text"
+            },
+            "type": "header"
+          },
+          {
+            "text": {
+              "text": "<@U1> is *away*",
+              "type": "mrkdwn"
+            },
+            "type": "section"
+          },
+          {
+            "type": "divider"
+          },
+          {
+            "text": {
+              "text": "• one &amp; two",
+              "type": "mrkdwn"
+            },
+            "type": "section"
+          },
+          {
+            "text": {
+              "text": "• _three_",
+              "type": "mrkdwn"
+            },
+            "type": "section"
+          },
+          {
+            "text": {
+              "text": "*User* | *Status*\n<@U1> | home",
+              "type": "mrkdwn"
+            },
+            "type": "section"
+          },
+          {
+            "elements": [
+              {
+                "action_id": "approve",
+                "style": "primary",
+                "text": {
+                  "text": "Approve",
+                  "type": "plain_text"
+                },
+                "type": "button",
+                "value": "1"
+              },
+              {
+                "action_id": "deny",
+                "text": {
+                  "text": "Deny",
+                  "type": "plain_text"
+                },
+                "type": "button",
+                "value": "1"
+              }
+            ],
+            "type": "actions"
+          }
+        ]
+        "###);
+    }
+
+    #[test]
+    fn mrkdwn() {
+        insta::assert_snapshot!(sample().render(Markup::Mrkdwn), @r###"
+        *Team &lt;ops&gt;*
+        <@U1> is *away*
+        • one &amp; two
+        • _three_
+        *User* | *Status*
+        <@U1> | home
+        "###);
+    }
+
+    #[test]
+    fn plain_text() {
+        insta::assert_snapshot!(sample().render(Markup::Plain), @r###"
+        Team <ops>
+
+        Alice is away
+        - one & two
+        - three
+        User | Status
+        Alice | home
+        "###);
+    }
+
+    #[test]
+    fn html() {
+        insta::assert_snapshot!(sample().render(Markup::Html), @r###"
+        <h2>Team &lt;ops&gt;</h2>
+        <p>Alice is <strong>away</strong></p>
+        <hr>
+        <ul>
+        <li>one &amp; two</li>
+        <li><em>three</em></li>
+        </ul>
+        <table><tr><th>User</th><th>Status</th></tr>
+        <tr><td>Alice</td><td>home</td></tr>
+        </table>
+        "###);
+    }
+}
@@ -0,0 +1,177 @@
+//! Replaying recorded requests (`statusbot replay <file.jsonl>`)
+//!
+//! Each line of the file is a request, as recorded by `--capture-dir`.  Requests are
+//! dispatched in order through the same middleware and handlers as the web server, against
+//! the database given by `--database`, and the response to each is printed.  Background tasks
+//! aren't started, and queued events are processed as soon as the request queueing them
+//! returns, so any errors are reported alongside the request that caused them.
+//!
+//! Recorded signatures will have expired, so they're dropped, and each request is signed
+//! again with `SLACK_SIGNING_SECRET` as it's replayed (or sent unsigned, if started with
+//! `--insecure-skip-signature`).
+
+use crate::{
+    build_app, build_state, connect, connect_replica, feed::StatusFeed, handlers, outbound, outbox,
+    signing, Opt,
+};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use futures::channel::mpsc;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+use tide::http::{Method, Request, Response, Url};
+
+/// Headers that aren't replayed, as they no longer apply
+const DROPPED_HEADERS: &[&str] = &[
+    "accept-encoding",
+    "content-length",
+    "x-slack-request-timestamp",
+    "x-slack-signature",
+];
+
+/// A request, as recorded by `--capture-dir`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecordedRequest {
+    /// HTTP method (e.g., `POST`)
+    pub method: String,
+
+    /// Path of the request, including any query string (e.g., `/location`)
+    pub path: String,
+
+    /// Request headers, by lowercase name
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Raw request body
+    #[serde(default)]
+    pub body: String,
+}
+
+impl RecordedRequest {
+    /// Builds the request to dispatch, signed with a fresh timestamp if a secret is given
+    ///
+    /// # Arguments
+    /// * `secret` - Slack signing secret to sign the request with
+    fn to_request(&self, secret: Option<&str>) -> Result<Request> {
+        let method: Method = self
+            .method
+            .parse()
+            .map_err(|_| anyhow::anyhow!("unknown method {}", self.method))?;
+        let url = Url::parse("http://replay.invalid")?.join(&self.path)?;
+
+        let mut req = Request::new(method, url);
+        for (name, value) in &self.headers {
+            if !DROPPED_HEADERS.contains(&name.to_lowercase().as_str()) {
+                req.insert_header(name.as_str(), value.as_str());
+            }
+        }
+
+        if let Some(secret) = secret {
+            let timestamp = Utc::now().timestamp().to_string();
+            let signature = signing::sign_slack(secret, &timestamp, self.body.as_bytes());
+            req.insert_header("X-Slack-Request-Timestamp", timestamp);
+            req.insert_header("X-Slack-Signature", signature);
+        }
+
+        req.set_body(self.body.clone());
+        Ok(req)
+    }
+}
+
+/// Replays every request recorded in a file, printing the response to each
+///
+/// # Arguments
+/// * `opt` - Command line options and arguments
+/// * `file` - File of recorded requests, one JSON object per line
+pub async fn run(opt: &Opt, file: &Path) -> Result<()> {
+    let recorded = std::fs::read_to_string(file)
+        .with_context(|| format!("failed to read {}", file.display()))?;
+
+    // requests are signed again with the server's secret, which is required like the server's
+    let secret = opt.signing_secret()?;
+
+    // handlers replayed here make the same outbound requests as the bot
+    if !outbound::installed() {
+        outbound::init(&opt.outbound())?;
+    }
+
+    let pool = connect(opt).await?;
+    let feed = StatusFeed::new();
+
+    // events are processed here, after each request, instead of on a worker
+    let (events, mut queued) = mpsc::channel(opt.event_queue_size);
+
+    let replica = connect_replica(opt).await;
+    let state = build_state(
+        opt,
+        pool.clone(),
+        replica,
+        feed.clone(),
+        events,
+        secret.clone(),
+    );
+    let app = build_app(opt, state, opt.rate_limit());
+
+    for (line, json) in recorded.lines().enumerate() {
+        let line = line + 1;
+        if json.trim().is_empty() {
+            continue;
+        }
+
+        let recorded: RecordedRequest = match serde_json::from_str(json) {
+            Ok(recorded) => recorded,
+            Err(e) => {
+                println!("{}: skipped, invalid record: {}", line, e);
+                continue;
+            }
+        };
+
+        let req = match recorded.to_request(secret.as_deref()) {
+            Ok(req) => req,
+            Err(e) => {
+                println!("{}: skipped, invalid request: {}", line, e);
+                continue;
+            }
+        };
+
+        let mut resp: Response = match app.respond(req).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                println!(
+                    "{}: {} {} failed: {}",
+                    line, recorded.method, recorded.path, e
+                );
+                continue;
+            }
+        };
+
+        let body = resp.body_string().await.unwrap_or_default();
+        println!(
+            "{}: {} {} -> {}",
+            line,
+            recorded.method,
+            recorded.path,
+            resp.status()
+        );
+        if !body.is_empty() {
+            println!("{}", body);
+        }
+
+        while let Ok(Some(event)) = queued.try_next() {
+            let event_id = event.event_id.clone();
+            match handlers::event::process(&pool, &feed, event, opt.event_dedup_ttl()).await {
+                Ok(()) => println!("{}: processed event {}", line, event_id),
+                Err(e) => println!("{}: failed to process event {}: {:?}", line, event_id, e),
+            }
+        }
+
+        // side effects are sent after each request too, instead of by a worker
+        match outbox::deliver_due(&pool).await {
+            Ok(0) => (),
+            Ok(count) => println!("{}: delivered {} side effects", line, count),
+            Err(e) => println!("{}: failed to deliver side effects: {:?}", line, e),
+        }
+    }
+
+    Ok(())
+}
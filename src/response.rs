@@ -0,0 +1,150 @@
+//! Responses to slash commands
+
+use crate::{error::Error, outbound, render::ResponseDoc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tide::StatusCode;
+
+/// Who can see a response to a slash command
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseType {
+    /// Only the user who ran the command
+    Ephemeral,
+
+    /// Everyone in the channel the command was run in
+    InChannel,
+}
+
+/// A message sent in response to a slash command, either as the body of the command's
+/// HTTP response or posted to its `response_url`
+#[derive(Clone, Debug, Serialize)]
+pub struct SlashResponse {
+    /// Who can see the response
+    response_type: ResponseType,
+
+    /// Plain text shown in notifications and by clients that can't render blocks
+    #[serde(skip_serializing_if = "String::is_empty")]
+    text: String,
+
+    /// Block Kit blocks making up the message
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    blocks: Vec<Value>,
+
+    /// Replace the message that triggered this response (`response_url` only)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    replace_original: bool,
+}
+
+impl Default for SlashResponse {
+    fn default() -> Self {
+        SlashResponse {
+            response_type: ResponseType::Ephemeral,
+            text: String::new(),
+            blocks: vec![],
+            replace_original: false,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl SlashResponse {
+    /// Creates an empty response only visible to the user who ran the command
+    pub fn new() -> Self {
+        SlashResponse::default()
+    }
+
+    /// Makes this response visible to everyone in the channel
+    pub fn in_channel(mut self) -> Self {
+        self.response_type = ResponseType::InChannel;
+        self
+    }
+
+    /// Replaces the original message when posted to a `response_url`
+    pub fn replace_original(mut self) -> Self {
+        self.replace_original = true;
+        self
+    }
+
+    /// Sets the plain text fallback
+    ///
+    /// If not set, the fallback is built from the text of the response's blocks
+    ///
+    /// # Arguments
+    /// * `text` - Plain text version of the message
+    pub fn text<S: Into<String>>(mut self, text: S) -> Self {
+        self.text = text.into();
+        self
+    }
+
+    /// Appends a Block Kit block to the message
+    ///
+    /// # Arguments
+    /// * `block` - The block to append
+    pub fn push(&mut self, block: Value) {
+        self.blocks.push(block);
+    }
+
+    /// Appends the blocks of a response laid out with `ResponseDoc`
+    ///
+    /// # Arguments
+    /// * `doc` - The response
+    pub fn extend(&mut self, doc: &ResponseDoc) {
+        self.blocks.extend(doc.to_blocks());
+    }
+
+    /// Returns the JSON body of this response, filling in the plain text fallback
+    pub fn to_json(&self) -> Value {
+        let mut value = json!(self);
+
+        if self.text.is_empty() {
+            let text = self
+                .blocks
+                .iter()
+                .filter_map(|block| block["text"]["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            value["text"] = Value::String(text);
+        }
+
+        value
+    }
+
+    /// Posts this response to a slash command's `response_url`
+    ///
+    /// # Arguments
+    /// * `response_url` - URL Slack provided with the command
+    pub async fn post(&self, response_url: &str) -> Result<(), Error> {
+        let resp = outbound::post(response_url)
+            .body_json(&self.to_json())
+            .map_err(|e| Error::SlackApi(format!("response_url: {}", e)))?
+            .send()
+            .await
+            .map_err(|e| Error::SlackApi(format!("response_url: {}", e)))?;
+
+        let code = resp.status();
+        if code.is_client_error() || code.is_server_error() {
+            return Err(Error::SlackApi(format!("response_url: HTTP {}", code)));
+        }
+
+        Ok(())
+    }
+}
+
+impl From<ResponseDoc> for SlashResponse {
+    fn from(doc: ResponseDoc) -> Self {
+        let mut resp = SlashResponse::new();
+        resp.extend(&doc);
+        resp
+    }
+}
+
+impl From<SlashResponse> for tide::Response {
+    fn from(resp: SlashResponse) -> Self {
+        tide::Response::builder(StatusCode::Ok)
+            .header("Content-Type", "application/json")
+            .body(resp.to_json())
+            .build()
+    }
+}
@@ -0,0 +1,85 @@
+//! Today's shifts of each team
+//!
+//! Run by the morning scheduler; every team with a bound channel and shifts running today
+//! gets a post listing who is on each of them.
+
+use crate::{
+    models::{Shift, Team, User},
+    slack, SqlConn,
+};
+use anyhow::Result;
+use chrono::{Datelike, Utc, Weekday};
+
+/// Fetches a team's shifts that run on a weekday, along with the members assigned to each
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `team` - The team
+/// * `weekday` - Day of the week
+pub async fn shifts_on(
+    db: &mut SqlConn,
+    team: &Team,
+    weekday: Weekday,
+) -> Result<Vec<(Shift, Vec<User>)>> {
+    let mut shifts = vec![];
+    for shift in Shift::fetch_by_team(&mut *db, team).await? {
+        if shift.runs_on(weekday) {
+            let members = shift.members(&mut *db).await?;
+            shifts.push((shift, members));
+        }
+    }
+
+    Ok(shifts)
+}
+
+/// Describes a shift and who is on it on a single line (e.g., `*early* (06:00-14:00): @bob`)
+///
+/// # Arguments
+/// * `shift` - The shift
+/// * `members` - Members assigned to the shift
+pub fn describe(shift: &Shift, members: &[User]) -> String {
+    let mut text = format!("*{}*", shift.name);
+    if let Some(hours) = &shift.hours {
+        text.push_str(&format!(" ({})", hours));
+    }
+
+    if members.is_empty() {
+        text.push_str(": nobody assigned");
+    } else {
+        let mentions: Vec<String> = members.iter().map(|m| format!("<@{}>", m.id)).collect();
+        text.push_str(&format!(": {}", mentions.join(", ")));
+    }
+
+    text
+}
+
+/// Posts today's shifts of every team in its bound channel
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+pub async fn post(db: &mut SqlConn) -> Result<()> {
+    let weekday = Utc::now().weekday();
+
+    for team in Team::fetch_all(&mut *db).await? {
+        let channel = match &team.channel {
+            Some(channel) => channel,
+            None => continue,
+        };
+
+        let shifts = shifts_on(&mut *db, &team, weekday).await?;
+        if shifts.is_empty() {
+            continue;
+        }
+
+        let mut text = format!("Today's shifts for team *{}*:", team.name);
+        for (shift, members) in &shifts {
+            text.push_str(&format!("\n• {}", describe(shift, members)));
+        }
+
+        if let Err(e) = slack::chat_post_message(channel, &text).await {
+            tracing::error!("Failed to post shifts of team {}: {:?}", team.name, e);
+        }
+    }
+
+    Ok(())
+}
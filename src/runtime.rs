@@ -0,0 +1,107 @@
+//! Executor abstraction
+//!
+//! Background tasks are spawned through this module so the bot can run on either
+//! async-std (feature `rt-async-std`, the default) or tokio (feature `rt-tokio`).
+
+use std::{future::Future, time::Duration};
+
+/// Error returned when a future does not complete before its timeout
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedOut;
+
+/// Spawns a future onto the runtime, detaching it
+///
+/// # Arguments
+/// * `future` - Future to run in the background
+#[cfg(feature = "rt-async-std")]
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    async_std::task::spawn(future);
+}
+
+/// Spawns a future onto the runtime, detaching it
+///
+/// # Arguments
+/// * `future` - Future to run in the background
+#[cfg(feature = "rt-tokio")]
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(future);
+}
+
+/// Sleeps for the specified duration
+///
+/// # Arguments
+/// * `duration` - How long to sleep
+#[cfg(feature = "rt-async-std")]
+pub async fn sleep(duration: Duration) {
+    async_std::task::sleep(duration).await
+}
+
+/// Sleeps for the specified duration
+///
+/// # Arguments
+/// * `duration` - How long to sleep
+#[cfg(feature = "rt-tokio")]
+pub async fn sleep(duration: Duration) {
+    tokio::time::delay_for(duration).await
+}
+
+/// Awaits a future, giving up if it does not complete within `duration`
+///
+/// # Arguments
+/// * `duration` - Maximum time to wait
+/// * `future` - Future to await
+#[cfg(feature = "rt-async-std")]
+pub async fn timeout<F, T>(duration: Duration, future: F) -> Result<T, TimedOut>
+where
+    F: Future<Output = T>,
+{
+    async_std::future::timeout(duration, future)
+        .await
+        .map_err(|_| TimedOut)
+}
+
+/// Awaits a future, giving up if it does not complete within `duration`
+///
+/// # Arguments
+/// * `duration` - Maximum time to wait
+/// * `future` - Future to await
+#[cfg(feature = "rt-tokio")]
+pub async fn timeout<F, T>(duration: Duration, future: F) -> Result<T, TimedOut>
+where
+    F: Future<Output = T>,
+{
+    tokio::time::timeout(duration, future)
+        .await
+        .map_err(|_| TimedOut)
+}
+
+/// Runs a future to completion, blocking the current thread
+///
+/// # Arguments
+/// * `future` - Future to run
+#[cfg(feature = "rt-async-std")]
+pub fn block_on<F, T>(future: F) -> anyhow::Result<T>
+where
+    F: Future<Output = T>,
+{
+    Ok(async_std::task::block_on(future))
+}
+
+/// Runs a future to completion, blocking the current thread
+///
+/// # Arguments
+/// * `future` - Future to run
+#[cfg(feature = "rt-tokio")]
+pub fn block_on<F, T>(future: F) -> anyhow::Result<T>
+where
+    F: Future<Output = T>,
+{
+    let mut rt = tokio::runtime::Runtime::new()?;
+    Ok(rt.block_on(future))
+}
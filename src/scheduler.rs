@@ -0,0 +1,162 @@
+//! The morning scheduler run
+//!
+//! Once a day, at the configured hour, users on leave are marked out of office, statuses set
+//! ahead of time for whole teams are set, checks that need to happen before the workday starts
+//! are run, today's shifts are posted, and team digests (and, on Mondays, the audit report)
+//! are sent.
+
+use crate::{
+    audit, changes, coverage, digest,
+    feed::StatusFeed,
+    models::{Availability, BulkStatus, Leave, User},
+    rota, runtime, SqlConn, SqlPool,
+};
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use std::collections::HashSet;
+
+/// Configuration of the morning run
+#[derive(Clone, Debug)]
+pub struct MorningConfig {
+    /// Hour of the day (UTC) to run at
+    pub hour: u32,
+
+    /// Channel to warn about teams nobody is covering, if coverage is checked
+    pub coverage_channel: Option<String>,
+
+    /// How often digests are sent to teams that haven't chosen, if at all
+    pub digest: Option<digest::Frequency>,
+
+    /// Channel to post the weekly audit report in, if it's posted
+    pub security_channel: Option<String>,
+}
+
+/// Marks everyone on leave today out of office, and everyone whose leave ended yesterday
+/// available again
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `feed` - Feed to publish status changes to
+async fn start_leave(db: &mut SqlConn, feed: &StatusFeed) -> Result<()> {
+    let today = Utc::now().date().naive_utc();
+    let on_leave: HashSet<String> = Leave::fetch_on(&mut *db, today)
+        .await?
+        .into_iter()
+        .map(|leave| leave.user_id)
+        .collect();
+
+    let returning: HashSet<String> = Leave::fetch_on(&mut *db, today - Duration::days(1))
+        .await?
+        .into_iter()
+        .map(|leave| leave.user_id)
+        .filter(|user_id| !on_leave.contains(user_id))
+        .collect();
+
+    for user_id in &on_leave {
+        if let Some(mut user) = User::fetch(&mut *db, user_id).await? {
+            if user.availability() != Some(Availability::Ooo) {
+                user.set_availability(Availability::Ooo);
+                changes::save(&mut *db, &user, None).await?;
+                feed.publish_saved(&mut *db, &user.id).await;
+            }
+        }
+    }
+
+    // users who came back early have already said so
+    for user_id in &returning {
+        if let Some(mut user) = User::fetch(&mut *db, user_id).await? {
+            if user.availability() == Some(Availability::Ooo) {
+                user.set_availability(Availability::Available);
+                changes::save(&mut *db, &user, None).await?;
+                feed.publish_saved(&mut *db, &user.id).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs everything scheduled for the morning
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `feed` - Feed to publish status changes to
+/// * `config` - Configuration of the morning run
+async fn run(pool: &SqlPool, feed: &StatusFeed, config: &MorningConfig) -> Result<()> {
+    let mut db = pool.acquire().await?;
+
+    // leave starts before coverage is checked, so it counts against it
+    if let Err(e) = start_leave(&mut db, feed).await {
+        tracing::error!("failed to start leave: {:?}", e);
+    }
+
+    let today = Utc::now().date().naive_utc();
+    match BulkStatus::apply_due(&mut db, today).await {
+        Ok(members) if members.is_empty() => (),
+        Ok(members) => {
+            tracing::info!("set team-wide statuses of {} members", members.len());
+            for member in &members {
+                feed.publish_saved(&mut db, &member.id).await;
+            }
+        }
+        Err(e) => tracing::error!("failed to set team-wide statuses: {:?}", e),
+    }
+
+    if let Some(channel) = &config.coverage_channel {
+        if let Err(e) = coverage::check(&mut db, channel).await {
+            tracing::error!("failed to check coverage: {:?}", e);
+        }
+    }
+
+    if let Err(e) = rota::post(&mut db).await {
+        tracing::error!("failed to post shifts: {:?}", e);
+    }
+
+    if let Err(e) = digest::send_all(&mut db, today, config.digest).await {
+        tracing::error!("failed to send digests: {:?}", e);
+    }
+
+    if let Some(channel) = &config.security_channel {
+        if let Err(e) = audit::post_weekly(&mut db, channel, today).await {
+            tracing::error!("failed to post the audit report: {:?}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Spawns a task that runs the morning run once a day
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `feed` - Feed to publish status changes to
+/// * `config` - Configuration of the morning run
+pub fn spawn(pool: SqlPool, feed: StatusFeed, config: MorningConfig) {
+    runtime::spawn(async move {
+        loop {
+            // sleep until the next time the configured hour comes around
+            let now = Utc::now();
+            let today = match now.date().and_hms_opt(config.hour, 0, 0) {
+                Some(today) => today,
+                None => {
+                    tracing::error!("invalid morning hour: {}", config.hour);
+                    return;
+                }
+            };
+
+            let next = if today <= now {
+                today + Duration::days(1)
+            } else {
+                today
+            };
+
+            let wait = (next - now).to_std().unwrap_or_default();
+            tracing::debug!("next morning run at {} ({}s)", next, wait.as_secs());
+            runtime::sleep(wait).await;
+
+            if let Err(e) = run(&pool, &feed, &config).await {
+                tracing::error!("morning run failed: {:?}", e);
+            }
+        }
+    });
+}
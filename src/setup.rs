@@ -0,0 +1,479 @@
+//! A modal that walks a new workspace through its initial configuration
+//!
+//! `/location setup` (admins only) opens a modal with three steps: the first team (its name,
+//! description, and lead), the channel bound to it, and its digest and presets (custom fields
+//! and a coverage requirement).  Each step is checked when it's submitted, and replaced by the
+//! next one, carrying what was entered so far in the view's private metadata.  Submitting the
+//! last step creates the team in a single transaction, so an abandoned or failed setup leaves
+//! nothing behind, and sends the admin a DM summarizing what was set up.
+
+use crate::{
+    digest::Frequency,
+    error::Error,
+    handlers::auth::{self, Role},
+    models::{validate_name, AuditEntry, MemberRole, Team, TeamField, User},
+    outbox::{self, Effect},
+    render::{self, ResponseDoc, Text},
+    slack, SqlConn,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Callback id of the first step, naming the team
+pub const TEAM_CALLBACK_ID: &str = "setup_team";
+
+/// Callback id of the second step, binding a channel
+pub const CHANNEL_CALLBACK_ID: &str = "setup_channel";
+
+/// Callback id of the last step, choosing the digest and presets
+pub const PRESETS_CALLBACK_ID: &str = "setup_presets";
+
+/// Custom fields offered as presets
+const FIELD_PRESETS: &[&str] = &["Desk", "Phone extension", "Badge number"];
+
+/// Weekdays a coverage requirement chosen in the wizard applies on (Monday to Friday)
+const WEEKDAYS: i64 = 0b11111;
+
+/// What was entered in the steps submitted so far
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Draft {
+    /// Name of the team
+    name: String,
+
+    /// What the team does
+    description: Option<String>,
+
+    /// Slack ID of the team's lead
+    lead: Option<String>,
+
+    /// Slack ID of the channel bound to the team
+    channel: Option<String>,
+
+    /// Post status changes of the team's members in its channel
+    notify_changes: bool,
+}
+
+/// Returns true if a modal is one of the wizard's steps
+///
+/// # Arguments
+/// * `callback_id` - Callback id of the modal
+pub fn is_step(callback_id: &str) -> bool {
+    [TEAM_CALLBACK_ID, CHANNEL_CALLBACK_ID, PRESETS_CALLBACK_ID].contains(&callback_id)
+}
+
+/// Builds a step of the wizard
+///
+/// # Arguments
+/// * `callback_id` - Which step
+/// * `draft` - What was entered in the previous steps
+/// * `submit` - Label of the submit button
+/// * `blocks` - Inputs of the step
+fn view(callback_id: &str, draft: &Draft, submit: &str, blocks: Vec<Value>) -> Value {
+    json!({
+        "type": "modal",
+        "callback_id": callback_id,
+        "private_metadata": serde_json::to_string(draft).unwrap_or_default(),
+        "title": { "type": "plain_text", "text": "Workspace setup" },
+        "submit": { "type": "plain_text", "text": submit },
+        "blocks": blocks,
+    })
+}
+
+/// Builds a static menu option
+///
+/// # Arguments
+/// * `value` - Value of the option
+/// * `label` - What the option is shown as
+fn option(value: &str, label: &str) -> Value {
+    json!({
+        "text": { "type": "plain_text", "text": label },
+        "value": value,
+    })
+}
+
+/// Opens the wizard
+///
+/// # Arguments
+/// * `user_id` - Slack ID of the admin running the command
+/// * `trigger_id` - Trigger received with the command
+pub async fn open(user_id: &str, trigger_id: &str) -> Result<()> {
+    let blocks = vec![
+        json!({
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": "Let's set up your first team. You can add more later with `/location team create <team>`.",
+            },
+        }),
+        json!({
+            "type": "input",
+            "block_id": "name",
+            "label": { "type": "plain_text", "text": "Team name" },
+            "element": { "type": "plain_text_input", "action_id": "value" },
+        }),
+        json!({
+            "type": "input",
+            "block_id": "description",
+            "optional": true,
+            "label": { "type": "plain_text", "text": "What the team does" },
+            "element": { "type": "plain_text_input", "action_id": "value" },
+        }),
+        json!({
+            "type": "input",
+            "block_id": "lead",
+            "optional": true,
+            "label": { "type": "plain_text", "text": "Team lead" },
+            "element": {
+                "type": "users_select",
+                "action_id": "value",
+                "initial_user": user_id,
+            },
+        }),
+    ];
+
+    slack::views_open(
+        trigger_id,
+        view(TEAM_CALLBACK_ID, &Draft::default(), "Next", blocks),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Builds the second step, binding a channel
+///
+/// # Arguments
+/// * `draft` - What was entered in the first step
+fn channel_step(draft: &Draft) -> Value {
+    let blocks = vec![
+        json!({
+            "type": "input",
+            "block_id": "channel",
+            "optional": true,
+            "label": { "type": "plain_text", "text": "Team channel" },
+            "hint": {
+                "type": "plain_text",
+                "text": "Digests and status changes are posted here. Invite the bot to it first.",
+            },
+            "element": { "type": "conversations_select", "action_id": "value" },
+        }),
+        json!({
+            "type": "input",
+            "block_id": "notify",
+            "optional": true,
+            "label": { "type": "plain_text", "text": "Status changes" },
+            "element": {
+                "type": "checkboxes",
+                "action_id": "value",
+                "options": [option("on", "Post members' status changes in the channel")],
+            },
+        }),
+    ];
+
+    view(CHANNEL_CALLBACK_ID, draft, "Next", blocks)
+}
+
+/// Builds the last step, choosing the digest and presets
+///
+/// # Arguments
+/// * `draft` - What was entered in the previous steps
+fn presets_step(draft: &Draft) -> Value {
+    let fields: Vec<Value> = FIELD_PRESETS
+        .iter()
+        .map(|label| option(label, label))
+        .collect();
+
+    let blocks = vec![
+        json!({
+            "type": "input",
+            "block_id": "digest",
+            "label": { "type": "plain_text", "text": "Digest" },
+            "element": {
+                "type": "static_select",
+                "action_id": "value",
+                "initial_option": option("default", "Workspace default"),
+                "options": [
+                    option("default", "Workspace default"),
+                    option("daily", "Every morning"),
+                    option("weekly", "Monday mornings, with the week's leave"),
+                ],
+            },
+        }),
+        json!({
+            "type": "input",
+            "block_id": "fields",
+            "optional": true,
+            "label": { "type": "plain_text", "text": "Custom fields to collect" },
+            "element": {
+                "type": "checkboxes",
+                "action_id": "value",
+                "options": fields,
+            },
+        }),
+        json!({
+            "type": "input",
+            "block_id": "coverage",
+            "optional": true,
+            "label": { "type": "plain_text", "text": "Members needed on site each weekday" },
+            "element": { "type": "plain_text_input", "action_id": "value" },
+        }),
+    ];
+
+    view(PRESETS_CALLBACK_ID, draft, "Set up", blocks)
+}
+
+/// Returns the value of one of a step's inputs, if it was filled in
+///
+/// # Arguments
+/// * `values` - Values of the modal's inputs, keyed by block id
+/// * `block_id` - Which input
+fn value(values: &Value, block_id: &str) -> Option<String> {
+    let value = &values[block_id]["value"];
+    value["selected_option"]["value"]
+        .as_str()
+        .or_else(|| value["selected_user"].as_str())
+        .or_else(|| value["selected_conversation"].as_str())
+        .or_else(|| value["value"].as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+}
+
+/// Returns the values of the options checked in one of a step's checkboxes
+///
+/// # Arguments
+/// * `values` - Values of the modal's inputs, keyed by block id
+/// * `block_id` - Which input
+fn checked(values: &Value, block_id: &str) -> Vec<String> {
+    values[block_id]["value"]["selected_options"]
+        .as_array()
+        .map(|options| {
+            options
+                .iter()
+                .filter_map(|option| option["value"].as_str())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Builds a response that shows an error next to one of the modal's inputs
+///
+/// # Arguments
+/// * `block_id` - Which input
+/// * `reason` - The error
+fn errors(block_id: &str, reason: &str) -> Value {
+    json!({
+        "response_action": "errors",
+        "errors": { block_id: reason },
+    })
+}
+
+/// Builds a response that replaces the modal with the next step
+///
+/// # Arguments
+/// * `view` - The next step
+fn update(view: Value) -> Value {
+    json!({
+        "response_action": "update",
+        "view": view,
+    })
+}
+
+/// Handles a submission of one of the wizard's steps, returning the response to send Slack:
+/// the next step, or the errors to show in the modal.  `None` closes the modal.
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `user_id` - Slack ID of the admin who submitted the step
+/// * `view` - The submitted modal
+pub async fn submit(db: &mut SqlConn, user_id: &str, view: &Value) -> Result<Option<Value>> {
+    let values = &view["state"]["values"];
+    let mut draft: Draft = view["private_metadata"]
+        .as_str()
+        .and_then(|metadata| serde_json::from_str(metadata).ok())
+        .unwrap_or_default();
+
+    match view["callback_id"].as_str().unwrap_or_default() {
+        TEAM_CALLBACK_ID => {
+            let name = value(values, "name").unwrap_or_default();
+            if let Err(e) = validate_name(&name) {
+                return Ok(Some(errors("name", &e.user_message())));
+            }
+
+            if Team::fetch(&mut *db, &name).await.is_some() {
+                return Ok(Some(errors(
+                    "name",
+                    &format!("Team {} already exists", name),
+                )));
+            }
+
+            draft.name = name;
+            draft.description = value(values, "description");
+            draft.lead = value(values, "lead");
+            Ok(Some(update(channel_step(&draft))))
+        }
+        CHANNEL_CALLBACK_ID => {
+            draft.channel = value(values, "channel");
+            draft.notify_changes = !checked(values, "notify").is_empty();
+            if draft.notify_changes && draft.channel.is_none() {
+                return Ok(Some(errors(
+                    "notify",
+                    "Please choose a channel to post status changes in",
+                )));
+            }
+
+            Ok(Some(update(presets_step(&draft))))
+        }
+        PRESETS_CALLBACK_ID => {
+            let digest = match value(values, "digest").as_deref() {
+                None | Some("default") => None,
+                Some(frequency) => match frequency.parse::<Frequency>() {
+                    Ok(_) => Some(frequency.to_owned()),
+                    Err(e) => return Ok(Some(errors("digest", &e))),
+                },
+            };
+
+            let coverage = match value(values, "coverage") {
+                None => None,
+                Some(min) => match min.parse::<i64>() {
+                    Ok(min) if min > 0 => Some(min),
+                    _ => {
+                        return Ok(Some(errors(
+                            "coverage",
+                            "Please enter a number of members, or leave it empty",
+                        )))
+                    }
+                },
+            };
+
+            // the team is created as an admin, so only admins may submit the last step
+            if auth::role_for(user_id) != Role::Admin {
+                return Ok(Some(errors(
+                    "digest",
+                    "Only admins may set up the workspace",
+                )));
+            }
+
+            let fields = checked(values, "fields");
+            match create(&mut *db, user_id, &draft, digest, &fields, coverage).await {
+                Ok(()) => Ok(None),
+                Err(e) => match e.downcast::<Error>() {
+                    Ok(e) => Ok(Some(errors("digest", &e.user_message()))),
+                    Err(e) => Err(e),
+                },
+            }
+        }
+        callback_id => Err(anyhow::anyhow!("unknown setup step `{}`", callback_id)),
+    }
+}
+
+/// Creates the team set up in the wizard, and sends the admin a summary
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `user_id` - Slack ID of the admin
+/// * `draft` - The team, its lead, and its channel
+/// * `digest` - How often the team is sent a digest, if not the `DIGEST` default
+/// * `fields` - Labels of the custom fields the team collects
+/// * `coverage` - Members the team needs on site each weekday, if any
+async fn create(
+    db: &mut SqlConn,
+    user_id: &str,
+    draft: &Draft,
+    digest: Option<String>,
+    fields: &[String],
+    coverage: Option<i64>,
+) -> Result<()> {
+    transaction!(db, async {
+        let mut team = Team::new(&mut *db, &draft.name, user_id).await?;
+        team.description = draft.description.clone();
+        team.channel = draft.channel.clone();
+        team.notify_changes = draft.notify_changes;
+        team.digest = digest;
+        if let Some(min) = coverage {
+            team.min_coverage = Some(min);
+            team.coverage_days = WEEKDAYS;
+        }
+        team.save(&mut *db).await?;
+
+        if let Some(lead) = &draft.lead {
+            let user = User::fetch_or_create(&mut *db, lead).await?;
+            team.add_member(&mut *db, &user).await?;
+            team.set_role(&mut *db, &user, MemberRole::Lead).await?;
+            AuditEntry::record(
+                &mut *db,
+                user_id,
+                AuditEntry::ROLE_GRANTED,
+                &team.name,
+                Some(&user.id),
+                Some(MemberRole::Lead.as_str()),
+            )
+            .await?;
+        }
+
+        for label in fields {
+            TeamField::add(&mut *db, &team, label).await?;
+        }
+
+        let effect = Effect::PostMessage {
+            channel: user_id.to_owned(),
+            text: summary(&team, draft.lead.as_deref(), fields).render(render::slack()),
+        };
+        outbox::enqueue(&mut *db, &effect).await?;
+
+        Ok::<_, anyhow::Error>(())
+    })
+}
+
+/// Lays out what the wizard set up
+///
+/// # Arguments
+/// * `team` - The team created
+/// * `lead` - Slack ID of the team's lead, if any
+/// * `fields` - Labels of the custom fields the team collects
+fn summary(team: &Team, lead: Option<&str>, fields: &[String]) -> ResponseDoc {
+    let mut items = vec![];
+    if let Some(lead) = lead {
+        items.push(Text::from("Lead: ").mention(lead, lead));
+    }
+
+    items.push(match &team.channel {
+        Some(channel) if team.notify_changes => Text::from("Channel: ")
+            .slack(format!("<#{}>", channel), channel.as_str())
+            .text(", with status changes"),
+        Some(channel) => Text::from("Channel: ").slack(format!("<#{}>", channel), channel.as_str()),
+        None => Text::from("Channel: none"),
+    });
+
+    items.push(Text::from(format!(
+        "Digest: {}",
+        team.digest.as_deref().unwrap_or("workspace default")
+    )));
+
+    if !fields.is_empty() {
+        items.push(Text::from(format!("Custom fields: {}", fields.join(", "))));
+    }
+
+    if let Some(min) = team.min_coverage {
+        items.push(Text::from(format!(
+            "Coverage: {} on site each weekday",
+            min
+        )));
+    }
+
+    let mut doc = ResponseDoc::new();
+    doc.section(
+        Text::new()
+            .text(":tada: Team ")
+            .strong(&team.name)
+            .text(" is set up"),
+    );
+    doc.list(items);
+    doc.section(format!(
+        "Members can join with `/location team {} add <username>`",
+        team.name
+    ));
+    doc
+}
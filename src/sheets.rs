@@ -0,0 +1,200 @@
+//! Daily export of team statuses to a Google Sheet
+//!
+//! Enabled with the `sheets` feature.  Once a day, a row (date, team, name, user, status,
+//! custom fields) is appended to the configured spreadsheet for every member of every team.
+//! Requests are authorized using a Google service account.
+
+use crate::{
+    models::{normalize_name, Profile, Team, TeamField, User},
+    outbound, runtime, SqlPool,
+};
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use futures::TryStreamExt;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// OAuth scope required to append rows to a spreadsheet
+const SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+
+/// Where and when to export statuses
+#[derive(Clone, Debug)]
+pub struct SheetsConfig {
+    /// Path to the service account's JSON key file
+    pub credentials: String,
+
+    /// Id of the spreadsheet to append to
+    pub spreadsheet_id: String,
+
+    /// Sheet (or A1 range) to append rows to
+    pub range: String,
+
+    /// Hour of the day (UTC) to run the export
+    pub hour: u32,
+}
+
+/// The fields of a service account key file we need
+#[derive(Debug, Deserialize)]
+struct Credentials {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+/// Claims of the JWT exchanged for an access token
+#[derive(Debug, Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+/// Exchanges the service account's credentials for an access token
+///
+/// # Arguments
+/// * `creds` - Service account credentials
+async fn access_token(creds: &Credentials) -> Result<String> {
+    let now = Utc::now().timestamp();
+    let claims = Claims {
+        iss: &creds.client_email,
+        scope: SCOPE,
+        aud: &creds.token_uri,
+        iat: now,
+        exp: now + 3600,
+    };
+
+    let key = EncodingKey::from_rsa_pem(creds.private_key.as_bytes())?;
+    let assertion = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key)?;
+
+    let mut resp = outbound::post(&creds.token_uri)
+        .set_header("Content-Type", "application/x-www-form-urlencoded")
+        .body_string(format!(
+            "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer&assertion={}",
+            assertion
+        ))
+        .send()
+        .await
+        .map_err(|e| anyhow!("token request failed: {}", e))?;
+
+    let body: Value = resp
+        .body_json()
+        .await
+        .map_err(|e| anyhow!("token response invalid: {}", e))?;
+
+    match body["access_token"].as_str() {
+        Some(token) => Ok(token.to_owned()),
+        None => Err(anyhow!("token request failed: {}", body)),
+    }
+}
+
+/// Appends rows to the configured sheet
+///
+/// # Arguments
+/// * `config` - Export configuration
+/// * `rows` - Rows to append
+async fn append(config: &SheetsConfig, rows: Vec<Vec<String>>) -> Result<()> {
+    let creds: Credentials = serde_json::from_slice(&std::fs::read(&config.credentials)?)?;
+    let token = access_token(&creds).await?;
+
+    let resp = outbound::post(format!(
+        "https://sheets.googleapis.com/v4/spreadsheets/{}/values/{}:append?valueInputOption=RAW",
+        config.spreadsheet_id, config.range
+    ))
+    .set_header("Authorization", format!("Bearer {}", token))
+    .body_json(&json!({ "values": rows }))
+    .map_err(|e| anyhow!("{}", e))?
+    .send()
+    .await
+    .map_err(|e| anyhow!("append request failed: {}", e))?;
+
+    let code = resp.status();
+    if code.is_client_error() || code.is_server_error() {
+        return Err(anyhow!("append request failed: HTTP {}", code));
+    }
+
+    Ok(())
+}
+
+/// Exports the current status of every member of every team
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `config` - Export configuration
+pub async fn export(pool: &SqlPool, config: &SheetsConfig) -> Result<()> {
+    let mut db = pool.acquire().await?;
+    let date = Utc::now().format("%Y-%m-%d").to_string();
+
+    // display names come from the cached profiles, so no slack calls are needed
+    let names: HashMap<String, String> = Profile::fetch_all(&mut db)
+        .await?
+        .into_iter()
+        .filter_map(|profile| Some((profile.user_id, profile.display_name?)))
+        .collect();
+
+    let mut rows = vec![];
+    for team in Team::fetch_all(&mut db).await? {
+        let fields = TeamField::fetch_by_team(&mut db, &team).await?;
+        let normalized = normalize_name(&team.name);
+        let members: Vec<User> = Team::stream_members(&mut db, &normalized)
+            .try_collect()
+            .await?;
+
+        for member in members {
+            let values = User::fetch_fields(&mut db, &member.id).await?;
+            rows.push(vec![
+                date.clone(),
+                team.name.clone(),
+                names.get(&member.id).cloned().unwrap_or_default(),
+                member.id,
+                member.status.unwrap_or_default(),
+                TeamField::describe(&fields, &values).unwrap_or_default(),
+            ]);
+        }
+    }
+
+    if !rows.is_empty() {
+        append(config, rows).await?;
+    }
+
+    Ok(())
+}
+
+/// Spawns a task that runs the export once a day
+///
+/// # Arguments
+/// * `pool` - A configured sql pool
+/// * `config` - Export configuration
+pub fn spawn(pool: SqlPool, config: SheetsConfig) {
+    runtime::spawn(async move {
+        loop {
+            // sleep until the next time the configured hour comes around
+            let now = Utc::now();
+            let today = match now.date().and_hms_opt(config.hour, 0, 0) {
+                Some(today) => today,
+                None => {
+                    tracing::error!("invalid sheets export hour: {}", config.hour);
+                    return;
+                }
+            };
+
+            let next = if today <= now {
+                today + Duration::days(1)
+            } else {
+                today
+            };
+
+            let wait = (next - now).to_std().unwrap_or_default();
+            tracing::debug!("next sheets export at {} ({}s)", next, wait.as_secs());
+            runtime::sleep(wait).await;
+
+            match export(&pool, &config).await {
+                Ok(()) => tracing::info!("exported statuses to google sheets"),
+                Err(e) => tracing::error!("failed to export statuses to google sheets: {:?}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,175 @@
+//! Request signing, as used by Slack
+//!
+//! A request is signed by computing the HMAC-SHA256 of `v0:<timestamp>:<body>` with a
+//! shared secret; the signature is sent as `v0=<hex digest>`.
+//!
+//! Webhooks sent by StatusBot are signed the same way with their own secret, as
+//! `v1:<timestamp>:<body>`, so consumers can verify them with `verify_webhook`.
+
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header carrying when a webhook was sent, in seconds since the Unix epoch
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Statusbot-Timestamp";
+
+/// Header carrying the signature of a webhook
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Statusbot-Signature";
+
+/// Returns the HMAC-SHA256 of `<version>:<timestamp>:<body>`
+///
+/// # Arguments
+/// * `version` - Version of the signing scheme (e.g., `v0`)
+/// * `secret` - The shared secret
+/// * `timestamp` - Time the request was sent
+/// * `body` - Raw request body
+fn mac(version: &str, secret: &str, timestamp: &str, body: &[u8]) -> HmacSha256 {
+    // HMAC accepts keys of any length
+    let mut mac = HmacSha256::new_varkey(secret.as_bytes()).expect("invalid hmac key");
+    mac.update(version.as_bytes());
+    mac.update(b":");
+    mac.update(timestamp.as_bytes());
+    mac.update(b":");
+    mac.update(body);
+    mac
+}
+
+/// Verifies a signature sent as `<version>=<hex digest>`, rejecting requests more than
+/// `max_skew` seconds old (or in the future)
+///
+/// # Arguments
+/// * `version` - Version of the signing scheme (e.g., `v0`)
+/// * `secret` - The shared secret
+/// * `timestamp` - Time the request was sent
+/// * `body` - Raw request body
+/// * `signature` - The signature
+/// * `max_skew` - Maximum age (in seconds) of the request, or `None` to accept requests of
+///   any age
+fn verify(
+    version: &str,
+    secret: &str,
+    timestamp: &str,
+    body: &[u8],
+    signature: &str,
+    max_skew: Option<u64>,
+) -> bool {
+    // reject old requests to prevent replay attacks
+    let fresh = match (timestamp.parse::<i64>(), max_skew) {
+        (Ok(_), None) => true,
+        (Ok(ts), Some(max_skew)) => Utc::now()
+            .timestamp()
+            .checked_sub(ts)
+            .and_then(i64::checked_abs)
+            .map_or(false, |age| age as u64 <= max_skew),
+        (Err(_), _) => false,
+    };
+    if !fresh {
+        return false;
+    }
+
+    let signature = match signature
+        .strip_prefix(version)
+        .and_then(|signature| signature.strip_prefix('='))
+        .and_then(|signature| hex::decode(signature).ok())
+    {
+        Some(signature) => signature,
+        None => return false,
+    };
+
+    mac(version, secret, timestamp, body).verify(&signature).is_ok()
+}
+
+/// Verifies the signature of a request signed by Slack
+///
+/// # Arguments
+/// * `secret` - The app's signing secret
+/// * `timestamp` - Value of the `X-Slack-Request-Timestamp` header
+/// * `body` - Raw request body
+/// * `signature` - Value of the `X-Slack-Signature` header
+/// * `max_skew` - Maximum age (in seconds) of the request, or `None` to accept requests of
+///   any age
+pub fn verify_slack(
+    secret: &str,
+    timestamp: &str,
+    body: &[u8],
+    signature: &str,
+    max_skew: Option<u64>,
+) -> bool {
+    verify("v0", secret, timestamp, body, signature, max_skew)
+}
+
+/// Signs a request the way Slack does, returning the value of the `X-Slack-Signature` header
+///
+/// # Arguments
+/// * `secret` - The app's signing secret
+/// * `timestamp` - Value of the `X-Slack-Request-Timestamp` header
+/// * `body` - Raw request body
+pub fn sign_slack(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let mac = mac("v0", secret, timestamp, body);
+    format!("v0={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Verifies the signature of a webhook sent by StatusBot
+///
+/// The signature header may hold several comma-separated signatures (while a webhook's
+/// secret is being rotated); the webhook is valid if any of them is.
+///
+/// # Arguments
+/// * `secret` - The webhook's secret
+/// * `timestamp` - Value of the `X-Statusbot-Timestamp` header
+/// * `body` - Raw request body
+/// * `signature` - Value of the `X-Statusbot-Signature` header
+/// * `max_skew` - Maximum age (in seconds) of the webhook, or `None` to accept webhooks of
+///   any age
+pub fn verify_webhook(
+    secret: &str,
+    timestamp: &str,
+    body: &[u8],
+    signature: &str,
+    max_skew: Option<u64>,
+) -> bool {
+    signature
+        .split(',')
+        .any(|signature| verify("v1", secret, timestamp, body, signature.trim(), max_skew))
+}
+
+/// Signs a webhook, returning the value of the `X-Statusbot-Signature` header
+///
+/// The signature is the HMAC-SHA256 of `v1:<timestamp>:<body>` with the webhook's secret,
+/// sent as `v1=<hex digest>`
+///
+/// # Arguments
+/// * `secret` - The webhook's secret
+/// * `timestamp` - Value of the `X-Statusbot-Timestamp` header
+/// * `body` - Raw request body
+pub fn sign_webhook(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let mac = mac("v1", secret, timestamp, body);
+    format!("v1={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Verifies the signature of a webhook signed the way GitHub signs them, with the
+/// HMAC-SHA256 of the body sent as `sha256=<hex digest>`
+///
+/// # Arguments
+/// * `secret` - The webhook's secret
+/// * `body` - Raw request body
+/// * `signature` - Value of the `X-Hub-Signature-256` header
+pub fn verify_github(secret: &str, body: &[u8], signature: &str) -> bool {
+    let signature = match signature
+        .strip_prefix("sha256=")
+        .and_then(|signature| hex::decode(signature).ok())
+    {
+        Some(signature) => signature,
+        None => return false,
+    };
+
+    match HmacSha256::new_varkey(secret.as_bytes()) {
+        Ok(mut mac) => {
+            mac.update(body);
+            mac.verify(&signature).is_ok()
+        }
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,501 @@
+//! Minimal client for the Slack Web API
+
+use crate::{error::Error, models::ScheduledMessage, outbound, SqlConn};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use tide::http::{url::Url, StatusCode};
+
+/// Checks the response to a Slack Web API call, returning its body if Slack reports success
+///
+/// # Arguments
+/// * `method` - Name of the API method (e.g. `reactions.add`)
+/// * `status` - Status code of the response
+/// * `retry_after` - `Retry-After` header of the response, if any
+/// * `body` - Body of the response
+fn check(
+    method: &str,
+    status: StatusCode,
+    retry_after: Option<&str>,
+    body: &str,
+) -> Result<Value, Error> {
+    if status == StatusCode::TooManyRequests {
+        return Err(Error::SlackApi(format!(
+            "{}: rate limited, retry after {} seconds",
+            method,
+            retry_after.unwrap_or("an unknown number of")
+        )));
+    }
+
+    if status.is_client_error() || status.is_server_error() {
+        return Err(Error::SlackApi(format!("{}: HTTP {}", method, status)));
+    }
+
+    let value: Value =
+        serde_json::from_str(body).map_err(|e| Error::SlackApi(format!("{}: {}", method, e)))?;
+
+    match value["ok"].as_bool() {
+        Some(true) => Ok(value),
+        _ => Err(Error::SlackApi(format!(
+            "{}: {}",
+            method,
+            value["error"].as_str().unwrap_or("unknown error")
+        ))),
+    }
+}
+
+/// Calls a Slack Web API method, returning the response if Slack reports success
+///
+/// # Arguments
+/// * `method` - Name of the API method (e.g. `reactions.add`)
+/// * `body` - JSON arguments for the method
+pub async fn call(method: &str, body: &Value) -> Result<Value, Error> {
+    let mut resp = outbound::post(format!("https://slack.com/api/{}", method))
+        .set_header(
+            "Authorization",
+            format!(
+                "Bearer {}",
+                dotenv::var("SLACK_BOT_TOKEN").unwrap_or_else(|_| "".to_owned())
+            ),
+        )
+        .body_json(body)
+        .map_err(|e| Error::SlackApi(format!("{}: {}", method, e)))?
+        .send()
+        .await
+        .map_err(|e| Error::SlackApi(format!("{}: {}", method, e)))?;
+
+    let retry_after = resp
+        .header("Retry-After")
+        .map(|values| values.as_str().to_owned());
+    let body = resp
+        .body_string()
+        .await
+        .map_err(|e| Error::SlackApi(format!("{}: {}", method, e)))?;
+
+    check(method, resp.status(), retry_after.as_deref(), &body)
+}
+
+/// Calls a read-only Slack Web API method with query string arguments, returning the
+/// response if Slack reports success
+///
+/// Most read methods (e.g. `users.list`) don't accept JSON bodies
+///
+/// # Arguments
+/// * `method` - Name of the API method (e.g. `users.list`)
+/// * `params` - Arguments for the method; empty values are omitted
+pub async fn get(method: &str, params: &[(&str, &str)]) -> Result<Value, Error> {
+    let mut url = Url::parse(&format!("https://slack.com/api/{}", method))
+        .map_err(|e| Error::SlackApi(format!("{}: {}", method, e)))?;
+
+    url.query_pairs_mut().extend_pairs(
+        params
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (*key, *value)),
+    );
+
+    let mut resp = outbound::get(url.as_str())
+        .set_header(
+            "Authorization",
+            format!(
+                "Bearer {}",
+                dotenv::var("SLACK_BOT_TOKEN").unwrap_or_else(|_| "".to_owned())
+            ),
+        )
+        .send()
+        .await
+        .map_err(|e| Error::SlackApi(format!("{}: {}", method, e)))?;
+
+    let retry_after = resp
+        .header("Retry-After")
+        .map(|values| values.as_str().to_owned());
+    let body = resp
+        .body_string()
+        .await
+        .map_err(|e| Error::SlackApi(format!("{}: {}", method, e)))?;
+
+    check(method, resp.status(), retry_after.as_deref(), &body)
+}
+
+/// Adds an emoji reaction to a message
+///
+/// # Arguments
+/// * `channel` - Channel the message was posted in
+/// * `name` - Name of the emoji (e.g. `thumbsup`)
+/// * `timestamp` - Timestamp of the message
+pub async fn reactions_add(channel: &str, name: &str, timestamp: &str) -> Result<()> {
+    call(
+        "reactions.add",
+        &json!({
+            "channel": channel,
+            "name": name,
+            "timestamp": timestamp
+        }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Posts a message to a channel
+///
+/// # Arguments
+/// * `channel` - Channel to post the message in
+/// * `text` - Text of the message
+pub async fn chat_post_message(channel: &str, text: &str) -> Result<()> {
+    call(
+        "chat.postMessage",
+        &json!({
+            "channel": channel,
+            "text": text
+        }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Replies to a message in its thread
+///
+/// # Arguments
+/// * `channel` - Channel the message was posted in
+/// * `thread_ts` - Timestamp of the thread's first message
+/// * `text` - Text of the reply
+pub async fn chat_post_reply(channel: &str, thread_ts: &str, text: &str) -> Result<()> {
+    call(
+        "chat.postMessage",
+        &json!({
+            "channel": channel,
+            "thread_ts": thread_ts,
+            "text": text
+        }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the user on the other side of a direct message channel, or `None` if the
+/// channel isn't a direct message
+///
+/// # Arguments
+/// * `channel` - Id of the channel
+pub async fn conversations_im_user(channel: &str) -> Result<Option<String>> {
+    let resp = get("conversations.info", &[("channel", channel)]).await?;
+
+    Ok(resp["channel"]["user"].as_str().map(str::to_owned))
+}
+
+/// Posts a message with Block Kit blocks to a channel, returning the message's timestamp
+///
+/// # Arguments
+/// * `channel` - Channel (or user, for a DM) to post the message in
+/// * `text` - Text of the message (used as a fallback for the blocks)
+/// * `blocks` - Block Kit blocks
+pub async fn chat_post_blocks(channel: &str, text: &str, blocks: Value) -> Result<String> {
+    let resp = call(
+        "chat.postMessage",
+        &json!({
+            "channel": channel,
+            "text": text,
+            "blocks": blocks
+        }),
+    )
+    .await?;
+
+    match resp["ts"].as_str() {
+        Some(ts) => Ok(ts.to_owned()),
+        None => Err(anyhow!("chat.postMessage: missing ts")),
+    }
+}
+
+/// Replaces the text and blocks of a message
+///
+/// # Arguments
+/// * `channel` - Channel the message was posted in
+/// * `ts` - Timestamp of the message
+/// * `text` - New text of the message (used as a fallback for the blocks)
+/// * `blocks` - New Block Kit blocks
+pub async fn chat_update(channel: &str, ts: &str, text: &str, blocks: Value) -> Result<()> {
+    call(
+        "chat.update",
+        &json!({
+            "channel": channel,
+            "ts": ts,
+            "text": text,
+            "blocks": blocks
+        }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Opens a modal view
+///
+/// # Arguments
+/// * `trigger_id` - Trigger received from an interaction
+/// * `view` - The view to open
+pub async fn views_open(trigger_id: &str, view: Value) -> Result<()> {
+    call(
+        "views.open",
+        &json!({
+            "trigger_id": trigger_id,
+            "view": view
+        }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Saves the configuration of a workflow step
+///
+/// # Arguments
+/// * `edit_id` - The `workflow_step_edit_id` received when the step was edited
+/// * `inputs` - Inputs the step will receive when executed
+/// * `outputs` - Outputs the step will provide to later steps
+pub async fn workflows_update_step(edit_id: &str, inputs: Value, outputs: Value) -> Result<()> {
+    call(
+        "workflows.updateStep",
+        &json!({
+            "workflow_step_edit_id": edit_id,
+            "inputs": inputs,
+            "outputs": outputs
+        }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Marks a workflow step as successfully completed
+///
+/// # Arguments
+/// * `execute_id` - The `workflow_step_execute_id` received when the step was executed
+/// * `outputs` - Values for the step's outputs
+pub async fn workflows_step_completed(execute_id: &str, outputs: Value) -> Result<()> {
+    call(
+        "workflows.stepCompleted",
+        &json!({
+            "workflow_step_execute_id": execute_id,
+            "outputs": outputs
+        }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Marks a workflow step as failed
+///
+/// # Arguments
+/// * `execute_id` - The `workflow_step_execute_id` received when the step was executed
+/// * `message` - Reason the step failed
+pub async fn workflows_step_failed(execute_id: &str, message: &str) -> Result<()> {
+    call(
+        "workflows.stepFailed",
+        &json!({
+            "workflow_step_execute_id": execute_id,
+            "error": { "message": message }
+        }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Schedules a message to be posted later, returning the id of the scheduled message
+///
+/// # Arguments
+/// * `channel` - Channel to post the message in
+/// * `post_at` - Unix timestamp to post the message at
+/// * `text` - Text of the message (used as a fallback if `blocks` are provided)
+/// * `blocks` - Optional Block Kit blocks
+pub async fn chat_schedule_message(
+    channel: &str,
+    post_at: i64,
+    text: &str,
+    blocks: Option<Value>,
+) -> Result<String> {
+    let mut body = json!({
+        "channel": channel,
+        "post_at": post_at,
+        "text": text
+    });
+
+    if let Some(blocks) = blocks {
+        body["blocks"] = blocks;
+    }
+
+    let resp = call("chat.scheduleMessage", &body).await?;
+    match resp["scheduled_message_id"].as_str() {
+        Some(id) => Ok(id.to_owned()),
+        None => Err(anyhow!(
+            "chat.scheduleMessage: missing scheduled_message_id"
+        )),
+    }
+}
+
+/// Deletes a message that has not yet been posted
+///
+/// # Arguments
+/// * `channel` - Channel the message was scheduled in
+/// * `message_id` - Id returned when the message was scheduled
+pub async fn chat_delete_scheduled_message(channel: &str, message_id: &str) -> Result<()> {
+    call(
+        "chat.deleteScheduledMessage",
+        &json!({
+            "channel": channel,
+            "scheduled_message_id": message_id
+        }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Schedules a message, cancelling any message previously scheduled with the same key
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `key` - Identifies what the message is for (e.g. `digest:<team>`)
+/// * `channel` - Channel to post the message in
+/// * `post_at` - Unix timestamp to post the message at
+/// * `text` - Text of the message
+/// * `blocks` - Optional Block Kit blocks
+#[allow(dead_code)]
+pub async fn schedule_message(
+    db: &mut SqlConn,
+    key: &str,
+    channel: &str,
+    post_at: i64,
+    text: &str,
+    blocks: Option<Value>,
+) -> Result<ScheduledMessage> {
+    if let Some(previous) = ScheduledMessage::fetch(&mut *db, key).await {
+        // the previous message may have already been posted, in which case slack
+        // reports an error that can safely be ignored
+        if let Err(e) = chat_delete_scheduled_message(&previous.channel, &previous.message_id).await
+        {
+            tracing::warn!("Failed to delete scheduled message: {:?}", e);
+        }
+    }
+
+    let message = ScheduledMessage {
+        key: key.to_owned(),
+        channel: channel.to_owned(),
+        message_id: chat_schedule_message(channel, post_at, text, blocks).await?,
+        post_at,
+    };
+
+    message.save(&mut *db).await?;
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    //! Contract tests against responses recorded from Slack (`fixtures/slack`)
+
+    use super::*;
+
+    /// Checks a recorded response, returning the message of the error it maps to
+    ///
+    /// # Arguments
+    /// * `method` - Name of the API method
+    /// * `status` - Status code of the response
+    /// * `retry_after` - `Retry-After` header of the response, if any
+    /// * `body` - Body of the response
+    fn error(method: &str, status: StatusCode, retry_after: Option<&str>, body: &str) -> String {
+        match check(method, status, retry_after, body) {
+            Err(Error::SlackApi(message)) => message,
+            other => panic!("expected a slack api error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reactions_add_ok() {
+        let body = include_str!("../fixtures/slack/reactions_add_ok.json");
+        let value = check("reactions.add", StatusCode::Ok, None, body).unwrap();
+        assert_eq!(value["ok"], true);
+    }
+
+    #[test]
+    fn reactions_add_already_reacted() {
+        let body = include_str!("../fixtures/slack/reactions_add_already_reacted.json");
+        assert_eq!(
+            error("reactions.add", StatusCode::Ok, None, body),
+            "reactions.add: already_reacted"
+        );
+    }
+
+    #[test]
+    fn reactions_add_message_not_found() {
+        let body = include_str!("../fixtures/slack/reactions_add_message_not_found.json");
+        assert_eq!(
+            error("reactions.add", StatusCode::Ok, None, body),
+            "reactions.add: message_not_found"
+        );
+    }
+
+    #[test]
+    fn invalid_auth() {
+        let body = include_str!("../fixtures/slack/invalid_auth.json");
+        assert_eq!(
+            error("chat.postMessage", StatusCode::Ok, None, body),
+            "chat.postMessage: invalid_auth"
+        );
+    }
+
+    #[test]
+    fn rate_limited() {
+        let body = include_str!("../fixtures/slack/ratelimited.json");
+        assert_eq!(
+            error("users.list", StatusCode::TooManyRequests, Some("30"), body),
+            "users.list: rate limited, retry after 30 seconds"
+        );
+    }
+
+    #[test]
+    fn rate_limited_without_retry_after() {
+        let body = include_str!("../fixtures/slack/ratelimited.json");
+        assert_eq!(
+            error("users.list", StatusCode::TooManyRequests, None, body),
+            "users.list: rate limited, retry after an unknown number of seconds"
+        );
+    }
+
+    #[test]
+    fn server_error() {
+        let body = include_str!("../fixtures/slack/server_error.html");
+        assert_eq!(
+            error("chat.update", StatusCode::ServiceUnavailable, None, body),
+            "chat.update: HTTP 503"
+        );
+    }
+
+    #[test]
+    fn html_with_ok_status() {
+        // proxies in front of slack sometimes answer with an html page and `200 OK`
+        let body = include_str!("../fixtures/slack/server_error.html");
+        let message = error("chat.update", StatusCode::Ok, None, body);
+        assert!(message.starts_with("chat.update: "), "{}", message);
+    }
+
+    #[test]
+    fn chat_post_message_with_warnings() {
+        let body = include_str!("../fixtures/slack/chat_post_message_ok.json");
+        let value = check("chat.postMessage", StatusCode::Ok, None, body).unwrap();
+        assert_eq!(value["ts"], "1603123456.000200");
+        assert_eq!(value["channel"], "C01B2PZQX8H");
+    }
+
+    #[test]
+    fn users_info_user_not_found() {
+        let body = include_str!("../fixtures/slack/users_info_user_not_found.json");
+        assert_eq!(
+            error("users.info", StatusCode::Ok, None, body),
+            "users.info: user_not_found"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Suggestions for commands that failed because of a typo
+//!
+//! When a command can't be parsed, or names a team that doesn't exist, each of its first few
+//! words that isn't a keyword, team, or known email is replaced by the closest keyword, team
+//! name, or cached user email.  If anything was replaced, the error response offers a button
+//! that runs the corrected command.
+
+use crate::{
+    models::{normalize_name, Profile, Team},
+    response::SlashResponse,
+    SqlConn,
+};
+use serde_json::json;
+
+/// Action id of the button that runs a corrected command
+pub const RERUN_ACTION: &str = "rerun_command";
+
+/// Keywords of the command grammar
+const KEYWORDS: &[&str] = &[
+    "ack",
+    "add",
+    "address",
+    "all",
+    "announce",
+    "assign",
+    "autoreply",
+    "availability",
+    "badge",
+    "book",
+    "calendar",
+    "cancel",
+    "capacity",
+    "channel",
+    "contact",
+    "coverage",
+    "create",
+    "del",
+    "delegate",
+    "delete",
+    "describe",
+    "feed",
+    "field",
+    "fields",
+    "guest",
+    "help",
+    "icon",
+    "lead",
+    "leave",
+    "list",
+    "member",
+    "muster",
+    "none",
+    "note",
+    "notify",
+    "off",
+    "office",
+    "on",
+    "remove",
+    "set",
+    "shift",
+    "site",
+    "status",
+    "team",
+    "timeline",
+    "tz",
+    "unassign",
+    "unbook",
+    "viewer",
+    "where",
+    "wizard",
+];
+
+/// Only the first few words are corrected, as later ones are usually notes or messages
+const MAX_CORRECTED_WORDS: usize = 4;
+
+/// Returns the number of single character edits needed to turn one word into another
+///
+/// # Arguments
+/// * `a` - A word
+/// * `b` - Another word
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substituted = previous + if ca == *cb { 0 } else { 1 };
+            previous = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Returns the candidate closest to a word, if any is close enough to be a typo of it
+///
+/// # Arguments
+/// * `word` - The word as typed
+/// * `candidates` - Words it may be a typo of
+fn closest<'a>(word: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let word = word.to_lowercase();
+    let allowed = (word.chars().count() / 3).max(1).min(2);
+
+    candidates
+        .map(|candidate| (edit_distance(&word, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance > 0 && *distance <= allowed)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns a corrected version of a command, or `None` if no typos were found
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `text` - Text of the command
+pub async fn correct(db: &mut SqlConn, text: &str) -> Option<String> {
+    let teams: Vec<String> = Team::fetch_all(&mut *db)
+        .await
+        .ok()?
+        .into_iter()
+        .map(|team| team.name)
+        .collect();
+    let emails: Vec<String> = Profile::fetch_all(&mut *db)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|profile| profile.email)
+        .collect();
+
+    let mut corrected = false;
+    let words: Vec<String> = text
+        .split_whitespace()
+        .enumerate()
+        .map(|(i, word)| {
+            let known = KEYWORDS.contains(&word.to_lowercase().as_str())
+                || teams
+                    .iter()
+                    .any(|team| normalize_name(team) == normalize_name(word))
+                || emails.iter().any(|email| email.eq_ignore_ascii_case(word));
+
+            // mentions, quoted names, dates, and page numbers are never typos
+            let skipped = i >= MAX_CORRECTED_WORDS
+                || word.starts_with('<')
+                || word.starts_with('"')
+                || word.starts_with(|c: char| c.is_ascii_digit());
+
+            let replacement = match (known || skipped, word.contains('@')) {
+                (true, _) => None,
+                (false, true) => closest(word, emails.iter().map(String::as_str)),
+                (false, false) => closest(
+                    word,
+                    KEYWORDS
+                        .iter()
+                        .copied()
+                        .chain(teams.iter().map(String::as_str)),
+                ),
+            };
+
+            match replacement {
+                Some(replacement) => {
+                    corrected = true;
+                    replacement.to_owned()
+                }
+                None => word.to_owned(),
+            }
+        })
+        .collect();
+
+    if corrected {
+        Some(words.join(" "))
+    } else {
+        None
+    }
+}
+
+/// Offers to run a corrected command, below an error response
+///
+/// # Arguments
+/// * `resp` - The error response
+/// * `corrected` - The corrected command
+pub fn offer(resp: &mut SlashResponse, corrected: &str) {
+    resp.push(json!({
+        "type": "section",
+        "text": {
+            "type": "mrkdwn",
+            "text": format!("Did you mean `/location {}`?", corrected),
+        },
+        "accessory": {
+            "type": "button",
+            "action_id": RERUN_ACTION,
+            "text": { "type": "plain_text", "text": "Run it" },
+            "value": corrected,
+        },
+    }));
+}
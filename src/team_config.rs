@@ -0,0 +1,448 @@
+//! Export and import of a team's configuration as YAML
+//!
+//! `statusbot config export-team <name>` prints everything needed to recreate a team:
+//! its description, icon, bound channel, coverage requirement, members and their roles,
+//! guests, custom fields, and shifts.  `statusbot config import-team <file>` creates the team
+//! if it doesn't exist and updates it to match the file, so a team can be copied between
+//! staging and production, or between workspaces.
+//!
+//! Imports only add and update: members, guests, fields, and shifts that aren't in the file
+//! are kept.  Users and channels are identified by Slack ID, so when copying between
+//! workspaces the IDs must be edited to match the destination.
+//!
+//! `statusbot config reconcile <dir>` reconciles the database with a desired state directory
+//! once (see `desired_state`), without starting the bot.  Both `import-team` and `reconcile`
+//! accept `--dry-run`, to print what they would change without changing it.
+
+use crate::{
+    audit, connect,
+    coverage::{describe_days, parse_days},
+    desired_state,
+    models::{normalize_name, AuditEntry, MemberRole, Shift, Team, TeamField, User},
+    Opt, SqlConn,
+};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Tools for team configuration
+#[derive(structopt::StructOpt, Debug)]
+pub enum ConfigCommand {
+    /// Prints a team's configuration as YAML
+    ExportTeam {
+        /// Name of the team
+        name: String,
+    },
+
+    /// Creates or updates a team from a YAML file written by `export-team`
+    ImportTeam {
+        /// YAML file describing the team
+        #[structopt(parse(from_os_str))]
+        file: std::path::PathBuf,
+
+        /// Print what would change, without changing anything
+        #[structopt(long)]
+        dry_run: bool,
+    },
+
+    /// Reconciles teams and sites with a desired state directory, removing anything declared
+    /// teams don't declare
+    Reconcile {
+        /// Directory of YAML files declaring teams and sites
+        #[structopt(parse(from_os_str))]
+        dir: std::path::PathBuf,
+
+        /// Print what would change, without changing anything
+        #[structopt(long)]
+        dry_run: bool,
+    },
+}
+
+/// Everything needed to recreate a team
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TeamConfig {
+    /// Name of the team
+    pub name: String,
+
+    /// What the team does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Emoji shown next to the team's name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+
+    /// Slack ID of the user who created the team
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+
+    /// Slack ID of the channel bound to the team
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+
+    /// Post status changes made outside the bound channel in it
+    #[serde(default)]
+    pub notify_changes: bool,
+
+    /// Members needed on site, if the team has a coverage requirement
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<CoverageConfig>,
+
+    /// Members on Slack, and their roles
+    #[serde(default)]
+    pub members: Vec<MemberConfig>,
+
+    /// Names of guests who aren't on Slack
+    #[serde(default)]
+    pub guests: Vec<String>,
+
+    /// Labels of the custom fields the team collects
+    #[serde(default)]
+    pub fields: Vec<String>,
+
+    /// Recurring shifts, and who is assigned to them
+    #[serde(default)]
+    pub shifts: Vec<ShiftConfig>,
+}
+
+/// A team's coverage requirement
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CoverageConfig {
+    /// Members needed on site
+    pub min: i64,
+
+    /// Weekdays the requirement applies on (e.g., `mon,tue,wed` or `daily`)
+    pub days: String,
+}
+
+/// A member of a team who is on Slack
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MemberConfig {
+    /// Slack ID of the member
+    pub id: String,
+
+    /// The member's role (`lead`, `member`, or `viewer`)
+    pub role: String,
+}
+
+/// A recurring shift
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ShiftConfig {
+    /// Name of the shift
+    pub name: String,
+
+    /// Weekdays the shift runs on (e.g., `mon,tue,wed` or `daily`)
+    pub days: String,
+
+    /// Hours the shift covers (e.g., `09:00-17:00`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hours: Option<String>,
+
+    /// Slack IDs of the members assigned to the shift
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+/// Describes a bitmask of weekdays so it can be parsed again
+///
+/// # Arguments
+/// * `days` - Bitmask of days (bit 0 is Monday)
+fn days_config(days: i64) -> String {
+    describe_days(days).replace(' ', "")
+}
+
+/// Builds the configuration of a team
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `name` - Name of the team, in any case
+pub async fn export(db: &mut SqlConn, name: &str) -> Result<TeamConfig> {
+    let team = Team::fetch(&mut *db, name)
+        .await
+        .ok_or_else(|| anyhow!("team {} not found", name))?;
+
+    let mut members = vec![];
+    let mut guests = vec![];
+    for member in Team::members_page(&mut *db, &team.name, i64::MAX, 0).await? {
+        match (&member.name, member.external) {
+            (Some(name), true) => guests.push(name.clone()),
+            _ => members.push(MemberConfig {
+                role: member.role().as_str().to_owned(),
+                id: member.id,
+            }),
+        }
+    }
+
+    let fields = TeamField::fetch_by_team(&mut *db, &team)
+        .await?
+        .into_iter()
+        .map(|field| field.label)
+        .collect();
+
+    let mut shifts = vec![];
+    for shift in Shift::fetch_by_team(&mut *db, &team).await? {
+        let members = shift
+            .members(&mut *db)
+            .await?
+            .into_iter()
+            .map(|user| user.id)
+            .collect();
+
+        shifts.push(ShiftConfig {
+            days: days_config(shift.days),
+            name: shift.name,
+            hours: shift.hours,
+            members,
+        });
+    }
+
+    Ok(TeamConfig {
+        coverage: team.min_coverage.map(|min| CoverageConfig {
+            min,
+            days: days_config(team.coverage_days),
+        }),
+        name: team.name,
+        description: team.description,
+        icon: team.icon,
+        created_by: team.created_by,
+        channel: team.channel,
+        notify_changes: team.notify_changes,
+        members,
+        guests,
+        fields,
+        shifts,
+    })
+}
+
+/// Creates or updates a team to match a configuration, returning what was changed
+///
+/// With `prune`, members, guests, fields, shifts, and shift assignments that aren't in the
+/// configuration are removed, and shifts whose days or hours differ are recreated, so the
+/// team matches it exactly
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `config` - Configuration of the team
+/// * `prune` - Remove anything that isn't in the configuration
+pub async fn import(db: &mut SqlConn, config: &TeamConfig, prune: bool) -> Result<Vec<String>> {
+    let mut changes = vec![];
+
+    // check everything that can be checked before changing anything
+    let roles = config
+        .members
+        .iter()
+        .map(|member| member.role.parse::<MemberRole>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let coverage_days = match &config.coverage {
+        Some(coverage) => Some(parse_days(&coverage.days)?),
+        None => None,
+    };
+    let shift_days = config
+        .shifts
+        .iter()
+        .map(|shift| parse_days(&shift.days))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut team = match Team::fetch(&mut *db, &config.name).await {
+        Some(team) => team,
+        None => {
+            let created_by = config
+                .created_by
+                .as_deref()
+                .or_else(|| config.members.first().map(|member| member.id.as_str()))
+                .unwrap_or_default();
+
+            changes.push(format!("created team {}", config.name));
+            Team::new(&mut *db, &config.name, created_by).await?
+        }
+    };
+
+    let min_coverage = config.coverage.as_ref().map(|coverage| coverage.min);
+    let coverage_days = coverage_days.unwrap_or(team.coverage_days);
+    if team.description != config.description
+        || team.icon != config.icon
+        || team.channel != config.channel
+        || team.notify_changes != config.notify_changes
+        || team.min_coverage != min_coverage
+        || team.coverage_days != coverage_days
+    {
+        team.description = config.description.clone();
+        team.icon = config.icon.clone();
+        team.channel = config.channel.clone();
+        team.notify_changes = config.notify_changes;
+        team.min_coverage = min_coverage;
+        team.coverage_days = coverage_days;
+        team.save(&mut *db).await?;
+        changes.push(format!("updated settings of team {}", team.name));
+    }
+
+    if prune {
+        for member in Team::members_page(&mut *db, &team.name, i64::MAX, 0).await? {
+            let user = match (&member.name, member.external) {
+                (Some(name), true) if !config.guests.contains(name) => {
+                    team.guest(&mut *db, name).await?
+                }
+                (_, false) if !config.members.iter().any(|m| m.id == member.id) => {
+                    Some(User::fetch_or_create(&mut *db, &member.id).await?)
+                }
+                _ => None,
+            };
+
+            if let Some(user) = user {
+                team.delete_member(&mut *db, &user).await?;
+                let name = member.name.as_deref().unwrap_or(&member.id);
+                changes.push(format!("removed {} from team {}", name, team.name));
+            }
+        }
+    }
+
+    for (member, role) in config.members.iter().zip(roles) {
+        let user = User::fetch_or_create(&mut *db, &member.id).await?;
+        if team.member_role(&mut *db, &user).await?.is_none() {
+            team.add_member(&mut *db, &user).await?;
+            changes.push(format!("added member {}", user.id));
+        }
+
+        if team.member_role(&mut *db, &user).await? != Some(role) {
+            team.set_role(&mut *db, &user, role).await?;
+            changes.push(format!("made {} a {}", user.id, role.as_str()));
+        }
+    }
+
+    for name in &config.guests {
+        if team.guest(&mut *db, name).await?.is_none() {
+            let guest = User::new_external(&mut *db, name).await?;
+            team.add_member(&mut *db, &guest).await?;
+            changes.push(format!("added guest {}", name));
+        }
+    }
+
+    let fields = TeamField::fetch_by_team(&mut *db, &team).await?;
+    if prune {
+        for field in &fields {
+            if !config
+                .fields
+                .iter()
+                .any(|label| field.label.eq_ignore_ascii_case(label))
+            {
+                TeamField::delete(&mut *db, &team, &field.label).await?;
+                changes.push(format!("removed field {}", field.label));
+            }
+        }
+    }
+
+    for label in &config.fields {
+        if !fields
+            .iter()
+            .any(|field| field.label.eq_ignore_ascii_case(label))
+        {
+            TeamField::add(&mut *db, &team, label).await?;
+            changes.push(format!("added field {}", label));
+        }
+    }
+
+    if prune {
+        for shift in Shift::fetch_by_team(&mut *db, &team).await? {
+            let declared = config.shifts.iter().zip(&shift_days).any(|(config, days)| {
+                normalize_name(&config.name) == shift.name
+                    && *days == shift.days
+                    && config.hours == shift.hours
+            });
+
+            if !declared {
+                changes.push(format!("removed shift {}", shift.name));
+                shift.delete(&mut *db).await?;
+            }
+        }
+    }
+
+    for (config, days) in config.shifts.iter().zip(shift_days) {
+        let shift = match Shift::fetch(&mut *db, &team, &config.name).await {
+            Some(shift) => shift,
+            None => {
+                changes.push(format!("created shift {}", config.name));
+                Shift::new(&mut *db, &team, &config.name, days, config.hours.as_deref()).await?
+            }
+        };
+
+        let assigned = shift.members(&mut *db).await?;
+        if prune {
+            for user in &assigned {
+                if !config.members.contains(&user.id) {
+                    shift.unassign(&mut *db, user).await?;
+                    changes.push(format!("unassigned {} from shift {}", user.id, shift.name));
+                }
+            }
+        }
+
+        for id in &config.members {
+            if !assigned.iter().any(|user| &user.id == id) {
+                let user = User::fetch_or_create(&mut *db, id).await?;
+                shift.assign(&mut *db, &user).await?;
+                changes.push(format!("assigned {} to shift {}", id, shift.name));
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Runs a configuration tool against `--database`
+///
+/// # Arguments
+/// * `opt` - Command line options and arguments
+/// * `command` - The tool to run
+pub async fn run(opt: &Opt, command: &ConfigCommand) -> Result<()> {
+    let pool = connect(opt).await?;
+    let mut db = pool.acquire().await?;
+
+    match command {
+        ConfigCommand::ExportTeam { name } => {
+            let config = export(&mut db, name).await?;
+            print!("{}", serde_yaml::to_string(&config)?);
+        }
+        ConfigCommand::ImportTeam { file, dry_run } => {
+            let config = read(file)?;
+            let changes = simulated!(db, *dry_run, import(&mut db, &config, false))?;
+            if *dry_run {
+                let team = normalize_name(&config.name);
+                audit::record_simulated(&mut db, AuditEntry::CONFIG, &team, &changes).await;
+            }
+            if changes.is_empty() {
+                println!("team {} is already up to date", config.name);
+            } else if *dry_run {
+                println!("dry run, nothing was changed:");
+            }
+            for change in changes {
+                println!("{}", change);
+            }
+        }
+        ConfigCommand::Reconcile { dir, dry_run } => {
+            let drift = desired_state::run(&pool, dir, *dry_run).await?;
+            if drift.changes.is_empty() {
+                println!("database matches the desired state");
+            } else if *dry_run {
+                println!("dry run, nothing was changed:");
+            }
+            for change in drift.changes {
+                println!("{}", change);
+            }
+            for unmanaged in drift.unmanaged {
+                println!("not in desired state: {}", unmanaged);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a team's configuration from a YAML file
+///
+/// # Arguments
+/// * `file` - Path to the file
+fn read(file: &Path) -> Result<TeamConfig> {
+    let yaml = std::fs::read_to_string(file)
+        .with_context(|| format!("failed to read {}", file.display()))?;
+
+    serde_yaml::from_str(&yaml).with_context(|| format!("failed to parse {}", file.display()))
+}
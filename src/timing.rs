@@ -0,0 +1,168 @@
+//! Timing of database queries
+//!
+//! Every query run by the models is wrapped in `timed!`, which runs it in a `query` span
+//! named after its SQL file, adds its latency to a histogram kept per query, and logs it if
+//! it took longer than `SLOW_QUERY_MS` milliseconds (default 500, `0` disables logging).
+//! Slow queries are logged with their SQL, so bound parameters only ever appear as their
+//! placeholders (`$1`, `$2`, ...), never their values.
+//!
+//! Streams returned to callers (e.g., `Team::stream_all`) aren't timed, as they're consumed
+//! a row at a time.
+
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::Instrument;
+
+/// Runs a query future, timing it (see the module documentation)
+///
+/// # Arguments
+/// * `path` - Path of the query's SQL file (e.g., `"sql/team/fetch_by_name.sql"`)
+/// * `query` - Future running the query
+macro_rules! timed {
+    ($path:literal, $query:expr) => {
+        crate::timing::run(
+            $path,
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)),
+            $query,
+        )
+    };
+}
+
+/// Upper bounds of the histogram buckets, in milliseconds (slower queries fall in a final,
+/// unbounded bucket)
+pub const BUCKETS_MS: &[u64] = &[1, 2, 5, 10, 25, 50, 100, 250, 500, 1000, 2500];
+
+/// Threshold above which queries are logged if `SLOW_QUERY_MS` isn't set, in milliseconds
+const DEFAULT_SLOW_QUERY_MS: u64 = 500;
+
+/// Latencies of every query run so far, by the path of the query's SQL file
+static HISTOGRAMS: Lazy<Mutex<HashMap<&'static str, Histogram>>> = Lazy::new(Default::default);
+
+/// Latencies of one query
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    /// Number of queries that fell in each bucket of `BUCKETS_MS`, plus the unbounded bucket
+    pub counts: Vec<u64>,
+
+    /// Number of queries run
+    pub count: u64,
+
+    /// Total time taken by all queries
+    pub total: Duration,
+
+    /// Time taken by the slowest query
+    pub max: Duration,
+}
+
+impl Histogram {
+    /// Creates an empty histogram
+    fn new() -> Self {
+        Histogram {
+            counts: vec![0; BUCKETS_MS.len() + 1],
+            count: 0,
+            total: Duration::default(),
+            max: Duration::default(),
+        }
+    }
+
+    /// Adds the latency of a query to the histogram
+    ///
+    /// # Arguments
+    /// * `elapsed` - Time taken by the query
+    fn record(&mut self, elapsed: Duration) {
+        let ms = elapsed.as_millis();
+        let bucket = BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= u128::from(*bound))
+            .unwrap_or(BUCKETS_MS.len());
+
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    /// Returns the average time taken by a query
+    pub fn mean(&self) -> Duration {
+        match self.count {
+            0 => Duration::default(),
+            count => self.total / count as u32,
+        }
+    }
+
+    /// Returns the upper bound (in milliseconds) of the bucket a quantile falls in, or `None`
+    /// if it falls in the unbounded bucket
+    ///
+    /// # Arguments
+    /// * `quantile` - The quantile, between 0 and 1 (e.g., `0.95`)
+    pub fn quantile(&self, quantile: f64) -> Option<u64> {
+        let rank = ((self.count as f64 * quantile).ceil() as u64).max(1);
+
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return BUCKETS_MS.get(bucket).copied();
+            }
+        }
+
+        None
+    }
+}
+
+/// Returns the threshold above which queries are logged, or `None` if logging is disabled
+fn slow_query_threshold() -> Option<Duration> {
+    let ms = dotenv::var("SLOW_QUERY_MS")
+        .ok()
+        .and_then(|ms| ms.parse().ok())
+        .unwrap_or(DEFAULT_SLOW_QUERY_MS);
+
+    match ms {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+/// Runs a query future, timing it (use `timed!` instead, which fills in the query's SQL)
+///
+/// # Arguments
+/// * `path` - Path of the query's SQL file
+/// * `sql` - The query's SQL
+/// * `query` - Future running the query
+pub async fn run<F: Future>(path: &'static str, sql: &'static str, query: F) -> F::Output {
+    let started = Instant::now();
+    let output = query.instrument(tracing::debug_span!("query", path)).await;
+    let elapsed = started.elapsed();
+
+    HISTOGRAMS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(path)
+        .or_insert_with(Histogram::new)
+        .record(elapsed);
+
+    if slow_query_threshold().map_or(false, |threshold| elapsed >= threshold) {
+        let sql = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+        tracing::warn!("slow query {} ({}ms): {}", path, elapsed.as_millis(), sql);
+    }
+
+    output
+}
+
+/// Returns the histograms of every query run so far, slowest (by total time) first
+pub fn histograms() -> Vec<(&'static str, Histogram)> {
+    let mut histograms: Vec<_> = HISTOGRAMS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(path, histogram)| (*path, histogram.clone()))
+        .collect();
+
+    histograms.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0)));
+    histograms
+}
@@ -0,0 +1,263 @@
+//! A modal for building commands, for users who can't remember the grammar
+//!
+//! `/location wizard` opens a modal with menus for an action, a team, a user, dates, and text.
+//! Submitting it builds the command the menus describe, which is parsed by the same parser as
+//! typed commands (so it's rejected for the same reasons) and run as if it had been typed, with
+//! the response posted to the channel the wizard was opened in.
+
+use crate::{
+    handlers::command::{self, SlashAction, SlashCommand},
+    models::Team,
+    slack, SqlConn, State,
+};
+use anyhow::Result;
+use serde_json::{json, Value};
+
+/// Callback id of the modal
+pub const CALLBACK_ID: &str = "command_wizard";
+
+/// Block id of the team menu, whose options are loaded with `block_suggestion` requests
+pub const TEAM_BLOCK: &str = "team";
+
+/// Most teams offered in the team menu at a time (Slack allows at most 100 options)
+const MAX_TEAM_OPTIONS: i64 = 100;
+
+/// Actions the wizard can build, as (value, label) pairs
+const ACTIONS: &[(&str, &str)] = &[
+    ("show_team", "Show a team's statuses"),
+    ("add_member", "Add a user to a team"),
+    ("remove_member", "Remove a user from a team"),
+    ("make_lead", "Make a user a lead of a team"),
+    ("timeline", "Show where a user was in a week"),
+    ("leave", "Record leave"),
+    ("set_note", "Set your status note"),
+    ("announce", "Send an announcement to a team"),
+];
+
+/// Opens the wizard
+///
+/// # Arguments
+/// * `form` - The command that opened the wizard
+pub async fn open(form: &SlashCommand) -> Result<()> {
+    let actions: Vec<Value> = ACTIONS
+        .iter()
+        .map(|(value, label)| {
+            json!({
+                "text": { "type": "plain_text", "text": label },
+                "value": value,
+            })
+        })
+        .collect();
+
+    let mut blocks = vec![
+        json!({
+            "type": "input",
+            "block_id": "action",
+            "label": { "type": "plain_text", "text": "Action" },
+            "element": {
+                "type": "static_select",
+                "action_id": "value",
+                "options": actions,
+            },
+        }),
+        // teams are searched as the user types, as there may be more than a menu can hold
+        json!({
+            "type": "input",
+            "block_id": TEAM_BLOCK,
+            "optional": true,
+            "label": { "type": "plain_text", "text": "Team" },
+            "element": {
+                "type": "external_select",
+                "action_id": "value",
+                "min_query_length": 0,
+            },
+        }),
+    ];
+
+    blocks.push(json!({
+        "type": "input",
+        "block_id": "user",
+        "optional": true,
+        "label": { "type": "plain_text", "text": "User" },
+        "element": { "type": "users_select", "action_id": "value" },
+    }));
+
+    for (block_id, label) in &[("from", "From"), ("to", "To")] {
+        blocks.push(json!({
+            "type": "input",
+            "block_id": block_id,
+            "optional": true,
+            "label": { "type": "plain_text", "text": label },
+            "element": { "type": "datepicker", "action_id": "value" },
+        }));
+    }
+
+    blocks.push(json!({
+        "type": "input",
+        "block_id": "text",
+        "optional": true,
+        "label": { "type": "plain_text", "text": "Note or message" },
+        "element": { "type": "plain_text_input", "action_id": "value", "multiline": true },
+    }));
+
+    // the command's channel and response_url are needed to respond once submitted
+    let metadata = json!({
+        "channel_id": form.channel_id,
+        "response_url": form.response_url,
+    });
+
+    slack::views_open(
+        &form.trigger_id,
+        json!({
+            "type": "modal",
+            "callback_id": CALLBACK_ID,
+            "private_metadata": metadata.to_string(),
+            "title": { "type": "plain_text", "text": "Command wizard" },
+            "submit": { "type": "plain_text", "text": "Run" },
+            "blocks": blocks,
+        }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the options of the team menu matching what the user has typed so far
+///
+/// # Arguments
+/// * `db` - Connection to the SQL database
+/// * `query` - What the user has typed
+pub async fn team_options(db: &mut SqlConn, query: &str) -> Result<Value> {
+    let options: Vec<Value> = Team::search(&mut *db, query, MAX_TEAM_OPTIONS)
+        .await?
+        .into_iter()
+        .map(|team| {
+            json!({
+                "text": { "type": "plain_text", "text": team.display_name() },
+                "value": team.name,
+            })
+        })
+        .collect();
+
+    Ok(json!({ "options": options }))
+}
+
+/// Returns the value of one of the wizard's inputs, if it was filled in
+///
+/// # Arguments
+/// * `values` - Values of the modal's inputs, keyed by block id
+/// * `block_id` - Which input
+fn value<'a>(values: &'a Value, block_id: &str) -> Option<&'a str> {
+    let value = &values[block_id]["value"];
+    value["selected_option"]["value"]
+        .as_str()
+        .or_else(|| value["selected_user"].as_str())
+        .or_else(|| value["selected_date"].as_str())
+        .or_else(|| value["value"].as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Builds the command described by the wizard's inputs
+///
+/// Fails with the block id of the input that's missing, and why
+///
+/// # Arguments
+/// * `values` - Values of the modal's inputs, keyed by block id
+pub fn build(values: &Value) -> Result<String, (&'static str, String)> {
+    let required = |block_id: &'static str, what: &str| {
+        value(values, block_id).ok_or_else(|| (block_id, format!("Please choose {}", what)))
+    };
+
+    let command = match required("action", "an action")? {
+        "show_team" => required(TEAM_BLOCK, "a team")?.to_owned(),
+        action @ "add_member" | action @ "remove_member" | action @ "make_lead" => {
+            let command = match action {
+                "add_member" => "add",
+                "remove_member" => "del",
+                _ => "lead",
+            };
+
+            format!(
+                "team {} {} {}",
+                required(TEAM_BLOCK, "a team")?,
+                command,
+                required("user", "a user")?
+            )
+        }
+        "timeline" => match value(values, "from") {
+            Some(from) => format!("timeline {} {}", required("user", "a user")?, from),
+            None => format!("timeline {}", required("user", "a user")?),
+        },
+        "leave" => {
+            let from = required("from", "the first day of leave")?;
+            format!("leave {} {}", from, value(values, "to").unwrap_or(from))
+        }
+        "set_note" => format!("set note \"{}\"", required("text", "a note")?),
+        "announce" => format!(
+            "announce {} {}",
+            required(TEAM_BLOCK, "a team")?,
+            required("text", "a message")?
+        ),
+        action => return Err(("action", format!("Unknown action `{}`", action))),
+    };
+
+    Ok(command)
+}
+
+/// Handles a submission of the wizard, returning the errors to show in the modal if the
+/// command it describes is invalid
+///
+/// Valid commands are run in the background, as they may take longer than Slack waits for
+/// the modal to close
+///
+/// # Arguments
+/// * `state` - Shared application state
+/// * `payload` - The `view_submission` payload
+pub fn submit(state: &State, payload: &Value) -> Option<Value> {
+    let view = &payload["view"];
+    let text = match build(&view["state"]["values"]) {
+        Ok(text) => text,
+        Err((block_id, reason)) => return Some(errors(block_id, &reason)),
+    };
+
+    // the same parser as typed commands, so the wizard can't build anything invalid
+    if let Err(e) = SlashAction::parse(&text) {
+        return Some(errors("action", &e.user_message()));
+    }
+
+    let metadata: Value = view["private_metadata"]
+        .as_str()
+        .and_then(|metadata| serde_json::from_str(metadata).ok())
+        .unwrap_or_default();
+
+    let str_of = |value: &Value| value.as_str().unwrap_or_default().to_owned();
+    let form = SlashCommand {
+        token: String::new(),
+        command: "/location".to_owned(),
+        text,
+        response_url: str_of(&metadata["response_url"]),
+        trigger_id: str_of(&payload["trigger_id"]),
+        user_id: str_of(&payload["user"]["id"]),
+        user_name: String::new(),
+        team_id: str_of(&payload["user"]["team_id"]),
+        channel_id: str_of(&metadata["channel_id"]),
+        api_app_id: str_of(&payload["api_app_id"]),
+    };
+
+    command::spawn_run(state, form, false);
+
+    None
+}
+
+/// Builds a response that shows an error next to one of the modal's inputs
+///
+/// # Arguments
+/// * `block_id` - Which input
+/// * `reason` - The error
+fn errors(block_id: &str, reason: &str) -> Value {
+    json!({
+        "response_action": "errors",
+        "errors": { block_id: reason },
+    })
+}